@@ -0,0 +1,271 @@
+#!/usr/bin/env python3
+"""Writes test_arm64, a minimal stripped AArch64 Linux executable equivalent to test.c.
+
+No cross toolchain is needed: the code is assembled by hand below, in the shape
+gcc -O0 gives it. _start passes the address of main to __libc_start_main
+through its PLT entry, main calls hello_world, which calls puts.
+"""
+
+import struct
+
+BASE = 0x400000
+# the writable segment is mapped on pages of its own
+DATA_DELTA = 0x10000
+PAGE = 0x1000
+
+R_AARCH64_JUMP_SLOT = 1026
+IMPORTS = ["__libc_start_main", "puts", "abort"]
+
+
+def align(value, alignment):
+    return (value + alignment - 1) & ~(alignment - 1)
+
+
+# A64 encodings used below
+def movz(rd, imm, sf=1):
+    return (sf << 31) | 0x52800000 | (imm << 5) | rd
+
+
+def mov_reg(rd, rm, sf=1):
+    return (sf << 31) | 0x2A0003E0 | (rm << 16) | rd
+
+
+def add_imm(rd, rn, imm):
+    return 0x91000000 | (imm << 10) | (rn << 5) | rd
+
+
+def ldr_x(rt, rn, offset):
+    return 0xF9400000 | (offset // 8 << 10) | (rn << 5) | rt
+
+
+def str_x(rt, rn, offset):
+    return 0xF9000000 | (offset // 8 << 10) | (rn << 5) | rt
+
+
+def str_w(rt, rn, offset):
+    return 0xB9000000 | (offset // 4 << 10) | (rn << 5) | rt
+
+
+def stp_pre(rt, rt2, rn, offset):
+    return 0xA9800000 | ((offset // 8) & 0x7F) << 15 | (rt2 << 10) | (rn << 5) | rt
+
+
+def ldp_post(rt, rt2, rn, offset):
+    return 0xA8C00000 | ((offset // 8) & 0x7F) << 15 | (rt2 << 10) | (rn << 5) | rt
+
+
+def adrp(rd, pc, target):
+    imm = ((target & ~0xFFF) - (pc & ~0xFFF)) >> 12
+    return 0x90000000 | (imm & 3) << 29 | ((imm >> 2) & 0x7FFFF) << 5 | rd
+
+
+def bl(pc, target):
+    return 0x94000000 | (((target - pc) >> 2) & 0x3FFFFFF)
+
+
+BR_X17 = 0xD61F0220
+RET = 0xD65F03C0
+NOP = 0xD503201F
+SP, FP, LR = 31, 29, 30
+
+# file layout: the headers and everything read-only in the first segment,
+# .dynamic and .got.plt in the second
+PHDRS = 4
+interp = b"/lib/ld-linux-aarch64.so.1\0"
+interp_off = 64 + 56 * PHDRS
+
+dynstr = bytearray(b"\0libc.so.6\0")
+names = {}
+for name in IMPORTS:
+    names[name] = len(dynstr)
+    dynstr += name.encode() + b"\0"
+
+dynsym_off = align(interp_off + len(interp), 8)
+dynsym = bytes(24) + b"".join(
+    # STB_GLOBAL | STT_FUNC, undefined
+    struct.pack("<IBBHQQ", names[name], 0x12, 0, 0, 0, 0) for name in IMPORTS
+)
+dynstr_off = dynsym_off + len(dynsym)
+rela_off = align(dynstr_off + len(dynstr), 8)
+rela_size = 24 * len(IMPORTS)
+plt_off = align(rela_off + rela_size, 16)
+plt_size = 32 + 16 * len(IMPORTS)
+text_off = plt_off + plt_size
+
+data_off = PAGE
+dynamic_off = data_off
+dynamic_size = 16 * 10
+got_off = dynamic_off + dynamic_size
+got_size = 8 * (3 + len(IMPORTS))
+
+
+def va(offset):
+    return BASE + offset
+
+
+def data_va(offset):
+    return BASE + DATA_DELTA + offset
+
+
+got = data_va(got_off)
+plt = va(plt_off)
+
+
+def slot(i):
+    return got + 8 * (3 + i)
+
+
+def stub(i):
+    return plt + 32 + 16 * i
+
+
+# .plt: PLT0 pushes the slot address and jumps to the resolver, the entries
+# jump through their GOT slots
+code = []
+
+
+def emit(pc, words):
+    code.extend(words)
+    return pc + 4 * len(words)
+
+
+pc = plt
+pc = emit(pc, [stp_pre(16, LR, SP, -16)])
+pc = emit(pc, [adrp(16, pc, got + 16)])
+pc = emit(pc, [ldr_x(17, 16, (got + 16) & 0xFFF), add_imm(16, 16, (got + 16) & 0xFFF), BR_X17])
+pc = emit(pc, [NOP, NOP, NOP])
+for i in range(len(IMPORTS)):
+    pc = emit(pc, [adrp(16, pc, slot(i))])
+    pc = emit(pc, [ldr_x(17, 16, slot(i) & 0xFFF), add_imm(16, 16, slot(i) & 0xFFF), BR_X17])
+assert pc == va(text_off)
+
+# .text: the functions, then the strings in .rodata right after
+start = pc
+hello_world = start + 4 * 14
+main = hello_world + 4 * 8
+vuoto = main + 4 * 10
+rodata = vuoto + 4 * 3
+hello = rodata
+str_vuoto = hello + len(b"Hello, world!\0")
+
+pc = emit(pc, [movz(FP, 0), movz(LR, 0), mov_reg(5, 0), ldr_x(1, SP, 0), add_imm(2, SP, 8)])
+pc = emit(pc, [add_imm(6, SP, 0)])
+pc = emit(pc, [adrp(0, pc, main)])
+pc = emit(pc, [add_imm(0, 0, main & 0xFFF), movz(3, 0), movz(4, 0)])
+pc = emit(pc, [bl(pc, stub(0))])
+pc = emit(pc, [bl(pc, stub(2))])
+pc = emit(pc, [NOP, NOP])
+assert pc == hello_world
+
+pc = emit(pc, [stp_pre(FP, LR, SP, -32), add_imm(FP, SP, 0), str_x(0, SP, 24), ldr_x(0, SP, 24)])
+pc = emit(pc, [bl(pc, stub(1))])
+pc = emit(pc, [NOP, ldp_post(FP, LR, SP, 32), RET])
+assert pc == main
+
+pc = emit(pc, [stp_pre(FP, LR, SP, -32), add_imm(FP, SP, 0), str_w(0, SP, 28), str_x(1, SP, 16)])
+pc = emit(pc, [adrp(0, pc, hello)])
+pc = emit(pc, [add_imm(0, 0, hello & 0xFFF)])
+pc = emit(pc, [bl(pc, hello_world)])
+pc = emit(pc, [movz(0, 0, sf=0), ldp_post(FP, LR, SP, 32), RET])
+assert pc == vuoto
+
+pc = emit(pc, [adrp(0, pc, str_vuoto)])
+pc = emit(pc, [add_imm(0, 0, str_vuoto & 0xFFF), RET])
+assert pc == rodata
+
+text = b"".join(struct.pack("<I", word) for word in code)
+plt_code, text = text[:plt_size], text[plt_size:]
+strings = b"Hello, world!\0FUNZIONA?\0"
+rodata_off = text_off + len(text)
+text_end = rodata_off + len(strings)
+
+rela = b"".join(
+    struct.pack("<QQq", slot(i), (i + 1) << 32 | R_AARCH64_JUMP_SLOT, 0) for i in range(len(IMPORTS))
+)
+
+DT_NEEDED, DT_PLTRELSZ, DT_PLTGOT, DT_STRTAB, DT_SYMTAB = 1, 2, 3, 5, 6
+DT_STRSZ, DT_SYMENT, DT_PLTREL, DT_JMPREL = 10, 11, 20, 23
+dynamic = b"".join(
+    struct.pack("<qQ", tag, value)
+    for tag, value in [
+        (DT_NEEDED, 1),
+        (DT_STRTAB, va(dynstr_off)),
+        (DT_SYMTAB, va(dynsym_off)),
+        (DT_STRSZ, len(dynstr)),
+        (DT_SYMENT, 24),
+        (DT_PLTGOT, got),
+        (DT_PLTRELSZ, rela_size),
+        (DT_PLTREL, 7),
+        (DT_JMPREL, va(rela_off)),
+        (0, 0),
+    ]
+)
+assert len(dynamic) == dynamic_size
+got_plt = struct.pack("<QQQ", data_va(dynamic_off), 0, 0) + b"".join(
+    struct.pack("<Q", plt) for _ in IMPORTS
+)
+
+shstrtab = bytearray(b"\0")
+sections = []
+
+
+def section(name, sh_type, flags, addr, offset, size, link=0, info=0, addralign=1, entsize=0):
+    sections.append((len(shstrtab), sh_type, flags, addr, offset, size, link, info, addralign, entsize))
+    shstrtab.extend(name + b"\0")
+
+
+SHF_WRITE, SHF_ALLOC, SHF_EXECINSTR, SHF_INFO_LINK = 1, 2, 4, 0x40
+sections.append((0,) * 10)
+section(b".interp", 1, SHF_ALLOC, va(interp_off), interp_off, len(interp))
+section(b".dynsym", 11, SHF_ALLOC, va(dynsym_off), dynsym_off, len(dynsym), 3, 1, 8, 24)
+section(b".dynstr", 3, SHF_ALLOC, va(dynstr_off), dynstr_off, len(dynstr))
+section(b".rela.plt", 4, SHF_ALLOC | SHF_INFO_LINK, va(rela_off), rela_off, rela_size, 2, 9, 8, 24)
+section(b".plt", 1, SHF_ALLOC | SHF_EXECINSTR, plt, plt_off, plt_size, addralign=16, entsize=16)
+section(b".text", 1, SHF_ALLOC | SHF_EXECINSTR, va(text_off), text_off, len(text), addralign=4)
+section(b".rodata", 1, SHF_ALLOC, va(rodata_off), rodata_off, len(strings))
+section(b".dynamic", 6, SHF_WRITE | SHF_ALLOC, data_va(dynamic_off), dynamic_off, dynamic_size, 3, 0, 8, 16)
+section(b".got.plt", 1, SHF_WRITE | SHF_ALLOC, got, got_off, got_size, addralign=8, entsize=8)
+shstrndx = len(sections)
+section(b".shstrtab", 3, 0, 0, 0, 0)
+
+shstrtab_off = got_off + got_size
+shoff = align(shstrtab_off + len(shstrtab), 8)
+sections[shstrndx] = sections[shstrndx][:4] + (shstrtab_off, len(shstrtab)) + sections[shstrndx][6:]
+
+PT_LOAD, PT_DYNAMIC, PT_INTERP = 1, 2, 3
+phdrs = [
+    (PT_INTERP, 4, interp_off, va(interp_off), len(interp), len(interp), 1),
+    (PT_LOAD, 5, 0, BASE, text_end, text_end, 0x10000),
+    (PT_LOAD, 6, data_off, data_va(data_off), got_off + got_size - data_off,
+     got_off + got_size - data_off, 0x10000),
+    (PT_DYNAMIC, 6, dynamic_off, data_va(dynamic_off), dynamic_size, dynamic_size, 8),
+]
+assert len(phdrs) == PHDRS
+
+# ELFCLASS64, ELFDATA2LSB, ET_EXEC, EM_AARCH64
+header = b"\x7fELF\x02\x01\x01" + bytes(9) + struct.pack(
+    "<HHIQQQIHHHHHH", 2, 183, 1, start, 64, shoff, 0, 64, 56, PHDRS, 64, len(sections), shstrndx
+)
+image = bytearray(header)
+for p_type, flags, offset, vaddr, filesz, memsz, alignment in phdrs:
+    image += struct.pack("<IIQQQQQQ", p_type, flags, offset, vaddr, vaddr, filesz, memsz, alignment)
+for offset, content in [
+    (interp_off, interp),
+    (dynsym_off, dynsym),
+    (dynstr_off, dynstr),
+    (rela_off, rela),
+    (plt_off, plt_code),
+    (text_off, text),
+    (rodata_off, strings),
+    (dynamic_off, dynamic),
+    (got_off, got_plt),
+    (shstrtab_off, shstrtab),
+]:
+    assert len(image) <= offset
+    image += b"\0" * (offset - len(image)) + content
+image += b"\0" * (shoff - len(image))
+for header in sections:
+    image += struct.pack("<IIQQQQIIQQ", *header)
+
+with open("test_arm64", "wb") as f:
+    f.write(image)
@@ -0,0 +1,74 @@
+#!/usr/bin/env python3
+"""Writes test_boot.bin, a real-mode boot sector printing a string through the BIOS.
+
+It is loaded at 0000:7C00 and stores the BIOS tick count, read through ES, just past
+itself at 0000:7E00. Decompile it with
+
+    deruster --raw --base 7c00 --bits 16 --region 7e00+200 test_boot.bin
+"""
+
+import struct
+
+BASE = 0x7C00
+TICKS = 0x7E00
+
+code = bytearray()
+labels, fixups = {}, []
+
+
+def emit(data):
+    code.extend(data)
+
+
+def rel(target, size):
+    fixups.append((len(code), target, size))
+    code.extend(b"\0" * size)
+
+
+def abs16(target):
+    fixups.append((len(code), target, "abs"))
+    code.extend(b"\0\0")
+
+
+emit(b"\xfa")                          # cli
+emit(b"\x31\xc0")                      # xor ax,ax
+emit(b"\x8e\xd8")                      # mov ds,ax
+emit(b"\x8e\xd0")                      # mov ss,ax
+emit(b"\xbc" + struct.pack("<H", BASE))  # mov sp,7C00h
+emit(b"\xfb")                          # sti
+emit(b"\xbe"); abs16("msg")            # mov si,msg
+emit(b"\xe8"); rel("print", 2)         # call print
+emit(b"\xb8\x40\x00")                  # mov ax,40h
+emit(b"\x8e\xc0")                      # mov es,ax
+emit(b"\x26\x8b\x06\x6c\x00")          # mov ax,es:[6Ch]
+emit(b"\x89\x06" + struct.pack("<H", TICKS))  # mov [ticks],ax
+labels["halt"] = len(code)
+emit(b"\xf4")                          # hlt
+emit(b"\xeb"); rel("halt", 1)          # jmp halt
+
+labels["print"] = len(code)
+emit(b"\xac")                          # lodsb
+emit(b"\x84\xc0")                      # test al,al
+emit(b"\x74"); rel("done", 1)          # jz done
+emit(b"\xb4\x0e")                      # mov ah,0Eh
+emit(b"\xbb\x07\x00")                  # mov bx,7
+emit(b"\xcd\x10")                      # int 10h
+emit(b"\xeb"); rel("print", 1)         # jmp print
+labels["done"] = len(code)
+emit(b"\xc3")                          # ret
+
+labels["msg"] = len(code)
+emit(b"Hello, world!\0")
+
+for offset, target, size in fixups:
+    if size == "abs":
+        struct.pack_into("<H", code, offset, BASE + labels[target])
+    else:
+        value = labels[target] - (offset + size)
+        struct.pack_into("<b" if size == 1 else "<h", code, offset, value)
+
+assert len(code) <= 510
+code += b"\0" * (510 - len(code)) + b"\x55\xaa"
+
+with open("test_boot.bin", "wb") as f:
+    f.write(code)
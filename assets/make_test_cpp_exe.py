@@ -0,0 +1,290 @@
+#!/usr/bin/env python3
+"""Writes test_cpp.exe, a minimal PE32+ image with the RTTI MSVC emits for
+
+    struct Shape { virtual ~Shape(); virtual int area() const; virtual const char *name() const; };
+    struct Circle : Shape { int r; int area() const override; };
+    struct Square : Shape { int side; int area() const override; const char *name() const override; };
+
+No Windows toolchain is needed: the code is assembled by hand below. Each vtable is
+preceded by a pointer to its complete object locator, which points to the type
+descriptor and class hierarchy of the class. The destructors and `area` methods are
+exported under their decorated names, the `name` methods are left unnamed.
+"""
+
+import struct
+
+IMAGE_BASE = 0x140000000
+FILE_ALIGN = 0x200
+SECT_ALIGN = 0x1000
+
+TEXT_RVA, RDATA_RVA, DATA_RVA, RELOC_RVA = 0x1000, 0x2000, 0x3000, 0x4000
+
+
+def align(value, to):
+    return (value + to - 1) & ~(to - 1)
+
+
+def put(section, base, data, alignment=1):
+    while len(section) % alignment:
+        section.append(0)
+    rva = base + len(section)
+    section.extend(data)
+    return rva
+
+
+rdata, data = bytearray(), bytearray()
+# the RVAs of the pointers the loader has to fix up
+relocations = []
+
+shape_str_rva = put(rdata, RDATA_RVA, b"shape\0")
+square_str_rva = put(rdata, RDATA_RVA, b"square\0")
+
+imports = [("KERNEL32.dll", ["ExitProcess"])]
+hint_names = {}
+for dll, funcs in imports:
+    for func in funcs:
+        hint_names[func] = put(rdata, RDATA_RVA, struct.pack("<H", 0) + func.encode() + b"\0", 2)
+dll_names = {dll: put(rdata, RDATA_RVA, dll.encode() + b"\0") for dll, _ in imports}
+
+iat, ilt = {}, {}
+iat_start = None
+for dll, funcs in imports:
+    table = b"".join(struct.pack("<Q", hint_names[f]) for f in funcs) + b"\0" * 8
+    iat[dll] = put(rdata, RDATA_RVA, table, 8)
+    iat_start = iat_start or iat[dll]
+iat_size = RDATA_RVA + len(rdata) - iat_start
+for dll, funcs in imports:
+    table = b"".join(struct.pack("<Q", hint_names[f]) for f in funcs) + b"\0" * 8
+    ilt[dll] = put(rdata, RDATA_RVA, table, 8)
+
+descriptors = b"".join(
+    struct.pack("<IIIII", ilt[dll], 0, 0, dll_names[dll], iat[dll]) for dll, _ in imports
+) + b"\0" * 20
+import_dir_rva = put(rdata, RDATA_RVA, descriptors, 4)
+import_dir_size = len(descriptors)
+
+# .text, assembled by hand
+text = bytearray()
+labels, fixups = {}, []
+
+
+def label(name):
+    labels[name] = TEXT_RVA + len(text)
+
+
+def emit(code):
+    text.extend(code)
+
+
+def rel32(target):
+    fixups.append((len(text), target))
+    text.extend(b"\0\0\0\0")
+
+
+label("mainCRTStartup")
+emit(b"\x48\x83\xec\x28")                      # sub rsp,28h
+emit(b"\xe8"); rel32("main")                   # call main
+emit(b"\x89\xc1")                              # mov ecx,eax
+emit(b"\xff\x15"); rel32(iat["KERNEL32.dll"])  # call [ExitProcess]
+emit(b"\xcc")                                  # int3
+
+# a virtual call on a global object, then on one constructed in place
+label("main")
+emit(b"\x48\x83\xec\x28")                      # sub rsp,28h
+emit(b"\x48\x8d\x0d"); rel32("g_square")       # lea rcx,[g_square]
+emit(b"\x48\x8b\x01")                          # mov rax,[rcx]
+emit(b"\xff\x50\x08")                          # call [rax+8]
+emit(b"\x48\x8d\x0d"); rel32("g_circle")       # lea rcx,[g_circle]
+emit(b"\xba\x02\x00\x00\x00")                  # mov edx,2
+emit(b"\xe8"); rel32("init_circle")            # call init_circle
+emit(b"\x31\xc0")                              # xor eax,eax
+emit(b"\x48\x83\xc4\x28")                      # add rsp,28h
+emit(b"\xc3")                                  # ret
+
+# Circle's constructor inlined, followed by calls to its area and name
+label("init_circle")
+emit(b"\x53")                                  # push rbx
+emit(b"\x48\x83\xec\x20")                      # sub rsp,20h
+emit(b"\x48\x89\xcb")                          # mov rbx,rcx
+emit(b"\x48\x8d\x05"); rel32("vft_Circle")     # lea rax,[Circle::`vftable']
+emit(b"\x48\x89\x03")                          # mov [rbx],rax
+emit(b"\x89\x53\x08")                          # mov [rbx+8],edx
+emit(b"\x48\x8b\x03")                          # mov rax,[rbx]
+emit(b"\x48\x89\xd9")                          # mov rcx,rbx
+emit(b"\xff\x50\x08")                          # call [rax+8]
+emit(b"\x48\x8b\x03")                          # mov rax,[rbx]
+emit(b"\x48\x89\xd9")                          # mov rcx,rbx
+emit(b"\xff\x50\x10")                          # call [rax+10h]
+emit(b"\x48\x83\xc4\x20")                      # add rsp,20h
+emit(b"\x5b")                                  # pop rbx
+emit(b"\xc3")                                  # ret
+
+for cls in ["Shape", "Circle", "Square"]:
+    label(f"dtor_{cls}")
+    emit(b"\x48\x89\xc8")                      # mov rax,rcx
+    emit(b"\xc3")                              # ret
+
+label("area_Shape")
+emit(b"\x31\xc0")                              # xor eax,eax
+emit(b"\xc3")                                  # ret
+
+label("name_Shape")
+emit(b"\x48\x8d\x05"); rel32(shape_str_rva)    # lea rax,["shape"]
+emit(b"\xc3")                                  # ret
+
+label("area_Circle")
+emit(b"\x8b\x41\x08")                          # mov eax,[rcx+8]
+emit(b"\x0f\xaf\xc0")                          # imul eax,eax
+emit(b"\x6b\xc0\x03")                          # imul eax,eax,3
+emit(b"\xc3")                                  # ret
+
+label("area_Square")
+emit(b"\x8b\x41\x08")                          # mov eax,[rcx+8]
+emit(b"\x0f\xaf\xc0")                          # imul eax,eax
+emit(b"\xc3")                                  # ret
+
+label("name_Square")
+emit(b"\x48\x8d\x05"); rel32(square_str_rva)   # lea rax,["square"]
+emit(b"\xc3")                                  # ret
+
+# .data: the type descriptors, whose type_info vtable pointer is left null
+descriptors = {}
+for cls in ["Shape", "Circle", "Square"]:
+    name = f".?AV{cls}@@".encode() + b"\0"
+    descriptors[cls] = put(data, DATA_RVA, struct.pack("<QQ", 0, 0) + name, 8)
+
+# .rdata: the base class descriptors, class hierarchies, object locators and vtables
+bases = {"Shape": [], "Circle": ["Shape"], "Square": ["Shape"]}
+methods = {
+    "Shape": ["dtor_Shape", "area_Shape", "name_Shape"],
+    "Circle": ["dtor_Circle", "area_Circle", "name_Shape"],
+    "Square": ["dtor_Square", "area_Square", "name_Square"],
+}
+hierarchies, base_descriptors = {}, {}
+for cls in ["Shape", "Circle", "Square"]:
+    hierarchies[cls] = RDATA_RVA + align(len(rdata), 4)
+    put(rdata, RDATA_RVA, b"\0" * 16, 4)
+for cls in ["Shape", "Circle", "Square"]:
+    base_descriptors[cls] = put(
+        rdata,
+        RDATA_RVA,
+        struct.pack("<IIiiiII", descriptors[cls], len(bases[cls]), 0, -1, 0, 0x40, hierarchies[cls]),
+        4,
+    )
+for cls in ["Shape", "Circle", "Square"]:
+    chain = [cls] + bases[cls]
+    array = put(rdata, RDATA_RVA, b"".join(struct.pack("<I", base_descriptors[c]) for c in chain), 4)
+    offset = hierarchies[cls] - RDATA_RVA
+    rdata[offset:offset + 16] = struct.pack("<IIII", 0, 0, len(chain), array)
+
+vtables = {}
+for cls in ["Shape", "Circle", "Square"]:
+    locator = RDATA_RVA + align(len(rdata), 4)
+    put(rdata, RDATA_RVA, struct.pack("<IIIIII", 1, 0, 0, descriptors[cls], hierarchies[cls], locator), 4)
+    relocations.append(put(rdata, RDATA_RVA, struct.pack("<Q", IMAGE_BASE + locator), 8))
+    vtables[cls] = RDATA_RVA + len(rdata)
+    for method in methods[cls]:
+        relocations.append(put(rdata, RDATA_RVA, struct.pack("<Q", IMAGE_BASE + labels[method]), 8))
+labels["vft_Circle"] = vtables["Circle"]
+
+# the global objects: a vtable pointer and one int
+labels["g_square"] = put(data, DATA_RVA, struct.pack("<QI", IMAGE_BASE + vtables["Square"], 3) + b"\0" * 4, 8)
+relocations.append(labels["g_square"])
+labels["g_circle"] = put(data, DATA_RVA, b"\0" * 16, 8)
+
+for offset, target in fixups:
+    target_rva = labels[target] if isinstance(target, str) else target
+    struct.pack_into("<i", text, offset, target_rva - (TEXT_RVA + offset + 4))
+
+# export directory: the methods under their decorated names
+exported = sorted({
+    "main": labels["main"],
+    "init_circle": labels["init_circle"],
+    "??_GShape@@UEAAPEAXI@Z": labels["dtor_Shape"],
+    "??_GCircle@@UEAAPEAXI@Z": labels["dtor_Circle"],
+    "??_GSquare@@UEAAPEAXI@Z": labels["dtor_Square"],
+    "?area@Shape@@UEBAHXZ": labels["area_Shape"],
+    "?area@Circle@@UEBAHXZ": labels["area_Circle"],
+    "?area@Square@@UEBAHXZ": labels["area_Square"],
+}.items())
+export_dir_rva = RDATA_RVA + align(len(rdata), 4)
+functions_rva = export_dir_rva + 40
+names_rva = functions_rva + 4 * len(exported)
+ordinals_rva = names_rva + 4 * len(exported)
+strings_rva = ordinals_rva + 2 * len(exported)
+strings = b"test_cpp.exe\0"
+name_rvas = []
+for name, _ in exported:
+    name_rvas.append(strings_rva + len(strings))
+    strings += name.encode() + b"\0"
+export_data = struct.pack(
+    "<IIHHIIIIIII",
+    0, 0, 0, 0, strings_rva, 1, len(exported), len(exported),
+    functions_rva, names_rva, ordinals_rva,
+)
+export_data += b"".join(struct.pack("<I", rva) for _, rva in exported)
+export_data += b"".join(struct.pack("<I", r) for r in name_rvas)
+export_data += b"".join(struct.pack("<H", i) for i in range(len(exported)))
+export_data += strings
+put(rdata, RDATA_RVA, export_data, 4)
+
+# .reloc: a block of IMAGE_REL_BASED_DIR64 entries per page, padded to 4 bytes
+reloc = bytearray()
+for page in sorted({rva & ~0xFFF for rva in relocations}):
+    entries = [(10 << 12) | (rva & 0xFFF) for rva in relocations if rva & ~0xFFF == page]
+    if len(entries) % 2:
+        entries.append(0)
+    reloc += struct.pack("<II", page, 8 + 2 * len(entries))
+    reloc += b"".join(struct.pack("<H", entry) for entry in entries)
+
+sections = [
+    (b".text", TEXT_RVA, bytes(text), 0x60000020),
+    (b".rdata", RDATA_RVA, bytes(rdata), 0x40000040),
+    (b".data", DATA_RVA, bytes(data), 0xC0000040),
+    (b".reloc", RELOC_RVA, bytes(reloc), 0x42000040),
+]
+
+headers_size = FILE_ALIGN
+size_of_image = align(RELOC_RVA + len(reloc), SECT_ALIGN)
+
+dos = bytearray(0x40)
+dos[0:2] = b"MZ"
+struct.pack_into("<I", dos, 0x3C, 0x40)
+
+coff = struct.pack("<HHIIIHH", 0x8664, len(sections), 0, 0, 0, 240, 0x0022)
+
+directories = [(0, 0)] * 16
+directories[0] = (export_dir_rva, len(export_data))
+directories[1] = (import_dir_rva, import_dir_size)
+directories[5] = (RELOC_RVA, len(reloc))
+directories[12] = (iat_start, iat_size)
+
+optional = struct.pack(
+    "<HBBIIIIIQIIHHHHHHIIIIHHQQQQII",
+    0x20B, 14, 0,
+    align(len(text), FILE_ALIGN), FILE_ALIGN * 3, 0,
+    labels["mainCRTStartup"], TEXT_RVA,
+    IMAGE_BASE, SECT_ALIGN, FILE_ALIGN,
+    6, 0, 0, 0, 6, 0, 0,
+    size_of_image, headers_size, 0,
+    3, 0x8160,
+    0x100000, 0x1000, 0x100000, 0x1000,
+    0, 16,
+) + b"".join(struct.pack("<II", rva, size) for rva, size in directories)
+
+section_headers = bytearray()
+raw = bytearray()
+file_offset = headers_size
+for name, rva, content, characteristics in sections:
+    raw_size = align(len(content), FILE_ALIGN)
+    section_headers += struct.pack(
+        "<8sIIIIIIHHI", name, len(content), rva, raw_size, file_offset, 0, 0, 0, 0, characteristics
+    )
+    raw += content + b"\0" * (raw_size - len(content))
+    file_offset += raw_size
+
+header = bytes(dos) + b"PE\0\0" + coff + optional + bytes(section_headers)
+header += b"\0" * (headers_size - len(header))
+
+with open("test_cpp.exe", "wb") as f:
+    f.write(header + bytes(raw))
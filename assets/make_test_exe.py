@@ -0,0 +1,194 @@
+#!/usr/bin/env python3
+"""Writes test.exe, a minimal PE32+ image equivalent to test.c.
+
+No Windows toolchain is needed: the code is assembled by hand below. The image
+imports KERNEL32.dll!ExitProcess and msvcrt.dll!puts through its IAT, exports
+main, hello_world and vuoto, and has a base relocation for the string pointer
+stored in .data.
+"""
+
+import struct
+
+IMAGE_BASE = 0x140000000
+FILE_ALIGN = 0x200
+SECT_ALIGN = 0x1000
+
+TEXT_RVA, RDATA_RVA, DATA_RVA, RELOC_RVA = 0x1000, 0x2000, 0x3000, 0x4000
+
+
+def align(value, to):
+    return (value + to - 1) & ~(to - 1)
+
+
+# .rdata: strings, then the import and export directories
+rdata = bytearray()
+
+
+def rdata_put(data, alignment=1):
+    while len(rdata) % alignment:
+        rdata.append(0)
+    rva = RDATA_RVA + len(rdata)
+    rdata.extend(data)
+    return rva
+
+
+hello_rva = rdata_put(b"Hello, world!\0")
+vuoto_str_rva = rdata_put(b"FUNZIONA?\0")
+
+imports = [("KERNEL32.dll", ["ExitProcess"]), ("msvcrt.dll", ["puts"])]
+hint_names = {}
+for dll, funcs in imports:
+    for func in funcs:
+        hint_names[func] = rdata_put(struct.pack("<H", 0) + func.encode() + b"\0", 2)
+dll_names = {dll: rdata_put(dll.encode() + b"\0") for dll, _ in imports}
+
+iat, ilt = {}, {}
+iat_start = None
+for dll, funcs in imports:
+    iat[dll] = rdata_put(b"".join(struct.pack("<Q", hint_names[f]) for f in funcs) + b"\0" * 8, 8)
+    iat_start = iat_start or iat[dll]
+iat_size = RDATA_RVA + len(rdata) - iat_start
+for dll, funcs in imports:
+    ilt[dll] = rdata_put(b"".join(struct.pack("<Q", hint_names[f]) for f in funcs) + b"\0" * 8, 8)
+
+import_dir_rva = RDATA_RVA + align(len(rdata), 4)
+descriptors = b"".join(
+    struct.pack("<IIIII", ilt[dll], 0, 0, dll_names[dll], iat[dll]) for dll, _ in imports
+) + b"\0" * 20
+rdata_put(descriptors, 4)
+import_dir_size = len(descriptors)
+
+
+def iat_slot(func):
+    for dll, funcs in imports:
+        if func in funcs:
+            return iat[dll] + 8 * funcs.index(func)
+
+
+# .text, assembled by hand
+text = bytearray()
+labels, fixups = {}, []
+
+
+def emit(data):
+    text.extend(data)
+
+
+def rel32(target, kind):
+    fixups.append((len(text), target, kind))
+    text.extend(b"\0\0\0\0")
+
+
+labels["mainCRTStartup"] = TEXT_RVA + len(text)
+emit(b"\x48\x83\xec\x28")                      # sub rsp,28h
+emit(b"\xe8"); rel32("main", "label")          # call main
+emit(b"\x89\xc1")                              # mov ecx,eax
+emit(b"\xff\x15"); rel32(iat_slot("ExitProcess"), "rva")  # call [ExitProcess]
+emit(b"\xcc")                                  # int3
+
+labels["main"] = TEXT_RVA + len(text)
+emit(b"\x48\x83\xec\x28")                      # sub rsp,28h
+emit(b"\x48\x8d\x0d"); rel32(hello_rva, "rva")  # lea rcx,[hello]
+emit(b"\xe8"); rel32("hello_world", "label")   # call hello_world
+emit(b"\x31\xc0")                              # xor eax,eax
+emit(b"\x48\x83\xc4\x28")                      # add rsp,28h
+emit(b"\xc3")                                  # ret
+
+labels["hello_world"] = TEXT_RVA + len(text)
+emit(b"\x48\x83\xec\x28")                      # sub rsp,28h
+emit(b"\xff\x15"); rel32(iat_slot("puts"), "rva")  # call [puts]
+emit(b"\x48\x83\xc4\x28")                      # add rsp,28h
+emit(b"\xc3")                                  # ret
+
+labels["vuoto"] = TEXT_RVA + len(text)
+emit(b"\x48\x8b\x05"); rel32(DATA_RVA, "rva")  # mov rax,[g_str]
+emit(b"\xc3")                                  # ret
+
+for offset, target, kind in fixups:
+    target_rva = labels[target] if kind == "label" else target
+    next_rva = TEXT_RVA + offset + 4
+    struct.pack_into("<i", text, offset, target_rva - next_rva)
+
+# export directory, appended to .rdata now that the function addresses are known;
+# the data directory has to cover the directory and all the tables it points to
+exported = sorted(["main", "hello_world", "vuoto"])
+while len(rdata) % 4:
+    rdata.append(0)
+export_dir_rva = RDATA_RVA + len(rdata)
+functions_rva = export_dir_rva + 40
+names_rva = functions_rva + 4 * len(exported)
+ordinals_rva = names_rva + 4 * len(exported)
+strings_rva = ordinals_rva + 2 * len(exported)
+strings = b"test.exe\0"
+name_rvas = []
+for name in exported:
+    name_rvas.append(strings_rva + len(strings))
+    strings += name.encode() + b"\0"
+export_data = struct.pack(
+    "<IIHHIIIIIII",
+    0, 0, 0, 0, strings_rva, 1, len(exported), len(exported),
+    functions_rva, names_rva, ordinals_rva,
+)
+export_data += b"".join(struct.pack("<I", labels[n]) for n in exported)
+export_data += b"".join(struct.pack("<I", r) for r in name_rvas)
+export_data += b"".join(struct.pack("<H", i) for i in range(len(exported)))
+export_data += strings
+rdata_put(export_data)
+
+# .data: a pointer to the string returned by vuoto, fixed up by the loader
+data = struct.pack("<Q", IMAGE_BASE + vuoto_str_rva)
+
+# .reloc: one IMAGE_REL_BASED_DIR64 entry, padded with an ABSOLUTE one
+reloc = struct.pack("<IIHH", DATA_RVA, 12, (10 << 12) | 0, 0)
+
+sections = [
+    (b".text", TEXT_RVA, bytes(text), 0x60000020),
+    (b".rdata", RDATA_RVA, bytes(rdata), 0x40000040),
+    (b".data", DATA_RVA, data, 0xC0000040),
+    (b".reloc", RELOC_RVA, reloc, 0x42000040),
+]
+
+headers_size = FILE_ALIGN
+size_of_image = align(RELOC_RVA + len(reloc), SECT_ALIGN)
+
+dos = bytearray(0x40)
+dos[0:2] = b"MZ"
+struct.pack_into("<I", dos, 0x3C, 0x40)
+
+coff = struct.pack("<HHIIIHH", 0x8664, len(sections), 0, 0, 0, 240, 0x0022)
+
+directories = [(0, 0)] * 16
+directories[0] = (export_dir_rva, len(export_data))
+directories[1] = (import_dir_rva, import_dir_size)
+directories[5] = (RELOC_RVA, len(reloc))
+directories[12] = (iat_start, iat_size)
+
+optional = struct.pack(
+    "<HBBIIIIIQIIHHHHHHIIIIHHQQQQII",
+    0x20B, 14, 0,
+    align(len(text), FILE_ALIGN), FILE_ALIGN * 3, 0,
+    labels["mainCRTStartup"], TEXT_RVA,
+    IMAGE_BASE, SECT_ALIGN, FILE_ALIGN,
+    6, 0, 0, 0, 6, 0, 0,
+    size_of_image, headers_size, 0,
+    3, 0x8160,
+    0x100000, 0x1000, 0x100000, 0x1000,
+    0, 16,
+) + b"".join(struct.pack("<II", rva, size) for rva, size in directories)
+
+section_headers = bytearray()
+raw = bytearray()
+file_offset = headers_size
+for name, rva, content, characteristics in sections:
+    raw_size = align(len(content), FILE_ALIGN)
+    section_headers += struct.pack(
+        "<8sIIIIIIHHI", name, len(content), rva, raw_size, file_offset, 0, 0, 0, 0, characteristics
+    )
+    raw += content + b"\0" * (raw_size - len(content))
+    file_offset += raw_size
+
+header = bytes(dos) + b"PE\0\0" + coff + optional + bytes(section_headers)
+header += b"\0" * (headers_size - len(header))
+
+with open("test.exe", "wb") as f:
+    f.write(header + bytes(raw))
@@ -0,0 +1,184 @@
+#!/usr/bin/env python3
+"""Writes test.macho and test.fat, minimal Mach-O images equivalent to test.c.
+
+No Apple toolchain is needed: the code is assembled by hand below. The image
+starts through LC_MAIN, calls _puts through a __stubs entry bound by
+__la_symbol_ptr, and keeps the string returned by vuoto in __data.
+
+test.fat is a universal binary holding an arm64 slice before the x86-64 one,
+so that the loader has to pick the right architecture.
+"""
+
+import struct
+
+TEXT_VA = 0x100000000
+PAGE = 0x1000
+
+CPU_TYPE_X86_64 = 0x01000007
+CPU_TYPE_ARM64 = 0x0100000C
+
+S_SYMBOL_STUBS = 0x8
+S_LAZY_SYMBOL_POINTERS = 0x7
+S_ATTR_CODE = 0x80000400  # S_ATTR_PURE_INSTRUCTIONS | S_ATTR_SOME_INSTRUCTIONS
+
+# file layout: headers and __TEXT in the first page, __DATA in the second,
+# __LINKEDIT in the third
+TEXT_OFF = 0x400
+DATA_OFF, LINKEDIT_OFF = PAGE, 2 * PAGE
+
+
+def va(offset):
+    return TEXT_VA + offset
+
+
+# __TEXT,__text and __TEXT,__stubs, assembled by hand
+text = bytearray()
+labels, fixups = {}, []
+
+
+def emit(data):
+    text.extend(data)
+
+
+def rel32(target):
+    fixups.append((len(text), target))
+    text.extend(b"\0\0\0\0")
+
+
+labels["_main"] = len(text)
+emit(b"\x55")                          # push rbp
+emit(b"\x48\x89\xe5")                  # mov rbp,rsp
+emit(b"\x48\x8d\x3d"); rel32("hello")  # lea rdi,[hello]
+emit(b"\xe8"); rel32("_hello_world")   # call _hello_world
+emit(b"\x31\xc0")                      # xor eax,eax
+emit(b"\x5d")                          # pop rbp
+emit(b"\xc3")                          # ret
+
+labels["_hello_world"] = len(text)
+emit(b"\x55")                          # push rbp
+emit(b"\x48\x89\xe5")                  # mov rbp,rsp
+emit(b"\xe8"); rel32("stub_puts")      # call _puts
+emit(b"\x5d")                          # pop rbp
+emit(b"\xc3")                          # ret
+
+labels["_vuoto"] = len(text)
+emit(b"\x48\x8b\x05"); rel32("g_str")  # mov rax,[g_str]
+emit(b"\xc3")                          # ret
+
+text_size = len(text)
+while len(text) % 2:
+    text.append(0x90)
+
+stubs_off = len(text)
+labels["stub_puts"] = stubs_off
+emit(b"\xff\x25"); rel32("la_puts")    # jmp [_puts lazy pointer]
+stubs_size = len(text) - stubs_off
+
+cstring_off = len(text)
+labels["hello"] = len(text)
+emit(b"Hello, world!\0")
+labels["str_vuoto"] = len(text)
+emit(b"FUNZIONA?\0")
+cstring_size = len(text) - cstring_off
+
+# __DATA,__la_symbol_ptr and __DATA,__data
+labels["la_puts"] = DATA_OFF - TEXT_OFF
+labels["g_str"] = DATA_OFF - TEXT_OFF + 8
+data = struct.pack("<QQ", va(TEXT_OFF + stubs_off), va(TEXT_OFF + labels["str_vuoto"]))
+
+for offset, target in fixups:
+    struct.pack_into("<i", text, offset, labels[target] - (offset + 4))
+
+# __LINKEDIT: symbols sorted as defined externals then undefined, indirect
+# symbols for __stubs and __la_symbol_ptr, and the string table
+defined = ["_hello_world", "_main", "_vuoto"]
+undefined = ["_puts"]
+strtab = bytearray(b" \0")
+symtab = bytearray()
+for name in defined + undefined:
+    n_strx = len(strtab)
+    strtab += name.encode() + b"\0"
+    if name in defined:
+        # N_SECT | N_EXT, in section 1 (__text)
+        symtab += struct.pack("<IBBHQ", n_strx, 0x0F, 1, 0, va(TEXT_OFF + labels[name]))
+    else:
+        # N_UNDF | N_EXT, bound from the first dylib
+        symtab += struct.pack("<IBBHQ", n_strx, 0x01, 0, 1 << 8, 0)
+puts_index = len(defined) + undefined.index("_puts")
+indirect = struct.pack("<II", puts_index, puts_index)
+
+symoff = LINKEDIT_OFF
+indirectoff = symoff + len(symtab)
+stroff = indirectoff + len(indirect)
+while len(strtab) % 8:
+    strtab.append(0)
+linkedit = symtab + indirect + strtab
+
+
+def section(name, segment, addr, size, offset, align, flags, reserved1=0, reserved2=0):
+    return struct.pack(
+        "<16s16sQQIIIIIIII", name, segment, addr, size, offset, align, 0, 0, flags, reserved1, reserved2, 0
+    )
+
+
+def segment(name, vmaddr, vmsize, fileoff, filesize, prot, sections):
+    return struct.pack(
+        "<II16sQQQQiiII", 0x19, 72 + 80 * len(sections), name, vmaddr, vmsize, fileoff, filesize,
+        prot, prot, len(sections), 0,
+    ) + b"".join(sections)
+
+
+dylib_path = b"/usr/lib/libSystem.B.dylib\0"
+dylib_path += b"\0" * (-(24 + len(dylib_path)) % 8)
+
+commands = [
+    segment(b"__PAGEZERO", 0, TEXT_VA, 0, 0, 0, []),
+    segment(b"__TEXT", TEXT_VA, PAGE, 0, PAGE, 5, [
+        section(b"__text", b"__TEXT", va(TEXT_OFF), text_size, TEXT_OFF, 4, S_ATTR_CODE),
+        section(b"__stubs", b"__TEXT", va(TEXT_OFF + stubs_off), stubs_size, TEXT_OFF + stubs_off, 1,
+                S_ATTR_CODE | S_SYMBOL_STUBS, reserved1=0, reserved2=stubs_size),
+        section(b"__cstring", b"__TEXT", va(TEXT_OFF + cstring_off), cstring_size,
+                TEXT_OFF + cstring_off, 0, 0x2),
+    ]),
+    segment(b"__DATA", va(DATA_OFF), PAGE, DATA_OFF, PAGE, 3, [
+        section(b"__la_symbol_ptr", b"__DATA", va(DATA_OFF), 8, DATA_OFF, 3,
+                S_LAZY_SYMBOL_POINTERS, reserved1=1),
+        section(b"__data", b"__DATA", va(DATA_OFF + 8), 8, DATA_OFF + 8, 3, 0),
+    ]),
+    segment(b"__LINKEDIT", va(LINKEDIT_OFF), PAGE, LINKEDIT_OFF, len(linkedit), 1, []),
+    struct.pack("<IIIIII", 0x2, 24, symoff, len(symtab) // 16, stroff, len(strtab)),
+    struct.pack(
+        "<20I", 0xB, 80, 0, 0, 0, len(defined), len(defined), len(undefined),
+        0, 0, 0, 0, 0, 0, indirectoff, len(indirect) // 4, 0, 0, 0, 0,
+    ),
+    struct.pack("<IIIIII", 0xC, 24 + len(dylib_path), 24, 2, 0x10000, 0x10000) + dylib_path,
+    struct.pack("<IIQQ", 0x80000028, 24, TEXT_OFF + labels["_main"], 0),
+]
+
+# MH_DYLDLINK | MH_TWOLEVEL | MH_PIE
+header = struct.pack(
+    "<IiiIIIII", 0xFEEDFACF, CPU_TYPE_X86_64, 3, 2, len(commands), sum(map(len, commands)),
+    0x200085, 0,
+)
+image = bytearray(header + b"".join(commands))
+assert len(image) <= TEXT_OFF
+image += b"\0" * (TEXT_OFF - len(image)) + text
+image += b"\0" * (DATA_OFF - len(image)) + data
+image += b"\0" * (LINKEDIT_OFF - len(image)) + linkedit
+
+with open("test.macho", "wb") as f:
+    f.write(image)
+
+# an arm64 slice with no load commands is enough to be skipped
+arm64 = struct.pack("<IiiIIIII", 0xFEEDFACF, CPU_TYPE_ARM64, 0, 2, 0, 0, 0, 0)
+slices = [(CPU_TYPE_ARM64, 0, arm64), (CPU_TYPE_X86_64, 3, bytes(image))]
+fat = bytearray(struct.pack(">II", 0xCAFEBABE, len(slices)))
+offset = PAGE
+for cputype, cpusubtype, content in slices:
+    fat += struct.pack(">IIIII", cputype, cpusubtype, offset, len(content), 12)
+    offset += (len(content) + PAGE - 1) & ~(PAGE - 1)
+for _, _, content in slices:
+    fat += b"\0" * (-len(fat) % PAGE) + content
+
+with open("test.fat", "wb") as f:
+    f.write(fat)
@@ -0,0 +1,371 @@
+#!/usr/bin/env python3
+"""Writes test_riscv64, a minimal stripped RV64GC Linux executable equivalent to test.c.
+
+No cross toolchain is needed: the code is assembled by hand below, in the shape
+gcc -O0 gives it, compressed instructions included. _start passes the address of
+main to __libc_start_main through its PLT entry, main calls hello_world, which
+calls puts.
+"""
+
+import struct
+
+BASE = 0x10000
+# the writable segment is mapped on pages of its own
+DATA_DELTA = 0x1000
+PAGE = 0x1000
+
+R_RISCV_JUMP_SLOT = 5
+IMPORTS = ["__libc_start_main", "puts", "abort"]
+
+ZERO, RA, SP, T0, T1, T2, S0 = 0, 1, 2, 5, 6, 7, 8
+A0, A1, A2, A3, A4, A5, A6 = 10, 11, 12, 13, 14, 15, 16
+T3 = 28
+
+
+def align(value, alignment):
+    return (value + alignment - 1) & ~(alignment - 1)
+
+
+def split(offset):
+    """Splits an offset into the %hi and %lo parts of lui/auipc and addi."""
+    hi = (offset + 0x800) >> 12
+    return hi & 0xFFFFF, offset - (hi << 12)
+
+
+# 32-bit encodings used below
+def i_type(opcode, funct3, rd, rs1, imm):
+    return struct.pack("<I", (imm & 0xFFF) << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode)
+
+
+def s_type(funct3, rs1, rs2, imm):
+    imm &= 0xFFF
+    word = (imm >> 5) << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | (imm & 0x1F) << 7 | 0x23
+    return struct.pack("<I", word)
+
+
+def r_type(funct7, funct3, rd, rs1, rs2):
+    return struct.pack("<I", funct7 << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | 0x33)
+
+
+def addi(rd, rs1, imm):
+    return i_type(0x13, 0, rd, rs1, imm)
+
+
+def andi(rd, rs1, imm):
+    return i_type(0x13, 7, rd, rs1, imm)
+
+
+def srli(rd, rs1, shamt):
+    return i_type(0x13, 5, rd, rs1, shamt)
+
+
+def ld(rd, rs1, imm):
+    return i_type(0x03, 3, rd, rs1, imm)
+
+
+def sd(rs2, rs1, imm):
+    return s_type(3, rs1, rs2, imm)
+
+
+def sw(rs2, rs1, imm):
+    return s_type(2, rs1, rs2, imm)
+
+
+def sub(rd, rs1, rs2):
+    return r_type(0x20, 0, rd, rs1, rs2)
+
+
+def jalr(rd, rs1, imm):
+    return i_type(0x67, 0, rd, rs1, imm)
+
+
+def lui(rd, imm):
+    return struct.pack("<I", imm << 12 | rd << 7 | 0x37)
+
+
+def auipc(rd, imm):
+    return struct.pack("<I", imm << 12 | rd << 7 | 0x17)
+
+
+def jal(rd, pc, target):
+    imm = (target - pc) & 0x1FFFFF
+    word = (
+        (imm >> 20) << 31
+        | ((imm >> 1) & 0x3FF) << 21
+        | ((imm >> 11) & 1) << 20
+        | ((imm >> 12) & 0xFF) << 12
+        | rd << 7
+        | 0x6F
+    )
+    return struct.pack("<I", word)
+
+
+NOP = addi(ZERO, ZERO, 0)
+
+
+# compressed encodings used below
+def half(value):
+    return struct.pack("<H", value)
+
+
+def c_mv(rd, rs2):
+    return half(0x8002 | rd << 7 | rs2 << 2)
+
+
+def c_li(rd, imm):
+    imm &= 0x3F
+    return half(0x4001 | (imm >> 5) << 12 | rd << 7 | (imm & 0x1F) << 2)
+
+
+def c_addi16sp(imm):
+    imm &= 0x3FF
+    return half(
+        0x6101
+        | (imm >> 9) << 12
+        | ((imm >> 4) & 1) << 6
+        | ((imm >> 6) & 1) << 5
+        | ((imm >> 7) & 3) << 3
+        | ((imm >> 5) & 1) << 2
+    )
+
+
+def c_addi4spn(rd, imm):
+    return half(
+        ((imm >> 4) & 3) << 11
+        | ((imm >> 6) & 0xF) << 7
+        | ((imm >> 2) & 1) << 6
+        | ((imm >> 3) & 1) << 5
+        | (rd - 8) << 2
+    )
+
+
+def c_ldsp(rd, imm):
+    return half(0x6002 | ((imm >> 5) & 1) << 12 | rd << 7 | ((imm >> 3) & 3) << 5 | ((imm >> 6) & 7) << 2)
+
+
+def c_sdsp(rs2, imm):
+    return half(0xE002 | ((imm >> 3) & 7) << 10 | ((imm >> 6) & 7) << 7 | rs2 << 2)
+
+
+C_NOP = half(0x0001)
+C_EBREAK = half(0x9002)
+C_RET = half(0x8082)
+
+
+def call(pc, target):
+    """`call`, the auipc/jalr pair through ra."""
+    hi, lo = split(target - pc)
+    return auipc(RA, hi) + jalr(RA, RA, lo)
+
+
+def la(rd, pc, target):
+    """`lla`, the pc-relative address of `target`."""
+    hi, lo = split(target - pc)
+    return auipc(rd, hi) + addi(rd, rd, lo)
+
+
+def absolute(rd, upper, target):
+    """lui and addi building the address of `target` in rd, as in the medlow code model."""
+    hi, lo = split(target)
+    return lui(upper, hi) + addi(rd, upper, lo)
+
+
+# file layout: the headers and everything read-only in the first segment,
+# .dynamic and .got.plt in the second
+PHDRS = 4
+interp = b"/lib/ld-linux-riscv64-lp64d.so.1\0"
+interp_off = 64 + 56 * PHDRS
+
+dynstr = bytearray(b"\0libc.so.6\0")
+names = {}
+for name in IMPORTS:
+    names[name] = len(dynstr)
+    dynstr += name.encode() + b"\0"
+
+dynsym_off = align(interp_off + len(interp), 8)
+dynsym = bytes(24) + b"".join(
+    # STB_GLOBAL | STT_FUNC, undefined
+    struct.pack("<IBBHQQ", names[name], 0x12, 0, 0, 0, 0) for name in IMPORTS
+)
+dynstr_off = dynsym_off + len(dynsym)
+rela_off = align(dynstr_off + len(dynstr), 8)
+rela_size = 24 * len(IMPORTS)
+plt_off = align(rela_off + rela_size, 16)
+plt_size = 32 + 16 * len(IMPORTS)
+text_off = plt_off + plt_size
+
+data_off = PAGE
+dynamic_off = data_off
+dynamic_size = 16 * 10
+got_off = dynamic_off + dynamic_size
+got_size = 8 * (2 + len(IMPORTS))
+
+
+def va(offset):
+    return BASE + offset
+
+
+def data_va(offset):
+    return BASE + DATA_DELTA + offset
+
+
+got = data_va(got_off)
+plt = va(plt_off)
+
+
+def slot(i):
+    return got + 8 * (2 + i)
+
+
+def stub(i):
+    return plt + 32 + 16 * i
+
+
+def assemble(labels):
+    """Lays out .plt and .text from `labels`, returning the code and the actual labels."""
+    code = bytearray()
+    found = {}
+
+    def pc():
+        return plt + len(code)
+
+    # .plt: PLT0 computes the relocation index from t1 and jumps to the resolver,
+    # the entries jump through their GOT slots
+    hi, lo = split(got - pc())
+    code.extend(auipc(T2, hi) + sub(T1, T1, T3) + ld(T3, T2, lo) + addi(T1, T1, -44))
+    code.extend(addi(T0, T2, lo) + srli(T1, T1, 1) + ld(T0, T0, 8) + jalr(ZERO, T3, 0))
+    for i in range(len(IMPORTS)):
+        hi, lo = split(slot(i) - pc())
+        code.extend(auipc(T3, hi) + ld(T3, T3, lo) + jalr(T1, T3, 0) + NOP)
+
+    # .text: the functions, then the strings in .rodata right after
+    found["start"] = pc()
+    code.extend(c_mv(A5, A0))
+    code.extend(la(A0, pc(), labels["main"]))
+    code.extend(c_ldsp(A1, 0) + c_addi4spn(A2, 8) + andi(SP, SP, -16))
+    code.extend(c_li(A3, 0) + c_li(A4, 0) + c_mv(A6, SP))
+    code.extend(call(pc(), stub(0)))
+    code.extend(C_EBREAK)
+
+    found["hello_world"] = pc()
+    code.extend(c_addi16sp(-32) + c_sdsp(RA, 24) + c_sdsp(S0, 16) + c_addi4spn(S0, 32))
+    code.extend(sd(A0, S0, -24) + ld(A0, S0, -24))
+    code.extend(call(pc(), stub(1)))
+    code.extend(C_NOP + c_ldsp(RA, 24) + c_ldsp(S0, 16) + c_addi16sp(32) + C_RET)
+
+    found["main"] = pc()
+    code.extend(c_addi16sp(-32) + c_sdsp(RA, 24) + c_sdsp(S0, 16) + c_addi4spn(S0, 32))
+    code.extend(c_mv(A5, A0) + sd(A1, S0, -32) + sw(A5, S0, -20))
+    code.extend(absolute(A0, A5, labels["hello"]))
+    code.extend(jal(RA, pc(), labels["hello_world"]))
+    code.extend(c_li(A5, 0) + c_mv(A0, A5))
+    code.extend(c_ldsp(RA, 24) + c_ldsp(S0, 16) + c_addi16sp(32) + C_RET)
+
+    found["vuoto"] = pc()
+    code.extend(c_addi16sp(-16) + c_sdsp(S0, 8) + c_addi4spn(S0, 16))
+    code.extend(absolute(A5, A5, labels["str_vuoto"]))
+    code.extend(c_mv(A0, A5) + c_ldsp(S0, 8) + c_addi16sp(16) + C_RET)
+
+    found["hello"] = pc()
+    found["str_vuoto"] = found["hello"] + len(b"Hello, world!\0")
+    return bytes(code), found
+
+
+# the sizes do not depend on the labels, so a second pass resolves them
+_, labels = assemble({name: plt for name in ["main", "hello_world", "hello", "str_vuoto"]})
+code, labels = assemble(labels)
+start = labels["start"]
+
+plt_code, text = code[:plt_size], code[plt_size:]
+strings = b"Hello, world!\0FUNZIONA?\0"
+rodata_off = text_off + len(text)
+text_end = rodata_off + len(strings)
+
+rela = b"".join(
+    struct.pack("<QQq", slot(i), (i + 1) << 32 | R_RISCV_JUMP_SLOT, 0) for i in range(len(IMPORTS))
+)
+
+DT_NEEDED, DT_PLTRELSZ, DT_PLTGOT, DT_STRTAB, DT_SYMTAB = 1, 2, 3, 5, 6
+DT_STRSZ, DT_SYMENT, DT_PLTREL, DT_JMPREL = 10, 11, 20, 23
+dynamic = b"".join(
+    struct.pack("<qQ", tag, value)
+    for tag, value in [
+        (DT_NEEDED, 1),
+        (DT_STRTAB, va(dynstr_off)),
+        (DT_SYMTAB, va(dynsym_off)),
+        (DT_STRSZ, len(dynstr)),
+        (DT_SYMENT, 24),
+        (DT_PLTGOT, got),
+        (DT_PLTRELSZ, rela_size),
+        (DT_PLTREL, 7),
+        (DT_JMPREL, va(rela_off)),
+        (0, 0),
+    ]
+)
+assert len(dynamic) == dynamic_size
+got_plt = struct.pack("<qQ", -1, 0) + b"".join(struct.pack("<Q", plt) for _ in IMPORTS)
+
+shstrtab = bytearray(b"\0")
+sections = []
+
+
+def section(name, sh_type, flags, addr, offset, size, link=0, info=0, addralign=1, entsize=0):
+    sections.append((len(shstrtab), sh_type, flags, addr, offset, size, link, info, addralign, entsize))
+    shstrtab.extend(name + b"\0")
+
+
+SHF_WRITE, SHF_ALLOC, SHF_EXECINSTR, SHF_INFO_LINK = 1, 2, 4, 0x40
+sections.append((0,) * 10)
+section(b".interp", 1, SHF_ALLOC, va(interp_off), interp_off, len(interp))
+section(b".dynsym", 11, SHF_ALLOC, va(dynsym_off), dynsym_off, len(dynsym), 3, 1, 8, 24)
+section(b".dynstr", 3, SHF_ALLOC, va(dynstr_off), dynstr_off, len(dynstr))
+section(b".rela.plt", 4, SHF_ALLOC | SHF_INFO_LINK, va(rela_off), rela_off, rela_size, 2, 9, 8, 24)
+section(b".plt", 1, SHF_ALLOC | SHF_EXECINSTR, plt, plt_off, plt_size, addralign=16, entsize=16)
+section(b".text", 1, SHF_ALLOC | SHF_EXECINSTR, va(text_off), text_off, len(text), addralign=2)
+section(b".rodata", 1, SHF_ALLOC, va(rodata_off), rodata_off, len(strings))
+section(b".dynamic", 6, SHF_WRITE | SHF_ALLOC, data_va(dynamic_off), dynamic_off, dynamic_size, 3, 0, 8, 16)
+section(b".got.plt", 1, SHF_WRITE | SHF_ALLOC, got, got_off, got_size, addralign=8, entsize=8)
+shstrndx = len(sections)
+section(b".shstrtab", 3, 0, 0, 0, 0)
+
+shstrtab_off = got_off + got_size
+shoff = align(shstrtab_off + len(shstrtab), 8)
+sections[shstrndx] = sections[shstrndx][:4] + (shstrtab_off, len(shstrtab)) + sections[shstrndx][6:]
+
+PT_LOAD, PT_DYNAMIC, PT_INTERP = 1, 2, 3
+phdrs = [
+    (PT_INTERP, 4, interp_off, va(interp_off), len(interp), len(interp), 1),
+    (PT_LOAD, 5, 0, BASE, text_end, text_end, PAGE),
+    (PT_LOAD, 6, data_off, data_va(data_off), got_off + got_size - data_off,
+     got_off + got_size - data_off, PAGE),
+    (PT_DYNAMIC, 6, dynamic_off, data_va(dynamic_off), dynamic_size, dynamic_size, 8),
+]
+assert len(phdrs) == PHDRS
+
+# ELFCLASS64, ELFDATA2LSB, ET_EXEC, EM_RISCV, EF_RISCV_RVC | EF_RISCV_FLOAT_ABI_DOUBLE
+header = b"\x7fELF\x02\x01\x01" + bytes(9) + struct.pack(
+    "<HHIQQQIHHHHHH", 2, 243, 1, start, 64, shoff, 0x5, 64, 56, PHDRS, 64, len(sections), shstrndx
+)
+image = bytearray(header)
+for p_type, flags, offset, vaddr, filesz, memsz, alignment in phdrs:
+    image += struct.pack("<IIQQQQQQ", p_type, flags, offset, vaddr, vaddr, filesz, memsz, alignment)
+for offset, content in [
+    (interp_off, interp),
+    (dynsym_off, dynsym),
+    (dynstr_off, dynstr),
+    (rela_off, rela),
+    (plt_off, plt_code),
+    (text_off, text),
+    (rodata_off, strings),
+    (dynamic_off, dynamic),
+    (got_off, got_plt),
+    (shstrtab_off, shstrtab),
+]:
+    assert len(image) <= offset
+    image += b"\0" * (offset - len(image)) + content
+image += b"\0" * (shoff - len(image))
+for header in sections:
+    image += struct.pack("<IIQQQQIIQQ", *header)
+
+with open("test_riscv64", "wb") as f:
+    f.write(image)
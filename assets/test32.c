@@ -0,0 +1,24 @@
+int puts(const char *s);
+
+char * vuoto() {
+    return "FUNZIONA?";
+}
+
+int hello_world(char * str) {
+    return puts(str);
+}
+
+int __attribute__((stdcall)) somma(int a, int b) {
+    return a + b;
+}
+
+int __attribute__((fastcall)) differenza(int a, int b, int c) {
+    return a - b - c;
+}
+
+int main(int argc, char ** argv) {
+    hello_world("Hello, world!");
+    differenza(5, 2, 1);
+
+    return somma(1, 2);
+}
@@ -0,0 +1,58 @@
+#include <cstdio>
+
+namespace zoo {
+struct Animal {
+    virtual ~Animal() {}
+    virtual const char *speak() const = 0;
+    virtual int legs() const { return 4; }
+};
+
+struct Dog : Animal {
+    const char *speak() const override { return "woof"; }
+};
+
+struct Bird : Animal {
+    const char *speak() const override { return "tweet"; }
+    int legs() const override { return 2; }
+};
+
+struct Named {
+    virtual const char *name() const { return "named"; }
+    virtual ~Named() {}
+};
+
+struct Parrot : Bird, Named {
+    const char *speak() const override { return "hello"; }
+    const char *name() const override { return "polly"; }
+};
+}
+
+using namespace zoo;
+
+__attribute__((noinline)) void talk(const Animal *a) {
+    printf("%s %d\n", a->speak(), a->legs());
+}
+
+__attribute__((noinline)) void call_name(const Named *n) {
+    puts(n->name());
+}
+
+__attribute__((noinline)) Animal *make(int i) {
+    switch (i) {
+    case 0:
+        return new Dog;
+    case 1:
+        return new Bird;
+    default:
+        return new Parrot;
+    }
+}
+
+int main(int argc, char **) {
+    Animal *a = make(argc);
+    talk(a);
+    if (argc > 2)
+        call_name(new Parrot);
+    delete a;
+    return 0;
+}
@@ -0,0 +1,52 @@
+#include <stdio.h>
+
+struct point {
+    int x;
+    int y;
+};
+
+typedef struct {
+    struct point origin;
+    unsigned long width;
+    unsigned long height;
+} rect_t;
+
+union value {
+    long integer;
+    char bytes[8];
+};
+
+enum shape {
+    SHAPE_NONE,
+    SHAPE_RECT = 4,
+    SHAPE_CIRCLE,
+};
+
+typedef int (*visitor_t)(const char *name, ...);
+
+long area(const rect_t *rect)
+{
+    long result = rect->width * rect->height;
+    return result;
+}
+
+int describe(enum shape kind, visitor_t visit)
+{
+    struct point corner;
+    union value value;
+
+    corner.x = 3;
+    corner.y = 4;
+    value.integer = corner.x + corner.y;
+    if (kind == SHAPE_RECT && visit != NULL)
+        puts("rect");
+    return corner.x + value.bytes[1];
+}
+
+int main(int argc, char **argv)
+{
+    rect_t rect = { { 0, 0 }, 6, 7 };
+
+    describe(SHAPE_RECT, printf);
+    return (int)area(&rect) + argc;
+}
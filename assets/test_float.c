@@ -0,0 +1,14 @@
+#include <stdio.h>
+__attribute__((noinline)) double mul(double a, double b) { return a * b + 1.0; }
+__attribute__((noinline)) float half(float x) { return x / 2.0f; }
+__attribute__((noinline)) int mixed(int n, double y) { return n + (int)y; }
+__attribute__((noinline)) double scale(int n, double y, long k) { return y * n + k; }
+__attribute__((noinline)) double zero(void) { return 0.0; }
+__attribute__((noinline)) void store(double *p, double v) { *p = v; }
+__attribute__((noinline)) long add(long a, long b) { return a + b; }
+int main(int argc, char **argv) {
+    double d;
+    store(&d, mul(argc, 2.5));
+    printf("%f %f %d %f %f %ld\n", d, half(argc), mixed(argc, d), scale(argc, d, 3), zero(), add(argc, 4));
+    return 0;
+}
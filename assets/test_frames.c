@@ -0,0 +1,32 @@
+#include <stdio.h>
+
+int greet(int n) {
+    return printf("%d items\n", n);
+}
+
+/* no frame pointer: the callback is left in a stack slot, which the push moves */
+int dispatch(int n);
+__asm__(
+    ".globl dispatch\n"
+    "dispatch:\n"
+    ".cfi_startproc\n"
+    "    sub $0x10, %rsp\n"
+    ".cfi_def_cfa_offset 0x18\n"
+    "    lea greet(%rip), %rax\n"
+    "    mov %rax, 0x8(%rsp)\n"
+    "    push %rbx\n"
+    ".cfi_def_cfa_offset 0x20\n"
+    ".cfi_offset %rbx, -0x20\n"
+    "    mov %edi, %ebx\n"
+    "    call *0x10(%rsp)\n"
+    "    add %ebx, %eax\n"
+    "    pop %rbx\n"
+    ".cfi_def_cfa_offset 0x18\n"
+    "    add $0x10, %rsp\n"
+    ".cfi_def_cfa_offset 0x8\n"
+    "    ret\n"
+    ".cfi_endproc\n");
+
+int main(int argc, char **argv) {
+    return dispatch(argc);
+}
@@ -0,0 +1,37 @@
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+__attribute__((noinline)) int greet(const char *name, int n) {
+    char buf[32];
+    strcpy(buf, name);
+    printf("%s has %d items\n", buf, n);
+    return (int)strlen(buf);
+}
+
+__attribute__((noinline)) void copy(char *dst, const char *src, size_t len) {
+    char tmp[64];
+    memcpy(tmp, src, len);
+    snprintf(dst, 16, "%.8s:%zu", tmp, len);
+}
+
+__attribute__((noinline)) ssize_t fill(int fd) {
+    char buf[128];
+    ssize_t n = read(fd, buf, sizeof buf);
+    fprintf(stderr, "read %zd bytes\n", n);
+    return n > 0 ? buf[0] : -1;
+}
+
+__attribute__((noinline)) void stash(const char *s) {
+    char buf[64];
+    memcpy(buf, s, 64);
+    puts(buf);
+}
+
+int main(int argc, char **argv) {
+    char out[16];
+    copy(out, argv[0], (size_t)argc * 4);
+    puts(out);
+    stash(argv[0]);
+    return greet(argv[0], argc) + (int)fill(0);
+}
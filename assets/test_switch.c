@@ -0,0 +1,49 @@
+#include <stdio.h>
+
+int total;
+
+/* a jump table indexed by a register */
+__attribute__((noinline)) void count(int op) {
+    switch (op) {
+    case 0:
+        puts("zero");
+        break;
+    case 1:
+        total += 3;
+        break;
+    case 2:
+        total *= 5;
+        break;
+    case 3:
+        total -= 7;
+        break;
+    case 5:
+        printf("%d\n", total);
+        break;
+    default:
+        total = 0;
+    }
+}
+
+/* the index compared in its stack slot, as unoptimized code does */
+__attribute__((noinline, optimize("O0"))) int grade(int score) {
+    switch (score) {
+    case 0:
+        return 'F';
+    case 1:
+        return 'D';
+    case 2:
+        return 'C';
+    case 3:
+        return 'B';
+    case 4:
+        return 'A';
+    default:
+        return '?';
+    }
+}
+
+int main(int argc, char **argv) {
+    count(argc);
+    return grade(argc);
+}
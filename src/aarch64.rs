@@ -0,0 +1,1920 @@
+//! Decoding and disassembly of the A64 instructions found in ordinary user-space code.
+//!
+//! The integer subset, scalar floating-point and the exclusive and acquire-release
+//! accesses are decoded in full; SIMD data processing, the LSE atomics and most system
+//! instructions come out as [`Opcode::Unknown`], which is enough to keep control flow
+//! recovery going.
+
+use std::fmt::{Display, Formatter, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegClass {
+    X,
+    W,
+    B,
+    H,
+    S,
+    D,
+    Q,
+}
+
+/// A general purpose or SIMD&FP register.
+///
+/// General purpose register 31 is the stack pointer when `sp` is set and the zero
+/// register otherwise, depending on the instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reg {
+    pub class: RegClass,
+    pub num: u8,
+    pub sp: bool,
+}
+
+impl Reg {
+    fn gp(sf: bool, num: u32) -> Self {
+        Self {
+            class: if sf { RegClass::X } else { RegClass::W },
+            num: num as u8,
+            sp: false,
+        }
+    }
+
+    fn gp_or_sp(sf: bool, num: u32) -> Self {
+        Self {
+            sp: num == 31,
+            ..Self::gp(sf, num)
+        }
+    }
+
+    fn x(num: u32) -> Self {
+        Self::gp(true, num)
+    }
+
+    fn fp(class: RegClass, num: u32) -> Self {
+        Self {
+            class,
+            num: num as u8,
+            sp: false,
+        }
+    }
+
+    /// Returns `true` for `xzr` and `wzr`.
+    pub fn is_zero(&self) -> bool {
+        self.num == 31 && !self.sp && matches!(self.class, RegClass::X | RegClass::W)
+    }
+
+    pub fn is_general(&self) -> bool {
+        matches!(self.class, RegClass::X | RegClass::W)
+    }
+}
+
+impl Display for Reg {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match (self.class, self.num, self.sp) {
+            (RegClass::X, 31, true) => f.write_str("sp"),
+            (RegClass::W, 31, true) => f.write_str("wsp"),
+            (RegClass::X, 31, false) => f.write_str("xzr"),
+            (RegClass::W, 31, false) => f.write_str("wzr"),
+            (class, num, _) => {
+                let prefix = match class {
+                    RegClass::X => "x",
+                    RegClass::W => "w",
+                    RegClass::B => "b",
+                    RegClass::H => "h",
+                    RegClass::S => "s",
+                    RegClass::D => "d",
+                    RegClass::Q => "q",
+                };
+                f.write_fmt(format_args!("{prefix}{num}"))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cond {
+    Eq,
+    Ne,
+    Hs,
+    Lo,
+    Mi,
+    Pl,
+    Vs,
+    Vc,
+    Hi,
+    Ls,
+    Ge,
+    Lt,
+    Gt,
+    Le,
+    Al,
+    Nv,
+}
+
+impl Cond {
+    fn from_bits(bits: u32) -> Self {
+        [
+            Cond::Eq,
+            Cond::Ne,
+            Cond::Hs,
+            Cond::Lo,
+            Cond::Mi,
+            Cond::Pl,
+            Cond::Vs,
+            Cond::Vc,
+            Cond::Hi,
+            Cond::Ls,
+            Cond::Ge,
+            Cond::Lt,
+            Cond::Gt,
+            Cond::Le,
+            Cond::Al,
+            Cond::Nv,
+        ][(bits & 0xf) as usize]
+    }
+
+    /// Returns the opposite condition, flipping the lowest bit of the encoding.
+    pub fn invert(self) -> Self {
+        Self::from_bits(self as u32 ^ 1)
+    }
+}
+
+impl Display for Cond {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(&format!("{:?}", self).to_lowercase())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shift {
+    Lsl,
+    Lsr,
+    Asr,
+    Ror,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Extend {
+    Uxtb,
+    Uxth,
+    Uxtw,
+    Uxtx,
+    Sxtb,
+    Sxth,
+    Sxtw,
+    Sxtx,
+    Lsl,
+}
+
+impl Extend {
+    fn from_bits(bits: u32) -> Self {
+        [
+            Extend::Uxtb,
+            Extend::Uxth,
+            Extend::Uxtw,
+            Extend::Uxtx,
+            Extend::Sxtb,
+            Extend::Sxth,
+            Extend::Sxtw,
+            Extend::Sxtx,
+        ][(bits & 7) as usize]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexMode {
+    Offset,
+    PreIndex,
+    PostIndex,
+}
+
+/// A memory operand: `[base, #offset]`, `[base, index, extend #amount]`, `[base, #offset]!`
+/// or `[base], #offset`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mem {
+    pub base: Reg,
+    pub offset: i64,
+    pub index: Option<(Reg, Extend, u8)>,
+    pub mode: IndexMode,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    None,
+    Reg(Reg),
+    Imm(u64),
+    /// The bitmask immediate of a logical instruction, always shown in hex.
+    Mask(u64),
+    /// An immediate shifted left, as in `#0x1234, lsl #16`.
+    ShiftedImm(u64, u8),
+    Shifted(Reg, Shift, u8),
+    Extended(Reg, Extend, u8),
+    /// A pc-relative target, already resolved.
+    Label(u64),
+    Mem(Mem),
+    Cond(Cond),
+    SysReg(u16),
+    /// The domain and access types of a `dmb` or `dsb`.
+    Barrier(u8),
+    /// The 8-bit floating-point immediate of `fmov`, see [`expand_float`].
+    FloatImm(u8),
+    /// The `#0.0` an `fcmp` compares with.
+    FloatZero,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Opcode {
+    Unknown,
+    Udf,
+    // pc-relative addressing and arithmetic
+    Adr,
+    Adrp,
+    Add,
+    Adds,
+    Sub,
+    Subs,
+    Cmp,
+    Cmn,
+    Neg,
+    Negs,
+    Adc,
+    Sbc,
+    // moves and logical operations
+    Mov,
+    Movz,
+    Movn,
+    Movk,
+    And,
+    Ands,
+    Orr,
+    Eor,
+    Bic,
+    Bics,
+    Orn,
+    Eon,
+    Mvn,
+    Tst,
+    // shifts and bitfields
+    Lsl,
+    Lsr,
+    Asr,
+    Ror,
+    Ubfx,
+    Sbfx,
+    Ubfiz,
+    Sbfiz,
+    Bfi,
+    Bfxil,
+    Uxtb,
+    Uxth,
+    Sxtb,
+    Sxth,
+    Sxtw,
+    Extr,
+    // conditional operations
+    Csel,
+    Csinc,
+    Csinv,
+    Csneg,
+    Cset,
+    Csetm,
+    Cinc,
+    Cneg,
+    Ccmp,
+    Ccmn,
+    // multiplication and division
+    Madd,
+    Msub,
+    Mul,
+    Mneg,
+    Smaddl,
+    Umaddl,
+    Smull,
+    Umull,
+    Smulh,
+    Umulh,
+    Udiv,
+    Sdiv,
+    Rbit,
+    Rev,
+    Rev16,
+    Rev32,
+    Clz,
+    Cls,
+    // branches
+    B,
+    Bl,
+    BCond,
+    Cbz,
+    Cbnz,
+    Tbz,
+    Tbnz,
+    Br,
+    Blr,
+    Ret,
+    Retaa,
+    Retab,
+    // system
+    Nop,
+    Hint,
+    Paciasp,
+    Autiasp,
+    Pacibsp,
+    Autibsp,
+    Bti,
+    Svc,
+    Brk,
+    Hlt,
+    Dmb,
+    Dsb,
+    Isb,
+    Mrs,
+    Msr,
+    // loads and stores
+    Ldr,
+    Ldrb,
+    Ldrh,
+    Ldrsb,
+    Ldrsh,
+    Ldrsw,
+    Str,
+    Strb,
+    Strh,
+    Ldur,
+    Ldurb,
+    Ldurh,
+    Ldursb,
+    Ldursh,
+    Ldursw,
+    Stur,
+    Sturb,
+    Sturh,
+    Ldp,
+    Ldpsw,
+    Stp,
+    Ldnp,
+    Stnp,
+    // exclusive and acquire-release accesses
+    Ldxr,
+    Ldxrb,
+    Ldxrh,
+    Ldaxr,
+    Ldaxrb,
+    Ldaxrh,
+    Stxr,
+    Stxrb,
+    Stxrh,
+    Stlxr,
+    Stlxrb,
+    Stlxrh,
+    Ldar,
+    Ldarb,
+    Ldarh,
+    Stlr,
+    Stlrb,
+    Stlrh,
+    // scalar floating-point
+    Fmov,
+    Fabs,
+    Fneg,
+    Fsqrt,
+    Fcvt,
+    Frintn,
+    Frintp,
+    Frintm,
+    Frintz,
+    Frinta,
+    Frintx,
+    Frinti,
+    Fmul,
+    Fdiv,
+    Fadd,
+    Fsub,
+    Fmax,
+    Fmin,
+    Fmaxnm,
+    Fminnm,
+    Fnmul,
+    Fmadd,
+    Fmsub,
+    Fnmadd,
+    Fnmsub,
+    Fcmp,
+    Fcmpe,
+    Fcsel,
+    Fcvtns,
+    Fcvtnu,
+    Fcvtps,
+    Fcvtpu,
+    Fcvtms,
+    Fcvtmu,
+    Fcvtzs,
+    Fcvtzu,
+    Fcvtas,
+    Fcvtau,
+    Scvtf,
+    Ucvtf,
+}
+
+impl Opcode {
+    pub fn name(self) -> String {
+        format!("{:?}", self).to_lowercase()
+    }
+
+    /// Returns `true` for the instructions writing memory rather than their first operand.
+    pub fn is_store(self) -> bool {
+        matches!(
+            self,
+            Opcode::Str
+                | Opcode::Strb
+                | Opcode::Strh
+                | Opcode::Stur
+                | Opcode::Sturb
+                | Opcode::Sturh
+                | Opcode::Stp
+                | Opcode::Stnp
+                | Opcode::Stlr
+                | Opcode::Stlrb
+                | Opcode::Stlrh
+        )
+    }
+
+    /// Returns `true` for the exclusive stores, which write their status to their first
+    /// operand.
+    pub fn is_exclusive_store(self) -> bool {
+        use Opcode::*;
+        matches!(self, Stxr | Stxrb | Stxrh | Stlxr | Stlxrb | Stlxrh)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u64,
+    pub word: u32,
+    pub opcode: Opcode,
+    pub operands: [Operand; 4],
+}
+
+impl Instruction {
+    /// Every A64 instruction is four bytes long.
+    pub const LEN: usize = 4;
+
+    fn new(address: u64, word: u32, opcode: Opcode, operands: &[Operand]) -> Self {
+        let mut all = [Operand::None; 4];
+        all[..operands.len()].copy_from_slice(operands);
+        Self {
+            address,
+            word,
+            opcode,
+            operands: all,
+        }
+    }
+
+    /// Returns the target of a direct branch or call.
+    pub fn branch_target(&self) -> Option<u64> {
+        match self.opcode {
+            Opcode::B
+            | Opcode::Bl
+            | Opcode::BCond
+            | Opcode::Cbz
+            | Opcode::Cbnz
+            | Opcode::Tbz
+            | Opcode::Tbnz => self.operands.iter().find_map(|operand| match operand {
+                Operand::Label(target) => Some(*target),
+                _ => None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the address computed or loaded from by `adr`, `adrp` and literal loads.
+    pub fn label(&self) -> Option<u64> {
+        if self.branch_target().is_some() {
+            return None;
+        }
+        self.operands.iter().find_map(|operand| match operand {
+            Operand::Label(target) => Some(*target),
+            _ => None,
+        })
+    }
+
+    /// Returns the general purpose registers written by this instruction, besides a
+    /// written-back base register.
+    pub fn destinations(&self) -> Vec<Reg> {
+        use Opcode::*;
+        match self.opcode {
+            Unknown | Udf | Cmp | Cmn | Tst | Ccmp | Ccmn | B | Bl | BCond | Cbz | Cbnz | Tbz
+            | Tbnz | Br | Blr | Ret | Retaa | Retab | Nop | Hint | Paciasp | Autiasp | Pacibsp
+            | Autibsp | Bti | Svc | Brk | Hlt | Dmb | Dsb | Isb | Msr | Fcmp | Fcmpe => Vec::new(),
+            _ if self.opcode.is_store() => Vec::new(),
+            Ldp | Ldpsw | Ldnp => self.operands[..2]
+                .iter()
+                .filter_map(|operand| match operand {
+                    Operand::Reg(reg) if reg.is_general() => Some(*reg),
+                    _ => None,
+                })
+                .collect(),
+            _ => match self.operands[0] {
+                Operand::Reg(reg) if reg.is_general() && !reg.is_zero() => vec![reg],
+                _ => Vec::new(),
+            },
+        }
+    }
+}
+
+fn write_imm(f: &mut Formatter<'_>, value: i64) -> Result {
+    if value < 0 {
+        f.write_fmt(format_args!("#-0x{:x}", value.unsigned_abs()))
+    } else if value < 10 {
+        f.write_fmt(format_args!("#{}", value))
+    } else {
+        f.write_fmt(format_args!("#0x{:x}", value))
+    }
+}
+
+fn shift_name(shift: Shift) -> &'static str {
+    match shift {
+        Shift::Lsl => "lsl",
+        Shift::Lsr => "lsr",
+        Shift::Asr => "asr",
+        Shift::Ror => "ror",
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Operand::None => Ok(()),
+            Operand::Reg(reg) => reg.fmt(f),
+            Operand::Imm(value) => write_imm(f, *value as i64),
+            Operand::Mask(value) => f.write_fmt(format_args!("#0x{:x}", value)),
+            Operand::ShiftedImm(value, 0) => write_imm(f, *value as i64),
+            Operand::ShiftedImm(value, shift) => {
+                write_imm(f, *value as i64)?;
+                f.write_fmt(format_args!(", lsl #{}", shift))
+            }
+            Operand::Shifted(reg, _, 0) => reg.fmt(f),
+            Operand::Shifted(reg, shift, amount) => {
+                f.write_fmt(format_args!("{}, {} #{}", reg, shift_name(*shift), amount))
+            }
+            Operand::Extended(reg, extend, amount) => {
+                let extend = format!("{:?}", extend).to_lowercase();
+                if *amount == 0 {
+                    f.write_fmt(format_args!("{}, {}", reg, extend))
+                } else {
+                    f.write_fmt(format_args!("{}, {} #{}", reg, extend, amount))
+                }
+            }
+            Operand::Label(target) => f.write_fmt(format_args!("0x{:x}", target)),
+            Operand::Mem(mem) => {
+                f.write_fmt(format_args!("[{}", mem.base))?;
+                if let Some((index, extend, amount)) = mem.index {
+                    f.write_fmt(format_args!(", {}", index))?;
+                    let extend = format!("{:?}", extend).to_lowercase();
+                    match (extend.as_str(), amount) {
+                        ("lsl", 0) => {}
+                        (_, 0) => f.write_fmt(format_args!(", {}", extend))?,
+                        _ => f.write_fmt(format_args!(", {} #{}", extend, amount))?,
+                    }
+                    return f.write_str("]");
+                }
+                match mem.mode {
+                    IndexMode::Offset if mem.offset == 0 => f.write_str("]"),
+                    IndexMode::Offset => {
+                        f.write_str(", ")?;
+                        write_imm(f, mem.offset)?;
+                        f.write_str("]")
+                    }
+                    IndexMode::PreIndex => {
+                        f.write_str(", ")?;
+                        write_imm(f, mem.offset)?;
+                        f.write_str("]!")
+                    }
+                    IndexMode::PostIndex => {
+                        f.write_str("], ")?;
+                        write_imm(f, mem.offset)
+                    }
+                }
+            }
+            Operand::Cond(cond) => cond.fmt(f),
+            Operand::SysReg(encoding) => match encoding {
+                0x5e82 => f.write_str("tpidr_el0"),
+                0x5e83 => f.write_str("tpidrro_el0"),
+                0x5a20 => f.write_str("fpcr"),
+                0x5a21 => f.write_str("fpsr"),
+                0x5f02 => f.write_str("cntvct_el0"),
+                _ => f.write_fmt(format_args!(
+                    "s{}_{}_c{}_c{}_{}",
+                    2 + (encoding >> 14),
+                    (encoding >> 11) & 7,
+                    (encoding >> 7) & 15,
+                    (encoding >> 3) & 15,
+                    encoding & 7
+                )),
+            },
+            Operand::Barrier(option) => match option {
+                15 => f.write_str("sy"),
+                14 => f.write_str("st"),
+                13 => f.write_str("ld"),
+                11 => f.write_str("ish"),
+                10 => f.write_str("ishst"),
+                9 => f.write_str("ishld"),
+                7 => f.write_str("nsh"),
+                3 => f.write_str("osh"),
+                _ => f.write_fmt(format_args!("#{}", option)),
+            },
+            Operand::FloatImm(imm8) => f.write_fmt(format_args!("#{:.8}", expand_float(*imm8))),
+            Operand::FloatZero => f.write_str("#0.0"),
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.opcode {
+            Opcode::Unknown => return f.write_fmt(format_args!(".inst 0x{:08x}", self.word)),
+            Opcode::BCond => {
+                return f.write_fmt(format_args!("b.{} {}", self.operands[0], self.operands[1]))
+            }
+            // the branch types a landing pad accepts, in bits 7:6 of the hint
+            Opcode::Bti => {
+                let targets = ["", " c", " j", " jc"][bits(self.word, 7, 6) as usize];
+                return f.write_fmt(format_args!("bti{targets}"));
+            }
+            // the immediate of a 32-bit `mov` is shown signed at that width, like `#-1`
+            Opcode::Mov => {
+                if let [Operand::Reg(rd), Operand::Imm(value), ..] = self.operands {
+                    if rd.class == RegClass::W {
+                        f.write_fmt(format_args!("mov {rd}, "))?;
+                        return write_imm(f, i64::from(value as u32 as i32));
+                    }
+                }
+            }
+            _ => {}
+        }
+        f.write_str(&self.opcode.name())?;
+        for (i, operand) in self
+            .operands
+            .iter()
+            .take_while(|operand| **operand != Operand::None)
+            .enumerate()
+        {
+            f.write_str(if i == 0 { " " } else { ", " })?;
+            operand.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn bits(word: u32, hi: u32, lo: u32) -> u32 {
+    (word >> lo) & ((1 << (hi - lo + 1)) - 1)
+}
+
+fn bit(word: u32, n: u32) -> bool {
+    word & (1 << n) != 0
+}
+
+/// Sign-extends the lowest `width` bits of `value`.
+fn sext(value: u32, width: u32) -> i64 {
+    let shift = 64 - width;
+    ((u64::from(value) << shift) as i64) >> shift
+}
+
+/// Expands the imm8 of `fmov`: a sign, a 3-bit exponent from -3 to 4 and a 4-bit fraction.
+pub fn expand_float(imm8: u8) -> f64 {
+    let exponent = i32::from((imm8 >> 4) & 7 ^ 4) - 3;
+    let value = f64::from(16 + (imm8 & 15)) / 16.0 * 2f64.powi(exponent);
+    if imm8 & 0x80 != 0 {
+        -value
+    } else {
+        value
+    }
+}
+
+/// Expands the N:immr:imms bitmask immediate of the logical instructions.
+fn decode_bit_mask(n: u32, imms: u32, immr: u32, sf: bool) -> Option<u64> {
+    let combined = (n << 6) | (!imms & 0x3f);
+    if combined == 0 {
+        return None;
+    }
+    let len = 31 - combined.leading_zeros();
+    if len < 1 || (!sf && n == 1) {
+        return None;
+    }
+    let levels = (1u32 << len) - 1;
+    let s = imms & levels;
+    let r = immr & levels;
+    if s == levels {
+        return None;
+    }
+    let esize = 1u32 << len;
+    let welem = if s + 1 == 64 {
+        u64::MAX
+    } else {
+        (1u64 << (s + 1)) - 1
+    };
+    let emask = if esize == 64 {
+        u64::MAX
+    } else {
+        (1u64 << esize) - 1
+    };
+    let elem = ((welem >> r) | (welem << ((esize - r) % esize))) & emask;
+    let mut value = 0u64;
+    let mut i = 0;
+    while i < 64 {
+        value |= elem << i;
+        i += esize;
+    }
+    Some(if sf { value } else { value & 0xffff_ffff })
+}
+
+/// Decodes the instruction `word` located at `address`.
+pub fn decode(word: u32, address: u64) -> Instruction {
+    let unknown = Instruction::new(address, word, Opcode::Unknown, &[]);
+    let decoded = if word >> 16 == 0 {
+        Some(Instruction::new(
+            address,
+            word,
+            Opcode::Udf,
+            &[Operand::Imm(u64::from(word & 0xffff))],
+        ))
+    } else {
+        match bits(word, 28, 25) {
+            0b1000 | 0b1001 => decode_data_immediate(word, address),
+            0b1010 | 0b1011 => decode_branch_system(word, address),
+            0b0100 | 0b0110 | 0b1100 | 0b1110 => decode_load_store(word, address),
+            0b0101 | 0b1101 => decode_data_register(word, address),
+            0b0111 | 0b1111 => decode_float(word, address),
+            _ => None,
+        }
+    };
+    decoded.unwrap_or(unknown)
+}
+
+fn decode_data_immediate(word: u32, address: u64) -> Option<Instruction> {
+    let sf = bit(word, 31);
+    let rd = bits(word, 4, 0);
+    let rn = bits(word, 9, 5);
+    let new =
+        |opcode, operands: &[Operand]| Some(Instruction::new(address, word, opcode, operands));
+
+    match bits(word, 25, 23) {
+        0b000 | 0b001 => {
+            let imm = sext((bits(word, 23, 5) << 2) | bits(word, 30, 29), 21);
+            if bit(word, 31) {
+                let page = (address & !0xfff).wrapping_add((imm << 12) as u64);
+                new(
+                    Opcode::Adrp,
+                    &[Operand::Reg(Reg::x(rd)), Operand::Label(page)],
+                )
+            } else {
+                let target = address.wrapping_add(imm as u64);
+                new(
+                    Opcode::Adr,
+                    &[Operand::Reg(Reg::x(rd)), Operand::Label(target)],
+                )
+            }
+        }
+        0b010 => {
+            let sub = bit(word, 30);
+            let set_flags = bit(word, 29);
+            let imm12 = u64::from(bits(word, 21, 10));
+            let shift = if bit(word, 22) { 12 } else { 0 };
+            let imm = Operand::ShiftedImm(imm12, shift);
+            let rn = Reg::gp_or_sp(sf, rn);
+            let rd = if set_flags {
+                Reg::gp(sf, rd)
+            } else {
+                Reg::gp_or_sp(sf, rd)
+            };
+            if set_flags && rd.is_zero() {
+                let opcode = if sub { Opcode::Cmp } else { Opcode::Cmn };
+                return new(opcode, &[Operand::Reg(rn), imm]);
+            }
+            if !sub && !set_flags && imm12 == 0 && shift == 0 && (rd.sp || rn.sp) {
+                return new(Opcode::Mov, &[Operand::Reg(rd), Operand::Reg(rn)]);
+            }
+            let opcode = match (sub, set_flags) {
+                (false, false) => Opcode::Add,
+                (false, true) => Opcode::Adds,
+                (true, false) => Opcode::Sub,
+                (true, true) => Opcode::Subs,
+            };
+            new(opcode, &[Operand::Reg(rd), Operand::Reg(rn), imm])
+        }
+        0b100 => {
+            let imm = decode_bit_mask(
+                bits(word, 22, 22),
+                bits(word, 15, 10),
+                bits(word, 21, 16),
+                sf,
+            )?;
+            let opc = bits(word, 30, 29);
+            let rd = if opc == 0b11 {
+                Reg::gp(sf, rd)
+            } else {
+                Reg::gp_or_sp(sf, rd)
+            };
+            let rn = Reg::gp(sf, rn);
+            match opc {
+                0b01 if rn.is_zero() => new(Opcode::Mov, &[Operand::Reg(rd), Operand::Mask(imm)]),
+                0b11 if rd.is_zero() => new(Opcode::Tst, &[Operand::Reg(rn), Operand::Mask(imm)]),
+                _ => {
+                    let opcode =
+                        [Opcode::And, Opcode::Orr, Opcode::Eor, Opcode::Ands][opc as usize];
+                    new(
+                        opcode,
+                        &[Operand::Reg(rd), Operand::Reg(rn), Operand::Mask(imm)],
+                    )
+                }
+            }
+        }
+        0b101 => {
+            let hw = bits(word, 22, 21);
+            if !sf && hw >= 2 {
+                return None;
+            }
+            let imm16 = u64::from(bits(word, 20, 5));
+            let shift = (hw * 16) as u8;
+            let rd = Reg::gp(sf, rd);
+            let mask = if sf { u64::MAX } else { 0xffff_ffff };
+            match bits(word, 30, 29) {
+                0b00 if !(imm16 == 0 && hw != 0) && (sf || imm16 != 0xffff) => {
+                    let value = !(imm16 << shift) & mask;
+                    new(Opcode::Mov, &[Operand::Reg(rd), Operand::Imm(value)])
+                }
+                0b00 => new(
+                    Opcode::Movn,
+                    &[Operand::Reg(rd), Operand::ShiftedImm(imm16, shift)],
+                ),
+                0b10 if !(imm16 == 0 && hw != 0) => new(
+                    Opcode::Mov,
+                    &[Operand::Reg(rd), Operand::Imm(imm16 << shift)],
+                ),
+                0b10 => new(
+                    Opcode::Movz,
+                    &[Operand::Reg(rd), Operand::ShiftedImm(imm16, shift)],
+                ),
+                0b11 => new(
+                    Opcode::Movk,
+                    &[Operand::Reg(rd), Operand::ShiftedImm(imm16, shift)],
+                ),
+                _ => None,
+            }
+        }
+        0b110 => decode_bitfield(word, address),
+        0b111 => {
+            if bits(word, 30, 29) != 0 || bit(word, 21) || bit(word, 22) != sf {
+                return None;
+            }
+            let rm = bits(word, 20, 16);
+            let lsb = u64::from(bits(word, 15, 10));
+            let (rd, rn_reg, rm_reg) = (Reg::gp(sf, rd), Reg::gp(sf, rn), Reg::gp(sf, rm));
+            if rn == rm {
+                new(
+                    Opcode::Ror,
+                    &[Operand::Reg(rd), Operand::Reg(rn_reg), Operand::Imm(lsb)],
+                )
+            } else {
+                new(
+                    Opcode::Extr,
+                    &[
+                        Operand::Reg(rd),
+                        Operand::Reg(rn_reg),
+                        Operand::Reg(rm_reg),
+                        Operand::Imm(lsb),
+                    ],
+                )
+            }
+        }
+        _ => None,
+    }
+}
+
+fn decode_bitfield(word: u32, address: u64) -> Option<Instruction> {
+    let sf = bit(word, 31);
+    if bit(word, 22) != sf {
+        return None;
+    }
+    let size = if sf { 64 } else { 32 };
+    let immr = bits(word, 21, 16);
+    let imms = bits(word, 15, 10);
+    let rd = Operand::Reg(Reg::gp(sf, bits(word, 4, 0)));
+    let rn_num = bits(word, 9, 5);
+    let rn = Operand::Reg(Reg::gp(sf, rn_num));
+    let imm = |value: u32| Operand::Imm(u64::from(value));
+    let new =
+        |opcode, operands: &[Operand]| Some(Instruction::new(address, word, opcode, operands));
+
+    match bits(word, 30, 29) {
+        // SBFM
+        0b00 => {
+            if imms == size - 1 {
+                new(Opcode::Asr, &[rd, rn, imm(immr)])
+            } else if immr == 0 && imms == 7 {
+                new(Opcode::Sxtb, &[rd, Operand::Reg(Reg::gp(false, rn_num))])
+            } else if immr == 0 && imms == 15 {
+                new(Opcode::Sxth, &[rd, Operand::Reg(Reg::gp(false, rn_num))])
+            } else if sf && immr == 0 && imms == 31 {
+                new(Opcode::Sxtw, &[rd, Operand::Reg(Reg::gp(false, rn_num))])
+            } else if imms < immr {
+                new(Opcode::Sbfiz, &[rd, rn, imm(size - immr), imm(imms + 1)])
+            } else {
+                new(Opcode::Sbfx, &[rd, rn, imm(immr), imm(imms - immr + 1)])
+            }
+        }
+        // BFM
+        0b01 => {
+            if imms < immr {
+                new(Opcode::Bfi, &[rd, rn, imm(size - immr), imm(imms + 1)])
+            } else {
+                new(Opcode::Bfxil, &[rd, rn, imm(immr), imm(imms - immr + 1)])
+            }
+        }
+        // UBFM
+        0b10 => {
+            if imms != size - 1 && imms + 1 == immr {
+                new(Opcode::Lsl, &[rd, rn, imm(size - 1 - imms)])
+            } else if imms == size - 1 {
+                new(Opcode::Lsr, &[rd, rn, imm(immr)])
+            } else if !sf && immr == 0 && imms == 7 {
+                new(Opcode::Uxtb, &[rd, rn])
+            } else if !sf && immr == 0 && imms == 15 {
+                new(Opcode::Uxth, &[rd, rn])
+            } else if imms < immr {
+                new(Opcode::Ubfiz, &[rd, rn, imm(size - immr), imm(imms + 1)])
+            } else {
+                new(Opcode::Ubfx, &[rd, rn, imm(immr), imm(imms - immr + 1)])
+            }
+        }
+        _ => None,
+    }
+}
+
+fn decode_branch_system(word: u32, address: u64) -> Option<Instruction> {
+    let new =
+        |opcode, operands: &[Operand]| Some(Instruction::new(address, word, opcode, operands));
+    let rt = bits(word, 4, 0);
+
+    if bits(word, 30, 26) == 0b00101 {
+        let target = address.wrapping_add((sext(bits(word, 25, 0), 26) << 2) as u64);
+        let opcode = if bit(word, 31) { Opcode::Bl } else { Opcode::B };
+        return new(opcode, &[Operand::Label(target)]);
+    }
+    if bits(word, 31, 24) == 0b0101_0100 && !bit(word, 4) {
+        let target = address.wrapping_add((sext(bits(word, 23, 5), 19) << 2) as u64);
+        return new(
+            Opcode::BCond,
+            &[
+                Operand::Cond(Cond::from_bits(bits(word, 3, 0))),
+                Operand::Label(target),
+            ],
+        );
+    }
+    if bits(word, 30, 25) == 0b011010 {
+        let target = address.wrapping_add((sext(bits(word, 23, 5), 19) << 2) as u64);
+        let opcode = if bit(word, 24) {
+            Opcode::Cbnz
+        } else {
+            Opcode::Cbz
+        };
+        return new(
+            opcode,
+            &[
+                Operand::Reg(Reg::gp(bit(word, 31), rt)),
+                Operand::Label(target),
+            ],
+        );
+    }
+    if bits(word, 30, 25) == 0b011011 {
+        let target = address.wrapping_add((sext(bits(word, 18, 5), 14) << 2) as u64);
+        let bit_number = (bits(word, 31, 31) << 5) | bits(word, 23, 19);
+        let opcode = if bit(word, 24) {
+            Opcode::Tbnz
+        } else {
+            Opcode::Tbz
+        };
+        return new(
+            opcode,
+            &[
+                Operand::Reg(Reg::gp(bit(word, 31), rt)),
+                Operand::Imm(u64::from(bit_number)),
+                Operand::Label(target),
+            ],
+        );
+    }
+    if bits(word, 31, 25) == 0b1101011 {
+        if bits(word, 20, 16) != 0b11111 {
+            return None;
+        }
+        let rn = Reg::x(bits(word, 9, 5));
+        return match (bits(word, 24, 21), bits(word, 15, 10)) {
+            (0b0000, _) => new(Opcode::Br, &[Operand::Reg(rn)]),
+            (0b0001, _) => new(Opcode::Blr, &[Operand::Reg(rn)]),
+            (0b0010, 0b000010) => new(Opcode::Retaa, &[]),
+            (0b0010, 0b000011) => new(Opcode::Retab, &[]),
+            (0b0010, _) if rn.num == 30 => new(Opcode::Ret, &[]),
+            (0b0010, _) => new(Opcode::Ret, &[Operand::Reg(rn)]),
+            _ => None,
+        };
+    }
+    if bits(word, 31, 24) == 0b1101_0100 {
+        let imm = Operand::Imm(u64::from(bits(word, 20, 5)));
+        return match (bits(word, 23, 21), bits(word, 4, 0)) {
+            (0b000, 0b00001) => new(Opcode::Svc, &[imm]),
+            (0b001, 0b00000) => new(Opcode::Brk, &[imm]),
+            (0b010, 0b00000) => new(Opcode::Hlt, &[imm]),
+            _ => None,
+        };
+    }
+    if word & 0xffff_f01f == 0xd503_201f {
+        return match bits(word, 11, 5) {
+            0 => new(Opcode::Nop, &[]),
+            25 => new(Opcode::Paciasp, &[]),
+            27 => new(Opcode::Pacibsp, &[]),
+            29 => new(Opcode::Autiasp, &[]),
+            31 => new(Opcode::Autibsp, &[]),
+            32 | 34 | 36 | 38 => new(Opcode::Bti, &[]),
+            imm => new(Opcode::Hint, &[Operand::Imm(u64::from(imm))]),
+        };
+    }
+    if word & 0xffff_f01f == 0xd503_301f {
+        let option = Operand::Barrier(bits(word, 11, 8) as u8);
+        return match bits(word, 7, 5) {
+            0b100 => new(Opcode::Dsb, &[option]),
+            0b101 => new(Opcode::Dmb, &[option]),
+            0b110 => new(Opcode::Isb, &[]),
+            _ => None,
+        };
+    }
+    if bits(word, 31, 20) == 0b1101_0101_0011 {
+        let sysreg = Operand::SysReg(bits(word, 19, 5) as u16);
+        return new(Opcode::Mrs, &[Operand::Reg(Reg::x(rt)), sysreg]);
+    }
+    if bits(word, 31, 20) == 0b1101_0101_0001 {
+        let sysreg = Operand::SysReg(bits(word, 19, 5) as u16);
+        return new(Opcode::Msr, &[sysreg, Operand::Reg(Reg::x(rt))]);
+    }
+    None
+}
+
+/// Returns the register class transferred by a SIMD&FP load or store.
+fn fp_class(size: u32, opc: u32) -> RegClass {
+    match (size, opc >> 1) {
+        (0b00, 1) => RegClass::Q,
+        (0b00, _) => RegClass::B,
+        (0b01, _) => RegClass::H,
+        (0b10, _) => RegClass::S,
+        _ => RegClass::D,
+    }
+}
+
+fn decode_load_store(word: u32, address: u64) -> Option<Instruction> {
+    let new =
+        |opcode, operands: &[Operand]| Some(Instruction::new(address, word, opcode, operands));
+    let vector = bit(word, 26);
+    let rt = bits(word, 4, 0);
+    let rn = Reg::gp_or_sp(true, bits(word, 9, 5));
+
+    if bits(word, 29, 24) == 0b001000 {
+        return decode_exclusive(word, address);
+    }
+
+    // load register (literal)
+    if bits(word, 29, 27) == 0b011 && bits(word, 25, 24) == 0b00 {
+        let target = address.wrapping_add((sext(bits(word, 23, 5), 19) << 2) as u64);
+        let opc = bits(word, 31, 30);
+        let (opcode, reg) = match (vector, opc) {
+            (false, 0b00) => (Opcode::Ldr, Reg::gp(false, rt)),
+            (false, 0b01) => (Opcode::Ldr, Reg::x(rt)),
+            (false, 0b10) => (Opcode::Ldrsw, Reg::x(rt)),
+            (true, 0b00) => (Opcode::Ldr, Reg::fp(RegClass::S, rt)),
+            (true, 0b01) => (Opcode::Ldr, Reg::fp(RegClass::D, rt)),
+            (true, 0b10) => (Opcode::Ldr, Reg::fp(RegClass::Q, rt)),
+            _ => return None,
+        };
+        return new(opcode, &[Operand::Reg(reg), Operand::Label(target)]);
+    }
+
+    // load/store pair
+    if bits(word, 29, 27) == 0b101 {
+        let load = bit(word, 22);
+        let opc = bits(word, 31, 30);
+        let (class, scale, opcode) = match (vector, opc, load) {
+            (false, 0b00, _) => (RegClass::W, 4, None),
+            (false, 0b01, true) => (RegClass::X, 4, Some(Opcode::Ldpsw)),
+            (false, 0b10, _) => (RegClass::X, 8, None),
+            (true, 0b00, _) => (RegClass::S, 4, None),
+            (true, 0b01, _) => (RegClass::D, 8, None),
+            (true, 0b10, _) => (RegClass::Q, 16, None),
+            _ => return None,
+        };
+        let mode = match bits(word, 24, 23) {
+            0b00 | 0b10 => IndexMode::Offset,
+            0b01 => IndexMode::PostIndex,
+            _ => IndexMode::PreIndex,
+        };
+        let opcode = opcode.unwrap_or(match (bits(word, 24, 23), load) {
+            (0b00, true) => Opcode::Ldnp,
+            (0b00, false) => Opcode::Stnp,
+            (_, true) => Opcode::Ldp,
+            (_, false) => Opcode::Stp,
+        });
+        let reg = |num| {
+            Operand::Reg(Reg {
+                class,
+                num: num as u8,
+                sp: false,
+            })
+        };
+        let mem = Mem {
+            base: rn,
+            offset: sext(bits(word, 21, 15), 7) * scale,
+            index: None,
+            mode,
+        };
+        return new(
+            opcode,
+            &[reg(rt), reg(bits(word, 14, 10)), Operand::Mem(mem)],
+        );
+    }
+
+    if bits(word, 29, 27) != 0b111 {
+        return None;
+    }
+    let size = bits(word, 31, 30);
+    let opc = bits(word, 23, 22);
+    let (mut opcode, reg) = if vector {
+        let class = fp_class(size, opc);
+        let opcode = if opc & 1 == 1 {
+            Opcode::Ldr
+        } else {
+            Opcode::Str
+        };
+        (opcode, Reg::fp(class, rt))
+    } else {
+        match (size, opc) {
+            (0b00, 0b00) => (Opcode::Strb, Reg::gp(false, rt)),
+            (0b00, 0b01) => (Opcode::Ldrb, Reg::gp(false, rt)),
+            (0b00, 0b10) => (Opcode::Ldrsb, Reg::x(rt)),
+            (0b00, 0b11) => (Opcode::Ldrsb, Reg::gp(false, rt)),
+            (0b01, 0b00) => (Opcode::Strh, Reg::gp(false, rt)),
+            (0b01, 0b01) => (Opcode::Ldrh, Reg::gp(false, rt)),
+            (0b01, 0b10) => (Opcode::Ldrsh, Reg::x(rt)),
+            (0b01, 0b11) => (Opcode::Ldrsh, Reg::gp(false, rt)),
+            (0b10, 0b00) => (Opcode::Str, Reg::gp(false, rt)),
+            (0b10, 0b01) => (Opcode::Ldr, Reg::gp(false, rt)),
+            (0b10, 0b10) => (Opcode::Ldrsw, Reg::x(rt)),
+            (0b11, 0b00) => (Opcode::Str, Reg::x(rt)),
+            (0b11, 0b01) => (Opcode::Ldr, Reg::x(rt)),
+            _ => return None,
+        }
+    };
+    let scale_log = if vector && size == 0 && opc >> 1 == 1 {
+        4
+    } else {
+        size
+    };
+
+    let mem = if bits(word, 25, 24) == 0b01 {
+        Mem {
+            base: rn,
+            offset: i64::from(bits(word, 21, 10)) << scale_log,
+            index: None,
+            mode: IndexMode::Offset,
+        }
+    } else if !bit(word, 21) {
+        let offset = sext(bits(word, 20, 12), 9);
+        let mode = match bits(word, 11, 10) {
+            0b00 => {
+                opcode = unscaled(opcode)?;
+                IndexMode::Offset
+            }
+            0b01 => IndexMode::PostIndex,
+            0b11 => IndexMode::PreIndex,
+            _ => return None,
+        };
+        Mem {
+            base: rn,
+            offset,
+            index: None,
+            mode,
+        }
+    } else if bits(word, 11, 10) == 0b10 {
+        let option = bits(word, 15, 13);
+        if option & 0b010 == 0 {
+            return None;
+        }
+        let index = Reg::gp(option & 1 == 1, bits(word, 20, 16));
+        let extend = if option == 0b011 {
+            Extend::Lsl
+        } else {
+            Extend::from_bits(option)
+        };
+        let amount = if bit(word, 12) { scale_log as u8 } else { 0 };
+        Mem {
+            base: rn,
+            offset: 0,
+            index: Some((index, extend, amount)),
+            mode: IndexMode::Offset,
+        }
+    } else {
+        return None;
+    };
+
+    new(opcode, &[Operand::Reg(reg), Operand::Mem(mem)])
+}
+
+/// Decodes the exclusive loads and stores and the load-acquire and store-release
+/// registers, leaving out the pairs and the LORegion forms.
+fn decode_exclusive(word: u32, address: u64) -> Option<Instruction> {
+    use Opcode::*;
+    let size = bits(word, 31, 30);
+    let rt = Operand::Reg(Reg::gp(size == 3, bits(word, 4, 0)));
+    let rs = Operand::Reg(Reg::gp(false, bits(word, 20, 16)));
+    let mem = Operand::Mem(Mem {
+        base: Reg::gp_or_sp(true, bits(word, 9, 5)),
+        offset: 0,
+        index: None,
+        mode: IndexMode::Offset,
+    });
+    // the byte, halfword and register forms of each
+    let pick = |opcodes: [Opcode; 3]| opcodes[size.min(2) as usize];
+    let (o2, load, o1, o0) = (bit(word, 23), bit(word, 22), bit(word, 21), bit(word, 15));
+    let opcode = match (o2, load, o1, o0) {
+        (false, true, false, false) => pick([Ldxrb, Ldxrh, Ldxr]),
+        (false, true, false, true) => pick([Ldaxrb, Ldaxrh, Ldaxr]),
+        (false, false, false, false) => pick([Stxrb, Stxrh, Stxr]),
+        (false, false, false, true) => pick([Stlxrb, Stlxrh, Stlxr]),
+        (true, true, false, true) => pick([Ldarb, Ldarh, Ldar]),
+        (true, false, false, true) => pick([Stlrb, Stlrh, Stlr]),
+        _ => return None,
+    };
+    let operands: &[Operand] = if opcode.is_exclusive_store() {
+        &[rs, rt, mem]
+    } else {
+        &[rt, mem]
+    };
+    Some(Instruction::new(address, word, opcode, operands))
+}
+
+/// Decodes the scalar floating-point data processing and the conversions between
+/// floating-point and integer registers. Half precision and the fixed-point conversions
+/// are left out.
+fn decode_float(word: u32, address: u64) -> Option<Instruction> {
+    use Opcode::*;
+    let new =
+        |opcode, operands: &[Operand]| Some(Instruction::new(address, word, opcode, operands));
+    // the M and S bits, and the bits telling the scalar encodings from the vector ones
+    if bit(word, 30) || bit(word, 29) || bits(word, 28, 24) & 0b11110 != 0b11110 {
+        return None;
+    }
+    let class = match bits(word, 23, 22) {
+        0b00 => RegClass::S,
+        0b01 => RegClass::D,
+        _ => return None,
+    };
+    let reg = |num| Operand::Reg(Reg::fp(class, num));
+    let (rd, rn, rm) = (
+        reg(bits(word, 4, 0)),
+        reg(bits(word, 9, 5)),
+        reg(bits(word, 20, 16)),
+    );
+
+    // data-processing (3 source)
+    if bit(word, 24) {
+        let opcode = match (bit(word, 21), bit(word, 15)) {
+            (false, false) => Fmadd,
+            (false, true) => Fmsub,
+            (true, false) => Fnmadd,
+            (true, true) => Fnmsub,
+        };
+        return new(opcode, &[rd, rn, rm, reg(bits(word, 14, 10))]);
+    }
+    if bit(word, 31) && bits(word, 15, 10) != 0 || !bit(word, 21) {
+        return None;
+    }
+
+    match bits(word, 11, 10) {
+        // conversion between floating-point and integer
+        0b00 if bits(word, 15, 10) == 0 => {
+            let sf = bit(word, 31);
+            let gp = |num| Operand::Reg(Reg::gp(sf, num));
+            let (rd_num, rn_num) = (bits(word, 4, 0), bits(word, 9, 5));
+            let opcode = match (bits(word, 20, 19), bits(word, 18, 16)) {
+                (0b00, 0b000) => Fcvtns,
+                (0b00, 0b001) => Fcvtnu,
+                (0b01, 0b000) => Fcvtps,
+                (0b01, 0b001) => Fcvtpu,
+                (0b10, 0b000) => Fcvtms,
+                (0b10, 0b001) => Fcvtmu,
+                (0b11, 0b000) => Fcvtzs,
+                (0b11, 0b001) => Fcvtzu,
+                (0b00, 0b100) => Fcvtas,
+                (0b00, 0b101) => Fcvtau,
+                (0b00, 0b010) => return new(Scvtf, &[rd, gp(rn_num)]),
+                (0b00, 0b011) => return new(Ucvtf, &[rd, gp(rn_num)]),
+                // the bits move as they are, between registers of the same size
+                (0b00, 0b110) if sf == (class == RegClass::D) => {
+                    return new(Fmov, &[gp(rd_num), rn])
+                }
+                (0b00, 0b111) if sf == (class == RegClass::D) => {
+                    return new(Fmov, &[rd, gp(rn_num)])
+                }
+                _ => return None,
+            };
+            new(opcode, &[gp(rd_num), rn])
+        }
+        // data-processing (1 source)
+        0b00 if bits(word, 14, 10) == 0b10000 => {
+            let opcode = match bits(word, 20, 15) {
+                0b000000 => Fmov,
+                0b000001 => Fabs,
+                0b000010 => Fneg,
+                0b000011 => Fsqrt,
+                0b000100 if class == RegClass::D => {
+                    let rd = Operand::Reg(Reg::fp(RegClass::S, bits(word, 4, 0)));
+                    return new(Fcvt, &[rd, rn]);
+                }
+                0b000101 if class == RegClass::S => {
+                    let rd = Operand::Reg(Reg::fp(RegClass::D, bits(word, 4, 0)));
+                    return new(Fcvt, &[rd, rn]);
+                }
+                0b001000 => Frintn,
+                0b001001 => Frintp,
+                0b001010 => Frintm,
+                0b001011 => Frintz,
+                0b001100 => Frinta,
+                0b001110 => Frintx,
+                0b001111 => Frinti,
+                _ => return None,
+            };
+            new(opcode, &[rd, rn])
+        }
+        // compare
+        0b00 if bits(word, 15, 10) == 0b001000 && bits(word, 2, 0) == 0 => {
+            let opcode = if bit(word, 4) { Fcmpe } else { Fcmp };
+            let rm = if bit(word, 3) { Operand::FloatZero } else { rm };
+            new(opcode, &[rn, rm])
+        }
+        // immediate
+        0b00 if bits(word, 12, 10) == 0b100 && bits(word, 9, 5) == 0 => {
+            new(Fmov, &[rd, Operand::FloatImm(bits(word, 20, 13) as u8)])
+        }
+        // data-processing (2 source)
+        0b10 => {
+            let opcode = match bits(word, 15, 12) {
+                0b0000 => Fmul,
+                0b0001 => Fdiv,
+                0b0010 => Fadd,
+                0b0011 => Fsub,
+                0b0100 => Fmax,
+                0b0101 => Fmin,
+                0b0110 => Fmaxnm,
+                0b0111 => Fminnm,
+                0b1000 => Fnmul,
+                _ => return None,
+            };
+            new(opcode, &[rd, rn, rm])
+        }
+        // conditional select
+        0b11 => new(
+            Fcsel,
+            &[rd, rn, rm, Operand::Cond(Cond::from_bits(bits(word, 15, 12)))],
+        ),
+        _ => None,
+    }
+}
+
+/// Returns the unscaled-offset variant of a load or store, `ldur` for `ldr`.
+fn unscaled(opcode: Opcode) -> Option<Opcode> {
+    Some(match opcode {
+        Opcode::Ldr => Opcode::Ldur,
+        Opcode::Ldrb => Opcode::Ldurb,
+        Opcode::Ldrh => Opcode::Ldurh,
+        Opcode::Ldrsb => Opcode::Ldursb,
+        Opcode::Ldrsh => Opcode::Ldursh,
+        Opcode::Ldrsw => Opcode::Ldursw,
+        Opcode::Str => Opcode::Stur,
+        Opcode::Strb => Opcode::Sturb,
+        Opcode::Strh => Opcode::Sturh,
+        _ => return None,
+    })
+}
+
+fn decode_data_register(word: u32, address: u64) -> Option<Instruction> {
+    let new =
+        |opcode, operands: &[Operand]| Some(Instruction::new(address, word, opcode, operands));
+    let sf = bit(word, 31);
+    let rd = Reg::gp(sf, bits(word, 4, 0));
+    let rn = Reg::gp(sf, bits(word, 9, 5));
+    let rm = Reg::gp(sf, bits(word, 20, 16));
+    let shift = [Shift::Lsl, Shift::Lsr, Shift::Asr, Shift::Ror][bits(word, 23, 22) as usize];
+    let amount = bits(word, 15, 10) as u8;
+
+    if !bit(word, 28) {
+        if !sf && amount >= 32 {
+            return None;
+        }
+        let operand2 = Operand::Shifted(rm, shift, amount);
+        // logical (shifted register)
+        if !bit(word, 24) {
+            let opcode = match (bits(word, 30, 29), bit(word, 21)) {
+                (0b00, false) => Opcode::And,
+                (0b00, true) => Opcode::Bic,
+                (0b01, false) => Opcode::Orr,
+                (0b01, true) => Opcode::Orn,
+                (0b10, false) => Opcode::Eor,
+                (0b10, true) => Opcode::Eon,
+                (0b11, false) => Opcode::Ands,
+                _ => Opcode::Bics,
+            };
+            return match opcode {
+                Opcode::Orr if rn.is_zero() && amount == 0 => {
+                    new(Opcode::Mov, &[Operand::Reg(rd), Operand::Reg(rm)])
+                }
+                Opcode::Orn if rn.is_zero() => new(Opcode::Mvn, &[Operand::Reg(rd), operand2]),
+                Opcode::Ands if rd.is_zero() => new(Opcode::Tst, &[Operand::Reg(rn), operand2]),
+                _ => new(opcode, &[Operand::Reg(rd), Operand::Reg(rn), operand2]),
+            };
+        }
+
+        let sub = bit(word, 30);
+        let set_flags = bit(word, 29);
+        // add/sub (shifted register)
+        if !bit(word, 21) {
+            if shift == Shift::Ror {
+                return None;
+            }
+            if set_flags && rd.is_zero() {
+                let opcode = if sub { Opcode::Cmp } else { Opcode::Cmn };
+                return new(opcode, &[Operand::Reg(rn), operand2]);
+            }
+            if sub && rn.is_zero() {
+                let opcode = if set_flags { Opcode::Negs } else { Opcode::Neg };
+                return new(opcode, &[Operand::Reg(rd), operand2]);
+            }
+            let opcode = match (sub, set_flags) {
+                (false, false) => Opcode::Add,
+                (false, true) => Opcode::Adds,
+                (true, false) => Opcode::Sub,
+                (true, true) => Opcode::Subs,
+            };
+            return new(opcode, &[Operand::Reg(rd), Operand::Reg(rn), operand2]);
+        }
+
+        // add/sub (extended register)
+        if bits(word, 23, 22) != 0 {
+            return None;
+        }
+        let option = bits(word, 15, 13);
+        let imm3 = bits(word, 12, 10) as u8;
+        if imm3 > 4 {
+            return None;
+        }
+        let rd = if set_flags {
+            Reg::gp(sf, bits(word, 4, 0))
+        } else {
+            Reg::gp_or_sp(sf, bits(word, 4, 0))
+        };
+        let rn = Reg::gp_or_sp(sf, bits(word, 9, 5));
+        let rm = Reg::gp(sf && option & 0b011 == 0b011, bits(word, 20, 16));
+        let lsl_option = if sf { 0b011 } else { 0b010 };
+        let operand2 = if option == lsl_option && (rd.sp || rn.sp) {
+            Operand::Shifted(rm, Shift::Lsl, imm3)
+        } else {
+            Operand::Extended(rm, Extend::from_bits(option), imm3)
+        };
+        if set_flags && rd.is_zero() {
+            let opcode = if sub { Opcode::Cmp } else { Opcode::Cmn };
+            return new(opcode, &[Operand::Reg(rn), operand2]);
+        }
+        let opcode = match (sub, set_flags) {
+            (false, false) => Opcode::Add,
+            (false, true) => Opcode::Adds,
+            (true, false) => Opcode::Sub,
+            (true, true) => Opcode::Subs,
+        };
+        return new(opcode, &[Operand::Reg(rd), Operand::Reg(rn), operand2]);
+    }
+
+    // data-processing (3 source)
+    if bit(word, 24) {
+        let ra = Reg::gp(sf, bits(word, 14, 10));
+        let subtract = bit(word, 15);
+        let wide = |opcode| {
+            let (rn, rm) = (
+                Reg::gp(false, bits(word, 9, 5)),
+                Reg::gp(false, bits(word, 20, 16)),
+            );
+            (opcode, Operand::Reg(rn), Operand::Reg(rm))
+        };
+        let (opcode, rn, rm) = match (bits(word, 23, 21), subtract) {
+            (0b000, false) => (Opcode::Madd, Operand::Reg(rn), Operand::Reg(rm)),
+            (0b000, true) => (Opcode::Msub, Operand::Reg(rn), Operand::Reg(rm)),
+            (0b001, false) if sf => wide(Opcode::Smaddl),
+            (0b101, false) if sf => wide(Opcode::Umaddl),
+            (0b010, false) if sf => (Opcode::Smulh, Operand::Reg(rn), Operand::Reg(rm)),
+            (0b110, false) if sf => (Opcode::Umulh, Operand::Reg(rn), Operand::Reg(rm)),
+            _ => return None,
+        };
+        let ra_zero = bits(word, 14, 10) == 31;
+        return match opcode {
+            Opcode::Smulh | Opcode::Umulh => new(opcode, &[Operand::Reg(rd), rn, rm]),
+            _ if ra_zero => {
+                let alias = match opcode {
+                    Opcode::Madd => Opcode::Mul,
+                    Opcode::Msub => Opcode::Mneg,
+                    Opcode::Smaddl => Opcode::Smull,
+                    _ => Opcode::Umull,
+                };
+                new(alias, &[Operand::Reg(rd), rn, rm])
+            }
+            _ => new(opcode, &[Operand::Reg(rd), rn, rm, Operand::Reg(ra)]),
+        };
+    }
+
+    match bits(word, 24, 21) {
+        // add/subtract with carry
+        0b0000 if bits(word, 15, 10) == 0 && !bit(word, 29) => {
+            let opcode = if bit(word, 30) {
+                Opcode::Sbc
+            } else {
+                Opcode::Adc
+            };
+            new(
+                opcode,
+                &[Operand::Reg(rd), Operand::Reg(rn), Operand::Reg(rm)],
+            )
+        }
+        // conditional compare
+        0b0010 if bit(word, 29) && !bit(word, 10) && !bit(word, 4) => {
+            let opcode = if bit(word, 30) {
+                Opcode::Ccmp
+            } else {
+                Opcode::Ccmn
+            };
+            let operand2 = if bit(word, 11) {
+                Operand::Imm(u64::from(bits(word, 20, 16)))
+            } else {
+                Operand::Reg(rm)
+            };
+            new(
+                opcode,
+                &[
+                    Operand::Reg(rn),
+                    operand2,
+                    Operand::Imm(u64::from(bits(word, 3, 0))),
+                    Operand::Cond(Cond::from_bits(bits(word, 15, 12))),
+                ],
+            )
+        }
+        // conditional select
+        0b0100 if !bit(word, 29) && !bit(word, 11) => {
+            let cond = Cond::from_bits(bits(word, 15, 12));
+            let invertible = !matches!(cond, Cond::Al | Cond::Nv);
+            let same = bits(word, 9, 5) == bits(word, 20, 16);
+            match (bit(word, 30), bit(word, 10)) {
+                (false, false) => new(
+                    Opcode::Csel,
+                    &[
+                        Operand::Reg(rd),
+                        Operand::Reg(rn),
+                        Operand::Reg(rm),
+                        Operand::Cond(cond),
+                    ],
+                ),
+                (false, true) if invertible && same && rn.is_zero() => new(
+                    Opcode::Cset,
+                    &[Operand::Reg(rd), Operand::Cond(cond.invert())],
+                ),
+                (false, true) if invertible && same => new(
+                    Opcode::Cinc,
+                    &[
+                        Operand::Reg(rd),
+                        Operand::Reg(rn),
+                        Operand::Cond(cond.invert()),
+                    ],
+                ),
+                (false, true) => new(
+                    Opcode::Csinc,
+                    &[
+                        Operand::Reg(rd),
+                        Operand::Reg(rn),
+                        Operand::Reg(rm),
+                        Operand::Cond(cond),
+                    ],
+                ),
+                (true, false) if invertible && same && rn.is_zero() => new(
+                    Opcode::Csetm,
+                    &[Operand::Reg(rd), Operand::Cond(cond.invert())],
+                ),
+                (true, false) => new(
+                    Opcode::Csinv,
+                    &[
+                        Operand::Reg(rd),
+                        Operand::Reg(rn),
+                        Operand::Reg(rm),
+                        Operand::Cond(cond),
+                    ],
+                ),
+                (true, true) if invertible && same => new(
+                    Opcode::Cneg,
+                    &[
+                        Operand::Reg(rd),
+                        Operand::Reg(rn),
+                        Operand::Cond(cond.invert()),
+                    ],
+                ),
+                (true, true) => new(
+                    Opcode::Csneg,
+                    &[
+                        Operand::Reg(rd),
+                        Operand::Reg(rn),
+                        Operand::Reg(rm),
+                        Operand::Cond(cond),
+                    ],
+                ),
+            }
+        }
+        // data-processing (2 source) and (1 source)
+        0b0110 if !bit(word, 29) => {
+            if !bit(word, 30) {
+                let opcode = match bits(word, 15, 10) {
+                    0b000010 => Opcode::Udiv,
+                    0b000011 => Opcode::Sdiv,
+                    0b001000 => Opcode::Lsl,
+                    0b001001 => Opcode::Lsr,
+                    0b001010 => Opcode::Asr,
+                    0b001011 => Opcode::Ror,
+                    _ => return None,
+                };
+                return new(
+                    opcode,
+                    &[Operand::Reg(rd), Operand::Reg(rn), Operand::Reg(rm)],
+                );
+            }
+            if bits(word, 20, 16) != 0 {
+                return None;
+            }
+            let opcode = match (bits(word, 15, 10), sf) {
+                (0b000000, _) => Opcode::Rbit,
+                (0b000001, _) => Opcode::Rev16,
+                (0b000010, false) => Opcode::Rev,
+                (0b000010, true) => Opcode::Rev32,
+                (0b000011, true) => Opcode::Rev,
+                (0b000100, _) => Opcode::Clz,
+                (0b000101, _) => Opcode::Cls,
+                _ => return None,
+            };
+            new(opcode, &[Operand::Reg(rd), Operand::Reg(rn)])
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Disassembly of `llvm-mc -triple=aarch64 -mattr=+v8.3a,+bti` output by `llvm-objdump`,
+    /// with the PC-relative `adr` operands turned into the addresses we print.
+    const LLVM: [(u64, u32, &str); 212] = [
+        (0x0, 0x00000001, "udf #1"),
+        (0x4, 0x10000080, "adr x0, 0x14"),
+        (0x8, 0x90000001, "adrp x1, 0x0"),
+        (0xc, 0x91004020, "add x0, x1, #16"),
+        (0x10, 0x11400462, "add w2, w3, #1, lsl #12"),
+        (0x14, 0x8b020c20, "add x0, x1, x2, lsl #3"),
+        (0x18, 0x910023e0, "add x0, sp, #8"),
+        (0x1c, 0xb1001020, "adds x0, x1, #4"),
+        (0x20, 0xd10083ff, "sub sp, sp, #32"),
+        (0x24, 0xcb020020, "sub x0, x1, x2"),
+        (0x28, 0x6b420820, "subs w0, w1, w2, lsr #2"),
+        (0x2c, 0xf100141f, "cmp x0, #5"),
+        (0x30, 0x6b02003f, "cmp w1, w2"),
+        (0x34, 0xb100041f, "cmn x0, #1"),
+        (0x38, 0xcb0103e0, "neg x0, x1"),
+        (0x3c, 0x6b0103e0, "negs w0, w1"),
+        (0x40, 0x9a020020, "adc x0, x1, x2"),
+        (0x44, 0x5a020020, "sbc w0, w1, w2"),
+        (0x48, 0xaa0103e0, "mov x0, x1"),
+        (0x4c, 0x52800540, "mov w0, #42"),
+        (0x50, 0x92800000, "mov x0, #-1"),
+        (0x54, 0xd2a00020, "mov x0, #65536"),
+        (0x58, 0x12800000, "mov w0, #-1"),
+        (0x5c, 0xf2c24680, "movk x0, #4660, lsl #32"),
+        (0x60, 0x92401c20, "and x0, x1, #0xff"),
+        (0x64, 0x0a020020, "and w0, w1, w2"),
+        (0x68, 0xea020020, "ands x0, x1, x2"),
+        (0x6c, 0xb2400020, "orr x0, x1, #0x1"),
+        (0x70, 0x2a021020, "orr w0, w1, w2, lsl #4"),
+        (0x74, 0xca020020, "eor x0, x1, x2"),
+        (0x78, 0x521c0c20, "eor w0, w1, #0xf0"),
+        (0x7c, 0x8a220020, "bic x0, x1, x2"),
+        (0x80, 0x6a220020, "bics w0, w1, w2"),
+        (0x84, 0xaa220020, "orn x0, x1, x2"),
+        (0x88, 0x4a220020, "eon w0, w1, w2"),
+        (0x8c, 0xaa2103e0, "mvn x0, x1"),
+        (0x90, 0xf27d001f, "tst x0, #0x8"),
+        (0x94, 0x6a01001f, "tst w0, w1"),
+        (0x98, 0xd37df020, "lsl x0, x1, #3"),
+        (0x9c, 0x53057c20, "lsr w0, w1, #5"),
+        (0xa0, 0x937ffc20, "asr x0, x1, #63"),
+        (0xa4, 0x13811c20, "ror w0, w1, #7"),
+        (0xa8, 0x9ac22020, "lsl x0, x1, x2"),
+        (0xac, 0x9ac22420, "lsr x0, x1, x2"),
+        (0xb0, 0x1ac22820, "asr w0, w1, w2"),
+        (0xb4, 0x9ac22c20, "ror x0, x1, x2"),
+        (0xb8, 0xd3442c20, "ubfx x0, x1, #4, #8"),
+        (0xbc, 0x13021020, "sbfx w0, w1, #2, #3"),
+        (0xc0, 0xd37e2420, "ubfiz x0, x1, #2, #10"),
+        (0xc4, 0x937d1020, "sbfiz x0, x1, #3, #5"),
+        (0xc8, 0x33180c20, "bfi w0, w1, #8, #4"),
+        (0xcc, 0xb3505c20, "bfxil x0, x1, #16, #8"),
+        (0xd0, 0x53001c20, "uxtb w0, w1"),
+        (0xd4, 0x53003c20, "uxth w0, w1"),
+        (0xd8, 0x93401c20, "sxtb x0, w1"),
+        (0xdc, 0x13003c20, "sxth w0, w1"),
+        (0xe0, 0x93407c20, "sxtw x0, w1"),
+        (0xe4, 0x93c23020, "extr x0, x1, x2, #12"),
+        (0xe8, 0x9a820020, "csel x0, x1, x2, eq"),
+        (0xec, 0x1a821420, "csinc w0, w1, w2, ne"),
+        (0xf0, 0xda82b020, "csinv x0, x1, x2, lt"),
+        (0xf4, 0x5a82a420, "csneg w0, w1, w2, ge"),
+        (0xf8, 0x1a9f97e0, "cset w0, hi"),
+        (0xfc, 0xda9f23e0, "csetm x0, lo"),
+        (0x100, 0x9a815420, "cinc x0, x1, mi"),
+        (0x104, 0x5a814420, "cneg w0, w1, pl"),
+        (0x108, 0xfa431804, "ccmp x0, #3, #4, ne"),
+        (0x10c, 0x3a410000, "ccmn w0, w1, #0, eq"),
+        (0x110, 0x9b020c20, "madd x0, x1, x2, x3"),
+        (0x114, 0x1b028c20, "msub w0, w1, w2, w3"),
+        (0x118, 0x9b027c20, "mul x0, x1, x2"),
+        (0x11c, 0x1b02fc20, "mneg w0, w1, w2"),
+        (0x120, 0x9b220c20, "smaddl x0, w1, w2, x3"),
+        (0x124, 0x9ba20c20, "umaddl x0, w1, w2, x3"),
+        (0x128, 0x9b227c20, "smull x0, w1, w2"),
+        (0x12c, 0x9ba27c20, "umull x0, w1, w2"),
+        (0x130, 0x9b427c20, "smulh x0, x1, x2"),
+        (0x134, 0x9bc27c20, "umulh x0, x1, x2"),
+        (0x138, 0x1ac20820, "udiv w0, w1, w2"),
+        (0x13c, 0x9ac20c20, "sdiv x0, x1, x2"),
+        (0x140, 0xdac00020, "rbit x0, x1"),
+        (0x144, 0x5ac00820, "rev w0, w1"),
+        (0x148, 0xdac00420, "rev16 x0, x1"),
+        (0x14c, 0xdac00820, "rev32 x0, x1"),
+        (0x150, 0x5ac01020, "clz w0, w1"),
+        (0x154, 0xdac01420, "cls x0, x1"),
+        (0x158, 0x14000002, "b 0x160"),
+        (0x15c, 0x97fffffc, "bl 0x14c"),
+        (0x160, 0x54000060, "b.eq 0x16c"),
+        (0x164, 0x54ffffec, "b.gt 0x160"),
+        (0x168, 0xb40000a0, "cbz x0, 0x17c"),
+        (0x16c, 0x35000041, "cbnz w1, 0x174"),
+        (0x170, 0xb6400080, "tbz x0, #40, 0x180"),
+        (0x174, 0x371fffc0, "tbnz w0, #3, 0x16c"),
+        (0x178, 0xd61f0220, "br x17"),
+        (0x17c, 0xd63f0100, "blr x8"),
+        (0x180, 0xd65f03c0, "ret"),
+        (0x184, 0xd65f0020, "ret x1"),
+        (0x188, 0xd65f0bff, "retaa"),
+        (0x18c, 0xd65f0fff, "retab"),
+        (0x190, 0xd503201f, "nop"),
+        (0x194, 0xd503245f, "bti c"),
+        (0x198, 0xd503233f, "paciasp"),
+        (0x19c, 0xd50323bf, "autiasp"),
+        (0x1a0, 0xd503237f, "pacibsp"),
+        (0x1a4, 0xd50323ff, "autibsp"),
+        (0x1a8, 0xd503245f, "bti c"),
+        (0x1ac, 0xd4000001, "svc #0"),
+        (0x1b0, 0xd4207d00, "brk #0x3e8"),
+        (0x1b4, 0xd4400000, "hlt #0"),
+        (0x1b8, 0xd5033bbf, "dmb ish"),
+        (0x1bc, 0xd5033f9f, "dsb sy"),
+        (0x1c0, 0xd5033fdf, "isb"),
+        (0x1c4, 0xd53bd040, "mrs x0, TPIDR_EL0"),
+        (0x1c8, 0xd51bd041, "msr TPIDR_EL0, x1"),
+        (0x1cc, 0xf9400020, "ldr x0, [x1]"),
+        (0x1d0, 0xb9400820, "ldr w0, [x1, #8]"),
+        (0x1d4, 0xf8410c20, "ldr x0, [x1, #16]!"),
+        (0x1d8, 0xf8410420, "ldr x0, [x1], #16"),
+        (0x1dc, 0xf8627820, "ldr x0, [x1, x2, lsl #3]"),
+        (0x1e0, 0xb862d820, "ldr w0, [x1, w2, sxtw #2]"),
+        (0x1e4, 0x58000100, "ldr x0, 0x204"),
+        (0x1e8, 0x39400420, "ldrb w0, [x1, #1]"),
+        (0x1ec, 0x79400420, "ldrh w0, [x1, #2]"),
+        (0x1f0, 0x39800020, "ldrsb x0, [x1]"),
+        (0x1f4, 0x79c00820, "ldrsh w0, [x1, #4]"),
+        (0x1f8, 0xb9800420, "ldrsw x0, [x1, #4]"),
+        (0x1fc, 0xf9000fe0, "str x0, [sp, #24]"),
+        (0x200, 0x38226820, "strb w0, [x1, x2]"),
+        (0x204, 0x79000c20, "strh w0, [x1, #6]"),
+        (0x208, 0xf85f8020, "ldur x0, [x1, #-8]"),
+        (0x20c, 0x385ff020, "ldurb w0, [x1, #-1]"),
+        (0x210, 0x785fe020, "ldurh w0, [x1, #-2]"),
+        (0x214, 0x38dff020, "ldursb w0, [x1, #-1]"),
+        (0x218, 0x789fe020, "ldursh x0, [x1, #-2]"),
+        (0x21c, 0xb89fc020, "ldursw x0, [x1, #-4]"),
+        (0x220, 0xf81f83a0, "stur x0, [x29, #-8]"),
+        (0x224, 0x381fd020, "sturb w0, [x1, #-3]"),
+        (0x228, 0x781fb020, "sturh w0, [x1, #-5]"),
+        (0x22c, 0xa8c27bfd, "ldp x29, x30, [sp], #32"),
+        (0x230, 0x69410440, "ldpsw x0, x1, [x2, #8]"),
+        (0x234, 0xa9be7bfd, "stp x29, x30, [sp, #-32]!"),
+        (0x238, 0x29010440, "stp w0, w1, [x2, #8]"),
+        (0x23c, 0xa8400440, "ldnp x0, x1, [x2]"),
+        (0x240, 0xa8010440, "stnp x0, x1, [x2, #16]"),
+        (0x244, 0xc85f7c20, "ldxr x0, [x1]"),
+        (0x248, 0x085f7c20, "ldxrb w0, [x1]"),
+        (0x24c, 0x485f7c20, "ldxrh w0, [x1]"),
+        (0x250, 0x885ffc20, "ldaxr w0, [x1]"),
+        (0x254, 0x085ffc20, "ldaxrb w0, [x1]"),
+        (0x258, 0x485ffc20, "ldaxrh w0, [x1]"),
+        (0x25c, 0xc8027c20, "stxr w2, x0, [x1]"),
+        (0x260, 0x08027c20, "stxrb w2, w0, [x1]"),
+        (0x264, 0x48027c20, "stxrh w2, w0, [x1]"),
+        (0x268, 0xc802fc20, "stlxr w2, x0, [x1]"),
+        (0x26c, 0x0802fc20, "stlxrb w2, w0, [x1]"),
+        (0x270, 0x4802fc20, "stlxrh w2, w0, [x1]"),
+        (0x274, 0xc8dffc20, "ldar x0, [x1]"),
+        (0x278, 0x08dffc20, "ldarb w0, [x1]"),
+        (0x27c, 0x48dffc20, "ldarh w0, [x1]"),
+        (0x280, 0xc89ffc20, "stlr x0, [x1]"),
+        (0x284, 0x089ffc20, "stlrb w0, [x1]"),
+        (0x288, 0x489ffc20, "stlrh w0, [x1]"),
+        (0x28c, 0xfd400420, "ldr d0, [x1, #8]"),
+        (0x290, 0xbd0007e1, "str s1, [sp, #4]"),
+        (0x294, 0x3dc00000, "ldr q0, [x0]"),
+        (0x298, 0x6d4127e8, "ldp d8, d9, [sp, #16]"),
+        (0x29c, 0x9e670020, "fmov d0, x1"),
+        (0x2a0, 0x1e260020, "fmov w0, s1"),
+        (0x2a4, 0x1e2e1000, "fmov s0, #1.00000000"),
+        (0x2a8, 0x1e604041, "fmov d1, d2"),
+        (0x2ac, 0x1e20c020, "fabs s0, s1"),
+        (0x2b0, 0x1e614020, "fneg d0, d1"),
+        (0x2b4, 0x1e61c020, "fsqrt d0, d1"),
+        (0x2b8, 0x1e22c020, "fcvt d0, s1"),
+        (0x2bc, 0x1e624020, "fcvt s0, d1"),
+        (0x2c0, 0x1e644020, "frintn d0, d1"),
+        (0x2c4, 0x1e24c020, "frintp s0, s1"),
+        (0x2c8, 0x1e654020, "frintm d0, d1"),
+        (0x2cc, 0x1e25c020, "frintz s0, s1"),
+        (0x2d0, 0x1e664020, "frinta d0, d1"),
+        (0x2d4, 0x1e274020, "frintx s0, s1"),
+        (0x2d8, 0x1e67c020, "frinti d0, d1"),
+        (0x2dc, 0x1e620820, "fmul d0, d1, d2"),
+        (0x2e0, 0x1e221820, "fdiv s0, s1, s2"),
+        (0x2e4, 0x1e622820, "fadd d0, d1, d2"),
+        (0x2e8, 0x1e223820, "fsub s0, s1, s2"),
+        (0x2ec, 0x1e624820, "fmax d0, d1, d2"),
+        (0x2f0, 0x1e225820, "fmin s0, s1, s2"),
+        (0x2f4, 0x1e626820, "fmaxnm d0, d1, d2"),
+        (0x2f8, 0x1e227820, "fminnm s0, s1, s2"),
+        (0x2fc, 0x1e628820, "fnmul d0, d1, d2"),
+        (0x300, 0x1f420c20, "fmadd d0, d1, d2, d3"),
+        (0x304, 0x1f028c20, "fmsub s0, s1, s2, s3"),
+        (0x308, 0x1f620c20, "fnmadd d0, d1, d2, d3"),
+        (0x30c, 0x1f228c20, "fnmsub s0, s1, s2, s3"),
+        (0x310, 0x1e612000, "fcmp d0, d1"),
+        (0x314, 0x1e202008, "fcmp s0, #0.0"),
+        (0x318, 0x1e612010, "fcmpe d0, d1"),
+        (0x31c, 0x1e62cc20, "fcsel d0, d1, d2, gt"),
+        (0x320, 0x1e600020, "fcvtns w0, d1"),
+        (0x324, 0x9e210020, "fcvtnu x0, s1"),
+        (0x328, 0x1e280020, "fcvtps w0, s1"),
+        (0x32c, 0x9e690020, "fcvtpu x0, d1"),
+        (0x330, 0x1e700020, "fcvtms w0, d1"),
+        (0x334, 0x9e310020, "fcvtmu x0, s1"),
+        (0x338, 0x1e780000, "fcvtzs w0, d0"),
+        (0x33c, 0x9e390020, "fcvtzu x0, s1"),
+        (0x340, 0x1e640020, "fcvtas w0, d1"),
+        (0x344, 0x9e650020, "fcvtau x0, d1"),
+        (0x348, 0x1e620020, "scvtf d0, w1"),
+        (0x34c, 0x9e230020, "ucvtf s0, x1"),
+    ];
+
+    /// Rewrites hexadecimal literals in decimal and lowercases system register names,
+    /// as `llvm-objdump` prints immediates in decimal and registers in upper case.
+    fn normalize(text: &str) -> String {
+        let text = text.to_lowercase();
+        let mut out = String::new();
+        let mut rest = text.as_str();
+        while let Some(at) = rest.find("0x") {
+            let digits = rest[at + 2..]
+                .find(|c: char| !c.is_ascii_hexdigit())
+                .map_or(rest.len(), |end| at + 2 + end);
+            out.push_str(&rest[..at]);
+            out.push_str(&u64::from_str_radix(&rest[at + 2..digits], 16).unwrap().to_string());
+            rest = &rest[digits..];
+        }
+        out + rest
+    }
+
+    #[test]
+    fn llvm_disassembly() {
+        for (address, word, expected) in LLVM {
+            let text = decode(word, address).to_string();
+            assert_eq!(normalize(&text), normalize(expected), "{word:08x} at {address:#x}");
+        }
+    }
+}
@@ -0,0 +1,677 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::aarch64::{Cond, IndexMode, Instruction, Mem, Opcode, Operand, Reg, RegClass, Shift};
+use crate::aarch64;
+use crate::arch;
+use crate::ast::*;
+use crate::cfg::BasicBlock;
+use crate::parser::{get_binary, get_flag, get_not, get_tail_call};
+use crate::sse_parser::{get_cast, get_intrinsic};
+
+/// Number of integer registers carrying arguments under AAPCS64, `x0` to `x7`.
+const ARGUMENT_REGISTERS: u8 = 8;
+
+/// Lifts the instructions of an AArch64 block and fills in the arguments of its calls.
+///
+/// Addresses built with `adr` or `adrp` and `add` are folded into constants, and so are
+/// the loads and stores going through them, much like rip-relative operands on x86-64.
+pub fn lift_block(block: &mut BasicBlock, tail_calls: &BTreeSet<u64>, symbols: &mut Vec<Symbol>) {
+    let mut known = HashMap::<u8, u64>::new();
+    let mut arguments = BTreeMap::<u8, Reg>::new();
+
+    block.statements = Vec::with_capacity(block.instructions.len());
+    for inst in block.instructions.iter() {
+        let arch::Instruction::AArch64(inst) = inst else {
+            block.statements.push(unparsed(*inst));
+            continue;
+        };
+
+        let mut stmt = match inst.operands[0] {
+            Operand::Label(target) if tail_calls.contains(&inst.address) => get_tail_call(target),
+            _ => to_expression(symbols, &known, inst),
+        };
+        if let ExprKind::Call(_, args) = &mut stmt.call_site_mut().kind {
+            for num in 0..ARGUMENT_REGISTERS {
+                let Some(reg) = arguments.get(&num) else {
+                    break;
+                };
+                args.push(get_register(symbols, *reg));
+            }
+        }
+        block.statements.push(stmt);
+
+        if matches!(inst.opcode, Opcode::Bl | Opcode::Blr) {
+            // the callee may clobber every argument and temporary register
+            arguments.clear();
+            known.clear();
+            continue;
+        }
+        let constant = get_constant(&known, inst);
+        for reg in inst.destinations() {
+            known.remove(&reg.num);
+            if reg.num < ARGUMENT_REGISTERS {
+                arguments.insert(reg.num, reg);
+            }
+        }
+        if let (Some(value), Operand::Reg(rd)) = (constant, inst.operands[0]) {
+            if !rd.is_zero() {
+                known.insert(rd.num, value);
+            }
+        }
+    }
+}
+
+/// Returns the address an instruction puts in its destination register, when it is
+/// computed from the program counter.
+fn get_constant(known: &HashMap<u8, u64>, inst: &Instruction) -> Option<u64> {
+    match (inst.opcode, inst.operands) {
+        (Opcode::Adr | Opcode::Adrp, [_, Operand::Label(target), ..]) => Some(target),
+        (Opcode::Add, [_, Operand::Reg(rn), Operand::ShiftedImm(imm, shift), _]) if !rn.sp => known
+            .get(&rn.num)
+            .map(|base| base.wrapping_add(imm << shift)),
+        _ => None,
+    }
+}
+
+/// Lifts a single instruction, using the registers in `known` to resolve addresses.
+pub fn to_expression(
+    symbols: &mut Vec<Symbol>,
+    known: &HashMap<u8, u64>,
+    inst: &Instruction,
+) -> Expr {
+    lift(symbols, known, inst).unwrap_or_else(|| unparsed(arch::Instruction::AArch64(*inst)))
+}
+
+fn lift(symbols: &mut Vec<Symbol>, known: &HashMap<u8, u64>, inst: &Instruction) -> Option<Expr> {
+    if let Some(expr) = lift_float(symbols, inst) {
+        return Some(expr);
+    }
+    let ops = inst.operands;
+    let expr = match (inst.opcode, ops) {
+        // saving registers on the stack, like a push
+        (
+            Opcode::Stp,
+            [_, _, Operand::Mem(Mem {
+                mode: IndexMode::PreIndex,
+                ..
+            }), _],
+        ) => get_lit(Lit::StillUnknown),
+
+        (Opcode::Mov, [Operand::Reg(rd), src, ..]) => {
+            get_assign(get_register(symbols, rd), get_operand(symbols, src)?)
+        }
+        (Opcode::Movz, [Operand::Reg(rd), Operand::ShiftedImm(imm, shift), ..]) => {
+            get_assign(get_register(symbols, rd), get_immediate(imm << shift))
+        }
+        (Opcode::Movn, [Operand::Reg(rd), Operand::ShiftedImm(imm, shift), ..]) => {
+            let mask = if rd.class == RegClass::X {
+                u64::MAX
+            } else {
+                0xffff_ffff
+            };
+            get_assign(
+                get_register(symbols, rd),
+                get_immediate(!(imm << shift) & mask),
+            )
+        }
+        (Opcode::Movk, [Operand::Reg(rd), Operand::ShiftedImm(imm, shift), ..]) => {
+            let mask = if rd.class == RegClass::X {
+                u64::MAX
+            } else {
+                0xffff_ffff
+            };
+            let kept = get_binary(
+                BinOpKind::And,
+                get_register(symbols, rd),
+                get_immediate(!(0xffff << shift) & mask),
+            );
+            let value = get_binary(BinOpKind::Or, kept, get_immediate(imm << shift));
+            get_assign(get_register(symbols, rd), value)
+        }
+        (Opcode::Adr | Opcode::Adrp, [Operand::Reg(rd), Operand::Label(target), ..]) => {
+            get_assign(get_register(symbols, rd), get_lit(Lit::U64(target)))
+        }
+
+        (
+            Opcode::Add
+            | Opcode::Adds
+            | Opcode::Sub
+            | Opcode::Subs
+            | Opcode::And
+            | Opcode::Ands
+            | Opcode::Orr
+            | Opcode::Eor
+            | Opcode::Lsl
+            | Opcode::Lsr
+            | Opcode::Asr
+            | Opcode::Mul
+            | Opcode::Smull
+            | Opcode::Umull
+            | Opcode::Udiv
+            | Opcode::Sdiv,
+            [Operand::Reg(rd), Operand::Reg(rn), rhs, Operand::None],
+        ) => {
+            if let Some(value) = get_constant(known, inst) {
+                return Some(get_assign(
+                    get_register(symbols, rd),
+                    get_lit(Lit::U64(value)),
+                ));
+            }
+            let op = match inst.opcode {
+                Opcode::Add | Opcode::Adds => BinOpKind::Add,
+                Opcode::Sub | Opcode::Subs => BinOpKind::Sub,
+                Opcode::And | Opcode::Ands => BinOpKind::And,
+                Opcode::Orr => BinOpKind::Or,
+                Opcode::Eor => BinOpKind::BitXor,
+                Opcode::Lsl => BinOpKind::Shl,
+                Opcode::Lsr | Opcode::Asr => BinOpKind::Shr,
+                Opcode::Udiv | Opcode::Sdiv => BinOpKind::Div,
+                _ => BinOpKind::Mul,
+            };
+            let (mut lhs, mut rhs) = (get_register(symbols, rn), get_operand(symbols, rhs)?);
+            // registers carry no signedness, so the signed operations cast their operands
+            match inst.opcode {
+                Opcode::Asr => lhs = get_cast(lhs, get_signed_type(rn)),
+                Opcode::Sdiv => {
+                    lhs = get_cast(lhs, get_signed_type(rn));
+                    rhs = get_cast(rhs, get_signed_type(rn));
+                }
+                // the 32-bit operands widen to a 64-bit product
+                Opcode::Smull => {
+                    lhs = get_cast(get_cast(lhs, Ty::I32), Ty::I64);
+                    rhs = get_cast(get_cast(rhs, Ty::I32), Ty::I64);
+                }
+                Opcode::Umull => {
+                    lhs = get_cast(lhs, Ty::U64);
+                    rhs = get_cast(rhs, Ty::U64);
+                }
+                _ => {}
+            }
+            let value = get_binary(op, lhs, rhs);
+            get_assign(get_register(symbols, rd), value)
+        }
+        (Opcode::Bic | Opcode::Orn | Opcode::Eon, [Operand::Reg(rd), Operand::Reg(rn), rhs, _]) => {
+            let op = match inst.opcode {
+                Opcode::Bic => BinOpKind::And,
+                Opcode::Orn => BinOpKind::Or,
+                _ => BinOpKind::BitXor,
+            };
+            let rhs = get_unary(UnOp::Not, get_operand(symbols, rhs)?);
+            get_assign(
+                get_register(symbols, rd),
+                get_binary(op, get_register(symbols, rn), rhs),
+            )
+        }
+        (
+            Opcode::Madd | Opcode::Msub,
+            [Operand::Reg(rd), Operand::Reg(rn), Operand::Reg(rm), Operand::Reg(ra)],
+        ) => {
+            let op = if inst.opcode == Opcode::Madd {
+                BinOpKind::Add
+            } else {
+                BinOpKind::Sub
+            };
+            let product = get_binary(
+                BinOpKind::Mul,
+                get_register(symbols, rn),
+                get_register(symbols, rm),
+            );
+            let value = get_binary(op, get_register(symbols, ra), product);
+            get_assign(get_register(symbols, rd), value)
+        }
+        (Opcode::Neg | Opcode::Negs | Opcode::Mvn, [Operand::Reg(rd), rhs, ..]) => {
+            let op = if inst.opcode == Opcode::Mvn {
+                UnOp::Not
+            } else {
+                UnOp::Neg
+            };
+            let value = get_unary(op, get_operand(symbols, rhs)?);
+            get_assign(get_register(symbols, rd), value)
+        }
+        (
+            Opcode::Uxtb | Opcode::Uxth | Opcode::Sxtb | Opcode::Sxth | Opcode::Sxtw,
+            [Operand::Reg(rd), Operand::Reg(rn), ..],
+        ) => {
+            let ty = match inst.opcode {
+                Opcode::Uxtb => Ty::U8,
+                Opcode::Uxth => Ty::U16,
+                Opcode::Sxtb => Ty::I8,
+                Opcode::Sxth => Ty::I16,
+                _ => Ty::I32,
+            };
+            let value = Expr {
+                id: 0,
+                kind: ExprKind::Cast(Box::new(get_register(symbols, rn)), ty),
+            };
+            get_assign(get_register(symbols, rd), value)
+        }
+        (Opcode::Cset, [Operand::Reg(rd), Operand::Cond(cond), ..]) => {
+            get_assign(get_register(symbols, rd), get_condition(symbols, cond))
+        }
+
+        (_, [Operand::Reg(rt), Operand::Mem(mem), ..] | [_, Operand::Reg(rt), Operand::Mem(mem), _])
+            if is_ordered(inst.opcode) =>
+        {
+            let ty = get_access_type(inst.opcode, rt)?;
+            let pointer = get_cast(get_address(symbols, known, &mem), Ty::Ptr(Box::new(ty)));
+            let order = |order: &str| get_lit(Lit::Symbol(Symbol::Local(order.to_string())));
+            let value = get_register(symbols, rt);
+            match (inst.opcode, ops[0]) {
+                (Opcode::Ldar | Opcode::Ldarb | Opcode::Ldarh, _) => {
+                    let load = get_intrinsic(
+                        "__atomic_load_n",
+                        vec![pointer, order("__ATOMIC_ACQUIRE")],
+                    );
+                    get_assign(value, load)
+                }
+                (Opcode::Stlr | Opcode::Stlrb | Opcode::Stlrh, _) => get_intrinsic(
+                    "__atomic_store_n",
+                    vec![pointer, value, order("__ATOMIC_RELEASE")],
+                ),
+                (Opcode::Ldxr | Opcode::Ldxrb | Opcode::Ldxrh, _) => {
+                    get_assign(value, get_intrinsic("load_exclusive", vec![pointer]))
+                }
+                (Opcode::Ldaxr | Opcode::Ldaxrb | Opcode::Ldaxrh, _) => {
+                    get_assign(value, get_intrinsic("load_acquire_exclusive", vec![pointer]))
+                }
+                (opcode, Operand::Reg(rs)) => {
+                    let name = match opcode {
+                        Opcode::Stxr | Opcode::Stxrb | Opcode::Stxrh => "store_exclusive",
+                        _ => "store_release_exclusive",
+                    };
+                    let store = get_intrinsic(name, vec![pointer, value]);
+                    get_assign(get_register(symbols, rs), store)
+                }
+                _ => return None,
+            }
+        }
+        (_, [Operand::Reg(rt), Operand::Label(target), ..]) if is_load(inst.opcode) => {
+            let ty = get_access_type(inst.opcode, rt)?;
+            get_assign(
+                get_register(symbols, rt),
+                get_deref(get_lit(Lit::U64(target)), ty),
+            )
+        }
+        (_, [Operand::Reg(rt), Operand::Mem(mem), ..]) if mem.mode == IndexMode::Offset => {
+            let ty = get_access_type(inst.opcode, rt)?;
+            let location = get_deref(get_address(symbols, known, &mem), ty);
+            if inst.opcode.is_store() {
+                get_assign(location, get_register(symbols, rt))
+            } else {
+                get_assign(get_register(symbols, rt), location)
+            }
+        }
+
+        (Opcode::B, [Operand::Label(target), ..]) => get_goto(target),
+        (Opcode::BCond, [Operand::Cond(cond), Operand::Label(target), ..]) => {
+            get_if(get_condition(symbols, cond), target)
+        }
+        (Opcode::Cbz | Opcode::Cbnz, [Operand::Reg(rt), Operand::Label(target), ..]) => {
+            let op = if inst.opcode == Opcode::Cbz {
+                BinOpKind::Eq
+            } else {
+                BinOpKind::Ne
+            };
+            let zero = get_immediate(0);
+            get_if(get_binary(op, get_register(symbols, rt), zero), target)
+        }
+        (
+            Opcode::Tbz | Opcode::Tbnz,
+            [Operand::Reg(rt), Operand::Imm(bit), Operand::Label(target), _],
+        ) => {
+            let test = get_binary(
+                BinOpKind::And,
+                get_register(symbols, rt),
+                get_immediate(1 << bit),
+            );
+            let cond = if inst.opcode == Opcode::Tbz {
+                get_not(test)
+            } else {
+                test
+            };
+            get_if(cond, target)
+        }
+        (Opcode::Bl, [Operand::Label(target), ..]) => Expr {
+            id: 0,
+            kind: ExprKind::Call(target, Vec::new()),
+        },
+        (Opcode::Ret | Opcode::Retaa | Opcode::Retab, [Operand::None, ..]) => Expr {
+            id: 0,
+            kind: ExprKind::Ret(None),
+        },
+        _ => return None,
+    };
+    Some(expr)
+}
+
+/// Lifts the scalar floating-point instructions, with the C library functions for those C
+/// has no operator for. Returns `None` for the other instructions, and for `fcmp`, which
+/// sets the flags like `cmp` does.
+fn lift_float(symbols: &mut Vec<Symbol>, inst: &Instruction) -> Option<Expr> {
+    use Opcode::*;
+    let [Operand::Reg(rd), source, ..] = inst.operands else {
+        return None;
+    };
+    // the C library function on doubles, `sqrt`, or on floats, `sqrtf`
+    let function = |reg: Reg, name: &str, args| match reg.class {
+        RegClass::S => get_intrinsic(&format!("{name}f"), args),
+        _ => get_intrinsic(name, args),
+    };
+    let mut operand = |index: usize| match inst.operands[index] {
+        Operand::Reg(reg) => Some(get_register(symbols, reg)),
+        _ => None,
+    };
+    let value = match inst.opcode {
+        Fadd | Fsub | Fmul | Fdiv => {
+            let op = match inst.opcode {
+                Fadd => BinOpKind::Add,
+                Fsub => BinOpKind::Sub,
+                Fmul => BinOpKind::Mul,
+                _ => BinOpKind::Div,
+            };
+            get_binary(op, operand(1)?, operand(2)?)
+        }
+        Fnmul => get_unary(
+            UnOp::Neg,
+            get_binary(BinOpKind::Mul, operand(1)?, operand(2)?),
+        ),
+        // C's fmax and fmin ignore a NaN like fmaxnm and fminnm do
+        Fmax | Fmaxnm => function(rd, "fmax", vec![operand(1)?, operand(2)?]),
+        Fmin | Fminnm => function(rd, "fmin", vec![operand(1)?, operand(2)?]),
+        Fmadd | Fmsub | Fnmadd | Fnmsub => {
+            let (n, m, a) = (operand(1)?, operand(2)?, operand(3)?);
+            match inst.opcode {
+                Fmadd => function(rd, "fma", vec![n, m, a]),
+                Fmsub => function(rd, "fma", vec![get_unary(UnOp::Neg, n), m, a]),
+                Fnmadd => get_unary(UnOp::Neg, function(rd, "fma", vec![n, m, a])),
+                _ => function(rd, "fma", vec![n, m, get_unary(UnOp::Neg, a)]),
+            }
+        }
+        Fabs => function(rd, "fabs", vec![operand(1)?]),
+        Fsqrt => function(rd, "sqrt", vec![operand(1)?]),
+        Fneg => get_unary(UnOp::Neg, operand(1)?),
+        Frintn | Frintp | Frintm | Frintz | Frinta | Frintx | Frinti => {
+            let name = match inst.opcode {
+                Frintn => "roundeven",
+                Frintp => "ceil",
+                Frintm => "floor",
+                Frintz => "trunc",
+                Frinta => "round",
+                Frintx => "rint",
+                _ => "nearbyint",
+            };
+            function(rd, name, vec![operand(1)?])
+        }
+        Fcvt => get_cast(operand(1)?, get_float_type(rd)),
+        // an immediate, or the bits of a register as they are
+        Fmov => match source {
+            Operand::FloatImm(imm8) => {
+                let value = aarch64::expand_float(imm8);
+                match rd.class {
+                    RegClass::S => get_lit(Lit::F32(value as f32)),
+                    _ => get_lit(Lit::F64(value)),
+                }
+            }
+            _ => operand(1)?,
+        },
+        Fcvtns | Fcvtnu | Fcvtps | Fcvtpu | Fcvtms | Fcvtmu | Fcvtzs | Fcvtzu | Fcvtas | Fcvtau => {
+            let Operand::Reg(rn) = source else {
+                return None;
+            };
+            let (name, signed) = match inst.opcode {
+                Fcvtns => ("roundeven", true),
+                Fcvtnu => ("roundeven", false),
+                Fcvtps => ("ceil", true),
+                Fcvtpu => ("ceil", false),
+                Fcvtms => ("floor", true),
+                Fcvtmu => ("floor", false),
+                Fcvtas => ("round", true),
+                Fcvtau => ("round", false),
+                Fcvtzs => ("", true),
+                _ => ("", false),
+            };
+            // the C conversion truncates, like fcvtzs; the others round as they tell
+            let value = match name {
+                "" => operand(1)?,
+                name => function(rn, name, vec![operand(1)?]),
+            };
+            let ty = match (rd.class, signed) {
+                (RegClass::W, true) => Ty::I32,
+                (RegClass::W, false) => Ty::U32,
+                (_, true) => Ty::I64,
+                (_, false) => Ty::U64,
+            };
+            get_cast(value, ty)
+        }
+        Scvtf | Ucvtf => {
+            let Operand::Reg(rn) = source else {
+                return None;
+            };
+            let value = match inst.opcode {
+                Scvtf => get_cast(operand(1)?, get_signed_type(rn)),
+                _ => operand(1)?,
+            };
+            get_cast(value, get_float_type(rd))
+        }
+        _ => return None,
+    };
+    Some(get_assign(get_register(symbols, rd), value))
+}
+
+pub fn unparsed(inst: arch::Instruction) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Unparsed(inst),
+    }
+}
+
+pub fn get_lit(lit: Lit) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Lit(lit),
+    }
+}
+
+pub fn get_immediate(value: u64) -> Expr {
+    match u32::try_from(value) {
+        Ok(value) => get_lit(Lit::U32(value)),
+        Err(_) => get_lit(Lit::U64(value)),
+    }
+}
+
+pub fn get_unary(op: UnOp, expr: Expr) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Unary(op, Box::new(expr)),
+    }
+}
+
+pub fn get_assign(lhs: Expr, rhs: Expr) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Assign(Box::new(lhs), Box::new(rhs)),
+    }
+}
+
+pub fn get_goto(target: u64) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Goto(target),
+    }
+}
+
+pub fn get_if(cond: Expr, target: u64) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::If(Box::new(cond), vec![get_goto(target)]),
+    }
+}
+
+pub fn get_deref(address: Expr, ty: Ty) -> Expr {
+    let pointer = Expr {
+        id: 0,
+        kind: ExprKind::Cast(Box::new(address), Ty::Ptr(Box::new(ty))),
+    };
+    get_unary(UnOp::Deref, pointer)
+}
+
+/// Lifts a register read, the zero register reading as the constant 0.
+fn get_register(symbols: &mut Vec<Symbol>, reg: Reg) -> Expr {
+    if reg.is_zero() {
+        return get_immediate(0);
+    }
+    let sym = Symbol::Reg(reg.to_string());
+    symbols.push(sym.clone());
+    get_lit(Lit::Symbol(sym))
+}
+
+/// Lifts a source operand. Shifts other than `lsl` are left to the caller to print as is,
+/// since they cannot be told apart from the surrounding operation without parentheses.
+fn get_operand(symbols: &mut Vec<Symbol>, operand: Operand) -> Option<Expr> {
+    let expr = match operand {
+        Operand::Reg(reg) => get_register(symbols, reg),
+        Operand::Imm(value) | Operand::Mask(value) => get_immediate(value),
+        Operand::ShiftedImm(value, shift) => get_immediate(value << shift),
+        Operand::Label(target) => get_lit(Lit::U64(target)),
+        Operand::Shifted(reg, _, 0) => get_register(symbols, reg),
+        Operand::Shifted(reg, Shift::Lsl, amount) | Operand::Extended(reg, _, amount) => {
+            get_scaled(symbols, reg, amount)
+        }
+        _ => return None,
+    };
+    Some(expr)
+}
+
+/// Lifts `reg << amount` as a multiplication, which keeps its precedence when printed.
+fn get_scaled(symbols: &mut Vec<Symbol>, reg: Reg, amount: u8) -> Expr {
+    let reg = get_register(symbols, reg);
+    if amount == 0 {
+        return reg;
+    }
+    get_binary(BinOpKind::Mul, reg, get_immediate(1 << amount))
+}
+
+/// Computes the address accessed by a memory operand without writeback.
+fn get_address(symbols: &mut Vec<Symbol>, known: &HashMap<u8, u64>, mem: &Mem) -> Expr {
+    if let Some((index, _, amount)) = mem.index {
+        let base = get_register(symbols, mem.base);
+        return get_binary(BinOpKind::Add, base, get_scaled(symbols, index, amount));
+    }
+    if let Some(base) = known.get(&mem.base.num).filter(|_| !mem.base.sp) {
+        return get_lit(Lit::U64(base.wrapping_add(mem.offset as u64)));
+    }
+    let base = get_register(symbols, mem.base);
+    match mem.offset {
+        0 => base,
+        offset if offset < 0 => {
+            get_binary(BinOpKind::Sub, base, get_immediate(offset.unsigned_abs()))
+        }
+        offset => get_binary(BinOpKind::Add, base, get_immediate(offset as u64)),
+    }
+}
+
+/// Returns the signed type of the size of a general purpose register.
+fn get_signed_type(reg: Reg) -> Ty {
+    match reg.class {
+        RegClass::W => Ty::I32,
+        _ => Ty::I64,
+    }
+}
+
+/// Returns the type held by a floating-point register.
+fn get_float_type(reg: Reg) -> Ty {
+    match reg.class {
+        RegClass::S => Ty::F32,
+        _ => Ty::F64,
+    }
+}
+
+/// Returns `true` for the exclusive and acquire-release accesses.
+fn is_ordered(opcode: Opcode) -> bool {
+    use Opcode::*;
+    opcode.is_exclusive_store()
+        || matches!(
+            opcode,
+            Ldxr | Ldxrb
+                | Ldxrh
+                | Ldaxr
+                | Ldaxrb
+                | Ldaxrh
+                | Ldar
+                | Ldarb
+                | Ldarh
+                | Stlr
+                | Stlrb
+                | Stlrh
+        )
+}
+
+fn is_load(opcode: Opcode) -> bool {
+    matches!(opcode, Opcode::Ldr | Opcode::Ldrsw)
+}
+
+/// Returns the type of the memory accessed by a load or store of integer register `reg`.
+fn get_access_type(opcode: Opcode, reg: Reg) -> Option<Ty> {
+    let ty = match opcode {
+        Opcode::Ldrb | Opcode::Ldurb | Opcode::Strb | Opcode::Sturb => Ty::U8,
+        Opcode::Ldxrb | Opcode::Ldaxrb | Opcode::Stxrb | Opcode::Stlxrb => Ty::U8,
+        Opcode::Ldarb | Opcode::Stlrb => Ty::U8,
+        Opcode::Ldxrh | Opcode::Ldaxrh | Opcode::Stxrh | Opcode::Stlxrh => Ty::U16,
+        Opcode::Ldarh | Opcode::Stlrh => Ty::U16,
+        Opcode::Ldrsb | Opcode::Ldursb => Ty::I8,
+        Opcode::Ldrh | Opcode::Ldurh | Opcode::Strh | Opcode::Sturh => Ty::U16,
+        Opcode::Ldrsh | Opcode::Ldursh => Ty::I16,
+        Opcode::Ldrsw | Opcode::Ldursw => Ty::I32,
+        Opcode::Ldr | Opcode::Ldur | Opcode::Str | Opcode::Stur
+        | Opcode::Ldxr
+        | Opcode::Ldaxr
+        | Opcode::Stxr
+        | Opcode::Stlxr
+        | Opcode::Ldar
+        | Opcode::Stlr => match reg.class {
+            RegClass::W => Ty::U32,
+            RegClass::X => Ty::U64,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(ty)
+}
+
+/// Expresses a condition in terms of the N, Z, C and V flags it tests.
+fn get_condition(symbols: &mut Vec<Symbol>, cond: Cond) -> Expr {
+    let n_eq_v = |symbols: &mut Vec<Symbol>, op| {
+        get_binary(op, get_flag(symbols, "N"), get_flag(symbols, "V"))
+    };
+    match cond {
+        Cond::Eq => get_flag(symbols, "Z"),
+        Cond::Ne => get_not(get_flag(symbols, "Z")),
+        Cond::Hs => get_flag(symbols, "C"),
+        Cond::Lo => get_not(get_flag(symbols, "C")),
+        Cond::Mi => get_flag(symbols, "N"),
+        Cond::Pl => get_not(get_flag(symbols, "N")),
+        Cond::Vs => get_flag(symbols, "V"),
+        Cond::Vc => get_not(get_flag(symbols, "V")),
+        Cond::Hi => get_binary(
+            BinOpKind::And,
+            get_flag(symbols, "C"),
+            get_not(get_flag(symbols, "Z")),
+        ),
+        Cond::Ls => get_binary(
+            BinOpKind::Or,
+            get_not(get_flag(symbols, "C")),
+            get_flag(symbols, "Z"),
+        ),
+        Cond::Ge => n_eq_v(symbols, BinOpKind::Eq),
+        Cond::Lt => n_eq_v(symbols, BinOpKind::Ne),
+        Cond::Gt => {
+            let n_eq_v = n_eq_v(symbols, BinOpKind::Eq);
+            get_binary(BinOpKind::And, get_not(get_flag(symbols, "Z")), n_eq_v)
+        }
+        Cond::Le => {
+            let n_ne_v = n_eq_v(symbols, BinOpKind::Ne);
+            get_binary(BinOpKind::Or, get_flag(symbols, "Z"), n_ne_v)
+        }
+        Cond::Al | Cond::Nv => get_lit(Lit::Bool(true)),
+    }
+}
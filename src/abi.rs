@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+
+use iced_x86::Register;
+use object::{BinaryFormat, Object};
+
+use crate::arch::Architecture;
+use crate::ast::{get_register_by_name, get_register_name};
+use crate::riscv;
+
+/// How arguments are passed to called functions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallingConvention {
+    /// System V AMD64 ABI, used on Linux and most Unix-like systems.
+    SysV,
+    /// Microsoft x64 calling convention.
+    Win64,
+    /// 32 and 16-bit C convention: everything is pushed, the caller cleans up.
+    Cdecl,
+    /// Like cdecl, but the callee pops the arguments; used by the Win32 API.
+    Stdcall,
+    /// First two arguments in ECX and EDX, the rest pushed.
+    Fastcall,
+    /// MSVC C++ methods: `this` in ECX, the rest pushed.
+    Thiscall,
+}
+
+impl CallingConvention {
+    pub fn for_file(file: &object::File) -> Self {
+        match file.format() {
+            BinaryFormat::Pe | BinaryFormat::Coff if file.is_64() => CallingConvention::Win64,
+            _ => Self::for_bitness(if file.is_64() { 64 } else { 32 }),
+        }
+    }
+
+    /// The usual convention of code decoded in `bitness`-bit mode, when nothing else is known.
+    pub fn for_bitness(bitness: u32) -> Self {
+        match bitness {
+            64 => CallingConvention::SysV,
+            _ => CallingConvention::Cdecl,
+        }
+    }
+
+    /// Registers holding the first integer or pointer arguments, in order.
+    pub fn argument_registers(self) -> &'static [Register] {
+        match self {
+            CallingConvention::SysV => &[
+                Register::RDI,
+                Register::RSI,
+                Register::RDX,
+                Register::RCX,
+                Register::R8,
+                Register::R9,
+            ],
+            CallingConvention::Win64 => &[Register::RCX, Register::RDX, Register::R8, Register::R9],
+            CallingConvention::Cdecl | CallingConvention::Stdcall => &[],
+            CallingConvention::Fastcall => &[Register::ECX, Register::EDX],
+            CallingConvention::Thiscall => &[Register::ECX],
+        }
+    }
+
+    /// Registers holding the first floating-point arguments, in order. Win64 gives each
+    /// argument a position, taking either its integer or its floating-point register.
+    pub fn float_argument_registers(self) -> &'static [Register] {
+        match self {
+            CallingConvention::SysV => &[
+                Register::XMM0,
+                Register::XMM1,
+                Register::XMM2,
+                Register::XMM3,
+                Register::XMM4,
+                Register::XMM5,
+                Register::XMM6,
+                Register::XMM7,
+            ],
+            CallingConvention::Win64 => {
+                &[Register::XMM0, Register::XMM1, Register::XMM2, Register::XMM3]
+            }
+            _ => &[],
+        }
+    }
+
+    /// Returns `true` if the `n`th argument goes in the `n`th integer or floating-point
+    /// register, whichever its type takes, rather than in the next one of its kind.
+    pub fn positional_arguments(self) -> bool {
+        self == CallingConvention::Win64
+    }
+
+    /// Register holding the floating-point return value; 32-bit code returns it on the
+    /// x87 stack, which is not followed.
+    pub fn float_return_register(self) -> Option<Register> {
+        match self {
+            CallingConvention::SysV | CallingConvention::Win64 => Some(Register::XMM0),
+            _ => None,
+        }
+    }
+
+    /// Registers a call may read arguments from, whatever the convention of its callee: the
+    /// conventions pushing the arguments are mixed with fastcall and thiscall, which take
+    /// their first ones in ECX and EDX.
+    pub fn call_registers(self) -> &'static [Register] {
+        match self {
+            CallingConvention::SysV | CallingConvention::Win64 => self.argument_registers(),
+            _ => &[Register::ECX, Register::EDX],
+        }
+    }
+
+    /// Registers a called function must give back unchanged.
+    pub fn preserved_registers(self) -> &'static [Register] {
+        match self {
+            CallingConvention::SysV => &[
+                Register::RBX,
+                Register::RBP,
+                Register::RSP,
+                Register::R12,
+                Register::R13,
+                Register::R14,
+                Register::R15,
+            ],
+            CallingConvention::Win64 => &[
+                Register::RBX,
+                Register::RBP,
+                Register::RSP,
+                Register::RDI,
+                Register::RSI,
+                Register::R12,
+                Register::R13,
+                Register::R14,
+                Register::R15,
+            ],
+            _ => &[
+                Register::EBX,
+                Register::EBP,
+                Register::ESP,
+                Register::EDI,
+                Register::ESI,
+            ],
+        }
+    }
+
+    /// Registers holding the integer return value; 32-bit code returns 64-bit values in
+    /// EDX:EAX.
+    pub fn return_registers(self) -> &'static [Register] {
+        match self {
+            CallingConvention::SysV | CallingConvention::Win64 => &[Register::RAX],
+            _ => &[Register::EAX, Register::EDX],
+        }
+    }
+
+    /// Returns `true` if the stack arguments are pushed right to left before the call,
+    /// rather than stored in space reserved by the prologue.
+    pub fn pushes_arguments(self) -> bool {
+        !matches!(self, CallingConvention::SysV | CallingConvention::Win64)
+    }
+
+    /// Returns `true` if the called function pops the arguments pushed for it, with `ret N`.
+    pub fn pops_arguments(self) -> bool {
+        matches!(
+            self,
+            CallingConvention::Stdcall | CallingConvention::Fastcall | CallingConvention::Thiscall
+        )
+    }
+
+    /// Offset from the stack pointer at the call of the first argument passed on the stack,
+    /// past the 32 bytes of shadow space reserved by Win64 callers.
+    pub fn stack_arguments_offset(self) -> u64 {
+        match self {
+            CallingConvention::Win64 => 0x20,
+            _ => 0,
+        }
+    }
+}
+
+/// The calling conventions of the functions found to follow another one than the default,
+/// like the stdcall and fastcall functions of a 32-bit program built for cdecl.
+#[derive(Clone, Debug)]
+pub struct Conventions {
+    pub default: CallingConvention,
+    functions: BTreeMap<u64, CallingConvention>,
+}
+
+impl Conventions {
+    pub fn new(default: CallingConvention) -> Self {
+        Self {
+            default,
+            functions: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the convention of the function at `addr`.
+    pub fn of(&self, addr: u64) -> CallingConvention {
+        self.functions.get(&addr).copied().unwrap_or(self.default)
+    }
+
+    pub fn insert(&mut self, addr: u64, convention: CallingConvention) {
+        if convention == self.default {
+            self.functions.remove(&addr);
+        } else {
+            self.functions.insert(addr, convention);
+        }
+    }
+
+    /// Returns `true` if every function follows the default convention.
+    pub fn is_uniform(&self) -> bool {
+        self.functions.is_empty()
+    }
+}
+
+/// The roles a calling convention gives the general-purpose registers of an architecture,
+/// by the names the lifted code reads them by, like `RDI` on x86, `x0` on AArch64 or `a0`
+/// on RISC-V.
+#[derive(Clone, Debug)]
+pub struct RegisterAbi {
+    architecture: Architecture,
+    /// The registers holding the first integer or pointer arguments, in order.
+    pub arguments: Vec<String>,
+    /// The register holding the integer result.
+    pub result: String,
+    /// The registers a called function gives back unchanged.
+    pub preserved: Vec<String>,
+    /// The stack pointer and the frame pointer.
+    pub stack: [String; 2],
+}
+
+impl RegisterAbi {
+    pub fn new(architecture: Architecture, convention: CallingConvention) -> Self {
+        let names = |registers: &[Register]| {
+            registers
+                .iter()
+                .map(|reg| get_register_name(*reg))
+                .collect::<Vec<_>>()
+        };
+        let (arguments, result, preserved, stack) = match architecture {
+            Architecture::X86 { .. } => (
+                names(convention.argument_registers()),
+                get_register_name(convention.return_registers()[0].full_register()),
+                names(convention.preserved_registers()),
+                ["RSP", "RBP"].map(str::to_string),
+            ),
+            // AAPCS64, with x29 as the frame pointer
+            Architecture::AArch64 => (
+                (0..8).map(|num| format!("x{num}")).collect(),
+                "x0".to_string(),
+                (19..30)
+                    .map(|num| format!("x{num}"))
+                    .chain(["sp".to_string()])
+                    .collect(),
+                ["sp", "x29"].map(str::to_string),
+            ),
+            // the LP64 psABI, with s0 as the frame pointer
+            Architecture::RiscV64 => (
+                (10..18)
+                    .map(|num| riscv::register_name(num).to_string())
+                    .collect(),
+                riscv::register_name(10).to_string(),
+                [2, 3, 4, 8, 9]
+                    .into_iter()
+                    .chain(18..28)
+                    .map(|num| riscv::register_name(num).to_string())
+                    .collect(),
+                ["sp", "s0"].map(str::to_string),
+            ),
+        };
+        let mut abi = Self {
+            architecture,
+            arguments,
+            result,
+            preserved: Vec::new(),
+            stack,
+        };
+        abi.preserved = preserved
+            .iter()
+            .filter_map(|name| Some(abi.whole(name)?.0))
+            .collect();
+        abi
+    }
+
+    /// Returns the name of the whole register `name` is part of, with the size in bytes of
+    /// that part, like `x0` and 4 for `w0`, or `RAX` and 4 for `EAX`. The high byte
+    /// registers of x86 are no part of one, and other names no register.
+    pub fn whole(&self, name: &str) -> Option<(String, usize)> {
+        match self.architecture {
+            Architecture::X86 { .. } => {
+                let reg = get_register_by_name(name)?;
+                if matches!(reg, Register::AH | Register::BH | Register::CH | Register::DH) {
+                    return None;
+                }
+                Some((get_register_name(reg.full_register()), reg.size()))
+            }
+            Architecture::AArch64 => match name.to_lowercase().as_str() {
+                "sp" => Some(("sp".to_string(), 8)),
+                "wsp" => Some(("sp".to_string(), 4)),
+                name => {
+                    let (size, num) = match name.split_at_checked(1)? {
+                        ("x", num) => (8, num),
+                        ("w", num) => (4, num),
+                        _ => return None,
+                    };
+                    let num = num.parse::<u8>().ok().filter(|num| *num < 31)?;
+                    Some((format!("x{num}"), size))
+                }
+            },
+            Architecture::RiscV64 => {
+                let name = name.to_lowercase();
+                (0..32)
+                    .map(riscv::register_name)
+                    .find(|other| *other == name)
+                    .map(|name| (name.to_string(), 8))
+            }
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use std::fmt::{Display, Formatter};
+
+use iced_x86::{Decoder, DecoderOptions, FlowControl, Formatter as _, IntelFormatter, OpKind};
+use object::Object;
+
+use crate::aarch64;
+use crate::riscv;
+
+/// The instruction set a [`crate::memory::memory::Memory`] image is decoded with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Architecture {
+    X86 { bitness: u32 },
+    AArch64,
+    RiscV64,
+}
+
+/// How control leaves an instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    Next,
+    Call,
+    IndirectCall,
+    Branch,
+    ConditionalBranch,
+    IndirectBranch,
+    Return,
+    /// Traps and halts, after which nothing is known to execute.
+    Stop,
+}
+
+impl Architecture {
+    /// Returns the architecture of `file`, if it can be decoded.
+    pub fn for_file(file: &object::File) -> Option<Self> {
+        match file.architecture() {
+            object::Architecture::X86_64 | object::Architecture::X86_64_X32 => {
+                Some(Self::X86 { bitness: 64 })
+            }
+            object::Architecture::I386 => Some(Self::X86 { bitness: 32 }),
+            object::Architecture::Aarch64 => Some(Self::AArch64),
+            object::Architecture::Riscv64 => Some(Self::RiscV64),
+            _ => None,
+        }
+    }
+
+    pub fn bitness(self) -> u32 {
+        match self {
+            Self::X86 { bitness } => bitness,
+            Self::AArch64 | Self::RiscV64 => 64,
+        }
+    }
+
+    /// Decodes the instruction at the start of `code`, which is located at `ip`.
+    pub fn decode(self, code: &[u8], ip: u64) -> Option<Instruction> {
+        match self {
+            Self::X86 { bitness } => {
+                let mut decoder = Decoder::with_ip(bitness, code, ip, DecoderOptions::NONE);
+                let inst = decoder.decode();
+                (!inst.is_invalid()).then_some(Instruction::X86(inst))
+            }
+            Self::AArch64 => {
+                let word = code.get(..aarch64::Instruction::LEN)?;
+                let word = u32::from_le_bytes(word.try_into().unwrap());
+                Some(Instruction::AArch64(aarch64::decode(word, ip)))
+            }
+            Self::RiscV64 => {
+                let parcel = u16::from_le_bytes(code.get(..2)?.try_into().unwrap());
+                let len = riscv::instruction_len(parcel);
+                let mut word = [0; 4];
+                word[..len].copy_from_slice(code.get(..len)?);
+                Some(Instruction::RiscV(riscv::decode(u32::from_le_bytes(word), ip)))
+            }
+        }
+    }
+}
+
+/// A decoded machine instruction of any supported architecture.
+#[derive(Clone, Copy, Debug)]
+pub enum Instruction {
+    X86(iced_x86::Instruction),
+    AArch64(aarch64::Instruction),
+    RiscV(riscv::Instruction),
+}
+
+impl Instruction {
+    pub fn ip(&self) -> u64 {
+        match self {
+            Self::X86(inst) => inst.ip(),
+            Self::AArch64(inst) => inst.address,
+            Self::RiscV(inst) => inst.address,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::X86(inst) => inst.len(),
+            Self::AArch64(_) => aarch64::Instruction::LEN,
+            Self::RiscV(inst) => inst.len,
+        }
+    }
+
+    pub fn next_ip(&self) -> u64 {
+        self.ip() + self.len() as u64
+    }
+
+    pub fn mnemonic(&self) -> String {
+        match self {
+            Self::X86(inst) => format!("{:?}", inst.mnemonic()).to_lowercase(),
+            Self::AArch64(inst) => match inst.opcode {
+                aarch64::Opcode::BCond => format!("b.{}", inst.operands[0]),
+                aarch64::Opcode::Unknown => ".inst".to_string(),
+                opcode => opcode.name(),
+            },
+            Self::RiscV(inst) => inst.opcode.name(),
+        }
+    }
+
+    pub fn flow(&self) -> Flow {
+        match self {
+            // software interrupts such as BIOS or DOS services return like calls, unlike int3
+            Self::X86(inst) if inst.code() == iced_x86::Code::Hlt => Flow::Stop,
+            Self::X86(inst)
+                if matches!(inst.code(), iced_x86::Code::Int_imm8 | iced_x86::Code::Into) =>
+            {
+                Flow::Next
+            }
+            Self::X86(inst) => match inst.flow_control() {
+                FlowControl::Next => Flow::Next,
+                FlowControl::Call => Flow::Call,
+                FlowControl::IndirectCall => Flow::IndirectCall,
+                FlowControl::UnconditionalBranch => Flow::Branch,
+                FlowControl::ConditionalBranch => Flow::ConditionalBranch,
+                FlowControl::IndirectBranch => Flow::IndirectBranch,
+                FlowControl::Return => Flow::Return,
+                FlowControl::Interrupt | FlowControl::XbeginXabortXend | FlowControl::Exception => {
+                    Flow::Stop
+                }
+            },
+            Self::AArch64(inst) => {
+                use aarch64::Opcode::*;
+                match inst.opcode {
+                    B => Flow::Branch,
+                    BCond | Cbz | Cbnz | Tbz | Tbnz => Flow::ConditionalBranch,
+                    Bl => Flow::Call,
+                    Blr => Flow::IndirectCall,
+                    Br => Flow::IndirectBranch,
+                    Ret | Retaa | Retab => Flow::Return,
+                    Udf | Brk | Hlt => Flow::Stop,
+                    _ => Flow::Next,
+                }
+            }
+            Self::RiscV(inst) => {
+                use riscv::Opcode::*;
+                match inst.opcode {
+                    J => Flow::Branch,
+                    Jr => Flow::IndirectBranch,
+                    Ret => Flow::Return,
+                    // only links through ra or t0 are calls, as PLT entries jump with t1
+                    Jal if inst.is_call() => Flow::Call,
+                    Jal => Flow::Branch,
+                    Jalr if inst.is_call() => Flow::IndirectCall,
+                    Jalr => Flow::IndirectBranch,
+                    Unimp | Ebreak => Flow::Stop,
+                    opcode if opcode.is_branch() => Flow::ConditionalBranch,
+                    _ => Flow::Next,
+                }
+            }
+        }
+    }
+
+    /// Returns the target of a direct branch or call.
+    pub fn branch_target(&self) -> Option<u64> {
+        match self {
+            Self::X86(inst) => match inst.op0_kind() {
+                OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64 => {
+                    Some(inst.near_branch_target())
+                }
+                _ => None,
+            },
+            Self::AArch64(inst) => inst.branch_target(),
+            Self::RiscV(inst) => inst.branch_target(),
+        }
+    }
+
+    /// Returns the slot an indirect branch or call reads its target from when its address
+    /// is constant, like the GOT or IAT slot of `call [rip + slot]`.
+    pub fn slot(&self) -> Option<u64> {
+        match self {
+            Self::X86(inst) if inst.op0_kind() == OpKind::Memory => {
+                if inst.is_ip_rel_memory_operand() {
+                    Some(inst.ip_rel_memory_address())
+                } else if inst.memory_base() == iced_x86::Register::None
+                    && inst.memory_index() == iced_x86::Register::None
+                {
+                    Some(inst.memory_displacement64())
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the names of the general-purpose registers this AArch64 or RISC-V instruction
+    /// writes, a written-back base register included, as the lifted code reads them. Those
+    /// of x86 instructions are told by iced.
+    pub fn written_registers(&self) -> Vec<String> {
+        match self {
+            Self::X86(_) => Vec::new(),
+            Self::AArch64(inst) => {
+                let written_back = inst.operands.iter().filter_map(|operand| match operand {
+                    aarch64::Operand::Mem(mem) if mem.mode != aarch64::IndexMode::Offset => {
+                        Some(mem.base)
+                    }
+                    _ => None,
+                });
+                inst.destinations()
+                    .into_iter()
+                    .chain(written_back)
+                    .map(|reg| reg.to_string())
+                    .collect()
+            }
+            Self::RiscV(inst) => inst
+                .destination()
+                .map(|reg| riscv::register_name(reg).to_string())
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    pub fn as_x86(&self) -> Option<&iced_x86::Instruction> {
+        match self {
+            Self::X86(inst) => Some(inst),
+            Self::AArch64(_) | Self::RiscV(_) => None,
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::X86(inst) => {
+                let mut text = String::new();
+                IntelFormatter::new().format(inst, &mut text);
+                f.write_str(&text)
+            }
+            Self::AArch64(inst) => inst.fmt(f),
+            Self::RiscV(inst) => inst.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced_x86::{MemorySizeOptions, NumberBase};
+
+    use super::*;
+
+    /// The functions of `assets/test32`, disassembled by `llvm-objdump --x86-asm-syntax=intel`
+    /// with the branch targets in decimal.
+    const LLVM_32: [(u64, &str, &str); 57] = [
+        (0x8049020, "55", "push ebp"),
+        (0x8049021, "89e5", "mov ebp, esp"),
+        (0x8049023, "b800a00408", "mov eax, 134520832"),
+        (0x8049028, "5d", "pop ebp"),
+        (0x8049029, "c3", "ret"),
+        (0x804902a, "55", "push ebp"),
+        (0x804902b, "89e5", "mov ebp, esp"),
+        (0x804902d, "83ec08", "sub esp, 8"),
+        (0x8049030, "83ec0c", "sub esp, 12"),
+        (0x8049033, "ff7508", "push dword ptr [ebp + 8]"),
+        (0x8049036, "e8d5ffffff", "call 134516752"),
+        (0x804903b, "83c410", "add esp, 16"),
+        (0x804903e, "c9", "leave"),
+        (0x804903f, "c3", "ret"),
+        (0x8049040, "55", "push ebp"),
+        (0x8049041, "89e5", "mov ebp, esp"),
+        (0x8049043, "8b5508", "mov edx, dword ptr [ebp + 8]"),
+        (0x8049046, "8b450c", "mov eax, dword ptr [ebp + 12]"),
+        (0x8049049, "01d0", "add eax, edx"),
+        (0x804904b, "5d", "pop ebp"),
+        (0x804904c, "c20800", "ret 8"),
+        (0x804904f, "55", "push ebp"),
+        (0x8049050, "89e5", "mov ebp, esp"),
+        (0x8049052, "83ec08", "sub esp, 8"),
+        (0x8049055, "894dfc", "mov dword ptr [ebp - 4], ecx"),
+        (0x8049058, "8955f8", "mov dword ptr [ebp - 8], edx"),
+        (0x804905b, "8b45fc", "mov eax, dword ptr [ebp - 4]"),
+        (0x804905e, "2b45f8", "sub eax, dword ptr [ebp - 8]"),
+        (0x8049061, "2b4508", "sub eax, dword ptr [ebp + 8]"),
+        (0x8049064, "c9", "leave"),
+        (0x8049065, "c20400", "ret 4"),
+        (0x8049068, "8d4c2404", "lea ecx, [esp + 4]"),
+        (0x804906c, "83e4f0", "and esp, -16"),
+        (0x804906f, "ff71fc", "push dword ptr [ecx - 4]"),
+        (0x8049072, "55", "push ebp"),
+        (0x8049073, "89e5", "mov ebp, esp"),
+        (0x8049075, "51", "push ecx"),
+        (0x8049076, "83ec04", "sub esp, 4"),
+        (0x8049079, "83ec0c", "sub esp, 12"),
+        (0x804907c, "680aa00408", "push 134520842"),
+        (0x8049081, "e8a4ffffff", "call 134516778"),
+        (0x8049086, "83c410", "add esp, 16"),
+        (0x8049089, "83ec0c", "sub esp, 12"),
+        (0x804908c, "6a01", "push 1"),
+        (0x804908e, "ba02000000", "mov edx, 2"),
+        (0x8049093, "b905000000", "mov ecx, 5"),
+        (0x8049098, "e8b2ffffff", "call 134516815"),
+        (0x804909d, "83c40c", "add esp, 12"),
+        (0x80490a0, "83ec08", "sub esp, 8"),
+        (0x80490a3, "6a02", "push 2"),
+        (0x80490a5, "6a01", "push 1"),
+        (0x80490a7, "e894ffffff", "call 134516800"),
+        (0x80490ac, "83c408", "add esp, 8"),
+        (0x80490af, "8b4dfc", "mov ecx, dword ptr [ebp - 4]"),
+        (0x80490b2, "c9", "leave"),
+        (0x80490b3, "8d61fc", "lea esp, [ecx - 4]"),
+        (0x80490b6, "c3", "ret"),
+    ];
+
+    /// The code of `assets/test_boot.bin` at 0x7C00, disassembled the same way for
+    /// `i386-unknown-unknown-code16`.
+    const LLVM_16: [(u64, &str, &str); 22] = [
+        (0x7c00, "fa", "cli"),
+        (0x7c01, "31c0", "xor ax, ax"),
+        (0x7c03, "8ed8", "mov ds, ax"),
+        (0x7c05, "8ed0", "mov ss, ax"),
+        (0x7c07, "bc007c", "mov sp, 31744"),
+        (0x7c0a, "fb", "sti"),
+        (0x7c0b, "be317c", "mov si, 31793"),
+        (0x7c0e, "e81100", "call 31778"),
+        (0x7c11, "b84000", "mov ax, 64"),
+        (0x7c14, "8ec0", "mov es, ax"),
+        (0x7c16, "268b066c00", "mov ax, word ptr es:[108]"),
+        (0x7c1b, "8906007e", "mov word ptr [32256], ax"),
+        (0x7c1f, "f4", "hlt"),
+        (0x7c20, "ebfd", "jmp 31775"),
+        // llvm also lists the implicit `al` destination
+        (0x7c22, "ac", "lodsb byte ptr [si]"),
+        (0x7c23, "84c0", "test al, al"),
+        (0x7c25, "7409", "je 31792"),
+        (0x7c27, "b40e", "mov ah, 14"),
+        (0x7c29, "bb0700", "mov bx, 7"),
+        (0x7c2c, "cd10", "int 16"),
+        (0x7c2e, "ebf2", "jmp 31778"),
+        (0x7c30, "c3", "ret"),
+    ];
+
+    fn check(bitness: u32, expected: &[(u64, &str, &str)]) {
+        let mut formatter = IntelFormatter::new();
+        let options = formatter.options_mut();
+        options.set_number_base(NumberBase::Decimal);
+        options.set_space_after_operand_separator(true);
+        options.set_space_between_memory_add_operators(true);
+        options.set_memory_size_options(MemorySizeOptions::Always);
+        options.set_show_branch_size(false);
+        options.set_signed_immediate_operands(true);
+        let architecture = Architecture::X86 { bitness };
+        for &(address, bytes, text) in expected {
+            let code: Vec<u8> = (0..bytes.len())
+                .step_by(2)
+                .map(|at| u8::from_str_radix(&bytes[at..at + 2], 16).unwrap())
+                .collect();
+            let inst = architecture.decode(&code, address).unwrap();
+            assert_eq!(inst.len(), code.len(), "{bytes} at {address:#x}");
+            let mut ours = String::new();
+            formatter.format(inst.as_x86().unwrap(), &mut ours);
+            assert_eq!(ours, text, "{bytes} at {address:#x}");
+        }
+    }
+
+    #[test]
+    fn llvm_disassembly_32() {
+        check(32, &LLVM_32);
+    }
+
+    #[test]
+    fn llvm_disassembly_16() {
+        check(16, &LLVM_16);
+    }
+}
@@ -2,9 +2,15 @@ use std::fmt::Display;
 use std::fmt::Write;
 
 use iced_x86::Instruction;
+use iced_x86::MemorySize;
 use iced_x86::OpKind;
 use iced_x86::Register;
+use serde::{Serialize, Serializer};
 
+use crate::arch;
+use crate::symbols::SymbolTable;
+
+#[derive(Serialize)]
 pub struct Expr {
     pub id: u32,
     pub kind: ExprKind,
@@ -17,20 +23,65 @@ impl Display for Expr {
     }
 }
 
+impl Expr {
+    /// Returns the call a statement makes, which a tail call makes in the value it returns.
+    pub fn call_site(&self) -> &Expr {
+        match &self.kind {
+            ExprKind::Ret(Some(value)) => value,
+            _ => self,
+        }
+    }
+
+    pub fn call_site_mut(&mut self) -> &mut Expr {
+        match self.kind {
+            ExprKind::Ret(Some(ref mut value)) => value,
+            _ => self,
+        }
+    }
+
+    /// Shows this expression with the names `symbols` gives to the functions it calls.
+    pub fn named<'a>(&'a self, symbols: &'a SymbolTable) -> Named<'a> {
+        Named {
+            expr: self,
+            symbols: Some(symbols),
+        }
+    }
+}
+
+/// An expression shown with known function names in place of `FUN_` addresses.
+pub struct Named<'a> {
+    expr: &'a Expr,
+    symbols: Option<&'a SymbolTable>,
+}
+
+impl Display for Named<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.expr.kind.write(f, self.symbols)
+    }
+}
+
 #[non_exhaustive]
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ExprKind {
     // Array(ThinVec<P<Expr>>),
     // ConstBlock(AnonConst),
-    Call(u64, Vec<Box<Expr>>),
-    // MethodCall(Box<MethodCall>),
-    // Tup(ThinVec<P<Expr>>),
+    Call(u64, Vec<Expr>),
+    /// A call through a slot of the vtable of a C++ object, the first argument, shown by
+    /// the name of the virtual function.
+    MethodCall(String, Vec<Expr>),
+    /// Several values assigned at once, like the flags set by a comparison.
+    Tup(Vec<Expr>),
+    /// A call to a C library function or compiler intrinsic standing for an operation
+    /// with no operator of its own, like `sqrt` or `_mm_add_ps`.
+    Intrinsic(String, Vec<Expr>),
     Binary(BinOpKind, Box<Expr>, Box<Expr>),
-    // Unary(UnOp, P<Expr>),
+    Unary(UnOp, Box<Expr>),
     Lit(Lit),
-    // Cast(P<Expr>, P<Ty>),
+    Cast(Box<Expr>, Ty),
     // Type(P<Expr>, P<Ty>),
     // Let(P<Pat>, P<Expr>, Span, Recovered),
-    // If(P<Expr>, P<Block>, Option<P<Expr>>),
+    If(Box<Expr>, Vec<Expr>),
     // While(P<Expr>, P<Block>, Option<Label>),
     // ForLoop {
     //     pat: P<Pat>,
@@ -39,7 +90,7 @@ pub enum ExprKind {
     //     label: Option<Label>,
     //     kind: ForLoopKind,
     // },
-    // Loop(P<Block>, Option<Label>, Span),
+    Loop(Vec<Expr>),
     // Match(P<Expr>, ThinVec<Arm>, MatchKind),
     // Closure(Box<Closure>),
     // Block(P<Block>, Option<Label>),
@@ -56,7 +107,10 @@ pub enum ExprKind {
     // AddrOf(BorrowKind, Mutability, P<Expr>),
     // Break(Option<Label>, Option<P<Expr>>),
     // Continue(Option<Label>),
-    // Ret(Option<P<Expr>>),
+    Goto(u64),
+    /// A jump through a table: the value of the index, and the target of each case.
+    Switch(Box<Expr>, Vec<(u64, u64)>),
+    Ret(Option<Box<Expr>>),
     // InlineAsm(P<InlineAsm>),
     // OffsetOf(P<Ty>, P<[Ident]>),
     // MacCall(P<MacCall>),
@@ -70,26 +124,98 @@ pub enum ExprKind {
     // IncludedBytes(Lrc<[u8]>),
     // FormatArgs(P<FormatArgs>),
     // Err(ErrorGuaranteed),
-    Unparsed(iced_x86::Instruction), // FIXME
+    #[serde(serialize_with = "serialize_instruction")]
+    Unparsed(arch::Instruction), // FIXME
+}
+
+/// Serializes an unparsed instruction as its disassembly, in Intel syntax for x86.
+fn serialize_instruction<S: Serializer>(
+    inst: &arch::Instruction,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&inst.to_string())
 }
 
 impl Display for ExprKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, None)
+    }
+}
+
+impl ExprKind {
+    /// Writes the expression, naming the functions called after `symbols` when given.
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, symbols: Option<&SymbolTable>) -> std::fmt::Result {
+        let show = |expr| Named { expr, symbols };
         match self {
             ExprKind::Binary(bin_op_kind, lhs, rhs) => {
-                f.write_fmt(format_args!("{} {} {}", &lhs, &bin_op_kind, &rhs))
+                write_operand(f, lhs, bin_op_kind.precedence(), symbols)?;
+                f.write_fmt(format_args!(" {} ", &bin_op_kind))?;
+                write_operand(f, rhs, bin_op_kind.precedence() + 1, symbols)
             }
             ExprKind::Lit(lit) => f.write_fmt(format_args!("{lit}")),
-            ExprKind::Assign(lhs, rhs) => f.write_fmt(format_args!("{lhs} = {rhs}")),
-            ExprKind::Call(addr, _args) => f.write_fmt(format_args!("FUN_{:X}()", &addr)),
-            ExprKind::Unparsed(code) => {
+            ExprKind::Assign(lhs, rhs) => f.write_fmt(format_args!("{} = {}", show(lhs), show(rhs))),
+            ExprKind::Call(addr, args) => {
+                match symbols {
+                    Some(symbols) => f.write_fmt(format_args!("{}(", symbols.display_name(*addr)))?,
+                    None => f.write_fmt(format_args!("FUN_{:X}(", &addr))?,
+                }
+                write_list(f, args, symbols)?;
+                f.write_str(")")
+            }
+            ExprKind::MethodCall(method, args) => {
+                match args.first() {
+                    Some(object) => f.write_fmt(format_args!("{}->{method}(", show(object)))?,
+                    None => f.write_fmt(format_args!("{method}("))?,
+                }
+                write_list(f, args.get(1..).unwrap_or_default(), symbols)?;
+                f.write_str(")")
+            }
+            ExprKind::Tup(items) => {
+                f.write_str("(")?;
+                write_list(f, items, symbols)?;
+                f.write_str(")")
+            }
+            ExprKind::Intrinsic(name, args) => {
+                f.write_fmt(format_args!("{name}("))?;
+                write_list(f, args, symbols)?;
+                f.write_str(")")
+            }
+            ExprKind::Unary(op, expr) => f.write_fmt(format_args!("{op}({})", show(expr))),
+            ExprKind::Cast(expr, ty) => f.write_fmt(format_args!("({ty})({})", show(expr))),
+            ExprKind::Loop(body) => {
+                f.write_str("do {")?;
+                for expr in body {
+                    f.write_fmt(format_args!(" {};", show(expr)))?;
+                }
+                f.write_str(" } while (true)")
+            }
+            ExprKind::If(cond, body) => {
+                f.write_fmt(format_args!("if ({}) {{", show(cond)))?;
+                for expr in body {
+                    f.write_fmt(format_args!(" {};", show(expr)))?;
+                }
+                f.write_str(" }")
+            }
+            ExprKind::Goto(addr) => f.write_fmt(format_args!("goto LAB_{:X}", &addr)),
+            ExprKind::Switch(index, cases) => {
+                f.write_fmt(format_args!("switch ({}) {{", show(index)))?;
+                for (value, target) in cases {
+                    f.write_fmt(format_args!(" case 0x{value:X}: goto LAB_{target:X};"))?;
+                }
+                f.write_str(" }")
+            }
+            ExprKind::Ret(None) => f.write_str("return"),
+            ExprKind::Ret(Some(expr)) => f.write_fmt(format_args!("return {}", show(expr))),
+            ExprKind::Unparsed(arch::Instruction::AArch64(inst)) => f.write_fmt(format_args!("{inst}")),
+            ExprKind::Unparsed(arch::Instruction::RiscV(inst)) => f.write_fmt(format_args!("{inst}")),
+            ExprKind::Unparsed(arch::Instruction::X86(code)) => {
                 f.write_fmt(format_args!("{:#?}", &code.code())).unwrap();
                 match code.op0_kind() {
-                    iced_x86::OpKind::Register => {
-                        if code.op0_register() != iced_x86::Register::None {
-                            f.write_fmt(format_args!(" {:#?}", code.op0_register()))
-                                .unwrap()
-                        }
+                    iced_x86::OpKind::Register
+                        if code.op0_register() != iced_x86::Register::None =>
+                    {
+                        f.write_fmt(format_args!(" {:#?}", code.op0_register()))
+                            .unwrap()
                     }
                     iced_x86::OpKind::Memory => f
                         .write_fmt(format_args!(" 0x{:X}", &code.memory_displacement64()))
@@ -100,11 +226,11 @@ impl Display for ExprKind {
                     _ => {}
                 }
                 match code.op1_kind() {
-                    iced_x86::OpKind::Register => {
-                        if code.op1_register() != iced_x86::Register::None {
-                            f.write_fmt(format_args!(" {:#?}", &code.op1_register()))
-                                .unwrap()
-                        }
+                    iced_x86::OpKind::Register
+                        if code.op1_register() != iced_x86::Register::None =>
+                    {
+                        f.write_fmt(format_args!(" {:#?}", &code.op1_register()))
+                            .unwrap()
                     }
                     iced_x86::OpKind::Memory => f
                         .write_fmt(format_args!(" 0x{:X}", &code.memory_displacement64()))
@@ -116,11 +242,11 @@ impl Display for ExprKind {
                 }
 
                 match code.op2_kind() {
-                    iced_x86::OpKind::Register => {
-                        if code.op2_register() != iced_x86::Register::None {
-                            f.write_fmt(format_args!(" {:#?}", &code.op2_register()))
-                                .unwrap()
-                        }
+                    iced_x86::OpKind::Register
+                        if code.op2_register() != iced_x86::Register::None =>
+                    {
+                        f.write_fmt(format_args!(" {:#?}", &code.op2_register()))
+                            .unwrap()
                     }
                     iced_x86::OpKind::Memory => f
                         .write_fmt(format_args!(" 0x{:X}", &code.memory_displacement64()))
@@ -132,11 +258,11 @@ impl Display for ExprKind {
                 }
 
                 match code.op3_kind() {
-                    iced_x86::OpKind::Register => {
-                        if code.op3_register() != iced_x86::Register::None {
-                            f.write_fmt(format_args!(" {:#?}", &code.op3_register()))
-                                .unwrap()
-                        }
+                    iced_x86::OpKind::Register
+                        if code.op3_register() != iced_x86::Register::None =>
+                    {
+                        f.write_fmt(format_args!(" {:#?}", &code.op3_register()))
+                            .unwrap()
                     }
                     iced_x86::OpKind::Memory => f
                         .write_fmt(format_args!(" 0x{:X}", &code.memory_displacement64()))
@@ -152,8 +278,42 @@ impl Display for ExprKind {
     }
 }
 
+/// Writes `exprs` separated by commas.
+fn write_list(
+    f: &mut std::fmt::Formatter<'_>,
+    exprs: &[Expr],
+    symbols: Option<&SymbolTable>,
+) -> std::fmt::Result {
+    for (i, expr) in exprs.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        expr.kind.write(f, symbols)?;
+    }
+    Ok(())
+}
+
+/// Writes an operand of a binary operator, in parentheses when it binds less tightly than
+/// `precedence`.
+fn write_operand(
+    f: &mut std::fmt::Formatter<'_>,
+    expr: &Expr,
+    precedence: u32,
+    symbols: Option<&SymbolTable>,
+) -> std::fmt::Result {
+    match &expr.kind {
+        ExprKind::Binary(op, ..) if op.precedence() < precedence => {
+            f.write_str("(")?;
+            expr.kind.write(f, symbols)?;
+            f.write_str(")")
+        }
+        _ => expr.kind.write(f, symbols),
+    }
+}
+
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum BinOpKind {
     Add,
     Sub,
@@ -175,6 +335,22 @@ pub enum BinOpKind {
     Gt,
 }
 
+impl BinOpKind {
+    /// Returns how tightly the operator binds in C, higher binding tighter.
+    pub fn precedence(&self) -> u32 {
+        match self {
+            BinOpKind::Mul | BinOpKind::Div | BinOpKind::Rem => 9,
+            BinOpKind::Add | BinOpKind::Sub => 8,
+            BinOpKind::Shl | BinOpKind::Shr => 7,
+            BinOpKind::Lt | BinOpKind::Le | BinOpKind::Gt | BinOpKind::Ge => 6,
+            BinOpKind::Eq | BinOpKind::Ne => 5,
+            BinOpKind::And | BinOpKind::BitAnd => 4,
+            BinOpKind::BitXor => 3,
+            BinOpKind::Or | BinOpKind::BitOr => 2,
+        }
+    }
+}
+
 impl Display for BinOpKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -201,13 +377,112 @@ impl Display for BinOpKind {
 }
 
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnOp {
+    Deref,
+    /// Bitwise complement, as computed by `not`.
+    Not,
+    /// Logical negation of a condition.
+    LogicalNot,
+    Neg,
+}
+
+impl Display for UnOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnOp::Deref => f.write_str("*"),
+            UnOp::Not => f.write_str("~"),
+            UnOp::LogicalNot => f.write_str("!"),
+            UnOp::Neg => f.write_str("-"),
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ty {
+    Void,
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    Ptr(Box<Ty>),
+    /// An SSE or AVX register of the given number of bits, holding elements of a type.
+    Vector(u32, Box<Ty>),
+}
+
+impl Ty {
+    pub fn from_memory_size(size: MemorySize) -> Ty {
+        match size {
+            MemorySize::UInt8 => Ty::U8,
+            MemorySize::UInt16 => Ty::U16,
+            MemorySize::UInt32 => Ty::U32,
+            MemorySize::UInt64 => Ty::U64,
+            MemorySize::Int8 => Ty::I8,
+            MemorySize::Int16 => Ty::I16,
+            MemorySize::Int32 => Ty::I32,
+            MemorySize::Int64 => Ty::I64,
+            MemorySize::Float32 => Ty::F32,
+            MemorySize::Float64 => Ty::F64,
+            _ if size.size() >= 16 => Ty::Vector(
+                size.size() as u32 * 8,
+                Box::new(match size.element_type() {
+                    element if element == size => Ty::I64,
+                    element => Ty::from_memory_size(element),
+                }),
+            ),
+            _ => Ty::Void,
+        }
+    }
+}
+
+impl Display for Ty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Ty::Void => f.write_str("void"),
+            Ty::Bool => f.write_str("bool"),
+            Ty::U8 => f.write_str("uint8_t"),
+            Ty::U16 => f.write_str("uint16_t"),
+            Ty::U32 => f.write_str("uint32_t"),
+            Ty::U64 => f.write_str("uint64_t"),
+            Ty::I8 => f.write_str("int8_t"),
+            Ty::I16 => f.write_str("int16_t"),
+            Ty::I32 => f.write_str("int32_t"),
+            Ty::I64 => f.write_str("int64_t"),
+            Ty::F32 => f.write_str("float"),
+            Ty::F64 => f.write_str("double"),
+            Ty::Ptr(ty) => f.write_fmt(format_args!("{ty} *")),
+            // the types of the Intel intrinsics, `__m128i` holding any integers
+            Ty::Vector(bits, element) => match **element {
+                Ty::F32 => f.write_fmt(format_args!("__m{bits}")),
+                Ty::F64 => f.write_fmt(format_args!("__m{bits}d")),
+                _ => f.write_fmt(format_args!("__m{bits}i")),
+            },
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Lit {
     StillUnknown,
     Symbol(Symbol),
     Bool(bool),
     U32(u32),
+    U64(u64),
     I64(i64),
+    F32(f32),
+    F64(f64),
     Str(String),
 }
 
@@ -218,16 +493,34 @@ impl Display for Lit {
             Lit::Symbol(arg0) => f.write_fmt(format_args!("{}", arg0)),
             Lit::Bool(arg0) => f.write_fmt(format_args!("{}", arg0)),
             Lit::U32(arg0) => f.write_fmt(format_args!("0x{:X}", arg0)),
-            Lit::Str(arg0) => f.write_fmt(format_args!("{}", arg0)),
+            Lit::Str(arg0) => f.write_fmt(format_args!("{:?}", arg0)),
+            Lit::U64(arg0) => f.write_fmt(format_args!("0x{:X}", arg0)),
             Lit::I64(arg0) => f.write_fmt(format_args!("0x{:X}", arg0)),
+            Lit::F32(arg0) if arg0.is_finite() => f.write_fmt(format_args!("{:?}f", arg0)),
+            Lit::F32(arg0) => f.write_str(&c_float(*arg0 as f64)),
+            Lit::F64(arg0) => f.write_str(&c_float(*arg0)),
         }
     }
 }
 
-#[derive(Clone, Debug)]
+/// Spells a floating-point value in C, with the `math.h` macros for infinities and NaN.
+fn c_float(value: f64) -> String {
+    if value.is_nan() {
+        "NAN".to_string()
+    } else if value.is_infinite() {
+        format!("{}INFINITY", if value < 0.0 { "-" } else { "" })
+    } else {
+        format!("{value:?}")
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Symbol {
     Var(usize),
     Reg(String),
+    /// A variable named by the debug info.
+    Local(String),
 }
 
 impl Display for Symbol {
@@ -235,6 +528,7 @@ impl Display for Symbol {
         match self {
             Symbol::Var(idx) => f.write_fmt(format_args!("#{}", &idx)),
             Symbol::Reg(name) => f.write_fmt(format_args!("@{}", &name.to_uppercase())),
+            Symbol::Local(name) => f.write_str(name),
         }
     }
 }
@@ -258,31 +552,33 @@ pub fn get_register_name(reg: Register) -> String {
     s
 }
 
+/// Returns the x86 register `get_register_name` prints as `name`, in any case.
+pub fn get_register_by_name(name: &str) -> Option<Register> {
+    Register::values().find(|reg| get_register_name(*reg).eq_ignore_ascii_case(name))
+}
+
 pub fn get_new_symbol_from_inst(
     symbols: &mut Vec<Symbol>,
     inst: Instruction,
     idx: usize,
 ) -> Lit {
     match idx {
-        0 => match inst.op0_kind() {
-            OpKind::Register => return Lit::Symbol(get_new_reg_symbol(symbols, inst.op0_register())),
-            _ => {}
-        },
+        0 if inst.op0_kind() == OpKind::Register => {
+            return Lit::Symbol(get_new_reg_symbol(symbols, inst.op0_register()))
+        }
         1 => match inst.op1_kind() {
             OpKind::Register => return Lit::Symbol(get_new_reg_symbol(symbols, inst.op1_register())),
             OpKind::Immediate8to64 => return Lit::I64(inst.immediate8to64()),
             _ => {}
         },
-        2 => match inst.op2_kind() {
-            OpKind::Register => return Lit::Symbol(get_new_reg_symbol(symbols, inst.op2_register())),
-            _ => {}
-        },
-        3 => match inst.op3_kind() {
-            OpKind::Register => return Lit::Symbol(get_new_reg_symbol(symbols, inst.op3_register())),
-            _ => {}
-        },
+        2 if inst.op2_kind() == OpKind::Register => {
+            return Lit::Symbol(get_new_reg_symbol(symbols, inst.op2_register()))
+        }
+        3 if inst.op3_kind() == OpKind::Register => {
+            return Lit::Symbol(get_new_reg_symbol(symbols, inst.op3_register()))
+        }
         _ => {}
     }
     // println!("{:#?}", inst);
-    return Lit::Symbol(get_new_var_symbol(symbols));
+    Lit::Symbol(get_new_var_symbol(symbols))
 }
@@ -0,0 +1,143 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::function::Function;
+
+/// How a function refers to another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CallKind {
+    /// A direct call, or a call through the GOT or IAT slot of an import.
+    Call,
+    /// The address of the function is taken, like a callback passed to `qsort`.
+    Address,
+}
+
+/// The calls and function pointers between the lifted functions, and to the imports and
+/// functions they reference which were not lifted.
+#[derive(Default)]
+pub struct CallGraph {
+    functions: BTreeSet<u64>,
+    edges: BTreeMap<u64, BTreeMap<u64, CallKind>>,
+}
+
+impl CallGraph {
+    pub fn build(functions: &[Function]) -> Self {
+        let mut graph = Self::default();
+        for function in functions {
+            graph.functions.insert(function.address);
+            let edges = graph.edges.entry(function.address).or_default();
+            for callee in function.callees() {
+                edges.insert(callee, CallKind::Call);
+            }
+            for target in function.code_references() {
+                edges.entry(target).or_insert(CallKind::Address);
+            }
+        }
+        graph
+    }
+
+    /// Returns the functions and imports referenced by the function at `addr`, in address
+    /// order.
+    pub fn callees(&self, addr: u64) -> impl Iterator<Item = (u64, CallKind)> + '_ {
+        self.edges
+            .get(&addr)
+            .into_iter()
+            .flat_map(|edges| edges.iter().map(|(to, kind)| (*to, *kind)))
+    }
+
+    /// Returns `true` if the functions of `scc`, a strongly connected component, call
+    /// themselves or each other.
+    pub fn is_recursive(&self, scc: &[u64]) -> bool {
+        scc.len() > 1
+            || scc
+                .iter()
+                .any(|addr| self.callees(*addr).any(|(to, _)| to == *addr))
+    }
+
+    /// Returns the strongly connected components of the lifted functions, callees before
+    /// their callers, found with Tarjan's algorithm. Each component is sorted by address.
+    pub fn bottom_up(&self) -> Vec<Vec<u64>> {
+        struct Node {
+            index: usize,
+            lowlink: usize,
+            on_stack: bool,
+        }
+
+        let mut nodes = BTreeMap::<u64, Node>::new();
+        let mut stack = Vec::<u64>::new();
+        let mut sccs = Vec::new();
+
+        for &root in self.functions.iter() {
+            if nodes.contains_key(&root) {
+                continue;
+            }
+            // the function being visited and the callees left to visit, in place of recursion
+            let mut path = vec![(root, self.successors(root))];
+            let index = nodes.len();
+            nodes.insert(
+                root,
+                Node {
+                    index,
+                    lowlink: index,
+                    on_stack: true,
+                },
+            );
+            stack.push(root);
+
+            while let Some((addr, successors)) = path.last_mut() {
+                let addr = *addr;
+                if let Some(next) = successors.pop() {
+                    match nodes.get(&next) {
+                        None => {
+                            let index = nodes.len();
+                            nodes.insert(
+                                next,
+                                Node {
+                                    index,
+                                    lowlink: index,
+                                    on_stack: true,
+                                },
+                            );
+                            stack.push(next);
+                            path.push((next, self.successors(next)));
+                        }
+                        Some(node) if node.on_stack => {
+                            let index = node.index;
+                            let node = nodes.get_mut(&addr).unwrap();
+                            node.lowlink = node.lowlink.min(index);
+                        }
+                        Some(_) => {}
+                    }
+                    continue;
+                }
+
+                path.pop();
+                let lowlink = nodes[&addr].lowlink;
+                if let Some((caller, _)) = path.last() {
+                    let caller = nodes.get_mut(caller).unwrap();
+                    caller.lowlink = caller.lowlink.min(lowlink);
+                }
+                if lowlink == nodes[&addr].index {
+                    let mut scc = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        nodes.get_mut(&member).unwrap().on_stack = false;
+                        scc.push(member);
+                        if member == addr {
+                            break;
+                        }
+                    }
+                    scc.sort_unstable();
+                    sccs.push(scc);
+                }
+            }
+        }
+        sccs
+    }
+
+    /// Returns the lifted functions referenced by the function at `addr`.
+    fn successors(&self, addr: u64) -> Vec<u64> {
+        self.callees(addr)
+            .map(|(to, _)| to)
+            .filter(|to| self.functions.contains(to))
+            .collect()
+    }
+}
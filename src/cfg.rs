@@ -0,0 +1,260 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::arch::{Flow, Instruction};
+use crate::ast::Expr;
+use crate::memory::memory::Memory;
+use crate::vsa::{self, JumpTable};
+
+/// Jump tables are followed this many times into the code they lead to, at most.
+const MAX_ROUNDS: usize = 16;
+
+pub struct BasicBlock {
+    pub start: u64,
+    pub end: u64,
+    pub instructions: Vec<Instruction>,
+    pub statements: Vec<Expr>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    Jump,
+    True,
+    False,
+    Fallthrough,
+    /// To one of the targets of a jump table.
+    Switch,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Edge {
+    pub from: u64,
+    pub to: u64,
+    pub kind: EdgeKind,
+}
+
+#[derive(Default)]
+pub struct Cfg {
+    pub blocks: BTreeMap<u64, BasicBlock>,
+    pub edges: Vec<Edge>,
+    /// The jump tables resolved, by address of their jump.
+    pub tables: BTreeMap<u64, JumpTable>,
+    /// The targets of the calls through a register or a pointer, by address of the call.
+    pub calls: BTreeMap<u64, Vec<u64>>,
+    /// The jumps to other functions, which return what their target returns.
+    pub tail_calls: BTreeSet<u64>,
+}
+
+/// Returns `true` if no instruction may follow `inst` in the same block, including calls to
+/// the functions in `noreturn`.
+pub fn is_terminator(inst: &Instruction, noreturn: &BTreeSet<u64>) -> bool {
+    match inst.flow() {
+        Flow::Next => false,
+        Flow::Call | Flow::IndirectCall => inst
+            .branch_target()
+            .or(inst.slot())
+            .is_some_and(|target| noreturn.contains(&target)),
+        _ => true,
+    }
+}
+
+impl Cfg {
+    /// Discovers the blocks reachable from `entry` by recursive descent. When the end of
+    /// the function is known, code is not followed past it, and neither is a call to one
+    /// of the functions in `noreturn`. Jumps out of the function or to one of the other
+    /// functions in `starts` are tail calls, and are not followed either.
+    ///
+    /// The jump tables found are followed in turn, until no new one turns up in the code
+    /// they lead to, and the calls through a register or a pointer are resolved last.
+    pub fn build(
+        memory: &Memory,
+        entry: u64,
+        end: Option<u64>,
+        noreturn: &BTreeSet<u64>,
+        starts: &BTreeSet<u64>,
+    ) -> Self {
+        let bounds = Bounds { entry, end, starts };
+        let mut cfg = Self::decode(memory, &bounds, noreturn, BTreeMap::new());
+        for _ in 0..MAX_ROUNDS {
+            let (tables, calls) = vsa::resolve(&cfg, entry, end, memory);
+            if tables == cfg.tables {
+                cfg.calls = calls;
+                break;
+            }
+            cfg = Self::decode(memory, &bounds, noreturn, tables);
+        }
+        cfg
+    }
+
+    fn decode(
+        memory: &Memory,
+        bounds: &Bounds,
+        noreturn: &BTreeSet<u64>,
+        tables: BTreeMap<u64, JumpTable>,
+    ) -> Self {
+        let Bounds { entry, end, .. } = *bounds;
+        let mut instructions = BTreeMap::<u64, Instruction>::new();
+        let mut leaders = BTreeSet::from([entry]);
+        let mut worklist = vec![entry];
+        let mut tail_calls = BTreeSet::new();
+
+        while let Some(start) = worklist.pop() {
+            if instructions.contains_key(&start) {
+                continue;
+            }
+
+            let mut ip = start;
+            while !instructions.contains_key(&ip) && end.is_none_or(|end| ip < end) {
+                let Some(inst) = memory
+                    .get(ip)
+                    .and_then(|code| memory.architecture().decode(code, ip))
+                else {
+                    break;
+                };
+                instructions.insert(ip, inst);
+                ip = inst.next_ip();
+
+                match inst.flow() {
+                    Flow::Branch => match inst.branch_target() {
+                        Some(target) if bounds.is_outside(target) => {
+                            tail_calls.insert(inst.ip());
+                        }
+                        Some(target) => {
+                            leaders.insert(target);
+                            worklist.push(target);
+                        }
+                        None => {}
+                    },
+                    Flow::ConditionalBranch => {
+                        if let Some(target) = inst.branch_target() {
+                            leaders.insert(target);
+                            worklist.push(target);
+                        }
+                        leaders.insert(inst.next_ip());
+                        worklist.push(inst.next_ip());
+                    }
+                    Flow::IndirectBranch => {
+                        let targets = tables.get(&inst.ip()).map(JumpTable::targets);
+                        for target in targets.unwrap_or_default() {
+                            leaders.insert(target);
+                            worklist.push(target);
+                        }
+                    }
+                    _ => {}
+                }
+
+                if is_terminator(&inst, noreturn) {
+                    break;
+                }
+            }
+        }
+
+        let mut cfg = Cfg {
+            tables,
+            tail_calls,
+            ..Cfg::default()
+        };
+        let mut current: Option<BasicBlock> = None;
+
+        for (&addr, &inst) in instructions.iter() {
+            if let Some(block) = current.take() {
+                if block.end == addr && !leaders.contains(&addr) {
+                    current = Some(block);
+                } else {
+                    cfg.close_block(block, &instructions, noreturn);
+                }
+            }
+
+            let block = current.get_or_insert_with(|| BasicBlock {
+                start: addr,
+                end: addr,
+                instructions: Vec::new(),
+                statements: Vec::new(),
+            });
+            block.instructions.push(inst);
+            block.end = inst.next_ip();
+
+            if is_terminator(&inst, noreturn) {
+                cfg.close_block(current.take().unwrap(), &instructions, noreturn);
+            }
+        }
+        if let Some(block) = current {
+            cfg.close_block(block, &instructions, noreturn);
+        }
+
+        cfg
+    }
+
+    fn close_block(
+        &mut self,
+        block: BasicBlock,
+        instructions: &BTreeMap<u64, Instruction>,
+        noreturn: &BTreeSet<u64>,
+    ) {
+        let last = *block.instructions.last().unwrap();
+        let target = last.branch_target().filter(|addr| instructions.contains_key(addr));
+
+        match last.flow() {
+            Flow::Branch => {
+                if let Some(to) = target {
+                    self.add_edge(block.start, to, EdgeKind::Jump);
+                }
+            }
+            Flow::ConditionalBranch => {
+                if let Some(to) = target {
+                    self.add_edge(block.start, to, EdgeKind::True);
+                }
+                if instructions.contains_key(&block.end) {
+                    self.add_edge(block.start, block.end, EdgeKind::False);
+                }
+            }
+            Flow::IndirectBranch => {
+                let targets = self.tables.get(&last.ip()).map(JumpTable::targets);
+                for to in targets.unwrap_or_default() {
+                    if instructions.contains_key(&to) {
+                        self.add_edge(block.start, to, EdgeKind::Switch);
+                    }
+                }
+            }
+            _ if !is_terminator(&last, noreturn) && instructions.contains_key(&block.end) => {
+                self.add_edge(block.start, block.end, EdgeKind::Fallthrough);
+            }
+            _ => {}
+        }
+
+        self.blocks.insert(block.start, block);
+    }
+
+    fn add_edge(&mut self, from: u64, to: u64, kind: EdgeKind) {
+        self.edges.push(Edge { from, to, kind });
+    }
+
+    /// Returns the blocks in the order they are printed: the entry first, then the others
+    /// by address.
+    pub fn ordered_blocks(&self, entry: u64) -> impl Iterator<Item = &BasicBlock> {
+        let first = self.blocks.get(&entry);
+        first
+            .into_iter()
+            .chain(self.blocks.values().filter(move |block| block.start != entry))
+    }
+}
+
+/// Where a function starts and ends, and where the others start.
+struct Bounds<'a> {
+    entry: u64,
+    end: Option<u64>,
+    starts: &'a BTreeSet<u64>,
+}
+
+impl Bounds<'_> {
+    /// Returns `true` if `target` belongs to another function.
+    fn is_outside(&self, target: u64) -> bool {
+        target != self.entry
+            && (self.starts.contains(&target)
+                || self
+                    .end
+                    .is_some_and(|end| target < self.entry || target >= end))
+    }
+}
@@ -0,0 +1,304 @@
+use crate::abi::{CallingConvention, Conventions};
+use crate::arch::Architecture;
+use crate::dot::BlockLabel;
+use crate::emitter::{CEmitter, Emitter, RustEmitter};
+use crate::listing::AsmSyntax;
+use crate::memory::memory::HexdumpOptions;
+use crate::symbols::SymbolTable;
+
+const USAGE: &str = "\
+Usage: deruster [COMMAND] [OPTIONS] [FILE]
+
+Commands:
+  decompile          Print the lifted functions [default]
+  hexdump            Dump the contents of the mapped sections
+  sections           List the mapped sections
+  xrefs <ADDR|NAME>  List the code and data references to ADDR (hex) or NAME
+
+Arguments:
+  [FILE]  Binary to analyse [default: assets/test]
+
+Options:
+  -b, --backend <c|rust>  Pseudocode syntax of the output [default: c]
+  -f, --function <ADDR>   Decompile the function at ADDR (hex), may be repeated
+      --base <ADDR>       Load the image at ADDR (hex), applying PE base relocations
+      --raw               Map FILE as a headerless image at the base address [default: 0,
+                          7c00 for a 16-bit boot sector]
+      --arch <x86|aarch64|riscv64>
+                          Instruction set of a raw image [default: x86]
+      --bits <16|32|64>   Decoding mode of a raw x86 image [default: 64]
+      --region <START-END|START+LEN>
+                          Map a zero-filled writable region (hex), may be repeated
+  -e, --entry <ADDR>      Start decompiling at ADDR (hex) too, may be repeated
+  -c, --convention <sysv|win64|cdecl|stdcall|fastcall|thiscall>
+                          Calling convention of the functions where their code does not tell
+                          [default: from the file]
+      --header <PATH>     Read function prototypes from the C header PATH, may be repeated
+  -l, --listing           Print an annotated disassembly listing instead of pseudocode
+  -s, --asm-syntax <intel|att|nasm|masm>
+                          Assembly syntax of the listing [default: intel]
+  -j, --json <PATH>       Write functions, CFGs and lifted ASTs to PATH as JSON
+  -d, --dot <DIR>         Write a Graphviz CFG per function and the call graph into DIR
+      --dot-labels <asm|ast>
+                          Label CFG blocks with disassembly or lifted statements [default: asm]
+      --xref-db <DIR>     Save the cross-reference index to DIR, keyed by a hash of FILE,
+                          and answer `xrefs` from it on the next runs
+  -h, --help              Print this help
+
+Hexdump options:
+  -r, --range <START-END|START+LEN>
+                          Only dump the given address range (hex)
+      --section <NAME>    Only dump the given section, may be repeated
+  -w, --width <N>         Bytes per line [default: 16]
+  -g, --group <N>         Bytes per group of hex digits [default: 4]";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Decompile,
+    Hexdump,
+    Sections,
+    Xrefs,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    C,
+    Rust,
+}
+
+impl Backend {
+    pub fn emitter<'a>(
+        self,
+        symbols: &'a SymbolTable,
+        architecture: Architecture,
+        conventions: &'a Conventions,
+    ) -> Box<dyn Emitter + 'a> {
+        match self {
+            Backend::C => Box::new(CEmitter { symbols }),
+            Backend::Rust => Box::new(RustEmitter {
+                symbols,
+                architecture,
+                conventions,
+            }),
+        }
+    }
+}
+
+pub struct Options {
+    pub command: Command,
+    /// The address or name given to `xrefs`.
+    pub target: Option<String>,
+    pub path: String,
+    pub backend: Backend,
+    pub functions: Vec<u64>,
+    pub base: Option<u64>,
+    pub raw: bool,
+    pub arch: Option<Architecture>,
+    pub bits: u32,
+    pub regions: Vec<std::ops::Range<u64>>,
+    pub entries: Vec<u64>,
+    pub convention: Option<CallingConvention>,
+    pub headers: Vec<String>,
+    pub listing: bool,
+    pub asm_syntax: AsmSyntax,
+    pub json: Option<String>,
+    pub dot: Option<String>,
+    pub dot_labels: BlockLabel,
+    /// The directory the cross-reference indexes are saved to.
+    pub xref_db: Option<String>,
+    pub hexdump: HexdumpOptions,
+}
+
+impl Options {
+    pub fn from_args() -> Result<Self, String> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.peekable();
+        let command = match args.peek().map(String::as_str) {
+            Some("decompile") => Some(Command::Decompile),
+            Some("hexdump") => Some(Command::Hexdump),
+            Some("sections") => Some(Command::Sections),
+            Some("xrefs") => Some(Command::Xrefs),
+            _ => None,
+        };
+        if command.is_some() {
+            args.next();
+        }
+
+        let mut options = Options {
+            command: command.unwrap_or(Command::Decompile),
+            target: None,
+            path: "assets/test".to_string(),
+            backend: Backend::C,
+            functions: Vec::new(),
+            base: None,
+            raw: false,
+            arch: None,
+            bits: 64,
+            regions: Vec::new(),
+            entries: Vec::new(),
+            convention: None,
+            headers: Vec::new(),
+            listing: false,
+            asm_syntax: AsmSyntax::Intel,
+            json: None,
+            dot: None,
+            dot_labels: BlockLabel::Disassembly,
+            xref_db: None,
+            hexdump: HexdumpOptions::default(),
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                "-b" | "--backend" => {
+                    options.backend = match value(&mut args, &arg)?.as_str() {
+                        "c" => Backend::C,
+                        "rust" => Backend::Rust,
+                        other => return Err(format!("unknown backend `{other}`\n\n{USAGE}")),
+                    }
+                }
+                "-f" | "--function" => {
+                    let addr = value(&mut args, &arg)?;
+                    options.functions.push(parse_address(&addr)?);
+                }
+                "--base" => options.base = Some(parse_address(&value(&mut args, &arg)?)?),
+                "--raw" => options.raw = true,
+                "--arch" => {
+                    options.arch = match value(&mut args, &arg)?.as_str() {
+                        "x86" => None,
+                        "aarch64" | "arm64" => Some(Architecture::AArch64),
+                        "riscv64" => Some(Architecture::RiscV64),
+                        other => return Err(format!("unknown architecture `{other}`\n\n{USAGE}")),
+                    }
+                }
+                "--bits" => {
+                    options.bits = match value(&mut args, &arg)?.as_str() {
+                        "16" => 16,
+                        "32" => 32,
+                        "64" => 64,
+                        other => return Err(format!("unknown bitness `{other}`\n\n{USAGE}")),
+                    }
+                }
+                "--region" => options.regions.push(parse_range(&value(&mut args, &arg)?)?),
+                "-e" | "--entry" => {
+                    let addr = value(&mut args, &arg)?;
+                    options.entries.push(parse_address(&addr)?);
+                }
+                "-c" | "--convention" => {
+                    options.convention = Some(match value(&mut args, &arg)?.as_str() {
+                        "sysv" => CallingConvention::SysV,
+                        "win64" => CallingConvention::Win64,
+                        "cdecl" => CallingConvention::Cdecl,
+                        "stdcall" => CallingConvention::Stdcall,
+                        "fastcall" => CallingConvention::Fastcall,
+                        "thiscall" => CallingConvention::Thiscall,
+                        other => {
+                            return Err(format!("unknown calling convention `{other}`\n\n{USAGE}"))
+                        }
+                    })
+                }
+                "--header" => options.headers.push(value(&mut args, &arg)?),
+                "-l" | "--listing" => options.listing = true,
+                "-s" | "--asm-syntax" => {
+                    options.asm_syntax = match value(&mut args, &arg)?.as_str() {
+                        "intel" => AsmSyntax::Intel,
+                        "att" | "gas" => AsmSyntax::Att,
+                        "nasm" => AsmSyntax::Nasm,
+                        "masm" => AsmSyntax::Masm,
+                        other => return Err(format!("unknown assembly syntax `{other}`\n\n{USAGE}")),
+                    }
+                }
+                "-j" | "--json" => options.json = Some(value(&mut args, &arg)?),
+                "-d" | "--dot" => options.dot = Some(value(&mut args, &arg)?),
+                "--dot-labels" => {
+                    options.dot_labels = match value(&mut args, &arg)?.as_str() {
+                        "asm" => BlockLabel::Disassembly,
+                        "ast" => BlockLabel::Statements,
+                        other => return Err(format!("unknown block label `{other}`\n\n{USAGE}")),
+                    }
+                }
+                "--xref-db" => options.xref_db = Some(value(&mut args, &arg)?),
+                "-r" | "--range" => {
+                    options.hexdump.range = Some(parse_range(&value(&mut args, &arg)?)?)
+                }
+                "--section" => options.hexdump.sections.push(value(&mut args, &arg)?),
+                "-w" | "--width" => options.hexdump.width = parse_count(&value(&mut args, &arg)?)?,
+                "-g" | "--group" => options.hexdump.group = parse_count(&value(&mut args, &arg)?)?,
+                flag if flag.starts_with('-') => {
+                    return Err(format!("unknown option `{flag}`\n\n{USAGE}"))
+                }
+                _ if options.command == Command::Xrefs && options.target.is_none() => {
+                    options.target = Some(arg)
+                }
+                _ => options.path = arg,
+            }
+        }
+        if options.command == Command::Xrefs && options.target.is_none() {
+            return Err(format!("missing address or name for `xrefs`\n\n{USAGE}"));
+        }
+
+        Ok(options)
+    }
+}
+
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("missing value for `{flag}`\n\n{USAGE}"))
+}
+
+/// Parses a hexadecimal address, with or without the `0x` prefix.
+pub fn parse_address(text: &str) -> Result<u64, String> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    u64::from_str_radix(digits, 16).map_err(|err| format!("invalid address `{text}`: {err}"))
+}
+
+/// Parses `START-END` or `START+LEN`, both hexadecimal, into a non-empty range.
+fn parse_range(text: &str) -> Result<std::ops::Range<u64>, String> {
+    let range = if let Some((start, end)) = text.split_once('-') {
+        parse_address(start)?..parse_address(end)?
+    } else if let Some((start, len)) = text.split_once('+') {
+        let start = parse_address(start)?;
+        let end = start
+            .checked_add(parse_address(len)?)
+            .ok_or_else(|| format!("invalid range `{text}`, it ends past the address space"))?;
+        start..end
+    } else {
+        return Err(format!("invalid range `{text}`, expected START-END or START+LEN"));
+    };
+    if range.is_empty() {
+        return Err(format!("invalid range `{text}`, it is empty or ends before it starts"));
+    }
+    Ok(range)
+}
+
+fn parse_count(text: &str) -> Result<usize, String> {
+    match text.parse::<usize>() {
+        Ok(count) if count > 0 => Ok(count),
+        _ => Err(format!("invalid count `{text}`, expected a positive number")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parse_range("1000-1200"), Ok(0x1000..0x1200));
+        assert_eq!(parse_range("0x1000+200"), Ok(0x1000..0x1200));
+        assert!(parse_range("5-3").is_err());
+        assert!(parse_range("5-5").is_err());
+        assert!(parse_range("5+0").is_err());
+        assert!(parse_range("ffffffffffffff00+100").is_err());
+        assert!(parse_range("5").is_err());
+    }
+}
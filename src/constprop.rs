@@ -0,0 +1,918 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use iced_x86::{FlowControl, InstructionInfoFactory, Mnemonic, OpAccess, OpKind, Register};
+
+use crate::abi::{Conventions, RegisterAbi};
+use crate::arch::{Architecture, Flow, Instruction};
+use crate::ast::{get_register_name, BinOpKind, Expr, ExprKind, Lit, Symbol, Ty, UnOp};
+use crate::function::Function;
+use crate::memory::memory::Memory;
+use crate::prototypes::PrototypeDb;
+use crate::signatures;
+use crate::slots::{frame_slot, mask, Slot, Slots, ENTRY};
+use crate::symbols::SymbolTable;
+use crate::types::Type;
+use crate::unwind::UnwindTable;
+use crate::xrefs::MIN_IMMEDIATE_ADDRESS;
+
+/// A constant held in a register or a stack slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Value {
+    bits: u64,
+    /// The value was computed from the instruction pointer or is large enough to be taken
+    /// for an address, rather than a number which happens to fall in a section.
+    address: bool,
+}
+
+impl Value {
+    fn number(bits: u64) -> Self {
+        Self {
+            bits,
+            address: bits >= MIN_IMMEDIATE_ADDRESS,
+        }
+    }
+
+    fn masked(self, size: usize) -> Self {
+        match size {
+            1..=7 => Self::number(mask(self.bits, size as u32 * 8)),
+            _ => self,
+        }
+    }
+}
+
+/// What is known on entry to an instruction, on every path reaching it.
+#[derive(Clone, Default, PartialEq, Eq)]
+struct State {
+    /// The constants in the general-purpose registers, by the name of the whole register.
+    registers: BTreeMap<String, Value>,
+    /// The constants stored in the stack frame.
+    slots: Slots<Value>,
+}
+
+impl State {
+    fn merge(&self, other: &State) -> State {
+        State {
+            registers: self
+                .registers
+                .iter()
+                .filter(|(reg, value)| other.registers.get(*reg) == Some(value))
+                .map(|(reg, value)| (reg.clone(), *value))
+                .collect(),
+            slots: self.slots.agreeing(&other.slots),
+        }
+    }
+
+    fn read(&self, reg: Register) -> Option<Value> {
+        if matches!(
+            reg,
+            Register::AH | Register::BH | Register::CH | Register::DH
+        ) {
+            return None;
+        }
+        self.read_whole(&get_register_name(reg.full_register()), reg.size())
+    }
+
+    /// Reads the `size` low bytes of the whole register `name`.
+    fn read_whole(&self, name: &str, size: usize) -> Option<Value> {
+        self.registers.get(name).map(|value| value.masked(size))
+    }
+
+    fn load(&self, inst: &iced_x86::Instruction, depth: Option<i64>, size: usize) -> Option<Value> {
+        self.load_slot(&frame_slot(inst, depth)?, size)
+    }
+
+    fn load_slot(&self, slot: &Slot, size: usize) -> Option<Value> {
+        let (stored, value) = self.slots.get(slot)?;
+        (size <= *stored).then(|| value.masked(size))
+    }
+}
+
+/// The constants passed between the lifted functions: the values some return on every path,
+/// and the arguments all the callers of a function pass the same constant in. x86 code is
+/// followed instruction by instruction, and that of the other architectures through its
+/// lifted statements, with the registers the [`RegisterAbi`] of each gives their roles.
+pub struct Constants {
+    architecture: Architecture,
+    conventions: Conventions,
+    functions: BTreeSet<u64>,
+    returns: BTreeMap<u64, Value>,
+    /// The calls to each function, with the argument registers known at each of them once
+    /// the caller has been visited, by the name of the whole register.
+    sites: BTreeMap<u64, BTreeMap<u64, Option<BTreeMap<String, Value>>>>,
+    /// The functions which may be reached other than by a direct call: their address is
+    /// taken, stored in data, or jumped to from another function.
+    escaped: BTreeSet<u64>,
+    /// The argument registers the functions making no calls may write, the others being
+    /// left as they were by the calls to them.
+    clobbers: BTreeMap<u64, Vec<String>>,
+    /// How far below its value on entry the stack pointer is before each instruction, where
+    /// the call frame information gives it.
+    depths: BTreeMap<u64, i64>,
+}
+
+impl Constants {
+    /// Finds the calls between `functions`, and those of them which may be called from
+    /// elsewhere, like the `entrypoints` and the callbacks stored in data. The stack depths
+    /// `unwind` gives let the slots of the functions with no frame pointer be followed.
+    pub fn new(
+        functions: &[Function],
+        entrypoints: &[u64],
+        memory: &Memory,
+        unwind: &UnwindTable,
+        conventions: &Conventions,
+    ) -> Self {
+        let mut constants = Self {
+            architecture: memory.architecture(),
+            conventions: conventions.clone(),
+            functions: functions.iter().map(|function| function.address).collect(),
+            returns: BTreeMap::new(),
+            sites: BTreeMap::new(),
+            escaped: entrypoints.iter().copied().collect(),
+            clobbers: functions
+                .iter()
+                .filter_map(|function| {
+                    Some((function.address, signatures::clobbers(function, conventions)?))
+                })
+                .collect(),
+            depths: functions
+                .iter()
+                .flat_map(|function| function.cfg.blocks.values())
+                .flat_map(|block| block.instructions.iter())
+                .filter_map(|inst| Some((inst.ip(), unwind.stack_depth(inst.ip())?)))
+                .collect(),
+        };
+        for function in functions {
+            for (inst, stmt) in function
+                .cfg
+                .blocks
+                .values()
+                .flat_map(|block| block.instructions.iter().zip(block.statements.iter()))
+            {
+                let Instruction::X86(inst) = inst else {
+                    // the constants of the other architectures are those they are lifted to
+                    match (inst.flow(), call_target(inst, stmt)) {
+                        (Flow::Call | Flow::IndirectCall, Some(target)) => {
+                            let sites = constants.sites.entry(target).or_default();
+                            sites.insert(inst.ip(), None);
+                        }
+                        (_, Some(target)) if target != function.address => {
+                            constants.escaped.insert(target);
+                        }
+                        _ => literals(stmt, &mut constants.escaped),
+                    }
+                    continue;
+                };
+                if matches!(
+                    inst.op0_kind(),
+                    OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64
+                ) {
+                    let target = inst.near_branch_target();
+                    if inst.flow_control() == FlowControl::Call {
+                        constants
+                            .sites
+                            .entry(target)
+                            .or_default()
+                            .insert(inst.ip(), None);
+                    } else if target != function.address {
+                        constants.escaped.insert(target);
+                    }
+                    continue;
+                }
+                if inst.mnemonic() == Mnemonic::Lea && inst.is_ip_rel_memory_operand() {
+                    constants.escaped.insert(inst.ip_rel_memory_address());
+                }
+                for op in 0..inst.op_count() {
+                    match inst.op_kind(op) {
+                        OpKind::Immediate32 => {
+                            constants.escaped.insert(u64::from(inst.immediate32()))
+                        }
+                        OpKind::Immediate32to64 => {
+                            constants.escaped.insert(inst.immediate32to64() as u64)
+                        }
+                        OpKind::Immediate64 => constants.escaped.insert(inst.immediate64()),
+                        _ => continue,
+                    };
+                }
+            }
+        }
+        // function pointer tables, like the callbacks of a struct or `.init_array`
+        let word = (memory.bitness() / 8) as usize;
+        for (range, section) in memory.sections() {
+            if section.permissions().execute {
+                continue;
+            }
+            let Some(bytes) = memory.get(range.start) else {
+                continue;
+            };
+            let skip = (word - range.start as usize % word) % word;
+            for chunk in bytes.get(skip..).unwrap_or_default().chunks_exact(word) {
+                let mut value = [0; 8];
+                value[..word].copy_from_slice(chunk);
+                let value = u64::from_le_bytes(value);
+                if constants.functions.contains(&value) {
+                    constants.escaped.insert(value);
+                }
+            }
+        }
+        constants
+    }
+
+    /// Records the constant `function` returns on every path, whatever its arguments, for
+    /// its callers. The callees are better visited first.
+    pub fn add_returns(&mut self, function: &Function) {
+        let returns = function
+            .prototype
+            .as_ref()
+            .is_some_and(|prototype| prototype.return_type != Type::Void);
+        if !returns {
+            return;
+        }
+        let Some((_, returned)) = self.analyze(function, State::default()) else {
+            return;
+        };
+        if let Some(value) = returned {
+            self.returns.insert(function.address, value);
+        }
+    }
+
+    /// Replaces the registers passed to the calls of `function` with the constants they
+    /// hold, following those its callers all pass it and those its callees return, and
+    /// resolves its calls through a register or a stack slot holding a function address.
+    /// The callers are better visited first, since the arguments of `function` are only
+    /// known once every call to it has been.
+    pub fn propagate(
+        &mut self,
+        function: &mut Function,
+        prototypes: &PrototypeDb,
+        symbols: &SymbolTable,
+        memory: &Memory,
+    ) {
+        let entry = self.arguments(function.address);
+        let Some((states, _)) = self.analyze(function, entry) else {
+            return;
+        };
+
+        let mut resolved = false;
+        for block in function.cfg.blocks.values_mut() {
+            for (inst, stmt) in block.instructions.iter().zip(block.statements.iter_mut()) {
+                let Some(state) = states.get(&inst.ip()) else {
+                    continue;
+                };
+                let indirect = inst.as_x86().and_then(|inst| self.indirect_target(inst, state));
+                if let Some(target) = indirect {
+                    stmt.kind = ExprKind::Call(target, Vec::new());
+                    resolved = true;
+                }
+                if !matches!(inst.flow(), Flow::Call | Flow::IndirectCall) {
+                    continue;
+                }
+                let Some(target) = call_target(inst, stmt) else {
+                    continue;
+                };
+                let abi = self.abi(Some(target));
+                let arguments = abi
+                    .arguments
+                    .iter()
+                    .filter_map(|name| {
+                        let (whole, size) = abi.whole(name)?;
+                        let value = state.read_whole(&whole, size)?;
+                        Some((whole, value))
+                    })
+                    .collect();
+                let sites = self.sites.entry(target).or_default();
+                sites.insert(inst.ip(), Some(arguments));
+            }
+        }
+        if resolved {
+            // fit the arguments of the resolved calls to their callees
+            prototypes.apply(function, symbols, memory, &self.conventions);
+        }
+
+        let abi = self.abi(None);
+        for block in function.cfg.blocks.values_mut() {
+            for (inst, stmt) in block.instructions.iter().zip(block.statements.iter_mut()) {
+                let ExprKind::Call(_, args) = &mut stmt.call_site_mut().kind else {
+                    continue;
+                };
+                let state = states.get(&inst.ip());
+                for arg in args.iter_mut() {
+                    self.substitute(arg, state, &abi, symbols, memory);
+                }
+            }
+        }
+    }
+
+    /// Returns the roles of the registers in the convention of the function at `addr`, or
+    /// in the default one.
+    fn abi(&self, addr: Option<u64>) -> RegisterAbi {
+        let convention = addr.map_or(self.conventions.default, |addr| self.conventions.of(addr));
+        RegisterAbi::new(self.architecture, convention)
+    }
+
+    /// Returns the constant each argument register is given by every call to the function
+    /// at `addr`, when all of them are known.
+    fn arguments(&self, addr: u64) -> State {
+        let mut state = State::default();
+        if self.escaped.contains(&addr) {
+            return state;
+        }
+        let Some(sites) = self.sites.get(&addr) else {
+            return state;
+        };
+        let mut sites = sites.values();
+        let Some(Some(first)) = sites.next() else {
+            return state;
+        };
+        state.registers = first.clone();
+        for site in sites {
+            let Some(site) = site else {
+                return State::default();
+            };
+            state
+                .registers
+                .retain(|reg, value| site.get(reg) == Some(value));
+        }
+        state
+    }
+
+    /// Runs the constants through `function` from `entry`, and returns the state before each
+    /// call with the constant returned on every path, if any.
+    fn analyze(
+        &self,
+        function: &Function,
+        entry: State,
+    ) -> Option<(BTreeMap<u64, State>, Option<Value>)> {
+        let mut factory = InstructionInfoFactory::new();
+        let abi = self.abi(Some(function.address));
+        // the address of a local given away lets anything write the frame
+        let mut escapes = false;
+        for (inst, stmt) in function
+            .cfg
+            .blocks
+            .values()
+            .flat_map(|block| block.instructions.iter().zip(block.statements.iter()))
+        {
+            escapes |= match inst {
+                Instruction::X86(inst) => {
+                    inst.mnemonic() == Mnemonic::Lea
+                        && matches!(
+                            inst.memory_base().full_register(),
+                            Register::RSP | Register::RBP
+                        )
+                }
+                _ => takes_frame_address(stmt, &abi),
+            };
+        }
+
+        let mut entries = BTreeMap::from([(function.address, entry)]);
+        let mut worklist = vec![function.address];
+        while let Some(start) = worklist.pop() {
+            let Some(block) = function.cfg.blocks.get(&start) else {
+                continue;
+            };
+            let mut state = entries[&start].clone();
+            for (inst, stmt) in block.instructions.iter().zip(block.statements.iter()) {
+                self.step(&mut state, inst, stmt, escapes, &mut factory);
+            }
+            for edge in function.cfg.edges.iter().filter(|edge| edge.from == start) {
+                let merged = match entries.get(&edge.to) {
+                    Some(other) => other.merge(&state),
+                    None => state.clone(),
+                };
+                if entries.get(&edge.to) != Some(&merged) {
+                    entries.insert(edge.to, merged);
+                    worklist.push(edge.to);
+                }
+            }
+        }
+
+        let mut states = BTreeMap::new();
+        let mut returned = Vec::new();
+        for (start, block) in function.cfg.blocks.iter() {
+            let Some(state) = entries.get(start) else {
+                continue;
+            };
+            let mut state = state.clone();
+            for (inst, stmt) in block.instructions.iter().zip(block.statements.iter()) {
+                match inst.flow() {
+                    Flow::Call | Flow::IndirectCall => {
+                        states.insert(inst.ip(), state.clone());
+                    }
+                    Flow::Return => returned.push(state.read_whole(&abi.result, 8)),
+                    _ => {}
+                }
+                self.step(&mut state, inst, stmt, escapes, &mut factory);
+            }
+        }
+        let value = match returned.split_first() {
+            Some((first, rest)) if rest.iter().all(|other| other == first) => *first,
+            _ => None,
+        };
+        Some((states, value))
+    }
+
+    fn step(
+        &self,
+        state: &mut State,
+        inst: &Instruction,
+        stmt: &Expr,
+        escapes: bool,
+        factory: &mut InstructionInfoFactory,
+    ) {
+        if matches!(inst.flow(), Flow::Call | Flow::IndirectCall) {
+            let target = match inst {
+                Instruction::X86(inst) if inst.flow_control() == FlowControl::IndirectCall => {
+                    self.indirect_target(inst, state)
+                }
+                inst => call_target(inst, stmt),
+            };
+            let abi = self.abi(target);
+            let clobbers = target.and_then(|target| self.clobbers.get(&target));
+            state.registers.retain(|reg, _| {
+                abi.preserved.contains(reg)
+                    || clobbers.is_some_and(|clobbers| {
+                        abi.arguments.contains(reg) && !clobbers.contains(reg)
+                    })
+            });
+            if escapes {
+                state.slots.clear();
+            }
+            if let Some(value) = target.and_then(|target| self.returns.get(&target)) {
+                state.registers.insert(abi.result, *value);
+            }
+            return;
+        }
+        match inst {
+            Instruction::X86(inst) => self.step_x86(state, inst, escapes, factory),
+            inst => self.step_lifted(state, inst, stmt, escapes),
+        }
+    }
+
+    /// Follows the constants through the lifted statement of the AArch64 or RISC-V `inst`,
+    /// which is no call: the registers it assigns a constant and the stack slots it stores
+    /// one in, all the others it writes being unknown after it.
+    fn step_lifted(&self, state: &mut State, inst: &Instruction, stmt: &Expr, escapes: bool) {
+        let abi = self.abi(None);
+        let depth = self.depths.get(&inst.ip()).copied();
+        let mut assigned = None;
+        // a store lifted to no single assignment of a stack slot may write any of them
+        let mut stored = match inst {
+            Instruction::AArch64(inst) => inst.opcode.is_store(),
+            Instruction::RiscV(inst) => inst.opcode.is_store() || inst.opcode.is_atomic(),
+            Instruction::X86(_) => false,
+        };
+        if let ExprKind::Assign(lhs, rhs) = &stmt.kind {
+            let value = evaluate_lifted(state, rhs, &abi, depth);
+            match &lhs.kind {
+                ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) => {
+                    if let Some((whole, size)) = abi.whole(name) {
+                        assigned = Some((whole, value.map(|value| value.masked(size))));
+                    }
+                }
+                ExprKind::Unary(UnOp::Deref, address) => match stack_slot(address, &abi, depth) {
+                    Some(((base, offset), size)) => {
+                        state.slots.clobber(&base, Some((offset, size)));
+                        if let Some(value) = value {
+                            state.slots.insert((base, offset), size, value.masked(size));
+                        }
+                        stored = false;
+                    }
+                    None if escapes => state.slots.clear(),
+                    None => stored = false,
+                },
+                _ => {}
+            }
+        }
+        if stored {
+            state.slots.clear();
+        }
+        for name in inst.written_registers() {
+            if let Some((whole, _)) = abi.whole(&name) {
+                state.registers.remove(&whole);
+                state.slots.clobber(&whole, None);
+            }
+        }
+        if let Some((whole, value)) = assigned {
+            state.registers.remove(&whole);
+            state.slots.clobber(&whole, None);
+            if let Some(value) = value {
+                state.registers.insert(whole, value);
+            }
+        }
+    }
+
+    /// Follows the constants through the x86 `inst`, which is no call.
+    fn step_x86(
+        &self,
+        state: &mut State,
+        inst: &iced_x86::Instruction,
+        escapes: bool,
+        factory: &mut InstructionInfoFactory,
+    ) {
+        let depth = self.depths.get(&inst.ip()).copied();
+        let value = evaluate(state, inst, depth);
+        let info = factory.info(inst);
+        for used in info.used_registers() {
+            if !matches!(used.access(), OpAccess::Read | OpAccess::CondRead) {
+                let reg = get_register_name(used.register().full_register());
+                state.registers.remove(&reg);
+            }
+        }
+        state.slots.forget(info, depth, escapes);
+
+        let Some(value) = value else {
+            return;
+        };
+        match inst.op0_kind() {
+            // writes of 32 bits clear the upper half, narrower ones keep it
+            OpKind::Register if inst.op0_register().size() >= 4 => {
+                let reg = inst.op0_register();
+                state.registers.insert(
+                    get_register_name(reg.full_register()),
+                    value.masked(reg.size()),
+                );
+            }
+            OpKind::Memory => {
+                if let Some(slot) = frame_slot(inst, depth) {
+                    let size = inst.memory_size().size();
+                    state.slots.insert(slot, size, value.masked(size));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the function a call through a register or a stack slot goes to, when it
+    /// holds the address of one.
+    fn indirect_target(&self, inst: &iced_x86::Instruction, state: &State) -> Option<u64> {
+        if inst.flow_control() != FlowControl::IndirectCall {
+            return None;
+        }
+        let value = match inst.op0_kind() {
+            OpKind::Register => state.read(inst.op0_register())?,
+            OpKind::Memory => {
+                let depth = self.depths.get(&inst.ip()).copied();
+                state.load(inst, depth, inst.memory_size().size())?
+            }
+            _ => return None,
+        };
+        self.functions.contains(&value.bits).then_some(value.bits)
+    }
+
+    /// Replaces the register `arg` reads with the constant it holds in `state`, and shows
+    /// a constant pointing to a string as the string and one pointing to a function as its
+    /// name.
+    fn substitute(
+        &self,
+        arg: &mut Expr,
+        state: Option<&State>,
+        abi: &RegisterAbi,
+        symbols: &SymbolTable,
+        memory: &Memory,
+    ) {
+        let value = match &mut arg.kind {
+            ExprKind::Cast(inner, ty) => {
+                self.substitute(inner, state, abi, symbols, memory);
+                // the string or the function tells the type of the pointer
+                if matches!(ty, Ty::Ptr(_))
+                    && matches!(
+                        inner.kind,
+                        ExprKind::Lit(Lit::Str(_) | Lit::Symbol(Symbol::Local(_)))
+                    )
+                {
+                    arg.kind = std::mem::replace(&mut inner.kind, ExprKind::Lit(Lit::StillUnknown));
+                }
+                return;
+            }
+            ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) => {
+                let Some(value) = abi.whole(name).and_then(|(whole, size)| {
+                    Some((size, state?.read_whole(&whole, size)?))
+                }) else {
+                    return;
+                };
+                value
+            }
+            ExprKind::Lit(Lit::U32(value)) => (4, Value::number(u64::from(*value))),
+            ExprKind::Lit(Lit::U64(value)) => (8, Value::number(*value)),
+            _ => return,
+        };
+        let (size, value) = value;
+        let lit = if value.address
+            && memory.is_read_only(value.bits)
+            && !memory.is_executable(value.bits)
+        {
+            memory.read_c_string(value.bits).map(Lit::Str)
+        } else if value.address && self.functions.contains(&value.bits) {
+            Some(Lit::Symbol(Symbol::Local(
+                symbols.display_name(value.bits),
+            )))
+        } else {
+            None
+        };
+        arg.kind = ExprKind::Lit(match lit {
+            Some(lit) => lit,
+            None if size == 8 => Lit::U64(value.bits),
+            None => Lit::U32(value.bits as u32),
+        });
+    }
+}
+
+/// Returns the value `inst` writes to its first operand, when it is a constant, with the
+/// stack pointer `depth` bytes below its value on entry if known.
+fn evaluate(state: &State, inst: &iced_x86::Instruction, depth: Option<i64>) -> Option<Value> {
+    let source = |op: u32| match inst.op_kind(op) {
+        OpKind::Register => state.read(inst.op_register(op)),
+        OpKind::Memory => state.load(inst, depth, inst.memory_size().size()),
+        OpKind::Immediate8 | OpKind::Immediate16 | OpKind::Immediate32 | OpKind::Immediate64 => {
+            Some(Value::number(inst.immediate(op)))
+        }
+        OpKind::Immediate8to16 | OpKind::Immediate8to32 | OpKind::Immediate8to64 => {
+            Some(Value::number(inst.immediate(op)))
+        }
+        OpKind::Immediate32to64 => Some(Value::number(inst.immediate32to64() as u64)),
+        _ => None,
+    };
+    match inst.mnemonic() {
+        Mnemonic::Mov => source(1),
+        Mnemonic::Lea if inst.is_ip_rel_memory_operand() => Some(Value {
+            bits: inst.ip_rel_memory_address(),
+            address: true,
+        }),
+        Mnemonic::Lea if inst.memory_index() == Register::None => {
+            let base = state.read(inst.memory_base())?;
+            Some(Value {
+                bits: base.bits.wrapping_add(inst.memory_displacement64()),
+                address: base.address,
+            })
+        }
+        Mnemonic::Xor
+            if inst.op0_kind() == OpKind::Register
+                && inst.op1_kind() == OpKind::Register
+                && inst.op0_register() == inst.op1_register() =>
+        {
+            Some(Value::number(0))
+        }
+        Mnemonic::Add | Mnemonic::Sub => {
+            let (lhs, rhs) = (source(0)?, source(1)?);
+            let bits = match inst.mnemonic() {
+                Mnemonic::Add => lhs.bits.wrapping_add(rhs.bits),
+                _ => lhs.bits.wrapping_sub(rhs.bits),
+            };
+            Some(Value {
+                bits,
+                address: lhs.address,
+            })
+        }
+        _ => None,
+    }
+}
+
+
+/// Returns the value of the lifted expression `expr`, when it is a constant: a literal, a
+/// register or a stack slot holding one, or a sum or difference of them.
+fn evaluate_lifted(
+    state: &State,
+    expr: &Expr,
+    abi: &RegisterAbi,
+    depth: Option<i64>,
+) -> Option<Value> {
+    match &expr.kind {
+        ExprKind::Lit(Lit::U32(value)) => Some(Value::number(u64::from(*value))),
+        ExprKind::Lit(Lit::U64(value)) => Some(Value::number(*value)),
+        ExprKind::Lit(Lit::I64(value)) => Some(Value::number(*value as u64)),
+        ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) => {
+            let (whole, size) = abi.whole(name)?;
+            state.read_whole(&whole, size)
+        }
+        ExprKind::Binary(op @ (BinOpKind::Add | BinOpKind::Sub), lhs, rhs) => {
+            let (lhs, rhs) = (
+                evaluate_lifted(state, lhs, abi, depth)?,
+                evaluate_lifted(state, rhs, abi, depth)?,
+            );
+            let bits = match op {
+                BinOpKind::Add => lhs.bits.wrapping_add(rhs.bits),
+                _ => lhs.bits.wrapping_sub(rhs.bits),
+            };
+            Some(Value {
+                bits,
+                address: lhs.address,
+            })
+        }
+        // narrowing to an unsigned type, or to a signed one keeping the value
+        ExprKind::Cast(inner, ty) => {
+            let value = evaluate_lifted(state, inner, abi, depth)?;
+            let size = ty_size(ty)?;
+            let signed = matches!(ty, Ty::I8 | Ty::I16 | Ty::I32);
+            match value.masked(size) {
+                masked if signed && masked.bits >> (size * 8 - 1) != 0 => None,
+                masked => Some(masked),
+            }
+        }
+        ExprKind::Unary(UnOp::Deref, address) => {
+            let (slot, size) = stack_slot(address, abi, depth)?;
+            state.load_slot(&slot, size)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the stack slot the lifted address `address` points to, `*((T *)(sp + offset))`
+/// through the stack or frame pointer, with the size of `T`. The slots of the stack pointer
+/// are taken from its value on entry when it is `depth` bytes below it.
+fn stack_slot(address: &Expr, abi: &RegisterAbi, depth: Option<i64>) -> Option<(Slot, usize)> {
+    let ExprKind::Cast(address, Ty::Ptr(ty)) = &address.kind else {
+        return None;
+    };
+    let base = |expr: &Expr| match &expr.kind {
+        ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) => abi
+            .whole(name)
+            .map(|(whole, _)| whole)
+            .filter(|whole| abi.stack.contains(whole)),
+        _ => None,
+    };
+    let offset = |expr: &Expr| match expr.kind {
+        ExprKind::Lit(Lit::U32(value)) => Some(i64::from(value)),
+        ExprKind::Lit(Lit::U64(value)) => Some(value as i64),
+        ExprKind::Lit(Lit::I64(value)) => Some(value),
+        _ => None,
+    };
+    let slot = match &address.kind {
+        ExprKind::Binary(BinOpKind::Add, lhs, rhs) => (base(lhs)?, offset(rhs)?),
+        ExprKind::Binary(BinOpKind::Sub, lhs, rhs) => (base(lhs)?, offset(rhs)?.wrapping_neg()),
+        _ => (base(address)?, 0),
+    };
+    let slot = match depth {
+        Some(depth) if slot.0 == abi.stack[0] => (ENTRY.to_string(), slot.1 - depth),
+        _ => slot,
+    };
+    Some((slot, ty_size(ty)?))
+}
+
+/// Returns the size in bytes of the scalar type `ty`.
+fn ty_size(ty: &Ty) -> Option<usize> {
+    match ty {
+        Ty::Bool | Ty::U8 | Ty::I8 => Some(1),
+        Ty::U16 | Ty::I16 => Some(2),
+        Ty::U32 | Ty::I32 | Ty::F32 => Some(4),
+        Ty::U64 | Ty::I64 | Ty::F64 | Ty::Ptr(_) => Some(8),
+        Ty::Void | Ty::Vector(..) => None,
+    }
+}
+
+/// Returns `true` if the lifted statement `stmt` gives away the address of a local: puts a
+/// value computed from the stack or frame pointer anywhere but in one of them.
+fn takes_frame_address(stmt: &Expr, abi: &RegisterAbi) -> bool {
+    fn computed(expr: &Expr, abi: &RegisterAbi) -> bool {
+        match &expr.kind {
+            ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) => abi
+                .whole(name)
+                .is_some_and(|(whole, _)| abi.stack.contains(&whole)),
+            // loads from the frame are no address
+            ExprKind::Unary(UnOp::Deref, _) => false,
+            ExprKind::Unary(_, inner) | ExprKind::Cast(inner, _) => computed(inner, abi),
+            ExprKind::Binary(_, lhs, rhs) => computed(lhs, abi) || computed(rhs, abi),
+            _ => false,
+        }
+    }
+    let ExprKind::Assign(lhs, rhs) = &stmt.kind else {
+        return false;
+    };
+    let to_stack = match &lhs.kind {
+        ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) => abi
+            .whole(name)
+            .is_some_and(|(whole, _)| abi.stack.contains(&whole)),
+        _ => false,
+    };
+    !to_stack && computed(rhs, abi)
+}
+
+/// Returns the target of the direct call or jump `inst`, or of the call through a register
+/// the lifter folded into the direct call `stmt`, like the `auipc` and `jalr` of RISC-V.
+fn call_target(inst: &Instruction, stmt: &Expr) -> Option<u64> {
+    match stmt.call_site().kind {
+        ExprKind::Call(target, _) if !matches!(inst, Instruction::X86(_)) => Some(target),
+        _ => inst.branch_target(),
+    }
+}
+
+/// Collects the integer constants `expr` reads, which may be the addresses of functions.
+fn literals(expr: &Expr, out: &mut BTreeSet<u64>) {
+    match &expr.kind {
+        ExprKind::Lit(Lit::U32(value)) => {
+            out.insert(u64::from(*value));
+        }
+        ExprKind::Lit(Lit::U64(value)) => {
+            out.insert(*value);
+        }
+        ExprKind::Call(_, args)
+        | ExprKind::MethodCall(_, args)
+        | ExprKind::Intrinsic(_, args)
+        | ExprKind::Tup(args) => args.iter().for_each(|arg| literals(arg, out)),
+        ExprKind::Binary(_, lhs, rhs) | ExprKind::Assign(lhs, rhs) => {
+            literals(lhs, out);
+            literals(rhs, out);
+        }
+        ExprKind::Unary(_, inner) | ExprKind::Cast(inner, _) => literals(inner, out),
+        ExprKind::Ret(Some(inner)) | ExprKind::Switch(inner, _) => literals(inner, out),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::abi::CallingConvention;
+    use crate::rtti::Classes;
+    use crate::signatures::infer;
+    use crate::unwind::UnwindTable;
+
+    /// Lifts the function at `callee` of the asset `name` and the `caller` passing it the
+    /// string, infers the prototype of the callee and follows the constants down the calls,
+    /// returning the declaration of the callee and the calls of both.
+    fn propagate(name: &str, callee: u64, caller: u64) -> (String, Vec<String>) {
+        let data = std::fs::read(format!("assets/{name}")).unwrap();
+        let file = object::File::parse(&*data).unwrap();
+        let memory = Memory::load(&file, &data, None).unwrap();
+        let symbols = SymbolTable::from_file(&file, &memory);
+        let unwind = UnwindTable::from_file(&file, &memory);
+        let conventions = Conventions::new(CallingConvention::SysV);
+        let none = BTreeSet::new();
+        let classes = Classes::default();
+        let lift = |address| {
+            Function::lift(&memory, &unwind, address, &conventions, &none, &none, &classes)
+        };
+        let mut functions = vec![lift(callee), lift(caller)];
+        let mut prototypes = PrototypeDb::new(8, 8);
+        let architecture = memory.architecture();
+        let prototype =
+            infer(&functions[0], &[], &prototypes, &symbols, &conventions, architecture).unwrap();
+        let declaration = prototype.declare("callee");
+        prototypes.insert_inferred(callee, prototype);
+        prototypes.apply(&mut functions[1], &symbols, &memory, &conventions);
+
+        let mut constants = Constants::new(&functions, &[caller], &memory, &unwind, &conventions);
+        for function in functions.iter() {
+            constants.add_returns(function);
+        }
+        for function in functions.iter_mut().rev() {
+            constants.propagate(function, &prototypes, &symbols, &memory);
+        }
+        let calls = functions
+            .iter()
+            .flat_map(|function| function.statements())
+            .filter(|statement| matches!(statement.kind, ExprKind::Call(..)))
+            .map(|statement| statement.named(&symbols).to_string())
+            .collect();
+        (declaration, calls)
+    }
+
+    #[test]
+    fn aarch64_arguments() {
+        let (declaration, calls) = propagate("test_arm64", 0x400298, 0x4002b8);
+        assert_eq!(declaration, "undefined4 callee(undefined8)");
+        assert_eq!(
+            calls,
+            ["puts@plt(\"Hello, world!\")", "FUN_400298(\"Hello, world!\")"]
+        );
+    }
+
+    #[test]
+    fn riscv_arguments() {
+        let (declaration, calls) = propagate("test_riscv64", 0x10292, 0x102b4);
+        assert_eq!(declaration, "undefined4 callee(undefined8)");
+        assert_eq!(
+            calls,
+            ["puts@plt(\"Hello, world!\")", "FUN_10292(\"Hello, world!\")"]
+        );
+    }
+    #[test]
+    fn frame_pointer_less() {
+        let data = std::fs::read("assets/test_frames").unwrap();
+        let file = object::File::parse(&*data).unwrap();
+        let memory = Memory::load(&file, &data, None).unwrap();
+        let symbols = SymbolTable::from_file(&file, &memory);
+        let unwind = UnwindTable::from_file(&file, &memory);
+        let conventions = Conventions::new(CallingConvention::SysV);
+        let none = BTreeSet::new();
+        let classes = Classes::default();
+        let lift = |address| {
+            Function::lift(&memory, &unwind, address, &conventions, &none, &none, &classes)
+        };
+        // the callback is stored before the push and called after it
+        let (dispatch, greet) = (0x1150, 0x1170);
+        assert_eq!(unwind.stack_depth(0x115b), Some(0x10));
+        assert_eq!(unwind.stack_depth(0x1163), Some(0x18));
+        let mut functions = vec![lift(dispatch), lift(greet)];
+        let prototypes = PrototypeDb::new(8, 8);
+        let mut constants = Constants::new(&functions, &[dispatch], &memory, &unwind, &conventions);
+        constants.propagate(&mut functions[0], &prototypes, &symbols, &memory);
+        let calls: Vec<_> = functions[0]
+            .statements()
+            .filter(|statement| matches!(statement.kind, ExprKind::Call(..)))
+            .map(|statement| statement.named(&symbols).to_string())
+            .collect();
+        assert_eq!(calls, ["greet()"]);
+    }
+}
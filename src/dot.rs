@@ -0,0 +1,141 @@
+use std::collections::BTreeSet;
+use std::fmt::{Result, Write};
+
+use crate::callgraph::{CallGraph, CallKind};
+use crate::cfg::EdgeKind;
+use crate::emitter::is_noise;
+use crate::function::Function;
+use crate::rtti::display_name;
+use crate::symbols::SymbolTable;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockLabel {
+    Disassembly,
+    Statements,
+}
+
+/// Escapes `text` for a double-quoted DOT string, keeping lines left-aligned.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\l"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn edge_color(kind: EdgeKind) -> &'static str {
+    match kind {
+        EdgeKind::True => "darkgreen",
+        EdgeKind::False => "red",
+        EdgeKind::Jump => "blue",
+        EdgeKind::Fallthrough => "black",
+        EdgeKind::Switch => "purple",
+    }
+}
+
+/// Writes the control-flow graph of `function`, one node per basic block, calling functions
+/// by the names of `symbols` like the pseudocode does.
+pub fn write_cfg(
+    out: &mut dyn Write,
+    function: &Function,
+    label: BlockLabel,
+    symbols: &SymbolTable,
+) -> Result {
+    let name = display_name(&function.name);
+    out.write_fmt(format_args!("digraph \"{}\" {{\n", escape(&name)))?;
+    out.write_str("    node [shape=box, fontname=\"monospace\"];\n")?;
+
+    for block in function.cfg.blocks.values() {
+        let mut text = format!("LAB_{:X}:\n", block.start);
+        match label {
+            BlockLabel::Disassembly => {
+                for inst in block.instructions.iter() {
+                    writeln!(&mut text, "{:X}  {}", inst.ip(), inst)?;
+                }
+            }
+            BlockLabel::Statements => {
+                for expr in block.statements.iter().filter(|expr| !is_noise(expr)) {
+                    writeln!(&mut text, "{};", expr.named(symbols))?;
+                }
+            }
+        }
+        out.write_fmt(format_args!(
+            "    \"{:X}\" [label=\"{}\"];\n",
+            block.start,
+            escape(&text)
+        ))?;
+    }
+
+    for edge in function.cfg.edges.iter() {
+        out.write_fmt(format_args!(
+            "    \"{:X}\" -> \"{:X}\" [color={}];\n",
+            edge.from,
+            edge.to,
+            edge_color(edge.kind)
+        ))?;
+    }
+
+    out.write_str("}\n")
+}
+
+/// Writes the call graph of `functions`; callees which were not lifted are drawn dashed, with
+/// the names of `symbols`, and so are the references to functions which take their address
+/// instead of calling them.
+pub fn write_call_graph(
+    out: &mut dyn Write,
+    functions: &[Function],
+    graph: &CallGraph,
+    symbols: &SymbolTable,
+) -> Result {
+    out.write_str("digraph callgraph {\n")?;
+    out.write_str("    node [shape=box, fontname=\"monospace\"];\n")?;
+
+    let lifted = functions
+        .iter()
+        .map(|function| function.address)
+        .collect::<BTreeSet<_>>();
+    let mut external = BTreeSet::new();
+
+    for function in functions {
+        out.write_fmt(format_args!(
+            "    \"{:X}\" [label=\"{}\"];\n",
+            function.address,
+            escape(&display_name(&function.name))
+        ))?;
+    }
+
+    for function in functions {
+        for (callee, kind) in graph.callees(function.address) {
+            let style = match kind {
+                CallKind::Call => "",
+                // only code is drawn, not the data whose address is taken
+                CallKind::Address if !lifted.contains(&callee) => continue,
+                CallKind::Address => " [style=dashed]",
+            };
+            if !lifted.contains(&callee) {
+                external.insert(callee);
+            }
+            out.write_fmt(format_args!(
+                "    \"{:X}\" -> \"{:X}\"{};\n",
+                function.address, callee, style
+            ))?;
+        }
+    }
+
+    for callee in external {
+        out.write_fmt(format_args!(
+            "    \"{:X}\" [label=\"{}\", style=dashed];\n",
+            callee,
+            escape(&symbols.display_name(callee))
+        ))?;
+    }
+
+    out.write_str("}\n")
+}
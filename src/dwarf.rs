@@ -0,0 +1,823 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::ops::Range;
+
+use gimli::{
+    AttributeValue, DebuggingInformationEntry, DwAt, EndianSlice, EntriesTreeNode, Expression,
+    Operation, Register, RunTimeEndian, UnitOffset, UnitRef,
+};
+use object::{Object, ObjectSection};
+
+use crate::arch::Architecture;
+use crate::ast::{get_register_by_name, BinOpKind, Expr, ExprKind, Lit, Symbol, UnOp};
+use crate::function::Function;
+use crate::memory::memory::Memory;
+use crate::riscv;
+use crate::types::{BaseKind, Prototype, Type, TypeDef, Variable};
+use crate::unwind::UnwindTable;
+
+type Reader<'data> = EndianSlice<'data, RunTimeEndian>;
+type Unit<'a, 'data> = UnitRef<'a, Reader<'data>>;
+type Entry<'a, 'b, 'data> = DebuggingInformationEntry<'a, 'b, Reader<'data>>;
+type Node<'a, 'b, 'c, 'data> = EntriesTreeNode<'a, 'b, 'c, Reader<'data>>;
+
+/// How many scalars of an aggregate variable are named, like `rect.origin.x`.
+const MAX_FIELDS: usize = 256;
+
+/// How deep type references are followed, in case of a malformed cycle.
+const MAX_DEPTH: usize = 16;
+
+/// Where a variable is found.
+#[derive(Clone, Copy, Debug)]
+enum Location {
+    Register(Register),
+    /// At an offset from the canonical frame address.
+    Frame(i64),
+    /// At an offset from the value of a register.
+    Memory(Register, i64),
+}
+
+/// A parameter or local variable, with the places it lives in while the function runs.
+struct LocatedVariable {
+    /// The names of the scalars it is made of by their offset, only its own for a scalar.
+    fields: Vec<(i64, String)>,
+    locations: Vec<(Range<u64>, Location)>,
+}
+
+impl LocatedVariable {
+    fn field(&self, offset: i64) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(start, _)| *start == offset)
+            .map(|(_, name)| name.as_str())
+    }
+}
+
+struct Subprogram {
+    name: String,
+    prototype: Prototype,
+    locals: Vec<Variable>,
+    variables: Vec<LocatedVariable>,
+}
+
+/// A position in the source, from the line tables.
+#[derive(PartialEq, Eq)]
+pub struct SourceLine {
+    pub file: String,
+    pub line: u64,
+}
+
+impl Display for SourceLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}:{}", self.file, self.line))
+    }
+}
+
+/// Names, prototypes, types, variables and line tables read from the DWARF debug info of
+/// binaries which were not stripped.
+#[derive(Default)]
+pub struct DebugInfo {
+    architecture: Option<Architecture>,
+    functions: BTreeMap<u64, Subprogram>,
+    types: Vec<TypeDef>,
+    lines: BTreeMap<u64, SourceLine>,
+}
+
+impl DebugInfo {
+    pub fn from_file(file: &object::File, memory: &Memory) -> Self {
+        let mut info = Self {
+            architecture: Architecture::for_file(file),
+            ..Self::default()
+        };
+        if file.section_by_name(".debug_info").is_none() {
+            return info;
+        }
+        if let Err(err) = info.add_units(file, memory) {
+            eprintln!("warning: cannot read the debug info: {}", err);
+        }
+        info
+    }
+
+    fn add_units(&mut self, file: &object::File, memory: &Memory) -> gimli::Result<()> {
+        let endian = if file.is_little_endian() {
+            RunTimeEndian::Little
+        } else {
+            RunTimeEndian::Big
+        };
+        let sections = gimli::DwarfSections::load(|id| -> gimli::Result<Cow<[u8]>> {
+            Ok(file
+                .section_by_name(id.name())
+                .and_then(|section| section.uncompressed_data().ok())
+                .unwrap_or_default())
+        })?;
+        let dwarf = sections.borrow(|section| EndianSlice::new(section, endian));
+
+        let mut headers = dwarf.units();
+        while let Some(header) = headers.next()? {
+            let unit = dwarf.unit(header)?;
+            let unit = unit.unit_ref(&dwarf);
+            self.add_lines(unit, memory)?;
+
+            let mut tree = unit.entries_tree(None)?;
+            let mut children = tree.root()?.children();
+            while let Some(child) = children.next()? {
+                let entry = child.entry();
+                match entry.tag() {
+                    gimli::DW_TAG_subprogram => self.add_subprogram(unit, child, memory)?,
+                    gimli::DW_TAG_structure_type
+                    | gimli::DW_TAG_class_type
+                    | gimli::DW_TAG_union_type
+                    | gimli::DW_TAG_enumeration_type
+                    | gimli::DW_TAG_typedef => {
+                        if let Some(def) = type_def(unit, entry.offset(), None)? {
+                            if !self.types.contains(&def) {
+                                self.types.push(def);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_lines(&mut self, unit: Unit, memory: &Memory) -> gimli::Result<()> {
+        let Some(program) = unit.line_program.clone() else {
+            return Ok(());
+        };
+        let mut rows = program.rows();
+        while let Some((header, row)) = rows.next_row()? {
+            if row.end_sequence() || !row.is_stmt() || row.address() == 0 {
+                continue;
+            }
+            let (Some(file), Some(line)) = (row.file(header), row.line()) else {
+                continue;
+            };
+            let file = unit
+                .attr_string(file.path_name())?
+                .to_string_lossy()
+                .into_owned();
+            let line = line.get();
+            self.lines
+                .insert(memory.rebase(row.address()), SourceLine { file, line });
+        }
+        Ok(())
+    }
+
+    fn add_subprogram<'data>(
+        &mut self,
+        unit: Unit<'_, 'data>,
+        node: Node<'_, '_, '_, 'data>,
+        memory: &Memory,
+    ) -> gimli::Result<()> {
+        let entry = node.entry();
+        // declarations and abstract instances of inlined functions have no code
+        let Some(low_pc) = entry.attr_value(gimli::DW_AT_low_pc)? else {
+            return Ok(());
+        };
+        let Some(low_pc) = unit.attr_address(low_pc)?.filter(|addr| *addr != 0) else {
+            return Ok(());
+        };
+        let Some(name) = name(unit, entry)? else {
+            return Ok(());
+        };
+        let frame_base = match entry.attr_value(gimli::DW_AT_frame_base)? {
+            Some(AttributeValue::Exprloc(expr)) => location(unit, expr, None)?,
+            _ => None,
+        };
+        let mut ranges = Vec::new();
+        let mut die_ranges = unit.die_ranges(entry)?;
+        while let Some(range) = die_ranges.next()? {
+            ranges.push(memory.rebase(range.begin)..memory.rebase(range.end));
+        }
+
+        let mut subprogram = Subprogram {
+            name,
+            prototype: Prototype {
+                return_type: type_of(unit, entry, 0)?,
+                parameters: Vec::new(),
+                variadic: false,
+                noreturn: matches!(
+                    entry.attr_value(gimli::DW_AT_noreturn)?,
+                    Some(AttributeValue::Flag(true))
+                ),
+            },
+            locals: Vec::new(),
+            variables: Vec::new(),
+        };
+        let scope = Scope {
+            unit,
+            memory,
+            frame_base,
+            ranges: &ranges,
+        };
+        scope.add_variables(node, &mut subprogram)?;
+        self.functions.insert(memory.rebase(low_pc), subprogram);
+        Ok(())
+    }
+
+    /// Returns the start and name of every function with debug info.
+    pub fn functions(&self) -> impl Iterator<Item = (u64, &str)> + '_ {
+        self.functions
+            .iter()
+            .map(|(addr, subprogram)| (*addr, subprogram.name.as_str()))
+    }
+
+    /// Returns the name and prototype of every function with debug info.
+    pub fn prototypes(&self) -> impl Iterator<Item = (&str, &Prototype)> + '_ {
+        self.functions
+            .values()
+            .map(|subprogram| (subprogram.name.as_str(), &subprogram.prototype))
+    }
+
+    /// Returns the structs, unions, enums and typedefs defined by the program.
+    pub fn types(&self) -> &[TypeDef] {
+        &self.types
+    }
+
+    /// Returns the source line starting at `addr`, if one does.
+    pub fn line(&self, addr: u64) -> Option<&SourceLine> {
+        self.lines.get(&addr)
+    }
+
+    /// Gives `function` its prototype and locals, and replaces the registers and stack slots
+    /// holding its variables with their names.
+    pub fn apply(&self, function: &mut Function, unwind: &UnwindTable) {
+        let Some(subprogram) = self.functions.get(&function.address) else {
+            return;
+        };
+        function.prototype = Some(subprogram.prototype.clone());
+        function.locals = subprogram.locals.clone();
+        for block in function.cfg.blocks.values_mut() {
+            for (inst, stmt) in block.instructions.iter().zip(block.statements.iter_mut()) {
+                let addr = inst.ip();
+                let cfa = unwind.cfa(addr);
+                for variable in subprogram.variables.iter() {
+                    for (range, location) in variable.locations.iter() {
+                        let live = range.contains(&addr);
+                        let written = range.contains(&inst.next_ip());
+                        match &mut stmt.kind {
+                            // a register holds the variable once the instruction writing it
+                            // has run, and no longer does while another value replaces it
+                            ExprKind::Assign(lhs, rhs)
+                                if matches!(
+                                    lhs.kind,
+                                    ExprKind::Lit(Lit::Symbol(Symbol::Reg(_)))
+                                ) =>
+                            {
+                                if written {
+                                    self.rename(lhs, variable, *location, cfa);
+                                }
+                                if live {
+                                    self.rename(rhs, variable, *location, cfa);
+                                }
+                            }
+                            _ if live => self.rename(stmt, variable, *location, cfa),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn rename(
+        &self,
+        expr: &mut Expr,
+        variable: &LocatedVariable,
+        location: Location,
+        cfa: Option<(Register, i64)>,
+    ) {
+        let name = match (&expr.kind, location) {
+            (ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))), Location::Register(register))
+                if self.is_register(name, register) =>
+            {
+                variable.field(0)
+            }
+            (ExprKind::Unary(UnOp::Deref, pointer), _) => match (slot(pointer), location) {
+                (Some((name, offset)), Location::Memory(register, start))
+                    if self.is_register(name, register) =>
+                {
+                    variable.field(offset - start)
+                }
+                (Some((name, offset)), Location::Frame(start)) => match cfa {
+                    // the slot is at `register + offset`, the CFA at `register + cfa_offset`
+                    Some((register, cfa_offset)) if self.is_register(name, register) => {
+                        variable.field(offset - cfa_offset - start)
+                    }
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(name) = name {
+            expr.kind = ExprKind::Lit(Lit::Symbol(Symbol::Local(name.to_string())));
+            return;
+        }
+
+        match &mut expr.kind {
+            ExprKind::Call(_, args)
+            | ExprKind::MethodCall(_, args)
+            | ExprKind::Intrinsic(_, args)
+            | ExprKind::Tup(args) => {
+                for arg in args {
+                    self.rename(arg, variable, location, cfa);
+                }
+            }
+            ExprKind::Binary(_, lhs, rhs) | ExprKind::Assign(lhs, rhs) => {
+                self.rename(lhs, variable, location, cfa);
+                self.rename(rhs, variable, location, cfa);
+            }
+            ExprKind::Unary(_, inner) | ExprKind::Cast(inner, _) => {
+                self.rename(inner, variable, location, cfa)
+            }
+            ExprKind::If(cond, body) => {
+                self.rename(cond, variable, location, cfa);
+                for expr in body {
+                    self.rename(expr, variable, location, cfa);
+                }
+            }
+            ExprKind::Loop(body) => {
+                for expr in body {
+                    self.rename(expr, variable, location, cfa);
+                }
+            }
+            ExprKind::Ret(Some(value)) => self.rename(value, variable, location, cfa),
+            _ => {}
+        }
+    }
+
+    /// Returns `true` if `name`, as printed in the lifted code, is the register DWARF numbers
+    /// `register`, or a part of it.
+    fn is_register(&self, name: &str, register: Register) -> bool {
+        match self.architecture {
+            Some(Architecture::X86 { bitness }) => {
+                let dwarf = if bitness == 64 {
+                    gimli::X86_64::register_name(register)
+                } else {
+                    gimli::X86::register_name(register)
+                };
+                match (dwarf.and_then(get_register_by_name), get_register_by_name(name)) {
+                    (Some(dwarf), Some(reg)) => dwarf.full_register() == reg.full_register(),
+                    _ => false,
+                }
+            }
+            Some(Architecture::AArch64) => match register.0 {
+                31 => matches!(name, "sp" | "wsp"),
+                num @ 0..=30 => name.starts_with(['x', 'w']) && name[1..] == num.to_string(),
+                _ => false,
+            },
+            Some(Architecture::RiscV64) => {
+                register.0 < 32 && riscv::register_name(register.0 as u8) == name
+            }
+            None => false,
+        }
+    }
+}
+
+/// Splits the address of a stack slot into the register it is relative to and the offset.
+fn slot(pointer: &Expr) -> Option<(&str, i64)> {
+    let address = match &pointer.kind {
+        ExprKind::Cast(address, _) => address,
+        _ => pointer,
+    };
+    match &address.kind {
+        ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) => Some((name, 0)),
+        ExprKind::Binary(op @ (BinOpKind::Add | BinOpKind::Sub), base, offset) => {
+            let ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) = &base.kind else {
+                return None;
+            };
+            let offset = match offset.kind {
+                ExprKind::Lit(Lit::I64(value)) => value,
+                ExprKind::Lit(Lit::U32(value)) => i64::from(value),
+                ExprKind::Lit(Lit::U64(value)) => value as i64,
+                _ => return None,
+            };
+            match op {
+                BinOpKind::Sub => Some((name, offset.wrapping_neg())),
+                _ => Some((name, offset)),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The function whose variables are being read.
+struct Scope<'a, 'data> {
+    unit: Unit<'a, 'data>,
+    memory: &'a Memory,
+    frame_base: Option<Location>,
+    ranges: &'a [Range<u64>],
+}
+
+impl<'data> Scope<'_, 'data> {
+    /// Adds the parameters and the local variables of the blocks under `node`.
+    fn add_variables(
+        &self,
+        node: Node<'_, '_, '_, 'data>,
+        subprogram: &mut Subprogram,
+    ) -> gimli::Result<()> {
+        let unit = self.unit;
+        let mut children = node.children();
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            match entry.tag() {
+                gimli::DW_TAG_formal_parameter | gimli::DW_TAG_variable => {
+                    let variable = Variable {
+                        name: name(unit, entry)?.unwrap_or_default(),
+                        ty: type_of(unit, entry, 0)?,
+                    };
+                    let mut fields = Vec::new();
+                    let ty = attr(unit, entry, gimli::DW_AT_type)?;
+                    add_fields(unit, ty, variable.name.clone(), 0, &mut fields, 0)?;
+                    let located = LocatedVariable {
+                        fields,
+                        locations: self.locations(entry)?,
+                    };
+                    if !variable.name.is_empty() {
+                        subprogram.variables.push(located);
+                    }
+                    if entry.tag() == gimli::DW_TAG_formal_parameter {
+                        subprogram.prototype.parameters.push(variable);
+                    } else if !variable.name.is_empty() {
+                        subprogram.locals.push(variable);
+                    }
+                }
+                gimli::DW_TAG_unspecified_parameters => subprogram.prototype.variadic = true,
+                gimli::DW_TAG_lexical_block => self.add_variables(child, subprogram)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns where a variable lives; a single location expression holds for the whole
+    /// function, a location list gives one per range.
+    fn locations(
+        &self,
+        entry: &Entry<'_, '_, 'data>,
+    ) -> gimli::Result<Vec<(Range<u64>, Location)>> {
+        let mut locations = Vec::new();
+        match entry.attr_value(gimli::DW_AT_location)? {
+            Some(AttributeValue::Exprloc(expr)) => {
+                if let Some(location) = location(self.unit, expr, self.frame_base)? {
+                    for range in self.ranges {
+                        locations.push((range.clone(), location));
+                    }
+                }
+            }
+            Some(value) => {
+                if let Some(mut entries) = self.unit.attr_locations(value)? {
+                    while let Some(entry) = entries.next()? {
+                        if let Some(location) = location(self.unit, entry.data, self.frame_base)? {
+                            let range = entry.range;
+                            let range =
+                                self.memory.rebase(range.begin)..self.memory.rebase(range.end);
+                            locations.push((range, location));
+                        }
+                    }
+                }
+            }
+            None => {}
+        }
+        Ok(locations)
+    }
+}
+
+/// Evaluates the location expressions compilers give variables and frame bases: a register,
+/// or an offset from a register, the frame base or the CFA.
+fn location(
+    unit: Unit,
+    expr: Expression<Reader>,
+    frame_base: Option<Location>,
+) -> gimli::Result<Option<Location>> {
+    let mut operations = expr.operations(unit.encoding());
+    let Some(operation) = operations.next()? else {
+        return Ok(None);
+    };
+    // pieces and computed values cannot be named as a whole
+    if operations.next()?.is_some() {
+        return Ok(None);
+    }
+    let location = match operation {
+        Operation::Register { register } => Location::Register(register),
+        Operation::RegisterOffset {
+            register, offset, ..
+        } => Location::Memory(register, offset),
+        Operation::CallFrameCFA => Location::Frame(0),
+        Operation::FrameOffset { offset } => match frame_base {
+            Some(Location::Frame(base)) => Location::Frame(base + offset),
+            Some(Location::Memory(register, base)) => Location::Memory(register, base + offset),
+            Some(Location::Register(register)) => Location::Memory(register, offset),
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(location))
+}
+
+/// Returns the value of `at`, looking it up in the declaration or abstract instance a DIE
+/// completes when it lacks it.
+fn attr<'data>(
+    unit: Unit<'_, 'data>,
+    entry: &Entry<'_, '_, 'data>,
+    at: DwAt,
+) -> gimli::Result<Option<AttributeValue<Reader<'data>>>> {
+    if let Some(value) = entry.attr_value(at)? {
+        return Ok(Some(value));
+    }
+    for origin in [gimli::DW_AT_abstract_origin, gimli::DW_AT_specification] {
+        if let Some(AttributeValue::UnitRef(offset)) = entry.attr_value(origin)? {
+            return attr(unit, &unit.entry(offset)?, at);
+        }
+    }
+    Ok(None)
+}
+
+fn name<'data>(
+    unit: Unit<'_, 'data>,
+    entry: &Entry<'_, '_, 'data>,
+) -> gimli::Result<Option<String>> {
+    match attr(unit, entry, gimli::DW_AT_name)? {
+        Some(value) => Ok(Some(
+            unit.attr_string(value)?.to_string_lossy().into_owned(),
+        )),
+        None => Ok(None),
+    }
+}
+
+fn udata(entry: &Entry<'_, '_, '_>, at: DwAt) -> gimli::Result<Option<u64>> {
+    Ok(entry.attr_value(at)?.and_then(|value| value.udata_value()))
+}
+
+/// Returns the type of a variable, member or function, `void` when it has none.
+fn type_of<'data>(
+    unit: Unit<'_, 'data>,
+    entry: &Entry<'_, '_, 'data>,
+    depth: usize,
+) -> gimli::Result<Type> {
+    match attr(unit, entry, gimli::DW_AT_type)? {
+        Some(AttributeValue::UnitRef(offset)) if depth < MAX_DEPTH => {
+            read_type(unit, offset, depth + 1)
+        }
+        _ => Ok(Type::Void),
+    }
+}
+
+fn read_type(unit: Unit, offset: UnitOffset, depth: usize) -> gimli::Result<Type> {
+    let entry = unit.entry(offset)?;
+    let tag = || -> gimli::Result<String> { Ok(name(unit, &entry)?.unwrap_or_default()) };
+    let ty = match entry.tag() {
+        gimli::DW_TAG_base_type => {
+            let kind = match entry.attr_value(gimli::DW_AT_encoding)? {
+                Some(AttributeValue::Encoding(gimli::DW_ATE_boolean)) => BaseKind::Bool,
+                Some(AttributeValue::Encoding(gimli::DW_ATE_float)) => BaseKind::Float,
+                Some(AttributeValue::Encoding(
+                    gimli::DW_ATE_unsigned | gimli::DW_ATE_unsigned_char,
+                )) => BaseKind::Unsigned,
+                _ => BaseKind::Signed,
+            };
+            Type::Base {
+                name: tag()?,
+                kind,
+                size: udata(&entry, gimli::DW_AT_byte_size)?.unwrap_or(0),
+            }
+        }
+        gimli::DW_TAG_pointer_type
+        | gimli::DW_TAG_reference_type
+        | gimli::DW_TAG_rvalue_reference_type => {
+            Type::Pointer(Box::new(type_of(unit, &entry, depth)?))
+        }
+        gimli::DW_TAG_const_type => Type::Const(Box::new(type_of(unit, &entry, depth)?)),
+        gimli::DW_TAG_volatile_type | gimli::DW_TAG_restrict_type | gimli::DW_TAG_atomic_type => {
+            type_of(unit, &entry, depth)?
+        }
+        gimli::DW_TAG_typedef => Type::Typedef(tag()?),
+        gimli::DW_TAG_structure_type | gimli::DW_TAG_class_type => Type::Struct(tag()?),
+        gimli::DW_TAG_union_type => Type::Union(tag()?),
+        gimli::DW_TAG_enumeration_type => Type::Enum(tag()?),
+        gimli::DW_TAG_array_type => {
+            let element = type_of(unit, &entry, depth)?;
+            Type::Array(Box::new(element), array_len(unit, offset)?)
+        }
+        gimli::DW_TAG_subroutine_type => {
+            let mut prototype = Prototype {
+                return_type: type_of(unit, &entry, depth)?,
+                parameters: Vec::new(),
+                variadic: false,
+                noreturn: false,
+            };
+            let mut tree = unit.entries_tree(Some(offset))?;
+            let mut children = tree.root()?.children();
+            while let Some(child) = children.next()? {
+                let entry = child.entry();
+                match entry.tag() {
+                    gimli::DW_TAG_formal_parameter => prototype.parameters.push(Variable {
+                        name: name(unit, entry)?.unwrap_or_default(),
+                        ty: type_of(unit, entry, depth)?,
+                    }),
+                    gimli::DW_TAG_unspecified_parameters => prototype.variadic = true,
+                    _ => {}
+                }
+            }
+            Type::Function(Box::new(prototype))
+        }
+        _ => Type::Void,
+    };
+    Ok(ty)
+}
+
+/// Returns the number of elements of an array type, from its first dimension.
+fn array_len(unit: Unit, offset: UnitOffset) -> gimli::Result<Option<u64>> {
+    let mut tree = unit.entries_tree(Some(offset))?;
+    let mut children = tree.root()?.children();
+    while let Some(child) = children.next()? {
+        let entry = child.entry();
+        if entry.tag() == gimli::DW_TAG_subrange_type {
+            if let Some(count) = udata(entry, gimli::DW_AT_count)? {
+                return Ok(Some(count));
+            }
+            return Ok(udata(entry, gimli::DW_AT_upper_bound)?.map(|bound| bound + 1));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the size in bytes of the type at `offset`.
+fn size_of(unit: Unit, offset: UnitOffset, depth: usize) -> gimli::Result<Option<u64>> {
+    let entry = unit.entry(offset)?;
+    if let Some(size) = udata(&entry, gimli::DW_AT_byte_size)? {
+        return Ok(Some(size));
+    }
+    let inner = match entry.attr_value(gimli::DW_AT_type)? {
+        Some(AttributeValue::UnitRef(inner)) if depth < MAX_DEPTH => inner,
+        _ => return Ok(None),
+    };
+    match entry.tag() {
+        gimli::DW_TAG_pointer_type | gimli::DW_TAG_reference_type => {
+            Ok(Some(u64::from(unit.encoding().address_size)))
+        }
+        gimli::DW_TAG_array_type => {
+            let len = array_len(unit, offset)?;
+            Ok(size_of(unit, inner, depth + 1)?
+                .zip(len)
+                .map(|(size, len)| size * len))
+        }
+        _ => size_of(unit, inner, depth + 1),
+    }
+}
+
+/// Names the scalars a variable of the type `ty` is made of, e.g. `rect.origin.x`, by their
+/// offset from `base`.
+fn add_fields<'data>(
+    unit: Unit<'_, 'data>,
+    ty: Option<AttributeValue<Reader<'data>>>,
+    name: String,
+    base: i64,
+    fields: &mut Vec<(i64, String)>,
+    depth: usize,
+) -> gimli::Result<()> {
+    if fields.len() >= MAX_FIELDS {
+        return Ok(());
+    }
+    let offset = match ty {
+        Some(AttributeValue::UnitRef(offset)) if depth < MAX_DEPTH => offset,
+        _ => {
+            fields.push((base, name));
+            return Ok(());
+        }
+    };
+    let entry = unit.entry(offset)?;
+    match entry.tag() {
+        gimli::DW_TAG_typedef
+        | gimli::DW_TAG_const_type
+        | gimli::DW_TAG_volatile_type
+        | gimli::DW_TAG_atomic_type => {
+            let ty = entry.attr_value(gimli::DW_AT_type)?;
+            add_fields(unit, ty, name, base, fields, depth + 1)
+        }
+        gimli::DW_TAG_structure_type | gimli::DW_TAG_class_type | gimli::DW_TAG_union_type => {
+            let mut tree = unit.entries_tree(Some(offset))?;
+            let mut children = tree.root()?.children();
+            while let Some(child) = children.next()? {
+                let member = child.entry();
+                if member.tag() != gimli::DW_TAG_member {
+                    continue;
+                }
+                let start = udata(member, gimli::DW_AT_data_member_location)?.unwrap_or(0);
+                let member_name = self::name(unit, member)?.unwrap_or_default();
+                add_fields(
+                    unit,
+                    member.attr_value(gimli::DW_AT_type)?,
+                    format!("{name}.{member_name}"),
+                    base + start as i64,
+                    fields,
+                    depth + 1,
+                )?;
+            }
+            Ok(())
+        }
+        gimli::DW_TAG_array_type => {
+            let element = entry.attr_value(gimli::DW_AT_type)?;
+            let size = match element {
+                Some(AttributeValue::UnitRef(element)) => size_of(unit, element, 0)?,
+                _ => None,
+            };
+            match (size, array_len(unit, offset)?) {
+                (Some(size), Some(len)) if size > 0 => {
+                    for i in 0..len.min(MAX_FIELDS as u64) {
+                        let start = base + (i * size) as i64;
+                        let name = format!("{name}[{i}]");
+                        add_fields(unit, element, name, start, fields, depth + 1)?;
+                    }
+                }
+                _ => fields.push((base, name)),
+            }
+            Ok(())
+        }
+        _ => {
+            fields.push((base, name));
+            Ok(())
+        }
+    }
+}
+
+/// Reads the definition of a struct, union, enum or typedef; an anonymous struct, union or
+/// enum is read through the typedef naming it, and named `typedef`.
+fn type_def(
+    unit: Unit,
+    offset: UnitOffset,
+    typedef: Option<String>,
+) -> gimli::Result<Option<TypeDef>> {
+    let entry = unit.entry(offset)?;
+    if entry.attr_value(gimli::DW_AT_declaration)?.is_some() {
+        return Ok(None);
+    }
+    let tag = name(unit, &entry)?;
+    let union = match entry.tag() {
+        gimli::DW_TAG_typedef => {
+            let Some(name) = tag else {
+                return Ok(None);
+            };
+            if let Some(AttributeValue::UnitRef(target)) = entry.attr_value(gimli::DW_AT_type)? {
+                let anonymous = unit.entry(target)?.attr_value(gimli::DW_AT_name)?.is_none();
+                let def = type_def(unit, target, Some(name.clone()))?;
+                if anonymous && def.is_some() {
+                    return Ok(def);
+                }
+            }
+            let ty = type_of(unit, &entry, 0)?;
+            return Ok(Some(TypeDef::Typedef(Variable { name, ty })));
+        }
+        _ if tag.is_none() && typedef.is_none() => return Ok(None),
+        // a named record is defined on its own, the typedef then only aliases it
+        _ if tag.is_some() && typedef.is_some() => return Ok(None),
+        gimli::DW_TAG_structure_type | gimli::DW_TAG_class_type => false,
+        gimli::DW_TAG_union_type => true,
+        gimli::DW_TAG_enumeration_type => {
+            let mut enumerators = Vec::new();
+            let mut tree = unit.entries_tree(Some(offset))?;
+            let mut children = tree.root()?.children();
+            while let Some(child) = children.next()? {
+                let entry = child.entry();
+                if entry.tag() != gimli::DW_TAG_enumerator {
+                    continue;
+                }
+                let value = match entry.attr_value(gimli::DW_AT_const_value)? {
+                    Some(AttributeValue::Sdata(value)) => value,
+                    Some(value) => value.udata_value().unwrap_or(0) as i64,
+                    None => 0,
+                };
+                enumerators.push((name(unit, entry)?.unwrap_or_default(), value));
+            }
+            return Ok(Some(TypeDef::Enum {
+                tag,
+                typedef,
+                enumerators,
+            }));
+        }
+        _ => return Ok(None),
+    };
+
+    let mut members = Vec::new();
+    let mut tree = unit.entries_tree(Some(offset))?;
+    let mut children = tree.root()?.children();
+    while let Some(child) = children.next()? {
+        let entry = child.entry();
+        if entry.tag() == gimli::DW_TAG_member {
+            members.push(Variable {
+                name: name(unit, entry)?.unwrap_or_default(),
+                ty: type_of(unit, entry, 0)?,
+            });
+        }
+    }
+    Ok(Some(TypeDef::Record {
+        union,
+        tag,
+        typedef,
+        members,
+    }))
+}
@@ -0,0 +1,729 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::{Result, Write};
+
+use crate::aarch64_parser::get_goto;
+use crate::abi::{Conventions, RegisterAbi};
+use crate::arch::Architecture;
+use crate::ast::*;
+use crate::function::Function;
+use crate::riscv;
+use crate::rtti::{declaration, display_name};
+use crate::symbols::SymbolTable;
+use crate::types::{rust_identifier, TypeDef};
+
+const INDENT: &str = "    ";
+
+/// Renders lifted functions as source-like pseudocode.
+pub trait Emitter {
+    fn emit_function(&self, out: &mut dyn Write, function: &Function) -> Result;
+
+    /// Writes the definition of a type known from the debug info.
+    fn emit_type(&self, out: &mut dyn Write, def: &TypeDef) -> Result;
+}
+
+/// Returns `true` for statements which carry no information worth printing.
+pub fn is_noise(expr: &Expr) -> bool {
+    matches!(expr.kind, ExprKind::Lit(Lit::StillUnknown))
+}
+
+/// C-like output, relying on the `Display` impls of the AST.
+pub struct CEmitter<'a> {
+    pub symbols: &'a SymbolTable,
+}
+
+impl CEmitter<'_> {
+    fn emit_statement(&self, out: &mut dyn Write, expr: &Expr, depth: usize) -> Result {
+        let indent = INDENT.repeat(depth);
+        match &expr.kind {
+            ExprKind::If(cond, body) => {
+                out.write_fmt(format_args!("{indent}if ({}) {{\n", cond.named(self.symbols)))?;
+                for expr in body.iter().filter(|expr| !is_noise(expr)) {
+                    self.emit_statement(out, expr, depth + 1)?;
+                }
+                out.write_fmt(format_args!("{indent}}}\n"))
+            }
+            ExprKind::Switch(index, cases) => {
+                out.write_fmt(format_args!("{indent}switch ({}) {{\n", index.named(self.symbols)))?;
+                for (value, target) in cases {
+                    out.write_fmt(format_args!("{indent}case 0x{value:X}: goto LAB_{target:X};\n"))?;
+                }
+                out.write_fmt(format_args!("{indent}}}\n"))
+            }
+            ExprKind::Loop(body) if body.iter().all(is_noise) => {
+                out.write_fmt(format_args!("{indent}do {{ }} while (true);\n"))
+            }
+            ExprKind::Loop(body) => {
+                out.write_fmt(format_args!("{indent}do {{\n"))?;
+                for expr in body.iter().filter(|expr| !is_noise(expr)) {
+                    self.emit_statement(out, expr, depth + 1)?;
+                }
+                out.write_fmt(format_args!("{indent}}} while (true);\n"))
+            }
+            _ => out.write_fmt(format_args!("{indent}{};\n", expr.named(self.symbols))),
+        }
+    }
+}
+
+impl Emitter for CEmitter<'_> {
+    fn emit_function(&self, out: &mut dyn Write, function: &Function) -> Result {
+        // C++ functions are headed with the parameters their decoration spells
+        let prototype = function.prototype.as_ref();
+        match (declaration(&function.name, prototype), prototype) {
+            (Some(declaration), _) => out.write_fmt(format_args!("{declaration}\n{{\n"))?,
+            (None, Some(prototype)) => {
+                out.write_fmt(format_args!("{}\n{{\n", prototype.declare(&display_name(&function.name))))?
+            }
+            (None, None) => {
+                out.write_fmt(format_args!("void {}(void)\n{{\n", display_name(&function.name)))?
+            }
+        }
+        for local in function.locals.iter() {
+            out.write_fmt(format_args!("{INDENT}{};\n", local.ty.declare(&local.name)))?;
+        }
+        if !function.locals.is_empty() {
+            out.write_str("\n")?;
+        }
+        for block in function.cfg.ordered_blocks(function.address) {
+            if function.needs_label(block.start) {
+                out.write_fmt(format_args!("LAB_{:X}:\n", block.start))?;
+            }
+            for expr in block.statements.iter().filter(|expr| !is_noise(expr)) {
+                self.emit_statement(out, expr, 1)?;
+            }
+            if let Some(to) = function.detached_fallthrough(block.start) {
+                self.emit_statement(out, &get_goto(to), 1)?;
+            }
+        }
+        out.write_str("}\n")
+    }
+
+    fn emit_type(&self, out: &mut dyn Write, def: &TypeDef) -> Result {
+        def.write_c(out)
+    }
+}
+
+/// Rust-like output: `let` bindings, `unsafe` raw-pointer derefs, `loop` and typed literals.
+pub struct RustEmitter<'a> {
+    pub symbols: &'a SymbolTable,
+    /// Gives the types of the registers declared.
+    pub architecture: Architecture,
+    /// Tells the registers the parameters are passed in.
+    pub conventions: &'a Conventions,
+}
+
+impl RustEmitter<'_> {
+    /// Returns the register each parameter of `function` is passed in, by the name the
+    /// calling convention gives it, with the name and Rust type of the parameter. Those
+    /// passed on the stack are left out.
+    fn parameter_registers(&self, function: &Function) -> Vec<(String, String, String)> {
+        let Some(prototype) = &function.prototype else {
+            return Vec::new();
+        };
+        let convention = self.conventions.of(function.address);
+        let integers = RegisterAbi::new(self.architecture, convention).arguments;
+        let floats = match self.architecture {
+            Architecture::X86 { .. } => convention
+                .float_argument_registers()
+                .iter()
+                .map(|reg| get_register_name(*reg))
+                .collect(),
+            Architecture::AArch64 => (0..8).map(|num| format!("d{num}")).collect(),
+            Architecture::RiscV64 => riscv::FP_REGISTER_NAMES[10..18]
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>(),
+        };
+        let (mut integer, mut float) = (0, 0);
+        let mut registers = Vec::new();
+        for (position, parameter) in prototype.parameters.iter().enumerate() {
+            let ty = parameter.ty.rust();
+            let (all, next) = match ty.as_str() {
+                "f32" | "f64" => (&floats, &mut float),
+                _ => (&integers, &mut integer),
+            };
+            let n = if convention.positional_arguments() { position } else { *next };
+            *next += 1;
+            let name = match parameter.name.as_str() {
+                "" => format!("arg_{position}"),
+                name => rust_identifier(name),
+            };
+            if let Some(register) = all.get(n) {
+                registers.push((register.clone(), name, ty));
+            }
+        }
+        registers
+    }
+
+    fn emit_statement(&self, out: &mut dyn Write, expr: &Expr, depth: usize) -> Result {
+        let indent = INDENT.repeat(depth);
+        match &expr.kind {
+            ExprKind::Assign(lhs, rhs) => match &lhs.kind {
+                ExprKind::Unary(UnOp::Deref, ptr) => {
+                    out.write_fmt(format_args!("{indent}unsafe {{ *"))?;
+                    self.emit_pointer(out, ptr, true)?;
+                    out.write_str(" = ")?;
+                    self.emit_typed(out, rhs, self.expr_ty(lhs).as_deref())?;
+                    out.write_str(" };\n")
+                }
+                _ => {
+                    out.write_str(&indent)?;
+                    self.emit_expr(out, lhs)?;
+                    out.write_str(" = ")?;
+                    self.emit_typed(out, rhs, self.expr_ty(lhs).as_deref())?;
+                    out.write_str(";\n")
+                }
+            },
+            ExprKind::If(cond, body) => {
+                out.write_fmt(format_args!("{indent}if "))?;
+                self.emit_expr(out, cond)?;
+                out.write_str(" {\n")?;
+                for expr in body.iter().filter(|expr| !is_noise(expr)) {
+                    self.emit_statement(out, expr, depth + 1)?;
+                }
+                out.write_fmt(format_args!("{indent}}}\n"))
+            }
+            ExprKind::Goto(addr) => out.write_fmt(format_args!("{indent}goto!('LAB_{:X});\n", addr)),
+            ExprKind::Switch(index, cases) => {
+                out.write_fmt(format_args!("{indent}match "))?;
+                self.emit_expr(out, index)?;
+                out.write_str(" {\n")?;
+                for (value, target) in cases {
+                    out.write_fmt(format_args!(
+                        "{indent}{INDENT}0x{value:X} => goto!('LAB_{target:X}),\n"
+                    ))?;
+                }
+                out.write_fmt(format_args!("{indent}{INDENT}_ => unreachable!(),\n{indent}}}\n"))
+            }
+            ExprKind::Loop(body) if body.iter().all(is_noise) => {
+                out.write_fmt(format_args!("{indent}loop {{}}\n"))
+            }
+            ExprKind::Loop(body) => {
+                out.write_fmt(format_args!("{indent}loop {{\n"))?;
+                for expr in body.iter().filter(|expr| !is_noise(expr)) {
+                    self.emit_statement(out, expr, depth + 1)?;
+                }
+                out.write_fmt(format_args!("{indent}}}\n"))
+            }
+            ExprKind::Ret(None) => out.write_fmt(format_args!("{indent}return;\n")),
+            ExprKind::Ret(Some(value)) => {
+                out.write_fmt(format_args!("{indent}return "))?;
+                self.emit_expr(out, value)?;
+                out.write_str(";\n")
+            }
+            ExprKind::Unparsed(inst) => {
+                out.write_fmt(format_args!("{indent}asm!({:?});\n", inst.to_string()))
+            }
+            _ => {
+                out.write_str(&indent)?;
+                self.emit_expr(out, expr)?;
+                out.write_str(";\n")
+            }
+        }
+    }
+
+    fn emit_expr(&self, out: &mut dyn Write, expr: &Expr) -> Result {
+        match &expr.kind {
+            ExprKind::Call(addr, args) => {
+                let name = rust_identifier(&self.symbols.function_name(*addr));
+                out.write_fmt(format_args!("{name}("))?;
+                self.emit_list(out, args)?;
+                out.write_str(")")
+            }
+            ExprKind::Intrinsic(name, args) => {
+                out.write_fmt(format_args!("{name}("))?;
+                self.emit_list(out, args)?;
+                out.write_str(")")
+            }
+            ExprKind::MethodCall(method, args) => {
+                if let Some(object) = args.first() {
+                    self.emit_operand(out, object)?;
+                    out.write_str(".")?;
+                }
+                out.write_fmt(format_args!("{}(", rust_identifier(method)))?;
+                self.emit_list(out, args.get(1..).unwrap_or_default())?;
+                out.write_str(")")
+            }
+            ExprKind::Tup(items) => {
+                out.write_str("(")?;
+                self.emit_list(out, items)?;
+                out.write_str(")")
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                let ty = self.expr_ty(lhs).or_else(|| self.expr_ty(rhs));
+                let ty = ty.as_deref().filter(|ty| is_integer(ty));
+                match lhs.kind {
+                    ExprKind::Lit(_) => self.emit_typed(out, lhs, ty)?,
+                    _ => self.emit_operand(out, lhs)?,
+                }
+                // a negative displacement of an unsigned value is subtracted instead
+                let (op, rhs_value) = match (op, &rhs.kind, ty) {
+                    (BinOpKind::Add, ExprKind::Lit(Lit::I64(value)), Some(ty))
+                        if *value < 0 && ty.starts_with('u') =>
+                    {
+                        (&BinOpKind::Sub, Some(value.unsigned_abs()))
+                    }
+                    (BinOpKind::Sub, ExprKind::Lit(Lit::I64(value)), Some(ty))
+                        if *value < 0 && ty.starts_with('u') =>
+                    {
+                        (&BinOpKind::Add, Some(value.unsigned_abs()))
+                    }
+                    _ => (op, None),
+                };
+                out.write_fmt(format_args!(" {} ", rust_bin_op(op)))?;
+                match (rhs_value, ty) {
+                    (Some(value), Some(ty)) => out.write_fmt(format_args!("0x{value:X}_{ty}")),
+                    _ if matches!(rhs.kind, ExprKind::Lit(_)) => self.emit_typed(out, rhs, ty),
+                    _ => self.emit_operand(out, rhs),
+                }
+            }
+            ExprKind::Unary(UnOp::Deref, ptr) => {
+                out.write_str("unsafe { *")?;
+                self.emit_pointer(out, ptr, false)?;
+                out.write_str(" }")
+            }
+            ExprKind::Unary(op, inner) => {
+                out.write_str(match op {
+                    UnOp::Not | UnOp::LogicalNot => "!",
+                    _ => "-",
+                })?;
+                self.emit_operand(out, inner)
+            }
+            ExprKind::Lit(lit) => self.emit_lit(out, lit),
+            ExprKind::Cast(inner, ty) => {
+                self.emit_operand(out, inner)?;
+                out.write_fmt(format_args!(" as {}", rust_ty(ty, false)))
+            }
+            ExprKind::Loop(_)
+            | ExprKind::If(..)
+            | ExprKind::Goto(_)
+            | ExprKind::Switch(..)
+            | ExprKind::Ret(_)
+            | ExprKind::Assign(..)
+            | ExprKind::Unparsed(_) => {
+                out.write_str("{ ")?;
+                self.emit_statement(out, expr, 0)?;
+                out.write_str(" }")
+            }
+        }
+    }
+
+    /// Writes `exprs` separated by commas.
+    fn emit_list(&self, out: &mut dyn Write, exprs: &[Expr]) -> Result {
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            self.emit_expr(out, expr)?;
+        }
+        Ok(())
+    }
+
+    /// Writes `expr`, parenthesized when it would not bind tighter than its parent.
+    fn emit_operand(&self, out: &mut dyn Write, expr: &Expr) -> Result {
+        if matches!(expr.kind, ExprKind::Binary(..) | ExprKind::Cast(..)) {
+            out.write_str("(")?;
+            self.emit_expr(out, expr)?;
+            out.write_str(")")
+        } else {
+            self.emit_expr(out, expr)
+        }
+    }
+
+    /// Writes the pointer operand of a dereference, as `*mut` when it is written through.
+    fn emit_pointer(&self, out: &mut dyn Write, ptr: &Expr, mutable: bool) -> Result {
+        out.write_str("(")?;
+        match &ptr.kind {
+            ExprKind::Cast(inner, ty) => {
+                self.emit_operand(out, inner)?;
+                out.write_fmt(format_args!(" as {}", rust_ty(ty, mutable)))?;
+            }
+            _ => self.emit_expr(out, ptr)?,
+        }
+        out.write_str(")")
+    }
+
+    /// Writes `expr`, spelling an integer literal as one of the integer type `ty` of the value
+    /// it is combined with, when that is known.
+    fn emit_typed(&self, out: &mut dyn Write, expr: &Expr, ty: Option<&str>) -> Result {
+        let value = match &expr.kind {
+            ExprKind::Lit(Lit::U32(value)) => i128::from(*value),
+            ExprKind::Lit(Lit::U64(value)) => i128::from(*value),
+            ExprKind::Lit(Lit::I64(value)) => i128::from(*value),
+            _ => return self.emit_expr(out, expr),
+        };
+        let Some(ty) = ty.filter(|ty| is_integer(ty)) else {
+            return self.emit_expr(out, expr);
+        };
+        let bits = ty[1..].parse::<u32>().unwrap_or(64);
+        // the value is wrapped to the width of the type, as the instruction computes it
+        let unsigned = value & ((1i128 << bits) - 1);
+        if ty.starts_with('i') && unsigned >> (bits - 1) != 0 {
+            out.write_fmt(format_args!("-0x{:X}_{ty}", (1i128 << bits) - unsigned))
+        } else {
+            out.write_fmt(format_args!("0x{unsigned:X}_{ty}"))
+        }
+    }
+
+    /// Returns the Rust type of the value of `expr`, when the registers, casts or literals
+    /// in it tell it.
+    fn expr_ty(&self, expr: &Expr) -> Option<String> {
+        match &expr.kind {
+            ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) => {
+                register_ty(self.architecture, &name.to_lowercase()).map(str::to_string)
+            }
+            ExprKind::Lit(Lit::U32(_)) => Some("u32".to_string()),
+            ExprKind::Lit(Lit::U64(_)) => Some("u64".to_string()),
+            ExprKind::Lit(Lit::F32(_)) => Some("f32".to_string()),
+            ExprKind::Lit(Lit::F64(_)) => Some("f64".to_string()),
+            ExprKind::Lit(Lit::Bool(_)) => Some("bool".to_string()),
+            ExprKind::Cast(_, ty) => Some(rust_ty(ty, false)),
+            ExprKind::Unary(UnOp::Deref, ptr) => match &ptr.kind {
+                ExprKind::Cast(_, Ty::Ptr(inner)) => Some(rust_ty(inner, false)),
+                _ => None,
+            },
+            ExprKind::Unary(UnOp::LogicalNot, _) => Some("bool".to_string()),
+            ExprKind::Unary(_, inner) => self.expr_ty(inner),
+            ExprKind::Binary(op, lhs, rhs) => match op {
+                BinOpKind::Eq
+                | BinOpKind::Ne
+                | BinOpKind::Lt
+                | BinOpKind::Le
+                | BinOpKind::Gt
+                | BinOpKind::Ge => Some("bool".to_string()),
+                // the shift amount need not have the type of the value shifted
+                BinOpKind::Shl | BinOpKind::Shr => self.expr_ty(lhs),
+                _ => self.expr_ty(lhs).or_else(|| self.expr_ty(rhs)),
+            },
+            _ => None,
+        }
+    }
+
+    fn emit_lit(&self, out: &mut dyn Write, lit: &Lit) -> Result {
+        match lit {
+            Lit::StillUnknown => out.write_str("_"),
+            Lit::Symbol(sym) => out.write_str(&symbol_name(sym)),
+            Lit::Bool(value) => out.write_fmt(format_args!("{value}")),
+            Lit::U32(value) => out.write_fmt(format_args!("0x{:X}_u32", value)),
+            Lit::U64(value) => out.write_fmt(format_args!("0x{:X}_u64", value)),
+            Lit::I64(value) if *value < 0 => {
+                out.write_fmt(format_args!("-0x{:X}_i64", value.unsigned_abs()))
+            }
+            Lit::I64(value) => out.write_fmt(format_args!("0x{:X}_i64", value)),
+            Lit::F32(value) => out.write_str(&rust_float(*value, "f32")),
+            Lit::F64(value) => out.write_str(&rust_float(*value, "f64")),
+            Lit::Str(value) => out.write_fmt(format_args!("{:?}", value)),
+        }
+    }
+}
+
+impl Emitter for RustEmitter<'_> {
+    fn emit_function(&self, out: &mut dyn Write, function: &Function) -> Result {
+        let mut declared = HashSet::new();
+        match &function.prototype {
+            Some(prototype) => {
+                out.write_fmt(format_args!("{} {{\n", prototype.rust_signature(&function.name)))?;
+                let names = prototype.parameters.iter().map(|parameter| &parameter.name);
+                declared.extend(names.map(|name| rust_identifier(name)));
+            }
+            None => out.write_fmt(format_args!("fn {}() {{\n", rust_identifier(&function.name)))?,
+        }
+        for local in function.locals.iter() {
+            let name = rust_identifier(&local.name);
+            out.write_fmt(format_args!("{INDENT}let mut {name}: {};\n", local.ty.rust()))?;
+            declared.insert(name);
+        }
+        // the registers and variables are all declared up front, those read before any
+        // assignment holding the values the function is entered with
+        let mut entry = declared.clone();
+        let mut undeclared = BTreeSet::new();
+        let mut assigned = BTreeSet::new();
+        for block in function.cfg.ordered_blocks(function.address) {
+            for expr in block.statements.iter().filter(|expr| !is_noise(expr)) {
+                collect_undeclared(expr, &mut entry, &mut undeclared);
+                collect_assigned(expr, &mut assigned);
+            }
+        }
+        let parameters = self.parameter_registers(function);
+        let abi = RegisterAbi::new(self.architecture, self.conventions.of(function.address));
+        for name in undeclared.union(&assigned).filter(|name| !declared.contains(*name)) {
+            let ty = register_ty(self.architecture, name);
+            out.write_fmt(format_args!("{INDENT}let mut {name}"))?;
+            if let Some(ty) = ty {
+                out.write_fmt(format_args!(": {ty}"))?;
+            }
+            // the argument registers are bound to the parameters they hold
+            let whole = abi.whole(name).map(|(whole, _)| whole);
+            let parameter = parameters.iter().find(|(register, ..)| {
+                register.eq_ignore_ascii_case(name)
+                    || whole.as_ref().is_some_and(|whole| whole.eq_ignore_ascii_case(register))
+            });
+            let bound = parameter.filter(|_| undeclared.contains(name));
+            if let Some((_, parameter, parameter_ty)) = bound {
+                out.write_fmt(format_args!(" = {parameter}"))?;
+                match ty {
+                    Some(ty) if ty != parameter_ty => out.write_fmt(format_args!(" as {ty}"))?,
+                    _ => {}
+                }
+            }
+            out.write_str(";\n")?;
+        }
+        for block in function.cfg.ordered_blocks(function.address) {
+            if function.needs_label(block.start) {
+                out.write_fmt(format_args!("'LAB_{:X}:\n", block.start))?;
+            }
+            for expr in block.statements.iter().filter(|expr| !is_noise(expr)) {
+                self.emit_statement(out, expr, 1)?;
+            }
+            if let Some(to) = function.detached_fallthrough(block.start) {
+                self.emit_statement(out, &get_goto(to), 1)?;
+            }
+        }
+        out.write_str("}\n")
+    }
+
+    fn emit_type(&self, out: &mut dyn Write, def: &TypeDef) -> Result {
+        def.write_rust(out)
+    }
+}
+
+/// Collects the names of the registers and variables `expr` assigns, in its nested
+/// statements too.
+fn collect_assigned(expr: &Expr, names: &mut BTreeSet<String>) {
+    match &expr.kind {
+        ExprKind::Assign(lhs, _) => {
+            if let ExprKind::Lit(Lit::Symbol(sym @ (Symbol::Reg(_) | Symbol::Var(_)))) = &lhs.kind {
+                names.insert(symbol_name(sym));
+            }
+        }
+        ExprKind::If(_, body) | ExprKind::Loop(body) => {
+            body.iter().for_each(|expr| collect_assigned(expr, names));
+        }
+        _ => {}
+    }
+}
+
+/// Calls `f` on each symbol `expr` reads.
+fn visit_symbols(expr: &Expr, f: &mut dyn FnMut(&Symbol)) {
+    match &expr.kind {
+        ExprKind::Lit(Lit::Symbol(sym)) => f(sym),
+        ExprKind::Call(_, args)
+        | ExprKind::MethodCall(_, args)
+        | ExprKind::Intrinsic(_, args)
+        | ExprKind::Tup(args) => args.iter().for_each(|arg| visit_symbols(arg, f)),
+        ExprKind::Binary(_, lhs, rhs) | ExprKind::Assign(lhs, rhs) => {
+            visit_symbols(lhs, f);
+            visit_symbols(rhs, f);
+        }
+        ExprKind::Unary(_, inner) | ExprKind::Cast(inner, _) => visit_symbols(inner, f),
+        ExprKind::Ret(Some(inner)) | ExprKind::Switch(inner, _) => visit_symbols(inner, f),
+        _ => {}
+    }
+}
+
+/// Collects the names of the registers and variables `expr` reads before they are assigned
+/// into `undeclared`, following the declarations `emit_statement` makes. The named symbols
+/// are locals, declared already, or globals.
+fn collect_undeclared(
+    expr: &Expr,
+    declared: &mut HashSet<String>,
+    undeclared: &mut BTreeSet<String>,
+) {
+    let mut read = |expr: &Expr, declared: &mut HashSet<String>| {
+        visit_symbols(expr, &mut |sym| {
+            let name = symbol_name(sym);
+            if !matches!(sym, Symbol::Local(_)) && declared.insert(name.clone()) {
+                undeclared.insert(name);
+            }
+        });
+    };
+    match &expr.kind {
+        ExprKind::Assign(lhs, rhs) => {
+            read(rhs, declared);
+            match &lhs.kind {
+                ExprKind::Lit(Lit::Symbol(sym)) => {
+                    declared.insert(symbol_name(sym));
+                }
+                _ => read(lhs, declared),
+            }
+        }
+        ExprKind::If(cond, body) => {
+            read(cond, declared);
+            for expr in body.iter().filter(|expr| !is_noise(expr)) {
+                collect_undeclared(expr, declared, undeclared);
+            }
+        }
+        ExprKind::Loop(body) => {
+            for expr in body.iter().filter(|expr| !is_noise(expr)) {
+                collect_undeclared(expr, declared, undeclared);
+            }
+        }
+        ExprKind::Goto(_) | ExprKind::Unparsed(_) | ExprKind::Ret(None) => {}
+        _ => read(expr, declared),
+    }
+}
+
+/// Returns the Rust type of the register `name` of `architecture`, as `symbol_name` spells
+/// it, or `None` for the symbols that are no register.
+fn register_ty(architecture: Architecture, name: &str) -> Option<&'static str> {
+    match architecture {
+        Architecture::X86 { .. } => {
+            let reg = get_register_by_name(name)?;
+            Some(if reg.is_xmm() {
+                "__m128"
+            } else if reg.is_ymm() {
+                "__m256"
+            } else if reg.is_zmm() {
+                "__m512"
+            } else {
+                match reg.size() {
+                    1 => "u8",
+                    2 => "u16",
+                    4 => "u32",
+                    _ => "u64",
+                }
+            })
+        }
+        Architecture::AArch64 => match name {
+            "sp" | "xzr" => Some("u64"),
+            "wsp" | "wzr" => Some("u32"),
+            _ => {
+                let (class, num) = name.split_at(1);
+                num.parse::<u8>().ok().filter(|num| *num < 32)?;
+                match class {
+                    "x" => Some("u64"),
+                    "w" => Some("u32"),
+                    "b" => Some("u8"),
+                    "h" => Some("u16"),
+                    "s" => Some("f32"),
+                    "d" => Some("f64"),
+                    "q" => Some("u128"),
+                    _ => None,
+                }
+            }
+        },
+        Architecture::RiscV64 => {
+            if riscv::FP_REGISTER_NAMES.contains(&name) {
+                Some("f64")
+            } else {
+                riscv::REGISTER_NAMES.contains(&name).then_some("u64")
+            }
+        }
+    }
+}
+
+fn symbol_name(sym: &Symbol) -> String {
+    match sym {
+        Symbol::Var(idx) => format!("var_{}", idx),
+        Symbol::Reg(name) => name.to_lowercase(),
+        Symbol::Local(name) => local_name(name),
+    }
+}
+
+/// Spells the named symbol `name` with the identifiers in it as Rust ones, keeping the
+/// member accesses, indices and address-of of the places the debug info names, like
+/// `value.bytes[1]` or `&counter`.
+fn local_name(name: &str) -> String {
+    let mut out = String::new();
+    let mut word = String::new();
+    let flush = |word: &mut String, out: &mut String| {
+        match word.bytes().all(|c| c.is_ascii_digit()) {
+            true => out.push_str(word),
+            false => out.push_str(&rust_identifier(word)),
+        }
+        word.clear();
+    };
+    for c in name.chars() {
+        if matches!(c, '.' | '[' | ']' | '&' | '*') {
+            flush(&mut word, &mut out);
+            out.push(c);
+        } else {
+            word.push(c);
+        }
+    }
+    flush(&mut word, &mut out);
+    out
+}
+
+/// Returns `true` if `ty` is a Rust integer type at most 64 bits wide.
+fn is_integer(ty: &str) -> bool {
+    matches!(ty, "u8" | "u16" | "u32" | "u64" | "i8" | "i16" | "i32" | "i64")
+}
+
+fn rust_bin_op(op: &BinOpKind) -> &'static str {
+    match op {
+        BinOpKind::Add => "+",
+        BinOpKind::Sub => "-",
+        BinOpKind::Mul => "*",
+        BinOpKind::Div => "/",
+        BinOpKind::Rem => "%",
+        BinOpKind::And | BinOpKind::BitAnd => "&",
+        BinOpKind::Or | BinOpKind::BitOr => "|",
+        BinOpKind::BitXor => "^",
+        BinOpKind::Shl => "<<",
+        BinOpKind::Shr => ">>",
+        BinOpKind::Eq => "==",
+        BinOpKind::Lt => "<",
+        BinOpKind::Le => "<=",
+        BinOpKind::Ne => "!=",
+        BinOpKind::Ge => ">=",
+        BinOpKind::Gt => ">",
+    }
+}
+
+fn rust_ty(ty: &Ty, mutable: bool) -> String {
+    match ty {
+        Ty::Void => "()".to_string(),
+        Ty::Bool => "bool".to_string(),
+        Ty::U8 => "u8".to_string(),
+        Ty::U16 => "u16".to_string(),
+        Ty::U32 => "u32".to_string(),
+        Ty::U64 => "u64".to_string(),
+        Ty::I8 => "i8".to_string(),
+        Ty::I16 => "i16".to_string(),
+        Ty::I32 => "i32".to_string(),
+        Ty::I64 => "i64".to_string(),
+        Ty::F32 => "f32".to_string(),
+        Ty::F64 => "f64".to_string(),
+        // `core::arch` names the vector types after the Intel intrinsics too
+        Ty::Vector(..) => ty.to_string(),
+        Ty::Ptr(inner) if mutable => format!("*mut {}", rust_ty(inner, false)),
+        Ty::Ptr(inner) => format!("*const {}", rust_ty(inner, false)),
+    }
+}
+
+/// Spells a floating-point literal of type `ty`, e.g. `1.5_f64` or `f64::NAN`.
+fn rust_float<T: Into<f64> + std::fmt::Debug + Copy>(value: T, ty: &str) -> String {
+    let wide: f64 = value.into();
+    if wide.is_nan() {
+        format!("{ty}::NAN")
+    } else if wide.is_infinite() {
+        format!("{}{ty}::INFINITY", if wide < 0.0 { "-" } else { "" })
+    } else {
+        format!("{value:?}_{ty}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_names() {
+        assert_eq!(local_name("value.bytes[1]"), "value.bytes[1]");
+        assert_eq!(local_name("&counter"), "&counter");
+        assert_eq!(local_name("match.type"), "r#match.r#type");
+    }
+
+    #[test]
+    fn register_types() {
+        let x86 = Architecture::X86 { bitness: 64 };
+        assert_eq!(register_ty(x86, "eax"), Some("u32"));
+        assert_eq!(register_ty(x86, "xmm0"), Some("__m128"));
+        assert_eq!(register_ty(x86, "var_0"), None);
+        assert_eq!(register_ty(Architecture::AArch64, "w0"), Some("u32"));
+        assert_eq!(register_ty(Architecture::AArch64, "sp"), Some("u64"));
+        assert_eq!(register_ty(Architecture::AArch64, "d8"), Some("f64"));
+        assert_eq!(register_ty(Architecture::RiscV64, "fa0"), Some("f64"));
+        assert_eq!(register_ty(Architecture::RiscV64, "s0"), Some("u64"));
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use iced_x86::{
+    FlowControl, Instruction, InstructionInfoFactory, Mnemonic, OpAccess, OpKind, Register,
+};
+
+use crate::abi::Conventions;
+use crate::aarch64::Opcode;
+use crate::aarch64_parser;
+use crate::hardening::Hardening;
+use crate::idioms;
+use crate::arch::{self, Architecture};
+use crate::ast::{get_new_reg_symbol, Expr, ExprKind, Lit, Symbol, Ty, UnOp};
+use crate::cfg::{BasicBlock, Cfg, EdgeKind};
+use crate::emitter::is_noise;
+use crate::memory::memory::Memory;
+use crate::parser::{get_operand, get_tail_call, to_expression};
+use crate::riscv;
+use crate::rtti::Classes;
+use crate::riscv_parser;
+use crate::slots::frame_slot;
+use crate::types::{Prototype, Variable};
+use crate::unwind::UnwindTable;
+use crate::vsa::Place;
+
+pub struct Function {
+    pub address: u64,
+    pub name: String,
+    pub cfg: Cfg,
+    /// The prototype and local variables given by the debug info.
+    pub prototype: Option<Prototype>,
+    pub locals: Vec<Variable>,
+    /// The stack canary, CET and `_FORTIFY_SOURCE` checks left out of the printed body.
+    pub hardening: Hardening,
+}
+
+impl Function {
+    /// Builds the control-flow graph of the function at `address` and lifts each of its
+    /// blocks. Calls to the functions in `noreturn` end their block, jumps to those in
+    /// `starts` are tail calls, and calls through the vtables of `classes` become method
+    /// calls. The arguments of each call are those of the convention of its callee.
+    pub fn lift(
+        memory: &Memory,
+        unwind: &UnwindTable,
+        address: u64,
+        conventions: &Conventions,
+        noreturn: &BTreeSet<u64>,
+        starts: &BTreeSet<u64>,
+        classes: &Classes,
+    ) -> Self {
+        let mut stack = Vec::<Lit>::new();
+        let mut symbols = Vec::<Symbol>::new();
+
+        let end = unwind.function_end(address);
+        let convention = conventions.of(address);
+        let mut cfg = Cfg::build(memory, address, end, noreturn, starts);
+        let methods = classes.virtual_calls(&cfg, address, convention, memory);
+        for block in cfg.blocks.values_mut() {
+            match memory.architecture() {
+                Architecture::AArch64 => {
+                    aarch64_parser::lift_block(block, &cfg.tail_calls, &mut symbols);
+                    continue;
+                }
+                Architecture::RiscV64 => {
+                    riscv_parser::lift_block(block, &cfg.tail_calls, &mut symbols);
+                    continue;
+                }
+                Architecture::X86 { .. } => {}
+            }
+            block.statements = block
+                .instructions
+                .iter()
+                .map(|inst| match inst {
+                    // a call through a register or a pointer which may only go to one place
+                    arch::Instruction::X86(inst) => match cfg.calls.get(&inst.ip()) {
+                        Some(targets) if targets.len() == 1 && inst.is_call_near_indirect() => {
+                            Expr {
+                                id: 0,
+                                kind: ExprKind::Call(targets[0], Vec::new()),
+                            }
+                        }
+                        _ if cfg.tail_calls.contains(&inst.ip()) => {
+                            get_tail_call(inst.near_branch_target())
+                        }
+                        _ => match methods.get(&inst.ip()) {
+                            Some(method) => Expr {
+                                id: 0,
+                                kind: ExprKind::MethodCall(method.clone(), Vec::new()),
+                            },
+                            None => to_expression(&mut stack, &mut symbols, *inst),
+                        },
+                    },
+                    _ => Expr {
+                        id: 0,
+                        kind: ExprKind::Unparsed(*inst),
+                    },
+                })
+                .collect();
+            recover_arguments(block, conventions, memory.bitness(), &mut symbols);
+        }
+
+        Self {
+            address,
+            name: format!("FUN_{:X}", address),
+            cfg,
+            prototype: None,
+            locals: Vec::new(),
+            hardening: Hardening::default(),
+        }
+    }
+
+    /// Rewrites the idioms of optimizing compilers and the jumps through tables of the lifted
+    /// blocks, then the floating-point constants they read. The registers left for the
+    /// failure `handlers` of hardening checks, like `__stack_chk_fail`, are not read, as
+    /// `hardening::hide` leaves these calls out.
+    pub fn rewrite_idioms(
+        &mut self,
+        memory: &Memory,
+        conventions: &Conventions,
+        handlers: &[u64],
+    ) {
+        let mut symbols = Vec::<Symbol>::new();
+        if let Architecture::X86 { .. } = memory.architecture() {
+            let convention = conventions.of(self.address);
+            idioms::rewrite(&mut self.cfg, memory, convention, handlers, &mut symbols);
+            lift_jump_tables(&mut self.cfg, &mut symbols);
+        }
+        // once the idioms are rewritten, as the comparisons they fold read constants too
+        for block in self.cfg.blocks.values_mut() {
+            for stmt in block.statements.iter_mut() {
+                fold_float_constants(stmt, memory);
+            }
+        }
+    }
+
+    /// Returns the lifted statements of every block, in address order.
+    pub fn statements(&self) -> impl Iterator<Item = &Expr> {
+        self.cfg
+            .blocks
+            .values()
+            .flat_map(|block| block.statements.iter())
+    }
+
+    /// Returns the code this function calls or takes the address of, like `main` in `_start`.
+    pub fn code_references(&self) -> Vec<u64> {
+        let mut refs = self.callees();
+        for block in self.cfg.blocks.values() {
+            for (inst, stmt) in block.instructions.iter().zip(block.statements.iter()) {
+                let addr = match inst {
+                    arch::Instruction::X86(inst)
+                        if inst.mnemonic() == Mnemonic::Lea && inst.is_ip_rel_memory_operand() =>
+                    {
+                        inst.ip_rel_memory_address()
+                    }
+                    // adr and adrp/add pairs, lifted to constants; a lone adrp is just a page,
+                    // and so is the upper half given by lui or auipc
+                    arch::Instruction::AArch64(inst) if inst.opcode == Opcode::Adrp => continue,
+                    arch::Instruction::RiscV(inst)
+                        if matches!(inst.opcode, riscv::Opcode::Lui | riscv::Opcode::Auipc) =>
+                    {
+                        continue
+                    }
+                    arch::Instruction::AArch64(_) | arch::Instruction::RiscV(_) => {
+                        match &stmt.kind {
+                            ExprKind::Assign(_, value) => match value.kind {
+                                ExprKind::Lit(Lit::U64(addr)) => addr,
+                                _ => continue,
+                            },
+                            _ => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                if !refs.contains(&addr) {
+                    refs.push(addr);
+                }
+            }
+        }
+        refs
+    }
+
+    /// Returns `true` if a statement left in the printed body branches to `addr`, which then
+    /// needs a label.
+    pub fn needs_label(&self, addr: u64) -> bool {
+        self.cfg.edges.iter().any(|edge| {
+            edge.to == addr
+                && (edge.kind == EdgeKind::Switch
+                    || matches!(edge.kind, EdgeKind::Jump | EdgeKind::True)
+                        && self.cfg.blocks[&edge.from]
+                            .statements
+                            .last()
+                            .is_some_and(|stmt| !is_noise(stmt))
+                    || self.detached_fallthrough(edge.from) == Some(addr))
+        })
+    }
+
+    /// Returns the block the one at `addr` falls through to, when it is not printed right
+    /// after it, since the entry block is printed first, and a `goto` has to lead there.
+    pub fn detached_fallthrough(&self, addr: u64) -> Option<u64> {
+        let next = self
+            .cfg
+            .ordered_blocks(self.address)
+            .map(|block| block.start)
+            .skip_while(|start| *start != addr)
+            .nth(1);
+        self.cfg
+            .edges
+            .iter()
+            .find(|edge| {
+                edge.from == addr && matches!(edge.kind, EdgeKind::Fallthrough | EdgeKind::False)
+            })
+            .map(|edge| edge.to)
+            .filter(|to| Some(*to) != next)
+    }
+
+    /// Returns the targets of the direct calls made by this function, including those left
+    /// out of the printed body or shown under another name by the hardening checks, and of
+    /// the indirect calls resolved to a few functions.
+    pub fn callees(&self) -> Vec<u64> {
+        let mut callees = Vec::new();
+        let calls = self.statements().filter_map(|expr| match expr.call_site().kind {
+            ExprKind::Call(func, _) => Some(func),
+            _ => None,
+        });
+        let hidden = self.hardening.fortified.iter().chain(self.hardening.handlers.iter());
+        let indirect = self.cfg.calls.values().flatten();
+        for func in calls.chain(hidden.copied()).chain(indirect.copied()) {
+            if !callees.contains(&func) {
+                callees.push(func);
+            }
+        }
+        callees
+    }
+}
+
+/// Replaces the floating-point values read from read-only data, where compilers keep the
+/// constants SSE code loads, with literals. Stores are left alone.
+fn fold_float_constants(expr: &mut Expr, memory: &Memory) {
+    if let ExprKind::Unary(UnOp::Deref, ptr) = &expr.kind {
+        if let ExprKind::Cast(addr, Ty::Ptr(ty)) = &ptr.kind {
+            if let ExprKind::Lit(Lit::U64(addr)) = addr.kind {
+                let bytes = memory.get(addr).filter(|_| memory.is_read_only(addr));
+                let lit = match (bytes, &**ty) {
+                    (Some(bytes), Ty::F32) if bytes.len() >= 4 => {
+                        Lit::F32(f32::from_le_bytes(bytes[..4].try_into().unwrap()))
+                    }
+                    (Some(bytes), Ty::F64) if bytes.len() >= 8 => {
+                        Lit::F64(f64::from_le_bytes(bytes[..8].try_into().unwrap()))
+                    }
+                    _ => return,
+                };
+                expr.kind = ExprKind::Lit(lit);
+                return;
+            }
+        }
+    }
+    match &mut expr.kind {
+        ExprKind::Assign(lhs, rhs) => {
+            if let ExprKind::Unary(UnOp::Deref, ptr) = &mut lhs.kind {
+                fold_float_constants(ptr, memory);
+            }
+            fold_float_constants(rhs, memory);
+        }
+        ExprKind::Call(_, args)
+        | ExprKind::MethodCall(_, args)
+        | ExprKind::Intrinsic(_, args)
+        | ExprKind::Tup(args) => {
+            args.iter_mut().for_each(|arg| fold_float_constants(arg, memory))
+        }
+        ExprKind::Binary(_, lhs, rhs) => {
+            fold_float_constants(lhs, memory);
+            fold_float_constants(rhs, memory);
+        }
+        ExprKind::Unary(_, inner) | ExprKind::Cast(inner, _) => fold_float_constants(inner, memory),
+        ExprKind::If(cond, _) => fold_float_constants(cond, memory),
+        ExprKind::Ret(Some(value)) => fold_float_constants(value, memory),
+        _ => {}
+    }
+}
+
+/// Replaces the instructions computing the target of a jump through a table from its index
+/// with a `switch` on the index.
+fn lift_jump_tables(cfg: &mut Cfg, symbols: &mut Vec<Symbol>) {
+    for block in cfg.blocks.values_mut() {
+        let Some(table) = block.instructions.last().and_then(|last| cfg.tables.get(&last.ip()))
+        else {
+            continue;
+        };
+        let index = match &table.index {
+            Place::Register(reg) => Expr {
+                id: 0,
+                kind: ExprKind::Lit(Lit::Symbol(get_new_reg_symbol(symbols, *reg))),
+            },
+            // shown as the first operand reading the slot
+            Place::Slot(slot, _) => {
+                let read = block.instructions.iter().filter_map(|inst| inst.as_x86()).find(|inst| {
+                    inst.ip() >= table.start && frame_slot(inst, None).as_ref() == Some(slot)
+                });
+                let Some(op) = read.and_then(|inst| {
+                    (0..inst.op_count()).find(|op| inst.op_kind(*op) == OpKind::Memory)
+                }) else {
+                    continue;
+                };
+                get_operand(symbols, *read.unwrap(), op)
+            }
+        };
+        let mut index = Some(Box::new(index));
+        for (inst, stmt) in block.instructions.iter().zip(block.statements.iter_mut()) {
+            if inst.ip() == table.start {
+                stmt.kind = ExprKind::Switch(index.take().unwrap(), table.cases.clone());
+            } else if inst.ip() > table.start {
+                stmt.kind = ExprKind::Lit(Lit::StillUnknown);
+            }
+        }
+    }
+}
+
+/// Fills in the arguments of the calls in `block` with the registers, pushes and stack
+/// slots written before each of them, following the convention of the callee: the argument
+/// registers up to the first left unwritten after one written, those before it being passed
+/// on as the caller received them. Method calls always pass their object.
+fn recover_arguments(
+    block: &mut BasicBlock,
+    conventions: &Conventions,
+    bitness: u32,
+    symbols: &mut Vec<Symbol>,
+) {
+    let mut factory = InstructionInfoFactory::new();
+    let mut registers = HashSet::<Register>::new();
+    let mut pushes = Vec::<Instruction>::new();
+    let mut stack = BTreeMap::<u64, Instruction>::new();
+    let slot_size = u64::from(bitness / 8);
+
+    for (inst, stmt) in block.instructions.iter().zip(block.statements.iter_mut()) {
+        let Some(inst) = inst.as_x86() else {
+            continue;
+        };
+        let method = matches!(stmt.kind, ExprKind::MethodCall(..));
+        if let ExprKind::Call(_, args) | ExprKind::MethodCall(_, args) = &mut stmt.call_site_mut().kind {
+            let convention = match inst.flow_control() {
+                FlowControl::Call => conventions.of(inst.near_branch_target()),
+                _ => conventions.default,
+            };
+            let written = |reg: &Register| registers.contains(&reg.full_register());
+            let all = convention.argument_registers();
+            let count = match all.iter().position(written) {
+                Some(first) => first + all[first..].iter().take_while(|reg| written(reg)).count(),
+                None => usize::from(method),
+            };
+            for reg in all[..count].iter() {
+                args.push(Expr {
+                    id: 0,
+                    kind: ExprKind::Lit(Lit::Symbol(get_new_reg_symbol(symbols, *reg))),
+                });
+            }
+            if args.len() == convention.argument_registers().len() {
+                if convention.pushes_arguments() && !pushes.is_empty() {
+                    // the last value pushed is the first argument
+                    for push in pushes.iter().rev() {
+                        args.push(get_operand(symbols, *push, 0));
+                    }
+                } else {
+                    let mut offset = convention.stack_arguments_offset();
+                    while let Some(store) = stack.get(&offset) {
+                        args.push(get_operand(symbols, *store, 0));
+                        offset += slot_size;
+                    }
+                }
+            }
+            registers.clear();
+            pushes.clear();
+            stack.clear();
+            continue;
+        }
+        let info = factory.info(inst);
+        if inst.mnemonic() == Mnemonic::Push {
+            pushes.push(*inst);
+        } else if info
+            .used_registers()
+            .iter()
+            .any(|used| used.register().full_register() == Register::RSP)
+        {
+            // frame setup or stack adjustments: what was pushed so far were saved registers
+            pushes.clear();
+        }
+        for used in info.used_registers() {
+            if matches!(
+                used.access(),
+                OpAccess::Write | OpAccess::CondWrite | OpAccess::ReadWrite | OpAccess::ReadCondWrite
+            ) {
+                registers.insert(used.register().full_register());
+            }
+        }
+        if inst.op0_kind() == OpKind::Memory
+            && inst.memory_base().full_register() == Register::RSP
+            && inst.memory_index() == Register::None
+            && info
+                .used_memory()
+                .iter()
+                .any(|used| matches!(used.access(), OpAccess::Write | OpAccess::ReadWrite))
+        {
+            stack.insert(inst.memory_displacement64(), *inst);
+        }
+    }
+}
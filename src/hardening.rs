@@ -0,0 +1,664 @@
+use std::collections::{BTreeSet, HashMap};
+
+use iced_x86::{Instruction, InstructionInfoFactory, Mnemonic, OpAccess, OpKind, Register};
+use serde::Serialize;
+
+use crate::arch::{self, Architecture, Flow};
+use crate::ast::{Expr, ExprKind, Lit};
+use crate::function::Function;
+use crate::memory::memory::Memory;
+use crate::prototypes::PrototypeDb;
+use crate::symbols::SymbolTable;
+
+/// The functions called when the stack canary was overwritten.
+const STACK_FAIL: &[&str] = &["__stack_chk_fail", "__stack_chk_fail_local"];
+
+/// The functions called when a `_FORTIFY_SOURCE` check inlined by the compiler fails.
+const CHECK_FAIL: &[&str] = &["__chk_fail", "__fortify_fail"];
+
+/// The `_FORTIFY_SOURCE` variants of library functions, the function each one checks and
+/// the arguments it takes on top of those: flags and the sizes of the destinations.
+const FORTIFIED: &[(&str, &str, &[usize])] = &[
+    ("__memcpy_chk", "memcpy", &[3]),
+    ("__memmove_chk", "memmove", &[3]),
+    ("__mempcpy_chk", "mempcpy", &[3]),
+    ("__memset_chk", "memset", &[3]),
+    ("__strcpy_chk", "strcpy", &[2]),
+    ("__stpcpy_chk", "stpcpy", &[2]),
+    ("__strcat_chk", "strcat", &[2]),
+    ("__strncpy_chk", "strncpy", &[3]),
+    ("__stpncpy_chk", "stpncpy", &[3]),
+    ("__strncat_chk", "strncat", &[3]),
+    ("__printf_chk", "printf", &[0]),
+    ("__fprintf_chk", "fprintf", &[1]),
+    ("__dprintf_chk", "dprintf", &[1]),
+    ("__sprintf_chk", "sprintf", &[1, 2]),
+    ("__snprintf_chk", "snprintf", &[2, 3]),
+    ("__vprintf_chk", "vprintf", &[0]),
+    ("__vfprintf_chk", "vfprintf", &[1]),
+    ("__vdprintf_chk", "vdprintf", &[1]),
+    ("__vsprintf_chk", "vsprintf", &[1, 2]),
+    ("__vsnprintf_chk", "vsnprintf", &[2, 3]),
+    ("__fgets_chk", "fgets", &[1]),
+    ("__fread_chk", "fread", &[1]),
+    ("__read_chk", "read", &[3]),
+    ("__pread_chk", "pread", &[4]),
+    ("__recv_chk", "recv", &[3]),
+    ("__getcwd_chk", "getcwd", &[2]),
+    ("__realpath_chk", "realpath", &[2]),
+    ("__explicit_bzero_chk", "explicit_bzero", &[2]),
+];
+
+/// The checks a compiler adds to harden a function, which are left out of its printed body.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Hardening {
+    /// A canary is stored on the stack on entry and compared before returning
+    /// (`-fstack-protector`, or `/GS` on Windows).
+    pub stack_protector: bool,
+    /// The function has `endbr64` or `endbr32` landing pads for CET indirect branch tracking.
+    pub endbr: bool,
+    /// The `_FORTIFY_SOURCE` functions it calls, like `__memcpy_chk`, printed as the
+    /// functions they check.
+    pub fortified: Vec<u64>,
+    /// The functions called by the hidden checks, like `__stack_chk_fail`.
+    pub handlers: Vec<u64>,
+    /// How many object size checks calling `__chk_fail` were inlined by `_FORTIFY_SOURCE`.
+    pub size_checks: usize,
+}
+
+/// What a block ending with a call to one of the failure handlers is there for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    Stack,
+    Check,
+}
+
+/// A register holding the canary, or the copy of it saved on the stack.
+struct Tracked {
+    reg: Register,
+    guard: bool,
+    stored: bool,
+    pending: Vec<usize>,
+}
+
+/// Finds the stack canary, `endbr` and `_FORTIFY_SOURCE` checks of `function`, records them
+/// in its `hardening` and turns the statements implementing them into noise. The calls to
+/// the checked variants of library functions are left to [`unfortify`].
+pub fn hide(function: &mut Function, symbols: &SymbolTable, memory: &Memory) {
+    let Architecture::X86 { bitness } = memory.architecture() else {
+        return;
+    };
+    let cookie = symbols
+        .names()
+        .find(|(_, name)| matches!(*name, "__security_cookie" | "___security_cookie"))
+        .map(|(addr, _)| addr);
+    let callee_name = |inst: &arch::Instruction| {
+        matches!(inst.flow(), Flow::Call | Flow::IndirectCall)
+            .then(|| inst.branch_target().or(inst.slot()))
+            .flatten()
+            .and_then(|addr| symbols.get(addr))
+            .map(base_name)
+    };
+
+    let mut failures = HashMap::new();
+    for block in function.cfg.blocks.values() {
+        if let Some((failure, _)) = block
+            .instructions
+            .last()
+            .and_then(|last| failure(last, symbols))
+        {
+            failures.insert(block.start, (failure, block.end));
+        }
+    }
+
+    // the stack slots the canary is saved to, by base register and displacement
+    let mut slots = BTreeSet::new();
+    for block in function.cfg.blocks.values() {
+        let mut held = None;
+        for inst in block.instructions.iter().filter_map(|inst| inst.as_x86()) {
+            if let Some(reg) = guard_load(inst, bitness, cookie) {
+                held = Some(reg);
+            } else if let (Some(reg), Some(slot)) = (held, stack_store(inst)) {
+                if inst.op1_kind() == OpKind::Register && inst.op1_register().full_register() == reg
+                {
+                    slots.insert(slot);
+                    held = None;
+                }
+            }
+        }
+    }
+
+    let mut factory = InstructionInfoFactory::new();
+    let mut hardening = Hardening::default();
+    let mut hidden_edges = BTreeSet::new();
+    for block in function.cfg.blocks.values_mut() {
+        let mut hidden = Vec::new();
+        let mut gotos = Vec::new();
+        let mut tracked: Option<Tracked> = None;
+        let last = block.instructions.len().saturating_sub(1);
+        // resolves the conditional branch ending the block after a check, hiding it when
+        // it leads to a failure handler: a branch taken when the check fails is dropped and
+        // one skipping over the handler becomes a jump, unless it lands right after it
+        let mut branch = |hidden: &mut Vec<usize>, gotos: &mut Vec<(usize, u64)>, kind| {
+            let Some(inst) = block.instructions.get(last) else {
+                return false;
+            };
+            if inst.flow() != Flow::ConditionalBranch {
+                return false;
+            }
+            let target = inst.branch_target().unwrap_or_default();
+            if failures
+                .get(&target)
+                .is_some_and(|failure| failure.0 == kind)
+            {
+                hidden.push(last);
+                hidden_edges.insert((block.start, target));
+            } else if let Some(&(failure, end)) = failures.get(&inst.next_ip()) {
+                if failure != kind {
+                    return false;
+                }
+                // no jump is needed to the code right after the handler
+                if target == end {
+                    hidden.push(last);
+                } else {
+                    gotos.push((last, target));
+                }
+                hidden_edges.insert((block.start, inst.next_ip()));
+            } else {
+                return false;
+            }
+            true
+        };
+
+        for (idx, inst) in block.instructions.iter().enumerate() {
+            let Some(inst) = inst.as_x86() else {
+                continue;
+            };
+            if matches!(inst.mnemonic(), Mnemonic::Endbr64 | Mnemonic::Endbr32) {
+                hardening.endbr = true;
+                hidden.push(idx);
+                continue;
+            }
+            if let Some(reg) = guard_load(inst, bitness, cookie) {
+                tracked = Some(Tracked {
+                    reg,
+                    guard: true,
+                    stored: false,
+                    pending: vec![idx],
+                });
+                continue;
+            }
+            if inst.mnemonic() == Mnemonic::Mov
+                && inst.op0_kind() == OpKind::Register
+                && stack_load(inst).is_some_and(|slot| slots.contains(&slot))
+            {
+                tracked = Some(Tracked {
+                    reg: inst.op0_register().full_register(),
+                    guard: false,
+                    stored: false,
+                    pending: vec![idx],
+                });
+                continue;
+            }
+            let Some(current) = tracked.as_mut() else {
+                continue;
+            };
+            let reg = current.reg;
+            let is_reg = |op: u32| {
+                inst.op_kind(op) == OpKind::Register && inst.op_register(op).full_register() == reg
+            };
+
+            if current.guard
+                && is_reg(1)
+                && stack_store(inst).is_some_and(|slot| slots.contains(&slot))
+            {
+                current.pending.push(idx);
+                hidden.append(&mut current.pending);
+                current.stored = true;
+                hardening.stack_protector = true;
+                continue;
+            }
+            if inst.mnemonic() == Mnemonic::Xor
+                && is_reg(0)
+                && inst.op_kind(1) == OpKind::Register
+                && is_frame_register(inst.op1_register())
+            {
+                // /GS mixes the cookie with the stack pointer
+                current.pending.push(idx);
+                continue;
+            }
+            if current.stored && inst.mnemonic() == Mnemonic::Xor && is_reg(0) && is_reg(1) {
+                // the register is cleared not to leak the canary, but the compiler may go
+                // on using the zero
+                if is_overwritten(&mut factory, &block.instructions[idx + 1..], reg) {
+                    hidden.push(idx);
+                }
+                tracked = None;
+                continue;
+            }
+            if matches!(
+                inst.mnemonic(),
+                Mnemonic::Cmp | Mnemonic::Sub | Mnemonic::Xor
+            ) && (is_reg(0) || is_reg(1))
+            {
+                let other = if is_reg(0) { 1 } else { 0 };
+                let compared = if current.guard {
+                    inst.op_kind(other) == OpKind::Memory
+                        && stack_slot(inst).is_some_and(|slot| slots.contains(&slot))
+                } else {
+                    is_guard(inst, bitness, cookie)
+                };
+                if compared && idx + 1 == last {
+                    let mut pending = std::mem::take(&mut current.pending);
+                    if branch(&mut hidden, &mut gotos, Failure::Stack) {
+                        pending.push(idx);
+                        hidden.append(&mut pending);
+                        hardening.stack_protector = true;
+                    }
+                    tracked = None;
+                    continue;
+                }
+            }
+            if !current.guard
+                && callee_name(&block.instructions[idx]) == Some("__security_check_cookie")
+            {
+                current.pending.push(idx);
+                hidden.append(&mut current.pending);
+                hardening.stack_protector = true;
+                tracked = None;
+                continue;
+            }
+            let info = factory.info(inst);
+            if info
+                .used_registers()
+                .iter()
+                .any(|used| used.register().full_register() == reg)
+            {
+                tracked = None;
+            }
+        }
+
+        // inlined object size checks, branching to __chk_fail
+        if !failures.is_empty()
+            && !hidden.contains(&last)
+            && !gotos.iter().any(|(idx, _)| *idx == last)
+            && branch(&mut hidden, &mut gotos, Failure::Check)
+        {
+            hardening.size_checks += 1;
+            let compare = block.instructions[..last]
+                .last()
+                .and_then(|inst| inst.as_x86())
+                .filter(|inst| matches!(inst.mnemonic(), Mnemonic::Cmp | Mnemonic::Test));
+            if compare.is_some() {
+                hidden.push(last - 1);
+            }
+        }
+
+        for idx in hidden {
+            hide_statement(&mut block.statements[idx], &mut hardening.handlers);
+        }
+        for (idx, target) in gotos {
+            block.statements[idx] = Expr {
+                id: 0,
+                kind: ExprKind::Goto(target),
+            };
+        }
+    }
+
+    // the failure handlers are left out once every branch to them is
+    for block in function.cfg.blocks.values_mut() {
+        if !failures.contains_key(&block.start) {
+            continue;
+        }
+        let mut incoming = function
+            .cfg
+            .edges
+            .iter()
+            .filter(|edge| edge.to == block.start)
+            .peekable();
+        if incoming.peek().is_some()
+            && incoming.all(|edge| hidden_edges.contains(&(edge.from, edge.to)))
+        {
+            for stmt in block.statements.iter_mut() {
+                hide_statement(stmt, &mut hardening.handlers);
+            }
+        }
+    }
+
+    function.hardening = hardening;
+}
+
+/// Shows the calls of `function` to the checked variants of library functions as calls to
+/// the plain ones, without the extra arguments, once the constants are propagated: the
+/// variadic arguments of the `printf`-like ones are counted again from their format, the
+/// format string and the arguments after it being shifted by the flag dropped before them.
+pub fn unfortify(function: &mut Function, symbols: &SymbolTable, prototypes: &PrototypeDb) {
+    for stmt in function
+        .cfg
+        .blocks
+        .values_mut()
+        .flat_map(|block| block.statements.iter_mut())
+    {
+        let call = stmt.call_site_mut();
+        let ExprKind::Call(addr, args) = &mut call.kind else {
+            continue;
+        };
+        let Some(name) = symbols.get(*addr).map(base_name) else {
+            continue;
+        };
+        let Some((_, plain, extra)) = FORTIFIED.iter().find(|(checked, _, _)| *checked == name)
+        else {
+            continue;
+        };
+        if extra.iter().any(|idx| *idx >= args.len()) {
+            continue;
+        }
+        if !function.hardening.fortified.contains(addr) {
+            function.hardening.fortified.push(*addr);
+        }
+        let mut args = std::mem::take(args)
+            .into_iter()
+            .enumerate()
+            .filter(|(idx, _)| !extra.contains(idx))
+            .map(|(_, arg)| arg)
+            .collect::<Vec<_>>();
+        if let Some(count) = prototypes.count_variadic(plain, &args) {
+            args.truncate(count);
+        }
+        call.kind = ExprKind::Intrinsic(plain.to_string(), args);
+    }
+}
+
+/// Returns the failure handlers the blocks of `function` end calling, like
+/// `__stack_chk_fail`, before `hide` leaves them out.
+pub fn failure_handlers(function: &Function, symbols: &SymbolTable) -> Vec<u64> {
+    let mut handlers = Vec::new();
+    for block in function.cfg.blocks.values() {
+        if let Some((_, addr)) = block
+            .instructions
+            .last()
+            .and_then(|last| failure(last, symbols))
+        {
+            if !handlers.contains(&addr) {
+                handlers.push(addr);
+            }
+        }
+    }
+    handlers
+}
+
+/// Returns what the call `inst` fails for, if it calls a failure handler, and the handler.
+fn failure(inst: &arch::Instruction, symbols: &SymbolTable) -> Option<(Failure, u64)> {
+    if !matches!(inst.flow(), Flow::Call | Flow::IndirectCall) {
+        return None;
+    }
+    let addr = inst.branch_target().or(inst.slot())?;
+    let name = base_name(symbols.get(addr)?);
+    if STACK_FAIL.contains(&name) {
+        Some((Failure::Stack, addr))
+    } else if CHECK_FAIL.contains(&name) {
+        Some((Failure::Check, addr))
+    } else {
+        None
+    }
+}
+
+/// Strips the decorations of an import or stub name, like `__stack_chk_fail@plt`.
+fn base_name(name: &str) -> &str {
+    let name = name.rsplit('!').next().unwrap_or(name);
+    let name = name.split('@').next().unwrap_or(name);
+    name.strip_prefix("__imp_").unwrap_or(name)
+}
+
+/// Turns `stmt` into noise, remembering the function it calls.
+fn hide_statement(stmt: &mut Expr, handlers: &mut Vec<u64>) {
+    if let ExprKind::Call(addr, _) = stmt.kind {
+        if !handlers.contains(&addr) {
+            handlers.push(addr);
+        }
+    }
+    stmt.kind = ExprKind::Lit(Lit::StillUnknown);
+}
+
+/// Returns `true` if a memory operand of `inst` is the canary: `fs:[0x28]` on x86-64 and
+/// `gs:[0x14]` on i386 Linux, or the `/GS` cookie on Windows.
+fn is_guard(inst: &Instruction, bitness: u32, cookie: Option<u64>) -> bool {
+    if !(0..inst.op_count()).any(|op| inst.op_kind(op) == OpKind::Memory) {
+        return false;
+    }
+    if inst.memory_base() == Register::None && inst.memory_index() == Register::None {
+        let tls = match bitness {
+            64 => (Register::FS, 0x28),
+            _ => (Register::GS, 0x14),
+        };
+        if (inst.memory_segment(), inst.memory_displacement64()) == tls {
+            return true;
+        }
+    }
+    let addr = if inst.is_ip_rel_memory_operand() {
+        inst.ip_rel_memory_address()
+    } else if inst.memory_base() == Register::None && inst.memory_index() == Register::None {
+        inst.memory_displacement64()
+    } else {
+        return false;
+    };
+    cookie == Some(addr)
+}
+
+/// Returns the register `inst` loads the canary into.
+fn guard_load(inst: &Instruction, bitness: u32, cookie: Option<u64>) -> Option<Register> {
+    (inst.mnemonic() == Mnemonic::Mov
+        && inst.op0_kind() == OpKind::Register
+        && is_guard(inst, bitness, cookie))
+    .then(|| inst.op0_register().full_register())
+}
+
+/// Returns the base register and displacement of the stack slot `inst` accesses, if any.
+fn stack_slot(inst: &Instruction) -> Option<(Register, u64)> {
+    (is_frame_register(inst.memory_base()) && inst.memory_index() == Register::None).then(|| {
+        (
+            inst.memory_base().full_register(),
+            inst.memory_displacement64(),
+        )
+    })
+}
+
+fn stack_store(inst: &Instruction) -> Option<(Register, u64)> {
+    (inst.mnemonic() == Mnemonic::Mov && inst.op0_kind() == OpKind::Memory)
+        .then(|| stack_slot(inst))
+        .flatten()
+}
+
+fn stack_load(inst: &Instruction) -> Option<(Register, u64)> {
+    (0..inst.op_count())
+        .skip(1)
+        .any(|op| inst.op_kind(op) == OpKind::Memory)
+        .then(|| stack_slot(inst))
+        .flatten()
+}
+
+/// Returns `true` if `reg` is written by `insts` before being read, or clobbered by a call.
+fn is_overwritten(
+    factory: &mut InstructionInfoFactory,
+    insts: &[arch::Instruction],
+    reg: Register,
+) -> bool {
+    for inst in insts.iter().filter_map(|inst| inst.as_x86()) {
+        if inst.mnemonic() == Mnemonic::Call {
+            return matches!(
+                reg,
+                Register::RAX
+                    | Register::RCX
+                    | Register::RDX
+                    | Register::R8
+                    | Register::R9
+                    | Register::R10
+                    | Register::R11
+            );
+        }
+        if inst.mnemonic() == Mnemonic::Xor
+            && inst.op0_kind() == OpKind::Register
+            && inst.op1_kind() == OpKind::Register
+            && inst.op0_register() == inst.op1_register()
+            && inst.op0_register().full_register() == reg
+        {
+            return true;
+        }
+        let accesses = factory
+            .info(inst)
+            .used_registers()
+            .iter()
+            .filter(|used| used.register().full_register() == reg)
+            .map(|used| used.access())
+            .collect::<Vec<_>>();
+        if !accesses.is_empty() {
+            return accesses.iter().all(|access| *access == OpAccess::Write);
+        }
+    }
+    false
+}
+
+fn is_frame_register(reg: Register) -> bool {
+    matches!(
+        reg.full_register(),
+        Register::RSP | Register::RBP | Register::ESP | Register::EBP
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::abi::{CallingConvention, Conventions};
+    use crate::rtti::Classes;
+    use crate::unwind::UnwindTable;
+
+    const REGISTERS: [&str; 6] = ["RDI", "RSI", "RDX", "RCX", "R8", "R9"];
+
+    /// Lifts `name` from `assets/test_hardened` and hides its checks, returning its
+    /// statements and what was found.
+    fn hardened(name: &str) -> (Vec<String>, Hardening, SymbolTable) {
+        let data = std::fs::read("assets/test_hardened").unwrap();
+        let file = object::File::parse(&*data).unwrap();
+        let memory = Memory::load(&file, &data, None).unwrap();
+        let symbols = SymbolTable::from_file(&file, &memory);
+        let unwind = UnwindTable::from_file(&file, &memory);
+        let conventions = Conventions::new(CallingConvention::SysV);
+        let none = BTreeSet::new();
+        let address = symbols.lookup(name).unwrap();
+        let classes = Classes::default();
+        let mut function =
+            Function::lift(&memory, &unwind, address, &conventions, &none, &none, &classes);
+        let handlers = failure_handlers(&function, &symbols);
+        function.rewrite_idioms(&memory, &conventions, &handlers);
+        let prototypes = PrototypeDb::new(8, 8);
+        prototypes.apply(&mut function, &symbols, &memory, &conventions);
+        hide(&mut function, &symbols, &memory);
+        unfortify(&mut function, &symbols, &prototypes);
+        let statements = function
+            .statements()
+            .filter(|stmt| !matches!(stmt.kind, ExprKind::Lit(Lit::StillUnknown)))
+            .map(|stmt| stmt.named(&symbols).to_string())
+            .collect();
+        (statements, function.hardening, symbols)
+    }
+
+    /// Lifts a call to `name` at 0x2000, given the argument registers 1 to 6 and `format`
+    /// for the argument of the index given, and shows it as a call to the plain function.
+    fn call(name: &str, format: Option<(usize, &str)>) -> String {
+        // mov edi, 1; mov esi, 2; mov edx, 3; mov ecx, 4; mov r8d, 5; mov r9d, 6; call 0x2000
+        let code = [
+            0xBF, 0x01, 0x00, 0x00, 0x00, 0xBE, 0x02, 0x00, 0x00, 0x00, 0xBA, 0x03, 0x00, 0x00,
+            0x00, 0xB9, 0x04, 0x00, 0x00, 0x00, 0x41, 0xB8, 0x05, 0x00, 0x00, 0x00, 0x41, 0xB9,
+            0x06, 0x00, 0x00, 0x00, 0xE8, 0xDB, 0x0F, 0x00, 0x00, 0xC3,
+        ];
+        let memory = Memory::raw(&code, 0x1000, Architecture::X86 { bitness: 64 }).unwrap();
+        let mut symbols = SymbolTable::default();
+        symbols.insert(0x2000, name);
+        let conventions = Conventions::new(CallingConvention::SysV);
+        let none = BTreeSet::new();
+        let classes = Classes::default();
+        let unwind = UnwindTable::default();
+        let mut function =
+            Function::lift(&memory, &unwind, 0x1000, &conventions, &none, &none, &classes);
+        let prototypes = PrototypeDb::new(8, 8);
+        prototypes.apply(&mut function, &symbols, &memory, &conventions);
+        let statements = function.cfg.blocks.values_mut().flat_map(|block| block.statements.iter_mut());
+        for stmt in statements {
+            if let (ExprKind::Call(_, args), Some((index, format))) = (&mut stmt.kind, format) {
+                args[index] = Expr {
+                    id: 0,
+                    kind: ExprKind::Lit(Lit::Str(format.to_string())),
+                };
+            }
+        }
+        unfortify(&mut function, &symbols, &prototypes);
+        let call = function
+            .statements()
+            .find(|stmt| matches!(stmt.kind, ExprKind::Intrinsic(..)))
+            .map(|stmt| stmt.to_string());
+        call.unwrap_or_default()
+    }
+
+    #[test]
+    fn stack_protector() {
+        let (statements, hardening, symbols) = hardened("stash");
+        assert!(hardening.stack_protector);
+        let handlers = hardening.handlers.iter().map(|addr| symbols.display_name(*addr));
+        assert_eq!(handlers.collect::<Vec<_>>(), ["__stack_chk_fail@plt"]);
+        assert!(
+            statements.iter().all(|stmt| !stmt.contains("FS") && !stmt.contains("0x28")),
+            "{statements:#?}"
+        );
+    }
+
+    #[test]
+    fn fortified_calls() {
+        let (statements, hardening, symbols) = hardened("greet");
+        let fortified = hardening.fortified.iter().map(|addr| symbols.display_name(*addr));
+        assert_eq!(
+            fortified.collect::<Vec<_>>(),
+            ["__strcpy_chk@plt", "__printf_chk@plt"]
+        );
+        assert!(
+            statements.contains(&"strcpy((int8_t *)(@RDI), (int8_t *)(@RSI))".to_string()),
+            "{statements:#?}"
+        );
+    }
+
+    #[test]
+    fn checked_variants() {
+        for (checked, plain, extra) in FORTIFIED {
+            let call = call(checked, None);
+            let (name, args) = call.split_once('(').unwrap();
+            assert_eq!(name, *plain);
+            // the arguments left are those of the registers not dropped
+            let registers = args
+                .split(", ")
+                .map(|arg| REGISTERS.iter().position(|reg| arg.contains(&format!("@{reg}"))))
+                .collect::<Option<Vec<_>>>()
+                .unwrap_or_else(|| panic!("{call}"));
+            assert!(registers.windows(2).all(|pair| pair[0] < pair[1]), "{call}");
+            assert!(registers.iter().all(|idx| !extra.contains(idx)), "{call}");
+            let kept = (0..=registers[registers.len() - 1]).filter(|idx| !extra.contains(idx));
+            assert_eq!(registers, kept.collect::<Vec<_>>(), "{call}");
+        }
+    }
+
+    #[test]
+    fn shifted_formats() {
+        assert_eq!(
+            call("__printf_chk", Some((1, "%d %s\n"))),
+            "printf(\"%d %s\\n\", @RDX, @RCX)"
+        );
+        assert_eq!(
+            call("__fprintf_chk", Some((2, "%d\n"))),
+            "fprintf((void *)(@RDI), \"%d\\n\", @RCX)"
+        );
+        assert_eq!(
+            call("__snprintf_chk", Some((4, "%s"))),
+            "snprintf((int8_t *)(@RDI), (uint64_t)(@RSI), \"%s\", @R9)"
+        );
+    }
+}
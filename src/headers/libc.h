@@ -0,0 +1,182 @@
+/* The C standard library, plus the glibc entry points compilers call behind the scenes.
+ * size_t, ptrdiff_t and the <stdint.h> types are built in and follow the target. */
+
+typedef struct _IO_FILE FILE;
+typedef long time_t;
+typedef long clock_t;
+typedef char *va_list;
+typedef long jmp_buf[8];
+
+/* <stdio.h> */
+int printf(const char *format, ...) __attribute__((format(printf, 1, 2)));
+int fprintf(FILE *stream, const char *format, ...) __attribute__((format(printf, 2, 3)));
+int dprintf(int fd, const char *format, ...) __attribute__((format(printf, 2, 3)));
+int sprintf(char *str, const char *format, ...) __attribute__((format(printf, 2, 3)));
+int snprintf(char *str, size_t size, const char *format, ...) __attribute__((format(printf, 3, 4)));
+int asprintf(char **strp, const char *format, ...) __attribute__((format(printf, 2, 3)));
+int vprintf(const char *format, va_list ap);
+int vfprintf(FILE *stream, const char *format, va_list ap);
+int vdprintf(int fd, const char *format, va_list ap);
+int vsprintf(char *str, const char *format, va_list ap);
+int vsnprintf(char *str, size_t size, const char *format, va_list ap);
+int scanf(const char *format, ...) __attribute__((format(scanf, 1, 2)));
+int fscanf(FILE *stream, const char *format, ...) __attribute__((format(scanf, 2, 3)));
+int sscanf(const char *str, const char *format, ...) __attribute__((format(scanf, 2, 3)));
+int puts(const char *s);
+int fputs(const char *s, FILE *stream);
+int putchar(int c);
+int fputc(int c, FILE *stream);
+int putc(int c, FILE *stream);
+int getchar(void);
+int fgetc(FILE *stream);
+int getc(FILE *stream);
+int ungetc(int c, FILE *stream);
+char *fgets(char *s, int size, FILE *stream);
+FILE *fopen(const char *pathname, const char *mode);
+FILE *freopen(const char *pathname, const char *mode, FILE *stream);
+FILE *fdopen(int fd, const char *mode);
+int fclose(FILE *stream);
+int fileno(FILE *stream);
+size_t fread(void *ptr, size_t size, size_t nmemb, FILE *stream);
+size_t fwrite(const void *ptr, size_t size, size_t nmemb, FILE *stream);
+int fseek(FILE *stream, long offset, int whence);
+long ftell(FILE *stream);
+void rewind(FILE *stream);
+int feof(FILE *stream);
+int ferror(FILE *stream);
+int fflush(FILE *stream);
+int setvbuf(FILE *stream, char *buf, int mode, size_t size);
+void setbuf(FILE *stream, char *buf);
+void perror(const char *s);
+int remove(const char *pathname);
+int rename(const char *oldpath, const char *newpath);
+FILE *tmpfile(void);
+
+/* <stdlib.h> */
+void *malloc(size_t size);
+void *calloc(size_t nmemb, size_t size);
+void *realloc(void *ptr, size_t size);
+void *aligned_alloc(size_t alignment, size_t size);
+void free(void *ptr);
+_Noreturn void exit(int status);
+_Noreturn void _Exit(int status);
+_Noreturn void quick_exit(int status);
+_Noreturn void abort(void);
+int atexit(void (*function)(void));
+int atoi(const char *nptr);
+long atol(const char *nptr);
+long long atoll(const char *nptr);
+double atof(const char *nptr);
+long strtol(const char *nptr, char **endptr, int base);
+long long strtoll(const char *nptr, char **endptr, int base);
+unsigned long strtoul(const char *nptr, char **endptr, int base);
+unsigned long long strtoull(const char *nptr, char **endptr, int base);
+double strtod(const char *nptr, char **endptr);
+char *getenv(const char *name);
+int setenv(const char *name, const char *value, int overwrite);
+int system(const char *command);
+int rand(void);
+void srand(unsigned int seed);
+void qsort(void *base, size_t nmemb, size_t size, int (*compar)(const void *, const void *));
+void *bsearch(const void *key, const void *base, size_t nmemb, size_t size, int (*compar)(const void *, const void *));
+int abs(int j);
+long labs(long j);
+
+/* <string.h> */
+void *memcpy(void *dest, const void *src, size_t n);
+void *memmove(void *dest, const void *src, size_t n);
+void *memset(void *s, int c, size_t n);
+void *mempcpy(void *dest, const void *src, size_t n);
+void explicit_bzero(void *s, size_t n);
+int memcmp(const void *s1, const void *s2, size_t n);
+void *memchr(const void *s, int c, size_t n);
+size_t strlen(const char *s);
+size_t strnlen(const char *s, size_t maxlen);
+char *strcpy(char *dest, const char *src);
+char *strncpy(char *dest, const char *src, size_t n);
+char *stpcpy(char *dest, const char *src);
+char *stpncpy(char *dest, const char *src, size_t n);
+char *strcat(char *dest, const char *src);
+char *strncat(char *dest, const char *src, size_t n);
+int strcmp(const char *s1, const char *s2);
+int strncmp(const char *s1, const char *s2, size_t n);
+int strcasecmp(const char *s1, const char *s2);
+int strncasecmp(const char *s1, const char *s2, size_t n);
+char *strchr(const char *s, int c);
+char *strrchr(const char *s, int c);
+char *strstr(const char *haystack, const char *needle);
+char *strdup(const char *s);
+char *strndup(const char *s, size_t n);
+char *strtok(char *str, const char *delim);
+size_t strspn(const char *s, const char *accept);
+size_t strcspn(const char *s, const char *reject);
+char *strerror(int errnum);
+
+/* <ctype.h> */
+int isalnum(int c);
+int isalpha(int c);
+int isdigit(int c);
+int isxdigit(int c);
+int islower(int c);
+int isupper(int c);
+int isspace(int c);
+int isprint(int c);
+int ispunct(int c);
+int tolower(int c);
+int toupper(int c);
+
+/* <time.h> */
+time_t time(time_t *tloc);
+clock_t clock(void);
+
+/* <setjmp.h> */
+int setjmp(jmp_buf env);
+int _setjmp(jmp_buf env);
+_Noreturn void longjmp(jmp_buf env, int val);
+_Noreturn void siglongjmp(jmp_buf env, int val);
+
+/* <signal.h> */
+void (*signal(int signum, void (*handler)(int)))(int);
+int raise(int sig);
+
+/* glibc and the Itanium C++ ABI */
+int __libc_start_main(int (*main)(int, char **, char **), int argc, char **argv, void (*init)(void), void (*fini)(void), void (*rtld_fini)(void), void *stack_end) __attribute__((noreturn));
+void __assert_fail(const char *assertion, const char *file, unsigned int line, const char *function) __attribute__((noreturn));
+void __stack_chk_fail(void) __attribute__((noreturn));
+void __stack_chk_fail_local(void) __attribute__((noreturn));
+void __chk_fail(void) __attribute__((noreturn));
+void __fortify_fail(const char *msg) __attribute__((noreturn));
+int *__errno_location(void);
+int __isoc99_scanf(const char *format, ...) __attribute__((format(scanf, 1, 2)));
+int __isoc99_fscanf(FILE *stream, const char *format, ...) __attribute__((format(scanf, 2, 3)));
+int __isoc99_sscanf(const char *str, const char *format, ...) __attribute__((format(scanf, 2, 3)));
+int __printf_chk(int flag, const char *format, ...) __attribute__((format(printf, 2, 3)));
+int __fprintf_chk(FILE *stream, int flag, const char *format, ...) __attribute__((format(printf, 3, 4)));
+int __sprintf_chk(char *s, int flag, size_t slen, const char *format, ...) __attribute__((format(printf, 4, 5)));
+int __snprintf_chk(char *s, size_t maxlen, int flag, size_t slen, const char *format, ...) __attribute__((format(printf, 5, 6)));
+int __dprintf_chk(int fd, int flag, const char *format, ...) __attribute__((format(printf, 3, 4)));
+int __vprintf_chk(int flag, const char *format, va_list ap);
+int __vfprintf_chk(FILE *stream, int flag, const char *format, va_list ap);
+int __vdprintf_chk(int fd, int flag, const char *format, va_list ap);
+int __vsprintf_chk(char *s, int flag, size_t slen, const char *format, va_list ap);
+int __vsnprintf_chk(char *s, size_t maxlen, int flag, size_t slen, const char *format, va_list ap);
+void *__memcpy_chk(void *dest, const void *src, size_t len, size_t destlen);
+void *__memmove_chk(void *dest, const void *src, size_t len, size_t destlen);
+void *__memset_chk(void *dest, int c, size_t len, size_t destlen);
+char *__strcpy_chk(char *dest, const char *src, size_t destlen);
+char *__strcat_chk(char *dest, const char *src, size_t destlen);
+void *__mempcpy_chk(void *dest, const void *src, size_t len, size_t destlen);
+char *__stpcpy_chk(char *dest, const char *src, size_t destlen);
+char *__strncpy_chk(char *dest, const char *src, size_t len, size_t destlen);
+char *__strncat_chk(char *dest, const char *src, size_t len, size_t destlen);
+char *__stpncpy_chk(char *dest, const char *src, size_t len, size_t destlen);
+char *__fgets_chk(char *s, size_t size, int n, FILE *stream);
+size_t __fread_chk(void *ptr, size_t ptrlen, size_t size, size_t nmemb, FILE *stream);
+void __explicit_bzero_chk(void *dest, size_t len, size_t destlen);
+int __cxa_atexit(void (*func)(void *), void *arg, void *dso_handle);
+void __cxa_finalize(void *d);
+void *__cxa_allocate_exception(size_t thrown_size);
+void __cxa_throw(void *thrown_exception, void *tinfo, void (*dest)(void *)) __attribute__((noreturn));
+void __cxa_rethrow(void) __attribute__((noreturn));
+void __cxa_pure_virtual(void) __attribute__((noreturn));
+void _Unwind_Resume(void *exception_object) __attribute__((noreturn));
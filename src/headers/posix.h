@@ -0,0 +1,108 @@
+/* POSIX system calls and libraries beyond the C standard. */
+
+typedef int pid_t;
+typedef unsigned int uid_t;
+typedef unsigned int gid_t;
+typedef unsigned int mode_t;
+typedef long off_t;
+typedef unsigned int socklen_t;
+typedef unsigned long pthread_t;
+typedef union pthread_attr_t pthread_attr_t;
+typedef union pthread_mutex_t pthread_mutex_t;
+typedef struct __dirstream DIR;
+
+/* <unistd.h>, <fcntl.h> */
+ssize_t read(int fd, void *buf, size_t count);
+ssize_t __read_chk(int fd, void *buf, size_t nbytes, size_t buflen);
+ssize_t write(int fd, const void *buf, size_t count);
+ssize_t pread(int fd, void *buf, size_t count, off_t offset);
+ssize_t __pread_chk(int fd, void *buf, size_t nbytes, off_t offset, size_t buflen);
+ssize_t pwrite(int fd, const void *buf, size_t count, off_t offset);
+int open(const char *pathname, int flags, ...);
+int openat(int dirfd, const char *pathname, int flags, ...);
+int creat(const char *pathname, mode_t mode);
+int close(int fd);
+off_t lseek(int fd, off_t offset, int whence);
+int fcntl(int fd, int cmd, ...);
+int ioctl(int fd, unsigned long request, ...);
+int pipe(int pipefd[2]);
+int dup(int oldfd);
+int dup2(int oldfd, int newfd);
+int unlink(const char *pathname);
+int rmdir(const char *pathname);
+int mkdir(const char *pathname, mode_t mode);
+int chdir(const char *path);
+char *getcwd(char *buf, size_t size);
+char *realpath(const char *path, char *resolved_path);
+char *__getcwd_chk(char *buf, size_t size, size_t buflen);
+char *__realpath_chk(const char *path, char *resolved_path, size_t resolvedlen);
+int access(const char *pathname, int mode);
+int chmod(const char *pathname, mode_t mode);
+ssize_t readlink(const char *pathname, char *buf, size_t bufsiz);
+unsigned int sleep(unsigned int seconds);
+int usleep(unsigned int usec);
+unsigned int alarm(unsigned int seconds);
+int isatty(int fd);
+long sysconf(int name);
+long syscall(long number, ...);
+_Noreturn void _exit(int status);
+
+/* processes */
+pid_t fork(void);
+pid_t vfork(void);
+pid_t getpid(void);
+pid_t getppid(void);
+uid_t getuid(void);
+uid_t geteuid(void);
+gid_t getgid(void);
+int setuid(uid_t uid);
+int setgid(gid_t gid);
+int execve(const char *pathname, char *const argv[], char *const envp[]);
+int execv(const char *pathname, char *const argv[]);
+int execvp(const char *file, char *const argv[]);
+int execl(const char *pathname, const char *arg, ...);
+int execlp(const char *file, const char *arg, ...);
+pid_t wait(int *wstatus);
+pid_t waitpid(pid_t pid, int *wstatus, int options);
+int kill(pid_t pid, int sig);
+FILE *popen(const char *command, const char *type);
+int pclose(FILE *stream);
+
+/* <sys/mman.h> */
+void *mmap(void *addr, size_t length, int prot, int flags, int fd, off_t offset);
+int munmap(void *addr, size_t length);
+int mprotect(void *addr, size_t len, int prot);
+
+/* <sys/socket.h> */
+int socket(int domain, int type, int protocol);
+int connect(int sockfd, const struct sockaddr *addr, socklen_t addrlen);
+int bind(int sockfd, const struct sockaddr *addr, socklen_t addrlen);
+int listen(int sockfd, int backlog);
+int accept(int sockfd, struct sockaddr *addr, socklen_t *addrlen);
+ssize_t send(int sockfd, const void *buf, size_t len, int flags);
+ssize_t recv(int sockfd, void *buf, size_t len, int flags);
+ssize_t __recv_chk(int sockfd, void *buf, size_t len, size_t buflen, int flags);
+ssize_t sendto(int sockfd, const void *buf, size_t len, int flags, const struct sockaddr *dest_addr, socklen_t addrlen);
+ssize_t recvfrom(int sockfd, void *buf, size_t len, int flags, struct sockaddr *src_addr, socklen_t *addrlen);
+int setsockopt(int sockfd, int level, int optname, const void *optval, socklen_t optlen);
+int shutdown(int sockfd, int how);
+
+/* <dirent.h> */
+DIR *opendir(const char *name);
+struct dirent *readdir(DIR *dirp);
+int closedir(DIR *dirp);
+
+/* <pthread.h> */
+int pthread_create(pthread_t *thread, const pthread_attr_t *attr, void *(*start_routine)(void *), void *arg);
+int pthread_join(pthread_t thread, void **retval);
+int pthread_detach(pthread_t thread);
+pthread_t pthread_self(void);
+_Noreturn void pthread_exit(void *retval);
+int pthread_mutex_lock(pthread_mutex_t *mutex);
+int pthread_mutex_unlock(pthread_mutex_t *mutex);
+
+/* <dlfcn.h> */
+void *dlopen(const char *filename, int flags);
+void *dlsym(void *handle, const char *symbol);
+int dlclose(void *handle);
+char *dlerror(void);
@@ -0,0 +1,3702 @@
+use std::collections::{BTreeSet, HashMap};
+
+use iced_x86::{
+    ConditionCode, Instruction, InstructionInfoFactory, Mnemonic, OpAccess, OpKind, Register,
+    RflagsBits,
+};
+
+use crate::abi::CallingConvention;
+use crate::arch::{self, Flow};
+use crate::ast::*;
+use crate::cfg::Cfg;
+use crate::memory::memory::Memory;
+use crate::parser::{get_binary, get_not, get_operand};
+use crate::slots::{mask, sign_extend};
+
+/// Values made of more nodes than this are not followed any further, they are only ever
+/// built by long chains of arithmetic no idiom needs.
+const MAX_NODES: usize = 48;
+
+/// The status flags a comparison is read through.
+const STATUS_FLAGS: u32 =
+    RflagsBits::OF | RflagsBits::SF | RflagsBits::ZF | RflagsBits::CF | RflagsBits::PF;
+
+/// A value computed by the instructions of a block, in terms of what they read.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Const(u64),
+    /// A register as written by an instruction of the block, or as it was on entry to it
+    /// when there is none.
+    Reg(Register, Option<usize>),
+    /// Memory operand `1` of instruction `0`, as it reads it.
+    Mem(usize, u32),
+    /// An operation on values of the given number of bits.
+    Op(Op, u32, Vec<Value>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Sar,
+    Neg,
+    Not,
+    /// The upper half of the double-width product.
+    MulHigh {
+        signed: bool,
+    },
+    Div {
+        signed: bool,
+    },
+    Rem {
+        signed: bool,
+    },
+    /// The low `from` bits of the operand, extended.
+    Extend {
+        from: u32,
+        signed: bool,
+    },
+    /// The first operand with its low `bits` replaced by those of the second, like a write
+    /// to AL leaves the rest of RAX alone.
+    Merge {
+        bits: u32,
+    },
+    /// 1 if the comparison of two values of `bits` holds, else 0.
+    Compare {
+        cmp: Cmp,
+        bits: u32,
+    },
+    /// 1 if the test of two floating-point values of `bits` holds, or does not when
+    /// `negated`, else 0.
+    FloatCompare {
+        test: FloatTest,
+        negated: bool,
+        bits: u32,
+    },
+    /// The second operand if the first is not 0, else the third.
+    Select,
+    Abs,
+    /// A call to a C library function inlined by the compiler at instruction `at`, as it
+    /// reads memory there.
+    Builtin {
+        builtin: Builtin,
+        at: usize,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Builtin {
+    Memcpy,
+    Memset,
+    Strlen,
+    Memcmp,
+    Strcmp,
+}
+
+impl Builtin {
+    fn name(self) -> &'static str {
+        match self {
+            Builtin::Memcpy => "memcpy",
+            Builtin::Memset => "memset",
+            Builtin::Strlen => "strlen",
+            Builtin::Memcmp => "memcmp",
+            Builtin::Strcmp => "strcmp",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Cmp {
+    Eq,
+    Ne,
+    Lt { signed: bool },
+    Le { signed: bool },
+    Gt { signed: bool },
+    Ge { signed: bool },
+}
+
+/// What the flags of `ucomisd` and friends tell of the two values compared. All of ZF, PF
+/// and CF are set when either is NaN, so that `jb` also branches then, and only the tests
+/// of `ja` and `jae` are C comparisons.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum FloatTest {
+    Gt,
+    Ge,
+    LessGreater,
+    Unordered,
+}
+
+/// What the status flags were last set from.
+#[derive(Clone, Debug)]
+enum Flags {
+    /// `cmp a, b`, `sub` and `neg`, as `0 - b`.
+    Compare(Value, Value, u32),
+    /// A result with the carry and overflow flags cleared, like `test` and `and` give.
+    Logic(Value, u32),
+    /// A result with the carry and overflow flags unknown, like `add` and `inc` give.
+    Result(Value, u32),
+    /// The result of a comparison function, like `repe cmpsb` gives: negative when the
+    /// carry flag is set, zero when the zero flag is.
+    Sign(Value),
+    /// `ucomisd a, b` and friends, comparing floating-point values of the given bits.
+    Float(Value, Value, u32),
+}
+
+/// Where the bytes stored by an instruction come from.
+#[derive(Clone, Debug)]
+enum Source {
+    /// Memory at an address, read with the same size.
+    Load(Value),
+    /// A byte, repeated.
+    Fill(Value),
+}
+
+/// What an SSE or AVX register holds, when it is a copy of memory or a repeated byte.
+#[derive(Clone)]
+struct VectorDef {
+    source: Source,
+    /// The number of low bytes of the register it covers.
+    bytes: u32,
+    chain: BTreeSet<usize>,
+}
+
+/// Bytes written to memory by an instruction, which may be part of an inlined `memcpy` or
+/// `memset`.
+#[derive(Clone)]
+struct Piece {
+    dst: Value,
+    len: Value,
+    source: Source,
+    chain: BTreeSet<usize>,
+    /// The `rep` prefixed string instructions, which write registers too.
+    rep: bool,
+}
+
+/// A register or the flags, as written by instructions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Location {
+    Reg(Register),
+    Flags,
+}
+
+/// The value a register holds at some point of the block, and the instructions it was
+/// computed by.
+#[derive(Clone)]
+struct Def {
+    value: Value,
+    chain: BTreeSet<usize>,
+    def: Option<usize>,
+}
+
+/// A register written by an instruction, with its value.
+struct Output {
+    reg: Register,
+    value: Value,
+    chain: BTreeSet<usize>,
+}
+
+/// Rewrites the idioms of optimizing compilers in the lifted x86 blocks of `cfg` into plain
+/// arithmetic: division and modulo by a constant done with a multiplication by its inverse,
+/// `lea` used to multiply, boolean tricks with `sbb` and `setcc`, sign extension with `cdq`
+/// and comparisons with `cmp` or `test` tested by the following branch. String instructions
+/// and unrolled copies and stores become calls to `memcpy`, `memset`, `strlen`, `memcmp`
+/// and `strcmp`. The instructions only computing parts of a rewritten value are dropped
+/// when nothing else reads them, the calls to the failure `handlers` of the hardening
+/// checks reading none.
+pub fn rewrite(
+    cfg: &mut Cfg,
+    memory: &Memory,
+    convention: CallingConvention,
+    handlers: &[u64],
+    symbols: &mut Vec<Symbol>,
+) {
+    let starts = cfg.blocks.keys().copied().collect::<Vec<_>>();
+    for start in starts {
+        let block = &cfg.blocks[&start];
+        if block
+            .instructions
+            .iter()
+            .any(|inst| inst.as_x86().is_none())
+        {
+            continue;
+        }
+        let analysis = Analysis::new(
+            block
+                .instructions
+                .iter()
+                .filter_map(|i| i.as_x86().copied())
+                .collect(),
+            memory,
+            convention,
+        );
+        let rewrites = analysis.rewrites(
+            cfg,
+            start,
+            convention,
+            handlers,
+            &cfg.blocks[&start].statements,
+            symbols,
+        );
+        let block = cfg.blocks.get_mut(&start).unwrap();
+        for (idx, rewrite) in rewrites {
+            match (rewrite, &mut block.statements[idx].kind) {
+                (Rewrite::Condition(cond), ExprKind::If(old, _)) => **old = cond,
+                (Rewrite::Statement(stmt), _) => block.statements[idx] = stmt,
+                (Rewrite::Noise, _) => block.statements[idx] = noise(),
+                _ => {}
+            }
+        }
+    }
+}
+
+enum Rewrite {
+    Noise,
+    Statement(Expr),
+    Condition(Expr),
+}
+
+/// The values computed by the instructions of a block.
+struct Analysis<'a> {
+    memory: &'a Memory,
+    /// The size of an address.
+    bits: u32,
+    insts: Vec<Instruction>,
+    /// The registers each instruction writes, with their values in terms of the inputs of
+    /// the block.
+    outputs: Vec<Vec<Output>>,
+    /// The same, in terms of the registers the instruction reads.
+    local: Vec<Vec<Output>>,
+    /// The conditions each conditional branch can be written with, with the instruction
+    /// setting the flags.
+    conditions: Vec<Vec<(Value, BTreeSet<usize>)>>,
+    /// The instructions reading what each instruction writes.
+    readers: Vec<BTreeSet<usize>>,
+    writes: Vec<Vec<Location>>,
+    writes_memory: Vec<bool>,
+    /// The instruction writing each location last.
+    last_writes: HashMap<Location, usize>,
+    /// The instructions whose register writes are all covered by `outputs`.
+    modeled: Vec<bool>,
+    /// The address each instruction accesses memory at, with the instructions computing it.
+    addresses: Vec<Option<(Value, BTreeSet<usize>)>>,
+    /// The bytes each instruction stores, when it may be part of an inlined copy or fill.
+    pieces: Vec<Option<Piece>>,
+}
+
+/// The state of the registers while the block is walked.
+#[derive(Default)]
+struct State {
+    regs: HashMap<Register, Def>,
+    flags: Option<FlagsDef>,
+    flags_def: Option<usize>,
+    /// The SSE and AVX registers, by their full names, holding a copy of memory or a
+    /// repeated byte.
+    vectors: HashMap<Register, VectorDef>,
+    /// The instruction writing each SSE and AVX register last.
+    vector_defs: HashMap<Register, usize>,
+}
+
+/// What the flags were last set from, with the operands as the setter reads them and with
+/// their values.
+struct FlagsDef {
+    local: Flags,
+    full: Flags,
+    /// The flags as set from the register written, when the setter subtracts.
+    result: Option<Flags>,
+    chain: BTreeSet<usize>,
+    /// The instructions `local` is computed by: the setter, and the one giving the count
+    /// of `repe cmpsb`.
+    local_chain: BTreeSet<usize>,
+    setter: usize,
+}
+
+impl State {
+    fn read(&self, full: Register) -> Def {
+        self.regs.get(&full).cloned().unwrap_or(Def {
+            value: Value::Reg(full, None),
+            chain: BTreeSet::new(),
+            def: None,
+        })
+    }
+
+    /// Returns the register as read by an instruction, without looking at its value.
+    fn leaf(&self, full: Register) -> Value {
+        Value::Reg(full, self.regs.get(&full).and_then(|def| def.def))
+    }
+}
+
+impl<'a> Analysis<'a> {
+    fn new(insts: Vec<Instruction>, memory: &'a Memory, convention: CallingConvention) -> Self {
+        let mut analysis = Self {
+            memory,
+            bits: memory.bitness(),
+            outputs: Vec::new(),
+            local: Vec::new(),
+            conditions: Vec::new(),
+            readers: vec![BTreeSet::new(); insts.len()],
+            writes: Vec::new(),
+            writes_memory: Vec::new(),
+            last_writes: HashMap::new(),
+            modeled: Vec::new(),
+            addresses: Vec::new(),
+            pieces: Vec::new(),
+            insts,
+        };
+        let mut factory = InstructionInfoFactory::new();
+        let mut state = State::default();
+        for idx in 0..analysis.insts.len() {
+            let inst = analysis.insts[idx];
+            let info = factory.info(&inst);
+
+            // what it reads, so that the instructions writing it are kept
+            let mut reads = info
+                .used_registers()
+                .iter()
+                .filter(|used| {
+                    matches!(
+                        used.access(),
+                        OpAccess::Read
+                            | OpAccess::ReadWrite
+                            | OpAccess::CondRead
+                            | OpAccess::ReadCondWrite
+                            | OpAccess::CondWrite
+                    ) || (used.access() == OpAccess::Write && used.register().size() < 4)
+                })
+                .map(|used| used.register().full_register())
+                .collect::<Vec<_>>();
+            if inst.flow_control() == iced_x86::FlowControl::Call {
+                reads.extend(
+                    convention
+                        .call_registers()
+                        .iter()
+                        .map(|reg| reg.full_register()),
+                );
+                // floating-point arguments
+                reads.extend(state.vector_defs.keys().copied());
+            }
+            for reg in reads {
+                if let Some(def) = state
+                    .regs
+                    .get(&reg)
+                    .and_then(|def| def.def)
+                    .or_else(|| state.vector_defs.get(&reg).copied())
+                {
+                    analysis.readers[def].insert(idx);
+                }
+            }
+            if inst.rflags_read() != 0 {
+                if let Some(def) = state.flags_def {
+                    analysis.readers[def].insert(idx);
+                }
+            }
+
+            let mut writes = info
+                .used_registers()
+                .iter()
+                .filter(|used| {
+                    matches!(
+                        used.access(),
+                        OpAccess::Write
+                            | OpAccess::ReadWrite
+                            | OpAccess::CondWrite
+                            | OpAccess::ReadCondWrite
+                    ) && used.register().is_gpr()
+                })
+                .map(|used| used.register().full_register())
+                .collect::<Vec<_>>();
+            let vector_writes = info
+                .used_registers()
+                .iter()
+                .filter(|used| {
+                    matches!(
+                        used.access(),
+                        OpAccess::Write
+                            | OpAccess::ReadWrite
+                            | OpAccess::CondWrite
+                            | OpAccess::ReadCondWrite
+                    ) && used.register().is_vector_register()
+                })
+                .map(|used| used.register().full_register())
+                .collect::<Vec<_>>();
+            if inst.flow_control() == iced_x86::FlowControl::Call {
+                for reg in GPRS {
+                    let preserved = convention
+                        .preserved_registers()
+                        .iter()
+                        .any(|preserved| preserved.full_register() == reg);
+                    if !preserved && !writes.contains(&reg) {
+                        writes.push(reg);
+                    }
+                }
+            }
+            analysis.writes_memory.push(
+                inst.flow_control() == iced_x86::FlowControl::Call
+                    || info.used_memory().iter().any(|mem| {
+                        matches!(
+                            mem.access(),
+                            OpAccess::Write
+                                | OpAccess::ReadWrite
+                                | OpAccess::CondWrite
+                                | OpAccess::ReadCondWrite
+                        )
+                    }),
+            );
+
+            let tested = |local: bool| {
+                let cc = condition_code(&inst)?;
+                let flags = state.flags.as_ref()?;
+                Some(match local {
+                    true => (condition(&flags.local, cc)?, flags.local_chain.clone()),
+                    false => (condition(&flags.full, cc)?, flags.chain.clone()),
+                })
+            };
+            let branch = tested(true);
+            // `sub ecx, 1; jne` too, when ECX is still read after the branch
+            let branch_result = condition_code(&inst).and_then(|cc| {
+                let flags = state.flags.as_ref()?;
+                Some((
+                    condition(flags.result.as_ref()?, cc)?,
+                    BTreeSet::from([flags.setter]),
+                ))
+            });
+            let local = analysis.semantics(&state, idx, true, branch.clone());
+            let outputs = analysis.semantics(&state, idx, false, tested(false));
+            let operands = [0, 1].map(|op| flag_operand(&state, &inst, idx, op));
+            let address = analysis.address(&state, &inst);
+            analysis.addresses.push(address);
+            let vector = analysis.vector(&state, idx);
+            let piece = analysis.piece(&state, idx);
+            analysis.pieces.push(piece);
+            let comparison = analysis.comparison(&state, idx);
+            analysis.modeled.push(
+                (writes.is_empty() || outputs.is_some())
+                    && (vector_writes.is_empty() || vector.is_some()),
+            );
+
+            // the registers written, by the model when it covers them
+            let mut locations = writes
+                .iter()
+                .map(|reg| Location::Reg(*reg))
+                .collect::<Vec<_>>();
+            let modeled = outputs.as_ref().map_or(Vec::new(), |outputs| {
+                outputs
+                    .iter()
+                    .map(|(reg, ..)| reg.full_register())
+                    .collect()
+            });
+            for reg in &writes {
+                if !modeled.contains(reg) {
+                    state.regs.insert(
+                        *reg,
+                        Def {
+                            value: Value::Reg(*reg, Some(idx)),
+                            chain: BTreeSet::new(),
+                            def: Some(idx),
+                        },
+                    );
+                }
+            }
+            let mut written = Vec::new();
+            for (reg, value, chain) in outputs.unwrap_or_default() {
+                written.push(write(&mut state, idx, reg, value, chain));
+                if !locations.contains(&Location::Reg(reg.full_register())) {
+                    locations.push(Location::Reg(reg.full_register()));
+                }
+            }
+            let local = local
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(reg, value, chain)| Output { reg, value, chain })
+                .collect();
+            for reg in &vector_writes {
+                state.vectors.remove(reg);
+                state.vector_defs.insert(*reg, idx);
+                locations.push(Location::Reg(*reg));
+            }
+            if inst.flow_control() == iced_x86::FlowControl::Call {
+                state.vectors.clear();
+            }
+            if let Some((reg, def)) = vector {
+                state.vectors.insert(reg, def);
+            }
+
+            if inst.rflags_modified() != 0 {
+                let mut chain = BTreeSet::from([idx]);
+                for (.., other) in operands.iter().flatten() {
+                    chain.extend(other.iter().copied());
+                }
+                let local = operands
+                    .clone()
+                    .map(|operand| operand.map(|(leaf, ..)| leaf));
+                let full = operands.map(|operand| operand.map(|(_, value, _)| value));
+                let result = match inst.op0_kind() {
+                    OpKind::Register if inst.op0_register().is_gpr() => {
+                        Some(inst.op0_register().full_register())
+                    }
+                    _ => None,
+                };
+                let full_result = result.map(|reg| {
+                    let def = state.read(reg);
+                    chain.extend(def.chain);
+                    def.value
+                });
+                let local_result = result.map(|reg| Value::Reg(reg, Some(idx)));
+                let subtracted = match inst.mnemonic() {
+                    Mnemonic::Sub | Mnemonic::Neg => local_result
+                        .clone()
+                        .map(|value| Flags::Result(value, inst.op0_register().size() as u32 * 8)),
+                    _ => None,
+                };
+                state.flags = flags(&inst, local, local_result)
+                    .zip(flags(&inst, full, full_result))
+                    .map(|(local, full)| FlagsDef {
+                        local,
+                        full,
+                        result: subtracted,
+                        chain,
+                        local_chain: BTreeSet::from([idx]),
+                        setter: idx,
+                    });
+                if let Some((local, full, local_chain, chain)) = comparison {
+                    state.flags = Some(FlagsDef {
+                        local: Flags::Sign(local),
+                        full: Flags::Sign(full),
+                        result: None,
+                        chain,
+                        local_chain,
+                        setter: idx,
+                    });
+                }
+                if let Some(flags) = float_flags(&state, &inst, idx) {
+                    state.flags = Some(FlagsDef {
+                        local: flags.clone(),
+                        full: flags,
+                        result: None,
+                        chain: BTreeSet::from([idx]),
+                        local_chain: BTreeSet::from([idx]),
+                        setter: idx,
+                    });
+                }
+                state.flags_def = Some(idx);
+                locations.push(Location::Flags);
+            }
+            for location in &locations {
+                analysis.last_writes.insert(*location, idx);
+            }
+
+            analysis.conditions.push(match inst.flow_control() {
+                iced_x86::FlowControl::ConditionalBranch => {
+                    [branch, branch_result].into_iter().flatten().collect()
+                }
+                _ => Vec::new(),
+            });
+            analysis.outputs.push(written);
+            analysis.local.push(local);
+            analysis.writes.push(locations);
+        }
+        analysis
+    }
+
+    /// Computes the registers instruction `idx` writes and their values, from the registers
+    /// as it reads them when `local` is set, else from their values. `condition` is the one
+    /// it tests, with the instruction setting the flags.
+    #[allow(clippy::type_complexity)]
+    fn semantics(
+        &self,
+        state: &State,
+        idx: usize,
+        local: bool,
+        condition: Option<(Value, BTreeSet<usize>)>,
+    ) -> Option<Vec<(Register, Value, BTreeSet<usize>)>> {
+        let inst = &self.insts[idx];
+        let read = |full: Register| -> (Value, BTreeSet<usize>) {
+            if local {
+                (state.leaf(full), BTreeSet::new())
+            } else {
+                let def = state.read(full);
+                (def.value, def.chain)
+            }
+        };
+        let operand = |op: u32| -> Option<(Value, BTreeSet<usize>)> {
+            match inst.op_kind(op) {
+                OpKind::Register => {
+                    let reg = inst.op_register(op);
+                    if !reg.is_gpr() || is_high_byte(reg) {
+                        return None;
+                    }
+                    Some(read(reg.full_register()))
+                }
+                OpKind::Memory => Some((Value::Mem(idx, op), BTreeSet::new())),
+                OpKind::Immediate8
+                | OpKind::Immediate16
+                | OpKind::Immediate32
+                | OpKind::Immediate64
+                | OpKind::Immediate8to16
+                | OpKind::Immediate8to32
+                | OpKind::Immediate8to64
+                | OpKind::Immediate32to64 => {
+                    Some((Value::Const(inst.immediate(op)), BTreeSet::new()))
+                }
+                _ => None,
+            }
+        };
+        let size = |op: u32| -> u32 {
+            match inst.op_kind(op) {
+                OpKind::Register => inst.op_register(op).size() as u32 * 8,
+                _ => inst.memory_size().size() as u32 * 8,
+            }
+        };
+        let dst = inst.op0_register();
+        let bits = dst.size() as u32 * 8;
+        if inst.op_count() > 0
+            && inst.op0_kind() == OpKind::Register
+            && (!dst.is_gpr() || is_high_byte(dst))
+        {
+            return None;
+        }
+        let join = |chains: &[&BTreeSet<usize>]| {
+            let mut chain = BTreeSet::from([idx]);
+            for other in chains {
+                chain.extend(other.iter().copied());
+            }
+            chain
+        };
+        let same_registers = inst.op_count() >= 2
+            && inst.op0_kind() == OpKind::Register
+            && inst.op1_kind() == OpKind::Register
+            && inst.op0_register() == inst.op1_register();
+
+        let binary = |op: Op| -> Option<Vec<(Register, Value, BTreeSet<usize>)>> {
+            let (a, chain_a) = operand(0)?;
+            let (b, chain_b) = operand(1)?;
+            Some(vec![(
+                dst,
+                op_value(op, bits, vec![a, b]),
+                join(&[&chain_a, &chain_b]),
+            )])
+        };
+        let unary = |op: Op| -> Option<Vec<(Register, Value, BTreeSet<usize>)>> {
+            let (a, chain) = operand(0)?;
+            Some(vec![(dst, op_value(op, bits, vec![a]), join(&[&chain]))])
+        };
+
+        if string_bits(inst).is_some() {
+            return match local {
+                true => None,
+                false => self.string(state, idx),
+            };
+        }
+        if inst.op_count() > 0 && inst.op0_kind() != OpKind::Register {
+            // stores, with their own lifting
+            return None;
+        }
+        match inst.mnemonic() {
+            Mnemonic::Mov => {
+                let (a, chain) = operand(1)?;
+                Some(vec![(dst, a, join(&[&chain]))])
+            }
+            Mnemonic::Movzx | Mnemonic::Movsx | Mnemonic::Movsxd => {
+                let (a, chain) = operand(1)?;
+                let op = Op::Extend {
+                    from: size(1),
+                    signed: inst.mnemonic() != Mnemonic::Movzx,
+                };
+                Some(vec![(dst, op_value(op, bits, vec![a]), join(&[&chain]))])
+            }
+            Mnemonic::Lea if inst.is_ip_rel_memory_operand() => Some(vec![(
+                dst,
+                Value::Const(inst.ip_rel_memory_address()),
+                BTreeSet::from([idx]),
+            )]),
+            Mnemonic::Lea => {
+                let mut chain = BTreeSet::from([idx]);
+                let mut terms = Vec::new();
+                for reg in [inst.memory_base(), inst.memory_index()] {
+                    if reg == Register::None {
+                        terms.push(Value::Const(0));
+                        continue;
+                    }
+                    if !reg.is_gpr() {
+                        return None;
+                    }
+                    let (value, other) = read(reg.full_register());
+                    chain.extend(other);
+                    terms.push(value);
+                }
+                let index = terms.pop().unwrap();
+                let base = terms.pop().unwrap();
+                let scaled = op_value(
+                    Op::Mul,
+                    bits,
+                    vec![index, Value::Const(u64::from(inst.memory_index_scale()))],
+                );
+                let sum = op_value(Op::Add, bits, vec![base, scaled]);
+                let sum = op_value(
+                    Op::Add,
+                    bits,
+                    vec![sum, Value::Const(inst.memory_displacement64())],
+                );
+                Some(vec![(dst, sum, chain)])
+            }
+            Mnemonic::Xor | Mnemonic::Sub if same_registers => {
+                Some(vec![(dst, Value::Const(0), BTreeSet::from([idx]))])
+            }
+            Mnemonic::Add => binary(Op::Add),
+            Mnemonic::Sub => binary(Op::Sub),
+            Mnemonic::And => binary(Op::And),
+            Mnemonic::Or => binary(Op::Or),
+            Mnemonic::Xor => binary(Op::Xor),
+            Mnemonic::Shl | Mnemonic::Sal => binary(Op::Shl),
+            Mnemonic::Shr => binary(Op::Shr),
+            Mnemonic::Sar => binary(Op::Sar),
+            Mnemonic::Neg => unary(Op::Neg),
+            Mnemonic::Not => unary(Op::Not),
+            Mnemonic::Inc | Mnemonic::Dec => {
+                let (a, chain) = operand(0)?;
+                let op = if inst.mnemonic() == Mnemonic::Inc {
+                    Op::Add
+                } else {
+                    Op::Sub
+                };
+                Some(vec![(
+                    dst,
+                    op_value(op, bits, vec![a, Value::Const(1)]),
+                    join(&[&chain]),
+                )])
+            }
+            Mnemonic::Imul if inst.op_count() == 3 => {
+                let (a, chain) = operand(1)?;
+                let (b, _) = operand(2)?;
+                Some(vec![(
+                    dst,
+                    op_value(Op::Mul, bits, vec![a, b]),
+                    join(&[&chain]),
+                )])
+            }
+            Mnemonic::Imul if inst.op_count() == 2 => binary(Op::Mul),
+            Mnemonic::Mul | Mnemonic::Imul if inst.op_count() == 1 => {
+                let bits = size(0);
+                if bits < 16 {
+                    return None;
+                }
+                let (a, chain_a) = read(Register::RAX);
+                let (b, chain_b) = operand(0)?;
+                let signed = inst.mnemonic() == Mnemonic::Imul;
+                let chain = join(&[&chain_a, &chain_b]);
+                Some(vec![
+                    (
+                        sized(Register::RAX, bits)?,
+                        op_value(Op::Mul, bits, vec![a.clone(), b.clone()]),
+                        chain.clone(),
+                    ),
+                    (
+                        sized(Register::RDX, bits)?,
+                        op_value(Op::MulHigh { signed }, bits, vec![a, b]),
+                        chain,
+                    ),
+                ])
+            }
+            // a dividend in EDX:EAX which is only EAX, extended
+            Mnemonic::Div | Mnemonic::Idiv => {
+                let bits = size(0);
+                if bits < 16 {
+                    return None;
+                }
+                let signed = inst.mnemonic() == Mnemonic::Idiv;
+                let high = state.read(Register::RDX);
+                let low = state.read(Register::RAX).value;
+                let extension = match signed {
+                    true => op_value(Op::Sar, bits, vec![low, Value::Const(u64::from(bits - 1))]),
+                    false => Value::Const(0),
+                };
+                if !equivalent(&high.value, &extension, bits) {
+                    return None;
+                }
+                let (a, chain_a) = read(Register::RAX);
+                let (b, chain_b) = operand(0)?;
+                let mut chain = join(&[&chain_a, &chain_b]);
+                if !local {
+                    chain.extend(high.chain);
+                }
+                Some(vec![
+                    (
+                        sized(Register::RAX, bits)?,
+                        op_value(Op::Div { signed }, bits, vec![a.clone(), b.clone()]),
+                        chain.clone(),
+                    ),
+                    (
+                        sized(Register::RDX, bits)?,
+                        op_value(Op::Rem { signed }, bits, vec![a, b]),
+                        chain,
+                    ),
+                ])
+            }
+            Mnemonic::Cdq | Mnemonic::Cqo | Mnemonic::Cwd => {
+                let bits = match inst.mnemonic() {
+                    Mnemonic::Cqo => 64,
+                    Mnemonic::Cdq => 32,
+                    _ => 16,
+                };
+                let (a, chain) = read(Register::RAX);
+                let value = op_value(Op::Sar, bits, vec![a, Value::Const(u64::from(bits - 1))]);
+                Some(vec![(sized(Register::RDX, bits)?, value, join(&[&chain]))])
+            }
+            Mnemonic::Cdqe | Mnemonic::Cwde | Mnemonic::Cbw => {
+                let bits = match inst.mnemonic() {
+                    Mnemonic::Cdqe => 64,
+                    Mnemonic::Cwde => 32,
+                    _ => 16,
+                };
+                let (a, chain) = read(Register::RAX);
+                let value = op_value(
+                    Op::Extend {
+                        from: bits / 2,
+                        signed: true,
+                    },
+                    bits,
+                    vec![a],
+                );
+                Some(vec![(sized(Register::RAX, bits)?, value, join(&[&chain]))])
+            }
+            _ if is_setcc(inst) => {
+                let (cond, chain) = condition?;
+                Some(vec![(dst, cond, join(&[&chain]))])
+            }
+            _ if is_cmovcc(inst) => {
+                let (cond, chain_cond) = condition?;
+                let (a, chain_a) = operand(1)?;
+                let (old, chain_old) = operand(0)?;
+                let chain = join(&[&chain_a, &chain_old, &chain_cond]);
+                Some(vec![(
+                    dst,
+                    op_value(Op::Select, bits, vec![cond, a, old]),
+                    chain,
+                )])
+            }
+            // all ones when the carry is set
+            Mnemonic::Sbb if same_registers => {
+                let (cond, chain) = self.carry(state, local)?;
+                let chain = join(&[&chain]);
+                Some(vec![(dst, op_value(Op::Neg, bits, vec![cond]), chain)])
+            }
+            Mnemonic::Adc | Mnemonic::Sbb => {
+                let (a, chain_a) = operand(0)?;
+                let (b, chain_b) = operand(1)?;
+                let (carry, chain_carry) = self.carry(state, local)?;
+                let chain = join(&[&chain_a, &chain_b, &chain_carry]);
+                let value = match inst.mnemonic() {
+                    Mnemonic::Adc => op_value(
+                        Op::Add,
+                        bits,
+                        vec![op_value(Op::Add, bits, vec![a, b]), carry],
+                    ),
+                    _ => op_value(
+                        Op::Sub,
+                        bits,
+                        vec![op_value(Op::Sub, bits, vec![a, b]), carry],
+                    ),
+                };
+                Some(vec![(dst, value, chain)])
+            }
+            _ => None,
+        }
+    }
+
+    /// Computes the registers a `rep` prefixed string instruction writes, or `repne scasb`
+    /// looking for the end of a string.
+    #[allow(clippy::type_complexity)]
+    fn string(&self, state: &State, idx: usize) -> Option<Vec<(Register, Value, BTreeSet<usize>)>> {
+        let inst = &self.insts[idx];
+        // real-mode addresses are more than the registers
+        if self.bits < 32 || string_bits(inst)? != self.bits {
+            return None;
+        }
+        let bits = self.bits;
+        let (rcx, rsi, rdi) = (
+            sized(Register::RCX, bits)?,
+            sized(Register::RSI, bits)?,
+            sized(Register::RDI, bits)?,
+        );
+        let count = state.read(Register::RCX);
+        let src = state.read(Register::RSI);
+        let dst = state.read(Register::RDI);
+        let mut chain = BTreeSet::from([idx]);
+        chain.extend(count.chain.iter().copied());
+        let len = op_value(
+            Op::Mul,
+            bits,
+            vec![
+                count.value.clone(),
+                Value::Const(inst.memory_size().size() as u64),
+            ],
+        );
+        let advance = |from: Value| op_value(Op::Add, bits, vec![from, len.clone()]);
+        match inst.mnemonic() {
+            Mnemonic::Movsb | Mnemonic::Movsw | Mnemonic::Movsd | Mnemonic::Movsq
+                if inst.has_rep_prefix() =>
+            {
+                chain.extend(src.chain.iter().chain(&dst.chain).copied());
+                Some(vec![
+                    (rcx, Value::Const(0), chain.clone()),
+                    (rdi, advance(dst.value), chain.clone()),
+                    (rsi, advance(src.value), chain),
+                ])
+            }
+            Mnemonic::Stosb | Mnemonic::Stosw | Mnemonic::Stosd | Mnemonic::Stosq
+                if inst.has_rep_prefix() =>
+            {
+                chain.extend(dst.chain.iter().copied());
+                Some(vec![
+                    (rcx, Value::Const(0), chain.clone()),
+                    (rdi, advance(dst.value), chain),
+                ])
+            }
+            // the length of the string, with RCX counting down from -1 past its terminator
+            Mnemonic::Scasb if inst.has_repne_prefix() => {
+                let terminator = state.read(Register::RAX);
+                if narrow(terminator.value, 8) != Value::Const(0)
+                    || !matches!(count.value, Value::Const(c) if mask(c, bits) == mask(u64::MAX, bits))
+                {
+                    return None;
+                }
+                chain.extend(terminator.chain.iter().chain(&dst.chain).copied());
+                let length = Value::Op(
+                    Op::Builtin {
+                        builtin: Builtin::Strlen,
+                        at: idx,
+                    },
+                    bits,
+                    vec![dst.value.clone()],
+                );
+                let negated = op_value(Op::Neg, bits, vec![length.clone()]);
+                let past = op_value(Op::Add, bits, vec![dst.value, length]);
+                Some(vec![
+                    (
+                        rcx,
+                        op_value(Op::Sub, bits, vec![negated, Value::Const(2)]),
+                        chain.clone(),
+                    ),
+                    (
+                        rdi,
+                        op_value(Op::Add, bits, vec![past, Value::Const(1)]),
+                        chain,
+                    ),
+                ])
+            }
+            // where the strings differ is left alone, only the flags are followed
+            Mnemonic::Cmpsb if inst.has_repe_prefix() => Some(
+                [rcx, rsi, rdi]
+                    .map(|reg| {
+                        (
+                            reg,
+                            Value::Reg(reg.full_register(), Some(idx)),
+                            BTreeSet::new(),
+                        )
+                    })
+                    .into(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Returns the comparison function `repe cmpsb` computes on a constant number of bytes,
+    /// as it reads its operands and as their values, with the instructions computing each.
+    #[allow(clippy::type_complexity)]
+    fn comparison(
+        &self,
+        state: &State,
+        idx: usize,
+    ) -> Option<(Value, Value, BTreeSet<usize>, BTreeSet<usize>)> {
+        let inst = &self.insts[idx];
+        if inst.mnemonic() != Mnemonic::Cmpsb
+            || !inst.has_repe_prefix()
+            || self.bits < 32
+            || string_bits(inst)? != self.bits
+        {
+            return None;
+        }
+        let count = state.read(Register::RCX);
+        let Value::Const(n) = count.value else {
+            return None;
+        };
+        let n = mask(n, self.bits);
+        if n == 0 {
+            return None;
+        }
+        let src = state.read(Register::RSI);
+        let dst = state.read(Register::RDI);
+        // comparing with a string literal and its terminator
+        let literal = [&src.value, &dst.value].iter().any(|value| {
+            matches!(value, Value::Const(addr) if self.memory.is_read_only(*addr)
+                && self
+                    .memory
+                    .read_c_string(*addr)
+                    .is_some_and(|string| string.len() as u64 + 1 == n))
+        });
+        let (builtin, bits) = match literal {
+            true => (Builtin::Strcmp, Vec::new()),
+            false => (Builtin::Memcmp, vec![Value::Const(n)]),
+        };
+        let call = |args: [Value; 2]| {
+            let mut args = args.to_vec();
+            args.extend(bits.iter().cloned());
+            Value::Op(Op::Builtin { builtin, at: idx }, 32, args)
+        };
+        let mut local_chain = BTreeSet::from([idx]);
+        local_chain.extend(count.chain.iter().copied());
+        let mut chain = local_chain.clone();
+        chain.extend(src.chain.iter().chain(&dst.chain).copied());
+        Some((
+            call([state.leaf(Register::RSI), state.leaf(Register::RDI)]),
+            call([src.value, dst.value]),
+            local_chain,
+            chain,
+        ))
+    }
+
+    /// Returns the address instruction `inst` accesses memory at, when it has a memory
+    /// operand, with the instructions computing it.
+    fn address(&self, state: &State, inst: &Instruction) -> Option<(Value, BTreeSet<usize>)> {
+        (0..inst.op_count()).find(|op| inst.op_kind(*op) == OpKind::Memory)?;
+        if matches!(inst.memory_segment(), Register::FS | Register::GS) {
+            return None;
+        }
+        if inst.is_ip_rel_memory_operand() {
+            return Some((Value::Const(inst.ip_rel_memory_address()), BTreeSet::new()));
+        }
+        let mut chain = BTreeSet::new();
+        let mut address = Value::Const(inst.memory_displacement64());
+        for (reg, scale) in [
+            (inst.memory_base(), 1),
+            (inst.memory_index(), inst.memory_index_scale()),
+        ] {
+            if reg == Register::None {
+                continue;
+            }
+            if !reg.is_gpr() {
+                return None;
+            }
+            let def = state.read(reg.full_register());
+            chain.extend(def.chain);
+            let scaled = op_value(
+                Op::Mul,
+                self.bits,
+                vec![def.value, Value::Const(u64::from(scale))],
+            );
+            address = op_value(Op::Add, self.bits, vec![address, scaled]);
+        }
+        Some((address, chain))
+    }
+
+    /// Returns the SSE or AVX register instruction `idx` fills with a copy of memory or a
+    /// repeated byte.
+    fn vector(&self, state: &State, idx: usize) -> Option<(Register, VectorDef)> {
+        let inst = &self.insts[idx];
+        if inst.op0_kind() != OpKind::Register
+            || !inst.op0_register().is_vector_register()
+            || inst.op_mask() != Register::None
+        {
+            return None;
+        }
+        let reg = inst.op0_register();
+        let bytes = reg.size() as u32;
+        let same = |a: u32, b: u32| {
+            inst.op_kind(a) == OpKind::Register
+                && inst.op_kind(b) == OpKind::Register
+                && inst.op_register(a) == inst.op_register(b)
+        };
+        let mut chain = BTreeSet::from([idx]);
+        let (source, bytes) = match inst.mnemonic() {
+            Mnemonic::Pxor | Mnemonic::Xorps | Mnemonic::Xorpd
+                if inst.op_count() == 2 && same(0, 1) =>
+            {
+                (Source::Fill(Value::Const(0)), bytes)
+            }
+            // the VEX forms clear the upper bits too
+            Mnemonic::Vpxor
+            | Mnemonic::Vpxord
+            | Mnemonic::Vpxorq
+            | Mnemonic::Vxorps
+            | Mnemonic::Vxorpd
+                if inst.op_count() == 3 && same(1, 2) =>
+            {
+                (Source::Fill(Value::Const(0)), 64)
+            }
+            Mnemonic::Pcmpeqb | Mnemonic::Pcmpeqw | Mnemonic::Pcmpeqd | Mnemonic::Pcmpeqq
+                if inst.op_count() == 2 && same(0, 1) =>
+            {
+                (Source::Fill(Value::Const(0xFF)), bytes)
+            }
+            Mnemonic::Vpcmpeqb | Mnemonic::Vpcmpeqw | Mnemonic::Vpcmpeqd | Mnemonic::Vpcmpeqq
+                if inst.op_count() == 3 && same(1, 2) =>
+            {
+                (Source::Fill(Value::Const(0xFF)), bytes)
+            }
+            mnemonic if VECTOR_MOVES.contains(&mnemonic) && inst.op_count() == 2 => {
+                match inst.op1_kind() {
+                    OpKind::Memory => {
+                        let (address, other) = self.addresses[idx].clone()?;
+                        chain.extend(other);
+                        let fill = match address {
+                            Value::Const(addr) if self.memory.is_read_only(addr) => self
+                                .memory
+                                .get(addr)
+                                .filter(|data| data.len() >= bytes as usize)
+                                .and_then(|data| repeated(&data[..bytes as usize])),
+                            _ => None,
+                        };
+                        match fill {
+                            Some(byte) => (Source::Fill(Value::Const(byte)), bytes),
+                            None => (Source::Load(address), bytes),
+                        }
+                    }
+                    OpKind::Register => {
+                        let def = state.vectors.get(&inst.op1_register().full_register())?;
+                        chain.extend(def.chain.iter().copied());
+                        (def.source.clone(), def.bytes.min(bytes))
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        Some((
+            reg.full_register(),
+            VectorDef {
+                source,
+                bytes,
+                chain,
+            },
+        ))
+    }
+
+    /// Returns the bytes instruction `idx` stores, when it copies them from memory or
+    /// repeats a byte.
+    fn piece(&self, state: &State, idx: usize) -> Option<Piece> {
+        let inst = &self.insts[idx];
+        if self.bits < 32 {
+            return None;
+        }
+        if string_bits(inst).is_some() {
+            if !inst.has_rep_prefix() || string_bits(inst)? != self.bits {
+                return None;
+            }
+            let size = inst.memory_size().size() as u64;
+            let count = state.read(Register::RCX);
+            let dst = state.read(Register::RDI);
+            let mut chain = BTreeSet::from([idx]);
+            chain.extend(count.chain.iter().chain(&dst.chain).copied());
+            let source = match inst.mnemonic() {
+                Mnemonic::Movsb | Mnemonic::Movsw | Mnemonic::Movsd | Mnemonic::Movsq => {
+                    let src = state.read(Register::RSI);
+                    chain.extend(src.chain);
+                    Source::Load(src.value)
+                }
+                Mnemonic::Stosb | Mnemonic::Stosw | Mnemonic::Stosd | Mnemonic::Stosq => {
+                    let fill = state.read(Register::RAX);
+                    chain.extend(fill.chain);
+                    match (size, narrow(fill.value, size as u32 * 8)) {
+                        (_, Value::Const(c)) => {
+                            let bytes = mask(c, size as u32 * 8).to_le_bytes();
+                            Source::Fill(Value::Const(repeated(&bytes[..size as usize])?))
+                        }
+                        (1, byte) => Source::Fill(byte),
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            };
+            let len = op_value(Op::Mul, self.bits, vec![count.value, Value::Const(size)]);
+            return Some(Piece {
+                dst: dst.value,
+                len,
+                source,
+                chain,
+                rep: true,
+            });
+        }
+        if inst.op_count() != 2 || inst.op0_kind() != OpKind::Memory {
+            return None;
+        }
+        let (dst, mut chain) = self.addresses[idx].clone()?;
+        chain.insert(idx);
+        let size = inst.memory_size().size();
+        let constant = |c: u64| {
+            let bytes = c.to_le_bytes();
+            Some(Source::Fill(Value::Const(repeated(bytes.get(..size)?)?)))
+        };
+        let source = match inst.op1_kind() {
+            OpKind::Register if inst.op1_register().is_vector_register() => {
+                if !(VECTOR_MOVES.contains(&inst.mnemonic())
+                    || VECTOR_LOW_STORES.contains(&inst.mnemonic()))
+                {
+                    return None;
+                }
+                let def = state.vectors.get(&inst.op1_register().full_register())?;
+                if size as u32 > def.bytes {
+                    return None;
+                }
+                chain.extend(def.chain.iter().copied());
+                def.source.clone()
+            }
+            OpKind::Register if inst.mnemonic() == Mnemonic::Mov => {
+                let reg = inst.op1_register();
+                if !reg.is_gpr() || is_high_byte(reg) {
+                    return None;
+                }
+                let def = state.read(reg.full_register());
+                chain.extend(def.chain);
+                match narrow(def.value, size as u32 * 8) {
+                    Value::Const(c) => constant(c)?,
+                    // a load of the same size
+                    Value::Mem(at, _)
+                        if self.insts[at].mnemonic() == Mnemonic::Mov
+                            && self.insts[at].memory_size().size() == size =>
+                    {
+                        let (address, other) = self.addresses[at].clone()?;
+                        chain.extend(other);
+                        Source::Load(address)
+                    }
+                    _ => return None,
+                }
+            }
+            OpKind::Immediate8
+            | OpKind::Immediate16
+            | OpKind::Immediate32
+            | OpKind::Immediate8to16
+            | OpKind::Immediate8to32
+            | OpKind::Immediate8to64
+            | OpKind::Immediate32to64
+                if inst.mnemonic() == Mnemonic::Mov =>
+            {
+                constant(inst.immediate(1))?
+            }
+            _ => return None,
+        };
+        Some(Piece {
+            dst,
+            len: Value::Const(size as u64),
+            source,
+            chain,
+            rep: false,
+        })
+    }
+
+    /// Finds the runs of stores filling a contiguous range of memory from one source, which
+    /// are calls to `memcpy` and `memset` inlined by the compiler, by the last store of each
+    /// or the last load of a copy whose loads come first.
+    fn runs(&self) -> HashMap<usize, (Value, BTreeSet<usize>, BTreeSet<usize>)> {
+        let mut factory = InstructionInfoFactory::new();
+        let accesses = self
+            .insts
+            .iter()
+            .map(|inst| {
+                inst.flow_control() == iced_x86::FlowControl::Call
+                    || inst.flow_control() == iced_x86::FlowControl::IndirectCall
+                    || factory
+                        .info(inst)
+                        .used_memory()
+                        .iter()
+                        .any(|mem| mem.access() != OpAccess::NoMemAccess)
+            })
+            .collect::<Vec<_>>();
+        // the loads feeding the stores are part of the runs
+        let feeding = self
+            .pieces
+            .iter()
+            .flatten()
+            .flat_map(|piece| piece.chain.iter().copied())
+            .collect::<BTreeSet<_>>();
+
+        let mut groups = Vec::new();
+        let mut group = Vec::<usize>::new();
+        for (idx, piece) in self.pieces.iter().enumerate() {
+            match piece {
+                Some(piece) => {
+                    if let Some(last) = group.last().and_then(|last| self.pieces[*last].as_ref()) {
+                        if last.rep || !same_source(&last.source, &piece.source) {
+                            groups.push(std::mem::take(&mut group));
+                        }
+                    }
+                    group.push(idx);
+                }
+                None if accesses[idx] && !feeding.contains(&idx) => {
+                    groups.push(std::mem::take(&mut group))
+                }
+                None => {}
+            }
+        }
+        groups.push(group);
+
+        let mut runs = HashMap::new();
+        for group in groups {
+            let (Some(&first), Some(&end)) = (group.first(), group.last()) else {
+                continue;
+            };
+            let pieces = group
+                .iter()
+                .map(|idx| self.pieces[*idx].as_ref().unwrap())
+                .collect::<Vec<_>>();
+            let rep = pieces.iter().any(|piece| piece.rep);
+            let vector = group.iter().any(|idx| {
+                let inst = &self.insts[*idx];
+                inst.op1_kind() == OpKind::Register && inst.op1_register().is_vector_register()
+            });
+            if !(rep || vector && group.len() > 1) {
+                continue;
+            }
+            let mut chain = BTreeSet::new();
+            for piece in &pieces {
+                chain.extend(piece.chain.iter().copied());
+            }
+            // nothing else touches memory while the run is stored, nor writes it while it is
+            // loaded
+            let start = chain
+                .iter()
+                .copied()
+                .filter(|member| accesses[*member])
+                .chain([first])
+                .min()
+                .unwrap();
+            let foreign = (start..=end).any(|other| {
+                !chain.contains(&other)
+                    && (self.writes_memory[other] && !self.saves_canary(other)
+                        || other > first && accesses[other])
+            });
+            if foreign {
+                continue;
+            }
+            let Some((dst, len, source)) = self.coverage(&pieces) else {
+                continue;
+            };
+            if !rep && !matches!(len, Value::Const(len) if len >= 32) {
+                continue;
+            }
+            let (builtin, source) = match source {
+                Source::Load(src) => (Builtin::Memcpy, src),
+                Source::Fill(byte) => (Builtin::Memset, byte),
+            };
+            // the stores and the loads feeding them are dropped whatever else is kept
+            let copies = chain
+                .iter()
+                .copied()
+                .filter(|member| {
+                    group.contains(member)
+                        || self.writes[*member].iter().all(|location| {
+                            matches!(location, Location::Reg(reg) if reg.is_vector_register())
+                        })
+                })
+                .collect::<BTreeSet<_>>();
+            let call = |at| {
+                let args = vec![
+                    self.rebase(&dst, at, &copies),
+                    self.rebase(&source, at, &copies),
+                    len.clone(),
+                ];
+                Value::Op(Op::Builtin { builtin, at }, self.bits, args)
+            };
+            let mut anchor = end;
+            // gcc hoists the loads of an SSE copy above its stores and reuses the registers
+            // they read in between, so the copy is made by the last load when nothing but
+            // the run touches memory after it
+            if !self.leaves_valid(&call(end), end, &copies) {
+                let Some(&load) = chain.range(..first).next_back() else {
+                    continue;
+                };
+                let touched =
+                    (load + 1..=end).any(|other| !chain.contains(&other) && accesses[other]);
+                if touched || !self.leaves_valid(&call(load), load, &copies) {
+                    continue;
+                }
+                anchor = load;
+            }
+            let forced = group.iter().copied().filter(|idx| *idx != anchor).collect();
+            runs.insert(anchor, (call(anchor), chain, forced));
+        }
+        runs
+    }
+
+    /// Rewrites the parts of `value` no longer computable at instruction `idx` once the
+    /// instructions in `hidden` are dropped, but still held by a register there, as that
+    /// register, like the frame a `sub rsp` allocates.
+    fn rebase(&self, value: &Value, idx: usize, hidden: &BTreeSet<usize>) -> Value {
+        let Value::Op(op, bits, args) = value else {
+            return value.clone();
+        };
+        if self.leaves_valid(value, idx, hidden) {
+            return value.clone();
+        }
+        let held = (0..idx).rev().find_map(|def| {
+            let output = self.outputs[def].iter().find(|output| {
+                output.value == *value && output.reg.size() as u32 * 8 == self.bits
+            })?;
+            let full = output.reg.full_register();
+            let kept = !(def + 1..idx)
+                .any(|other| self.writes[other].contains(&Location::Reg(full)));
+            kept.then_some(Value::Reg(full, Some(def)))
+        });
+        held.unwrap_or_else(|| {
+            let args = args.iter().map(|arg| self.rebase(arg, idx, hidden)).collect();
+            Value::Op(*op, *bits, args)
+        })
+    }
+
+    /// Returns `true` if instruction `idx` saves the stack canary it read from `fs` or `gs`
+    /// to the frame, a slot no pointer of the program reaches.
+    fn saves_canary(&self, idx: usize) -> bool {
+        let inst = &self.insts[idx];
+        inst.mnemonic() == Mnemonic::Mov
+            && inst.op0_kind() == OpKind::Memory
+            && inst.op1_kind() == OpKind::Register
+            && (0..idx).any(|load| {
+                let load_inst = &self.insts[load];
+                load_inst.mnemonic() == Mnemonic::Mov
+                    && load_inst.op1_kind() == OpKind::Memory
+                    && matches!(load_inst.memory_segment(), Register::FS | Register::GS)
+                    && self.readers[load].contains(&idx)
+            })
+    }
+
+    /// Returns the destination, the length and the source of the bytes `pieces` store, when
+    /// they cover a single range of constant length copied or filled from one source.
+    fn coverage(&self, pieces: &[&Piece]) -> Option<(Value, Value, Source)> {
+        if let [piece] = pieces {
+            return Some((piece.dst.clone(), piece.len.clone(), piece.source.clone()));
+        }
+        let bits = self.bits;
+        let mut seed = 0x9E37_79B9_7F4A_7C15_u64;
+        let mut found = None;
+        for _ in 0..16 {
+            seed = random(seed);
+            let base = seed;
+            let leaf = |value: &Value| -> u64 {
+                let mut hash = base;
+                for byte in format!("{value:?}").bytes() {
+                    hash = (hash ^ u64::from(byte)).wrapping_mul(0x100_0000_01B3);
+                }
+                hash
+            };
+            let origin = eval(&pieces[0].dst, &leaf);
+            let mut ranges = Vec::new();
+            let mut deltas = BTreeSet::new();
+            for piece in pieces {
+                let dst = eval(&piece.dst, &leaf);
+                let offset = sign_extend(mask(dst.wrapping_sub(origin), bits), bits);
+                let len = mask(eval(&piece.len, &leaf), bits);
+                if len == 0 || len > 1 << 20 || offset.unsigned_abs() > 1 << 20 {
+                    return None;
+                }
+                ranges.push((offset, offset + len as i64));
+                if let Source::Load(src) = &piece.source {
+                    deltas.insert(mask(eval(src, &leaf).wrapping_sub(dst), bits));
+                }
+            }
+            if deltas.len() > 1 {
+                return None;
+            }
+            let lowest = (0..ranges.len()).min_by_key(|i| ranges[*i].0)?;
+            let mut sorted = ranges.clone();
+            sorted.sort();
+            let mut covered = sorted[0].1;
+            for (start, end) in &sorted[1..] {
+                if *start > covered {
+                    return None;
+                }
+                covered = covered.max(*end);
+            }
+            let len = (covered - sorted[0].0) as u64;
+            match found {
+                None => found = Some((lowest, len)),
+                Some(previous) if previous == (lowest, len) => {}
+                Some(_) => return None,
+            }
+        }
+        let (lowest, len) = found?;
+        let piece = pieces[lowest];
+        Some((piece.dst.clone(), Value::Const(len), piece.source.clone()))
+    }
+
+    /// Returns the carry flag, as the condition it stands for, with the instructions it
+    /// is computed by.
+    fn carry(&self, state: &State, local: bool) -> Option<(Value, BTreeSet<usize>)> {
+        let flags = state.flags.as_ref()?;
+        Some(match local {
+            true => (
+                condition(&flags.local, ConditionCode::b)?,
+                flags.local_chain.clone(),
+            ),
+            false => (
+                condition(&flags.full, ConditionCode::b)?,
+                flags.chain.clone(),
+            ),
+        })
+    }
+
+    /// Decides the rewrites of the statements of the block starting at `start`.
+    fn rewrites(
+        &self,
+        cfg: &Cfg,
+        start: u64,
+        convention: CallingConvention,
+        handlers: &[u64],
+        statements: &[Expr],
+        symbols: &mut Vec<Symbol>,
+    ) -> Vec<(usize, Rewrite)> {
+        let mut rewrites = Vec::new();
+        let mut suppressed = BTreeSet::new();
+        let mut pinned = BTreeSet::new();
+        // the instructions rewritten in terms of what they read, no longer reading the
+        // values the others computed but through the leaves pinned
+        let mut rewritten = BTreeSet::new();
+        let live = |location: Location| is_live_out(cfg, start, location, convention, handlers);
+        let runs = self.runs();
+
+        // from the last instruction, so that the ones only computing parts of a value are
+        // dropped before they are looked at
+        for idx in (0..self.insts.len()).rev() {
+            if suppressed.contains(&idx) {
+                continue;
+            }
+            let mut candidates = Vec::new();
+            // the stores of the run are dropped whatever reads them, what computes their
+            // addresses and values is kept when still needed
+            if let Some((call, chain, forced)) = runs.get(&idx) {
+                // the vector registers loaded for the copy are no floating-point arguments of
+                // the calls after it
+                let copied = |other: usize| {
+                    self.readers[other].iter().all(|reader| {
+                        chain.contains(reader)
+                            || self.insts[*reader].flow_control() == iced_x86::FlowControl::Call
+                    }) && self.writes[other].iter().all(|location| {
+                        self.last_writes.get(location) != Some(&other) || !live(*location)
+                    })
+                };
+                if copied(idx) {
+                    let mut forced = forced.clone();
+                    forced.extend(chain.iter().copied().filter(|other| {
+                        let inst = &self.insts[*other];
+                        inst.op0_kind() == OpKind::Register
+                            && inst.op0_register().is_vector_register()
+                            && inst.op1_kind() == OpKind::Memory
+                            && copied(*other)
+                    }));
+                    candidates.push((vec![(None, call)], chain.clone(), forced, true));
+                }
+            }
+            for (cond, chain) in &self.conditions[idx] {
+                let mut chain = chain.clone();
+                chain.insert(idx);
+                candidates.push((vec![(None, cond)], chain, BTreeSet::new(), true));
+            }
+            if self.is_idiom(idx) {
+                let (values, chain) = self.candidate(&self.outputs[idx]);
+                candidates.push((values, chain, BTreeSet::new(), false));
+            }
+            // single instructions which were left unparsed, with the operands they read
+            let unparsed = matches!(
+                statements[idx].kind,
+                ExprKind::Unparsed(_) | ExprKind::Binary(..)
+            );
+            if !self.local[idx].is_empty() && (unparsed || self.is_multiplication(idx)) {
+                let (values, chain) = self.candidate(&self.local[idx]);
+                candidates.push((values, chain, BTreeSet::new(), false));
+            }
+            // a branch leaves what set the flags alone when it is still needed
+            for (values, chain, forced, keeps) in candidates {
+                let mut dropped = BTreeSet::new();
+                let mut valid = true;
+                for &other in chain.iter().filter(|other| **other != idx) {
+                    let mut keep = chain.clone();
+                    keep.extend(suppressed.iter().copied());
+                    keep.extend(rewritten.iter().copied());
+                    if forced.contains(&other) || self.is_dead(other, &keep, &pinned, &live) {
+                        dropped.insert(other);
+                    } else if !keeps
+                        && self.writes[other]
+                            .iter()
+                            .any(|location| *location != Location::Flags)
+                    {
+                        valid = false;
+                        break;
+                    }
+                }
+                let mut hidden = suppressed.clone();
+                hidden.extend(dropped.iter().copied());
+                if !valid
+                    || !values
+                        .iter()
+                        .all(|(_, value)| self.leaves_valid(value, idx, &hidden))
+                {
+                    continue;
+                }
+                let Some(stmt) = self.statement(&values, symbols) else {
+                    continue;
+                };
+                for (_, value) in &values {
+                    pin_leaves(value, &mut pinned);
+                }
+                rewrites.push((idx, stmt));
+                rewritten.insert(idx);
+                for other in dropped {
+                    rewrites.push((other, Rewrite::Noise));
+                    suppressed.insert(other);
+                }
+                break;
+            }
+        }
+        rewrites
+    }
+
+    /// Returns the values written by an instruction, with the instructions computing them.
+    #[allow(clippy::type_complexity)]
+    fn candidate<'b>(
+        &self,
+        outputs: &'b [Output],
+    ) -> (Vec<(Option<Register>, &'b Value)>, BTreeSet<usize>) {
+        let mut chain = BTreeSet::new();
+        for output in outputs {
+            chain.extend(output.chain.iter().copied());
+        }
+        let values = outputs
+            .iter()
+            .map(|output| (Some(output.reg), &output.value))
+            .collect();
+        (values, chain)
+    }
+
+    /// Returns `true` if instruction `idx` is a `lea` multiplying a register by a constant.
+    fn is_multiplication(&self, idx: usize) -> bool {
+        let inst = &self.insts[idx];
+        inst.mnemonic() == Mnemonic::Lea && inst.memory_base() == inst.memory_index()
+    }
+
+    /// Returns `true` if the value instruction `idx` computes is worth writing in terms of
+    /// the inputs of the block rather than instruction by instruction.
+    fn is_idiom(&self, idx: usize) -> bool {
+        // what string instructions leave in the registers is only read through others
+        if string_bits(&self.insts[idx]).is_some() {
+            return false;
+        }
+        self.outputs[idx].iter().any(|output| {
+            contains(&output.value, &|op| {
+                matches!(
+                    op,
+                    Op::Div { .. }
+                        | Op::Rem { .. }
+                        | Op::Abs
+                        | Op::Compare { .. }
+                        | Op::FloatCompare { .. }
+                        | Op::Builtin { .. }
+                )
+            }) || (output.chain.len() > 1 && contains(&output.value, &|op| op == Op::Mul))
+                || matches!(&output.value, Value::Op(op, bits, args) if is_sign_shift(*op, *bits, args))
+        }) || self.is_multiplication(idx)
+    }
+
+    /// Returns `true` if what instruction `idx` writes is only read by the instructions in
+    /// `readers` and is overwritten or dead at the end of the block.
+    fn is_dead(
+        &self,
+        idx: usize,
+        readers: &BTreeSet<usize>,
+        pinned: &BTreeSet<usize>,
+        live: &dyn Fn(Location) -> bool,
+    ) -> bool {
+        if pinned.contains(&idx) || self.writes_memory[idx] || !self.modeled[idx] {
+            return false;
+        }
+        self.readers[idx].is_subset(readers)
+            && self.writes[idx]
+                .iter()
+                .all(|location| self.last_writes.get(location) != Some(&idx) || !live(*location))
+    }
+
+    /// Returns `true` if the registers and memory `value` reads still hold the same at
+    /// instruction `idx`, once the instructions in `hidden` are dropped.
+    fn leaves_valid(&self, value: &Value, idx: usize, hidden: &BTreeSet<usize>) -> bool {
+        let written = |location: Location, from: usize| {
+            (from..idx)
+                .any(|other| !hidden.contains(&other) && self.writes[other].contains(&location))
+        };
+        match value {
+            Value::Const(_) => true,
+            Value::Reg(reg, def) => !written(Location::Reg(*reg), def.map_or(0, |def| def + 1)),
+            Value::Mem(at, _) => {
+                let inst = &self.insts[*at];
+                *at == idx
+                    || (!(*at..idx)
+                        .any(|other| !hidden.contains(&other) && self.writes_memory[other])
+                        && [inst.memory_base(), inst.memory_index()].iter().all(|reg| {
+                            *reg == Register::None
+                                || !reg.is_gpr()
+                                || !written(Location::Reg(reg.full_register()), *at + 1)
+                        }))
+            }
+            Value::Op(Op::Builtin { at, .. }, _, args) => {
+                !(*at..idx).any(|other| !hidden.contains(&other) && self.writes_memory[other])
+                    && args.iter().all(|arg| self.leaves_valid(arg, idx, hidden))
+            }
+            Value::Op(_, _, args) => args.iter().all(|arg| self.leaves_valid(arg, idx, hidden)),
+        }
+    }
+
+    /// Builds the statement of an instruction from the values it writes, or the condition
+    /// of a branch when the register is missing.
+    fn statement(
+        &self,
+        values: &[(Option<Register>, &Value)],
+        symbols: &mut Vec<Symbol>,
+    ) -> Option<Rewrite> {
+        if let [(None, call @ Value::Op(Op::Builtin { .. }, ..))] = values {
+            return Some(Rewrite::Statement(self.emit(call, self.bits, symbols)?));
+        }
+        if let [(None, cond)] = values {
+            return Some(Rewrite::Condition(self.emit(cond, 1, symbols)?));
+        }
+        // a conditional move left as one, when it is not part of a bigger value
+        if let [(Some(reg), Value::Op(Op::Select, _, args))] = values {
+            if matches!(&args[2], Value::Reg(full, _) if *full == reg.full_register()) {
+                let bits = reg.size() as u32 * 8;
+                let cond = self.emit(&args[0], 1, symbols)?;
+                let value = self.emit(&args[1], bits, symbols)?;
+                return Some(Rewrite::Statement(expr(ExprKind::If(
+                    Box::new(cond),
+                    vec![assign(register(symbols, *reg), value)],
+                ))));
+            }
+        }
+        let mut lhs = Vec::new();
+        let mut rhs = Vec::new();
+        for (reg, value) in values {
+            let mut reg = (*reg)?;
+            // a 64-bit register written with a 32-bit value, like `imul rax, rdx` on
+            // zero-extended operands
+            if reg.size() == 8 && width(value) <= 32 {
+                reg = sized(reg, 32)?;
+            }
+            rhs.push(self.emit(value, reg.size() as u32 * 8, symbols)?);
+            lhs.push(register(symbols, reg));
+        }
+        Some(Rewrite::Statement(match (lhs.len(), rhs.len()) {
+            (1, 1) => assign(lhs.pop().unwrap(), rhs.pop().unwrap()),
+            _ => assign(expr(ExprKind::Tup(lhs)), expr(ExprKind::Tup(rhs))),
+        }))
+    }
+
+    /// Lifts `value`, as read with `bits`.
+    fn emit(&self, value: &Value, bits: u32, symbols: &mut Vec<Symbol>) -> Option<Expr> {
+        match value {
+            Value::Const(value) => Some(literal(mask(*value, bits.max(8)), bits)),
+            Value::Reg(full, _) => Some(register(symbols, sized(*full, bits.max(8))?)),
+            Value::Mem(idx, op) => {
+                let operand = get_operand(symbols, self.insts[*idx], *op);
+                let size = self.insts[*idx].memory_size().size() as u32 * 8;
+                Some(match size > bits.max(8) {
+                    true => cast(operand, unsigned_ty(bits)),
+                    false => operand,
+                })
+            }
+            Value::Op(op, op_bits, args) => {
+                // the low bits of these only depend on the low bits of their operands
+                let op_bits = match op {
+                    Op::Add
+                    | Op::Sub
+                    | Op::Mul
+                    | Op::And
+                    | Op::Or
+                    | Op::Xor
+                    | Op::Shl
+                    | Op::Neg
+                    | Op::Not
+                        if bits > 1 =>
+                    {
+                        (*op_bits).min(bits)
+                    }
+                    _ => *op_bits,
+                };
+                let lifted = self.emit_op(*op, op_bits, args, symbols)?;
+                Some(match width(value).min(op_bits) > bits.max(8) {
+                    true => cast(lifted, unsigned_ty(bits)),
+                    false => lifted,
+                })
+            }
+        }
+    }
+
+    fn emit_op(
+        &self,
+        op: Op,
+        bits: u32,
+        args: &[Value],
+        symbols: &mut Vec<Symbol>,
+    ) -> Option<Expr> {
+        let binary = |kind: BinOpKind, symbols: &mut Vec<Symbol>, signed: bool| -> Option<Expr> {
+            let lhs = self.emit(&args[0], bits, symbols)?;
+            let rhs = self.emit(&args[1], bits, symbols)?;
+            Some(match signed {
+                true => get_binary(
+                    kind,
+                    as_signed(lhs, &args[0], bits),
+                    as_signed(rhs, &args[1], bits),
+                ),
+                false => get_binary(kind, lhs, rhs),
+            })
+        };
+        match op {
+            Op::Add => binary(BinOpKind::Add, symbols, false),
+            Op::Sub => binary(BinOpKind::Sub, symbols, false),
+            Op::Mul => binary(BinOpKind::Mul, symbols, false),
+            Op::And => binary(BinOpKind::And, symbols, false),
+            Op::Or => binary(BinOpKind::Or, symbols, false),
+            Op::Xor => binary(BinOpKind::BitXor, symbols, false),
+            Op::Shl => binary(BinOpKind::Shl, symbols, false),
+            // the sign bit shifted down, `x < 0` or `-(x < 0)`
+            Op::Shr | Op::Sar if is_sign_shift(op, bits, args) => {
+                let lhs = self.emit(&args[0], bits, symbols)?;
+                let zero = literal(0, bits);
+                let negative = get_binary(BinOpKind::Lt, as_signed(lhs, &args[0], bits), zero);
+                Some(match op {
+                    Op::Shr => negative,
+                    _ => expr(ExprKind::Unary(UnOp::Neg, Box::new(negative))),
+                })
+            }
+            Op::Shr => binary(BinOpKind::Shr, symbols, false),
+            Op::Sar => {
+                let lhs = self.emit(&args[0], bits, symbols)?;
+                let rhs = self.emit(&args[1], bits, symbols)?;
+                Some(get_binary(
+                    BinOpKind::Shr,
+                    as_signed(lhs, &args[0], bits),
+                    rhs,
+                ))
+            }
+            Op::Div { signed } => binary(BinOpKind::Div, symbols, signed),
+            Op::Rem { signed } => binary(BinOpKind::Rem, symbols, signed),
+            Op::Neg => Some(expr(ExprKind::Unary(
+                UnOp::Neg,
+                Box::new(self.emit(&args[0], bits, symbols)?),
+            ))),
+            Op::Not => Some(expr(ExprKind::Unary(
+                UnOp::Not,
+                Box::new(self.emit(&args[0], bits, symbols)?),
+            ))),
+            Op::Extend { from, signed: true } => {
+                let inner = self.emit(&args[0], from, symbols)?;
+                Some(cast(as_signed(inner, &args[0], from), signed_ty(bits)))
+            }
+            Op::Extend {
+                from,
+                signed: false,
+            } => self.emit(&args[0], from, symbols),
+            Op::Compare { cmp, bits } => {
+                let (kind, signed) = match cmp {
+                    Cmp::Eq => (BinOpKind::Eq, false),
+                    Cmp::Ne => (BinOpKind::Ne, false),
+                    Cmp::Lt { signed } => (BinOpKind::Lt, signed),
+                    Cmp::Le { signed } => (BinOpKind::Le, signed),
+                    Cmp::Gt { signed } => (BinOpKind::Gt, signed),
+                    Cmp::Ge { signed } => (BinOpKind::Ge, signed),
+                };
+                let lhs = self.emit(&args[0], bits, symbols)?;
+                let rhs = self.emit(&args[1], bits, symbols)?;
+                Some(match signed {
+                    true => get_binary(
+                        kind,
+                        as_signed(lhs, &args[0], bits),
+                        as_signed(rhs, &args[1], bits),
+                    ),
+                    false => get_binary(kind, lhs, rhs),
+                })
+            }
+            Op::FloatCompare {
+                test,
+                negated,
+                bits,
+            } => {
+                let mut operand = |value: &Value| match value {
+                    Value::Reg(full, _) => Some(register(symbols, xmm(*full)?)),
+                    _ => self.emit(value, bits, symbols),
+                };
+                let lhs = operand(&args[0])?;
+                let rhs = operand(&args[1])?;
+                let test = match test {
+                    FloatTest::Gt => get_binary(BinOpKind::Gt, lhs, rhs),
+                    FloatTest::Ge => get_binary(BinOpKind::Ge, lhs, rhs),
+                    FloatTest::LessGreater => expr(ExprKind::Intrinsic(
+                        "islessgreater".to_string(),
+                        vec![lhs, rhs],
+                    )),
+                    FloatTest::Unordered => expr(ExprKind::Intrinsic(
+                        "isunordered".to_string(),
+                        vec![lhs, rhs],
+                    )),
+                };
+                Some(match negated {
+                    true => get_not(test),
+                    false => test,
+                })
+            }
+            Op::Abs => {
+                let inner = self.emit(&args[0], bits, symbols)?;
+                let name = if bits == 64 { "labs" } else { "abs" };
+                Some(expr(ExprKind::Intrinsic(
+                    name.to_string(),
+                    vec![as_signed(inner, &args[0], bits)],
+                )))
+            }
+            Op::Builtin { builtin, .. } => {
+                let widths: &[u32] = match builtin {
+                    Builtin::Memset => &[self.bits, 8, self.bits],
+                    _ => &[self.bits; 3],
+                };
+                let args = args
+                    .iter()
+                    .zip(widths)
+                    .map(|(arg, bits)| self.emit(arg, *bits, symbols))
+                    .collect::<Option<Vec<_>>>()?;
+                Some(expr(ExprKind::Intrinsic(builtin.name().to_string(), args)))
+            }
+            Op::MulHigh { .. } | Op::Merge { .. } | Op::Select => None,
+        }
+    }
+}
+
+/// The general-purpose registers, by their full names.
+const GPRS: [Register; 16] = [
+    Register::RAX,
+    Register::RCX,
+    Register::RDX,
+    Register::RBX,
+    Register::RSP,
+    Register::RBP,
+    Register::RSI,
+    Register::RDI,
+    Register::R8,
+    Register::R9,
+    Register::R10,
+    Register::R11,
+    Register::R12,
+    Register::R13,
+    Register::R14,
+    Register::R15,
+];
+
+/// The SSE and AVX moves of whole registers.
+const VECTOR_MOVES: [Mnemonic; 14] = [
+    Mnemonic::Movups,
+    Mnemonic::Movaps,
+    Mnemonic::Movupd,
+    Mnemonic::Movapd,
+    Mnemonic::Movdqu,
+    Mnemonic::Movdqa,
+    Mnemonic::Vmovups,
+    Mnemonic::Vmovaps,
+    Mnemonic::Vmovdqu,
+    Mnemonic::Vmovdqa,
+    Mnemonic::Vmovdqu8,
+    Mnemonic::Vmovdqu64,
+    Mnemonic::Vmovdqa32,
+    Mnemonic::Vmovdqa64,
+];
+
+/// The SSE and AVX stores of the low bytes of a register.
+const VECTOR_LOW_STORES: [Mnemonic; 10] = [
+    Mnemonic::Movq,
+    Mnemonic::Vmovq,
+    Mnemonic::Movd,
+    Mnemonic::Vmovd,
+    Mnemonic::Movlps,
+    Mnemonic::Vmovlps,
+    Mnemonic::Movlpd,
+    Mnemonic::Vmovlpd,
+    Mnemonic::Movss,
+    Mnemonic::Vmovss,
+];
+
+/// Records the value instruction `idx` writes to `reg`, merged into the rest of the
+/// register, and returns what the instruction is then found to compute.
+fn write(
+    state: &mut State,
+    idx: usize,
+    reg: Register,
+    value: Value,
+    chain: BTreeSet<usize>,
+) -> Output {
+    let full = reg.full_register();
+    let bits = reg.size() as u32 * 8;
+    let (mut value, mut chain, mut reg) = match bits {
+        // 32-bit writes clear the upper half
+        32 | 64 => (
+            op_value(
+                Op::Extend {
+                    from: bits,
+                    signed: false,
+                },
+                64,
+                vec![value],
+            ),
+            chain,
+            reg,
+        ),
+        _ => {
+            let old = state.read(full);
+            match old.value {
+                Value::Const(0) => {
+                    let mut chain = chain;
+                    chain.extend(old.chain);
+                    let value = op_value(
+                        Op::Extend {
+                            from: bits,
+                            signed: false,
+                        },
+                        64,
+                        vec![value],
+                    );
+                    (value, chain, sized(full, 32).unwrap_or(reg))
+                }
+                old_value => {
+                    let mut merged = chain.clone();
+                    merged.extend(old.chain);
+                    let value = op_value(Op::Merge { bits }, 64, vec![old_value, value]);
+                    (value, merged, reg)
+                }
+            }
+        }
+    };
+    if size(&value) > MAX_NODES {
+        value = Value::Reg(full, Some(idx));
+        chain = BTreeSet::new();
+        reg = sized(full, bits).unwrap_or(reg);
+    }
+    state.regs.insert(
+        full,
+        Def {
+            value: value.clone(),
+            chain: chain.clone(),
+            def: Some(idx),
+        },
+    );
+    Output { reg, value, chain }
+}
+
+/// Returns operand `op` of `inst` as the flags are set from it: as it is read, as its value
+/// and the instructions computing it.
+fn flag_operand(
+    state: &State,
+    inst: &Instruction,
+    idx: usize,
+    op: u32,
+) -> Option<(Value, Value, BTreeSet<usize>)> {
+    if op >= inst.op_count() {
+        return None;
+    }
+    match inst.op_kind(op) {
+        OpKind::Register => {
+            let reg = inst.op_register(op);
+            if !reg.is_gpr() || is_high_byte(reg) {
+                return None;
+            }
+            let def = state.read(reg.full_register());
+            Some((state.leaf(reg.full_register()), def.value, def.chain))
+        }
+        OpKind::Memory => Some((Value::Mem(idx, op), Value::Mem(idx, op), BTreeSet::new())),
+        OpKind::Immediate8
+        | OpKind::Immediate16
+        | OpKind::Immediate32
+        | OpKind::Immediate64
+        | OpKind::Immediate8to16
+        | OpKind::Immediate8to32
+        | OpKind::Immediate8to64
+        | OpKind::Immediate32to64 => Some((
+            Value::Const(inst.immediate(op)),
+            Value::Const(inst.immediate(op)),
+            BTreeSet::new(),
+        )),
+        _ => None,
+    }
+}
+
+/// Returns what the flags are set from by `inst`, given its operands and the register it
+/// writes.
+fn flags(inst: &Instruction, operands: [Option<Value>; 2], result: Option<Value>) -> Option<Flags> {
+    let bits = match inst.op0_kind() {
+        OpKind::Register => inst.op0_register().size() as u32 * 8,
+        _ => inst.memory_size().size() as u32 * 8,
+    };
+    let same_registers = inst.op_count() == 2
+        && inst.op0_kind() == OpKind::Register
+        && inst.op1_kind() == OpKind::Register
+        && inst.op0_register() == inst.op1_register();
+    let [a, b] = operands;
+    match inst.mnemonic() {
+        Mnemonic::Cmp | Mnemonic::Sub if !same_registers => Some(Flags::Compare(a?, b?, bits)),
+        Mnemonic::Neg => Some(Flags::Compare(Value::Const(0), a?, bits)),
+        Mnemonic::Test if same_registers => Some(Flags::Logic(a?, bits)),
+        Mnemonic::Test => Some(Flags::Logic(op_value(Op::And, bits, vec![a?, b?]), bits)),
+        Mnemonic::Xor | Mnemonic::Sub if same_registers => {
+            Some(Flags::Logic(Value::Const(0), bits))
+        }
+        Mnemonic::And | Mnemonic::Or | Mnemonic::Xor => Some(Flags::Logic(result?, bits)),
+        Mnemonic::Add | Mnemonic::Inc | Mnemonic::Dec => Some(Flags::Result(result?, bits)),
+        // a shift by a count other than 0 sets the zero and sign flags from its result, by
+        // 0 it leaves them alone
+        Mnemonic::Shl | Mnemonic::Sal | Mnemonic::Shr | Mnemonic::Sar
+            if matches!(b, Some(Value::Const(count)) if count & u64::from(bits.max(32) - 1) != 0) =>
+        {
+            Some(Flags::Result(result?, bits))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the flags `ucomisd` and friends set, from the registers or memory they compare.
+fn float_flags(state: &State, inst: &Instruction, idx: usize) -> Option<Flags> {
+    let bits = match inst.mnemonic() {
+        Mnemonic::Ucomiss | Mnemonic::Comiss | Mnemonic::Vucomiss | Mnemonic::Vcomiss => 32,
+        Mnemonic::Ucomisd | Mnemonic::Comisd | Mnemonic::Vucomisd | Mnemonic::Vcomisd => 64,
+        _ => return None,
+    };
+    let operand = |op: u32| match inst.op_kind(op) {
+        OpKind::Register => {
+            let full = inst.op_register(op).full_register();
+            Value::Reg(full, state.vector_defs.get(&full).copied())
+        }
+        _ => Value::Mem(idx, op),
+    };
+    Some(Flags::Float(operand(0), operand(1), bits))
+}
+
+/// Expresses condition code `cc` in terms of what the flags were set from.
+fn condition(flags: &Flags, cc: ConditionCode) -> Option<Value> {
+    let compare = |cmp, a: &Value, b: &Value, bits| {
+        op_value(Op::Compare { cmp, bits }, 1, vec![a.clone(), b.clone()])
+    };
+    let zero = Value::Const(0);
+    Some(match flags {
+        Flags::Compare(a, b, bits) => match cc {
+            ConditionCode::e => compare(Cmp::Eq, a, b, *bits),
+            ConditionCode::ne => compare(Cmp::Ne, a, b, *bits),
+            ConditionCode::b => compare(Cmp::Lt { signed: false }, a, b, *bits),
+            ConditionCode::ae => compare(Cmp::Ge { signed: false }, a, b, *bits),
+            ConditionCode::a => compare(Cmp::Gt { signed: false }, a, b, *bits),
+            ConditionCode::be => compare(Cmp::Le { signed: false }, a, b, *bits),
+            ConditionCode::l => compare(Cmp::Lt { signed: true }, a, b, *bits),
+            ConditionCode::ge => compare(Cmp::Ge { signed: true }, a, b, *bits),
+            ConditionCode::g => compare(Cmp::Gt { signed: true }, a, b, *bits),
+            ConditionCode::le => compare(Cmp::Le { signed: true }, a, b, *bits),
+            ConditionCode::s | ConditionCode::ns => {
+                let difference = op_value(Op::Sub, *bits, vec![a.clone(), b.clone()]);
+                let cmp = match cc {
+                    ConditionCode::s => Cmp::Lt { signed: true },
+                    _ => Cmp::Ge { signed: true },
+                };
+                compare(cmp, &difference, &zero, *bits)
+            }
+            _ => return None,
+        },
+        Flags::Logic(value, bits) => match cc {
+            ConditionCode::e | ConditionCode::be => compare(Cmp::Eq, value, &zero, *bits),
+            ConditionCode::ne | ConditionCode::a => compare(Cmp::Ne, value, &zero, *bits),
+            ConditionCode::s | ConditionCode::l => {
+                compare(Cmp::Lt { signed: true }, value, &zero, *bits)
+            }
+            ConditionCode::ns | ConditionCode::ge => {
+                compare(Cmp::Ge { signed: true }, value, &zero, *bits)
+            }
+            ConditionCode::le => compare(Cmp::Le { signed: true }, value, &zero, *bits),
+            ConditionCode::g => compare(Cmp::Gt { signed: true }, value, &zero, *bits),
+            ConditionCode::b => Value::Const(0),
+            ConditionCode::ae => Value::Const(1),
+            _ => return None,
+        },
+        Flags::Result(value, bits) => match cc {
+            ConditionCode::e => compare(Cmp::Eq, value, &zero, *bits),
+            ConditionCode::ne => compare(Cmp::Ne, value, &zero, *bits),
+            ConditionCode::s => compare(Cmp::Lt { signed: true }, value, &zero, *bits),
+            ConditionCode::ns => compare(Cmp::Ge { signed: true }, value, &zero, *bits),
+            _ => return None,
+        },
+        Flags::Float(a, b, bits) => {
+            let (test, negated) = match cc {
+                ConditionCode::a => (FloatTest::Gt, false),
+                ConditionCode::ae => (FloatTest::Ge, false),
+                ConditionCode::b => (FloatTest::Ge, true),
+                ConditionCode::be => (FloatTest::Gt, true),
+                ConditionCode::ne => (FloatTest::LessGreater, false),
+                ConditionCode::e => (FloatTest::LessGreater, true),
+                ConditionCode::p => (FloatTest::Unordered, false),
+                ConditionCode::np => (FloatTest::Unordered, true),
+                _ => return None,
+            };
+            let op = Op::FloatCompare {
+                test,
+                negated,
+                bits: *bits,
+            };
+            op_value(op, 1, vec![a.clone(), b.clone()])
+        }
+        Flags::Sign(value) => match cc {
+            ConditionCode::e => compare(Cmp::Eq, value, &zero, 32),
+            ConditionCode::ne => compare(Cmp::Ne, value, &zero, 32),
+            ConditionCode::b => compare(Cmp::Lt { signed: true }, value, &zero, 32),
+            ConditionCode::ae => compare(Cmp::Ge { signed: true }, value, &zero, 32),
+            ConditionCode::a => compare(Cmp::Gt { signed: true }, value, &zero, 32),
+            ConditionCode::be => compare(Cmp::Le { signed: true }, value, &zero, 32),
+            _ => return None,
+        },
+    })
+}
+
+/// Returns `true` if `location` may be read after the end of the block starting at
+/// `start`, before it is written. The blocks calling one of the failure `handlers` read
+/// nothing, as they are hidden with the check leading there.
+fn is_live_out(
+    cfg: &Cfg,
+    start: u64,
+    location: Location,
+    convention: CallingConvention,
+    handlers: &[u64],
+) -> bool {
+    let mut factory = InstructionInfoFactory::new();
+    let mut visited = BTreeSet::new();
+    // the block itself is only scanned when it is reached again
+    let mut pending = vec![(start, false)];
+    while let Some((addr, scan)) = pending.pop() {
+        let block = &cfg.blocks[&addr];
+        let fails = block.instructions.last().is_some_and(|last| {
+            matches!(last.flow(), Flow::Call | Flow::IndirectCall)
+                && last
+                    .branch_target()
+                    .or(last.slot())
+                    .is_some_and(|target| handlers.contains(&target))
+        });
+        if fails {
+            continue;
+        }
+        if scan {
+            let mut killed = false;
+            for inst in block.instructions.iter() {
+                let Some(inst) = inst.as_x86() else {
+                    return true;
+                };
+                match access(&mut factory, inst, location, convention) {
+                    Some(true) => return true,
+                    Some(false) => {
+                        killed = true;
+                        break;
+                    }
+                    None => {}
+                }
+            }
+            if killed {
+                continue;
+            }
+        }
+        let mut successors = cfg.edges.iter().filter(|edge| edge.from == addr).peekable();
+        if successors.peek().is_none() {
+            let last = block.instructions.last();
+            match last.map(arch::Instruction::flow) {
+                Some(Flow::Return) => {
+                    if let Location::Reg(reg) = location {
+                        let mut kept = convention
+                            .preserved_registers()
+                            .iter()
+                            .chain(convention.return_registers());
+                        if kept.any(|kept| kept.full_register() == reg) {
+                            return true;
+                        }
+                    }
+                }
+                // calls to functions which do not return
+                Some(Flow::Call) | Some(Flow::IndirectCall) | Some(Flow::Stop) => {}
+                // a tail call reads the arguments of its callee, never the flags, and neither
+                // does a jump through a register nothing is known of
+                Some(Flow::Branch)
+                    if location == Location::Flags
+                        && last.is_some_and(|last| cfg.tail_calls.contains(&last.ip())) => {}
+                Some(Flow::IndirectBranch) if location == Location::Flags => {}
+                _ => return true,
+            }
+        }
+        for edge in successors {
+            if !cfg.blocks.contains_key(&edge.to) {
+                return true;
+            }
+            if visited.insert(edge.to) {
+                pending.push((edge.to, true));
+            }
+        }
+    }
+    false
+}
+
+/// Returns `Some(true)` if `inst` reads `location`, `Some(false)` if it overwrites it
+/// without reading it and `None` if it does neither.
+fn access(
+    factory: &mut InstructionInfoFactory,
+    inst: &Instruction,
+    location: Location,
+    convention: CallingConvention,
+) -> Option<bool> {
+    let is_call = inst.flow_control() == iced_x86::FlowControl::Call
+        || inst.flow_control() == iced_x86::FlowControl::IndirectCall;
+    match location {
+        // the flags `int` pushes are not what a BIOS or system call reads its arguments from
+        Location::Flags if inst.flow_control() == iced_x86::FlowControl::Interrupt => Some(false),
+        Location::Flags => {
+            if inst.rflags_read() != 0 {
+                Some(true)
+            } else if is_call || inst.rflags_modified() & STATUS_FLAGS == STATUS_FLAGS {
+                Some(false)
+            } else {
+                None
+            }
+        }
+        Location::Reg(reg) => {
+            let info = factory.info(inst);
+            let mut written = false;
+            for used in info.used_registers() {
+                if used.register().full_register() != reg {
+                    continue;
+                }
+                match used.access() {
+                    OpAccess::Write if used.register().size() >= 4 => written = true,
+                    OpAccess::None | OpAccess::NoMemAccess => {}
+                    // partial and conditional writes keep what the rest of the register held
+                    _ => return Some(true),
+                }
+            }
+            if is_call {
+                // floating-point arguments
+                if reg.is_vector_register()
+                    || convention
+                        .call_registers()
+                        .iter()
+                        .any(|arg| arg.full_register() == reg)
+                {
+                    return Some(true);
+                }
+                let preserved = convention
+                    .preserved_registers()
+                    .iter()
+                    .any(|preserved| preserved.full_register() == reg);
+                return (!preserved).then_some(false);
+            }
+            written.then_some(false)
+        }
+    }
+}
+
+fn condition_code(inst: &Instruction) -> Option<ConditionCode> {
+    match inst.condition_code() {
+        ConditionCode::None => None,
+        cc => Some(cc),
+    }
+}
+
+fn is_setcc(inst: &Instruction) -> bool {
+    format!("{:?}", inst.mnemonic()).starts_with("Set")
+        && inst.condition_code() != ConditionCode::None
+}
+
+fn is_cmovcc(inst: &Instruction) -> bool {
+    format!("{:?}", inst.mnemonic()).starts_with("Cmov")
+        && inst.condition_code() != ConditionCode::None
+}
+
+/// Returns the address size of a string instruction, like `movsb` and `scasb`.
+fn string_bits(inst: &Instruction) -> Option<u32> {
+    (0..inst.op_count()).find_map(|op| match inst.op_kind(op) {
+        OpKind::MemoryESRDI | OpKind::MemorySegRSI => Some(64),
+        OpKind::MemoryESEDI | OpKind::MemorySegESI => Some(32),
+        OpKind::MemoryESDI | OpKind::MemorySegSI => Some(16),
+        _ => None,
+    })
+}
+
+/// Returns the byte `bytes` repeat.
+fn repeated(bytes: &[u8]) -> Option<u64> {
+    let (first, rest) = bytes.split_first()?;
+    rest.iter()
+        .all(|byte| byte == first)
+        .then_some(u64::from(*first))
+}
+
+fn same_source(a: &Source, b: &Source) -> bool {
+    match (a, b) {
+        (Source::Load(_), Source::Load(_)) => true,
+        (Source::Fill(a), Source::Fill(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn is_high_byte(reg: Register) -> bool {
+    matches!(
+        reg,
+        Register::AH | Register::BH | Register::CH | Register::DH
+    )
+}
+
+/// Returns the register of `bits` within the general-purpose register `full`.
+/// Returns the SSE register of an AVX-512 one, given by its full name.
+fn xmm(full: Register) -> Option<Register> {
+    Register::values().find(|reg| reg.is_xmm() && reg.full_register() == full)
+}
+
+fn sized(full: Register, bits: u32) -> Option<Register> {
+    Register::values().find(|reg| {
+        reg.is_gpr()
+            && !is_high_byte(*reg)
+            && reg.full_register() == full.full_register()
+            && reg.size() as u32 * 8 == bits
+    })
+}
+
+fn pin_leaves(value: &Value, pinned: &mut BTreeSet<usize>) {
+    match value {
+        Value::Reg(_, Some(def)) => {
+            pinned.insert(*def);
+        }
+        Value::Op(_, _, args) => args.iter().for_each(|arg| pin_leaves(arg, pinned)),
+        _ => {}
+    }
+}
+
+/// Returns `true` if `op` on `args` of `bits` shifts the sign bit of the first down to bit 0.
+fn is_sign_shift(op: Op, bits: u32, args: &[Value]) -> bool {
+    matches!(op, Op::Shr | Op::Sar)
+        && matches!(args, [_, Value::Const(count)] if *count == u64::from(bits) - 1)
+}
+
+fn contains(value: &Value, pred: &dyn Fn(Op) -> bool) -> bool {
+    match value {
+        Value::Op(op, _, args) => pred(*op) || args.iter().any(|arg| contains(arg, pred)),
+        _ => false,
+    }
+}
+
+fn size(value: &Value) -> usize {
+    match value {
+        Value::Op(_, _, args) => 1 + args.iter().map(size).sum::<usize>(),
+        _ => 1,
+    }
+}
+
+/// Returns the number of bits of `value`.
+fn width(value: &Value) -> u32 {
+    match value {
+        Value::Op(Op::Compare { .. } | Op::FloatCompare { .. }, ..) => 1,
+        Value::Op(
+            Op::Extend {
+                from,
+                signed: false,
+            },
+            ..,
+        ) => *from,
+        Value::Op(_, bits, _) => *bits,
+        _ => 64,
+    }
+}
+
+/// Evaluates `value`, with `leaf` giving the registers and memory it reads.
+fn eval(value: &Value, leaf: &dyn Fn(&Value) -> u64) -> u64 {
+    let Value::Op(op, bits, args) = value else {
+        return match value {
+            Value::Const(value) => *value,
+            _ => leaf(value),
+        };
+    };
+    let bits = *bits;
+    let arg = |i: usize| eval(&args[i], leaf);
+    let signed = |i: usize| sign_extend(mask(arg(i), bits), bits);
+    let shift = |i: usize| (arg(i) & if bits == 64 { 63 } else { 31 }) as u32;
+    let result = match op {
+        Op::Add => arg(0).wrapping_add(arg(1)),
+        Op::Sub => arg(0).wrapping_sub(arg(1)),
+        Op::Mul => arg(0).wrapping_mul(arg(1)),
+        Op::And => arg(0) & arg(1),
+        Op::Or => arg(0) | arg(1),
+        Op::Xor => arg(0) ^ arg(1),
+        Op::Shl => mask(arg(0), bits).checked_shl(shift(1)).unwrap_or(0),
+        Op::Shr => mask(arg(0), bits).checked_shr(shift(1)).unwrap_or(0),
+        Op::Sar => (signed(0) >> shift(1).min(63)) as u64,
+        Op::Neg => arg(0).wrapping_neg(),
+        Op::Not => !arg(0),
+        Op::MulHigh { signed: true } => {
+            ((i128::from(signed(0)) * i128::from(signed(1))) >> bits) as u64
+        }
+        Op::MulHigh { signed: false } => {
+            ((u128::from(mask(arg(0), bits)) * u128::from(mask(arg(1), bits))) >> bits) as u64
+        }
+        Op::Div { signed: true } | Op::Rem { signed: true } => {
+            let (a, b) = (signed(0), signed(1));
+            match (b, op) {
+                (0, _) => 0,
+                (_, Op::Div { .. }) => a.wrapping_div(b) as u64,
+                _ => a.wrapping_rem(b) as u64,
+            }
+        }
+        Op::Div { signed: false } | Op::Rem { signed: false } => {
+            let (a, b) = (mask(arg(0), bits), mask(arg(1), bits));
+            match (b, op) {
+                (0, _) => 0,
+                (_, Op::Div { .. }) => a / b,
+                _ => a % b,
+            }
+        }
+        Op::Extend { from, signed: true } => sign_extend(mask(arg(0), *from), *from) as u64,
+        Op::Extend {
+            from,
+            signed: false,
+        } => mask(arg(0), *from),
+        Op::Merge { bits: low } => (arg(0) & !mask(u64::MAX, *low)) | mask(arg(1), *low),
+        Op::Compare { cmp, bits } => {
+            let (a, b) = (mask(arg(0), *bits), mask(arg(1), *bits));
+            let (sa, sb) = (sign_extend(a, *bits), sign_extend(b, *bits));
+            let holds = match cmp {
+                Cmp::Eq => a == b,
+                Cmp::Ne => a != b,
+                Cmp::Lt { signed: true } => sa < sb,
+                Cmp::Le { signed: true } => sa <= sb,
+                Cmp::Gt { signed: true } => sa > sb,
+                Cmp::Ge { signed: true } => sa >= sb,
+                Cmp::Lt { signed: false } => a < b,
+                Cmp::Le { signed: false } => a <= b,
+                Cmp::Gt { signed: false } => a > b,
+                Cmp::Ge { signed: false } => a >= b,
+            };
+            return u64::from(holds);
+        }
+        Op::Select => match arg(0) {
+            0 => arg(2),
+            _ => arg(1),
+        },
+        Op::Abs => signed(0).unsigned_abs(),
+        Op::Builtin { .. } | Op::FloatCompare { .. } => leaf(value),
+    };
+    mask(result, bits)
+}
+
+/// Returns `true` if `a` and `b` agree on `bits` for any input, once what does not change
+/// those bits is dropped from both.
+fn equivalent(a: &Value, b: &Value, bits: u32) -> bool {
+    a == b || narrow(a.clone(), bits) == narrow(b.clone(), bits)
+}
+
+fn random(seed: u64) -> u64 {
+    seed.wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407)
+}
+
+/// Builds and simplifies an operation.
+fn op_value(op: Op, bits: u32, args: Vec<Value>) -> Value {
+    simplify(Value::Op(op, bits, args))
+}
+
+fn simplify(value: Value) -> Value {
+    let Value::Op(op, bits, mut args) = value else {
+        return value;
+    };
+    // extensions and wider arithmetic read with fewer bits change nothing
+    for arg in args.iter_mut() {
+        let operand_bits = match op {
+            Op::Extend { from, .. } => from,
+            Op::Compare { bits, .. } => bits,
+            Op::Merge { .. } | Op::Select | Op::Builtin { .. } | Op::FloatCompare { .. } => {
+                continue
+            }
+            _ => bits,
+        };
+        *arg = narrow(std::mem::replace(arg, Value::Const(0)), operand_bits);
+    }
+    if !matches!(op, Op::Builtin { .. } | Op::FloatCompare { .. })
+        && args.iter().all(|arg| matches!(arg, Value::Const(_)))
+    {
+        return Value::Const(eval(&Value::Op(op, bits, args), &|_| 0));
+    }
+    match (op, args.as_slice()) {
+        (
+            Op::Extend {
+                from,
+                signed: false,
+            },
+            [arg],
+        ) if width(arg) <= from => return args.pop().unwrap(),
+        (Op::Merge { bits: low }, [Value::Const(0), _]) => {
+            return op_value(
+                Op::Extend {
+                    from: low,
+                    signed: false,
+                },
+                bits,
+                vec![args.pop().unwrap()],
+            )
+        }
+        (Op::Xor | Op::Sub, [a, b]) if a == b => return Value::Const(0),
+        (Op::Or, [_, Value::Const(c)]) | (Op::And, [_, Value::Const(c)])
+            if mask(*c, bits) == mask(u64::MAX, bits) || *c == 0 =>
+        {
+            let absorbs = (op == Op::Or) == (*c != 0);
+            return match absorbs {
+                true => Value::Const(mask(*c, bits)),
+                false => op_value(
+                    Op::Extend {
+                        from: bits,
+                        signed: false,
+                    },
+                    bits,
+                    vec![args.swap_remove(0)],
+                ),
+            };
+        }
+        (Op::Xor, [_, Value::Const(0)]) => {
+            return op_value(
+                Op::Extend {
+                    from: bits,
+                    signed: false,
+                },
+                bits,
+                vec![args.swap_remove(0)],
+            )
+        }
+        // -(c) & k, with c 0 or 1
+        (Op::And, [Value::Op(Op::Neg, _, inner), Value::Const(k)])
+            if matches!(inner[0], Value::Op(Op::Compare { .. }, ..)) =>
+        {
+            return op_value(Op::Mul, bits, vec![inner[0].clone(), Value::Const(*k)])
+        }
+        _ => {}
+    }
+    let value = match op {
+        Op::Add | Op::Sub | Op::Mul | Op::Shl | Op::Neg | Op::Not => {
+            linear(Value::Op(op, bits, args))
+        }
+        _ => Value::Op(op, bits, args),
+    };
+    recognize(&value).unwrap_or(value)
+}
+
+/// Drops what does not change the low `bits` of `value`.
+fn narrow(value: Value, bits: u32) -> Value {
+    match value {
+        Value::Op(Op::Extend { from, .. }, _, mut args) if from >= bits => {
+            narrow(args.pop().unwrap(), bits)
+        }
+        Value::Op(Op::Merge { bits: low }, _, mut args) if low >= bits => {
+            narrow(args.pop().unwrap(), bits)
+        }
+        Value::Op(
+            op @ (Op::Add
+            | Op::Sub
+            | Op::Mul
+            | Op::And
+            | Op::Or
+            | Op::Xor
+            | Op::Shl
+            | Op::Neg
+            | Op::Not),
+            width,
+            args,
+        ) if width > bits => simplify(Value::Op(op, bits, args)),
+        value => value,
+    }
+}
+
+/// Rewrites a sum of multiples of some terms and a constant in its simplest form, and
+/// `x - x / d * d` as `x % d`.
+fn linear(value: Value) -> Value {
+    let bits = width(&value);
+    let mut terms = Vec::<(Value, u64)>::new();
+    let mut constant = 0_u64;
+    collect(&value, 1, bits, &mut terms, &mut constant);
+    terms.retain(|(_, coefficient)| mask(*coefficient, bits) != 0);
+
+    // x % d
+    let mut i = 0;
+    while i < terms.len() {
+        let (term, coefficient) = &terms[i];
+        if let Value::Op(Op::Div { signed }, div_bits, args) = term {
+            if let [dividend, Value::Const(d)] = args.as_slice() {
+                if *div_bits == bits && mask(coefficient.wrapping_add(*d), bits) == 0 {
+                    let dividend = dividend.clone();
+                    if let Some(j) = terms.iter().position(|(other, coefficient)| {
+                        *other == dividend && mask(*coefficient, bits) == 1
+                    }) {
+                        let rem = Value::Op(
+                            Op::Rem { signed: *signed },
+                            bits,
+                            vec![dividend, Value::Const(*d)],
+                        );
+                        terms[j] = (rem, 1);
+                        terms.remove(i);
+                        i = 0;
+                        continue;
+                    }
+                }
+            }
+        }
+        i += 1;
+    }
+
+    let signed = |value: u64| sign_extend(mask(value, bits), bits);
+    let term_value = |term: Value, magnitude: u64| match magnitude {
+        1 => term,
+        m if m.is_power_of_two()
+            && terms.len() == 1
+            && mask(constant, bits) == 0
+            && matches!(value, Value::Op(Op::Shl, ..)) =>
+        {
+            Value::Op(
+                Op::Shl,
+                bits,
+                vec![term, Value::Const(u64::from(m.trailing_zeros()))],
+            )
+        }
+        m => Value::Op(Op::Mul, bits, vec![term, Value::Const(m)]),
+    };
+    let first = terms
+        .iter()
+        .position(|(_, coefficient)| signed(*coefficient) > 0);
+    let mut result: Option<Value> = None;
+    let mut order = Vec::new();
+    if let Some(first) = first {
+        order.push(first);
+    }
+    order.extend((0..terms.len()).filter(|i| Some(*i) != first));
+    for i in order {
+        let (term, coefficient) = terms[i].clone();
+        let negative = signed(coefficient) < 0;
+        let magnitude = mask(
+            if negative {
+                coefficient.wrapping_neg()
+            } else {
+                coefficient
+            },
+            bits,
+        );
+        let term = term_value(term, magnitude);
+        result = Some(match (result, negative) {
+            (None, false) => term,
+            (None, true) => Value::Op(Op::Neg, bits, vec![term]),
+            (Some(acc), false) => Value::Op(Op::Add, bits, vec![acc, term]),
+            (Some(acc), true) => Value::Op(Op::Sub, bits, vec![acc, term]),
+        });
+    }
+    let constant = mask(constant, bits);
+    match result {
+        None => Value::Const(constant),
+        Some(Value::Op(Op::Neg, _, mut args)) if constant == mask(u64::MAX, bits) => {
+            Value::Op(Op::Not, bits, vec![args.pop().unwrap()])
+        }
+        Some(acc) if constant == 0 => acc,
+        Some(acc) if signed(constant) < 0 && bits > 1 => Value::Op(
+            Op::Sub,
+            bits,
+            vec![acc, Value::Const(mask(constant.wrapping_neg(), bits))],
+        ),
+        Some(acc) => Value::Op(Op::Add, bits, vec![acc, Value::Const(constant)]),
+    }
+}
+
+fn collect(
+    value: &Value,
+    coefficient: u64,
+    bits: u32,
+    terms: &mut Vec<(Value, u64)>,
+    constant: &mut u64,
+) {
+    match value {
+        Value::Const(c) => *constant = constant.wrapping_add(coefficient.wrapping_mul(*c)),
+        Value::Op(Op::Add, width, args) if *width >= bits => {
+            collect(&args[0], coefficient, bits, terms, constant);
+            collect(&args[1], coefficient, bits, terms, constant);
+        }
+        Value::Op(Op::Sub, width, args) if *width >= bits => {
+            collect(&args[0], coefficient, bits, terms, constant);
+            collect(&args[1], coefficient.wrapping_neg(), bits, terms, constant);
+        }
+        Value::Op(Op::Neg, width, args) if *width >= bits => {
+            collect(&args[0], coefficient.wrapping_neg(), bits, terms, constant)
+        }
+        // -x - 1
+        Value::Op(Op::Not, width, args) if *width >= bits => {
+            collect(&args[0], coefficient.wrapping_neg(), bits, terms, constant);
+            *constant = constant.wrapping_sub(coefficient);
+        }
+        Value::Op(Op::Mul, width, args) if *width >= bits => match args.as_slice() {
+            [a, Value::Const(c)] | [Value::Const(c), a] => {
+                collect(a, coefficient.wrapping_mul(*c), bits, terms, constant)
+            }
+            _ => add_term(value, coefficient, terms),
+        },
+        Value::Op(Op::Shl, width, args) if *width >= bits => match args.as_slice() {
+            [a, Value::Const(c)] if *c < u64::from(bits) => {
+                collect(a, coefficient.wrapping_mul(1 << c), bits, terms, constant)
+            }
+            _ => add_term(value, coefficient, terms),
+        },
+        Value::Op(Op::Extend { from, .. }, _, args) if *from >= bits => {
+            collect(&args[0], coefficient, bits, terms, constant)
+        }
+        _ => add_term(value, coefficient, terms),
+    }
+}
+
+fn add_term(value: &Value, coefficient: u64, terms: &mut Vec<(Value, u64)>) {
+    match terms.iter_mut().find(|(term, _)| term == value) {
+        Some((_, existing)) => *existing = existing.wrapping_add(coefficient),
+        None => terms.push((value.clone(), coefficient)),
+    }
+}
+
+/// Recognizes division by a constant done with a multiplication, shifts or a conditional
+/// adjustment, the absolute value, and the sign of a comparison function turned into its
+/// result, on the single register or memory operand `value` reads. The divisor is guessed
+/// by evaluating `value`, and every idiom is then proven to hold for all inputs.
+fn recognize(value: &Value) -> Option<Value> {
+    let Value::Op(op, bits, _) = value else {
+        return None;
+    };
+    if !matches!(
+        op,
+        Op::Shr
+            | Op::Sar
+            | Op::Add
+            | Op::Sub
+            | Op::Select
+            | Op::MulHigh { .. }
+            | Op::Xor
+            | Op::Extend { signed: true, .. }
+    ) {
+        return None;
+    }
+    let mut leaves = Vec::new();
+    collect_leaves(value, &mut leaves);
+    let [leaf] = leaves.as_slice() else {
+        return None;
+    };
+    let input = Value::Reg(Register::None, None);
+    let value = &substitute(value, leaf, &input);
+
+    // `seta` and `setb` subtracted, after `repe cmpsb`
+    if let Value::Op(
+        Op::Builtin {
+            builtin: Builtin::Memcmp | Builtin::Strcmp,
+            ..
+        },
+        ..,
+    ) = leaf
+    {
+        let bits = *bits;
+        let sign = bits >= 32 && proves(value, 32, true, Target::Sign, bits, 0);
+        return sign.then(|| match bits {
+            32 => leaf.clone(),
+            _ => Value::Op(
+                Op::Extend {
+                    from: 32,
+                    signed: true,
+                },
+                bits,
+                vec![leaf.clone()],
+            ),
+        });
+    }
+    let has = |pred: &dyn Fn(Op) -> bool| contains(value, pred);
+    let multiplies = has(&|op| matches!(op, Op::MulHigh { .. } | Op::Mul));
+    let selects = has(&|op| op == Op::Select);
+    let adjusts =
+        has(&|op| matches!(op, Op::Add | Op::Sub)) && has(&|op| matches!(op, Op::Shr | Op::Sar));
+    if !(multiplies || selects || adjusts) {
+        return None;
+    }
+    let bits = *bits;
+    let run = |x: u64| mask(eval(value, &|_| x), bits);
+
+    for (input, signed) in [(32, true), (32, false), (64, true), (64, false)] {
+        if input > bits {
+            continue;
+        }
+        let max = match signed {
+            true => mask(u64::MAX, input - 1),
+            false => mask(u64::MAX, input),
+        };
+        let quotient = mask(run(max), input);
+        if quotient == 0 || quotient > max {
+            continue;
+        }
+        let guess = max / quotient;
+        for d in [guess, guess.saturating_add(1)] {
+            if d < 2 || d > max {
+                continue;
+            }
+            if divides(value, input, signed, d, bits) {
+                let div = Value::Op(
+                    Op::Div { signed },
+                    input,
+                    vec![leaf.clone(), Value::Const(d)],
+                );
+                return Some(match input < bits {
+                    true => Value::Op(
+                        Op::Extend {
+                            from: input,
+                            signed,
+                        },
+                        bits,
+                        vec![div],
+                    ),
+                    false => div,
+                });
+            }
+        }
+    }
+
+    if selects || has(&|op| op == Op::Xor) {
+        for input in [32, 64] {
+            if input != bits {
+                continue;
+            }
+            if proves(value, input, true, Target::Abs, bits, 0) {
+                return Some(Value::Op(Op::Abs, bits, vec![leaf.clone()]));
+            }
+        }
+    }
+    None
+}
+
+/// Collects the registers and memory `value` reads, and the divisions and absolute values
+/// already recognized in it, which idioms are computed on like on any other input.
+fn collect_leaves(value: &Value, leaves: &mut Vec<Value>) {
+    match value {
+        Value::Const(_) => {}
+        Value::Op(op, _, args)
+            if !matches!(
+                op,
+                Op::Div { .. } | Op::Rem { .. } | Op::Abs | Op::Builtin { .. }
+            ) =>
+        {
+            args.iter().for_each(|arg| collect_leaves(arg, leaves))
+        }
+        leaf => {
+            if !leaves.contains(leaf) {
+                leaves.push(leaf.clone())
+            }
+        }
+    }
+}
+
+/// Replaces `from` with `to` in `value`.
+fn substitute(value: &Value, from: &Value, to: &Value) -> Value {
+    match value {
+        _ if value == from => to.clone(),
+        Value::Op(op, bits, args) => Value::Op(
+            *op,
+            *bits,
+            args.iter().map(|arg| substitute(arg, from, to)).collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+/// What a value computed from the single input of an idiom comes to.
+#[derive(Clone, Copy)]
+enum Target {
+    /// The input divided by a constant, truncated towards zero.
+    Quotient(u64),
+    Abs,
+    /// -1, 0 or 1 as the input is negative, zero or positive.
+    Sign,
+}
+
+/// `floor((factor * x + bias) / 2^shift)` of the input `x`, what the multiplications,
+/// shifts and adjustments compilers divide with come to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Form {
+    factor: i128,
+    bias: i128,
+    shift: u32,
+}
+
+impl Form {
+    const INPUT: Form = Form {
+        factor: 1,
+        bias: 0,
+        shift: 0,
+    };
+
+    fn constant(value: i128) -> Self {
+        Self {
+            factor: 0,
+            bias: value,
+            shift: 0,
+        }
+    }
+
+    /// The sum of two forms, when one of them is not divided.
+    fn add(self, other: Form) -> Option<Form> {
+        let (a, b) = match (self.shift, other.shift) {
+            (_, 0) => (self, other),
+            (0, _) => (other, self),
+            _ => return None,
+        };
+        Some(Form {
+            factor: a
+                .factor
+                .checked_add(b.factor.checked_mul(power(a.shift)?)?)?,
+            bias: a.bias.checked_add(b.bias.checked_mul(power(a.shift)?)?)?,
+            shift: a.shift,
+        })
+    }
+
+    fn scale(self, by: i128) -> Option<Form> {
+        (self.shift == 0).then_some(())?;
+        Some(Form {
+            factor: self.factor.checked_mul(by)?,
+            bias: self.bias.checked_mul(by)?,
+            shift: 0,
+        })
+    }
+
+    fn shift_right(self, by: u32) -> Option<Form> {
+        let shift = self.shift + by;
+        (shift < 120).then_some(Form { shift, ..self })
+    }
+}
+
+/// The inputs of `bits` from `lo` to `hi`, on which the comparisons and shifts testing the
+/// sign of the input are constants.
+struct Class {
+    lo: i128,
+    hi: i128,
+    bits: u32,
+}
+
+impl Class {
+    /// Returns the least and greatest values `form` takes on the class.
+    fn range(&self, form: Form) -> Option<(i128, i128)> {
+        let a = floor_affine(form.factor, self.lo, form.bias, form.shift)?;
+        let b = floor_affine(form.factor, self.hi, form.bias, form.shift)?;
+        Some((a.min(b), a.max(b)))
+    }
+
+    /// Returns `form` as a constant if it takes a single value on the class.
+    fn collapse(&self, form: Form) -> Option<Form> {
+        let (lo, hi) = self.range(form)?;
+        Some(match lo == hi {
+            true => Form::constant(lo),
+            false => form,
+        })
+    }
+
+    /// Returns `form`, whose low `bits` are those of a value, as the `signed` or unsigned
+    /// value of `bits` an instruction reading it sees, or `None` if that wraps around
+    /// within the class.
+    fn normalize(&self, form: Form, bits: u32, signed: bool) -> Option<Form> {
+        let (lo, hi) = self.range(form)?;
+        let base = match signed {
+            true => -(1_i128 << (bits - 1)),
+            false => 0,
+        };
+        let window = 1_i128 << bits;
+        let wraps = (lo - base).div_euclid(window);
+        if (hi - base).div_euclid(window) != wraps {
+            return None;
+        }
+        let offset = wraps.checked_mul(window)?.checked_mul(power(form.shift)?)?;
+        Some(Form {
+            bias: form.bias.checked_sub(offset)?,
+            ..form
+        })
+    }
+}
+
+fn power(shift: u32) -> Option<i128> {
+    (shift < 127).then(|| 1 << shift)
+}
+
+/// Returns `floor((factor * x + bias) / 2^shift)`, computed on 256 bits, if it fits.
+fn floor_affine(factor: i128, x: i128, bias: i128, shift: u32) -> Option<i128> {
+    let (p, q) = (factor.unsigned_abs(), x.unsigned_abs());
+    let half = u128::from(u64::MAX);
+    let (p1, p0, q1, q0) = (p >> 64, p & half, q >> 64, q & half);
+    let (middle, carry) = (p0 * q1).overflowing_add(p1 * q0);
+    let (mut low, carry_low) = (p0 * q0).overflowing_add(middle << 64);
+    let mut high = (p1 * q1)
+        .wrapping_add(middle >> 64)
+        .wrapping_add(u128::from(carry) << 64)
+        .wrapping_add(u128::from(carry_low));
+    if (factor < 0) != (x < 0) {
+        high = (!high).wrapping_add(u128::from(low == 0));
+        low = low.wrapping_neg();
+    }
+    let (sum, carry) = low.overflowing_add(bias as u128);
+    low = sum;
+    high = high
+        .wrapping_add(u128::from(carry))
+        .wrapping_add(if bias < 0 { u128::MAX } else { 0 });
+    let high = high as i128;
+    let (high, low) = match shift {
+        0 => (high, low),
+        1..=127 => (
+            high >> shift,
+            (low >> shift) | ((high as u128) << (128 - shift)),
+        ),
+        _ => (high >> 127, (high >> (shift - 128).min(127)) as u128),
+    };
+    (high == (low as i128) >> 127).then_some(low as i128)
+}
+
+/// Returns the form `value` comes to on the inputs of `class`, with its low `read` bits
+/// those of the value.
+fn form(value: &Value, class: &Class, read: u32) -> Option<Form> {
+    let Value::Op(op, bits, args) = value else {
+        return match value {
+            Value::Const(value) => Some(Form::constant(i128::from(*value))),
+            // the bits of the input above its width are not known
+            Value::Reg(Register::None, None) => (read <= class.bits).then_some(Form::INPUT),
+            _ => None,
+        };
+    };
+    let bits = *bits;
+    let arg = |i: usize| form(&args[i], class, bits);
+    let constant = |i: usize| match args[i] {
+        Value::Const(value) => Some(value),
+        _ => None,
+    };
+    let form = match op {
+        Op::Add => average(args, class, bits).or_else(|| arg(0)?.add(arg(1)?))?,
+        Op::Sub => arg(0)?.add(arg(1)?.scale(-1)?)?,
+        Op::Neg => arg(0)?.scale(-1)?,
+        Op::Not => arg(0)?.scale(-1)?.add(Form::constant(-1))?,
+        Op::Mul => match (constant(0), constant(1)) {
+            (_, Some(by)) => arg(0)?.scale(i128::from(by))?,
+            (Some(by), _) => arg(1)?.scale(i128::from(by))?,
+            _ => return None,
+        },
+        Op::Xor => {
+            let (i, by) = match (constant(0), constant(1)) {
+                (_, Some(by)) => (0, by),
+                (Some(by), _) => (1, by),
+                _ => return None,
+            };
+            match mask(by, bits) {
+                0 => arg(i)?,
+                by if by == mask(u64::MAX, bits) => arg(i)?.scale(-1)?.add(Form::constant(-1))?,
+                _ => return None,
+            }
+        }
+        Op::Shl => {
+            let by = constant(1)? & if bits == 64 { 63 } else { 31 };
+            arg(0)?.scale(power(by as u32)?)?
+        }
+        Op::Shr | Op::Sar => {
+            let by = (constant(1)? & if bits == 64 { 63 } else { 31 }) as u32;
+            let signed = *op == Op::Sar;
+            class.normalize(arg(0)?, bits, signed)?.shift_right(by)?
+        }
+        Op::MulHigh { signed } => {
+            let (i, by) = match (constant(0), constant(1)) {
+                (_, Some(by)) => (0, by),
+                (Some(by), _) => (1, by),
+                _ => return None,
+            };
+            let by = match signed {
+                true => i128::from(sign_extend(by, bits)),
+                false => i128::from(mask(by, bits)),
+            };
+            let factor = class.normalize(arg(i)?, bits, *signed)?;
+            factor.scale(by)?.shift_right(bits)?
+        }
+        Op::Extend { from, signed } => {
+            class.normalize(form(&args[0], class, *from)?, *from, *signed)?
+        }
+        Op::Compare { cmp, bits } => {
+            let signed = matches!(
+                cmp,
+                Cmp::Lt { signed: true }
+                    | Cmp::Le { signed: true }
+                    | Cmp::Gt { signed: true }
+                    | Cmp::Ge { signed: true }
+            );
+            let side = |i: usize| {
+                let form = class.normalize(form(&args[i], class, *bits)?, *bits, signed)?;
+                class.range(form)
+            };
+            let ((a_lo, a_hi), (b_lo, b_hi)) = (side(0)?, side(1)?);
+            let holds = match cmp {
+                Cmp::Eq | Cmp::Ne if a_lo == a_hi && b_lo == b_hi => {
+                    (a_lo == b_lo) == (*cmp == Cmp::Eq)
+                }
+                Cmp::Eq | Cmp::Ne if a_hi < b_lo || b_hi < a_lo => *cmp == Cmp::Ne,
+                Cmp::Lt { .. } | Cmp::Ge { .. } if a_hi < b_lo || a_lo >= b_hi => {
+                    (a_hi < b_lo) == matches!(cmp, Cmp::Lt { .. })
+                }
+                Cmp::Le { .. } | Cmp::Gt { .. } if a_hi <= b_lo || a_lo > b_hi => {
+                    (a_hi <= b_lo) == matches!(cmp, Cmp::Le { .. })
+                }
+                _ => return None,
+            };
+            Form::constant(i128::from(holds))
+        }
+        Op::Select => {
+            let condition = class.collapse(form(&args[0], class, 64)?)?;
+            if condition.factor != 0 {
+                return None;
+            }
+            match condition.bias.rem_euclid(1 << 64) {
+                0 => arg(2)?,
+                _ => arg(1)?,
+            }
+        }
+        _ => return None,
+    };
+    let form = class.collapse(form)?;
+    // the upper bits of a narrower result are clear
+    match read > bits && !matches!(op, Op::Compare { .. }) {
+        true => class.normalize(form, bits, false),
+        false => Some(form),
+    }
+}
+
+/// Returns the form of `t + ((x - t) >> 1)`, the average of `x` and `t` taken without
+/// overflowing, which `args` of an addition on `bits` are.
+fn average(args: &[Value], class: &Class, bits: u32) -> Option<Form> {
+    let halved = |i: usize| match &args[i] {
+        Value::Op(Op::Shr, shr_bits, halved)
+            if *shr_bits == bits && halved[1] == Value::Const(1) =>
+        {
+            match &halved[0] {
+                Value::Op(Op::Sub, sub_bits, sub) if *sub_bits == bits && sub[1] == args[1 - i] => {
+                    Some(&sub[0])
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    let (t, x) = match (halved(0), halved(1)) {
+        (_, Some(x)) => (&args[0], x),
+        (Some(x), _) => (&args[1], x),
+        _ => return None,
+    };
+    let (t, x) = (form(t, class, bits)?, form(x, class, bits)?);
+    if x.shift != 0 {
+        return None;
+    }
+    // x - t lies within one of x - (factor * x + bias) / 2^shift, and must not wrap around
+    let scaled = Form {
+        factor: x.factor.checked_mul(power(t.shift)?)?,
+        bias: x.bias.checked_mul(power(t.shift)?)?,
+        shift: t.shift,
+    };
+    let difference = Form {
+        factor: scaled.factor.checked_sub(t.factor)?,
+        bias: scaled.bias.checked_sub(t.bias)?,
+        shift: t.shift,
+    };
+    let (lo, hi) = class.range(difference)?;
+    if lo < 0 || hi + 1 >= 1 << bits {
+        return None;
+    }
+    // t + floor((x - t) / 2) = floor((x + t) / 2), and t is the floor of its form
+    Some(Form {
+        factor: scaled.factor.checked_add(t.factor)?,
+        bias: scaled.bias.checked_add(t.bias)?,
+        shift: t.shift + 1,
+    })
+}
+
+/// Returns `true` if `value`, of `bits`, comes to `target` for every `signed` or unsigned
+/// input of `input` bits shifted right by `pre` bits. The inputs are split by sign, and the
+/// form of the value on each class checked at its ends, which is exact as both the form
+/// and the target are monotonic there.
+fn proves(value: &Value, input: u32, signed: bool, target: Target, bits: u32, pre: u32) -> bool {
+    let max = match signed {
+        true => (1_i128 << (input - 1)) - 1,
+        false => (1_i128 << input) - 1,
+    };
+    let classes = match signed {
+        true => vec![(-max - 1, -max - 1), (-max, -1), (0, 0), (1, max)],
+        false => vec![(0, max >> pre)],
+    };
+    classes.into_iter().all(|(lo, hi)| {
+        let class = Class {
+            lo,
+            hi,
+            bits: input,
+        };
+        let form = form(value, &class, bits).and_then(|form| match target {
+            Target::Quotient(_) => class.normalize(form, bits, signed),
+            _ => Some(form),
+        });
+        form.is_some_and(|form| holds(form, &class, target, bits))
+    })
+}
+
+/// Returns `true` if `form` is `target` on the inputs of `class`, on `bits`.
+fn holds(form: Form, class: &Class, target: Target, bits: u32) -> bool {
+    let (factor, bias) = match target {
+        Target::Quotient(d) => return quotient(form, class, d) == Some(true),
+        Target::Abs if class.hi < 0 => (-1, 0),
+        Target::Abs => (1, 0),
+        Target::Sign => (0, class.lo.signum()),
+    };
+    let window = 1_i128 << bits;
+    let at = |x: i128| floor_affine(form.factor, x, form.bias, form.shift);
+    let linear =
+        class.lo == class.hi || (form.shift == 0 && (form.factor - factor).rem_euclid(window) == 0);
+    linear
+        && at(class.lo)
+            .is_some_and(|value| (value - factor * class.lo - bias).rem_euclid(window) == 0)
+}
+
+/// Returns whether `form` is the quotient of its input by `d`, truncated, on `class`.
+///
+/// With `e = factor * d - 2^shift`, the integer `form` differs from `x / d` by less than
+/// `(e * x + bias * d) / (d * 2^shift)`, and by more than that minus one. Rounding down is
+/// then exact where `e * x + bias * d` lies within `[0, 2^shift)`, and rounding up, for the
+/// negative inputs, where it lies within `[(d - 1) * 2^shift, d * 2^shift)`.
+fn quotient(form: Form, class: &Class, d: u64) -> Option<bool> {
+    let d = i128::from(d);
+    if class.lo == class.hi {
+        let value = floor_affine(form.factor, class.lo, form.bias, form.shift)?;
+        return Some(value == class.lo / d);
+    }
+    let one = power(form.shift)?;
+    let e = form.factor.checked_mul(d)?.checked_sub(one)?;
+    let rest = form.bias.rem_euclid(one).checked_mul(d)?;
+    let whole = form.bias.div_euclid(one).checked_mul(d)?;
+    let expected = match class.hi < 0 {
+        true => d - 1,
+        false => 0,
+    };
+    let at = |x: i128| floor_affine(e, x, rest, form.shift)?.checked_add(whole);
+    Some(at(class.lo)? == expected && at(class.hi)? == expected)
+}
+
+/// Returns `true` if `value` of `bits` is the quotient of its `signed` or unsigned input of
+/// `input` bits by `d`, also when an unsigned input is shifted right first, dividing by the
+/// odd part of an even `d` with a smaller factor.
+fn divides(value: &Value, input: u32, signed: bool, d: u64, bits: u32) -> bool {
+    if proves(value, input, signed, Target::Quotient(d), bits, 0) {
+        return true;
+    }
+    let pre = d.trailing_zeros();
+    if signed || pre == 0 {
+        return false;
+    }
+    // the shifted input, whose upper bits are clear, as the input, when it is read only so
+    let x = Value::Reg(Register::None, None);
+    let y = Value::Reg(Register::None, Some(usize::MAX));
+    let shifted = Value::Op(
+        Op::Shr,
+        input,
+        vec![x.clone(), Value::Const(u64::from(pre))],
+    );
+    let extended = Value::Op(
+        Op::Extend {
+            from: input,
+            signed: false,
+        },
+        64,
+        vec![y.clone()],
+    );
+    let value = substitute(value, &shifted, &extended);
+    let mut leaves = Vec::new();
+    collect_leaves(&value, &mut leaves);
+    leaves == [y.clone()]
+        && proves(
+            &substitute(&value, &y, &x),
+            input,
+            false,
+            Target::Quotient(d >> pre),
+            bits,
+            pre,
+        )
+}
+
+fn expr(kind: ExprKind) -> Expr {
+    Expr { id: 0, kind }
+}
+
+fn noise() -> Expr {
+    expr(ExprKind::Lit(Lit::StillUnknown))
+}
+
+fn assign(lhs: Expr, rhs: Expr) -> Expr {
+    expr(ExprKind::Assign(Box::new(lhs), Box::new(rhs)))
+}
+
+fn cast(inner: Expr, ty: Ty) -> Expr {
+    expr(ExprKind::Cast(Box::new(inner), ty))
+}
+
+fn register(symbols: &mut Vec<Symbol>, reg: Register) -> Expr {
+    expr(ExprKind::Lit(Lit::Symbol(get_new_reg_symbol(symbols, reg))))
+}
+
+fn literal(value: u64, bits: u32) -> Expr {
+    match bits {
+        ..=32 => expr(ExprKind::Lit(Lit::U32(value as u32))),
+        _ => expr(ExprKind::Lit(Lit::U64(value))),
+    }
+}
+
+/// Reads `lifted`, the lifting of `value`, as a signed integer of `bits`.
+fn as_signed(lifted: Expr, value: &Value, bits: u32) -> Expr {
+    match value {
+        Value::Const(c) if sign_extend(mask(*c, bits), bits) < 0 => expr(ExprKind::Unary(
+            UnOp::Neg,
+            Box::new(literal(
+                sign_extend(mask(*c, bits), bits).unsigned_abs(),
+                bits,
+            )),
+        )),
+        Value::Const(_) => lifted,
+        Value::Op(Op::Extend { signed: true, .. }, ..) => lifted,
+        Value::Op(
+            Op::Div { signed: true } | Op::Rem { signed: true } | Op::Sar | Op::Abs,
+            op_bits,
+            _,
+        ) if *op_bits == bits => lifted,
+        // `int` results
+        Value::Op(
+            Op::Builtin {
+                builtin: Builtin::Memcmp | Builtin::Strcmp,
+                ..
+            },
+            ..,
+        ) if bits == 32 => lifted,
+        _ => cast(lifted, signed_ty(bits)),
+    }
+}
+
+fn signed_ty(bits: u32) -> Ty {
+    match bits {
+        ..=8 => Ty::I8,
+        16 => Ty::I16,
+        32 => Ty::I32,
+        _ => Ty::I64,
+    }
+}
+
+fn unsigned_ty(bits: u32) -> Ty {
+    match bits {
+        ..=8 => Ty::U8,
+        16 => Ty::U16,
+        32 => Ty::U32,
+        _ => Ty::U64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::Conventions;
+    use crate::function::Function;
+    use crate::rtti::Classes;
+    use crate::unwind::UnwindTable;
+
+    fn op(op: Op, bits: u32, args: Vec<Value>) -> Value {
+        Value::Op(op, bits, args)
+    }
+
+    fn x() -> Value {
+        Value::Reg(Register::EDI, None)
+    }
+
+    fn extend(value: Value, signed: bool) -> Value {
+        op(Op::Extend { from: 32, signed }, 64, vec![value])
+    }
+
+    /// `(uint64_t)x * factor >> shift` of the 32-bit `x`.
+    fn unsigned_magic(factor: u64, shift: u64) -> Value {
+        let product = op(Op::Mul, 64, vec![extend(x(), false), Value::Const(factor)]);
+        op(Op::Shr, 64, vec![product, Value::Const(shift)])
+    }
+
+    /// `((int64_t)x * factor >> shift) - (x >> 31)` of the 32-bit `x`.
+    fn signed_magic(factor: u64, shift: u64) -> Value {
+        let product = op(Op::Mul, 64, vec![extend(x(), true), Value::Const(factor)]);
+        let high = op(Op::Sar, 64, vec![product, Value::Const(shift)]);
+        let sign = op(Op::Sar, 32, vec![x(), Value::Const(31)]);
+        op(Op::Sub, 32, vec![high, sign])
+    }
+
+    /// Lifts the x86-64 function `code` and rewrites its idioms, returning the statements
+    /// left.
+    fn lift(code: &[u8]) -> Vec<String> {
+        let memory = Memory::raw(code, 0x1000, arch::Architecture::X86 { bitness: 64 }).unwrap();
+        let conventions = Conventions::new(CallingConvention::SysV);
+        let none = BTreeSet::new();
+        let unwind = UnwindTable::default();
+        let classes = Classes::default();
+        let mut function =
+            Function::lift(&memory, &unwind, 0x1000, &conventions, &none, &none, &classes);
+        function.rewrite_idioms(&memory, &conventions, &[]);
+        function
+            .statements()
+            .filter(|stmt| !matches!(stmt.kind, ExprKind::Lit(Lit::StillUnknown)))
+            .map(|stmt| stmt.to_string())
+            .collect()
+    }
+
+    fn divisor(value: &Value) -> Option<(bool, u64)> {
+        match value {
+            Value::Op(Op::Div { signed }, _, args) => match args[1] {
+                Value::Const(d) => Some((*signed, d)),
+                _ => None,
+            },
+            Value::Op(Op::Extend { .. }, _, args) => divisor(&args[0]),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn unsigned_division() {
+        let value = recognize(&unsigned_magic(3435973837, 35));
+        assert_eq!(value.as_ref().and_then(divisor), Some((false, 10)));
+        // one less rounds 0x9 / 10 and the like down too far
+        assert_eq!(recognize(&unsigned_magic(3435973836, 35)), None);
+    }
+
+    #[test]
+    fn unsigned_division_by_average() {
+        // t + ((x - t) >> 1) >> 2, with t the high half of x * 0x24924925
+        let high = || {
+            let product = op(
+                Op::Mul,
+                64,
+                vec![extend(x(), false), Value::Const(613566757)],
+            );
+            op(Op::Shr, 64, vec![product, Value::Const(32)])
+        };
+        let difference = op(Op::Sub, 32, vec![x(), high()]);
+        let halved = op(Op::Shr, 32, vec![difference, Value::Const(1)]);
+        let sum = op(Op::Add, 32, vec![high(), halved]);
+        let value = op(Op::Shr, 32, vec![sum, Value::Const(2)]);
+        assert_eq!(
+            recognize(&value).as_ref().and_then(divisor),
+            Some((false, 7))
+        );
+    }
+
+    #[test]
+    fn unsigned_division_of_shifted_input() {
+        let shifted = op(Op::Shr, 32, vec![x(), Value::Const(1)]);
+        let product = op(Op::Mul, 64, vec![shifted, Value::Const(2454267027)]);
+        let value = op(Op::Shr, 64, vec![product, Value::Const(34)]);
+        assert_eq!(
+            recognize(&value).as_ref().and_then(divisor),
+            Some((false, 14))
+        );
+    }
+
+    #[test]
+    fn signed_division() {
+        let value = recognize(&signed_magic(1717986919, 34));
+        assert_eq!(value.as_ref().and_then(divisor), Some((true, 10)));
+        // rounds the negative multiples of 10 towards zero one too far
+        assert_eq!(recognize(&signed_magic(1717986918, 34)), None);
+    }
+
+    #[test]
+    fn signed_division_by_power_of_two() {
+        let positive = op(
+            Op::Compare {
+                cmp: Cmp::Ge { signed: true },
+                bits: 32,
+            },
+            1,
+            vec![x(), Value::Const(0)],
+        );
+        let biased = |bias| {
+            let adjusted = op(Op::Add, 32, vec![x(), Value::Const(bias)]);
+            let select = op(Op::Select, 32, vec![positive.clone(), x(), adjusted]);
+            op(Op::Sar, 32, vec![select, Value::Const(4)])
+        };
+        assert_eq!(
+            recognize(&biased(15)).as_ref().and_then(divisor),
+            Some((true, 16))
+        );
+        assert_eq!(recognize(&biased(14)), None);
+    }
+
+    #[test]
+    fn absolute_value() {
+        let negated = || op(Op::Neg, 32, vec![x()]);
+        let negative = op(
+            Op::Compare {
+                cmp: Cmp::Lt { signed: true },
+                bits: 32,
+            },
+            1,
+            vec![negated(), Value::Const(0)],
+        );
+        let value = op(Op::Select, 32, vec![negative, x(), negated()]);
+        assert_eq!(recognize(&value), Some(op(Op::Abs, 32, vec![x()])));
+    }
+
+    #[test]
+    fn floor_affine_is_exact_on_256_bits() {
+        let big = i128::from(u64::MAX) + 7;
+        assert_eq!(floor_affine(big, -big, 0, 126), Some(-5));
+        assert_eq!(floor_affine(3, -1, 0, 1), Some(-2));
+        assert_eq!(floor_affine(1 << 100, 1 << 100, 0, 0), None);
+    }
+
+    #[test]
+    fn sign_shifts() {
+        // mov eax, edi; shr eax, 31; ret
+        let statements = lift(&[0x89, 0xf8, 0xc1, 0xe8, 0x1f, 0xc3]);
+        assert_eq!(statements[0], "@EAX = (int32_t)(@EDI) < 0x0");
+        // sar rdi, 63; mov rax, rdi; ret
+        let statements = lift(&[0x48, 0xc1, 0xff, 0x3f, 0x48, 0x89, 0xf8, 0xc3]);
+        assert_eq!(statements[0], "@RAX = -((int64_t)(@RDI) < 0x0)");
+    }
+
+    #[test]
+    fn string_instructions() {
+        // mov ecx, 8; rep movsq; ret
+        let statements = lift(&[0xb9, 0x08, 0x00, 0x00, 0x00, 0xf3, 0x48, 0xa5, 0xc3]);
+        assert_eq!(statements[0], "memcpy(@RDI, @RSI, 0x40)");
+        // xor eax, eax; mov ecx, 0x40; rep stosb; ret
+        let statements = lift(&[0x31, 0xc0, 0xb9, 0x40, 0x00, 0x00, 0x00, 0xf3, 0xaa, 0xc3]);
+        assert_eq!(statements[1], "memset(@RDI, 0x0, 0x40)");
+    }
+
+    #[test]
+    fn vector_copies() {
+        // sub rsp, 0x58; movdqu xmm0, [rsi]; movups [rsp], xmm0; movdqu xmm1, [rsi+0x10];
+        // movups [rsp+0x10], xmm1; add rsp, 0x58; ret
+        let statements = lift(&[
+            0x48, 0x83, 0xec, 0x58, 0xf3, 0x0f, 0x6f, 0x06, 0x0f, 0x11, 0x04, 0x24, 0xf3, 0x0f,
+            0x6f, 0x4e, 0x10, 0x0f, 0x11, 0x4c, 0x24, 0x10, 0x48, 0x83, 0xc4, 0x58, 0xc3,
+        ]);
+        assert_eq!(statements[1], "memcpy(@RSP, @RSI, 0x20)");
+        assert_eq!(statements.len(), 4, "{statements:?}");
+        // the loads first, with the source register reused before the stores:
+        // movdqu xmm0, [rdi]; movdqu xmm1, [rdi+0x10]; mov rdi, rsp; movaps [rsp], xmm0;
+        // movaps [rsp+0x10], xmm1; call 0x101f; ret
+        let statements = lift(&[
+            0xf3, 0x0f, 0x6f, 0x07, 0xf3, 0x0f, 0x6f, 0x4f, 0x10, 0x48, 0x89, 0xe7, 0x0f, 0x29,
+            0x04, 0x24, 0x0f, 0x29, 0x4c, 0x24, 0x10, 0xe8, 0x00, 0x00, 0x00, 0x00, 0xc3,
+        ]);
+        assert_eq!(statements[..2], ["memcpy(@RSP, @RDI, 0x20)", "@RDI = @RSP"]);
+    }
+
+    #[test]
+    fn shift_flags() {
+        // sar edi, 1; je 1f; ret; 1: ret
+        let statements = lift(&[0xd1, 0xff, 0x74, 0x01, 0xc3, 0xc3]);
+        assert_eq!(statements[0], "if (@EDI == 0x0) { goto LAB_1005; }");
+        // shl edi, cl; je 1f; ret; 1: ret
+        let statements = lift(&[0xd3, 0xe7, 0x74, 0x01, 0xc3, 0xc3]);
+        assert!(statements.iter().any(|stmt| stmt.contains("@ZF")), "{statements:?}");
+    }
+}
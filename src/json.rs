@@ -0,0 +1,219 @@
+use std::error::Error;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::BufWriter;
+
+use serde::Serialize;
+
+use crate::ast::Expr;
+use crate::cfg::Edge;
+use crate::dwarf::DebugInfo;
+use crate::function::Function;
+use crate::hardening::Hardening;
+use crate::memory::memory::Memory;
+use crate::unwind::UnwindTable;
+use crate::xrefs::{Xref, XrefDb};
+
+/// Bumped whenever a field is renamed, removed or changes meaning.
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Serialize)]
+pub struct Document<'a> {
+    pub schema_version: u32,
+    pub binary: &'a str,
+    pub entry: u64,
+    pub functions: Vec<FunctionEntry<'a>>,
+    /// The code and data references made by the functions, in order of target.
+    pub xrefs: Vec<&'a Xref>,
+}
+
+#[derive(Serialize)]
+pub struct FunctionEntry<'a> {
+    pub address: u64,
+    pub name: &'a str,
+    /// The C prototype given by the debug info or the headers, `_Noreturn` when the function
+    /// is found never to return.
+    pub prototype: Option<String>,
+    /// The stack canary, CET and `_FORTIFY_SOURCE` checks found in the function.
+    pub hardening: &'a Hardening,
+    pub blocks: Vec<BlockEntry<'a>>,
+    pub edges: &'a [Edge],
+}
+
+#[derive(Serialize)]
+pub struct BlockEntry<'a> {
+    pub start: u64,
+    pub end: u64,
+    pub instructions: Vec<InstructionEntry>,
+    pub statements: &'a [Expr],
+}
+
+#[derive(Serialize)]
+pub struct InstructionEntry {
+    pub address: u64,
+    pub bytes: String,
+    pub mnemonic: String,
+    pub text: String,
+    /// How far the stack pointer is below its value on entry, from the call frame information.
+    pub stack_depth: Option<i64>,
+    /// The `file:line` starting at this instruction, from the line tables.
+    pub source: Option<String>,
+}
+
+impl<'a> Document<'a> {
+    pub fn new(
+        binary: &'a str,
+        entry: u64,
+        memory: &Memory,
+        unwind: &UnwindTable,
+        debug: &DebugInfo,
+        functions: &'a [Function],
+        xrefs: &'a XrefDb,
+    ) -> Self {
+        let functions = functions
+            .iter()
+            .map(|function| FunctionEntry {
+                address: function.address,
+                name: &function.name,
+                prototype: function
+                    .prototype
+                    .as_ref()
+                    .map(|prototype| prototype.declare(&function.name)),
+                hardening: &function.hardening,
+                blocks: function
+                    .cfg
+                    .blocks
+                    .values()
+                    .map(|block| BlockEntry {
+                        start: block.start,
+                        end: block.end,
+                        instructions: block
+                            .instructions
+                            .iter()
+                            .map(|inst| {
+                                let mut bytes = String::new();
+                                for byte in memory.get(inst.ip()).unwrap_or_default().iter().take(inst.len()) {
+                                    write!(&mut bytes, "{:02x}", byte).unwrap();
+                                }
+                                InstructionEntry {
+                                    address: inst.ip(),
+                                    bytes,
+                                    mnemonic: inst.mnemonic(),
+                                    text: inst.to_string(),
+                                    stack_depth: unwind.stack_depth(inst.ip()),
+                                    source: debug.line(inst.ip()).map(|line| line.to_string()),
+                                }
+                            })
+                            .collect(),
+                        statements: &block.statements,
+                    })
+                    .collect(),
+                edges: &function.cfg.edges,
+            })
+            .collect();
+
+        Self {
+            schema_version: SCHEMA_VERSION,
+            binary,
+            entry,
+            functions,
+            xrefs: xrefs.iter().collect(),
+        }
+    }
+
+    pub fn write_to(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::abi::{CallingConvention, Conventions};
+    use crate::arch::Architecture;
+    use crate::ast::{ExprKind, Lit, Symbol, UnOp};
+    use crate::rtti::Classes;
+    use crate::types::{Prototype, Type};
+
+    /// The shape of the documents of this schema version, which any change to has to bump.
+    #[test]
+    fn serialized_shape() {
+        // ud2
+        let memory = Memory::raw(&[0x0f, 0x0b], 0x1000, Architecture::X86 { bitness: 64 }).unwrap();
+        let conventions = Conventions::new(CallingConvention::SysV);
+        let none = BTreeSet::new();
+        let unwind = UnwindTable::default();
+        let classes = Classes::default();
+        let mut function =
+            Function::lift(&memory, &unwind, 0x1000, &conventions, &none, &none, &classes);
+        function.name = "trap".to_string();
+        function.prototype = Some(Prototype {
+            return_type: Type::Void,
+            parameters: Vec::new(),
+            variadic: false,
+            noreturn: true,
+        });
+        let condition = Expr {
+            id: 0,
+            kind: ExprKind::Lit(Lit::Symbol(Symbol::Reg("EDI".to_string()))),
+        };
+        let block = function.cfg.blocks.values_mut().next().unwrap();
+        block.statements = vec![Expr {
+            id: 0,
+            kind: ExprKind::Unary(UnOp::LogicalNot, Box::new(condition)),
+        }];
+
+        let functions = [function];
+        let debug = DebugInfo::default();
+        let xrefs = XrefDb::default();
+        let document = Document::new("trap", 0x1000, &memory, &unwind, &debug, &functions, &xrefs);
+        assert_eq!(SCHEMA_VERSION, 2);
+        assert_eq!(
+            serde_json::to_value(&document).unwrap(),
+            json!({
+                "schema_version": 2,
+                "binary": "trap",
+                "entry": 0x1000,
+                "functions": [{
+                    "address": 0x1000,
+                    "name": "trap",
+                    "prototype": "_Noreturn void trap(void)",
+                    "hardening": {
+                        "stack_protector": false,
+                        "endbr": false,
+                        "fortified": [],
+                        "handlers": [],
+                        "size_checks": 0,
+                    },
+                    "blocks": [{
+                        "start": 0x1000,
+                        "end": 0x1002,
+                        "instructions": [{
+                            "address": 0x1000,
+                            "bytes": "0f0b",
+                            "mnemonic": "ud2",
+                            "text": "ud2",
+                            "stack_depth": null,
+                            "source": null,
+                        }],
+                        "statements": [{
+                            "id": 0,
+                            "kind": {"unary": ["logical_not", {
+                                "id": 0,
+                                "kind": {"lit": {"symbol": {"reg": "EDI"}}},
+                            }]},
+                        }],
+                    }],
+                    "edges": [],
+                }],
+                "xrefs": [],
+            })
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt::{Result, Write};
+
+use iced_x86::{Formatter, GasFormatter, IntelFormatter, MasmFormatter, NasmFormatter};
+
+use crate::arch::Instruction;
+use crate::dwarf::DebugInfo;
+use crate::emitter::is_noise;
+use crate::function::Function;
+use crate::memory::memory::Memory;
+use crate::rtti::display_name;
+use crate::symbols::SymbolTable;
+use crate::xrefs::XrefDb;
+
+/// Number of instruction bytes shown before eliding the rest.
+const MAX_BYTES: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AsmSyntax {
+    Intel,
+    Att,
+    Nasm,
+    Masm,
+}
+
+impl AsmSyntax {
+    /// Creates a formatter for this syntax which prints known names in place of addresses.
+    pub fn formatter(self, symbols: &SymbolTable) -> Box<dyn Formatter> {
+        let resolver = Some(Box::new(symbols.clone()) as Box<_>);
+        match self {
+            AsmSyntax::Intel => Box::new(IntelFormatter::with_options(resolver, None)),
+            AsmSyntax::Att => Box::new(GasFormatter::with_options(resolver, None)),
+            AsmSyntax::Nasm => Box::new(NasmFormatter::with_options(resolver, None)),
+            AsmSyntax::Masm => Box::new(MasmFormatter::with_options(resolver, None)),
+        }
+    }
+}
+
+/// Writes an objdump-like listing of `function`, enriched with names, strings,
+/// cross-references, source lines and the statement lifted from each instruction.
+pub fn write_listing(
+    out: &mut dyn Write,
+    function: &Function,
+    memory: &Memory,
+    symbols: &SymbolTable,
+    debug: &DebugInfo,
+    xrefs: &XrefDb,
+    formatter: &mut dyn Formatter,
+) -> Result {
+    out.write_fmt(format_args!("{}:\n", function.name))?;
+    if let Some(prototype) = &function.prototype {
+        let name = display_name(&function.name);
+        out.write_fmt(format_args!("{:>34}; {}\n", "", prototype.declare(&name)))?;
+    }
+
+    // the line tables have a row per statement and per expression within it
+    let mut last_source = None;
+    for block in function.cfg.blocks.values() {
+        if block.start != function.address {
+            out.write_fmt(format_args!("LAB_{:X}:\n", block.start))?;
+        }
+        let refs = xrefs.to(block.start);
+        if !refs.is_empty() {
+            let refs = refs
+                .iter()
+                .map(|xref| format!("{:X}({})", xref.from, xref.kind))
+                .collect::<Vec<_>>();
+            out.write_fmt(format_args!(
+                "{:>34}; XREF[{}]: {}\n",
+                "",
+                refs.len(),
+                refs.join(", ")
+            ))?;
+        }
+
+        for (inst, expr) in block.instructions.iter().zip(block.statements.iter()) {
+            if let Some(source) = debug.line(inst.ip()) {
+                if last_source != Some(source) {
+                    out.write_fmt(format_args!("{:>34}; {}\n", "", source))?;
+                }
+                last_source = Some(source);
+            }
+            let mut bytes = String::new();
+            let code = memory.get(inst.ip()).unwrap_or_default();
+            for byte in code.iter().take(inst.len().min(MAX_BYTES)) {
+                write!(&mut bytes, "{:02x} ", byte)?;
+            }
+            if inst.len() > MAX_BYTES {
+                bytes.push_str("..");
+            }
+
+            // the syntax only applies to x86, other architectures have a single one
+            let mut text = String::new();
+            match inst {
+                Instruction::X86(inst) => formatter.format(inst, &mut text),
+                _ => text = inst.to_string(),
+            }
+
+            let mut line = format!("{:>8X}  {:<26}{:<40}", inst.ip(), bytes, text);
+            if !is_noise(expr) {
+                write!(&mut line, "| {}", expr.named(symbols))?;
+            }
+            if let Some(xref) = xrefs.from(inst.ip()).first() {
+                if let Some(name) = symbols.get(xref.to) {
+                    write!(&mut line, "  ; {}", name)?;
+                } else if let Some(string) = memory.read_c_string(xref.to) {
+                    write!(&mut line, "  ; {:?}", string)?;
+                }
+            }
+            out.write_fmt(format_args!("{}\n", line.trim_end()))?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,42 +1,229 @@
-use crate::parser::to_expression;
-use ast::{Lit, Symbol};
-use iced_x86::Decoder;
-use memory::memory::Memory;
+use abi::{CallingConvention, Conventions};
+use arch::Architecture;
+use callgraph::CallGraph;
+use cli::{Command, Options};
+use constprop::Constants;
+use dwarf::DebugInfo;
+use function::Function;
+use json::Document;
 use object::Object;
+use prototypes::PrototypeDb;
+use rtti::Classes;
+use symbols::SymbolTable;
+use tls::ThreadLocals;
+use types::{Prototype, Type};
+use unwind::UnwindTable;
+use xrefs::XrefDb;
+use memory::memory::Memory;
 use rangemap::RangeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::fs;
+use std::path::Path;
+mod aarch64;
+mod aarch64_parser;
+mod abi;
+mod arch;
 mod ast;
+mod callgraph;
+mod cfg;
+mod cli;
+mod constprop;
+mod dot;
+mod dwarf;
+mod emitter;
+mod function;
+mod hardening;
+mod idioms;
+mod json;
+mod listing;
+mod noreturn;
 mod parser;
+mod prototypes;
+mod riscv;
+mod riscv_parser;
+mod rtti;
+mod signatures;
+mod slots;
+mod sse_parser;
+mod symbols;
+mod tls;
+mod types;
+mod unwind;
+mod vsa;
+mod xrefs;
 
 mod memory;
 
-/// Reads a file and displays the name of each section.
+/// Reads a file and prints the decompiled functions.
 fn main() -> Result<(), Box<dyn Error>> {
-    let binary_data = fs::read("assets/test")?;
-    let file = object::File::parse(&*binary_data)?;
-
-    let entrypoint = file.entry();
-    println!("Entrypoint: 0x{:X}", entrypoint);
+    let options = Options::from_args()?;
 
-    let data = &binary_data[(entrypoint as usize)..];
+    let binary_data = fs::read(&options.path)?;
+    // the settings changing what is lifted key the saved index along with the file
+    let index_path = options.xref_db.as_ref().map(|dir| {
+        let settings = format!(
+            "{:?} {} {:?} {} {:?} {:?} {:?} {:?} {:?}",
+            options.base,
+            options.raw,
+            options.arch,
+            options.bits,
+            options.regions,
+            options.entries,
+            options.functions,
+            options.convention,
+            options.headers
+        );
+        XrefDb::path(Path::new(dir), &binary_data, &settings)
+    });
+    let (slice_offset, binary_data) = if options.raw {
+        (0, &binary_data[..])
+    } else {
+        memory::memory::select_slice(&binary_data)
+            .map_err(|err| format!("{err} (use --raw to load a headerless image)"))?
+    };
+    let file = if options.raw {
+        None
+    } else {
+        Some(object::File::parse(binary_data)?)
+    };
 
-    println!("Data: {:x?} 0x{:x}", &data[0..20], binary_data.len());
-
-    let mut functions = Vec::<u64>::new();
-    let mut stack = Vec::<Lit>::new();
-    let mut symbols = Vec::<Symbol>::new();
+    let mut functions = options.functions.clone();
 
     let _vm_mappings = RangeMap::<u64, u64>::new();
 
-    let _memory = Memory::from_binary(&binary_data);
+    let mut base = options.base;
+    let mut memory = match &file {
+        Some(file) => Memory::load(file, binary_data, options.base)?,
+        None => {
+            let architecture = options.arch.unwrap_or(Architecture::X86 {
+                bitness: options.bits,
+            });
+            let default = Memory::default_raw_base(binary_data, architecture);
+            Memory::raw(binary_data, *base.get_or_insert(default), architecture)?
+        }
+    };
+    for region in options.regions.iter() {
+        memory.add_region(region.clone());
+    }
     // dbg!(&memory);
 
-    let _sect = file.section_by_name(".eh_frame");
-    // dbg!(sect);
+    let mut entrypoints = file.iter().map(|file| memory.entry(file)).collect::<Vec<_>>();
+    entrypoints.extend(options.entries.iter().copied());
+    if entrypoints.is_empty() {
+        // a raw image is expected to start with code
+        entrypoints.push(base.unwrap_or(0));
+    }
+    let entrypoint = entrypoints[0];
+
+    match options.command {
+        Command::Hexdump => {
+            let mut out = String::new();
+            memory.hexdump(&mut out, &options.hexdump)?;
+            print!("{}", out);
+            return Ok(());
+        }
+        Command::Sections => {
+            println!(
+                "{:<20} {:<24} {:<24} {:>10} Perm",
+                "Name", "Virtual range", "File range", "Size"
+            );
+            for (range, section) in memory.sections() {
+                // the offsets within a slice of a universal binary are relative to it
+                let file_range = match section.file_range() {
+                    Some((offset, size)) => {
+                        let offset = slice_offset + offset;
+                        format!("{:X}-{:X}", offset, offset + size)
+                    }
+                    None => "-".to_string(),
+                };
+                println!(
+                    "{:<20} {:<24} {:<24} {:>10X} {}",
+                    section.name(),
+                    format!("{:X}-{:X}", range.start, range.end),
+                    file_range,
+                    range.end - range.start,
+                    section.permissions()
+                );
+            }
+            return Ok(());
+        }
+        Command::Decompile | Command::Xrefs => {}
+    }
+
+    if let Some(file) = &file {
+        if Architecture::for_file(file).is_none() {
+            return Err(format!("cannot decompile {:?} code", file.architecture()).into());
+        }
+    }
+
+    if options.command == Command::Decompile {
+        for entry in entrypoints.iter() {
+            println!("Entrypoint: 0x{:X}", entry);
+        }
+    }
+
+    let mut symbols = file
+        .as_ref()
+        .map(|file| SymbolTable::from_file(file, &memory))
+        .unwrap_or_default();
+    let debug = file
+        .as_ref()
+        .map(|file| DebugInfo::from_file(file, &memory))
+        .unwrap_or_default();
+    for (addr, name) in debug.functions() {
+        symbols.insert_function(addr, name);
+    }
+    let convention = options.convention.unwrap_or_else(|| match &file {
+        Some(file) => CallingConvention::for_file(file),
+        None => CallingConvention::for_bitness(memory.bitness()),
+    });
+    let unwind = file
+        .as_ref()
+        .map(|file| UnwindTable::from_file(file, &memory))
+        .unwrap_or_default();
+    let tls = file
+        .as_ref()
+        .map(|file| ThreadLocals::from_file(file, &memory))
+        .unwrap_or_default();
+    // virtual functions are decompiled even when no symbol names them
+    let mut classes = file
+        .as_ref()
+        .map(|file| Classes::from_file(file, &memory, &symbols))
+        .unwrap_or_default();
+    classes.name_functions(&mut symbols);
 
-    // functions.push(file.entry());
-    functions.push(0x1161u64);
+    // the references saved by an earlier run answer without lifting anything
+    let saved = index_path.as_deref().and_then(XrefDb::load);
+    if let (Some(target), Some(xrefs)) = (&options.target, &saved) {
+        return print_references(target, xrefs, &symbols, &memory);
+    }
+
+    // LP64 on Unix-like systems, LLP64 on 64-bit Windows and ILP32 elsewhere
+    let long_size = if convention == CallingConvention::SysV { 8 } else { 4 };
+    let mut prototypes = PrototypeDb::new(long_size, u64::from(memory.bitness() / 8));
+    for path in options.headers.iter() {
+        let source = fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+        prototypes.add_header(path, &source);
+    }
+    prototypes.add_types(debug.types());
+    for (name, prototype) in debug.prototypes() {
+        prototypes.insert(name, prototype.clone());
+    }
+    if functions.is_empty() {
+        for entry in entrypoints.iter() {
+            if !functions.contains(entry) {
+                functions.push(*entry);
+            }
+        }
+        functions.extend(symbols.functions().filter(|addr| !entrypoints.contains(addr)));
+        // stripped binaries keep the FDEs of their functions for unwinding
+        for addr in unwind.functions() {
+            if memory.is_executable(addr) && !functions.contains(&addr) {
+                functions.push(addr);
+            }
+        }
+    }
 
     // dbg!(&file.symbol_map());
     // dbg!(&file.symbol_table());
@@ -51,34 +238,234 @@ fn main() -> Result<(), Box<dyn Error>> {
     // dbg!(&file.dynamic_symbols());
     // dbg!(&file.has_debug_symbols());
 
-    while let Some(func_start) = functions.pop() {
-        println!("\nDebugging function at 0x{:X}", &func_start);
+    // jumps to any of these are tail calls
+    let starts = entrypoints
+        .iter()
+        .copied()
+        .chain(symbols.functions())
+        .chain(unwind.functions())
+        .chain(functions.iter().copied())
+        .collect::<BTreeSet<_>>();
+    let noreturn =
+        noreturn::find_noreturn(&memory, &unwind, &symbols, &prototypes, &functions, &starts);
 
-        let mut decoder =
-            Decoder::with_ip(64, &binary_data[(func_start as usize)..], func_start, 0);
+    let mut lifted = Vec::<Function>::new();
+    let mut seen = functions.clone();
+    let mut conventions = Conventions::new(convention);
 
-        while decoder.can_decode() {
-            let inst = decoder.decode();
-            let expr = to_expression(&mut stack, &mut symbols, inst);
+    while let Some(func_start) = functions.pop() {
+        let mut function = Function::lift(
+            &memory,
+            &unwind,
+            func_start,
+            &conventions,
+            &noreturn,
+            &starts,
+            &classes,
+        );
+        function.name = symbols.function_name(func_start);
 
-            if inst.code() == iced_x86::Code::Aadd_m32_r32 {
-                dbg!(&inst);
-                println!("0x{:X}", &inst.memory_displacement64());
+        for func in function.code_references() {
+            if memory.is_executable(func) && !seen.contains(&func) {
+                seen.push(func);
+                functions.push(func);
             }
-            println!("{} {:?}", &expr, &stack);
+        }
 
-            // if let ast::ExprKind::Call(func, _) = expr.kind {
-            //     functions.push(func);
-            // }
+        lifted.push(function);
+    }
 
-            if inst.code() == iced_x86::Code::Retnq {
-                break;
+    // the convention given, or that of the file, is only the default: each function follows
+    // the one its code tells, and its callers are lifted again to pass the arguments there
+    for function in lifted.iter() {
+        conventions.insert(function.address, signatures::convention(function, convention));
+    }
+    if !conventions.is_uniform() {
+        for function in lifted.iter_mut() {
+            let name = std::mem::take(&mut function.name);
+            *function = Function::lift(
+                &memory,
+                &unwind,
+                function.address,
+                &conventions,
+                &noreturn,
+                &starts,
+                &classes,
+            );
+            function.name = name;
+        }
+    }
+
+    lifted.sort_by_key(|function| function.address);
+
+    // callees come first, so the prototypes inferred for them are known to their callers
+    let graph = CallGraph::build(&lifted);
+    let sccs = graph.bottom_up();
+    let index = lifted
+        .iter()
+        .enumerate()
+        .map(|(i, function)| (function.address, i))
+        .collect::<BTreeMap<_, _>>();
+    // the leaves leaving some argument registers as they were, for the calls after them
+    for function in lifted.iter() {
+        if let Some(clobbers) = signatures::clobbers(function, &conventions) {
+            prototypes.insert_clobbers(function.address, clobbers);
+        }
+    }
+    for scc in sccs.iter() {
+        let mut unknown = Vec::new();
+        for addr in scc.iter() {
+            let function = &mut lifted[index[addr]];
+            let handlers = hardening::failure_handlers(function, &symbols);
+            function.rewrite_idioms(&memory, &conventions, &handlers);
+            debug.apply(function, &unwind);
+            tls.apply(function);
+            prototypes.apply(function, &symbols, &memory, &conventions);
+            hardening::hide(function, &symbols, &memory);
+            if function.prototype.is_none() {
+                unknown.push(*addr);
             }
+        }
 
-            if inst.code() == iced_x86::Code::Hlt {
+        // recursive functions pass their parameters and results to each other, so the
+        // prototypes of those calling each other are inferred again until none changes
+        let recursive = graph.is_recursive(scc);
+        for _ in 0..=scc.len() {
+            let mut changed = false;
+            for addr in unknown.iter() {
+                let function = &lifted[index[addr]];
+                let pending = unknown
+                    .iter()
+                    .copied()
+                    .filter(|other| prototypes.prototype_at(*other, &symbols).is_none())
+                    .collect::<Vec<_>>();
+                let Some(prototype) = signatures::infer(
+                    function,
+                    &pending,
+                    &prototypes,
+                    &symbols,
+                    &conventions,
+                    memory.architecture(),
+                ) else {
+                    continue;
+                };
+                if prototypes.prototype_at(*addr, &symbols) != Some(&prototype) {
+                    prototypes.insert_inferred(*addr, prototype);
+                    changed = true;
+                }
+            }
+            if !changed || !recursive {
                 break;
             }
         }
+        if recursive && !unknown.is_empty() {
+            // the calls within the cycle were lifted before their callees were inferred
+            for addr in scc.iter() {
+                prototypes.apply(&mut lifted[index[addr]], &symbols, &memory, &conventions);
+            }
+        }
+
+        for addr in scc.iter() {
+            let function = &mut lifted[index[addr]];
+            if function.prototype.is_none() {
+                function.prototype = prototypes.prototype_at(*addr, &symbols).cloned();
+            }
+            prototypes.apply_returns(function, &memory, &conventions);
+            if noreturn.contains(addr) {
+                function
+                    .prototype
+                    .get_or_insert_with(|| Prototype {
+                        return_type: Type::Void,
+                        parameters: Vec::new(),
+                        variadic: false,
+                        noreturn: true,
+                    })
+                    .noreturn = true;
+            }
+        }
+    }
+
+    // constants are returned up the call graph and passed down it
+    let mut constants = Constants::new(&lifted, &entrypoints, &memory, &unwind, &conventions);
+    for addr in sccs.iter().flatten() {
+        constants.add_returns(&lifted[index[addr]]);
+    }
+    for addr in sccs.iter().rev().flatten() {
+        constants.propagate(&mut lifted[index[addr]], &prototypes, &symbols, &memory);
+        hardening::unfortify(&mut lifted[index[addr]], &symbols, &prototypes);
+    }
+
+    // the calls resolved by the constants are edges too
+    let graph = CallGraph::build(&lifted);
+    let mut xrefs = XrefDb::default();
+    for function in lifted.iter() {
+        xrefs.add_function(function, &memory);
+    }
+
+    if let Some(path) = &index_path {
+        xrefs
+            .save(path)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+    }
+
+    if let Some(target) = &options.target {
+        return print_references(target, &xrefs, &symbols, &memory);
+    }
+
+    if options.listing {
+        let mut formatter = options.asm_syntax.formatter(&symbols);
+        for function in lifted.iter() {
+            let mut out = String::new();
+            listing::write_listing(
+                &mut out,
+                function,
+                &memory,
+                &symbols,
+                &debug,
+                &xrefs,
+                &mut *formatter,
+            )?;
+            println!("\n{}", out.trim_end());
+        }
+    } else {
+        let prototypes = lifted
+            .iter()
+            .filter_map(|function| Some((function.address, function.prototype.clone()?)))
+            .collect();
+        let class_defs = classes.type_defs(&symbols, &prototypes);
+        let emitter = options.backend.emitter(&symbols, memory.architecture(), &conventions);
+        if !debug.types().is_empty() || !class_defs.is_empty() {
+            let mut out = String::new();
+            for def in debug.types().iter().chain(class_defs.iter()) {
+                out.push('\n');
+                emitter.emit_type(&mut out, def)?;
+            }
+            print!("{}", out);
+        }
+        for function in lifted.iter() {
+            println!("\nDebugging function at 0x{:X}", &function.address);
+            let mut out = String::new();
+            emitter.emit_function(&mut out, function)?;
+            print!("{}", out);
+        }
+    }
+
+    if let Some(path) = &options.json {
+        Document::new(&options.path, entrypoint, &memory, &unwind, &debug, &lifted, &xrefs)
+            .write_to(path)?;
+    }
+
+    if let Some(dir) = &options.dot {
+        let dir = Path::new(dir);
+        fs::create_dir_all(dir)?;
+        for function in lifted.iter() {
+            let mut out = String::new();
+            dot::write_cfg(&mut out, function, options.dot_labels, &symbols)?;
+            fs::write(dir.join(format!("{}.dot", function.name)), out)?;
+        }
+        let mut out = String::new();
+        dot::write_call_graph(&mut out, &lifted, &graph, &symbols)?;
+        fs::write(dir.join("callgraph.dot"), out)?;
     }
 
     Ok(())
@@ -108,3 +495,20 @@ fn main() -> Result<(), Box<dyn Error>> {
 //                     /* WARNING: Do nothing block with infinite loop */
 //   } while( true );
 // }
+
+/// Prints the references to `target`, a name or a hexadecimal address.
+fn print_references(
+    target: &str,
+    xrefs: &XrefDb,
+    symbols: &SymbolTable,
+    memory: &Memory,
+) -> Result<(), Box<dyn Error>> {
+    let addr = match symbols.lookup_function(target) {
+        Some(addr) => addr,
+        None => cli::parse_address(target).map_err(|_| format!("no symbol or address `{target}`"))?,
+    };
+    let mut out = String::new();
+    xrefs::write_references(&mut out, xrefs, addr, symbols, memory)?;
+    print!("{}", out);
+    Ok(())
+}
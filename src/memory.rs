@@ -1,10 +1,20 @@
 #[allow(dead_code)]
+#[allow(clippy::module_inception)]
 pub mod memory {
-    use std::fmt::{Debug, Write};
+    use std::error::Error;
+    use std::fmt::{Debug, Display, Write};
+    use std::ops::Range;
 
-    use object::{Object, ObjectSection};
+    use object::read::macho::{FatArch, MachOFatFile32, MachOFatFile64};
+    use object::read::pe;
+    use object::{
+        elf, macho, BinaryFormat, FileKind, Object, ObjectSection, ObjectSegment, SectionFlags,
+        SectionKind,
+    };
     use rangemap::RangeMap;
 
+    use crate::arch::Architecture;
+
     #[derive(Clone, Copy)]
     pub struct VirtualMemory(u64);
 
@@ -20,30 +30,238 @@ pub mod memory {
 
     pub struct Memory {
         sections: RangeMap<usize, Section>,
+        delta: u64,
+        architecture: Architecture,
     }
 
     #[derive(Clone, PartialEq, Eq)]
     pub struct Section {
         name: String,
+        kind: SectionKind,
+        file_range: Option<(u64, u64)>,
+        permissions: Permissions,
         block: Vec<u8>,
     }
 
+    impl Section {
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+
+        /// Returns the offset and size of the section contents in the file, if any.
+        pub fn file_range(&self) -> Option<(u64, u64)> {
+            self.file_range
+        }
+
+        pub fn permissions(&self) -> Permissions {
+            self.permissions
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct Permissions {
+        pub read: bool,
+        pub write: bool,
+        pub execute: bool,
+    }
+
+    impl Permissions {
+        fn of(section: &object::Section) -> Self {
+            match section.flags() {
+                SectionFlags::Elf { sh_flags } => Self {
+                    read: true,
+                    write: sh_flags & u64::from(elf::SHF_WRITE) != 0,
+                    execute: sh_flags & u64::from(elf::SHF_EXECINSTR) != 0,
+                },
+                SectionFlags::Coff { characteristics } => Self {
+                    read: characteristics & object::pe::IMAGE_SCN_MEM_READ != 0,
+                    write: characteristics & object::pe::IMAGE_SCN_MEM_WRITE != 0,
+                    execute: characteristics & object::pe::IMAGE_SCN_MEM_EXECUTE != 0,
+                },
+                // Mach-O sections only say whether they hold code, the rest comes from the segment
+                SectionFlags::MachO { flags } => Self {
+                    read: true,
+                    write: section
+                        .segment_name()
+                        .ok()
+                        .flatten()
+                        .is_some_and(|segment| segment.starts_with("__DATA")),
+                    execute: flags
+                        & (macho::S_ATTR_PURE_INSTRUCTIONS | macho::S_ATTR_SOME_INSTRUCTIONS)
+                        != 0,
+                },
+                _ => Self {
+                    read: true,
+                    write: matches!(
+                        section.kind(),
+                        SectionKind::Data | SectionKind::UninitializedData | SectionKind::Tls
+                    ),
+                    execute: section.kind() == SectionKind::Text,
+                },
+            }
+        }
+    }
+
+    impl Display for Permissions {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(if self.read { "r" } else { "-" })?;
+            f.write_str(if self.write { "w" } else { "-" })?;
+            f.write_str(if self.execute { "x" } else { "-" })
+        }
+    }
+
+    /// Selects what a hex dump shows and how.
+    pub struct HexdumpOptions {
+        pub range: Option<Range<u64>>,
+        pub sections: Vec<String>,
+        pub width: usize,
+        pub group: usize,
+    }
+
+    impl Default for HexdumpOptions {
+        fn default() -> Self {
+            Self {
+                range: None,
+                sections: Vec::new(),
+                width: 0x10,
+                group: 4,
+            }
+        }
+    }
+
     impl Memory {
         pub fn new() -> Self {
             Self {
                 sections: RangeMap::new(),
+                delta: 0,
+                architecture: Architecture::X86 { bitness: 64 },
+            }
+        }
+
+        /// Returns the address a headerless image is mapped at without `--base`: 0x7C00, where
+        /// the BIOS loads it, for a 16-bit boot sector of 512 bytes ending with the `55 AA`
+        /// signature, and 0 for any other.
+        pub fn default_raw_base(binary_data: &[u8], architecture: Architecture) -> u64 {
+            let boot = architecture == Architecture::X86 { bitness: 16 }
+                && binary_data.len() == 512
+                && binary_data.ends_with(&[0x55, 0xAA]);
+            if boot {
+                0x7C00
+            } else {
+                0
+            }
+        }
+
+        /// Maps a headerless image such as shellcode or a firmware dump at `base`.
+        ///
+        /// Nothing is known about its layout, so the whole file is readable, writable and
+        /// executable, and decoded as `architecture` code. Fails when the image does not fit
+        /// in the address space above `base`.
+        pub fn raw(
+            binary_data: &[u8],
+            base: u64,
+            architecture: Architecture,
+        ) -> Result<Self, Box<dyn Error>> {
+            let mut memory = Self {
+                architecture,
+                ..Self::new()
+            };
+            if !binary_data.is_empty() {
+                let end = base.checked_add(binary_data.len() as u64).ok_or_else(|| {
+                    format!(
+                        "an image of {:#x} bytes does not fit in the address space at {:#x}",
+                        binary_data.len(),
+                        base
+                    )
+                })?;
+                let section = Section {
+                    name: "raw".to_string(),
+                    kind: SectionKind::Text,
+                    file_range: Some((0, binary_data.len() as u64)),
+                    permissions: Permissions {
+                        read: true,
+                        write: true,
+                        execute: true,
+                    },
+                    block: binary_data.to_vec(),
+                };
+                memory
+                    .sections
+                    .insert(base as usize..end as usize, section);
+            }
+            Ok(memory)
+        }
+
+        /// Maps a zero-filled, writable region, e.g. the RAM of a firmware image.
+        ///
+        /// Sections already mapped in `range` are left untouched.
+        pub fn add_region(&mut self, range: Range<u64>) {
+            let range = range.start as usize..range.end as usize;
+            let gaps = self.sections.gaps(&range).collect::<Vec<_>>();
+            for gap in gaps {
+                let section = Section {
+                    name: format!("region_{:X}", range.start),
+                    kind: SectionKind::UninitializedData,
+                    file_range: None,
+                    permissions: Permissions {
+                        read: true,
+                        write: true,
+                        execute: false,
+                    },
+                    block: vec![0; gap.len()],
+                };
+                self.sections.insert(gap, section);
             }
         }
 
         pub fn from_binary(binary_data: &[u8]) -> Self {
             let file = object::File::parse(binary_data).unwrap();
+            Self::load(&file, binary_data, None).unwrap()
+        }
 
-            let mut sections = RangeMap::new();
+        /// Maps the sections of `file`, moving the image to `base` when given.
+        ///
+        /// Rebasing a PE image applies its base relocations, like the Windows loader does.
+        /// Fails when a section does not fit in the address space once moved.
+        pub fn load(
+            file: &object::File,
+            binary_data: &[u8],
+            base: Option<u64>,
+        ) -> Result<Self, Box<dyn Error>> {
+            let delta = base
+                .map(|base| base.wrapping_sub(file.relative_address_base()))
+                .unwrap_or(0);
+
+            let mut sections = Vec::new();
 
             for section in file.sections() {
-                let start = section.address() as usize;
                 let size = section.size() as usize;
-                let end = start + size;
+                if size == 0 {
+                    continue;
+                }
+                let start = match base {
+                    Some(base) => section
+                        .address()
+                        .checked_sub(file.relative_address_base())
+                        .and_then(|offset| base.checked_add(offset)),
+                    None => Some(section.address()),
+                };
+                let end = start.and_then(|start| start.checked_add(size as u64));
+                let (Some(start), Some(end)) = (start, end) else {
+                    return Err(format!(
+                        "section {} does not fit in the address space at base {:#x}",
+                        section.name().unwrap_or("?"),
+                        base.unwrap_or(0)
+                    )
+                    .into());
+                };
+                let (start, end) = (start as usize, end as usize);
+                // Sections such as .comment or .symtab are not loaded in memory
+                if let SectionFlags::Elf { sh_flags } = section.flags() {
+                    if sh_flags & u64::from(elf::SHF_ALLOC) == 0 {
+                        continue;
+                    }
+                }
 
                 let block = if let Some((file_ptr, offset)) = section.file_range() {
                     let slice = &binary_data[file_ptr as usize..(file_ptr + offset) as usize];
@@ -58,51 +276,306 @@ pub mod memory {
 
                 let section = Section {
                     name: section.name().unwrap().to_string(),
+                    kind: section.kind(),
+                    file_range: section.file_range(),
+                    permissions: Permissions::of(&section),
                     block,
                 };
 
-                sections.insert(start..end, section);
+                sections.push((start..end, section));
+            }
+
+            if delta != 0 {
+                let relocated = match file {
+                    object::File::Pe32(pe) => apply_base_relocations(pe, &mut sections, delta),
+                    object::File::Pe64(pe) => apply_base_relocations(pe, &mut sections, delta),
+                    _ => Ok(()),
+                };
+                if let Err(err) = relocated {
+                    eprintln!("warning: cannot apply base relocations: {}", err);
+                }
             }
 
-            Self { sections }
+            Ok(Self {
+                sections: sections.into_iter().collect(),
+                delta,
+                architecture: Architecture::for_file(file).unwrap_or(Architecture::X86 {
+                    bitness: if file.is_64() { 64 } else { 32 },
+                }),
+            })
         }
-    }
 
-    impl Debug for Memory {
-        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            f.write_str("\n").unwrap();
-            for (range, section) in self.sections.clone().into_iter() {
-                f.write_fmt(format_args!("Contents of section {}:\n", section.name))
-                    .unwrap();
+        /// Returns the address of the entry point of `file`, as mapped.
+        pub fn entry(&self, file: &object::File) -> u64 {
+            let entry = match file.format() {
+                // LC_MAIN stores a file offset rather than an address
+                BinaryFormat::MachO => file
+                    .segments()
+                    .find_map(|segment| {
+                        let (offset, size) = segment.file_range();
+                        (offset..offset + size)
+                            .contains(&file.entry())
+                            .then(|| segment.address() + file.entry() - offset)
+                    })
+                    .unwrap_or(0),
+                _ => file.entry(),
+            };
+            self.rebase(entry)
+        }
 
-                let mut addr = range.start;
-                for data in section.block.chunks(0x10) {
+        /// Translates an address read from the file to where the image is mapped.
+        pub fn rebase(&self, addr: u64) -> u64 {
+            addr.wrapping_add(self.delta)
+        }
+
+        /// Returns the instruction set the code is decoded with.
+        pub fn architecture(&self) -> Architecture {
+            self.architecture
+        }
+
+        /// Returns the mode the code is decoded in: 16, 32 or 64-bit.
+        pub fn bitness(&self) -> u32 {
+            self.architecture.bitness()
+        }
+
+        /// Iterates over the sections, ordered by virtual address.
+        pub fn sections(&self) -> impl Iterator<Item = (Range<u64>, &Section)> {
+            self.sections
+                .iter()
+                .map(|(range, section)| (range.start as u64..range.end as u64, section))
+        }
+
+        /// Writes an objdump-style hex dump of the sections selected by `options`.
+        pub fn hexdump(&self, f: &mut dyn Write, options: &HexdumpOptions) -> std::fmt::Result {
+            let width = options.width.max(1);
+            let group = options.group.max(1);
+            // two digits per byte, plus a space after each group
+            let columns = width * 2 + width.div_ceil(group);
+
+            for (range, section) in self.sections() {
+                if !options.sections.is_empty() && !options.sections.contains(&section.name) {
+                    continue;
+                }
+                let (start, end) = match &options.range {
+                    Some(wanted) => (range.start.max(wanted.start), range.end.min(wanted.end)),
+                    None => (range.start, range.end),
+                };
+                if start >= end {
+                    continue;
+                }
+
+                f.write_fmt(format_args!("Contents of section {}:\n", section.name))?;
+
+                let block =
+                    &section.block[(start - range.start) as usize..(end - range.start) as usize];
+                let mut addr = start;
+                for data in block.chunks(width) {
                     let mut s = String::new();
-                    let mut counter = 0;
-                    for byte in data.into_iter() {
-                        write!(&mut s, "{:02X}", byte).expect("Unable to write");
-                        if counter % 4 == 3 {
-                            write!(&mut s, " ").expect("Unable to write");
+                    for (counter, byte) in data.iter().enumerate() {
+                        write!(&mut s, "{:02X}", byte)?;
+                        if counter % group == group - 1 {
+                            write!(&mut s, " ")?;
                         }
-                        counter += 1;
                     }
                     let mut s2 = String::new();
-                    for byte in data.into_iter() {
+                    for byte in data.iter() {
                         let c = *byte as char;
                         if c.is_ascii_alphanumeric() || c.is_ascii_punctuation() || c == ' ' {
-                            write!(&mut s2, "{}", c).expect("Unable to write");
+                            write!(&mut s2, "{}", c)?;
                         } else {
-                            write!(&mut s2, ".").expect("Unable to write");
+                            write!(&mut s2, ".")?;
                         }
                     }
-                    f.write_fmt(format_args!("{:>5X} {:<36} {}\n", addr, s, s2))
-                        .unwrap();
-                    addr += 0x10;
+                    f.write_fmt(format_args!("{:>5X} {:<columns$} {}\n", addr, s, s2))?;
+                    addr += width as u64;
                 }
-                f.write_str("\n").unwrap();
+                f.write_str("\n")?;
             }
 
             Ok(())
         }
+
+        /// Returns the bytes mapped from `addr` up to the end of its section.
+        pub fn get(&self, addr: u64) -> Option<&[u8]> {
+            let (range, section) = self.sections.get_key_value(&(addr as usize))?;
+            Some(&section.block[addr as usize - range.start..])
+        }
+
+        /// Returns the virtual address range of the section called `name`.
+        pub fn section_range(&self, name: &str) -> Option<std::ops::Range<u64>> {
+            self.sections
+                .iter()
+                .find(|(_, section)| section.name == name)
+                .map(|(range, _)| range.start as u64..range.end as u64)
+        }
+
+        /// Reads the NUL-terminated string at `addr` if it is made of printable characters.
+        pub fn read_c_string(&self, addr: u64) -> Option<String> {
+            let bytes = self.get(addr)?;
+            let len = bytes.iter().position(|byte| *byte == 0)?;
+            let bytes = &bytes[..len];
+            if len == 0
+                || !bytes
+                    .iter()
+                    .all(|byte| byte.is_ascii_graphic() || b" \t\n\r".contains(byte))
+            {
+                return None;
+            }
+            Some(String::from_utf8_lossy(bytes).into_owned())
+        }
+
+        /// Returns `true` if `addr` belongs to a section holding code.
+        pub fn is_executable(&self, addr: u64) -> bool {
+            self.sections
+                .get(&(addr as usize))
+                .is_some_and(|section| section.permissions.execute)
+        }
+
+        /// Returns `true` if `addr` belongs to a section the program cannot write to, whose
+        /// contents are then known.
+        pub fn is_read_only(&self, addr: u64) -> bool {
+            self.sections
+                .get(&(addr as usize))
+                .is_some_and(|section| !section.permissions.write)
+        }
+    }
+
+    /// Returns the x86-64 slice of a Mach-O universal binary, or `data` itself for any other file,
+    /// with its offset in `data`, which the file offsets within the slice are relative to.
+    pub fn select_slice(data: &[u8]) -> Result<(u64, &[u8]), Box<dyn Error>> {
+        let slice = match FileKind::parse(data)? {
+            FileKind::MachOFat32 => MachOFatFile32::parse(data)?
+                .arches()
+                .iter()
+                .find(|arch| arch.cputype() == macho::CPU_TYPE_X86_64)
+                .map(|arch| Ok::<_, object::Error>((arch.file_range().0, arch.data(data)?))),
+            FileKind::MachOFat64 => MachOFatFile64::parse(data)?
+                .arches()
+                .iter()
+                .find(|arch| arch.cputype() == macho::CPU_TYPE_X86_64)
+                .map(|arch| Ok::<_, object::Error>((arch.file_range().0, arch.data(data)?))),
+            _ => return Ok((0, data)),
+        };
+        match slice {
+            Some(slice) => Ok(slice?),
+            None => Err("the universal binary has no x86-64 slice".into()),
+        }
+    }
+
+    /// Adds `delta` to every absolute address listed in the base relocation directory.
+    fn apply_base_relocations<Pe: pe::ImageNtHeaders>(
+        file: &pe::PeFile<Pe>,
+        sections: &mut [(Range<usize>, Section)],
+        delta: u64,
+    ) -> Result<(), Box<dyn Error>> {
+        let Some(mut blocks) = file
+            .data_directories()
+            .relocation_blocks(file.data(), &file.section_table())?
+        else {
+            return Ok(());
+        };
+
+        let image_base = file.relative_address_base().wrapping_add(delta) as usize;
+        while let Some(block) = blocks.next()? {
+            for relocation in block {
+                let addr = image_base
+                    .checked_add(relocation.virtual_address as usize)
+                    .ok_or("a base relocation is past the end of the address space")?;
+                let Some((range, section)) = sections
+                    .iter_mut()
+                    .find(|(range, _)| range.contains(&addr))
+                else {
+                    continue;
+                };
+                let offset = addr - range.start;
+                match relocation.typ {
+                    object::pe::IMAGE_REL_BASED_DIR64 if offset + 8 <= section.block.len() => {
+                        let bytes = &mut section.block[offset..offset + 8];
+                        let value = u64::from_le_bytes(bytes.try_into().unwrap());
+                        bytes.copy_from_slice(&value.wrapping_add(delta).to_le_bytes());
+                    }
+                    object::pe::IMAGE_REL_BASED_HIGHLOW if offset + 4 <= section.block.len() => {
+                        let bytes = &mut section.block[offset..offset + 4];
+                        let value = u32::from_le_bytes(bytes.try_into().unwrap());
+                        bytes.copy_from_slice(&value.wrapping_add(delta as u32).to_le_bytes());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    impl Debug for Memory {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("\n")?;
+            self.hexdump(f, &HexdumpOptions::default())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use object::{Object, ObjectSection};
+
+    use super::memory::{select_slice, Memory};
+    use crate::arch::Architecture;
+
+    #[test]
+    fn fat_slice_offsets() {
+        let data = std::fs::read("assets/test.fat").unwrap();
+        let (offset, slice) = select_slice(&data).unwrap();
+        assert_eq!(offset, 0x2000);
+        let file = object::File::parse(slice).unwrap();
+        let cstring = file.section_by_name("__cstring").unwrap();
+        let (start, _) = cstring.file_range().unwrap();
+        let start = (offset + start) as usize;
+        assert_eq!(&data[start..start + 13], b"Hello, world!");
+
+        let data = std::fs::read("assets/test.macho").unwrap();
+        assert_eq!(select_slice(&data).unwrap().0, 0);
+    }
+
+    #[test]
+    fn pe_base_relocations() {
+        let data = std::fs::read("assets/test.exe").unwrap();
+        let file = object::File::parse(&*data).unwrap();
+        let memory = Memory::load(&file, &data, None).unwrap();
+        assert_eq!(memory.get(0x1_4000_3000).unwrap()[..8], 0x1_4000_200Eu64.to_le_bytes());
+
+        let memory = Memory::load(&file, &data, Some(0x1000_0000)).unwrap();
+        assert_eq!(memory.entry(&file), 0x1000_1000);
+        assert_eq!(memory.get(0x1000_3000).unwrap()[..8], 0x1000_200Eu64.to_le_bytes());
+        assert_eq!(memory.read_c_string(0x1000_200E).as_deref(), Some("FUNZIONA?"));
+    }
+
+    #[test]
+    fn rebase_past_the_address_space() {
+        let data = std::fs::read("assets/test.exe").unwrap();
+        let file = object::File::parse(&*data).unwrap();
+        assert!(Memory::load(&file, &data, Some(0xFFFF_FFFF_FFFF_F000)).is_err());
+        let data = std::fs::read("assets/test").unwrap();
+        let file = object::File::parse(&*data).unwrap();
+        assert!(Memory::load(&file, &data, Some(0xFFFF_FFFF_FFFF_F000)).is_err());
+    }
+
+    #[test]
+    fn boot_sector_base() {
+        let data = std::fs::read("assets/test_boot.bin").unwrap();
+        let real = Architecture::X86 { bitness: 16 };
+        assert_eq!(Memory::default_raw_base(&data, real), 0x7C00);
+        let protected = Architecture::X86 { bitness: 32 };
+        assert_eq!(Memory::default_raw_base(&data, protected), 0);
+        assert_eq!(Memory::default_raw_base(&data[..510], real), 0);
+    }
+
+    #[test]
+    fn raw_image_past_the_address_space() {
+        let data = std::fs::read("assets/test_boot.bin").unwrap();
+        let real = Architecture::X86 { bitness: 16 };
+        assert!(Memory::raw(&data, 0xFFFF_FFFF_FFFF_FF00, real).is_err());
+        let memory = Memory::raw(&data, 0xFFFF_FFFF_FFFF_FD00, real).unwrap();
+        assert_eq!(memory.get(0xFFFF_FFFF_FFFF_FEFE).unwrap(), [0x55, 0xAA]);
     }
 }
@@ -0,0 +1,102 @@
+use std::collections::BTreeSet;
+
+use crate::arch::Flow;
+use crate::cfg::{is_terminator, Cfg};
+use crate::memory::memory::Memory;
+use crate::prototypes::PrototypeDb;
+use crate::symbols::SymbolTable;
+use crate::unwind::UnwindTable;
+
+/// Finds the functions which never return: the imports, stubs and functions the headers or
+/// the debug info declare so, like `exit@plt`, and the functions called from `roots` which
+/// reach no return once the calls to the others are known not to return either. Jumps to
+/// the functions in `starts` are tail calls.
+pub fn find_noreturn(
+    memory: &Memory,
+    unwind: &UnwindTable,
+    symbols: &SymbolTable,
+    prototypes: &PrototypeDb,
+    roots: &[u64],
+    starts: &BTreeSet<u64>,
+) -> BTreeSet<u64> {
+    let mut noreturn = symbols
+        .names()
+        .filter(|(_, name)| prototypes.is_noreturn(name))
+        .map(|(addr, _)| addr)
+        .collect::<BTreeSet<_>>();
+
+    // cutting the calls to a function found not to return may leave its callers without a
+    // return, so this runs until nothing changes
+    loop {
+        let mut changed = false;
+        let mut seen = roots.iter().copied().collect::<BTreeSet<_>>();
+        let mut worklist = roots.to_vec();
+        while let Some(entry) = worklist.pop() {
+            let cfg = Cfg::build(memory, entry, unwind.function_end(entry), &noreturn, starts);
+            for block in cfg.blocks.values() {
+                for inst in block.instructions.iter() {
+                    let Some(callee) = inst.branch_target() else {
+                        continue;
+                    };
+                    if (inst.flow() == Flow::Call || cfg.tail_calls.contains(&inst.ip()))
+                        && memory.is_executable(callee)
+                        && seen.insert(callee)
+                    {
+                        worklist.push(callee);
+                    }
+                }
+            }
+            if !noreturn.contains(&entry) && !may_return(&cfg, entry, &noreturn) {
+                noreturn.insert(entry);
+                changed = true;
+            }
+        }
+        if !changed {
+            return noreturn;
+        }
+    }
+}
+
+/// Returns `true` if a return may be reached from `entry`, without going through the
+/// functions in `noreturn`. Indirect jumps other than to those functions, and code which
+/// could not be decoded, are assumed to return.
+fn may_return(cfg: &Cfg, entry: u64, noreturn: &BTreeSet<u64>) -> bool {
+    let mut seen = BTreeSet::new();
+    let mut worklist = vec![entry];
+    while let Some(start) = worklist.pop() {
+        if !seen.insert(start) {
+            continue;
+        }
+        let Some(block) = cfg.blocks.get(&start) else {
+            return true;
+        };
+        let Some(last) = block.instructions.last() else {
+            continue;
+        };
+        let successors = cfg
+            .edges
+            .iter()
+            .filter(|edge| edge.from == start)
+            .map(|edge| edge.to)
+            .collect::<Vec<_>>();
+        match last.flow() {
+            Flow::Return => return true,
+            Flow::IndirectBranch if !last.slot().is_some_and(|slot| noreturn.contains(&slot)) => {
+                return true
+            }
+            // a tail call, which returns unless its callee never does, or decoding stopped
+            // at the branch target or before the end of the block
+            Flow::Branch
+                if successors.is_empty()
+                    && !last.branch_target().is_some_and(|to| noreturn.contains(&to)) =>
+            {
+                return true
+            }
+            _ if !is_terminator(last, noreturn) && successors.is_empty() => return true,
+            _ => {}
+        }
+        // a tail call to a function which never returns
+        worklist.extend(successors.into_iter().filter(|to| !noreturn.contains(to)));
+    }
+    false
+}
@@ -1,5 +1,9 @@
+use crate::arch;
 use crate::ast::*;
-use iced_x86::{Code, Instruction, OpKind};
+use crate::sse_parser;
+use iced_x86::{
+    Code, CodeSize, ConditionCode, FlowControl, Instruction, Mnemonic, OpKind, Register,
+};
 
 pub fn to_expression(
     _current_stack: &mut Vec<Lit>,
@@ -7,24 +11,42 @@ pub fn to_expression(
     inst: Instruction,
 ) -> Expr {
     match inst.code() {
-        iced_x86::Code::Push_r64 => {
+        iced_x86::Code::Push_r64 | Code::Push_r32 | Code::Push_r16 => {
             _current_stack.push(get_new_symbol_from_inst(symbols, inst, 0));
-            return Expr {
+            Expr {
                 id: 0,
                 kind: ExprKind::Lit(Lit::StillUnknown),
-            };
+            }
         },
-        iced_x86::Code::Call_rm64 => {
-            if inst.op0_kind() == OpKind::Memory {
-                return Expr {
-                    id: 0,
-                    kind: ExprKind::Call(inst.memory_displacement64(), Vec::new()),
-                };
-            } else {
-                todo!();
+        // 32 and 16-bit callers push the arguments, recovered when the call is reached
+        Code::Pushd_imm32 | Code::Pushd_imm8 | Code::Push_rm32 | Code::Push_imm16 | Code::Pushw_imm8
+        | Code::Push_rm16 => Expr {
+            id: 0,
+            kind: ExprKind::Lit(Lit::StillUnknown),
+        },
+        // calls through a register or a pointer are left unparsed until their target is
+        // known, only those through a GOT or IAT slot go to the import named after it
+        Code::Call_rm64 | Code::Call_rm32 | Code::Call_rm16
+            if arch::Instruction::X86(inst).slot().is_some() =>
+        {
+            Expr {
+                id: 0,
+                kind: ExprKind::Call(inst.memory_displacement64(), Vec::new()),
             }
         }
-        iced_x86::Code::Xor_rm32_r32 => {
+        iced_x86::Code::Call_rel32_64 | Code::Call_rel32_32 | Code::Call_rel16 => Expr {
+            id: 0,
+            kind: ExprKind::Call(inst.near_branch_target(), Vec::new()),
+        },
+        // real-mode far calls, to the linear address of segment:offset
+        Code::Call_ptr1616 => Expr {
+            id: 0,
+            kind: ExprKind::Call(
+                (u64::from(inst.far_branch_selector()) << 4) + u64::from(inst.far_branch16()),
+                Vec::new(),
+            ),
+        },
+        iced_x86::Code::Xor_rm32_r32 | Code::Xor_rm16_r16 => {
             if inst.op0_kind() == OpKind::Register
                 && inst.op1_kind() == OpKind::Register
                 && inst.op0_register() == inst.op1_register()
@@ -45,7 +67,7 @@ pub fn to_expression(
                     ),
                 };
             }
-            return Expr {
+            Expr {
                 id: 0,
                 kind: ExprKind::Binary(
                     BinOpKind::BitXor,
@@ -62,55 +84,393 @@ pub fn to_expression(
                         )),
                     }),
                 ),
-            };
+            }
+        }
+        Code::Mov_rm64_r64
+        | Code::Mov_rm32_r32
+        | Code::Mov_rm16_r16
+        | Code::Mov_rm8_r8
+        | Code::Mov_r64_rm64
+        | Code::Mov_r32_rm32
+        | Code::Mov_r16_rm16
+        | Code::Mov_r8_rm8
+        | Code::Mov_r32_imm32
+        | Code::Mov_r16_imm16
+        | Code::Mov_r8_imm8
+        | Code::Mov_r64_imm64
+        | Code::Mov_rm32_imm32
+        | Code::Mov_rm16_imm16
+        | Code::Mov_rm8_imm8
+        | Code::Mov_rm64_imm32
+        // the short forms with an absolute address, like `mov eax, fs:[0x30]` in i386 code
+        | Code::Mov_AL_moffs8
+        | Code::Mov_AX_moffs16
+        | Code::Mov_EAX_moffs32
+        | Code::Mov_RAX_moffs64
+        | Code::Mov_moffs8_AL
+        | Code::Mov_moffs16_AX
+        | Code::Mov_moffs32_EAX
+        | Code::Mov_moffs64_RAX => Expr {
+            id: 0,
+            kind: ExprKind::Assign(
+                Box::new(get_operand(symbols, inst, 0)),
+                Box::new(get_operand(symbols, inst, 1)),
+            ),
+        },
+        Code::Lea_r64_m | Code::Lea_r32_m | Code::Lea_r16_m => Expr {
+            id: 0,
+            kind: ExprKind::Assign(
+                Box::new(get_operand(symbols, inst, 0)),
+                Box::new(get_address(symbols, inst)),
+            ),
+        },
+        Code::And_rm64_imm8 => Expr {
+            id: 0,
+            kind: ExprKind::Assign(
+                Box::new(Expr {
+                    id: 0,
+                    kind: ExprKind::Lit(get_new_symbol_from_inst(symbols, inst, 0)),
+                }),
+                Box::new(Expr {
+                    id: 0,
+                    kind: ExprKind::Binary(
+                        BinOpKind::And,
+                        Box::new(Expr {
+                            id: 0,
+                            kind: ExprKind::Lit(get_new_symbol_from_inst(symbols, inst, 0)),
+                        }),
+                        Box::new(Expr {
+                            id: 0,
+                            kind: ExprKind::Lit(get_new_symbol_from_inst(symbols, inst, 1)),
+                        }),
+                    ),
+                }),
+            ),
+        },
+        Code::And_rm32_imm8 | Code::And_rm32_imm32 | Code::And_rm16_imm8 | Code::And_rm16_imm16 => {
+            get_compound_assign(symbols, inst, BinOpKind::And)
         }
-        Code::Mov_rm64_r64 => {
-            return Expr {
+        Code::Add_rm64_r64
+        | Code::Add_rm32_r32
+        | Code::Add_rm16_r16
+        | Code::Add_r64_rm64
+        | Code::Add_r32_rm32
+        | Code::Add_r16_rm16
+        | Code::Add_rm64_imm8
+        | Code::Add_rm64_imm32
+        | Code::Add_rm32_imm8
+        | Code::Add_rm32_imm32
+        | Code::Add_rm16_imm8
+        | Code::Add_rm16_imm16 => get_compound_assign(symbols, inst, BinOpKind::Add),
+        Code::Sub_rm64_r64
+        | Code::Sub_rm32_r32
+        | Code::Sub_rm16_r16
+        | Code::Sub_r64_rm64
+        | Code::Sub_r32_rm32
+        | Code::Sub_r16_rm16
+        | Code::Sub_rm64_imm8
+        | Code::Sub_rm64_imm32
+        | Code::Sub_rm32_imm8
+        | Code::Sub_rm32_imm32
+        | Code::Sub_rm16_imm8
+        | Code::Sub_rm16_imm16 => get_compound_assign(symbols, inst, BinOpKind::Sub),
+        // callee-cleanup conventions such as stdcall pop their arguments with `ret N`
+        Code::Retnq | Code::Retnd | Code::Retnw | Code::Retnq_imm16 | Code::Retnd_imm16 | Code::Retnw_imm16 => Expr {
+            id: 0,
+            kind: ExprKind::Ret(None),
+        },
+        Code::Hlt => Expr {
+            id: 0,
+            kind: ExprKind::Loop(Vec::new()),
+        },
+        // software interrupts, like the BIOS and DOS services, as calls to the vector
+        Code::Int_imm8 => Expr {
+            id: 0,
+            kind: ExprKind::Intrinsic("int".to_string(), vec![get_operand(symbols, inst, 0)]),
+        },
+        _ if inst.flow_control() == FlowControl::UnconditionalBranch && is_near_branch(&inst) =>
+        {
+            Expr {
                 id: 0,
-                kind: ExprKind::Assign(
-                    Box::new(Expr {
+                kind: ExprKind::Goto(inst.near_branch_target()),
+            }
+        }
+        _ if inst.flow_control() == FlowControl::ConditionalBranch && is_near_branch(&inst) =>
+        {
+            Expr {
+                id: 0,
+                kind: ExprKind::If(
+                    Box::new(get_condition(symbols, inst.condition_code())),
+                    vec![Expr {
                         id: 0,
-                        kind: ExprKind::Lit(get_new_symbol_from_inst(
-                            symbols, inst, 0,
-                        )),
-                    }),
+                        kind: ExprKind::Goto(inst.near_branch_target()),
+                    }],
+                ),
+            }
+        }
+        _ => sse_parser::to_expression(symbols, inst).unwrap_or(Expr {
+            id: 0,
+            kind: ExprKind::Unparsed(arch::Instruction::X86(inst)),
+        }),
+    }
+}
+
+fn is_near_branch(inst: &Instruction) -> bool {
+    matches!(
+        inst.op0_kind(),
+        OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64
+    )
+}
+
+pub fn get_flag(symbols: &mut Vec<Symbol>, name: &str) -> Expr {
+    let sym = Symbol::Reg(name.to_string());
+    symbols.push(sym.clone());
+    Expr {
+        id: 0,
+        kind: ExprKind::Lit(Lit::Symbol(sym)),
+    }
+}
+
+pub fn get_binary(op: BinOpKind, lhs: Expr, rhs: Expr) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)),
+    }
+}
+
+/// Lifts a jump to another function, which returns what the call returns.
+pub fn get_tail_call(target: u64) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Ret(Some(Box::new(Expr {
+            id: 0,
+            kind: ExprKind::Call(target, Vec::new()),
+        }))),
+    }
+}
+
+/// Negates a condition: `!expr`, unlike the bitwise `~` of `not`.
+pub fn get_not(expr: Expr) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Unary(UnOp::LogicalNot, Box::new(expr)),
+    }
+}
+
+/// Expresses a condition code in terms of the status flags it tests.
+fn get_condition(symbols: &mut Vec<Symbol>, cc: ConditionCode) -> Expr {
+    match cc {
+        ConditionCode::o => get_flag(symbols, "OF"),
+        ConditionCode::no => get_not(get_flag(symbols, "OF")),
+        ConditionCode::b => get_flag(symbols, "CF"),
+        ConditionCode::ae => get_not(get_flag(symbols, "CF")),
+        ConditionCode::e => get_flag(symbols, "ZF"),
+        ConditionCode::ne => get_not(get_flag(symbols, "ZF")),
+        ConditionCode::be => get_binary(
+            BinOpKind::Or,
+            get_flag(symbols, "CF"),
+            get_flag(symbols, "ZF"),
+        ),
+        ConditionCode::a => get_not(get_binary(
+            BinOpKind::Or,
+            get_flag(symbols, "CF"),
+            get_flag(symbols, "ZF"),
+        )),
+        ConditionCode::s => get_flag(symbols, "SF"),
+        ConditionCode::ns => get_not(get_flag(symbols, "SF")),
+        ConditionCode::p => get_flag(symbols, "PF"),
+        ConditionCode::np => get_not(get_flag(symbols, "PF")),
+        ConditionCode::l => get_binary(BinOpKind::Ne, get_flag(symbols, "SF"), get_flag(symbols, "OF")),
+        ConditionCode::ge => get_binary(BinOpKind::Eq, get_flag(symbols, "SF"), get_flag(symbols, "OF")),
+        ConditionCode::le => get_binary(
+            BinOpKind::Or,
+            get_flag(symbols, "ZF"),
+            get_binary(BinOpKind::Ne, get_flag(symbols, "SF"), get_flag(symbols, "OF")),
+        ),
+        ConditionCode::g => get_binary(
+            BinOpKind::And,
+            get_not(get_flag(symbols, "ZF")),
+            get_binary(BinOpKind::Eq, get_flag(symbols, "SF"), get_flag(symbols, "OF")),
+        ),
+        // loop/jrcxz and friends test a counter rather than the flags
+        ConditionCode::None => Expr {
+            id: 0,
+            kind: ExprKind::Lit(Lit::StillUnknown),
+        },
+    }
+}
+
+/// Lifts `op0 = op0 <op> op1`, as used by the two-operand arithmetic instructions.
+fn get_compound_assign(symbols: &mut Vec<Symbol>, inst: Instruction, op: BinOpKind) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Assign(
+            Box::new(get_operand(symbols, inst, 0)),
+            Box::new(Expr {
+                id: 0,
+                kind: ExprKind::Binary(
+                    op,
+                    Box::new(get_operand(symbols, inst, 0)),
+                    Box::new(get_operand(symbols, inst, 1)),
+                ),
+            }),
+        ),
+    }
+}
+
+/// Lifts operand `idx`, turning memory operands into a typed pointer dereference.
+pub fn get_operand(symbols: &mut Vec<Symbol>, inst: Instruction, idx: u32) -> Expr {
+    let kind = match inst.op_kind(idx) {
+        OpKind::Memory => ExprKind::Unary(
+            UnOp::Deref,
+            Box::new(Expr {
+                id: 0,
+                kind: ExprKind::Cast(
+                    Box::new(get_address(symbols, inst)),
+                    Ty::Ptr(Box::new(Ty::from_memory_size(inst.memory_size()))),
+                ),
+            }),
+        ),
+        OpKind::Immediate8 | OpKind::Immediate16 | OpKind::Immediate32 => {
+            ExprKind::Lit(Lit::U32(inst.immediate(idx) as u32))
+        }
+        OpKind::Immediate64 => ExprKind::Lit(Lit::U64(inst.immediate64())),
+        // sign-extended to the operand size, not further
+        OpKind::Immediate8to16 => ExprKind::Lit(Lit::U32(inst.immediate8to16() as u16 as u32)),
+        OpKind::Immediate8to32 => ExprKind::Lit(Lit::U32(inst.immediate8to32() as u32)),
+        OpKind::Immediate8to64 | OpKind::Immediate32to64 => {
+            ExprKind::Lit(Lit::I64(inst.immediate(idx) as i64))
+        }
+        _ => ExprKind::Lit(get_new_symbol_from_inst(symbols, inst, idx as usize)),
+    };
+    Expr { id: 0, kind }
+}
+
+/// Computes the effective address of the memory operand of `inst`.
+///
+/// In 16-bit code this is the real-mode linear address `segment * 0x10 + offset`. Elsewhere
+/// only FS and GS have a base, the thread-local storage or the Windows TEB, which is added
+/// to the offset as `@FS_BASE` or `@GS_BASE`.
+pub fn get_address(symbols: &mut Vec<Symbol>, inst: Instruction) -> Expr {
+    let offset = get_offset(symbols, inst);
+    if inst.code_size() != CodeSize::Code16 {
+        let base = match inst.memory_segment() {
+            // lea only computes the offset
+            _ if inst.mnemonic() == Mnemonic::Lea => return offset,
+            Register::FS => "FS_BASE",
+            Register::GS => "GS_BASE",
+            _ => return offset,
+        };
+        let base = Symbol::Reg(base.to_string());
+        symbols.push(base.clone());
+        let base = Expr {
+            id: 0,
+            kind: ExprKind::Lit(Lit::Symbol(base)),
+        };
+        // thread-local variables sit below the thread pointer
+        let displacement = match inst.code_size() {
+            CodeSize::Code32 => inst.memory_displacement32() as i32 as i64,
+            _ => inst.memory_displacement64() as i64,
+        };
+        return match offset.kind {
+            ExprKind::Lit(Lit::U64(0)) => base,
+            ExprKind::Lit(Lit::U64(_)) if displacement < 0 => {
+                let offset = Expr {
+                    id: 0,
+                    kind: ExprKind::Lit(Lit::I64(displacement.wrapping_neg())),
+                };
+                get_binary(BinOpKind::Sub, base, offset)
+            }
+            _ => get_binary(BinOpKind::Add, base, offset),
+        };
+    }
+    let segment = Expr {
+        id: 0,
+        kind: ExprKind::Lit(Lit::Symbol(get_new_reg_symbol(symbols, inst.memory_segment()))),
+    };
+    let segment = get_binary(
+        BinOpKind::Mul,
+        segment,
+        Expr {
+            id: 0,
+            kind: ExprKind::Lit(Lit::U32(0x10)),
+        },
+    );
+    get_binary(BinOpKind::Add, segment, offset)
+}
+
+/// Computes the offset of the memory operand of `inst` within its segment.
+fn get_offset(symbols: &mut Vec<Symbol>, inst: Instruction) -> Expr {
+    if inst.is_ip_rel_memory_operand() {
+        return Expr {
+            id: 0,
+            kind: ExprKind::Lit(Lit::U64(inst.ip_rel_memory_address())),
+        };
+    }
+
+    let mut address = None;
+    if inst.memory_base() != Register::None {
+        address = Some(Expr {
+            id: 0,
+            kind: ExprKind::Lit(Lit::Symbol(get_new_reg_symbol(symbols, inst.memory_base()))),
+        });
+    }
+    if inst.memory_index() != Register::None {
+        let mut index = Expr {
+            id: 0,
+            kind: ExprKind::Lit(Lit::Symbol(get_new_reg_symbol(symbols, inst.memory_index()))),
+        };
+        if inst.memory_index_scale() > 1 {
+            index = Expr {
+                id: 0,
+                kind: ExprKind::Binary(
+                    BinOpKind::Mul,
+                    Box::new(index),
                     Box::new(Expr {
                         id: 0,
-                        kind: ExprKind::Lit(get_new_symbol_from_inst(
-                            symbols, inst, 1,
-                        )),
+                        kind: ExprKind::Lit(Lit::U32(inst.memory_index_scale())),
                     }),
                 ),
             };
         }
-        Code::And_rm64_imm8 => {
-            return Expr {
+        address = Some(match address {
+            Some(base) => Expr {
                 id: 0,
-                kind: ExprKind::Assign(
-                    Box::new(Expr {
-                        id: 0,
-                        kind: ExprKind::Lit(get_new_symbol_from_inst(symbols, inst, 0)),
-                    }),
+                kind: ExprKind::Binary(BinOpKind::Add, Box::new(base), Box::new(index)),
+            },
+            None => index,
+        });
+    }
+
+    // the displacement wraps around at the address size
+    let displacement = match inst.memory_base().size().max(inst.memory_index().size()) {
+        2 => inst.memory_displacement32() as i16 as i64,
+        4 => inst.memory_displacement32() as i32 as i64,
+        _ => inst.memory_displacement64() as i64,
+    };
+    match address {
+        None => Expr {
+            id: 0,
+            kind: ExprKind::Lit(Lit::U64(displacement as u64)),
+        },
+        Some(address) if displacement == 0 => address,
+        Some(address) => {
+            let (op, value) = if displacement < 0 {
+                (BinOpKind::Sub, displacement.wrapping_neg())
+            } else {
+                (BinOpKind::Add, displacement)
+            };
+            Expr {
+                id: 0,
+                kind: ExprKind::Binary(
+                    op,
+                    Box::new(address),
                     Box::new(Expr {
                         id: 0,
-                        kind: ExprKind::Binary(
-                            BinOpKind::And,
-                            Box::new(Expr {
-                                id: 0,
-                                kind: ExprKind::Lit(get_new_symbol_from_inst(symbols, inst, 0)),
-                            }),
-                            Box::new(Expr {
-                                id: 0,
-                                kind: ExprKind::Lit(get_new_symbol_from_inst(symbols, inst, 1)),
-                            }),
-                        ),
+                        kind: ExprKind::Lit(Lit::I64(value)),
                     }),
                 ),
-            };
+            }
         }
-        _ => Expr {
-            id: 0,
-            kind: ExprKind::Unparsed(inst),
-        },
     }
 }
@@ -0,0 +1,1310 @@
+use std::collections::HashMap;
+
+use crate::abi::Conventions;
+use crate::arch::Architecture;
+use crate::ast::{
+    get_register_by_name, get_register_name, BinOpKind, Expr, ExprKind, Lit, Symbol, Ty, UnOp,
+};
+use crate::function::Function;
+use crate::memory::memory::Memory;
+use crate::riscv;
+use crate::symbols::SymbolTable;
+use crate::types::{BaseKind, Prototype, Type, TypeDef, Variable};
+
+/// The headers bundled with the decompiler, by the name their warnings give.
+const HEADERS: [(&str, &str); 3] = [
+    ("libc.h", include_str!("headers/libc.h")),
+    ("posix.h", include_str!("headers/posix.h")),
+    ("win32.h", include_str!("headers/win32.h")),
+];
+
+/// Words left out of declarations: storage classes, qualifiers the types do not model,
+/// calling conventions and the export macros of system headers.
+const IGNORED: &[&str] = &[
+    "extern",
+    "static",
+    "inline",
+    "__inline",
+    "__inline__",
+    "__extension__",
+    "register",
+    "volatile",
+    "__volatile__",
+    "restrict",
+    "__restrict",
+    "__restrict__",
+    "__cdecl",
+    "_cdecl",
+    "__stdcall",
+    "_stdcall",
+    "__fastcall",
+    "__thiscall",
+    "WINAPI",
+    "WINAPIV",
+    "APIENTRY",
+    "CALLBACK",
+    "NTAPI",
+    "WINBASEAPI",
+    "WINUSERAPI",
+    "DECLSPEC_IMPORT",
+    "__THROW",
+    "__wur",
+];
+
+/// Attributes and macros taking parenthesized arguments, left out along with them.
+const ATTRIBUTES: &[&str] = &[
+    "__attribute__",
+    "__attribute",
+    "__declspec",
+    "__asm__",
+    "__asm",
+    "asm",
+    "__nonnull",
+    "__attr_access",
+];
+
+/// Words which make up the C base types.
+const BASE_TYPES: &[&str] = &[
+    "void", "char", "short", "int", "long", "signed", "unsigned", "float", "double", "_Bool",
+    "bool",
+];
+
+/// How a function with a `format` attribute reads its variadic arguments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FormatStyle {
+    Printf,
+    Scanf,
+}
+
+/// A function declared by a header.
+#[derive(Clone, Debug)]
+struct Declaration {
+    prototype: Prototype,
+    /// The style and index of the format string parameter.
+    format: Option<(FormatStyle, usize)>,
+}
+
+/// Prototypes of library functions, from the bundled headers for libc, POSIX and Win32,
+/// the header files given with `--header` and the debug info.
+pub struct PrototypeDb {
+    long_size: u64,
+    functions: HashMap<String, Declaration>,
+    typedefs: HashMap<String, Type>,
+    /// The prototypes inferred for the lifted functions the others do not cover.
+    inferred: HashMap<u64, Prototype>,
+    /// The argument registers the lifted functions making no calls may write, by address:
+    /// the others are left as they were by the calls to them.
+    clobbers: HashMap<u64, Vec<String>>,
+}
+
+impl PrototypeDb {
+    /// Reads the bundled headers, with `long` and pointers taking the given number of bytes.
+    pub fn new(long_size: u64, pointer_size: u64) -> Self {
+        let mut db = Self {
+            long_size,
+            functions: HashMap::new(),
+            typedefs: HashMap::new(),
+            inferred: HashMap::new(),
+            clobbers: HashMap::new(),
+        };
+        let base = |name: &str, kind, size| Type::Base {
+            name: name.to_string(),
+            kind,
+            size,
+        };
+        for (name, ty) in [
+            (
+                "size_t",
+                base("unsigned long", BaseKind::Unsigned, pointer_size),
+            ),
+            ("ssize_t", base("long", BaseKind::Signed, pointer_size)),
+            ("ptrdiff_t", base("long", BaseKind::Signed, pointer_size)),
+            ("intptr_t", base("long", BaseKind::Signed, pointer_size)),
+            (
+                "uintptr_t",
+                base("unsigned long", BaseKind::Unsigned, pointer_size),
+            ),
+            ("int8_t", base("signed char", BaseKind::Signed, 1)),
+            ("int16_t", base("short", BaseKind::Signed, 2)),
+            ("int32_t", base("int", BaseKind::Signed, 4)),
+            ("int64_t", base("long long", BaseKind::Signed, 8)),
+            ("uint8_t", base("unsigned char", BaseKind::Unsigned, 1)),
+            ("uint16_t", base("unsigned short", BaseKind::Unsigned, 2)),
+            ("uint32_t", base("unsigned int", BaseKind::Unsigned, 4)),
+            (
+                "uint64_t",
+                base("unsigned long long", BaseKind::Unsigned, 8),
+            ),
+        ] {
+            db.typedefs.insert(name.to_string(), ty);
+        }
+        for (path, source) in HEADERS {
+            db.add_header(path, source);
+        }
+        db
+    }
+
+    /// Adds the functions and typedefs declared by the C header `source`, warning about the
+    /// declarations which cannot be parsed. Preprocessor directives are ignored.
+    pub fn add_header(&mut self, path: &str, source: &str) {
+        for statement in statements(&tokenize(source)) {
+            if let Err(err) = self.add_declaration(&statement) {
+                let text = statement
+                    .iter()
+                    .map(Token::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                eprintln!("warning: {path}: cannot parse `{text}`: {err}");
+            }
+        }
+    }
+
+    /// Adds the prototype of a function of the program, replacing any from the headers.
+    pub fn insert(&mut self, name: &str, prototype: Prototype) {
+        self.functions.insert(
+            name.to_string(),
+            Declaration {
+                prototype,
+                format: None,
+            },
+        );
+    }
+
+    /// Adds the prototype inferred for the lifted function at `addr`, used for the calls to
+    /// it when no other is known.
+    pub fn insert_inferred(&mut self, addr: u64, prototype: Prototype) {
+        self.inferred.insert(addr, prototype);
+    }
+
+    /// Records the argument registers the lifted function at `addr` may write, when it makes
+    /// no call: the calls to it leave the others as they were.
+    pub fn insert_clobbers(&mut self, addr: u64, registers: Vec<String>) {
+        self.clobbers.insert(addr, registers);
+    }
+
+    /// Returns the prototype of the function at `addr`, declared for its name or inferred.
+    pub fn prototype_at(&self, addr: u64, symbols: &SymbolTable) -> Option<&Prototype> {
+        match symbols.get(addr).and_then(|name| self.lookup(name)) {
+            Some(declaration) => Some(&declaration.prototype),
+            None => self.inferred.get(&addr),
+        }
+    }
+
+    /// Adds the typedefs among the types defined by the program.
+    pub fn add_types(&mut self, defs: &[TypeDef]) {
+        for def in defs {
+            let (name, ty) = match def {
+                TypeDef::Typedef(variable) => (variable.name.clone(), variable.ty.clone()),
+                TypeDef::Record {
+                    union,
+                    typedef: Some(name),
+                    ..
+                } if *union => (name.clone(), Type::Union(name.clone())),
+                TypeDef::Record {
+                    typedef: Some(name),
+                    ..
+                } => (name.clone(), Type::Struct(name.clone())),
+                TypeDef::Enum {
+                    typedef: Some(name),
+                    ..
+                } => (name.clone(), Type::Enum(name.clone())),
+                _ => continue,
+            };
+            self.typedefs.insert(name, ty);
+        }
+    }
+
+    fn add_declaration(&mut self, tokens: &[Token]) -> Result<(), String> {
+        let mut noreturn = false;
+        let mut format = None;
+        let mut cleaned = Vec::with_capacity(tokens.len());
+        let mut i = 0;
+        while i < tokens.len() {
+            match &tokens[i] {
+                Token::Ident(word) if IGNORED.contains(&word.as_str()) => {}
+                Token::Ident(word) if matches!(word.as_str(), "_Noreturn" | "noreturn") => {
+                    noreturn = true
+                }
+                Token::Ident(word) if ATTRIBUTES.contains(&word.as_str()) => {
+                    let end = matching(tokens, i + 1).ok_or("unbalanced parentheses")?;
+                    let arguments = &tokens[i + 1..end];
+                    noreturn |= arguments.iter().any(|token| {
+                        matches!(token, Token::Ident(word) if word.trim_matches('_') == "noreturn")
+                    });
+                    format = format.or(format_attribute(arguments));
+                    i = end;
+                }
+                // the `extern "C"` of C++ headers
+                Token::Str => {}
+                token => cleaned.push(token.clone()),
+            }
+            i += 1;
+        }
+        if cleaned.is_empty() {
+            return Ok(());
+        }
+
+        let mut parser = Parser {
+            tokens: &cleaned,
+            pos: 0,
+            long_size: self.long_size,
+        };
+        let typedef = parser.eat_ident("typedef");
+        let base = parser.specifiers()?;
+        if parser.at_end() {
+            // a struct, union or enum declared on its own
+            return Ok(());
+        }
+        loop {
+            let (name, ty) = parser.declarator(base.clone())?;
+            if name.is_empty() {
+                return Err("expected a name".to_string());
+            }
+            if typedef {
+                self.typedefs.insert(name, ty);
+            } else if let Type::Function(mut prototype) = ty {
+                prototype.noreturn = noreturn;
+                self.functions.insert(
+                    name,
+                    Declaration {
+                        prototype: *prototype,
+                        format,
+                    },
+                );
+            }
+            if !parser.eat_punct(',') {
+                break;
+            }
+        }
+        match parser.peek() {
+            None => Ok(()),
+            Some(token) => Err(format!("unexpected `{token}`")),
+        }
+    }
+
+    /// Looks a function up by the name of its symbol, leaving out the `DLL!` of imports, the
+    /// `@plt`, `@stub` or symbol version suffixes and the leading underscore of Mach-O and
+    /// 32-bit Windows names.
+    fn lookup(&self, name: &str) -> Option<&Declaration> {
+        let name = name.rsplit('!').next()?;
+        let name = name.split('@').next()?;
+        let name = name.strip_prefix("__imp_").unwrap_or(name);
+        self.functions
+            .get(name)
+            .or_else(|| self.functions.get(name.strip_prefix('_')?))
+    }
+
+    /// Returns `true` if the function named `name` is declared as never returning.
+    pub fn is_noreturn(&self, name: &str) -> bool {
+        self.lookup(name)
+            .is_some_and(|declaration| declaration.prototype.noreturn)
+    }
+
+    /// Gives `function` the prototype declared for its name, if it has none, and fits the
+    /// arguments of its calls to the prototypes of their callees: the registers missing from
+    /// a call are added and those beyond its parameters dropped, each argument is cast to
+    /// the type of its parameter, and the arguments of `printf` and `scanf`-like functions
+    /// are counted from their format string when it is a constant, those past the registers
+    /// being read from the stack. When it is not, they are the argument registers written
+    /// before the call.
+    pub fn apply(
+        &self,
+        function: &mut Function,
+        symbols: &SymbolTable,
+        memory: &Memory,
+        conventions: &Conventions,
+    ) {
+        if function.prototype.is_none() {
+            function.prototype = self
+                .lookup(&function.name)
+                .map(|declaration| declaration.prototype.clone());
+        }
+
+        let architecture = memory.architecture();
+        // the argument registers of the convention of each callee
+        let registers = |addr: u64| -> Vec<String> {
+            match architecture {
+                Architecture::X86 { .. } => conventions
+                    .of(addr)
+                    .argument_registers()
+                    .iter()
+                    .map(|reg| get_register_name(*reg))
+                    .collect(),
+                Architecture::AArch64 => (0..8).map(|num| format!("x{num}")).collect(),
+                Architecture::RiscV64 => (10..18)
+                    .map(|num| riscv::register_name(num).to_string())
+                    .collect(),
+            }
+        };
+        // and its floating-point ones, for a value of `size` bytes
+        let float_registers = |addr: u64, size: u64| -> Vec<String> {
+            match architecture {
+                Architecture::X86 { .. } => conventions
+                    .of(addr)
+                    .float_argument_registers()
+                    .iter()
+                    .map(|reg| get_register_name(*reg))
+                    .collect(),
+                Architecture::AArch64 if size == 4 => (0..8).map(|num| format!("s{num}")).collect(),
+                Architecture::AArch64 => (0..8).map(|num| format!("d{num}")).collect(),
+                Architecture::RiscV64 => (10..18)
+                    .map(|num| riscv::fp_register_name(num).to_string())
+                    .collect(),
+            }
+        };
+        let positional = |addr: u64| {
+            matches!(architecture, Architecture::X86 { .. })
+                && conventions.of(addr).positional_arguments()
+        };
+
+        for block in function.cfg.blocks.values_mut() {
+            for i in 0..block.statements.len() {
+                let (before, rest) = block.statements.split_at_mut(i);
+                let ExprKind::Call(addr, args) = &mut rest[0].call_site_mut().kind else {
+                    continue;
+                };
+                let registers = registers(*addr);
+                let (prototype, format) =
+                    match symbols.get(*addr).and_then(|name| self.lookup(name)) {
+                        Some(declaration) => (&declaration.prototype, declaration.format),
+                        None => match self.inferred.get(addr) {
+                            Some(prototype) => (prototype, None),
+                            None => continue,
+                        },
+                    };
+                // floating-point parameters travel in registers of their own
+                let parameters = prototype
+                    .parameters
+                    .iter()
+                    .filter(|parameter| !self.is_float(&parameter.ty))
+                    .collect::<Vec<_>>();
+
+                let count = match format {
+                    _ if !prototype.variadic => Some(parameters.len()),
+                    Some((style, index)) => args
+                        .get(index)
+                        .or(registers.get(index).map(|name| register(name)).as_ref())
+                        .and_then(|arg| self.string_constant(before, arg, architecture))
+                        .and_then(|addr| memory.read_c_string(addr))
+                        .map(|format| parameters.len() + count_arguments(&format, style)),
+                    None => None,
+                };
+                if let Some(count) = count {
+                    args.truncate(count);
+                }
+                let wanted = match count {
+                    Some(count) => count,
+                    None => parameters
+                        .len()
+                        .max(self.written_before(before, &registers, architecture)),
+                };
+                while args.len() < wanted.min(registers.len()) {
+                    args.push(register(&registers[args.len()]));
+                }
+                // the variadic arguments past the registers are in the stack slots the
+                // callee finds above its return address
+                let word = u64::from(memory.bitness() / 8);
+                while args.len() < wanted {
+                    let slot = (args.len() - registers.len()) as u64;
+                    let offset = match architecture {
+                        Architecture::X86 { .. } => conventions.of(*addr).stack_arguments_offset(),
+                        _ => 0,
+                    };
+                    args.push(stack_argument(architecture, offset + slot * word));
+                }
+
+                for (arg, parameter) in args.iter_mut().zip(parameters.iter()) {
+                    let Some(ty) = self.ty(&parameter.ty, 0) else {
+                        continue;
+                    };
+                    if matches!(&arg.kind, ExprKind::Cast(_, cast) if *cast == ty) {
+                        continue;
+                    }
+                    let value = std::mem::replace(
+                        arg,
+                        Expr {
+                            id: 0,
+                            kind: ExprKind::Lit(Lit::StillUnknown),
+                        },
+                    );
+                    *arg = Expr {
+                        id: 0,
+                        kind: ExprKind::Cast(Box::new(value), ty),
+                    };
+                }
+
+                // the floating-point arguments go between the others, in the order of the
+                // parameters
+                if parameters.len() == prototype.parameters.len() {
+                    continue;
+                }
+                let mut integers = std::mem::take(args).into_iter();
+                let mut floats = 0;
+                for (position, parameter) in prototype.parameters.iter().enumerate() {
+                    let Some(size) = self.float_size(&parameter.ty) else {
+                        let Some(arg) = integers.next() else {
+                            continue;
+                        };
+                        // in the register of its position rather than that of its rank
+                        match registers.get(position) {
+                            Some(name) if positional(*addr) && floats > 0 => {
+                                args.push(register(name))
+                            }
+                            _ => args.push(arg),
+                        }
+                        continue;
+                    };
+                    let n = if positional(*addr) { position } else { floats };
+                    floats += 1;
+                    if let Some(name) = float_registers(*addr, size).get(n) {
+                        args.push(register(name));
+                    }
+                }
+                args.extend(integers);
+            }
+        }
+    }
+
+    /// Returns how many arguments a call to the `printf` or `scanf`-like `name` passes in
+    /// integer registers, counted from its format string when `args` give it as a string.
+    pub fn count_variadic(&self, name: &str, args: &[Expr]) -> Option<usize> {
+        let declaration = self.lookup(name)?;
+        let (style, index) = declaration.format?;
+        let ExprKind::Lit(Lit::Str(format)) = &args.get(index)?.kind else {
+            return None;
+        };
+        let parameters = declaration
+            .prototype
+            .parameters
+            .iter()
+            .filter(|parameter| !self.is_float(&parameter.ty))
+            .count();
+        Some(parameters + count_arguments(format, style))
+    }
+
+    /// Makes the returns of `function` give the value left in the register its prototype
+    /// returns it in, like `return @EAX` for an `int`.
+    pub fn apply_returns(&self, function: &mut Function, memory: &Memory, conventions: &Conventions) {
+        let Some(prototype) = &function.prototype else {
+            return;
+        };
+        let word = u64::from(memory.bitness() / 8);
+        let ty = &prototype.return_type;
+        let name = match (memory.architecture(), self.float_size(ty)) {
+            (Architecture::X86 { .. }, Some(_)) => conventions
+                .of(function.address)
+                .float_return_register()
+                .map(get_register_name),
+            (Architecture::X86 { .. }, None) => self.size_of(ty, word).and_then(|size| {
+                let full = conventions.of(function.address).return_registers()[0].full_register();
+                iced_x86::Register::values()
+                    .find(|reg| reg.full_register() == full && reg.size() as u64 == size)
+                    .map(get_register_name)
+            }),
+            (Architecture::AArch64, Some(4)) => Some("s0".to_string()),
+            (Architecture::AArch64, Some(_)) => Some("d0".to_string()),
+            (Architecture::AArch64, None) => match self.size_of(ty, word) {
+                Some(size) if size <= 4 => Some("w0".to_string()),
+                Some(_) => Some("x0".to_string()),
+                None => None,
+            },
+            (Architecture::RiscV64, Some(_)) => Some(riscv::fp_register_name(10).to_string()),
+            (Architecture::RiscV64, None) => self
+                .size_of(ty, word)
+                .map(|_| riscv::register_name(10).to_string()),
+        };
+        let Some(name) = name else {
+            return;
+        };
+        for block in function.cfg.blocks.values_mut() {
+            for statement in block.statements.iter_mut() {
+                if let ExprKind::Ret(value @ None) = &mut statement.kind {
+                    *value = Some(Box::new(register(&name)));
+                }
+            }
+        }
+    }
+
+    /// Resolves the typedefs naming `ty`, up to a few levels deep.
+    fn resolve<'a>(&'a self, mut ty: &'a Type) -> &'a Type {
+        for _ in 0..16 {
+            match ty {
+                Type::Typedef(name) => match self.typedefs.get(name) {
+                    Some(target) => ty = target,
+                    None => break,
+                },
+                Type::Const(inner) => ty = inner,
+                _ => break,
+            }
+        }
+        ty
+    }
+
+    pub fn is_float(&self, ty: &Type) -> bool {
+        matches!(
+            self.resolve(ty),
+            Type::Base {
+                kind: BaseKind::Float,
+                ..
+            }
+        )
+    }
+
+    /// Returns the size of a floating-point `ty`, `None` for other types.
+    pub fn float_size(&self, ty: &Type) -> Option<u64> {
+        match self.resolve(ty) {
+            Type::Base {
+                kind: BaseKind::Float,
+                size,
+                ..
+            } => Some(*size),
+            _ => None,
+        }
+    }
+
+    /// Returns the size of an integer or pointer `ty` returned in a register `word` bytes
+    /// wide, `None` for `void` and floating-point types.
+    pub fn size_of(&self, ty: &Type, word: u64) -> Option<u64> {
+        match self.resolve(ty) {
+            Type::Void => None,
+            Type::Base {
+                kind: BaseKind::Float,
+                ..
+            } => None,
+            Type::Base { size, .. } => Some((*size).min(word)),
+            Type::Typedef(name) => name
+                .strip_prefix("undefined")
+                .and_then(|size| size.parse().ok())
+                .or(Some(word)),
+            _ => Some(word),
+        }
+    }
+
+    /// Returns the type of the lifted code an argument of type `ty` is cast to.
+    fn ty(&self, ty: &Type, depth: usize) -> Option<Ty> {
+        let pointee = |inner: &Type| {
+            let inner = match depth {
+                0..=3 => self.ty(inner, depth + 1),
+                _ => None,
+            };
+            Some(Ty::Ptr(Box::new(inner.unwrap_or(Ty::Void))))
+        };
+        match self.resolve(ty) {
+            Type::Base { kind, size, .. } => match (kind, size) {
+                (BaseKind::Bool, _) => Some(Ty::Bool),
+                (BaseKind::Signed, 1) => Some(Ty::I8),
+                (BaseKind::Signed, 2) => Some(Ty::I16),
+                (BaseKind::Signed, 4) => Some(Ty::I32),
+                (BaseKind::Signed, 8) => Some(Ty::I64),
+                (BaseKind::Unsigned, 1) => Some(Ty::U8),
+                (BaseKind::Unsigned, 2) => Some(Ty::U16),
+                (BaseKind::Unsigned, 4) => Some(Ty::U32),
+                (BaseKind::Unsigned, 8) => Some(Ty::U64),
+                _ => None,
+            },
+            Type::Enum(_) => Some(Ty::I32),
+            Type::Pointer(inner) if matches!(self.resolve(inner), Type::Function(_)) => {
+                Some(Ty::Ptr(Box::new(Ty::Void)))
+            }
+            // arrays are passed as pointers to their elements
+            Type::Pointer(inner) | Type::Array(inner, _) => pointee(inner),
+            Type::Void | Type::Struct(_) | Type::Union(_) | Type::Function(_) => None,
+            Type::Typedef(_) | Type::Const(_) => None,
+        }
+    }
+}
+
+/// Returns the `format(printf, m, n)` or `format(scanf, m, n)` given by the arguments of an
+/// attribute, with the index of the format string parameter.
+fn format_attribute(tokens: &[Token]) -> Option<(FormatStyle, usize)> {
+    let start = tokens.iter().position(
+        |token| matches!(token, Token::Ident(word) if word.trim_matches('_') == "format"),
+    )?;
+    let (Some(Token::Ident(style)), Some(Token::Number(index))) =
+        (tokens.get(start + 2), tokens.get(start + 4))
+    else {
+        return None;
+    };
+    let style = match style.trim_matches('_') {
+        "printf" | "gnu_printf" | "ms_printf" => FormatStyle::Printf,
+        "scanf" | "gnu_scanf" | "ms_scanf" => FormatStyle::Scanf,
+        _ => return None,
+    };
+    Some((style, (*index as usize).checked_sub(1)?))
+}
+
+/// Counts the variadic arguments a format string reads which go in integer registers: the
+/// floating-point conversions of `printf` take their own, and the conversions of `scanf`
+/// marked with `*` take none.
+fn count_arguments(format: &str, style: FormatStyle) -> usize {
+    let mut count = 0;
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            continue;
+        }
+        let mut assigned = true;
+        while let Some(&c) = chars.peek() {
+            match c {
+                '*' if style == FormatStyle::Scanf => assigned = false,
+                // a width or precision given as an argument
+                '*' => count += 1,
+                '-' | '+' | ' ' | '#' | '\'' | '.' | '$' | '0'..='9' => {}
+                'h' | 'l' | 'L' | 'q' | 'j' | 'z' | 't' => {}
+                _ => break,
+            }
+            chars.next();
+        }
+        match chars.next() {
+            Some('f' | 'F' | 'e' | 'E' | 'g' | 'G' | 'a' | 'A') if style == FormatStyle::Printf => {
+                assigned = false
+            }
+            Some('[') => {
+                // a scanset may start with `]` or `^]`
+                if chars.peek() == Some(&'^') {
+                    chars.next();
+                }
+                if chars.peek() == Some(&']') {
+                    chars.next();
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            Some(_) => {}
+            None => break,
+        }
+        if assigned {
+            count += 1;
+        }
+    }
+    count
+}
+
+impl PrototypeDb {
+    /// Returns the constant held by the argument `arg` of a call, following the registers
+    /// it was copied through back to the address or immediate last put in them in
+    /// `statements`, past the calls leaving them as they were.
+    fn string_constant(
+        &self,
+        statements: &[Expr],
+        arg: &Expr,
+        architecture: Architecture,
+    ) -> Option<u64> {
+        let mut name = match &arg.kind {
+            ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) => name.as_str(),
+            kind => return constant(kind),
+        };
+        for stmt in statements.iter().rev() {
+            let ExprKind::Assign(lhs, value) = &stmt.kind else {
+                if matches!(stmt.kind, ExprKind::Call(..) | ExprKind::MethodCall(..))
+                    && !self
+                        .call_clobbers(stmt)
+                        .is_some_and(|clobbers| {
+                            !clobbers.iter().any(|reg| same_register(reg, name, architecture))
+                        })
+                {
+                    return None;
+                }
+                continue;
+            };
+            let ExprKind::Lit(Lit::Symbol(Symbol::Reg(written))) = &lhs.kind else {
+                continue;
+            };
+            if !same_register(written, name, architecture) {
+                continue;
+            }
+            match &value.kind {
+                ExprKind::Lit(Lit::Symbol(Symbol::Reg(source))) => name = source,
+                kind => return constant(kind),
+            }
+        }
+        None
+    }
+
+    /// Returns how many of the argument `registers` are written in `statements` before a
+    /// call, one after the other from the first, past the calls leaving them as they were.
+    fn written_before(
+        &self,
+        statements: &[Expr],
+        registers: &[String],
+        architecture: Architecture,
+    ) -> usize {
+        let mut written = vec![false; registers.len()];
+        let mut clobbered = vec![false; registers.len()];
+        for stmt in statements.iter().rev() {
+            if matches!(stmt.kind, ExprKind::Call(..) | ExprKind::MethodCall(..)) {
+                let Some(clobbers) = self.call_clobbers(stmt) else {
+                    break;
+                };
+                for (i, name) in registers.iter().enumerate() {
+                    if clobbers.iter().any(|reg| same_register(reg, name, architecture)) {
+                        clobbered[i] = true;
+                    }
+                }
+                continue;
+            }
+            let ExprKind::Assign(lhs, _) = &stmt.kind else {
+                continue;
+            };
+            let ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) = &lhs.kind else {
+                continue;
+            };
+            if let Some(i) = registers
+                .iter()
+                .position(|reg| same_register(reg, name, architecture))
+            {
+                written[i] |= !clobbered[i];
+            }
+        }
+        written.iter().take_while(|written| **written).count()
+    }
+
+    /// Returns the argument registers the call `stmt` may write, when its callee is known
+    /// to leave the others as they were.
+    fn call_clobbers(&self, stmt: &Expr) -> Option<&Vec<String>> {
+        match stmt.kind {
+            ExprKind::Call(addr, _) => self.clobbers.get(&addr),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the stack slot `offset` bytes above the stack pointer, where a call finds the
+/// arguments passed on the stack.
+fn stack_argument(architecture: Architecture, offset: u64) -> Expr {
+    let (pointer, ty) = match architecture {
+        Architecture::X86 { bitness: 64 } => ("RSP", Ty::U64),
+        Architecture::X86 { bitness: 16 } => ("SP", Ty::U16),
+        Architecture::X86 { .. } => ("ESP", Ty::U32),
+        _ => ("sp", Ty::U64),
+    };
+    let mut address = register(pointer);
+    if offset != 0 {
+        let offset = Expr {
+            id: 0,
+            kind: ExprKind::Lit(Lit::U64(offset)),
+        };
+        address = Expr {
+            id: 0,
+            kind: ExprKind::Binary(BinOpKind::Add, Box::new(address), Box::new(offset)),
+        };
+    }
+    Expr {
+        id: 0,
+        kind: ExprKind::Unary(
+            UnOp::Deref,
+            Box::new(Expr {
+                id: 0,
+                kind: ExprKind::Cast(Box::new(address), Ty::Ptr(Box::new(ty))),
+            }),
+        ),
+    }
+}
+
+fn constant(kind: &ExprKind) -> Option<u64> {
+    match kind {
+        ExprKind::Lit(Lit::U64(value)) => Some(*value),
+        ExprKind::Lit(Lit::U32(value)) => Some(u64::from(*value)),
+        ExprKind::Lit(Lit::I64(value)) => Some(*value as u64),
+        _ => None,
+    }
+}
+
+/// Returns `true` if the registers printed as `a` and `b` overlap, like `EDI` and `RDI`.
+fn same_register(a: &str, b: &str, architecture: Architecture) -> bool {
+    match architecture {
+        Architecture::X86 { .. } => match (get_register_by_name(a), get_register_by_name(b)) {
+            (Some(a), Some(b)) => a.full_register() == b.full_register(),
+            _ => false,
+        },
+        // w0 is the lower half of x0
+        Architecture::AArch64 => a.get(1..) == b.get(1..) && a.starts_with(['x', 'w']),
+        Architecture::RiscV64 => a == b,
+    }
+}
+
+/// A token of a C declaration.
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(u64),
+    Punct(char),
+    Ellipsis,
+    /// A string literal, whose contents no declaration needs.
+    Str,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(word) => f.write_str(word),
+            Token::Number(value) => f.write_fmt(format_args!("{value}")),
+            Token::Punct(c) => f.write_fmt(format_args!("{c}")),
+            Token::Ellipsis => f.write_str("..."),
+            Token::Str => f.write_str("\"\""),
+        }
+    }
+}
+
+/// Splits C source into tokens, leaving out comments and preprocessor directives.
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line_start = true;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => {
+                line_start = true;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '#' if line_start => {
+                // up to the end of the line, following the continuations
+                let mut previous = '#';
+                for c in chars.by_ref() {
+                    if c == '\n' && previous != '\\' {
+                        break;
+                    }
+                    previous = c;
+                }
+                continue;
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                line_start = true;
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                continue;
+            }
+            '"' | '\'' => {
+                let mut escaped = false;
+                for next in chars.by_ref() {
+                    if next == c && !escaped {
+                        break;
+                    }
+                    escaped = next == '\\' && !escaped;
+                }
+                tokens.push(Token::Str);
+            }
+            '.' if chars.peek() == Some(&'.') => {
+                chars.next();
+                chars.next();
+                tokens.push(Token::Ellipsis);
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Ident(word));
+            }
+            c if c.is_ascii_digit() => {
+                let mut literal = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_ascii_alphanumeric() {
+                        break;
+                    }
+                    literal.push(c);
+                    chars.next();
+                }
+                let digits = literal.trim_end_matches(['u', 'U', 'l', 'L']);
+                let value = match digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+                    Some(hex) => u64::from_str_radix(hex, 16),
+                    None => digits.parse(),
+                };
+                tokens.push(Token::Number(value.unwrap_or(0)));
+            }
+            c => tokens.push(Token::Punct(c)),
+        }
+        line_start = false;
+    }
+    tokens
+}
+
+/// Splits the tokens of a header into declarations, at the semicolons outside braces.
+/// Function bodies end their definition and are left out, and so are the braces of
+/// `extern "C"` blocks.
+fn statements(tokens: &[Token]) -> Vec<Vec<Token>> {
+    let mut statements = Vec::new();
+    let mut current = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i] {
+            Token::Punct(';') => statements.push(std::mem::take(&mut current)),
+            Token::Punct('{') if matches!(current.last(), Some(Token::Str)) => {
+                current.clear();
+            }
+            // a closing brace left over from an `extern "C"` block
+            Token::Punct('}') if current.is_empty() => {}
+            Token::Punct('{') => {
+                let end = matching(tokens, i).unwrap_or(tokens.len() - 1);
+                if matches!(current.last(), Some(Token::Punct(')'))) {
+                    statements.push(std::mem::take(&mut current));
+                } else {
+                    current.extend_from_slice(&tokens[i..=end]);
+                }
+                i = end;
+            }
+            token => current.push(token.clone()),
+        }
+        i += 1;
+    }
+    statements
+}
+
+/// Returns the index of the bracket closing the one at `start`.
+fn matching(tokens: &[Token], start: usize) -> Option<usize> {
+    let (open, close) = match tokens.get(start)? {
+        Token::Punct('(') => ('(', ')'),
+        Token::Punct('[') => ('[', ']'),
+        Token::Punct('{') => ('{', '}'),
+        _ => return None,
+    };
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        match token {
+            Token::Punct(c) if *c == open => depth += 1,
+            Token::Punct(c) if *c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parses a declaration, once its attributes are left out.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    long_size: u64,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn eat_punct(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(&Token::Punct(c));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat_ident(&mut self, word: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Ident(ident)) if ident == word);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat_punct(c) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(token) => Err(format!("expected `{c}`, found `{token}`")),
+            None => Err(format!("expected `{c}`")),
+        }
+    }
+
+    /// Parses the type specifiers and qualifiers starting a declaration. An identifier where
+    /// a type is expected is taken for a typedef name, declared or not.
+    fn specifiers(&mut self) -> Result<Type, String> {
+        let mut words = Vec::new();
+        let mut ty = None;
+        let mut constant = false;
+        while let Some(Token::Ident(word)) = self.peek() {
+            let word = word.clone();
+            match word.as_str() {
+                "const" => constant = true,
+                word if BASE_TYPES.contains(&word) && ty.is_none() => words.push(word.to_string()),
+                "struct" | "union" | "enum" if ty.is_none() && words.is_empty() => {
+                    self.pos += 1;
+                    let tag = match self.peek() {
+                        Some(Token::Ident(tag)) => {
+                            let tag = tag.clone();
+                            self.pos += 1;
+                            tag
+                        }
+                        _ => String::new(),
+                    };
+                    if self.peek() == Some(&Token::Punct('{')) {
+                        self.pos = matching(self.tokens, self.pos).ok_or("unbalanced braces")? + 1;
+                    }
+                    ty = Some(match word.as_str() {
+                        "struct" => Type::Struct(tag),
+                        "union" => Type::Union(tag),
+                        _ => Type::Enum(tag),
+                    });
+                    continue;
+                }
+                _ if ty.is_none() && words.is_empty() => ty = Some(Type::Typedef(word)),
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        let ty = match ty {
+            Some(ty) => ty,
+            None if words.is_empty() => {
+                return Err(match self.peek() {
+                    Some(token) => format!("expected a type, found `{token}`"),
+                    None => "expected a type".to_string(),
+                })
+            }
+            None => self.base_type(&words),
+        };
+        Ok(if constant {
+            Type::Const(Box::new(ty))
+        } else {
+            ty
+        })
+    }
+
+    /// Returns the base type spelled by `words`, like `unsigned long`.
+    fn base_type(&self, words: &[String]) -> Type {
+        let has = |word: &str| words.iter().any(|w| w == word);
+        let longs = words.iter().filter(|word| *word == "long").count();
+        let kind = if has("unsigned") {
+            BaseKind::Unsigned
+        } else {
+            BaseKind::Signed
+        };
+        let (kind, size) = if has("void") {
+            return Type::Void;
+        } else if has("_Bool") || has("bool") {
+            (BaseKind::Bool, 1)
+        } else if has("float") {
+            (BaseKind::Float, 4)
+        } else if has("double") {
+            (BaseKind::Float, if longs > 0 { 16 } else { 8 })
+        } else if has("char") {
+            (kind, 1)
+        } else if has("short") {
+            (kind, 2)
+        } else if longs == 1 {
+            (kind, self.long_size)
+        } else if longs > 1 {
+            (kind, 8)
+        } else {
+            (kind, 4)
+        };
+        Type::Base {
+            name: words.join(" "),
+            kind,
+            size,
+        }
+    }
+
+    /// Parses a declarator applying to `ty`, like `*argv[]` or `(*compar)(const void *)`,
+    /// and returns the name it declares, empty for an abstract declarator.
+    fn declarator(&mut self, mut ty: Type) -> Result<(String, Type), String> {
+        while self.eat_punct('*') {
+            ty = Type::Pointer(Box::new(ty));
+            while self.eat_ident("const") {
+                ty = Type::Const(Box::new(ty));
+            }
+        }
+        // a parenthesized declarator, rather than the parameters of an abstract one
+        if self.peek() == Some(&Token::Punct('('))
+            && matches!(
+                self.tokens.get(self.pos + 1),
+                Some(Token::Punct('*' | '(' | '^'))
+            )
+        {
+            // the suffixes following the parentheses apply first
+            let start = self.pos;
+            self.pos = matching(self.tokens, start).ok_or("unbalanced parentheses")? + 1;
+            let ty = self.suffixes(ty)?;
+            let end = self.pos;
+            self.pos = start + 1;
+            let (name, ty) = self.declarator(ty)?;
+            self.expect(')')?;
+            self.pos = end;
+            return Ok((name, ty));
+        }
+        let name = match self.peek() {
+            Some(Token::Ident(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                name
+            }
+            _ => String::new(),
+        };
+        Ok((name, self.suffixes(ty)?))
+    }
+
+    /// Parses the array dimensions and parameter lists following a declarator.
+    fn suffixes(&mut self, ty: Type) -> Result<Type, String> {
+        if self.eat_punct('[') {
+            let len = match self.peek() {
+                Some(Token::Number(len)) => {
+                    let len = *len;
+                    self.pos += 1;
+                    Some(len)
+                }
+                _ => None,
+            };
+            self.expect(']')?;
+            return Ok(Type::Array(Box::new(self.suffixes(ty)?), len));
+        }
+        if self.eat_punct('(') {
+            let (parameters, variadic) = self.parameters()?;
+            return Ok(Type::Function(Box::new(Prototype {
+                return_type: self.suffixes(ty)?,
+                parameters,
+                variadic,
+                noreturn: false,
+            })));
+        }
+        Ok(ty)
+    }
+
+    /// Parses a parameter list, after its opening parenthesis.
+    fn parameters(&mut self) -> Result<(Vec<Variable>, bool), String> {
+        let mut parameters = Vec::new();
+        let mut variadic = false;
+        if self.eat_punct(')') {
+            return Ok((parameters, variadic));
+        }
+        if self.tokens.get(self.pos..self.pos + 2)
+            == Some(&[Token::Ident("void".to_string()), Token::Punct(')')])
+        {
+            self.pos += 2;
+            return Ok((parameters, variadic));
+        }
+        loop {
+            if self.peek() == Some(&Token::Ellipsis) {
+                self.pos += 1;
+                variadic = true;
+            } else {
+                let base = self.specifiers()?;
+                let (name, ty) = self.declarator(base)?;
+                // arrays and functions are passed as pointers to them
+                let ty = match ty {
+                    Type::Array(inner, _) => Type::Pointer(inner),
+                    Type::Function(_) => Type::Pointer(Box::new(ty)),
+                    ty => ty,
+                };
+                parameters.push(Variable { name, ty });
+            }
+            if !self.eat_punct(',') {
+                break;
+            }
+        }
+        self.expect(')')?;
+        Ok((parameters, variadic))
+    }
+}
+
+/// Returns the read of the register `name`.
+fn register(name: &str) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Lit(Lit::Symbol(Symbol::Reg(name.to_string()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::abi::CallingConvention;
+    use crate::rtti::Classes;
+    use crate::signatures;
+    use crate::unwind::UnwindTable;
+
+    /// Lifts the x86-64 `code` loaded at 0x1000, calling `printf` at 0x2000 and the leaf
+    /// functions at the `leaves` addresses, and returns its call to `printf`.
+    fn printf(code: &[u8], leaves: &[u64]) -> String {
+        let memory = Memory::raw(code, 0x1000, Architecture::X86 { bitness: 64 }).unwrap();
+        let mut symbols = SymbolTable::default();
+        symbols.insert(0x2000, "printf");
+        let conventions = Conventions::new(CallingConvention::SysV);
+        let none = BTreeSet::new();
+        let classes = Classes::default();
+        let unwind = UnwindTable::default();
+        let lift = |addr| Function::lift(&memory, &unwind, addr, &conventions, &none, &none, &classes);
+        let mut prototypes = PrototypeDb::new(8, 8);
+        for leaf in leaves {
+            let clobbers = signatures::clobbers(&lift(*leaf), &conventions).unwrap();
+            prototypes.insert_clobbers(*leaf, clobbers);
+        }
+        let mut function = lift(0x1000);
+        prototypes.apply(&mut function, &symbols, &memory, &conventions);
+        let call = function
+            .statements()
+            .map(|stmt| stmt.named(&symbols).to_string())
+            .find(|stmt| stmt.starts_with("printf"));
+        call.unwrap()
+    }
+
+    #[test]
+    fn unknown_format() {
+        // mov rdi, rbx; mov esi, 1; mov edx, 2; call 0x1020; call printf; ret; with a leaf
+        // at 0x1020 clearing eax, or esi too
+        let mut code = vec![
+            0x48, 0x89, 0xDF, 0xBE, 0x01, 0x00, 0x00, 0x00, 0xBA, 0x02, 0x00, 0x00, 0x00, 0xE8,
+            0x0E, 0x00, 0x00, 0x00, 0xE8, 0xE9, 0x0F, 0x00, 0x00, 0xC3, 0x90, 0x90, 0x90, 0x90,
+            0x90, 0x90, 0x90, 0x90, 0x31, 0xC0, 0xC3,
+        ];
+        assert_eq!(printf(&code, &[0x1020]), "printf((int8_t *)(@RDI), @RSI, @RDX)");
+        // the arguments written before a call to a function of unknown clobbers are not kept
+        assert_eq!(printf(&code, &[]), "printf((int8_t *)(@RDI))");
+        code.splice(0x20..0x22, [0x31, 0xF6]);
+        assert_eq!(printf(&code, &[0x1020]), "printf((int8_t *)(@RDI))");
+    }
+
+    #[test]
+    fn stack_arguments() {
+        // lea rdi, [format]; mov esi, 1; mov edx, 2; mov ecx, 3; mov r8d, 4; mov r9d, 5;
+        // mov qword [rsp], 6; call printf; ret
+        let mut code = vec![
+            0x48, 0x8D, 0x3D, 0x29, 0x00, 0x00, 0x00, 0xBE, 0x01, 0x00, 0x00, 0x00, 0xBA, 0x02,
+            0x00, 0x00, 0x00, 0xB9, 0x03, 0x00, 0x00, 0x00, 0x41, 0xB8, 0x04, 0x00, 0x00, 0x00,
+            0x41, 0xB9, 0x05, 0x00, 0x00, 0x00, 0x48, 0xC7, 0x04, 0x24, 0x06, 0x00, 0x00, 0x00,
+            0xE8, 0xD1, 0x0F, 0x00, 0x00, 0xC3,
+        ];
+        code.extend_from_slice(b"%d %d %d %d %d %d %d\n\0");
+        assert_eq!(
+            printf(&code, &[]),
+            "printf((int8_t *)(@RDI), @RSI, @RDX, @RCX, @R8, @R9, *((uint64_t *)(@RSP)), \
+             *((uint64_t *)(@RSP + 0x8)))"
+        );
+    }
+}
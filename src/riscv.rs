@@ -0,0 +1,1243 @@
+//! Decoding and disassembly of RV64GC code: the RV64I base with the M, A, F, D and C
+//! extensions.
+//!
+//! Compressed instructions are expanded to their 32-bit equivalents, and the usual
+//! pseudo-instructions such as `li`, `mv`, `ret`, `beqz` or `fneg.d` are recognized when
+//! decoding.
+
+use std::fmt::{Display, Formatter, Result, Write};
+
+/// ABI names of the integer registers.
+pub const REGISTER_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// Names of the control and status registers of the user, supervisor and machine modes.
+const CSR_NAMES: [(u16, &str); 29] = [
+    (0x001, "fflags"),
+    (0x002, "frm"),
+    (0x003, "fcsr"),
+    (0x100, "sstatus"),
+    (0x104, "sie"),
+    (0x105, "stvec"),
+    (0x140, "sscratch"),
+    (0x141, "sepc"),
+    (0x142, "scause"),
+    (0x143, "stval"),
+    (0x144, "sip"),
+    (0x180, "satp"),
+    (0x300, "mstatus"),
+    (0x301, "misa"),
+    (0x302, "medeleg"),
+    (0x303, "mideleg"),
+    (0x304, "mie"),
+    (0x305, "mtvec"),
+    (0x340, "mscratch"),
+    (0x341, "mepc"),
+    (0x342, "mcause"),
+    (0x343, "mtval"),
+    (0x344, "mip"),
+    (0xc00, "cycle"),
+    (0xc01, "time"),
+    (0xc02, "instret"),
+    (0xf11, "mvendorid"),
+    (0xf12, "marchid"),
+    (0xf14, "mhartid"),
+];
+
+/// ABI names of the floating-point registers.
+pub const FP_REGISTER_NAMES: [&str; 32] = [
+    "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "fs0", "fs1", "fa0", "fa1", "fa2",
+    "fa3", "fa4", "fa5", "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7", "fs8", "fs9",
+    "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+];
+
+pub const ZERO: u8 = 0;
+pub const RA: u8 = 1;
+pub const SP: u8 = 2;
+pub const T0: u8 = 5;
+pub const T3: u8 = 28;
+
+/// Returns the ABI name of integer register `x<num>`.
+pub fn register_name(num: u8) -> &'static str {
+    REGISTER_NAMES[num as usize & 31]
+}
+
+/// Returns the ABI name of floating-point register `f<num>`.
+pub fn fp_register_name(num: u8) -> &'static str {
+    FP_REGISTER_NAMES[num as usize & 31]
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    None,
+    Reg(u8),
+    FReg(u8),
+    Imm(i64),
+    /// A pc-relative target, already resolved.
+    Label(u64),
+    /// `offset(base)`.
+    Mem(u8, i64),
+    Csr(u16),
+    /// The predecessor or successor set of a `fence`, as its `iorw` bits.
+    FenceSet(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Opcode {
+    Unknown,
+    Unimp,
+    // RV64I
+    Lui,
+    Auipc,
+    Jal,
+    Jalr,
+    Beq,
+    Bne,
+    Blt,
+    Bge,
+    Bltu,
+    Bgeu,
+    Lb,
+    Lh,
+    Lw,
+    Ld,
+    Lbu,
+    Lhu,
+    Lwu,
+    Sb,
+    Sh,
+    Sw,
+    Sd,
+    Addi,
+    Slti,
+    Sltiu,
+    Xori,
+    Ori,
+    Andi,
+    Slli,
+    Srli,
+    Srai,
+    Add,
+    Sub,
+    Sll,
+    Slt,
+    Sltu,
+    Xor,
+    Srl,
+    Sra,
+    Or,
+    And,
+    Addiw,
+    Slliw,
+    Srliw,
+    Sraiw,
+    Addw,
+    Subw,
+    Sllw,
+    Srlw,
+    Sraw,
+    Fence,
+    Ecall,
+    Ebreak,
+    Csrrw,
+    Csrrs,
+    Csrrc,
+    Csrrwi,
+    Csrrsi,
+    Csrrci,
+    // M
+    Mul,
+    Mulh,
+    Mulhsu,
+    Mulhu,
+    Div,
+    Divu,
+    Rem,
+    Remu,
+    Mulw,
+    Divw,
+    Divuw,
+    Remw,
+    Remuw,
+    // F and D loads and stores
+    Flw,
+    Fld,
+    Fsw,
+    Fsd,
+    // F and D arithmetic, on single and double precision
+    FaddS,
+    FsubS,
+    FmulS,
+    FdivS,
+    FsqrtS,
+    FsgnjS,
+    FsgnjnS,
+    FsgnjxS,
+    FminS,
+    FmaxS,
+    FeqS,
+    FltS,
+    FleS,
+    FclassS,
+    FcvtWS,
+    FcvtWuS,
+    FcvtLS,
+    FcvtLuS,
+    FcvtSW,
+    FcvtSWu,
+    FcvtSL,
+    FcvtSLu,
+    FmvXW,
+    FmvWX,
+    FmaddS,
+    FmsubS,
+    FnmsubS,
+    FnmaddS,
+    FaddD,
+    FsubD,
+    FmulD,
+    FdivD,
+    FsqrtD,
+    FsgnjD,
+    FsgnjnD,
+    FsgnjxD,
+    FminD,
+    FmaxD,
+    FeqD,
+    FltD,
+    FleD,
+    FclassD,
+    FcvtWD,
+    FcvtWuD,
+    FcvtLD,
+    FcvtLuD,
+    FcvtDW,
+    FcvtDWu,
+    FcvtDL,
+    FcvtDLu,
+    FmvXD,
+    FmvDX,
+    FmaddD,
+    FmsubD,
+    FnmsubD,
+    FnmaddD,
+    FcvtSD,
+    FcvtDS,
+    // A
+    LrW,
+    ScW,
+    AmoswapW,
+    AmoaddW,
+    AmoxorW,
+    AmoandW,
+    AmoorW,
+    AmominW,
+    AmomaxW,
+    AmominuW,
+    AmomaxuW,
+    LrD,
+    ScD,
+    AmoswapD,
+    AmoaddD,
+    AmoxorD,
+    AmoandD,
+    AmoorD,
+    AmominD,
+    AmomaxD,
+    AmominuD,
+    AmomaxuD,
+    // pseudo-instructions
+    Nop,
+    Li,
+    Mv,
+    Not,
+    Neg,
+    Negw,
+    SextW,
+    Seqz,
+    Snez,
+    J,
+    Jr,
+    Ret,
+    Beqz,
+    Bnez,
+    Blez,
+    Bgez,
+    Bltz,
+    Bgtz,
+    Csrr,
+    Rdcycle,
+    Rdtime,
+    Rdinstret,
+    FmvS,
+    FnegS,
+    FabsS,
+    FmvD,
+    FnegD,
+    FabsD,
+}
+
+impl Opcode {
+    /// Spells the mnemonic, with a dot before each of the suffixes, like `fcvt.wu.d`.
+    pub fn name(self) -> String {
+        if self == Opcode::Unknown {
+            return ".insn".to_string();
+        }
+        let mut name = String::new();
+        for (i, c) in format!("{:?}", self).chars().enumerate() {
+            if i > 0 && c.is_ascii_uppercase() {
+                name.push('.');
+            }
+            name.push(c.to_ascii_lowercase());
+        }
+        name
+    }
+
+    /// Returns `true` for the load-reserved, store-conditional and atomic memory operations.
+    pub fn is_atomic(self) -> bool {
+        use Opcode::*;
+        matches!(
+            self,
+            LrW | ScW
+                | AmoswapW
+                | AmoaddW
+                | AmoxorW
+                | AmoandW
+                | AmoorW
+                | AmominW
+                | AmomaxW
+                | AmominuW
+                | AmomaxuW
+                | LrD
+                | ScD
+                | AmoswapD
+                | AmoaddD
+                | AmoxorD
+                | AmoandD
+                | AmoorD
+                | AmominD
+                | AmomaxD
+                | AmominuD
+                | AmomaxuD
+        )
+    }
+
+    /// Returns `true` for the fused multiply-adds, which read a third source register.
+    pub fn is_fused(self) -> bool {
+        use Opcode::*;
+        matches!(
+            self,
+            FmaddS | FmsubS | FnmsubS | FnmaddS | FmaddD | FmsubD | FnmsubD | FnmaddD
+        )
+    }
+
+    /// Returns `true` for the floating-point instructions which round their result, as
+    /// told by their rounding mode.
+    pub fn rounds(self) -> bool {
+        use Opcode::*;
+        self.is_fused()
+            || matches!(
+                self,
+                FaddS
+                    | FsubS
+                    | FmulS
+                    | FdivS
+                    | FsqrtS
+                    | FcvtWS
+                    | FcvtWuS
+                    | FcvtLS
+                    | FcvtLuS
+                    | FcvtSW
+                    | FcvtSWu
+                    | FcvtSL
+                    | FcvtSLu
+                    | FaddD
+                    | FsubD
+                    | FmulD
+                    | FdivD
+                    | FsqrtD
+                    | FcvtWD
+                    | FcvtWuD
+                    | FcvtLD
+                    | FcvtLuD
+                    | FcvtDL
+                    | FcvtDLu
+                    | FcvtSD
+            )
+    }
+
+    pub fn is_store(self) -> bool {
+        matches!(
+            self,
+            Opcode::Sb | Opcode::Sh | Opcode::Sw | Opcode::Sd | Opcode::Fsw | Opcode::Fsd
+        )
+    }
+
+    pub fn is_branch(self) -> bool {
+        use Opcode::*;
+        matches!(
+            self,
+            Beq | Bne | Blt | Bge | Bltu | Bgeu | Beqz | Bnez | Blez | Bgez | Bltz | Bgtz
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u64,
+    /// 2 for compressed instructions, 4 otherwise.
+    pub len: usize,
+    pub word: u32,
+    pub opcode: Opcode,
+    pub operands: [Operand; 3],
+}
+
+impl Instruction {
+    /// Returns the target of a direct jump, call or branch.
+    pub fn branch_target(&self) -> Option<u64> {
+        if !matches!(self.opcode, Opcode::Jal | Opcode::J) && !self.opcode.is_branch() {
+            return None;
+        }
+        self.operands.iter().find_map(|operand| match operand {
+            Operand::Label(target) => Some(*target),
+            _ => None,
+        })
+    }
+
+    /// Whether this is a `jal` or `jalr` linking through `ra` or `t0`, which the psABI
+    /// reserves for calls.
+    pub fn is_call(&self) -> bool {
+        matches!(self.opcode, Opcode::Jal | Opcode::Jalr)
+            && matches!(self.destination(), Some(RA | T0))
+    }
+
+    /// Returns the third source register of a fused multiply-add.
+    pub fn rs3(&self) -> Option<u8> {
+        self.opcode.is_fused().then_some(bits(self.word, 31, 27) as u8)
+    }
+
+    /// Returns the rounding mode of a floating-point instruction, as its `rm` field, when
+    /// it rounds its result.
+    pub fn rounding_mode(&self) -> Option<u8> {
+        self.opcode.rounds().then_some(bits(self.word, 14, 12) as u8)
+    }
+
+    /// Returns the integer register written by this instruction, other than `zero`.
+    pub fn destination(&self) -> Option<u8> {
+        use Opcode::*;
+        match self.opcode {
+            Unknown | Unimp | Fence | Ecall | Ebreak | Nop | J | Jr | Ret | Flw | Fld => None,
+            _ if self.opcode.is_store() || self.opcode.is_branch() => None,
+            // jal and jalr with a single operand link through ra
+            Jal | Jalr if self.operands[1] == Operand::None => Some(RA),
+            _ => match self.operands[0] {
+                Operand::Reg(rd) if rd != ZERO => Some(rd),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Operand::None => Ok(()),
+            Operand::Reg(num) => f.write_str(register_name(*num)),
+            Operand::FReg(num) => f.write_str(fp_register_name(*num)),
+            Operand::Imm(value) => f.write_fmt(format_args!("{}", value)),
+            Operand::Label(target) => f.write_fmt(format_args!("0x{:x}", target)),
+            Operand::Mem(base, offset) => {
+                f.write_fmt(format_args!("{}({})", offset, register_name(*base)))
+            }
+            Operand::Csr(csr) => match CSR_NAMES.iter().find(|(other, _)| other == csr) {
+                Some((_, name)) => f.write_str(name),
+                None => f.write_fmt(format_args!("{}", csr)),
+            },
+            Operand::FenceSet(set) => {
+                for (bit, name) in [(8, 'i'), (4, 'o'), (2, 'r'), (1, 'w')] {
+                    if set & bit != 0 {
+                        f.write_char(name)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if self.opcode == Opcode::Unknown {
+            return match self.len {
+                2 => f.write_fmt(format_args!(".insn 0x{:04x}", self.word)),
+                _ => f.write_fmt(format_args!(".insn 0x{:08x}", self.word)),
+            };
+        }
+        f.write_str(&self.opcode.name())?;
+        if self.opcode.is_atomic() {
+            // the acquire and release bits
+            f.write_str(["", ".rl", ".aq", ".aqrl"][bits(self.word, 26, 25) as usize])?;
+        }
+        for (i, operand) in self
+            .operands
+            .iter()
+            .take_while(|operand| **operand != Operand::None)
+            .enumerate()
+        {
+            f.write_str(if i == 0 { " " } else { ", " })?;
+            match operand {
+                // atomics take no offset
+                Operand::Mem(base, 0) if self.opcode.is_atomic() => {
+                    f.write_fmt(format_args!("({})", register_name(*base)))?
+                }
+                _ => operand.fmt(f)?,
+            }
+        }
+        if let Some(rs3) = self.rs3() {
+            f.write_fmt(format_args!(", {}", Operand::FReg(rs3)))?;
+        }
+        match self.rounding_mode() {
+            Some(rm @ 0..=4) => {
+                f.write_fmt(format_args!(", {}", ["rne", "rtz", "rdn", "rup", "rmm"][rm as usize]))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Returns the value of the 20-bit upper immediate of `lui` and `auipc`, sign-extended.
+pub fn upper_immediate(imm: i64) -> u64 {
+    ((imm << 44) >> 32) as u64
+}
+
+/// Returns the length of the instruction starting with the 16-bit `parcel`.
+pub fn instruction_len(parcel: u16) -> usize {
+    if parcel & 3 == 3 {
+        4
+    } else {
+        2
+    }
+}
+
+fn bits(word: u32, hi: u32, lo: u32) -> u32 {
+    (word >> lo) & ((1 << (hi - lo + 1)) - 1)
+}
+
+/// Sign-extends the lowest `width` bits of `value`.
+fn sext(value: u32, width: u32) -> i64 {
+    let shift = 64 - width;
+    ((u64::from(value) << shift) as i64) >> shift
+}
+
+/// An instruction in its base form, before pseudo-instructions are recognized.
+struct Base {
+    opcode: Opcode,
+    rd: u8,
+    rs1: u8,
+    rs2: u8,
+    imm: i64,
+}
+
+impl Base {
+    fn new(opcode: Opcode, rd: u32, rs1: u32, rs2: u32, imm: i64) -> Option<Self> {
+        Some(Self {
+            opcode,
+            rd: rd as u8,
+            rs1: rs1 as u8,
+            rs2: rs2 as u8,
+            imm,
+        })
+    }
+}
+
+/// Decodes the instruction `word` located at `address`. Only the low 16 bits are used
+/// for a compressed instruction.
+pub fn decode(word: u32, address: u64) -> Instruction {
+    let len = instruction_len(word as u16);
+    let word = if len == 2 { word & 0xffff } else { word };
+    let base = if len == 2 {
+        decode_compressed(word)
+    } else {
+        decode_base(word)
+    };
+    let (opcode, operands) = match base {
+        Some(base) => finish(base, address),
+        None => (Opcode::Unknown, [Operand::None; 3]),
+    };
+    Instruction {
+        address,
+        len,
+        word,
+        opcode,
+        operands,
+    }
+}
+
+fn decode_base(word: u32) -> Option<Base> {
+    use Opcode::*;
+    let rd = bits(word, 11, 7);
+    let rs1 = bits(word, 19, 15);
+    let rs2 = bits(word, 24, 20);
+    let funct3 = bits(word, 14, 12);
+    let funct7 = bits(word, 31, 25);
+    let imm_i = sext(bits(word, 31, 20), 12);
+    let imm_s = sext((bits(word, 31, 25) << 5) | bits(word, 11, 7), 12);
+    let imm_u = sext(bits(word, 31, 12), 20);
+
+    match word & 0x7f {
+        0x37 => Base::new(Lui, rd, 0, 0, imm_u),
+        0x17 => Base::new(Auipc, rd, 0, 0, imm_u),
+        0x6f => {
+            let imm = (bits(word, 31, 31) << 20)
+                | (bits(word, 19, 12) << 12)
+                | (bits(word, 20, 20) << 11)
+                | (bits(word, 30, 21) << 1);
+            Base::new(Jal, rd, 0, 0, sext(imm, 21))
+        }
+        0x67 if funct3 == 0 => Base::new(Jalr, rd, rs1, 0, imm_i),
+        0x63 => {
+            let imm = (bits(word, 31, 31) << 12)
+                | (bits(word, 7, 7) << 11)
+                | (bits(word, 30, 25) << 5)
+                | (bits(word, 11, 8) << 1);
+            let opcode = match funct3 {
+                0 => Beq,
+                1 => Bne,
+                4 => Blt,
+                5 => Bge,
+                6 => Bltu,
+                7 => Bgeu,
+                _ => return None,
+            };
+            Base::new(opcode, 0, rs1, rs2, sext(imm, 13))
+        }
+        0x03 => {
+            let opcode = [Lb, Lh, Lw, Ld, Lbu, Lhu, Lwu].get(funct3 as usize)?;
+            Base::new(*opcode, rd, rs1, 0, imm_i)
+        }
+        0x23 => {
+            let opcode = [Sb, Sh, Sw, Sd].get(funct3 as usize)?;
+            Base::new(*opcode, 0, rs1, rs2, imm_s)
+        }
+        0x07 => match funct3 {
+            2 => Base::new(Flw, rd, rs1, 0, imm_i),
+            3 => Base::new(Fld, rd, rs1, 0, imm_i),
+            _ => None,
+        },
+        0x27 => match funct3 {
+            2 => Base::new(Fsw, 0, rs1, rs2, imm_s),
+            3 => Base::new(Fsd, 0, rs1, rs2, imm_s),
+            _ => None,
+        },
+        0x13 => {
+            let shamt = i64::from(bits(word, 25, 20));
+            match (funct3, bits(word, 31, 26)) {
+                (0, _) => Base::new(Addi, rd, rs1, 0, imm_i),
+                (2, _) => Base::new(Slti, rd, rs1, 0, imm_i),
+                (3, _) => Base::new(Sltiu, rd, rs1, 0, imm_i),
+                (4, _) => Base::new(Xori, rd, rs1, 0, imm_i),
+                (6, _) => Base::new(Ori, rd, rs1, 0, imm_i),
+                (7, _) => Base::new(Andi, rd, rs1, 0, imm_i),
+                (1, 0b000000) => Base::new(Slli, rd, rs1, 0, shamt),
+                (5, 0b000000) => Base::new(Srli, rd, rs1, 0, shamt),
+                (5, 0b010000) => Base::new(Srai, rd, rs1, 0, shamt),
+                _ => None,
+            }
+        }
+        0x1b => {
+            let shamt = i64::from(bits(word, 24, 20));
+            match (funct3, funct7) {
+                (0, _) => Base::new(Addiw, rd, rs1, 0, imm_i),
+                (1, 0b0000000) => Base::new(Slliw, rd, rs1, 0, shamt),
+                (5, 0b0000000) => Base::new(Srliw, rd, rs1, 0, shamt),
+                (5, 0b0100000) => Base::new(Sraiw, rd, rs1, 0, shamt),
+                _ => None,
+            }
+        }
+        0x33 => {
+            let opcode = match (funct7, funct3) {
+                (0x00, 0) => Add,
+                (0x20, 0) => Sub,
+                (0x00, 1) => Sll,
+                (0x00, 2) => Slt,
+                (0x00, 3) => Sltu,
+                (0x00, 4) => Xor,
+                (0x00, 5) => Srl,
+                (0x20, 5) => Sra,
+                (0x00, 6) => Or,
+                (0x00, 7) => And,
+                (0x01, funct3) => [Mul, Mulh, Mulhsu, Mulhu, Div, Divu, Rem, Remu][funct3 as usize],
+                _ => return None,
+            };
+            Base::new(opcode, rd, rs1, rs2, 0)
+        }
+        0x3b => {
+            let opcode = match (funct7, funct3) {
+                (0x00, 0) => Addw,
+                (0x20, 0) => Subw,
+                (0x00, 1) => Sllw,
+                (0x00, 5) => Srlw,
+                (0x20, 5) => Sraw,
+                (0x01, 0) => Mulw,
+                (0x01, 4) => Divw,
+                (0x01, 5) => Divuw,
+                (0x01, 6) => Remw,
+                (0x01, 7) => Remuw,
+                _ => return None,
+            };
+            Base::new(opcode, rd, rs1, rs2, 0)
+        }
+        // the rounding mode is kept as the immediate
+        0x53 => {
+            let fmt = bits(word, 26, 25);
+            let pick = |single, double| match fmt {
+                0 => Some(single),
+                1 => Some(double),
+                _ => None,
+            };
+            let opcode = match (bits(word, 31, 27), funct3, rs2) {
+                (0x00, _, _) => pick(FaddS, FaddD)?,
+                (0x01, _, _) => pick(FsubS, FsubD)?,
+                (0x02, _, _) => pick(FmulS, FmulD)?,
+                (0x03, _, _) => pick(FdivS, FdivD)?,
+                (0x0b, _, 0) => pick(FsqrtS, FsqrtD)?,
+                (0x04, 0, _) => pick(FsgnjS, FsgnjD)?,
+                (0x04, 1, _) => pick(FsgnjnS, FsgnjnD)?,
+                (0x04, 2, _) => pick(FsgnjxS, FsgnjxD)?,
+                (0x05, 0, _) => pick(FminS, FminD)?,
+                (0x05, 1, _) => pick(FmaxS, FmaxD)?,
+                (0x08, _, 1) if fmt == 0 => FcvtSD,
+                (0x08, _, 0) if fmt == 1 => FcvtDS,
+                (0x14, 2, _) => pick(FeqS, FeqD)?,
+                (0x14, 1, _) => pick(FltS, FltD)?,
+                (0x14, 0, _) => pick(FleS, FleD)?,
+                (0x18, _, 0) => pick(FcvtWS, FcvtWD)?,
+                (0x18, _, 1) => pick(FcvtWuS, FcvtWuD)?,
+                (0x18, _, 2) => pick(FcvtLS, FcvtLD)?,
+                (0x18, _, 3) => pick(FcvtLuS, FcvtLuD)?,
+                (0x1a, _, 0) => pick(FcvtSW, FcvtDW)?,
+                (0x1a, _, 1) => pick(FcvtSWu, FcvtDWu)?,
+                (0x1a, _, 2) => pick(FcvtSL, FcvtDL)?,
+                (0x1a, _, 3) => pick(FcvtSLu, FcvtDLu)?,
+                (0x1c, 0, 0) => pick(FmvXW, FmvXD)?,
+                (0x1c, 1, 0) => pick(FclassS, FclassD)?,
+                (0x1e, 0, 0) => pick(FmvWX, FmvDX)?,
+                _ => return None,
+            };
+            Base::new(opcode, rd, rs1, rs2, i64::from(funct3))
+        }
+        // the third source of the fused multiply-adds is read back from the word
+        0x43 | 0x47 | 0x4b | 0x4f => {
+            let opcode = match (word & 0x7f, bits(word, 26, 25)) {
+                (0x43, 0) => FmaddS,
+                (0x47, 0) => FmsubS,
+                (0x4b, 0) => FnmsubS,
+                (0x4f, 0) => FnmaddS,
+                (0x43, 1) => FmaddD,
+                (0x47, 1) => FmsubD,
+                (0x4b, 1) => FnmsubD,
+                (0x4f, 1) => FnmaddD,
+                _ => return None,
+            };
+            Base::new(opcode, rd, rs1, rs2, i64::from(funct3))
+        }
+        0x2f => {
+            let opcodes = match funct3 {
+                2 => [
+                    LrW, ScW, AmoswapW, AmoaddW, AmoxorW, AmoandW, AmoorW, AmominW, AmomaxW,
+                    AmominuW, AmomaxuW,
+                ],
+                3 => [
+                    LrD, ScD, AmoswapD, AmoaddD, AmoxorD, AmoandD, AmoorD, AmominD, AmomaxD,
+                    AmominuD, AmomaxuD,
+                ],
+                _ => return None,
+            };
+            let index = match bits(word, 31, 27) {
+                0b00010 if rs2 == 0 => 0,
+                0b00011 => 1,
+                0b00001 => 2,
+                0b00000 => 3,
+                0b00100 => 4,
+                0b01100 => 5,
+                0b01000 => 6,
+                0b10000 => 7,
+                0b10100 => 8,
+                0b11000 => 9,
+                0b11100 => 10,
+                _ => return None,
+            };
+            Base::new(opcodes[index], rd, rs1, rs2, 0)
+        }
+        0x0f if funct3 == 0 => Base::new(Fence, 0, 0, 0, i64::from(bits(word, 27, 20))),
+        0x73 => {
+            let csr = i64::from(bits(word, 31, 20));
+            match (funct3, word) {
+                (0, 0x0000_0073) => Base::new(Ecall, 0, 0, 0, 0),
+                (0, 0x0010_0073) => Base::new(Ebreak, 0, 0, 0, 0),
+                // `csrrw zero, cycle, zero`, the canonical 32-bit illegal instruction
+                (1, 0xc000_1073) => Base::new(Unimp, 0, 0, 0, 0),
+                (1, _) => Base::new(Csrrw, rd, rs1, 0, csr),
+                (2, _) => Base::new(Csrrs, rd, rs1, 0, csr),
+                (3, _) => Base::new(Csrrc, rd, rs1, 0, csr),
+                (5, _) => Base::new(Csrrwi, rd, rs1, 0, csr),
+                (6, _) => Base::new(Csrrsi, rd, rs1, 0, csr),
+                (7, _) => Base::new(Csrrci, rd, rs1, 0, csr),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Expands a compressed instruction into its base form.
+fn decode_compressed(word: u32) -> Option<Base> {
+    use Opcode::*;
+    if word == 0 {
+        return Base::new(Unimp, 0, 0, 0, 0);
+    }
+    let funct3 = bits(word, 15, 13);
+    // the 3-bit register fields address x8 to x15
+    let rd_short = bits(word, 4, 2) + 8;
+    let rs1_short = bits(word, 9, 7) + 8;
+    let rd = bits(word, 11, 7);
+    let rs2 = bits(word, 6, 2);
+    let imm6 = sext((bits(word, 12, 12) << 5) | bits(word, 6, 2), 6);
+    // offsets of c.ld, c.sd, c.fld and c.fsd, scaled by 8
+    let uimm_d = i64::from((bits(word, 12, 10) << 3) | (bits(word, 6, 5) << 6));
+    let uimm_w =
+        i64::from((bits(word, 12, 10) << 3) | (bits(word, 6, 6) << 2) | (bits(word, 5, 5) << 6));
+
+    match (word & 3, funct3) {
+        (0, 0b000) => {
+            let imm = (bits(word, 12, 11) << 4)
+                | (bits(word, 10, 7) << 6)
+                | (bits(word, 6, 6) << 2)
+                | (bits(word, 5, 5) << 3);
+            if imm == 0 {
+                return None;
+            }
+            Base::new(Addi, rd_short, SP.into(), 0, i64::from(imm))
+        }
+        (0, 0b001) => Base::new(Fld, rd_short, rs1_short, 0, uimm_d),
+        (0, 0b010) => Base::new(Lw, rd_short, rs1_short, 0, uimm_w),
+        (0, 0b011) => Base::new(Ld, rd_short, rs1_short, 0, uimm_d),
+        (0, 0b101) => Base::new(Fsd, 0, rs1_short, rd_short, uimm_d),
+        (0, 0b110) => Base::new(Sw, 0, rs1_short, rd_short, uimm_w),
+        (0, 0b111) => Base::new(Sd, 0, rs1_short, rd_short, uimm_d),
+
+        (1, 0b000) => Base::new(Addi, rd, rd, 0, imm6),
+        (1, 0b001) if rd != 0 => Base::new(Addiw, rd, rd, 0, imm6),
+        (1, 0b010) => Base::new(Addi, rd, 0, 0, imm6),
+        (1, 0b011) if rd == 2 => {
+            let imm = (bits(word, 12, 12) << 9)
+                | (bits(word, 6, 6) << 4)
+                | (bits(word, 5, 5) << 6)
+                | (bits(word, 4, 3) << 7)
+                | (bits(word, 2, 2) << 5);
+            if imm == 0 {
+                return None;
+            }
+            Base::new(Addi, 2, 2, 0, sext(imm, 10))
+        }
+        (1, 0b011) if imm6 != 0 => Base::new(Lui, rd, 0, 0, sext(imm6 as u32 & 0xfffff, 20)),
+        (1, 0b100) => {
+            let rs2_short = bits(word, 4, 2) + 8;
+            let shamt = i64::from((bits(word, 12, 12) << 5) | bits(word, 6, 2));
+            match (bits(word, 11, 10), bits(word, 12, 12), bits(word, 6, 5)) {
+                (0b00, _, _) => Base::new(Srli, rs1_short, rs1_short, 0, shamt),
+                (0b01, _, _) => Base::new(Srai, rs1_short, rs1_short, 0, shamt),
+                (0b10, _, _) => Base::new(Andi, rs1_short, rs1_short, 0, imm6),
+                (0b11, 0, op) => {
+                    let opcode = [Sub, Xor, Or, And][op as usize];
+                    Base::new(opcode, rs1_short, rs1_short, rs2_short, 0)
+                }
+                (0b11, 1, 0b00) => Base::new(Subw, rs1_short, rs1_short, rs2_short, 0),
+                (0b11, 1, 0b01) => Base::new(Addw, rs1_short, rs1_short, rs2_short, 0),
+                _ => None,
+            }
+        }
+        (1, 0b101) => {
+            let imm = (bits(word, 12, 12) << 11)
+                | (bits(word, 11, 11) << 4)
+                | (bits(word, 10, 9) << 8)
+                | (bits(word, 8, 8) << 10)
+                | (bits(word, 7, 7) << 6)
+                | (bits(word, 6, 6) << 7)
+                | (bits(word, 5, 3) << 1)
+                | (bits(word, 2, 2) << 5);
+            Base::new(Jal, 0, 0, 0, sext(imm, 12))
+        }
+        (1, 0b110 | 0b111) => {
+            let imm = (bits(word, 12, 12) << 8)
+                | (bits(word, 11, 10) << 3)
+                | (bits(word, 6, 5) << 6)
+                | (bits(word, 4, 3) << 1)
+                | (bits(word, 2, 2) << 5);
+            let opcode = if funct3 == 0b110 { Beq } else { Bne };
+            Base::new(opcode, 0, rs1_short, 0, sext(imm, 9))
+        }
+
+        (2, 0b000) => {
+            let shamt = i64::from((bits(word, 12, 12) << 5) | bits(word, 6, 2));
+            Base::new(Slli, rd, rd, 0, shamt)
+        }
+        (2, 0b001 | 0b011) => {
+            let imm = (bits(word, 12, 12) << 5) | (bits(word, 6, 5) << 3) | (bits(word, 4, 2) << 6);
+            let opcode = if funct3 == 0b001 { Fld } else { Ld };
+            Base::new(opcode, rd, SP.into(), 0, i64::from(imm))
+        }
+        (2, 0b010) if rd != 0 => {
+            let imm = (bits(word, 12, 12) << 5) | (bits(word, 6, 4) << 2) | (bits(word, 3, 2) << 6);
+            Base::new(Lw, rd, SP.into(), 0, i64::from(imm))
+        }
+        (2, 0b100) => match (bits(word, 12, 12), rd, rs2) {
+            (0, 0, _) => None,
+            (0, rs1, 0) => Base::new(Jalr, 0, rs1, 0, 0),
+            (0, rd, rs2) => Base::new(Add, rd, 0, rs2, 0),
+            (1, 0, 0) => Base::new(Ebreak, 0, 0, 0, 0),
+            (1, rs1, 0) => Base::new(Jalr, RA.into(), rs1, 0, 0),
+            (_, rd, rs2) => Base::new(Add, rd, rd, rs2, 0),
+        },
+        (2, 0b101 | 0b111) => {
+            let imm = (bits(word, 12, 10) << 3) | (bits(word, 9, 7) << 6);
+            let opcode = if funct3 == 0b101 { Fsd } else { Sd };
+            Base::new(opcode, 0, SP.into(), rs2, i64::from(imm))
+        }
+        (2, 0b110) => {
+            let imm = (bits(word, 12, 9) << 2) | (bits(word, 8, 7) << 6);
+            Base::new(Sw, 0, SP.into(), rs2, i64::from(imm))
+        }
+        _ => None,
+    }
+}
+
+/// Recognizes the pseudo-instructions and lays out the operands in assembly order.
+fn finish(base: Base, address: u64) -> (Opcode, [Operand; 3]) {
+    use Opcode::*;
+    use Operand::{FReg, Imm, Label, Mem, Reg};
+    let Base {
+        opcode,
+        rd,
+        rs1,
+        rs2,
+        imm,
+    } = base;
+    let target = Label(address.wrapping_add(imm as u64));
+    let none = Operand::None;
+
+    match opcode {
+        // a full fence is shown without its sets, like the assemblers do
+        Fence if imm & 0xff == 0xff => (opcode, [none; 3]),
+        Fence => (opcode, [Operand::FenceSet((imm >> 4) as u8 & 15), Operand::FenceSet(imm as u8 & 15), none]),
+        Unimp | Ecall | Ebreak => (opcode, [none; 3]),
+        Lui | Auipc => (opcode, [Reg(rd), Imm(imm & 0xfffff), none]),
+        Jal if rd == ZERO => (J, [target, none, none]),
+        Jal if rd == RA => (Jal, [target, none, none]),
+        Jal => (Jal, [Reg(rd), target, none]),
+        Jalr if rd == ZERO && rs1 == RA && imm == 0 => (Ret, [none; 3]),
+        Jalr if rd == ZERO && imm == 0 => (Jr, [Reg(rs1), none, none]),
+        Jalr if rd == ZERO => (Jr, [Mem(rs1, imm), none, none]),
+        Jalr if rd == RA && imm == 0 => (Jalr, [Reg(rs1), none, none]),
+        Jalr if rd == RA => (Jalr, [Mem(rs1, imm), none, none]),
+        Jalr if imm == 0 => (Jalr, [Reg(rd), Reg(rs1), none]),
+        Jalr => (Jalr, [Reg(rd), Mem(rs1, imm), none]),
+        Beq if rs2 == ZERO => (Beqz, [Reg(rs1), target, none]),
+        Bne if rs2 == ZERO => (Bnez, [Reg(rs1), target, none]),
+        Blt if rs2 == ZERO => (Bltz, [Reg(rs1), target, none]),
+        Bge if rs2 == ZERO => (Bgez, [Reg(rs1), target, none]),
+        Blt if rs1 == ZERO => (Bgtz, [Reg(rs2), target, none]),
+        Bge if rs1 == ZERO => (Blez, [Reg(rs2), target, none]),
+        Beq | Bne | Blt | Bge | Bltu | Bgeu => (opcode, [Reg(rs1), Reg(rs2), target]),
+        Lb | Lh | Lw | Ld | Lbu | Lhu | Lwu => (opcode, [Reg(rd), Mem(rs1, imm), none]),
+        Flw | Fld => (opcode, [FReg(rd), Mem(rs1, imm), none]),
+        Sb | Sh | Sw | Sd => (opcode, [Reg(rs2), Mem(rs1, imm), none]),
+        Fsw | Fsd => (opcode, [FReg(rs2), Mem(rs1, imm), none]),
+        Addi if rd == ZERO && rs1 == ZERO && imm == 0 => (Nop, [none; 3]),
+        Addi if rs1 == ZERO => (Li, [Reg(rd), Imm(imm), none]),
+        Addi if imm == 0 => (Mv, [Reg(rd), Reg(rs1), none]),
+        Addiw if imm == 0 => (SextW, [Reg(rd), Reg(rs1), none]),
+        Xori if imm == -1 => (Not, [Reg(rd), Reg(rs1), none]),
+        Sltiu if imm == 1 => (Seqz, [Reg(rd), Reg(rs1), none]),
+        Addi | Slti | Sltiu | Xori | Ori | Andi | Slli | Srli | Srai | Addiw | Slliw | Srliw
+        | Sraiw => (opcode, [Reg(rd), Reg(rs1), Imm(imm)]),
+        Add if rs1 == ZERO => (Mv, [Reg(rd), Reg(rs2), none]),
+        Sub if rs1 == ZERO => (Neg, [Reg(rd), Reg(rs2), none]),
+        Subw if rs1 == ZERO => (Negw, [Reg(rd), Reg(rs2), none]),
+        Sltu if rs1 == ZERO => (Snez, [Reg(rd), Reg(rs2), none]),
+        Csrrs if rs1 == ZERO && imm == 0xc00 => (Rdcycle, [Reg(rd), none, none]),
+        Csrrs if rs1 == ZERO && imm == 0xc01 => (Rdtime, [Reg(rd), none, none]),
+        Csrrs if rs1 == ZERO && imm == 0xc02 => (Rdinstret, [Reg(rd), none, none]),
+        Csrrs if rs1 == ZERO => (Csrr, [Reg(rd), Operand::Csr(imm as u16), none]),
+        Csrrw | Csrrs | Csrrc => (opcode, [Reg(rd), Operand::Csr(imm as u16), Reg(rs1)]),
+        Csrrwi | Csrrsi | Csrrci => (opcode, [Reg(rd), Operand::Csr(imm as u16), Imm(rs1.into())]),
+        FsgnjS if rs1 == rs2 => (FmvS, [FReg(rd), FReg(rs1), none]),
+        FsgnjnS if rs1 == rs2 => (FnegS, [FReg(rd), FReg(rs1), none]),
+        FsgnjxS if rs1 == rs2 => (FabsS, [FReg(rd), FReg(rs1), none]),
+        FsgnjD if rs1 == rs2 => (FmvD, [FReg(rd), FReg(rs1), none]),
+        FsgnjnD if rs1 == rs2 => (FnegD, [FReg(rd), FReg(rs1), none]),
+        FsgnjxD if rs1 == rs2 => (FabsD, [FReg(rd), FReg(rs1), none]),
+        FsqrtS | FsqrtD | FcvtSD | FcvtDS => (opcode, [FReg(rd), FReg(rs1), none]),
+        FeqS | FltS | FleS | FeqD | FltD | FleD => (opcode, [Reg(rd), FReg(rs1), FReg(rs2)]),
+        FcvtWS | FcvtWuS | FcvtLS | FcvtLuS | FmvXW | FclassS | FcvtWD | FcvtWuD | FcvtLD
+        | FcvtLuD | FmvXD | FclassD => (opcode, [Reg(rd), FReg(rs1), none]),
+        FcvtSW | FcvtSWu | FcvtSL | FcvtSLu | FmvWX | FcvtDW | FcvtDWu | FcvtDL | FcvtDLu
+        | FmvDX => (opcode, [FReg(rd), Reg(rs1), none]),
+        _ if opcode.is_atomic() && matches!(opcode, LrW | LrD) => {
+            (opcode, [Reg(rd), Mem(rs1, 0), none])
+        }
+        _ if opcode.is_atomic() => (opcode, [Reg(rd), Reg(rs2), Mem(rs1, 0)]),
+        FaddS | FsubS | FmulS | FdivS | FsgnjS | FsgnjnS | FsgnjxS | FminS | FmaxS | FaddD
+        | FsubD | FmulD | FdivD | FsgnjD | FsgnjnD | FsgnjxD | FminD | FmaxD => {
+            (opcode, [FReg(rd), FReg(rs1), FReg(rs2)])
+        }
+        _ if opcode.is_fused() => (opcode, [FReg(rd), FReg(rs1), FReg(rs2)]),
+        _ => (opcode, [Reg(rd), Reg(rs1), Reg(rs2)]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Disassembly of `llvm-mc -triple=riscv64 -mattr=+m,+a,+f,+d` output by `llvm-objdump`.
+    const LLVM: [(u64, u32, &str); 184] = [
+        (0x0, 0xc0001073, "unimp"),
+        (0x4, 0x12345537, "lui a0, 74565"),
+        (0x8, 0x00002e17, "auipc t3, 2"),
+        (0xc, 0x010000ef, "jal 0x1c"),
+        (0x10, 0xff9ff06f, "j 0x8"),
+        (0x14, 0x008780e7, "jalr 8(a5)"),
+        (0x18, 0x000e0367, "jalr t1, t3"),
+        (0x1c, 0x00b50463, "beq a0, a1, 0x24"),
+        (0x20, 0xfeb51ee3, "bne a0, a1, 0x1c"),
+        (0x24, 0x00b54663, "blt a0, a1, 0x30"),
+        (0x28, 0x00d65863, "bge a2, a3, 0x38"),
+        (0x2c, 0x00b56a63, "bltu a0, a1, 0x40"),
+        (0x30, 0x00b57c63, "bgeu a0, a1, 0x48"),
+        (0x34, 0x00050463, "beqz a0, 0x3c"),
+        (0x38, 0x00059463, "bnez a1, 0x40"),
+        (0x3c, 0x00a05463, "blez a0, 0x44"),
+        (0x40, 0x00055463, "bgez a0, 0x48"),
+        (0x44, 0x00054463, "bltz a0, 0x4c"),
+        (0x48, 0x00a04463, "bgtz a0, 0x50"),
+        (0x4c, 0x00158503, "lb a0, 1(a1)"),
+        (0x50, 0xffe59503, "lh a0, -2(a1)"),
+        (0x54, 0x00412503, "lw a0, 4(sp)"),
+        (0x58, 0x01813083, "ld ra, 24(sp)"),
+        (0x5c, 0x0005c503, "lbu a0, 0(a1)"),
+        (0x60, 0x0025d503, "lhu a0, 2(a1)"),
+        (0x64, 0x0085e503, "lwu a0, 8(a1)"),
+        (0x68, 0x00a580a3, "sb a0, 1(a1)"),
+        (0x6c, 0x00a59123, "sh a0, 2(a1)"),
+        (0x70, 0xfea42e23, "sw a0, -4(s0)"),
+        (0x74, 0x00113c23, "sd ra, 24(sp)"),
+        (0x78, 0xffb58513, "addi a0, a1, -5"),
+        (0x7c, 0x02a00513, "li a0, 42"),
+        (0x80, 0x00058513, "mv a0, a1"),
+        (0x84, 0x00000013, "nop"),
+        (0x88, 0x0035a513, "slti a0, a1, 3"),
+        (0x8c, 0x0035b513, "sltiu a0, a1, 3"),
+        (0x90, 0x0015b513, "seqz a0, a1"),
+        (0x94, 0x0075c513, "xori a0, a1, 7"),
+        (0x98, 0xfff5c513, "not a0, a1"),
+        (0x9c, 0x0085e513, "ori a0, a1, 8"),
+        (0xa0, 0x0ff5f513, "andi a0, a1, 255"),
+        (0xa4, 0x00359513, "slli a0, a1, 3"),
+        (0xa8, 0x03c5d513, "srli a0, a1, 60"),
+        (0xac, 0x43f5d513, "srai a0, a1, 63"),
+        (0xb0, 0x00c58533, "add a0, a1, a2"),
+        (0xb4, 0x40c58533, "sub a0, a1, a2"),
+        (0xb8, 0x40b00533, "neg a0, a1"),
+        (0xbc, 0x00c59533, "sll a0, a1, a2"),
+        (0xc0, 0x00c5a533, "slt a0, a1, a2"),
+        (0xc4, 0x00c5b533, "sltu a0, a1, a2"),
+        (0xc8, 0x00b03533, "snez a0, a1"),
+        (0xcc, 0x00c5c533, "xor a0, a1, a2"),
+        (0xd0, 0x00c5d533, "srl a0, a1, a2"),
+        (0xd4, 0x40c5d533, "sra a0, a1, a2"),
+        (0xd8, 0x00c5e533, "or a0, a1, a2"),
+        (0xdc, 0x00c5f533, "and a0, a1, a2"),
+        (0xe0, 0x0015851b, "addiw a0, a1, 1"),
+        (0xe4, 0x0005851b, "sext.w a0, a1"),
+        (0xe8, 0x0035951b, "slliw a0, a1, 3"),
+        (0xec, 0x0045d51b, "srliw a0, a1, 4"),
+        (0xf0, 0x4055d51b, "sraiw a0, a1, 5"),
+        (0xf4, 0x00c5853b, "addw a0, a1, a2"),
+        (0xf8, 0x40c5853b, "subw a0, a1, a2"),
+        (0xfc, 0x40b0053b, "negw a0, a1"),
+        (0x100, 0x00c5953b, "sllw a0, a1, a2"),
+        (0x104, 0x00c5d53b, "srlw a0, a1, a2"),
+        (0x108, 0x40c5d53b, "sraw a0, a1, a2"),
+        (0x10c, 0x0ff0000f, "fence"),
+        (0x110, 0x0330000f, "fence rw, rw"),
+        (0x114, 0x00000073, "ecall"),
+        (0x118, 0x00100073, "ebreak"),
+        (0x11c, 0x30059573, "csrrw a0, mstatus, a1"),
+        (0x120, 0x3005a573, "csrrs a0, mstatus, a1"),
+        (0x124, 0x3005b573, "csrrc a0, mstatus, a1"),
+        (0x128, 0x3001d573, "csrrwi a0, mstatus, 3"),
+        (0x12c, 0x3001e573, "csrrsi a0, mstatus, 3"),
+        (0x130, 0x3001f573, "csrrci a0, mstatus, 3"),
+        (0x134, 0xc0002573, "rdcycle a0"),
+        (0x138, 0x02c58533, "mul a0, a1, a2"),
+        (0x13c, 0x02c59533, "mulh a0, a1, a2"),
+        (0x140, 0x02c5a533, "mulhsu a0, a1, a2"),
+        (0x144, 0x02c5b533, "mulhu a0, a1, a2"),
+        (0x148, 0x02c5c533, "div a0, a1, a2"),
+        (0x14c, 0x02c5d533, "divu a0, a1, a2"),
+        (0x150, 0x02c5e533, "rem a0, a1, a2"),
+        (0x154, 0x02c5f533, "remu a0, a1, a2"),
+        (0x158, 0x02c5853b, "mulw a0, a1, a2"),
+        (0x15c, 0x02c5c53b, "divw a0, a1, a2"),
+        (0x160, 0x02c5d53b, "divuw a0, a1, a2"),
+        (0x164, 0x02c5e53b, "remw a0, a1, a2"),
+        (0x168, 0x02c5f53b, "remuw a0, a1, a2"),
+        (0x16c, 0x00452507, "flw fa0, 4(a0)"),
+        (0x170, 0x00813587, "fld fa1, 8(sp)"),
+        (0x174, 0x00a52227, "fsw fa0, 4(a0)"),
+        (0x178, 0x00813427, "fsd fs0, 8(sp)"),
+        (0x17c, 0x00c5f553, "fadd.s fa0, fa1, fa2"),
+        (0x180, 0x08c5f553, "fsub.s fa0, fa1, fa2"),
+        (0x184, 0x10c5f553, "fmul.s fa0, fa1, fa2"),
+        (0x188, 0x18c5f553, "fdiv.s fa0, fa1, fa2"),
+        (0x18c, 0x5805f553, "fsqrt.s fa0, fa1"),
+        (0x190, 0x20c58553, "fsgnj.s fa0, fa1, fa2"),
+        (0x194, 0x20c59553, "fsgnjn.s fa0, fa1, fa2"),
+        (0x198, 0x20c5a553, "fsgnjx.s fa0, fa1, fa2"),
+        (0x19c, 0x20b58553, "fmv.s fa0, fa1"),
+        (0x1a0, 0x20b59553, "fneg.s fa0, fa1"),
+        (0x1a4, 0x20b5a553, "fabs.s fa0, fa1"),
+        (0x1a8, 0x28c58553, "fmin.s fa0, fa1, fa2"),
+        (0x1ac, 0x28c59553, "fmax.s fa0, fa1, fa2"),
+        (0x1b0, 0xa0c5a553, "feq.s a0, fa1, fa2"),
+        (0x1b4, 0xa0c59553, "flt.s a0, fa1, fa2"),
+        (0x1b8, 0xa0c58553, "fle.s a0, fa1, fa2"),
+        (0x1bc, 0xe0059553, "fclass.s a0, fa1"),
+        (0x1c0, 0xc0059553, "fcvt.w.s a0, fa1, rtz"),
+        (0x1c4, 0xc0159553, "fcvt.wu.s a0, fa1, rtz"),
+        (0x1c8, 0xc0259553, "fcvt.l.s a0, fa1, rtz"),
+        (0x1cc, 0xc0359553, "fcvt.lu.s a0, fa1, rtz"),
+        (0x1d0, 0xd005f553, "fcvt.s.w fa0, a1"),
+        (0x1d4, 0xd015f553, "fcvt.s.wu fa0, a1"),
+        (0x1d8, 0xd025f553, "fcvt.s.l fa0, a1"),
+        (0x1dc, 0xd035f553, "fcvt.s.lu fa0, a1"),
+        (0x1e0, 0xe0058553, "fmv.x.w a0, fa1"),
+        (0x1e4, 0xf0058553, "fmv.w.x fa0, a1"),
+        (0x1e8, 0x68c5f543, "fmadd.s fa0, fa1, fa2, fa3"),
+        (0x1ec, 0x68c5f547, "fmsub.s fa0, fa1, fa2, fa3"),
+        (0x1f0, 0x68c5f54b, "fnmsub.s fa0, fa1, fa2, fa3"),
+        (0x1f4, 0x68c5f54f, "fnmadd.s fa0, fa1, fa2, fa3"),
+        (0x1f8, 0x02c5f553, "fadd.d fa0, fa1, fa2"),
+        (0x1fc, 0x0ac5f553, "fsub.d fa0, fa1, fa2"),
+        (0x200, 0x12c5f553, "fmul.d fa0, fa1, fa2"),
+        (0x204, 0x1ac5f553, "fdiv.d fa0, fa1, fa2"),
+        (0x208, 0x5a05f553, "fsqrt.d fa0, fa1"),
+        (0x20c, 0x22c58553, "fsgnj.d fa0, fa1, fa2"),
+        (0x210, 0x22c59553, "fsgnjn.d fa0, fa1, fa2"),
+        (0x214, 0x22c5a553, "fsgnjx.d fa0, fa1, fa2"),
+        (0x218, 0x22b58553, "fmv.d fa0, fa1"),
+        (0x21c, 0x22b59553, "fneg.d fa0, fa1"),
+        (0x220, 0x22b5a553, "fabs.d fa0, fa1"),
+        (0x224, 0x2ac58553, "fmin.d fa0, fa1, fa2"),
+        (0x228, 0x2ac59553, "fmax.d fa0, fa1, fa2"),
+        (0x22c, 0xa2c5a553, "feq.d a0, fa1, fa2"),
+        (0x230, 0xa2c59553, "flt.d a0, fa1, fa2"),
+        (0x234, 0xa2c58553, "fle.d a0, fa1, fa2"),
+        (0x238, 0xe2059553, "fclass.d a0, fa1"),
+        (0x23c, 0xc2059553, "fcvt.w.d a0, fa1, rtz"),
+        (0x240, 0xc2159553, "fcvt.wu.d a0, fa1, rtz"),
+        (0x244, 0xc2259553, "fcvt.l.d a0, fa1, rtz"),
+        (0x248, 0xc2359553, "fcvt.lu.d a0, fa1, rtz"),
+        (0x24c, 0xd2058553, "fcvt.d.w fa0, a1"),
+        (0x250, 0xd2158553, "fcvt.d.wu fa0, a1"),
+        (0x254, 0xd225f553, "fcvt.d.l fa0, a1"),
+        (0x258, 0xd235f553, "fcvt.d.lu fa0, a1"),
+        (0x25c, 0xe2058553, "fmv.x.d a0, fa1"),
+        (0x260, 0xf2058553, "fmv.d.x fa0, a1"),
+        (0x264, 0x6ac5f543, "fmadd.d fa0, fa1, fa2, fa3"),
+        (0x268, 0x6ac5f547, "fmsub.d fa0, fa1, fa2, fa3"),
+        (0x26c, 0x6ac5f54b, "fnmsub.d fa0, fa1, fa2, fa3"),
+        (0x270, 0x6ac5f54f, "fnmadd.d fa0, fa1, fa2, fa3"),
+        (0x274, 0x4015f553, "fcvt.s.d fa0, fa1"),
+        (0x278, 0x42058553, "fcvt.d.s fa0, fa1"),
+        (0x27c, 0x1005a52f, "lr.w a0, (a1)"),
+        (0x280, 0x18c5a52f, "sc.w a0, a2, (a1)"),
+        (0x284, 0x08c5a52f, "amoswap.w a0, a2, (a1)"),
+        (0x288, 0x00c5a52f, "amoadd.w a0, a2, (a1)"),
+        (0x28c, 0x20c5a52f, "amoxor.w a0, a2, (a1)"),
+        (0x290, 0x60c5a52f, "amoand.w a0, a2, (a1)"),
+        (0x294, 0x40c5a52f, "amoor.w a0, a2, (a1)"),
+        (0x298, 0x80c5a52f, "amomin.w a0, a2, (a1)"),
+        (0x29c, 0xa0c5a52f, "amomax.w a0, a2, (a1)"),
+        (0x2a0, 0xc0c5a52f, "amominu.w a0, a2, (a1)"),
+        (0x2a4, 0xe0c5a52f, "amomaxu.w a0, a2, (a1)"),
+        (0x2a8, 0x1405b52f, "lr.d.aq a0, (a1)"),
+        (0x2ac, 0x1ac5b52f, "sc.d.rl a0, a2, (a1)"),
+        (0x2b0, 0x0ec5b52f, "amoswap.d.aqrl a0, a2, (a1)"),
+        (0x2b4, 0x00c5b52f, "amoadd.d a0, a2, (a1)"),
+        (0x2b8, 0x20c5b52f, "amoxor.d a0, a2, (a1)"),
+        (0x2bc, 0x60c5b52f, "amoand.d a0, a2, (a1)"),
+        (0x2c0, 0x40c5b52f, "amoor.d a0, a2, (a1)"),
+        (0x2c4, 0x80c5b52f, "amomin.d a0, a2, (a1)"),
+        (0x2c8, 0xa0c5b52f, "amomax.d a0, a2, (a1)"),
+        (0x2cc, 0xc0c5b52f, "amominu.d a0, a2, (a1)"),
+        (0x2d0, 0xe0c5b52f, "amomaxu.d a0, a2, (a1)"),
+        (0x2d4, 0x0080006f, "j 0x2dc"),
+        (0x2d8, 0x00078067, "jr a5"),
+        (0x2dc, 0x00008067, "ret"),
+    ];
+
+    /// The same with `+c`, for the compressed encodings.
+    const LLVM_COMPRESSED: [(u64, u32, &str); 37] = [
+        (0x0, 0x0808, "addi a0, sp, 16"),
+        (0x2, 0x2588, "fld fa0, 8(a1)"),
+        (0x4, 0x41c8, "lw a0, 4(a1)"),
+        (0x6, 0x6588, "ld a0, 8(a1)"),
+        (0x8, 0xa588, "fsd fa0, 8(a1)"),
+        (0xa, 0xc1c8, "sw a0, 4(a1)"),
+        (0xc, 0xe588, "sd a0, 8(a1)"),
+        (0xe, 0x0001, "nop"),
+        (0x10, 0x1575, "addi a0, a0, -3"),
+        (0x12, 0x2515, "addiw a0, a0, 5"),
+        (0x14, 0x5565, "li a0, -7"),
+        (0x16, 0x7139, "addi sp, sp, -64"),
+        (0x18, 0x650d, "lui a0, 3"),
+        (0x1a, 0x8109, "srli a0, a0, 2"),
+        (0x1c, 0x850d, "srai a0, a0, 3"),
+        (0x1e, 0x9979, "andi a0, a0, -2"),
+        (0x20, 0x8d0d, "sub a0, a0, a1"),
+        (0x22, 0x8d2d, "xor a0, a0, a1"),
+        (0x24, 0x8d4d, "or a0, a0, a1"),
+        (0x26, 0x8d6d, "and a0, a0, a1"),
+        (0x28, 0x9d0d, "subw a0, a0, a1"),
+        (0x2a, 0x9d2d, "addw a0, a0, a1"),
+        (0x2c, 0xa801, "j 0x3c"),
+        (0x2e, 0xc501, "beqz a0, 0x36"),
+        (0x30, 0xfd65, "bnez a0, 0x28"),
+        (0x32, 0x0512, "slli a0, a0, 4"),
+        (0x34, 0x2542, "fld fa0, 16(sp)"),
+        (0x36, 0x4532, "lw a0, 12(sp)"),
+        (0x38, 0x6562, "ld a0, 24(sp)"),
+        (0x3a, 0x8082, "ret"),
+        (0x3c, 0x852e, "mv a0, a1"),
+        (0x3e, 0x9002, "ebreak"),
+        (0x40, 0x9502, "jalr a0"),
+        (0x42, 0x952e, "add a0, a0, a1"),
+        (0x44, 0xa82a, "fsd fa0, 16(sp)"),
+        (0x46, 0xc62a, "sw a0, 12(sp)"),
+        (0x48, 0xec2a, "sd a0, 24(sp)"),
+    ];
+
+    #[test]
+    fn llvm_disassembly() {
+        for (address, word, expected) in LLVM.into_iter().chain(LLVM_COMPRESSED) {
+            let inst = decode(word, address);
+            assert_eq!(inst.to_string(), expected, "{word:08x} at {address:#x}");
+            assert_eq!(inst.len, instruction_len(word as u16));
+        }
+    }
+}
@@ -0,0 +1,550 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::aarch64_parser::{
+    get_assign, get_deref, get_goto, get_if, get_immediate, get_lit, get_unary, unparsed,
+};
+use crate::arch;
+use crate::ast::*;
+use crate::cfg::BasicBlock;
+use crate::parser::{get_binary, get_tail_call};
+use crate::riscv::{
+    fp_register_name, register_name, upper_immediate, Instruction, Opcode, Operand, SP, ZERO,
+};
+use crate::sse_parser::{get_cast, get_intrinsic};
+
+/// The psABI argument registers `a0` to `a7`.
+const ARGUMENT_REGISTERS: std::ops::Range<u8> = 10..18;
+
+/// Lifts the instructions of a RISC-V block and fills in the arguments of its calls.
+///
+/// Addresses built with `lui` or `auipc` and `addi` are folded into constants, as are the
+/// loads, stores and `jalr` calls going through them.
+pub fn lift_block(block: &mut BasicBlock, tail_calls: &BTreeSet<u64>, symbols: &mut Vec<Symbol>) {
+    let mut known = HashMap::<u8, u64>::new();
+    let mut arguments = BTreeSet::<u8>::new();
+
+    block.statements = Vec::with_capacity(block.instructions.len());
+    for inst in block.instructions.iter() {
+        let arch::Instruction::RiscV(inst) = inst else {
+            block.statements.push(unparsed(*inst));
+            continue;
+        };
+
+        let mut stmt = match inst.operands[0] {
+            Operand::Label(target) if tail_calls.contains(&inst.address) => get_tail_call(target),
+            _ => to_expression(symbols, &known, inst),
+        };
+        if let ExprKind::Call(_, args) = &mut stmt.call_site_mut().kind {
+            for reg in ARGUMENT_REGISTERS {
+                if !arguments.contains(&reg) {
+                    break;
+                }
+                args.push(get_register(symbols, reg));
+            }
+        }
+        block.statements.push(stmt);
+
+        if inst.is_call() {
+            // the callee may clobber every argument and temporary register
+            arguments.clear();
+            known.clear();
+            continue;
+        }
+        let constant = get_constant(&known, inst);
+        if let Some(rd) = inst.destination() {
+            known.remove(&rd);
+            if ARGUMENT_REGISTERS.contains(&rd) {
+                arguments.insert(rd);
+            }
+            if let Some(value) = constant {
+                known.insert(rd, value);
+            }
+        }
+    }
+}
+
+/// Returns the address an instruction puts in its destination register, when it is
+/// computed from the program counter or an absolute upper immediate.
+fn get_constant(known: &HashMap<u8, u64>, inst: &Instruction) -> Option<u64> {
+    match (inst.opcode, inst.operands) {
+        (Opcode::Lui, [_, Operand::Imm(imm), _]) => Some(upper_immediate(imm)),
+        (Opcode::Auipc, [_, Operand::Imm(imm), _]) => {
+            Some(inst.address.wrapping_add(upper_immediate(imm)))
+        }
+        (Opcode::Addi, [_, Operand::Reg(rs1), Operand::Imm(imm)]) if rs1 != SP => {
+            known.get(&rs1).map(|base| base.wrapping_add(imm as u64))
+        }
+        _ => None,
+    }
+}
+
+/// Lifts a single instruction, using the registers in `known` to resolve addresses.
+pub fn to_expression(
+    symbols: &mut Vec<Symbol>,
+    known: &HashMap<u8, u64>,
+    inst: &Instruction,
+) -> Expr {
+    lift(symbols, known, inst).unwrap_or_else(|| unparsed(arch::Instruction::RiscV(*inst)))
+}
+
+fn lift(symbols: &mut Vec<Symbol>, known: &HashMap<u8, u64>, inst: &Instruction) -> Option<Expr> {
+    use Opcode::*;
+    if let (Some(value), Operand::Reg(rd)) = (get_constant(known, inst), inst.operands[0]) {
+        return Some(get_assign(
+            get_register(symbols, rd),
+            get_lit(Lit::U64(value)),
+        ));
+    }
+    if let Some(expr) = lift_float(symbols, known, inst) {
+        return Some(expr);
+    }
+    if inst.opcode.is_atomic() {
+        return lift_atomic(symbols, known, inst);
+    }
+    let expr = match (inst.opcode, inst.operands) {
+        (Li, [Operand::Reg(rd), Operand::Imm(imm), _]) => {
+            get_assign(get_register(symbols, rd), get_signed(imm))
+        }
+        (Mv, [Operand::Reg(rd), Operand::Reg(rs), _]) => {
+            get_assign(get_register(symbols, rd), get_register(symbols, rs))
+        }
+        (Not | Neg | Negw, [Operand::Reg(rd), Operand::Reg(rs), _]) => {
+            let op = if inst.opcode == Not {
+                UnOp::Not
+            } else {
+                UnOp::Neg
+            };
+            let mut value = get_unary(op, get_register(symbols, rs));
+            if inst.opcode == Negw {
+                value = get_cast(value, Ty::I32);
+            }
+            get_assign(get_register(symbols, rd), value)
+        }
+        (SextW, [Operand::Reg(rd), Operand::Reg(rs), _]) => {
+            let value = Expr {
+                id: 0,
+                kind: ExprKind::Cast(Box::new(get_register(symbols, rs)), Ty::I32),
+            };
+            get_assign(get_register(symbols, rd), value)
+        }
+        (Seqz | Snez, [Operand::Reg(rd), Operand::Reg(rs), _]) => {
+            let op = if inst.opcode == Seqz {
+                BinOpKind::Eq
+            } else {
+                BinOpKind::Ne
+            };
+            let value = get_binary(op, get_register(symbols, rs), get_immediate(0));
+            get_assign(get_register(symbols, rd), value)
+        }
+        (_, [Operand::Reg(rd), Operand::Reg(rs1), rhs @ (Operand::Reg(_) | Operand::Imm(_))]) => {
+            let mut op = get_operator(inst.opcode)?;
+            // the type the operands are taken as, when not the unsigned 64 bits of a register
+            let ty = get_operand_type(inst.opcode);
+            let shift = matches!(op, BinOpKind::Shl | BinOpKind::Shr);
+            let rhs = match rhs {
+                Operand::Reg(rs2) => {
+                    let rs2 = get_register(symbols, rs2);
+                    match ty.clone() {
+                        Some(ty) if !shift => get_cast(rs2, ty),
+                        _ => rs2,
+                    }
+                }
+                // sp = sp - 0x20 rather than sp = sp + -(0x20)
+                Operand::Imm(imm) if imm < 0 && matches!(op, BinOpKind::Add) => {
+                    op = BinOpKind::Sub;
+                    get_immediate(imm.unsigned_abs())
+                }
+                // sign-extended, then compared unsigned
+                Operand::Imm(imm) if inst.opcode == Sltiu => get_immediate(imm as u64),
+                Operand::Imm(imm) => get_signed(imm),
+                _ => return None,
+            };
+            let mut lhs = get_register(symbols, rs1);
+            if let Some(ty) = ty.clone() {
+                lhs = get_cast(lhs, ty);
+            }
+            let mut value = get_binary(op, lhs, rhs);
+            // the 32-bit operations sign-extend their result into the whole register
+            if is_word(inst.opcode) && ty != Some(Ty::I32) {
+                value = get_cast(value, Ty::I32);
+            }
+            get_assign(get_register(symbols, rd), value)
+        }
+
+        (Jal, [Operand::Label(target), ..]) => get_call(target),
+        // `call`: auipc followed by jalr through the same register
+        (Jalr, [Operand::Reg(rs), Operand::None, _] | [_, Operand::Reg(rs), _])
+            if inst.is_call() =>
+        {
+            get_call(*known.get(&rs)?)
+        }
+        (Jalr, [Operand::Mem(base, offset), ..] | [_, Operand::Mem(base, offset), _])
+            if inst.is_call() =>
+        {
+            get_call(known.get(&base)?.wrapping_add(offset as u64))
+        }
+
+        (_, [Operand::Reg(rt), Operand::Mem(base, offset), _]) => {
+            let ty = get_access_type(inst.opcode)?;
+            let location = get_deref(get_address(symbols, known, base, offset), ty);
+            if inst.opcode.is_store() {
+                get_assign(location, get_register(symbols, rt))
+            } else {
+                get_assign(get_register(symbols, rt), location)
+            }
+        }
+
+        (J, [Operand::Label(target), ..]) => get_goto(target),
+        (_, [Operand::Reg(rs), Operand::Label(target), _]) if inst.opcode.is_branch() => {
+            let cond = get_binary(
+                get_comparison(inst.opcode),
+                get_compared(symbols, inst.opcode, rs),
+                get_immediate(0),
+            );
+            get_if(cond, target)
+        }
+        (_, [Operand::Reg(rs1), Operand::Reg(rs2), Operand::Label(target)]) => {
+            let cond = get_binary(
+                get_comparison(inst.opcode),
+                get_compared(symbols, inst.opcode, rs1),
+                get_compared(symbols, inst.opcode, rs2),
+            );
+            get_if(cond, target)
+        }
+
+        (Ret, _) => Expr {
+            id: 0,
+            kind: ExprKind::Ret(None),
+        },
+        _ => return None,
+    };
+    Some(expr)
+}
+
+fn get_call(target: u64) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Call(target, Vec::new()),
+    }
+}
+
+/// Lifts the floating-point arithmetic, comparisons, conversions and moves of the F and D
+/// extensions, with the C library functions for those C has no operator for. Returns `None`
+/// for the other instructions.
+fn lift_float(
+    symbols: &mut Vec<Symbol>,
+    known: &HashMap<u8, u64>,
+    inst: &Instruction,
+) -> Option<Expr> {
+    use Opcode::*;
+    let single = inst.opcode.name().ends_with(".s");
+    // the C library function on doubles, `sqrt`, or on floats, `sqrtf`
+    let function = |name: &str, args| match single {
+        true => get_intrinsic(&format!("{name}f"), args),
+        false => get_intrinsic(name, args),
+    };
+    let mut freg = |num| get_float_register(symbols, num);
+    let value = match (inst.opcode, inst.operands) {
+        (_, [Operand::FReg(rd), Operand::FReg(rs1), Operand::FReg(rs2)]) if inst.opcode.is_fused() => {
+            let (a, b, c) = (freg(rs1), freg(rs2), freg(inst.rs3()?));
+            let value = match inst.opcode {
+                FmaddS | FmaddD => function("fma", vec![a, b, c]),
+                FmsubS | FmsubD => function("fma", vec![a, b, get_unary(UnOp::Neg, c)]),
+                FnmsubS | FnmsubD => function("fma", vec![get_unary(UnOp::Neg, a), b, c]),
+                _ => get_unary(UnOp::Neg, function("fma", vec![a, b, c])),
+            };
+            return Some(get_assign(freg(rd), value));
+        }
+        (
+            FaddS | FaddD | FsubS | FsubD | FmulS | FmulD | FdivS | FdivD,
+            [Operand::FReg(rd), Operand::FReg(rs1), Operand::FReg(rs2)],
+        ) => {
+            let op = match inst.opcode {
+                FaddS | FaddD => BinOpKind::Add,
+                FsubS | FsubD => BinOpKind::Sub,
+                FmulS | FmulD => BinOpKind::Mul,
+                _ => BinOpKind::Div,
+            };
+            (rd, get_binary(op, freg(rs1), freg(rs2)))
+        }
+        (
+            FminS | FminD | FmaxS | FmaxD | FsgnjS | FsgnjD | FsgnjnS | FsgnjnD,
+            [Operand::FReg(rd), Operand::FReg(rs1), Operand::FReg(rs2)],
+        ) => {
+            let (a, b) = (freg(rs1), freg(rs2));
+            let value = match inst.opcode {
+                FminS | FminD => function("fmin", vec![a, b]),
+                FmaxS | FmaxD => function("fmax", vec![a, b]),
+                FsgnjS | FsgnjD => function("copysign", vec![a, b]),
+                _ => function("copysign", vec![a, get_unary(UnOp::Neg, b)]),
+            };
+            (rd, value)
+        }
+        (FsqrtS | FsqrtD, [Operand::FReg(rd), Operand::FReg(rs), _]) => {
+            (rd, function("sqrt", vec![freg(rs)]))
+        }
+        (FabsS | FabsD, [Operand::FReg(rd), Operand::FReg(rs), _]) => {
+            (rd, function("fabs", vec![freg(rs)]))
+        }
+        (FnegS | FnegD, [Operand::FReg(rd), Operand::FReg(rs), _]) => {
+            (rd, get_unary(UnOp::Neg, freg(rs)))
+        }
+        (FmvS | FmvD, [Operand::FReg(rd), Operand::FReg(rs), _]) => (rd, freg(rs)),
+        (FcvtSD, [Operand::FReg(rd), Operand::FReg(rs), _]) => (rd, get_cast(freg(rs), Ty::F32)),
+        (FcvtDS, [Operand::FReg(rd), Operand::FReg(rs), _]) => (rd, get_cast(freg(rs), Ty::F64)),
+        // the integer moves copy the bits, like movq does on x86
+        (FmvWX | FmvDX, [Operand::FReg(rd), Operand::Reg(rs), _]) => {
+            (rd, get_register(symbols, rs))
+        }
+        (FmvXW | FmvXD, [Operand::Reg(rd), Operand::FReg(rs), _]) => {
+            let value = freg(rs);
+            return Some(get_assign(get_register(symbols, rd), value));
+        }
+        (
+            FeqS | FeqD | FltS | FltD | FleS | FleD,
+            [Operand::Reg(rd), Operand::FReg(rs1), Operand::FReg(rs2)],
+        ) => {
+            let op = match inst.opcode {
+                FeqS | FeqD => BinOpKind::Eq,
+                FltS | FltD => BinOpKind::Lt,
+                _ => BinOpKind::Le,
+            };
+            let value = get_binary(op, freg(rs1), freg(rs2));
+            return Some(get_assign(get_register(symbols, rd), value));
+        }
+        (
+            FcvtWS | FcvtWuS | FcvtLS | FcvtLuS | FcvtWD | FcvtWuD | FcvtLD | FcvtLuD,
+            [Operand::Reg(rd), Operand::FReg(rs), _],
+        ) => {
+            // the C conversion truncates, like rtz; the others round as they tell
+            let value = match inst.rounding_mode()? {
+                1 => freg(rs),
+                rm => {
+                    let name = ["roundeven", "", "floor", "ceil", "round", "", "", "lrint"]
+                        .get(rm as usize)
+                        .filter(|name| !name.is_empty())?;
+                    function(name, vec![freg(rs)])
+                }
+            };
+            let value = match inst.opcode {
+                FcvtWS | FcvtWD => get_cast(value, Ty::I32),
+                // sign-extended from 32 bits too
+                FcvtWuS | FcvtWuD => get_cast(get_cast(value, Ty::U32), Ty::I32),
+                FcvtLS | FcvtLD => get_cast(value, Ty::I64),
+                _ => get_cast(value, Ty::U64),
+            };
+            return Some(get_assign(get_register(symbols, rd), value));
+        }
+        (
+            FcvtSW | FcvtSWu | FcvtSL | FcvtSLu | FcvtDW | FcvtDWu | FcvtDL | FcvtDLu,
+            [Operand::FReg(rd), Operand::Reg(rs), _],
+        ) => {
+            let source = get_register(symbols, rs);
+            let source = match inst.opcode {
+                FcvtSW | FcvtDW => get_cast(source, Ty::I32),
+                FcvtSWu | FcvtDWu => get_cast(source, Ty::U32),
+                FcvtSL | FcvtDL => get_cast(source, Ty::I64),
+                _ => source,
+            };
+            let ty = match inst.opcode {
+                FcvtSW | FcvtSWu | FcvtSL | FcvtSLu => Ty::F32,
+                _ => Ty::F64,
+            };
+            (rd, get_cast(source, ty))
+        }
+        (Flw | Fld | Fsw | Fsd, [Operand::FReg(rt), Operand::Mem(base, offset), _]) => {
+            let ty = if matches!(inst.opcode, Flw | Fsw) { Ty::F32 } else { Ty::F64 };
+            let location = get_deref(get_address(symbols, known, base, offset), ty);
+            if inst.opcode.is_store() {
+                return Some(get_assign(location, get_float_register(symbols, rt)));
+            }
+            (rt, location)
+        }
+        _ => return None,
+    };
+    let (rd, value) = value;
+    Some(get_assign(get_float_register(symbols, rd), value))
+}
+
+/// Lifts the atomic memory operations as the GCC builtins doing the same, and the
+/// load-reserved and store-conditional pairs as calls of their own.
+fn lift_atomic(
+    symbols: &mut Vec<Symbol>,
+    known: &HashMap<u8, u64>,
+    inst: &Instruction,
+) -> Option<Expr> {
+    use Opcode::*;
+    let word = inst.opcode.name().ends_with(".w");
+    let (rd, value, base) = match inst.operands {
+        [Operand::Reg(rd), Operand::Mem(base, _), _] => (rd, None, base),
+        [Operand::Reg(rd), Operand::Reg(rs2), Operand::Mem(base, _)] => {
+            (rd, Some(get_register(symbols, rs2)), base)
+        }
+        _ => return None,
+    };
+    // the words loaded are sign-extended, and compared as signed unless said otherwise
+    let ty = match (inst.opcode, word) {
+        (AmominuW | AmomaxuW, _) => Ty::U32,
+        (AmominuD | AmomaxuD, _) => Ty::U64,
+        (AmominD | AmomaxD, _) => Ty::I64,
+        (_, true) => Ty::I32,
+        (_, false) => Ty::U64,
+    };
+    let pointer = get_cast(get_address(symbols, known, base, 0), Ty::Ptr(Box::new(ty)));
+    let name = match inst.opcode {
+        LrW | LrD => "load_reserved",
+        ScW | ScD => "store_conditional",
+        AmoswapW | AmoswapD => "__atomic_exchange_n",
+        AmoaddW | AmoaddD => "__atomic_fetch_add",
+        AmoxorW | AmoxorD => "__atomic_fetch_xor",
+        AmoandW | AmoandD => "__atomic_fetch_and",
+        AmoorW | AmoorD => "__atomic_fetch_or",
+        AmominW | AmominD | AmominuW | AmominuD => "__atomic_fetch_min",
+        _ => "__atomic_fetch_max",
+    };
+    let mut args = vec![pointer];
+    args.extend(value);
+    if name.starts_with("__atomic") {
+        // the acquire and release bits
+        let order = ["__ATOMIC_RELAXED", "__ATOMIC_RELEASE", "__ATOMIC_ACQUIRE", "__ATOMIC_ACQ_REL"]
+            [(inst.word >> 25) as usize & 3];
+        args.push(get_lit(Lit::Symbol(Symbol::Local(order.to_string()))));
+    }
+    Some(get_assign(get_register(symbols, rd), get_intrinsic(name, args)))
+}
+
+/// Lifts a floating-point register read.
+fn get_float_register(symbols: &mut Vec<Symbol>, reg: u8) -> Expr {
+    let sym = Symbol::Reg(fp_register_name(reg).to_string());
+    symbols.push(sym.clone());
+    get_lit(Lit::Symbol(sym))
+}
+
+/// Lifts a register read, `zero` reading as the constant 0.
+fn get_register(symbols: &mut Vec<Symbol>, reg: u8) -> Expr {
+    if reg == ZERO {
+        return get_immediate(0);
+    }
+    let sym = Symbol::Reg(register_name(reg).to_string());
+    symbols.push(sym.clone());
+    get_lit(Lit::Symbol(sym))
+}
+
+/// Lifts a sign-extended immediate, negative values as a negation.
+fn get_signed(imm: i64) -> Expr {
+    if imm < 0 {
+        get_unary(UnOp::Neg, get_immediate(imm.unsigned_abs()))
+    } else {
+        get_immediate(imm as u64)
+    }
+}
+
+/// Computes the address `offset(base)`, folding a known base into a constant.
+fn get_address(symbols: &mut Vec<Symbol>, known: &HashMap<u8, u64>, base: u8, offset: i64) -> Expr {
+    if let Some(base) = known.get(&base).filter(|_| base != SP) {
+        return get_lit(Lit::U64(base.wrapping_add(offset as u64)));
+    }
+    let base = get_register(symbols, base);
+    match offset {
+        0 => base,
+        offset if offset < 0 => {
+            get_binary(BinOpKind::Sub, base, get_immediate(offset.unsigned_abs()))
+        }
+        offset => get_binary(BinOpKind::Add, base, get_immediate(offset as u64)),
+    }
+}
+
+/// Returns the operation of a register-register or register-immediate instruction.
+fn get_operator(opcode: Opcode) -> Option<BinOpKind> {
+    use Opcode::*;
+    let op = match opcode {
+        Add | Addi | Addw | Addiw => BinOpKind::Add,
+        Sub | Subw => BinOpKind::Sub,
+        And | Andi => BinOpKind::And,
+        Or | Ori => BinOpKind::Or,
+        Xor | Xori => BinOpKind::BitXor,
+        Sll | Slli | Sllw | Slliw => BinOpKind::Shl,
+        Srl | Srli | Srlw | Srliw | Sra | Srai | Sraw | Sraiw => BinOpKind::Shr,
+        Slt | Slti | Sltu | Sltiu => BinOpKind::Lt,
+        Mul | Mulw => BinOpKind::Mul,
+        Div | Divu | Divw | Divuw => BinOpKind::Div,
+        Rem | Remu | Remw | Remuw => BinOpKind::Rem,
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// Returns the type a signed or 32-bit operation takes its operands as, `None` when it
+/// reads the whole registers as unsigned. The shifts only take their first operand so.
+fn get_operand_type(opcode: Opcode) -> Option<Ty> {
+    use Opcode::*;
+    match opcode {
+        Sra | Srai | Slt | Slti | Div | Rem => Some(Ty::I64),
+        Sraw | Sraiw | Divw | Remw => Some(Ty::I32),
+        Srlw | Srliw | Divuw | Remuw => Some(Ty::U32),
+        _ => None,
+    }
+}
+
+/// Returns `true` for the 32-bit operations of RV64, which sign-extend their result.
+fn is_word(opcode: Opcode) -> bool {
+    use Opcode::*;
+    matches!(
+        opcode,
+        Addw | Addiw
+            | Subw
+            | Sllw
+            | Slliw
+            | Srlw
+            | Srliw
+            | Sraw
+            | Sraiw
+            | Mulw
+            | Divw
+            | Divuw
+            | Remw
+            | Remuw
+    )
+}
+
+/// Lifts a register compared by a branch, as signed unless the branch is unsigned.
+fn get_compared(symbols: &mut Vec<Symbol>, opcode: Opcode, reg: u8) -> Expr {
+    let value = get_register(symbols, reg);
+    match opcode {
+        Opcode::Beq | Opcode::Bne | Opcode::Bltu | Opcode::Bgeu | Opcode::Beqz | Opcode::Bnez => {
+            value
+        }
+        _ if reg == ZERO => value,
+        _ => get_cast(value, Ty::I64),
+    }
+}
+
+/// Returns the comparison a conditional branch makes, the signedness being that of its
+/// operands.
+fn get_comparison(opcode: Opcode) -> BinOpKind {
+    use Opcode::*;
+    match opcode {
+        Beq | Beqz => BinOpKind::Eq,
+        Bne | Bnez => BinOpKind::Ne,
+        Blt | Bltu | Bltz => BinOpKind::Lt,
+        Bge | Bgeu | Bgez => BinOpKind::Ge,
+        Blez => BinOpKind::Le,
+        _ => BinOpKind::Gt,
+    }
+}
+
+/// Returns the type of the memory accessed by an integer load or store.
+fn get_access_type(opcode: Opcode) -> Option<Ty> {
+    use Opcode::*;
+    let ty = match opcode {
+        Lb => Ty::I8,
+        Lbu | Sb => Ty::U8,
+        Lh => Ty::I16,
+        Lhu | Sh => Ty::U16,
+        Lw => Ty::I32,
+        Lwu | Sw => Ty::U32,
+        Ld | Sd => Ty::U64,
+        _ => return None,
+    };
+    Some(ty)
+}
@@ -0,0 +1,1641 @@
+use std::collections::BTreeMap;
+
+use cpp_demangle::{DemangleOptions, Symbol as Mangled};
+use iced_x86::{InstructionInfoFactory, Mnemonic, OpAccess, OpKind, Register};
+use object::{BinaryFormat, Object, ObjectSymbol, ObjectSymbolTable};
+use object::{RelocationFlags, RelocationTarget};
+
+use crate::abi::CallingConvention;
+use crate::arch::{Architecture, Instruction};
+use crate::cfg::Cfg;
+use crate::memory::memory::Memory;
+use crate::slots::{frame_slot, sign_extend, Slot, Slots};
+use crate::symbols::SymbolTable;
+use crate::types::{BaseKind, Prototype, Type, TypeDef, VirtualFunction};
+
+/// The vtables of the Itanium ABI classes describing a class without bases, with a single
+/// public non-virtual base and with any other bases.
+const CLASS_TYPE_INFO: [(&str, Kind); 3] = [
+    ("_ZTVN10__cxxabiv117__class_type_infoE", Kind::Root),
+    ("_ZTVN10__cxxabiv120__si_class_type_infoE", Kind::Single),
+    ("_ZTVN10__cxxabiv121__vmi_class_type_infoE", Kind::Multiple),
+];
+
+/// The functions the compilers put in the slots of pure virtual functions.
+const PURE_VIRTUAL: [&str; 2] = ["__cxa_pure_virtual", "_purecall"];
+
+/// Objects are not expected to be larger than this, which bounds `offset_to_top`.
+const MAX_OBJECT_SIZE: i64 = 0x10000;
+
+/// How deep class hierarchies are followed, in case of a cycle in corrupt data.
+const MAX_DEPTH: usize = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Root,
+    Single,
+    Multiple,
+}
+
+/// A table of virtual function pointers, of a class or of one of its base subobjects.
+pub struct VTable {
+    /// The address point objects store: that of the first virtual function pointer.
+    pub address: u64,
+    /// Where the subobject pointing to it starts in the object.
+    pub offset: i64,
+    /// The virtual functions, in slot order, with 0 for a pure virtual one.
+    pub functions: Vec<u64>,
+}
+
+/// A polymorphic class, as described by its run-time type information.
+pub struct Class {
+    pub name: String,
+    /// The type information of its direct bases, with where they start in the object.
+    pub bases: Vec<(u64, i64)>,
+    /// Its vtables, the primary one first.
+    pub vtables: Vec<VTable>,
+}
+
+/// What a register or stack slot holds, while it is known.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Value {
+    /// A pointer `offset` bytes into an object of the class.
+    Object(u64, i64),
+    /// A pointer `delta` bytes past the address point of one of the vtables of the class.
+    VTable(u64, usize, i64),
+    /// The function in a slot of one of the vtables of the class.
+    Method(u64, usize, usize),
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Location {
+    Register(Register),
+    Slot(Slot),
+}
+
+/// What the registers and stack slots hold, where it is known.
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    registers: BTreeMap<Register, Value>,
+    slots: Slots<Value>,
+}
+
+impl State {
+    fn get(&self, location: &Location) -> Option<&Value> {
+        match location {
+            Location::Register(reg) => self.registers.get(reg),
+            Location::Slot(slot) => self.slots.get(slot).map(|(_, value)| value),
+        }
+    }
+
+    fn insert(&mut self, location: Location, value: Value) {
+        match location {
+            Location::Register(reg) => {
+                self.registers.insert(reg, value);
+            }
+            // the values followed are all pointers, whose size is left out
+            Location::Slot(slot) => self.slots.insert(slot, 0, value),
+        }
+    }
+
+    fn remove(&mut self, location: &Location) {
+        match location {
+            Location::Register(reg) => {
+                self.registers.remove(reg);
+            }
+            Location::Slot(slot) => self.slots.remove(slot),
+        }
+    }
+
+    /// Keeps what `other` agrees with.
+    fn agreeing(&self, other: &State) -> State {
+        State {
+            registers: self
+                .registers
+                .iter()
+                .filter(|(reg, value)| other.registers.get(reg) == Some(value))
+                .map(|(reg, value)| (*reg, *value))
+                .collect(),
+            slots: self.slots.agreeing(&other.slots),
+        }
+    }
+}
+
+/// The classes of a C++ program, from the `type_info` objects of the Itanium ABI or the
+/// complete object locators of MSVC, by address of their type information.
+#[derive(Default)]
+pub struct Classes {
+    classes: BTreeMap<u64, Class>,
+    /// The class and vtable index of each address point.
+    address_points: BTreeMap<u64, (u64, usize)>,
+    /// The method name calls to each virtual function are shown with.
+    methods: BTreeMap<u64, String>,
+    /// The parameters of each function known to point to an object, as the index of the
+    /// parameter, the class and the offset of the subobject.
+    parameters: BTreeMap<u64, Vec<(usize, u64, i64)>>,
+    /// The words the dynamic linker fills in, by address.
+    relocated: BTreeMap<u64, u64>,
+    pointer_size: u64,
+}
+
+/// The pointer-sized words of an image, including those a shared object leaves zero for
+/// the dynamic linker to fill in.
+struct Image<'a> {
+    memory: &'a Memory,
+    relocated: &'a BTreeMap<u64, u64>,
+    size: u64,
+}
+
+impl Classes {
+    /// Reads the run-time type information and the vtables of the classes of `file`.
+    pub fn from_file(file: &object::File, memory: &Memory, symbols: &SymbolTable) -> Self {
+        let mut classes = Self {
+            pointer_size: u64::from(memory.bitness() / 8),
+            ..Self::default()
+        };
+        let relocated = relocated_words(file, memory);
+        let image = Image {
+            memory,
+            relocated: &relocated,
+            size: classes.pointer_size,
+        };
+        match file.format() {
+            BinaryFormat::Elf | BinaryFormat::MachO => classes.read_itanium(file, &image, symbols),
+            BinaryFormat::Pe => classes.read_msvc(file, &image, symbols),
+            _ => {}
+        }
+        classes.relocated = relocated;
+        for (class, info) in classes.classes.iter_mut() {
+            info.vtables.sort_by_key(|vtable| vtable.offset);
+            for (index, vtable) in info.vtables.iter().enumerate() {
+                classes
+                    .address_points
+                    .insert(vtable.address, (*class, index));
+            }
+        }
+        classes
+    }
+
+    /// Finds the `type_info` objects from the vtables their first word points into, and
+    /// the vtables from the `type_info` before their address point.
+    fn read_itanium(&mut self, file: &object::File, image: &Image, symbols: &SymbolTable) {
+        let memory = image.memory;
+        let size = self.pointer_size;
+        let mut kinds = BTreeMap::new();
+        // the dynamic linker fills the vtable pointers of shared libraries' type_info
+        if let (Some(relocations), Some(dynsym)) =
+            (file.dynamic_relocations(), file.dynamic_symbol_table())
+        {
+            for (offset, relocation) in relocations {
+                let RelocationTarget::Symbol(idx) = relocation.target() else {
+                    continue;
+                };
+                let Ok(name) = dynsym.symbol_by_index(idx).and_then(|sym| sym.name()) else {
+                    continue;
+                };
+                if let Some((_, kind)) = CLASS_TYPE_INFO.iter().find(|(other, _)| *other == name) {
+                    kinds.insert(memory.rebase(offset), *kind);
+                }
+            }
+        }
+        // while statically linked ones point into vtables of the program itself
+        let mut points = BTreeMap::new();
+        for sym in file.symbols().chain(file.dynamic_symbols()) {
+            let Ok(name) = sym.name() else {
+                continue;
+            };
+            let name = name
+                .strip_prefix('_')
+                .filter(|_| name.starts_with("__Z"))
+                .unwrap_or(name);
+            if let Some((_, kind)) = CLASS_TYPE_INFO.iter().find(|(other, _)| *other == name) {
+                if !sym.is_undefined() && sym.address() != 0 {
+                    points.insert(memory.rebase(sym.address()) + 2 * size, *kind);
+                }
+            }
+        }
+        if !points.is_empty() {
+            for (addr, value) in image.data_words() {
+                if let Some(kind) = points.get(&value) {
+                    kinds.insert(addr, *kind);
+                }
+            }
+        }
+
+        for (info, kind) in kinds.iter() {
+            let Some(name) = image
+                .word(info + size)
+                .and_then(|addr| memory.read_c_string(addr))
+                .and_then(|name| demangle(&format!("_ZTS{name}"), false))
+                .and_then(|name| name.strip_prefix("typeinfo name for ").map(str::to_string))
+            else {
+                continue;
+            };
+            let bases = match kind {
+                Kind::Root => Vec::new(),
+                Kind::Single => image
+                    .word(info + 2 * size)
+                    .map(|base| vec![(base, 0)])
+                    .unwrap_or_default(),
+                Kind::Multiple => {
+                    let count = read_u32(memory, info + 2 * size + 4).unwrap_or(0);
+                    (0..u64::from(count.min(64)))
+                        .filter_map(|i| {
+                            let entry = info + 3 * size + i * 2 * size;
+                            let base = image.word(entry)?;
+                            let flags = image.word(entry + size)?;
+                            // the offset is in the bits above the flags, signed
+                            let flags = sign_extend(flags, size as u32 * 8);
+                            Some((base, flags >> 8))
+                        })
+                        .collect()
+                }
+            };
+            self.classes.insert(
+                *info,
+                Class {
+                    name,
+                    bases,
+                    vtables: Vec::new(),
+                },
+            );
+        }
+
+        // a vtable is the offset to the top of the object, the type_info and the functions
+        for (addr, value) in image.data_words() {
+            if !self.classes.contains_key(&value) || addr < size {
+                continue;
+            }
+            let Some(to_top) = image.word(addr - size) else {
+                continue;
+            };
+            let to_top = sign_extend(to_top, size as u32 * 8);
+            if !(-MAX_OBJECT_SIZE..=0).contains(&to_top) {
+                continue;
+            }
+            let address = addr + size;
+            let functions = image.functions(symbols, address);
+            if !functions.is_empty() {
+                self.classes.get_mut(&value).unwrap().vtables.push(VTable {
+                    address,
+                    offset: -to_top,
+                    functions,
+                });
+            }
+        }
+    }
+
+    /// Finds the complete object locators MSVC puts before each vtable, and the classes
+    /// from the type descriptors and class hierarchies they point to.
+    fn read_msvc(&mut self, file: &object::File, image: &Image, symbols: &SymbolTable) {
+        let memory = image.memory;
+        let size = self.pointer_size;
+        // 64-bit images refer to the structures by their offset from the image base
+        let base = memory.rebase(file.relative_address_base());
+        let pointer = |addr: u64| match size {
+            8 => read_u32(memory, addr).map(|rva| base + u64::from(rva)),
+            _ => read_u32(memory, addr).map(u64::from),
+        };
+        let type_name = |descriptor: u64| {
+            memory
+                .read_c_string(descriptor + 2 * size)
+                .and_then(|name| msvc_type_name(&name))
+        };
+
+        let mut locators = BTreeMap::new();
+        for (range, _) in memory.sections() {
+            if memory.is_executable(range.start) || !memory.is_read_only(range.start) {
+                continue;
+            }
+            for addr in (range.start..range.end.saturating_sub(20)).step_by(4) {
+                let signature = read_u32(memory, addr);
+                let valid = match size {
+                    8 => signature == Some(1) && pointer(addr + 20) == Some(addr),
+                    _ => signature == Some(0),
+                };
+                if !valid {
+                    continue;
+                }
+                let (Some(offset), Some(descriptor), Some(hierarchy)) = (
+                    read_u32(memory, addr + 4),
+                    pointer(addr + 12),
+                    pointer(addr + 16),
+                ) else {
+                    continue;
+                };
+                let Some(name) = type_name(descriptor) else {
+                    continue;
+                };
+                locators.insert(addr, (descriptor, i64::from(offset)));
+                // the base class array starts with the class itself, followed by each base
+                // and the bases it contains in turn
+                let count = read_u32(memory, hierarchy + 8).unwrap_or(0).min(64);
+                let mut bases = Vec::new();
+                let mut i = 1;
+                while i < count {
+                    let Some(entry) =
+                        pointer(hierarchy + 12).and_then(|array| pointer(array + 4 * u64::from(i)))
+                    else {
+                        break;
+                    };
+                    let (Some(base), Some(contained), Some(displacement)) = (
+                        pointer(entry),
+                        read_u32(memory, entry + 4),
+                        read_u32(memory, entry + 8),
+                    ) else {
+                        break;
+                    };
+                    // added without their own bases until their own locator is found
+                    if let Some(base_name) = type_name(base) {
+                        bases.push((base, i64::from(displacement as i32)));
+                        self.classes.entry(base).or_insert(Class {
+                            name: base_name,
+                            bases: Vec::new(),
+                            vtables: Vec::new(),
+                        });
+                    }
+                    i += 1 + contained;
+                }
+                self.classes.insert(
+                    descriptor,
+                    Class {
+                        name,
+                        bases,
+                        vtables: Vec::new(),
+                    },
+                );
+            }
+        }
+        // a vtable follows a pointer to its locator
+        for (addr, value) in image.data_words() {
+            let Some((descriptor, offset)) = locators.get(&value) else {
+                continue;
+            };
+            let address = addr + size;
+            let functions = image.functions(symbols, address);
+            if !functions.is_empty() {
+                self.classes
+                    .get_mut(descriptor)
+                    .unwrap()
+                    .vtables
+                    .push(VTable {
+                        address,
+                        offset: *offset,
+                        functions,
+                    });
+            }
+        }
+    }
+
+    /// Names the virtual functions no symbol names `Class::vfunc_N` after the first class
+    /// they are found in, from the root classes down, or `Class::vfunc_8_N` in the vtable
+    /// of a base 8 bytes into the object, and notes what their calls are shown as and
+    /// which parameters of the functions point to objects.
+    pub fn name_functions(&mut self, symbols: &mut SymbolTable) {
+        let mut order = self.classes.keys().copied().collect::<Vec<_>>();
+        order.sort_by_key(|class| self.depth(*class, 0));
+        let mut this = BTreeMap::new();
+        for class in order.iter() {
+            let info = &self.classes[class];
+            for vtable in info.vtables.iter() {
+                for (slot, func) in vtable.functions.iter().enumerate() {
+                    if *func == 0 {
+                        continue;
+                    }
+                    let name = slot_name(vtable.offset, slot);
+                    symbols.insert_function(*func, &format!("{}::{name}", info.name));
+                    this.entry(*func).or_insert((*class, vtable.offset));
+                }
+            }
+        }
+        for func in this.keys() {
+            let name = symbols.function_name(*func);
+            let name = demangle(&name, false).unwrap_or(name);
+            self.methods
+                .insert(*func, last_component(&name).to_string());
+        }
+
+        let names = self
+            .classes
+            .iter()
+            .map(|(class, info)| (info.name.as_str(), *class))
+            .collect::<BTreeMap<_, _>>();
+        let mut parameters = BTreeMap::new();
+        for func in symbols.functions() {
+            let Some(name) = symbols.get(func).and_then(|name| demangle(name, true)) else {
+                continue;
+            };
+            let open = name.find('(').unwrap_or(name.len());
+            let mut known = Vec::new();
+            // methods take the object as their first parameter
+            let qualified = &name[..open];
+            let method = qualified
+                .rfind("::")
+                .and_then(|end| names.get(&qualified[..end]));
+            if let Some(class) = method {
+                known.push((0, *class, 0));
+            }
+            let first = usize::from(method.is_some());
+            for (i, param) in split_parameters(&name[open..]).into_iter().enumerate() {
+                let ty = param
+                    .strip_suffix(['*', '&'])
+                    .map(|ty| ty.trim_end().trim_end_matches(" const"))
+                    .map(|ty| ty.trim_start_matches("const "));
+                if let Some(class) = ty.and_then(|ty| names.get(ty)) {
+                    known.push((first + i, *class, 0));
+                }
+            }
+            if !known.is_empty() {
+                parameters.insert(func, known);
+            }
+        }
+        for (func, (class, offset)) in this {
+            parameters.entry(func).or_insert(vec![(0, class, offset)]);
+        }
+        self.parameters = parameters;
+    }
+
+    /// Returns the number of classes above `class`, through its first bases.
+    fn depth(&self, class: u64, level: usize) -> usize {
+        match self.classes.get(&class).and_then(|info| info.bases.first()) {
+            Some((base, _)) if level < MAX_DEPTH => 1 + self.depth(*base, level + 1),
+            _ => 0,
+        }
+    }
+
+    /// Returns where a `base` subobject starts in an object of `class`, if it derives
+    /// from it.
+    fn base_offset(&self, class: u64, base: u64, level: usize) -> Option<i64> {
+        if class == base {
+            return Some(0);
+        }
+        if level >= MAX_DEPTH {
+            return None;
+        }
+        self.classes
+            .get(&class)?
+            .bases
+            .iter()
+            .find_map(|(other, offset)| Some(offset + self.base_offset(*other, base, level + 1)?))
+    }
+
+    /// Returns the vtable pointed to at `offset` in an object of `class`. Compilers leave
+    /// out those of abstract classes, which then share that of the first class below.
+    fn vtable_at(&self, class: u64, offset: i64) -> Option<(u64, usize)> {
+        let mut derived = self
+            .classes
+            .keys()
+            .filter_map(|other| Some((*other, self.base_offset(*other, class, 0)?)))
+            .collect::<Vec<_>>();
+        derived.sort_by_key(|(other, _)| self.depth(*other, 0));
+        derived.into_iter().find_map(|(other, start)| {
+            let vtables = &self.classes[&other].vtables;
+            let index = vtables
+                .iter()
+                .position(|vtable| vtable.offset == start + offset)?;
+            Some((other, index))
+        })
+    }
+
+    /// Returns the name calls through a slot are shown with: that of the function in it,
+    /// or for a pure virtual function that of a function overriding it.
+    fn method_name(&self, class: u64, vtable: usize, slot: usize) -> String {
+        let offset = self.classes[&class].vtables[vtable].offset;
+        let overrides = self
+            .classes
+            .iter()
+            .filter_map(|(other, info)| Some((info, self.base_offset(*other, class, 0)?)))
+            .flat_map(|(info, start)| {
+                info.vtables
+                    .iter()
+                    .filter(move |other| other.offset == start + offset)
+            })
+            .filter_map(|other| other.functions.get(slot));
+        let mut names = overrides.filter_map(|func| self.methods.get(func));
+        match names.next() {
+            Some(name) => name.clone(),
+            None => slot_name(offset, slot),
+        }
+    }
+
+    /// Returns the definitions of the classes, with the functions in their vtables.
+    pub fn type_defs(
+        &self,
+        symbols: &SymbolTable,
+        prototypes: &BTreeMap<u64, Prototype>,
+    ) -> Vec<TypeDef> {
+        let mut order = self.classes.keys().copied().collect::<Vec<_>>();
+        order.sort_by_key(|class| self.depth(*class, 0));
+        order
+            .into_iter()
+            .map(|class| {
+                let info = &self.classes[&class];
+                let bases = info
+                    .bases
+                    .iter()
+                    .filter_map(|(base, _)| self.classes.get(base))
+                    .map(|base| base.name.clone())
+                    .collect();
+                if info.vtables.is_empty() {
+                    return TypeDef::Class {
+                        name: info.name.clone(),
+                        bases,
+                        vtables: self.inherited_vtables(class, symbols, prototypes),
+                    };
+                }
+                let vtables = info
+                    .vtables
+                    .iter()
+                    .enumerate()
+                    .map(|(index, vtable)| {
+                        let functions = vtable
+                            .functions
+                            .iter()
+                            .enumerate()
+                            .map(|(slot, func)| {
+                                let method = self.method_name(class, index, slot);
+                                let declaration = match func {
+                                    0 => None,
+                                    func => Some(member_declaration(
+                                        &info.name,
+                                        &symbols.function_name(*func),
+                                        prototypes.get(func),
+                                    )),
+                                };
+                                VirtualFunction {
+                                    method,
+                                    declaration,
+                                    address: *func,
+                                }
+                            })
+                            .collect();
+                        (vtable.address, functions)
+                    })
+                    .collect();
+                TypeDef::Class {
+                    name: info.name.clone(),
+                    bases,
+                    vtables,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the virtual functions of `class` when the compiler left its vtables out, as
+    /// for an abstract class, from the slots laid out for it in those of the classes derived
+    /// from it. A primary vtable is taken as long as the shortest of them, the slots past it
+    /// being those the derived classes add. The functions in the slots are given with their
+    /// address when they are those of `class` itself, not overriders.
+    fn inherited_vtables(
+        &self,
+        class: u64,
+        symbols: &SymbolTable,
+        prototypes: &BTreeMap<u64, Prototype>,
+    ) -> Vec<(u64, Vec<VirtualFunction>)> {
+        let name = &self.classes[&class].name;
+        let Some((derived, index)) = self.vtable_at(class, 0) else {
+            return Vec::new();
+        };
+        let Some(start) = self.base_offset(derived, class, 0) else {
+            return Vec::new();
+        };
+        let offset = self.classes[&derived].vtables[index].offset - start;
+        let slots = self
+            .classes
+            .iter()
+            .filter_map(|(other, info)| {
+                let start = self.base_offset(*other, class, 0)?;
+                let vtable = info.vtables.iter().find(|vtable| vtable.offset == start + offset)?;
+                Some(vtable.functions.len())
+            })
+            .min()
+            .unwrap_or_default();
+        let vtable = &self.classes[&derived].vtables[index];
+        let functions = vtable.functions[..slots]
+            .iter()
+            .enumerate()
+            .map(|(slot, func)| {
+                let method = self.method_name(derived, index, slot);
+                let (declaration, address) = match func {
+                    0 => (None, 0),
+                    func => {
+                        let function = symbols.function_name(*func);
+                        let own = demangle(&function, false)
+                            .is_some_and(|qualified| qualified.starts_with(&format!("{name}::")));
+                        let declaration =
+                            inherited_declaration(name, &function, prototypes.get(func));
+                        (Some(declaration), if own { *func } else { 0 })
+                    }
+                };
+                VirtualFunction {
+                    method,
+                    declaration,
+                    address,
+                }
+            })
+            .collect();
+        vec![(0, functions)]
+    }
+
+    /// Follows the objects and vtables the registers and stack slots of the function at
+    /// `address` point to, from its parameters and the vtable pointers it stores or
+    /// loads, and returns the method called by each call through one of their slots.
+    pub fn virtual_calls(
+        &self,
+        cfg: &Cfg,
+        address: u64,
+        convention: CallingConvention,
+        memory: &Memory,
+    ) -> BTreeMap<u64, String> {
+        let mut calls = BTreeMap::new();
+        if self.classes.is_empty() || !matches!(memory.architecture(), Architecture::X86 { .. }) {
+            return calls;
+        }
+        let mut entry = State::default();
+        for (index, class, offset) in self.parameters.get(&address).into_iter().flatten() {
+            if let Some(reg) = convention.argument_registers().get(*index) {
+                entry.insert(
+                    Location::Register(reg.full_register()),
+                    Value::Object(*class, *offset),
+                );
+            }
+        }
+        let mut successors = BTreeMap::<u64, Vec<u64>>::new();
+        for edge in cfg.edges.iter() {
+            successors.entry(edge.from).or_default().push(edge.to);
+        }
+
+        // what every path agrees on, which only shrinks until nothing changes
+        let mut factory = InstructionInfoFactory::new();
+        let mut states = BTreeMap::from([(address, entry)]);
+        let mut worklist = vec![address];
+        while let Some(start) = worklist.pop() {
+            let Some(block) = cfg.blocks.get(&start) else {
+                continue;
+            };
+            let mut state = states[&start].clone();
+            for inst in block.instructions.iter().filter_map(Instruction::as_x86) {
+                self.step(&mut state, inst, convention, memory, &mut factory);
+            }
+            for to in successors.get(&start).into_iter().flatten() {
+                let merged = match states.get(to) {
+                    Some(old) => old.agreeing(&state),
+                    None => state.clone(),
+                };
+                if states.get(to) != Some(&merged) {
+                    states.insert(*to, merged);
+                    worklist.push(*to);
+                }
+            }
+        }
+
+        let this = convention
+            .argument_registers()
+            .first()
+            .map(|reg| reg.full_register());
+        for (start, block) in cfg.blocks.iter() {
+            let Some(mut state) = states.get(start).cloned() else {
+                continue;
+            };
+            // the vtable pointers loaded from objects of no known class, with the registers
+            // still pointing to the object
+            let mut vptrs = BTreeMap::<Register, Vec<Register>>::new();
+            for inst in block.instructions.iter().filter_map(Instruction::as_x86) {
+                if inst.mnemonic() == Mnemonic::Call {
+                    let target = match inst.op0_kind() {
+                        OpKind::Memory => self.load(&state, inst),
+                        OpKind::Register => state
+                            .get(&Location::Register(inst.op0_register().full_register()))
+                            .copied(),
+                        _ => None,
+                    };
+                    if let Some(Value::Method(class, vtable, slot)) = target {
+                        calls.insert(inst.ip(), self.method_name(class, vtable, slot));
+                    } else if let Some(slot) = self.unknown_slot(inst, &vptrs, this) {
+                        calls.insert(inst.ip(), self.slot_method(slot));
+                    }
+                }
+                self.step(&mut state, inst, convention, memory, &mut factory);
+                self.step_vptrs(&mut vptrs, &state, inst, convention, &mut factory);
+            }
+        }
+        calls
+    }
+
+    /// Returns the slot a call through the vtable of an object of no known class goes
+    /// through, when the object is passed as `this` like to a virtual function.
+    fn unknown_slot(
+        &self,
+        inst: &iced_x86::Instruction,
+        vptrs: &BTreeMap<Register, Vec<Register>>,
+        this: Option<Register>,
+    ) -> Option<usize> {
+        if inst.op0_kind() != OpKind::Memory || inst.memory_index() != Register::None {
+            return None;
+        }
+        let objects = vptrs.get(&inst.memory_base().full_register())?;
+        let displacement = inst.memory_displacement64() as i64;
+        let size = self.pointer_size as i64;
+        if !objects.contains(&this?) || displacement < 0 || displacement % size != 0 {
+            return None;
+        }
+        Some((displacement / size) as usize)
+    }
+
+    /// Returns the name calls through a slot of the vtable of an object of no known class
+    /// are shown with: that of the functions all primary vtables have in it, if they agree,
+    /// or else after the slot.
+    fn slot_method(&self, slot: usize) -> String {
+        let mut names = self
+            .classes
+            .values()
+            .flat_map(|info| info.vtables.iter().filter(|vtable| vtable.offset == 0))
+            .filter_map(|vtable| vtable.functions.get(slot))
+            .filter_map(|func| self.methods.get(func));
+        let first = names.next();
+        match first {
+            Some(name) if names.all(|other| other == name) => name.clone(),
+            _ => slot_name(0, slot),
+        }
+    }
+
+    /// Updates the vtable pointers `vptrs` knows of objects of no known class after `inst`,
+    /// with the registers pointing to each object.
+    fn step_vptrs(
+        &self,
+        vptrs: &mut BTreeMap<Register, Vec<Register>>,
+        state: &State,
+        inst: &iced_x86::Instruction,
+        convention: CallingConvention,
+        factory: &mut InstructionInfoFactory,
+    ) {
+        let pointer = |reg: Register| reg.size() as u64 == self.pointer_size;
+        let written = match (inst.mnemonic(), inst.op0_kind(), inst.op1_kind()) {
+            (Mnemonic::Mov, OpKind::Register, OpKind::Register) if pointer(inst.op0_register()) => {
+                let from = inst.op1_register().full_register();
+                let copies = vptrs
+                    .iter()
+                    .filter(|(_, objects)| objects.contains(&from))
+                    .map(|(vptr, _)| *vptr)
+                    .collect::<Vec<_>>();
+                Some((None, copies))
+            }
+            // the vtable pointer at the start of an object
+            (Mnemonic::Mov, OpKind::Register, OpKind::Memory)
+                if pointer(inst.op0_register())
+                    && inst.memory_index() == Register::None
+                    && inst.memory_displacement64() == 0
+                    && inst.memory_base().is_gpr()
+                    && !matches!(
+                        inst.memory_base().full_register(),
+                        Register::RSP | Register::RBP
+                    )
+                    && !state.registers.contains_key(&inst.op0_register().full_register()) =>
+            {
+                let base = inst.memory_base().full_register();
+                let mut objects = vptrs
+                    .values()
+                    .find(|objects| objects.contains(&base))
+                    .cloned()
+                    .unwrap_or_default();
+                if !objects.contains(&base) {
+                    objects.push(base);
+                }
+                Some((Some(objects), Vec::new()))
+            }
+            _ => None,
+        };
+        let mut forget = |reg: Register| {
+            vptrs.remove(&reg);
+            for objects in vptrs.values_mut() {
+                objects.retain(|other| *other != reg);
+            }
+        };
+        if inst.mnemonic() == Mnemonic::Call {
+            let preserved = convention.preserved_registers();
+            let clobbered = [Register::RAX, Register::RCX, Register::RDX, Register::RSI]
+                .into_iter()
+                .chain([Register::RDI, Register::R8, Register::R9, Register::R10])
+                .chain([Register::R11, Register::EAX, Register::ECX, Register::EDX]);
+            for reg in clobbered {
+                if !preserved.iter().any(|other| other.full_register() == reg) {
+                    forget(reg);
+                }
+            }
+            return;
+        }
+        let info = factory.info(inst);
+        for used in info.used_registers() {
+            if matches!(
+                used.access(),
+                OpAccess::Write
+                    | OpAccess::CondWrite
+                    | OpAccess::ReadWrite
+                    | OpAccess::ReadCondWrite
+            ) {
+                forget(used.register().full_register());
+            }
+        }
+        let reg = inst.op0_register().full_register();
+        match written {
+            Some((Some(objects), _)) => {
+                vptrs.insert(reg, objects);
+            }
+            Some((None, copies)) => {
+                for vptr in copies {
+                    if let Some(objects) = vptrs.get_mut(&vptr) {
+                        objects.push(reg);
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Updates what `state` knows after `inst`.
+    fn step(
+        &self,
+        state: &mut State,
+        inst: &iced_x86::Instruction,
+        convention: CallingConvention,
+        memory: &Memory,
+        factory: &mut InstructionInfoFactory,
+    ) {
+        if inst.mnemonic() == Mnemonic::Call {
+            state.registers.retain(|reg, _| {
+                convention
+                    .preserved_registers()
+                    .iter()
+                    .any(|preserved| preserved.full_register() == *reg)
+            });
+            return;
+        }
+        let pointer = |reg: Register| reg.size() as u64 == self.pointer_size;
+        let written = match (inst.mnemonic(), inst.op0_kind()) {
+            (Mnemonic::Mov, OpKind::Register) if pointer(inst.op0_register()) => {
+                let value = match inst.op1_kind() {
+                    OpKind::Register => state
+                        .get(&Location::Register(inst.op1_register().full_register()))
+                        .copied(),
+                    OpKind::Memory => self.load(state, inst),
+                    OpKind::Immediate32 | OpKind::Immediate32to64 | OpKind::Immediate64 => {
+                        self.constant(inst.immediate(1), memory)
+                    }
+                    _ => None,
+                };
+                Some((
+                    Location::Register(inst.op0_register().full_register()),
+                    value,
+                ))
+            }
+            (Mnemonic::Lea, _) if pointer(inst.op0_register()) => {
+                let value = match inst.memory_base() {
+                    _ if inst.memory_index() != Register::None => None,
+                    Register::None | Register::RIP | Register::EIP => {
+                        self.constant(inst.memory_displacement64(), memory)
+                    }
+                    base => state
+                        .get(&Location::Register(base.full_register()))
+                        .and_then(|value| add(*value, inst.memory_displacement64() as i64)),
+                };
+                Some((
+                    Location::Register(inst.op0_register().full_register()),
+                    value,
+                ))
+            }
+            (Mnemonic::Add | Mnemonic::Sub, OpKind::Register)
+                if pointer(inst.op0_register())
+                    && matches!(
+                        inst.op1_kind(),
+                        OpKind::Immediate8to64
+                            | OpKind::Immediate32to64
+                            | OpKind::Immediate8to32
+                            | OpKind::Immediate32
+                    ) =>
+            {
+                let delta = match inst.mnemonic() {
+                    Mnemonic::Add => inst.immediate(1) as i64,
+                    _ => (inst.immediate(1) as i64).wrapping_neg(),
+                };
+                let delta = sign_extend(delta as u64, self.pointer_size as u32 * 8);
+                let place = Location::Register(inst.op0_register().full_register());
+                let value = state.get(&place).and_then(|value| add(*value, delta));
+                Some((place, value))
+            }
+            (Mnemonic::Mov, OpKind::Memory) if inst.memory_index() == Register::None => {
+                let value = match inst.op1_kind() {
+                    OpKind::Register if pointer(inst.op1_register()) => state
+                        .get(&Location::Register(inst.op1_register().full_register()))
+                        .copied(),
+                    OpKind::Immediate32 | OpKind::Immediate32to64 => {
+                        self.constant(inst.immediate(1), memory)
+                    }
+                    _ => None,
+                };
+                let base = inst.memory_base().full_register();
+                let displacement = inst.memory_displacement64() as i64;
+                match (base, value) {
+                    (Register::RSP | Register::RBP, _) => {
+                        frame_slot(inst, None).map(|slot| (Location::Slot(slot), value))
+                    }
+                    (Register::None | Register::RIP, _) => None,
+                    // a constructor storing the vtable pointer into the object
+                    (base, Some(Value::VTable(class, index, 0))) => {
+                        let offset = self.classes[&class].vtables[index].offset;
+                        Some((
+                            Location::Register(base),
+                            Some(Value::Object(class, offset - displacement)),
+                        ))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let info = factory.info(inst);
+        for used in info.used_registers() {
+            if matches!(
+                used.access(),
+                OpAccess::Write
+                    | OpAccess::CondWrite
+                    | OpAccess::ReadWrite
+                    | OpAccess::ReadCondWrite
+            ) {
+                state.registers.remove(&used.register().full_register());
+            }
+        }
+        // stores through the frame pointer or the stack pointer forget the slots they overlap
+        state.slots.forget(info, None, false);
+        if let Some((place, value)) = written {
+            match value {
+                Some(value) => state.insert(place, value),
+                None => state.remove(&place),
+            };
+        }
+    }
+
+    /// Returns what is loaded by the memory operand of `inst`: the vtable pointer of an
+    /// object, a function from a vtable or a stack slot.
+    fn load(&self, state: &State, inst: &iced_x86::Instruction) -> Option<Value> {
+        if inst.memory_index() != Register::None {
+            return None;
+        }
+        let base = inst.memory_base().full_register();
+        let displacement = inst.memory_displacement64() as i64;
+        if let Some(slot) = frame_slot(inst, None) {
+            return state.get(&Location::Slot(slot)).copied();
+        }
+        match state.get(&Location::Register(base))? {
+            Value::Object(class, offset) => {
+                let (class, index) = self.vtable_at(*class, offset + displacement)?;
+                Some(Value::VTable(class, index, 0))
+            }
+            Value::VTable(class, index, delta) => {
+                let at = delta + displacement;
+                let size = self.pointer_size as i64;
+                let vtable = &self.classes.get(class)?.vtables[*index];
+                if at < 0 || at % size != 0 || at / size >= vtable.functions.len() as i64 {
+                    return None;
+                }
+                Some(Value::Method(*class, *index, (at / size) as usize))
+            }
+            Value::Method(..) => None,
+        }
+    }
+
+    /// Returns what an address the code uses points to: the address point of a vtable, or
+    /// a global object starting with a vtable pointer.
+    fn constant(&self, addr: u64, memory: &Memory) -> Option<Value> {
+        if let Some((class, index)) = self.address_points.get(&addr) {
+            return Some(Value::VTable(*class, *index, 0));
+        }
+        if memory.is_executable(addr) {
+            return None;
+        }
+        let image = Image {
+            memory,
+            relocated: &self.relocated,
+            size: self.pointer_size,
+        };
+        let word = image.word(addr)?;
+        let (class, index) = self.address_points.get(&word)?;
+        let offset = self.classes[class].vtables[*index].offset;
+        Some(Value::Object(*class, offset))
+    }
+}
+
+/// Returns the name of an unnamed virtual function, after its slot and the offset of the
+/// subobject pointing to its vtable.
+fn slot_name(offset: i64, slot: usize) -> String {
+    match offset {
+        0 => format!("vfunc_{slot}"),
+        _ => format!("vfunc_{offset:X}_{slot}"),
+    }
+}
+
+/// Returns what `value` points to `delta` bytes further.
+fn add(value: Value, delta: i64) -> Option<Value> {
+    match value {
+        Value::Object(class, offset) => Some(Value::Object(class, offset + delta)),
+        Value::VTable(class, index, offset) => Some(Value::VTable(class, index, offset + delta)),
+        Value::Method(..) => None,
+    }
+}
+
+impl Image<'_> {
+    fn word(&self, addr: u64) -> Option<u64> {
+        match self.relocated.get(&addr) {
+            Some(value) => Some(*value),
+            None => read_word(self.memory, addr, self.size),
+        }
+    }
+
+    /// Iterates over the aligned words of the sections which do not hold code, with their
+    /// address.
+    fn data_words(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        let size = self.size;
+        self.memory
+            .sections()
+            .filter(|(range, _)| !self.memory.is_executable(range.start))
+            .flat_map(move |(range, _)| {
+                let start = range.start.div_ceil(size) * size;
+                (start..range.end.saturating_sub(size - 1))
+                    .step_by(size as usize)
+                    .filter_map(move |addr| self.word(addr).map(|word| (addr, word)))
+            })
+    }
+
+    /// Reads the function pointers of a vtable from its address point, up to the first
+    /// word which is neither code nor the slot of a pure virtual function.
+    fn functions(&self, symbols: &SymbolTable, address: u64) -> Vec<u64> {
+        let pure = |name: Option<&str>| name.is_some_and(|name| PURE_VIRTUAL.contains(&name));
+        let mut functions = Vec::new();
+        let mut slot = address;
+        while let Some(func) = self.word(slot) {
+            if self.memory.is_executable(func) && !pure(symbols.get(func)) {
+                functions.push(func);
+            } else if (func == 0 || self.memory.is_executable(func))
+                && (pure(symbols.get(slot)) || pure(symbols.get(func)))
+            {
+                // left for the dynamic linker, which names the slot after the symbol
+                functions.push(0);
+            } else {
+                break;
+            }
+            slot += self.size;
+        }
+        functions
+    }
+}
+
+/// Returns the words the absolute and relative dynamic relocations of an x86 ELF file set,
+/// by address.
+fn relocated_words(file: &object::File, memory: &Memory) -> BTreeMap<u64, u64> {
+    let mut words = BTreeMap::new();
+    let (absolute, relative, size) = match memory.architecture() {
+        Architecture::X86 { bitness: 64 } => {
+            (object::elf::R_X86_64_64, object::elf::R_X86_64_RELATIVE, 8)
+        }
+        Architecture::X86 { bitness: 32 } => {
+            (object::elf::R_386_32, object::elf::R_386_RELATIVE, 4)
+        }
+        _ => return words,
+    };
+    let (Some(relocations), Some(dynsym)) =
+        (file.dynamic_relocations(), file.dynamic_symbol_table())
+    else {
+        return words;
+    };
+    for (offset, relocation) in relocations {
+        let RelocationFlags::Elf { r_type } = relocation.flags() else {
+            continue;
+        };
+        let addr = memory.rebase(offset);
+        let addend = match relocation.has_implicit_addend() {
+            true => read_word(memory, addr, size).unwrap_or(0),
+            false => relocation.addend() as u64,
+        };
+        let value = match relocation.target() {
+            _ if r_type == relative => memory.rebase(addend),
+            RelocationTarget::Symbol(idx) if r_type == absolute => {
+                match dynsym.symbol_by_index(idx) {
+                    Ok(sym) if !sym.is_undefined() => {
+                        memory.rebase(sym.address()).wrapping_add(addend)
+                    }
+                    _ => continue,
+                }
+            }
+            _ => continue,
+        };
+        words.insert(addr, value);
+    }
+    words
+}
+
+fn read_u32(memory: &Memory, addr: u64) -> Option<u32> {
+    let bytes = memory.get(addr)?.get(..4)?;
+    Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_word(memory: &Memory, addr: u64, size: u64) -> Option<u64> {
+    let bytes = memory.get(addr)?.get(..size as usize)?;
+    let mut word = [0; 8];
+    word[..bytes.len()].copy_from_slice(bytes);
+    Some(u64::from_le_bytes(word))
+}
+
+/// Returns the qualified name of the C++ function `name`, without its parameters, like
+/// `operator new@plt` for `_Znwm@plt`, or `name` itself when it is not mangled.
+pub fn display_name(name: &str) -> String {
+    let (dll, base, suffix) = split_stub(name);
+    match demangle(base, false) {
+        Some(demangled) => format!("{dll}{demangled}{suffix}"),
+        None => name.to_string(),
+    }
+}
+
+/// Splits `name` into the DLL of an import, with its `!`, the symbol, and the suffix of a
+/// stub or PLT entry, like `@plt`.
+fn split_stub(name: &str) -> (&str, &str, &str) {
+    let (dll, rest) = match name.rsplit_once('!') {
+        Some((dll, rest)) => (&name[..=dll.len()], rest),
+        None => ("", name),
+    };
+    let base = match rest.starts_with('?') {
+        true => rest,
+        false => rest.split('@').next().unwrap_or(rest),
+    };
+    (dll, base, &rest[base.len()..])
+}
+
+/// Demangles an Itanium ABI name, with the parameter types of functions or without, or
+/// the qualified name of an MSVC decorated one.
+fn demangle(name: &str, parameters: bool) -> Option<String> {
+    if name.starts_with('?') {
+        return msvc_function_name(name);
+    }
+    // Mach-O symbols have an extra leading underscore
+    let name = name
+        .strip_prefix('_')
+        .filter(|name| name.starts_with("_Z"))
+        .unwrap_or(name);
+    if !name.starts_with("_Z") {
+        return None;
+    }
+    let options = match parameters {
+        true => DemangleOptions::new().no_return_type(),
+        false => DemangleOptions::new().no_params().no_return_type(),
+    };
+    let name = Mangled::new(name.as_bytes())
+        .ok()?
+        .demangle(&options)
+        .ok()?;
+    // thunks adjusting `this` are shown as the function they go to
+    if name.starts_with("{virtual override thunk(") {
+        let start = name.find("}, ")? + 3;
+        return Some(name.get(start..name.len().checked_sub(2)?)?.to_string());
+    }
+    Some(name)
+}
+
+/// Spells the declaration of a virtual function `name` of `class`, with its return type
+/// and parameters from its decoration or else from its lifted `prototype`, less `this`.
+fn member_declaration(class: &str, name: &str, prototype: Option<&Prototype>) -> String {
+    let qualified = demangle(name, true).unwrap_or_else(|| name.to_string());
+    let own = qualified
+        .strip_prefix(&format!("{class}::"))
+        .unwrap_or(&qualified);
+    declare(class, name, own, prototype)
+}
+
+/// Spells the declaration in `class` of the virtual function a class derived from it puts
+/// in one of its slots, `name`: that of the function it overrides, like `~Animal()` for
+/// `zoo::Dog::~Dog()`.
+fn inherited_declaration(class: &str, name: &str, prototype: Option<&Prototype>) -> String {
+    let qualified = demangle(name, false).unwrap_or_else(|| name.to_string());
+    let parameters = demangle(name, true)
+        .and_then(|declarator| Some(declarator.strip_prefix(&qualified)?.to_string()))
+        .unwrap_or_default();
+    let component = last_component(&qualified);
+    let own = match component.starts_with('~') {
+        true => format!("~{}{parameters}", last_component(class)),
+        false => format!("{component}{parameters}"),
+    };
+    declare(class, name, &own, prototype)
+}
+
+/// Spells the declaration the definition of the C++ function `name` is headed with: its
+/// qualified name with the parameters and qualifiers its decoration spells, and the return
+/// type of its `prototype`, like `undefined8 zoo::Dog::speak() const`. Those with no
+/// prototype, like the imported operators, return a pointer, but for `operator delete`.
+/// `None` when the name is not mangled.
+pub fn declaration(name: &str, prototype: Option<&Prototype>) -> Option<String> {
+    let (dll, base, suffix) = split_stub(name);
+    let qualified = demangle(base, false)?;
+    let parameters = demangle(base, true)
+        .and_then(|declarator| Some(declarator.strip_prefix(&qualified)?.to_string()))
+        .unwrap_or_default();
+    let component = last_component(&qualified);
+    let class = qualified[..qualified.len() - component.len()].trim_end_matches("::");
+    let default = Prototype {
+        return_type: match component {
+            "operator delete" | "operator delete[]" => Type::Void,
+            _ => Type::Pointer(Box::new(Type::Void)),
+        },
+        parameters: Vec::new(),
+        variadic: false,
+        noreturn: false,
+    };
+    let prototype = prototype.unwrap_or(&default);
+    let own = format!("{dll}{qualified}{suffix}{parameters}");
+    let declaration = declare(class, base, &own, Some(prototype));
+    Some(match prototype.noreturn {
+        true => format!("_Noreturn {declaration}"),
+        false => declaration,
+    })
+}
+
+/// Spells the declaration of the function `name` of `class`, shown as `own`, with its return
+/// type and parameters from its decoration or else from its lifted `prototype`, less `this`.
+fn declare(class: &str, name: &str, own: &str, prototype: Option<&Prototype>) -> String {
+    let signature = match name.starts_with('?') {
+        true => msvc_signature(name),
+        false => None,
+    };
+    let declarator = match &signature {
+        Some(signature) => format!(
+            "{own}({}){}",
+            signature.parameters.join(", "),
+            signature.constant
+        ),
+        // Itanium names spell their parameters and qualifiers
+        None if own.contains('(') => own.to_string(),
+        None => {
+            let parameters = prototype
+                .map(|prototype| prototype.parameters.iter().skip(1))
+                .into_iter()
+                .flatten()
+                .map(|parameter| parameter.ty.declare(""))
+                .collect::<Vec<_>>();
+            format!("{own}({})", parameters.join(", "))
+        }
+    };
+    let component = last_component(own.split('(').next().unwrap_or(own));
+    let structor = component.starts_with('~') || class.rsplit("::").next() == Some(component);
+    let return_type = match signature {
+        Some(signature) => signature.return_type,
+        None if structor => None,
+        None => prototype.map(|prototype| prototype.return_type.clone()),
+    };
+    match return_type {
+        Some(ty) => ty.declare(&declarator),
+        None => declarator,
+    }
+}
+
+/// The return type, parameters and qualifier of a member function from its MSVC
+/// decoration.
+struct MsvcSignature {
+    /// `None` for constructors and destructors.
+    return_type: Option<Type>,
+    parameters: Vec<String>,
+    /// ` const` for functions on a `const` object.
+    constant: &'static str,
+}
+
+/// Decodes the signature after the name in the MSVC decoration of a member function, like
+/// `UEBAHXZ` for `virtual int area() const` in `?area@Square@@UEBAHXZ`.
+fn msvc_signature(name: &str) -> Option<MsvcSignature> {
+    let mut rest = &name[name.find("@@")? + 2..];
+    let mut next = || {
+        let c = rest.chars().next()?;
+        rest = &rest[1..];
+        Some(c)
+    };
+    let access = next()?;
+    // static and free functions have no `this`
+    let constant = match access {
+        'C' | 'D' | 'K' | 'L' | 'S' | 'T' | 'Y' | 'Z' => "",
+        'A'..='X' => {
+            let mut cv = next()?;
+            if cv == 'E' {
+                cv = next()?;
+            }
+            match cv {
+                'A' | 'C' => "",
+                'B' | 'D' => " const",
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    // the calling convention
+    next()?;
+    let return_type = match rest.strip_prefix('@') {
+        Some(after) => {
+            rest = after;
+            None
+        }
+        None => Some(msvc_type(&mut rest)?),
+    };
+    let mut parameters = Vec::new();
+    // `X` for no parameters
+    if !rest.starts_with('X') {
+        while !rest.starts_with('@') && !rest.starts_with('Z') {
+            parameters.push(msvc_type(&mut rest)?.declare(""));
+        }
+        if rest.starts_with('Z') {
+            parameters.push("...".to_string());
+        }
+    }
+    Some(MsvcSignature {
+        return_type,
+        parameters,
+        constant,
+    })
+}
+
+/// Decodes the MSVC type encoding at the start of `rest` and skips past it. Back references
+/// and templates are not decoded.
+fn msvc_type(rest: &mut &str) -> Option<Type> {
+    let base = |name: &str, kind, size| Type::Base {
+        name: name.to_string(),
+        kind,
+        size,
+    };
+    let (ty, len) = match rest.as_bytes() {
+        [b'_', b'J', ..] => (base("int64_t", BaseKind::Signed, 8), 2),
+        [b'_', b'K', ..] => (base("uint64_t", BaseKind::Unsigned, 8), 2),
+        [b'_', b'N', ..] => (base("bool", BaseKind::Bool, 1), 2),
+        [b'C' | b'D', ..] => (base("char", BaseKind::Signed, 1), 1),
+        [b'E', ..] => (base("unsigned char", BaseKind::Unsigned, 1), 1),
+        [b'F', ..] => (base("short", BaseKind::Signed, 2), 1),
+        [b'G', ..] => (base("unsigned short", BaseKind::Unsigned, 2), 1),
+        [b'H', ..] => (base("int", BaseKind::Signed, 4), 1),
+        [b'I', ..] => (base("unsigned int", BaseKind::Unsigned, 4), 1),
+        [b'J', ..] => (base("long", BaseKind::Signed, 4), 1),
+        [b'K', ..] => (base("unsigned long", BaseKind::Unsigned, 4), 1),
+        [b'M', ..] => (base("float", BaseKind::Float, 4), 1),
+        [b'N', ..] => (base("double", BaseKind::Float, 8), 1),
+        [b'X', ..] => (Type::Void, 1),
+        [b'V' | b'U', ..] => {
+            let end = rest.find("@@")?;
+            let name = msvc_type_name(&format!(".?AV{}", &rest[1..end + 2]))?;
+            *rest = &rest[end + 2..];
+            return Some(Type::Typedef(name));
+        }
+        [kind @ (b'P' | b'Q' | b'A'), ..] => {
+            let reference = *kind == b'A';
+            *rest = rest[1..].strip_prefix('E').unwrap_or(&rest[1..]);
+            let constant = match rest.as_bytes().first()? {
+                b'A' | b'C' => false,
+                b'B' | b'D' => true,
+                _ => return None,
+            };
+            *rest = &rest[1..];
+            let inner = msvc_type(rest)?;
+            let inner = match constant {
+                true => Type::Const(Box::new(inner)),
+                false => inner,
+            };
+            return Some(match reference {
+                true => Type::Typedef(format!("{} &", inner.declare(""))),
+                false => Type::Pointer(Box::new(inner)),
+            });
+        }
+        _ => return None,
+    };
+    *rest = &rest[len..];
+    Some(ty)
+}
+
+/// Returns the qualified name of a function from its MSVC decoration, like
+/// `zoo::Dog::speak` for `?speak@Dog@zoo@@UEBAPEBDXZ` or `zoo::Dog::~Dog` for
+/// `??1Dog@zoo@@UEAA@XZ`.
+fn msvc_function_name(name: &str) -> Option<String> {
+    let rest = name.strip_prefix('?')?;
+    let (special, rest) = match rest.strip_prefix('?') {
+        Some(rest) if rest.starts_with('_') => (Some(rest.get(..2)?), &rest[2..]),
+        Some(rest) => (Some(rest.get(..1)?), &rest[1..]),
+        None => (None, rest),
+    };
+    let mut parts = rest[..rest.find("@@")?].split('@').collect::<Vec<_>>();
+    // templates and back references are left alone
+    if parts
+        .iter()
+        .any(|part| part.is_empty() || !part.starts_with(|c: char| c.is_alphabetic() || c == '_'))
+    {
+        return None;
+    }
+    let name = match special {
+        None => parts.remove(0).to_string(),
+        Some("0") => parts[0].to_string(),
+        Some("1") => format!("~{}", parts[0]),
+        Some("_G") => "`scalar deleting destructor'".to_string(),
+        Some("_E") => "`vector deleting destructor'".to_string(),
+        Some(_) => return None,
+    };
+    parts.reverse();
+    parts.push(&name);
+    Some(parts.join("::"))
+}
+
+/// Turns the decorated name of an MSVC type descriptor, like `.?AVDog@zoo@@`, into
+/// `zoo::Dog`.
+fn msvc_type_name(name: &str) -> Option<String> {
+    let name = name
+        .strip_prefix(".?AV")
+        .or_else(|| name.strip_prefix(".?AU"))?
+        .strip_suffix("@@")?;
+    // templates keep their decoration
+    if name.contains('?') {
+        return Some(name.to_string());
+    }
+    let mut parts = name.split('@').collect::<Vec<_>>();
+    parts.reverse();
+    Some(parts.join("::"))
+}
+
+/// Returns the last component of a qualified name, like `speak` for `zoo::Dog::speak`,
+/// ignoring those in template arguments.
+fn last_component(name: &str) -> &str {
+    let mut depth = 0i32;
+    let mut start = 0;
+    let bytes = name.as_bytes();
+    for (i, byte) in bytes.iter().enumerate() {
+        match byte {
+            b'<' | b'(' => depth += 1,
+            b'>' | b')' => depth -= 1,
+            b':' if depth == 0 && bytes.get(i + 1) == Some(&b':') => start = i + 2,
+            _ => {}
+        }
+    }
+    &name[start..]
+}
+
+/// Splits the parameter list starting at the `(` of a demangled function name.
+fn split_parameters(list: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut depth = 0;
+    let mut start = 1;
+    for (i, byte) in list.bytes().enumerate() {
+        match byte {
+            b'(' | b'<' => depth += 1,
+            b')' | b'>' => {
+                depth -= 1;
+                if depth == 0 {
+                    params.push(list[start..i].trim());
+                    break;
+                }
+            }
+            b',' if depth == 1 => {
+                params.push(list[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    params.retain(|param| !param.is_empty());
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Variable;
+
+    fn classes(path: &str) -> Vec<TypeDef> {
+        let data = std::fs::read(path).unwrap();
+        let file = object::File::parse(&*data).unwrap();
+        let memory = Memory::load(&file, &data, None).unwrap();
+        let mut symbols = SymbolTable::from_file(&file, &memory);
+        let mut classes = Classes::from_file(&file, &memory, &symbols);
+        classes.name_functions(&mut symbols);
+        classes.type_defs(&symbols, &BTreeMap::new())
+    }
+
+    fn declarations(defs: &[TypeDef], class: &str) -> Vec<String> {
+        defs.iter()
+            .find_map(|def| match def {
+                TypeDef::Class { name, vtables, .. } if name == class => Some(vtables),
+                _ => None,
+            })
+            .unwrap()
+            .iter()
+            .flat_map(|(_, functions)| functions.iter())
+            .map(|function| function.declaration.clone().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn display_names() {
+        assert_eq!(display_name("_Z4talkPKN3zoo6AnimalE"), "talk");
+        assert_eq!(display_name("_ZNK3zoo3Dog5speakEv"), "zoo::Dog::speak");
+        assert_eq!(display_name("_Znwm@plt"), "operator new@plt");
+        assert_eq!(display_name("?area@Square@@UEBAHXZ"), "Square::area");
+        assert_eq!(
+            display_name("??_GShape@@UEAAPEAXI@Z"),
+            "Shape::`scalar deleting destructor'"
+        );
+        assert_eq!(display_name("KERNEL32.dll!ExitProcess"), "KERNEL32.dll!ExitProcess");
+        assert_eq!(display_name("puts@GLIBC_2.2.5"), "puts@GLIBC_2.2.5");
+    }
+
+    #[test]
+    fn msvc_signatures() {
+        let declare = |name| member_declaration("Square", name, None);
+        assert_eq!(declare("?area@Square@@UEBAHXZ"), "int area() const");
+        assert_eq!(
+            declare("??_GSquare@@UEAAPEAXI@Z"),
+            "void *`scalar deleting destructor'(unsigned int)"
+        );
+        assert_eq!(declare("??1Square@@UEAA@XZ"), "~Square()");
+        assert_eq!(
+            declare("?name@Square@@UEBAPEBDXZ"),
+            "const char *name() const"
+        );
+        assert_eq!(
+            declare("?scale@Square@@QEAAXNAEBVShape@@_N@Z"),
+            "void scale(double, const Shape &, bool)"
+        );
+    }
+
+    #[test]
+    fn itanium_classes() {
+        let defs = classes("assets/test_cpp");
+        assert_eq!(
+            declarations(&defs, "zoo::Dog"),
+            ["~Dog()", "~Dog()", "speak() const", "zoo::Animal::legs() const"]
+        );
+        let parrot = defs.iter().find_map(|def| match def {
+            TypeDef::Class { name, bases, .. } if name == "zoo::Parrot" => Some(bases),
+            _ => None,
+        });
+        assert_eq!(parrot.unwrap(), &["zoo::Bird", "zoo::Named"]);
+        // the abstract bases have their members from the vtables of the derived classes
+        assert_eq!(
+            declarations(&defs, "zoo::Animal"),
+            ["~Animal()", "~Animal()", "speak() const", "legs() const"]
+        );
+        assert_eq!(declarations(&defs, "zoo::Named"), ["name() const", "~Named()", "~Named()"]);
+    }
+
+    #[test]
+    fn definitions() {
+        assert_eq!(
+            declaration("_Znwm@plt", None).unwrap(),
+            "void *operator new@plt(unsigned long)"
+        );
+        assert_eq!(
+            declaration("_ZdlPvm@plt", None).unwrap(),
+            "void operator delete@plt(void*, unsigned long)"
+        );
+        let prototype = Prototype {
+            return_type: Type::Typedef("undefined8".to_string()),
+            parameters: vec![Variable {
+                name: "this".to_string(),
+                ty: Type::Typedef("undefined8".to_string()),
+            }],
+            variadic: false,
+            noreturn: false,
+        };
+        // the same prototype the member is declared with in its class
+        assert_eq!(
+            declaration("_ZNK3zoo3Dog5speakEv", Some(&prototype)).unwrap(),
+            "undefined8 zoo::Dog::speak() const"
+        );
+        assert_eq!(
+            member_declaration("zoo::Dog", "_ZNK3zoo3Dog5speakEv", Some(&prototype)),
+            "undefined8 speak() const"
+        );
+        assert_eq!(declaration("main", Some(&prototype)), None);
+    }
+
+    #[test]
+    fn virtual_delete() {
+        let data = std::fs::read("assets/test_cpp").unwrap();
+        let file = object::File::parse(&*data).unwrap();
+        let memory = Memory::load(&file, &data, None).unwrap();
+        let symbols = SymbolTable::from_file(&file, &memory);
+        let classes = Classes::from_file(&file, &memory, &symbols);
+        let main = symbols.lookup("main").unwrap();
+        let none = std::collections::BTreeSet::new();
+        let cfg = Cfg::build(&memory, main, None, &none, &none);
+        let calls = classes.virtual_calls(&cfg, main, CallingConvention::SysV, &memory);
+        // `delete a` on the `zoo::Animal *` main gets from `make`
+        assert!(calls.values().any(|method| method == "vfunc_1"));
+    }
+
+    #[test]
+    fn msvc_classes() {
+        let defs = classes("assets/test_cpp.exe");
+        assert_eq!(
+            declarations(&defs, "Square"),
+            [
+                "void *`scalar deleting destructor'(unsigned int)",
+                "int area() const",
+                "vfunc_2()",
+            ]
+        );
+    }
+}
@@ -0,0 +1,999 @@
+use std::collections::BTreeMap;
+
+use iced_x86::{
+    CodeSize, FlowControl, InstructionInfo, InstructionInfoFactory, Mnemonic, OpAccess, OpKind,
+    Register,
+};
+
+use crate::abi::{CallingConvention, Conventions, RegisterAbi};
+use crate::arch::{Architecture, Flow, Instruction};
+use crate::ast::{get_register_name, Expr, ExprKind, Lit, Symbol};
+use crate::emitter::is_noise;
+use crate::function::Function;
+use crate::prototypes::PrototypeDb;
+use crate::symbols::SymbolTable;
+use crate::types::{BaseKind, Prototype, Type, Variable};
+
+/// What is known on entry to an instruction, on every path reaching it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct State {
+    /// The argument registers written since the entry of the function, by index.
+    written: u32,
+    /// The same of the floating-point argument registers.
+    float_written: u32,
+    /// The argument registers written since the last call, by index.
+    fresh: u32,
+    /// The size of the value in the return register, if one was put there.
+    value: Option<u64>,
+    /// The size of the value in the floating-point return register, if one was put there
+    /// after any in the integer one.
+    float: Option<u64>,
+    /// Whether the value in the return register is that of a callee of unknown prototype,
+    /// not yet read: taken for a result once read.
+    guessed: bool,
+    /// The offsets of the stack and frame pointers from the stack pointer on entry, while
+    /// they are known.
+    stack: Option<i64>,
+    frame: Option<i64>,
+}
+
+impl State {
+    fn merge(self, other: State) -> State {
+        State {
+            written: self.written & other.written,
+            float_written: self.float_written & other.float_written,
+            fresh: self.fresh & other.fresh,
+            value: self.value.zip(other.value).map(|(a, b)| a.max(b)),
+            float: self.float.zip(other.float).map(|(a, b)| a.max(b)),
+            guessed: self.guessed || other.guessed,
+            stack: self.stack.filter(|_| self.stack == other.stack),
+            frame: self.frame.filter(|_| self.frame == other.frame),
+        }
+    }
+}
+
+/// The most parameters taken from the stack, past which what is read is rather taken for
+/// a stack pointer followed wrongly.
+const MAX_STACK_PARAMETERS: u64 = 16;
+
+/// What a call reads and returns: the integer and floating-point argument registers, by
+/// index, and the size of the value left in each return register.
+struct Callee {
+    reads: u32,
+    float_reads: u32,
+    value: Option<u64>,
+    float: Option<u64>,
+}
+
+impl Callee {
+    /// Returns what a call to a callee of unknown prototype is taken to read and return,
+    /// given the argument registers written since the previous call, `fresh`: those up to
+    /// the first left unwritten after one written, as the others may be scratch. Those
+    /// before the first written are passed on as they were received, the arguments being
+    /// passed in order. Its result is a word, if read.
+    fn unknown(fresh: u32, word: u64) -> Callee {
+        let count = match fresh {
+            0 => 0,
+            _ => fresh.trailing_zeros() + (fresh >> fresh.trailing_zeros()).trailing_ones(),
+        };
+        Callee {
+            reads: ((1u64 << count) - 1) as u32,
+            float_reads: 0,
+            value: Some(word),
+            float: None,
+        }
+    }
+}
+
+/// Returns what a call to `target` reads and returns, from its prototype, with `counts`
+/// integer and floating-point argument registers, each argument taking that of its position
+/// if `positional`. Those in `pending` have no prototype yet, and are taken to return a
+/// value of a size yet unknown.
+fn callee(
+    target: u64,
+    pending: &[u64],
+    prototypes: &PrototypeDb,
+    symbols: &SymbolTable,
+    counts: (usize, usize),
+    positional: bool,
+    word: u64,
+) -> Option<Callee> {
+    let Some(prototype) = prototypes.prototype_at(target, symbols) else {
+        return pending.contains(&target).then_some(Callee {
+            reads: 0,
+            float_reads: 0,
+            value: Some(0),
+            float: None,
+        });
+    };
+    let (mut reads, mut float_reads) = (0u32, 0u32);
+    let (mut integers, mut floats) = (0, 0);
+    for (position, parameter) in prototype.parameters.iter().enumerate() {
+        let (set, next, count) = match prototypes.is_float(&parameter.ty) {
+            true => (&mut float_reads, &mut floats, counts.1),
+            false => (&mut reads, &mut integers, counts.0),
+        };
+        let n = if positional { position } else { *next };
+        if n < count {
+            *set |= 1 << n;
+        }
+        *next += 1;
+    }
+    Some(Callee {
+        reads,
+        float_reads,
+        value: prototypes.size_of(&prototype.return_type, word),
+        float: prototypes.float_size(&prototype.return_type),
+    })
+}
+
+/// Infers the prototype of `function` from its code, in the style of Ghidra: the argument
+/// registers it reads before writing them are its parameters, and a value left in the
+/// return register on every path to a return is its result. Calls read the parameters of
+/// their callee and return its result, so the callees are better inferred first. The
+/// integer types are only known by size, as `undefined4` or `undefined8`, while the
+/// floating-point ones are told by the instructions using them.
+///
+/// The functions in `pending`, calling each other with `function`, have no prototype yet:
+/// they are taken to return a value until found not to.
+///
+/// The parameters of the conventions pushing the arguments are the stack slots read above
+/// the return address, through the stack or the frame pointer, and those `ret N` pops,
+/// after those passed in registers. The code of the other architectures than x86 is followed
+/// through its lifted statements, for its integer registers only.
+pub fn infer(
+    function: &Function,
+    pending: &[u64],
+    prototypes: &PrototypeDb,
+    symbols: &SymbolTable,
+    conventions: &Conventions,
+    architecture: Architecture,
+) -> Option<Prototype> {
+    if function.cfg.blocks.is_empty() {
+        return None;
+    }
+    let convention = conventions.of(function.address);
+    let word = u64::from(architecture.bitness() / 8);
+    if !matches!(architecture, Architecture::X86 { .. }) {
+        let abi = RegisterAbi::new(architecture, convention);
+        return infer_lifted(function, pending, prototypes, symbols, &abi, word);
+    }
+    let registers = convention.argument_registers();
+    let result = convention.return_registers()[0];
+    let index = |reg: Register| {
+        registers
+            .iter()
+            .position(|other| other.full_register() == reg.full_register())
+    };
+    let floats = convention.float_argument_registers();
+    let float_index = |reg: Register| floats.iter().position(|other| *other == reg);
+    let float_result = convention.float_return_register();
+    let all = (1u32 << registers.len()) - 1;
+    let all_floats = (1u32 << floats.len()) - 1;
+    let mut factory = InstructionInfoFactory::new();
+
+    // the parameters read by a callee, and the size of the result it returns, if known
+    let callee = |inst: &iced_x86::Instruction| {
+        let target = if matches!(
+            inst.flow_control(),
+            FlowControl::Call | FlowControl::UnconditionalBranch
+        ) {
+            inst.near_branch_target()
+        } else if inst.is_ip_rel_memory_operand() {
+            inst.ip_rel_memory_address()
+        } else if inst.op0_kind() == OpKind::Memory
+            && inst.memory_base() == Register::None
+            && inst.memory_index() == Register::None
+        {
+            inst.memory_displacement64()
+        } else {
+            return None;
+        };
+        let counts = (registers.len(), floats.len());
+        let positional = convention.positional_arguments();
+        callee(target, pending, prototypes, symbols, counts, positional, word)
+    };
+
+    let mut entry = BTreeMap::<u64, State>::new();
+    entry.insert(
+        function.address,
+        State {
+            written: 0,
+            float_written: 0,
+            fresh: 0,
+            value: None,
+            float: None,
+            guessed: false,
+            stack: Some(0),
+            frame: None,
+        },
+    );
+    let mut reads = 0u32;
+    let mut float_reads = 0u32;
+    // the stack slots read above the return address, or popped on return
+    let mut slots = 0u64;
+    // the widest read of each parameter
+    let mut sizes = vec![0; registers.len()];
+    let mut float_sizes = vec![0; floats.len()];
+    let mut returned = Vec::<(Option<u64>, Option<u64>)>::new();
+    let mut worklist = vec![function.address];
+    while let Some(start) = worklist.pop() {
+        let Some(block) = function.cfg.blocks.get(&start) else {
+            continue;
+        };
+        let mut state = entry[&start];
+        for (inst, stmt) in block.instructions.iter().zip(block.statements.iter()) {
+            let Instruction::X86(inst) = inst else {
+                return None;
+            };
+            match inst.flow_control() {
+                FlowControl::Call | FlowControl::IndirectCall => {
+                    let known = callee(inst);
+                    state.guessed = known.is_none();
+                    let called = known.unwrap_or(Callee::unknown(state.fresh, word));
+                    reads |= called.reads & !state.written;
+                    float_reads |= called.float_reads & !state.float_written;
+                    // the argument registers are not preserved across calls
+                    state.written = all;
+                    state.float_written = all_floats;
+                    state.fresh = 0;
+                    state.value = called.value;
+                    state.float = called.float;
+                    // and the callee may pop its arguments, as many as they are
+                    let target = match inst.flow_control() {
+                        FlowControl::Call => conventions.of(inst.near_branch_target()),
+                        _ => conventions.default,
+                    };
+                    if target.pops_arguments() {
+                        state.stack = None;
+                    }
+                    continue;
+                }
+                // a jump through an import slot, like in a PLT stub, is a tail call to a
+                // function nothing may be known about, and so is a jump to another function
+                FlowControl::IndirectBranch | FlowControl::UnconditionalBranch
+                    if function.cfg.tail_calls.contains(&inst.ip())
+                        || inst.op0_kind() == OpKind::Memory
+                            && inst.memory_index() == Register::None =>
+                {
+                    let called = callee(inst)?;
+                    reads |= called.reads & !state.written;
+                    float_reads |= called.float_reads & !state.float_written;
+                    returned.push((called.value, called.float));
+                    continue;
+                }
+                FlowControl::Return => {
+                    if inst.op_count() > 0 {
+                        slots = slots.max(u64::from(inst.immediate16()) / word);
+                    }
+                    let value = state.value.filter(|_| !state.guessed);
+                    returned.push((value, state.float));
+                    continue;
+                }
+                _ => {}
+            }
+            let info = factory.info(inst);
+            if let Some(offset) = stack_offset(&state, inst) {
+                let slot = offset / word as i64 - 1;
+                if (0..MAX_STACK_PARAMETERS as i64).contains(&slot) {
+                    slots = slots.max(slot as u64 + 1);
+                }
+            }
+            track_stack(&mut state, inst, info, word as i64);
+            // an instruction reads its registers before writing any of them, as the base of
+            // a load into that same register
+            let (written, float_written) = (state.written, state.float_written);
+            for used in info.used_registers() {
+                let reg = used.register();
+                let read = matches!(
+                    used.access(),
+                    OpAccess::Read
+                        | OpAccess::CondRead
+                        | OpAccess::ReadWrite
+                        | OpAccess::ReadCondWrite
+                );
+                let write = matches!(used.access(), OpAccess::Write | OpAccess::ReadWrite);
+                if let Some(i) = index(reg) {
+                    if read && written & (1 << i) == 0 {
+                        reads |= 1 << i;
+                        sizes[i] = sizes[i].max(reg.size());
+                    }
+                    if write {
+                        state.written |= 1 << i;
+                        state.fresh |= 1 << i;
+                    }
+                }
+                if read && reg.full_register() == result.full_register() {
+                    state.guessed = false;
+                }
+                if write && reg.full_register() == result.full_register() {
+                    // 32-bit writes are given as writes to the whole register they clear
+                    let size = match inst.op0_kind() {
+                        OpKind::Register if inst.op0_register().full_register() == reg => {
+                            inst.op0_register().size()
+                        }
+                        _ => reg.size(),
+                    };
+                    // the scratch uses of the checks left out of the output are no result
+                    state.value = (!is_noise(stmt)).then_some(size as u64);
+                    state.guessed = false;
+                    if state.value.is_some() {
+                        state.float = None;
+                    }
+                }
+                if let Some(i) = float_index(reg) {
+                    if read && float_written & (1 << i) == 0 && !only_written(inst, reg) {
+                        float_reads |= 1 << i;
+                        float_sizes[i] = float_sizes[i].max(float_size(inst, false).unwrap_or(8));
+                    }
+                    if write {
+                        state.float_written |= 1 << i;
+                    }
+                }
+                if write && Some(reg) == float_result {
+                    state.float = float_size(inst, true);
+                    if state.float.is_some() {
+                        state.value = None;
+                    }
+                } else if read && Some(reg) == float_result {
+                    // the register is also the scratch of floating-point copies, whose values
+                    // are stored or read again once put there
+                    state.float = None;
+                }
+            }
+        }
+        for edge in function.cfg.edges.iter().filter(|edge| edge.from == start) {
+            let merged = match entry.get(&edge.to) {
+                Some(other) => other.merge(state),
+                None => state,
+            };
+            if entry.insert(edge.to, merged) != Some(merged) {
+                worklist.push(edge.to);
+            }
+        }
+    }
+
+    let size = |size: u64| Type::Typedef(format!("undefined{size}"));
+    if !convention.pushes_arguments() {
+        slots = 0;
+    }
+    // the stack arguments come after all those in registers
+    let count = match slots {
+        0 => u32::BITS - reads.leading_zeros(),
+        _ => registers.len() as u32,
+    };
+    let float_count = 32 - float_reads.leading_zeros();
+    let value = match returned.iter().map(|(value, _)| *value).collect::<Option<Vec<_>>>() {
+        Some(sizes) => sizes.into_iter().max(),
+        None => None,
+    };
+    let float = match returned.iter().map(|(_, float)| *float).collect::<Option<Vec<_>>>() {
+        Some(sizes) => sizes.into_iter().max(),
+        None => None,
+    };
+    let floating = |i: usize| Variable {
+        name: String::new(),
+        ty: float_type(float_sizes[i]),
+    };
+    let mut parameters = (0..count)
+        .map(|i| Variable {
+            name: String::new(),
+            ty: match sizes[i as usize] {
+                // passed on to a callee, or left unread before a later parameter
+                0 => size(registers[i as usize].size() as u64),
+                read => size(read as u64),
+            },
+        })
+        .collect::<Vec<_>>();
+    if convention.positional_arguments() {
+        // each argument takes its position in either kind of register
+        for i in 0..float_count as usize {
+            match parameters.get_mut(i) {
+                Some(parameter) if float_reads & (1 << i) != 0 && reads & (1 << i) == 0 => {
+                    *parameter = floating(i)
+                }
+                Some(_) => {}
+                None => parameters.push(floating(i)),
+            }
+        }
+    } else {
+        parameters.extend((0..float_count as usize).map(floating));
+    }
+    Some(Prototype {
+        return_type: match (value, float) {
+            (Some(0), _) => size(word),
+            (Some(value), _) => size(value),
+            (None, Some(float)) => float_type(float),
+            (None, None) => Type::Void,
+        },
+        parameters: parameters
+            .into_iter()
+            .chain((0..slots).map(|_| Variable {
+                name: String::new(),
+                ty: size(word),
+            }))
+            .collect(),
+        variadic: false,
+        noreturn: false,
+    })
+}
+
+/// Returns the integer argument registers the x86 `function` may write, when it makes no
+/// call: the compilers allocating registers across functions leave the arguments of a later
+/// call in the others across the calls to it.
+pub fn clobbers(function: &Function, conventions: &Conventions) -> Option<Vec<String>> {
+    let registers = conventions.of(function.address).argument_registers();
+    let mut factory = InstructionInfoFactory::new();
+    let mut written = Vec::new();
+    for inst in function.cfg.blocks.values().flat_map(|block| block.instructions.iter()) {
+        let Instruction::X86(inst) = inst else {
+            return None;
+        };
+        if matches!(
+            inst.flow_control(),
+            FlowControl::Call | FlowControl::IndirectCall | FlowControl::IndirectBranch
+        ) || function.cfg.tail_calls.contains(&inst.ip())
+        {
+            return None;
+        }
+        for used in factory.info(inst).used_registers() {
+            let reg = used.register().full_register();
+            if matches!(
+                used.access(),
+                OpAccess::Write | OpAccess::CondWrite | OpAccess::ReadWrite | OpAccess::ReadCondWrite
+            ) && registers.contains(&reg)
+                && !written.contains(&reg)
+            {
+                written.push(reg);
+            }
+        }
+    }
+    Some(written.into_iter().map(get_register_name).collect())
+}
+
+/// Infers the prototype of the AArch64 or RISC-V `function` like [`infer`] does that of an
+/// x86 one, from the registers its lifted statements read and write.
+fn infer_lifted(
+    function: &Function,
+    pending: &[u64],
+    prototypes: &PrototypeDb,
+    symbols: &SymbolTable,
+    abi: &RegisterAbi,
+    word: u64,
+) -> Option<Prototype> {
+    let registers = &abi.arguments;
+    let all = (1u32 << registers.len()) - 1;
+    // the calls through a register take the target the lifter folded, or the single one the
+    // CFG resolved them to
+    let called = |inst: &Instruction, stmt: &Expr| {
+        let target = match (&stmt.call_site().kind, function.cfg.calls.get(&inst.ip())) {
+            (ExprKind::Call(target, _), _) => Some(*target),
+            (_, Some(targets)) if targets.len() == 1 => Some(targets[0]),
+            _ => inst.branch_target(),
+        };
+        let counts = (registers.len(), 0);
+        callee(target?, pending, prototypes, symbols, counts, false, word)
+    };
+
+    let mut entry = BTreeMap::<u64, State>::new();
+    entry.insert(
+        function.address,
+        State {
+            written: 0,
+            float_written: 0,
+            fresh: 0,
+            value: None,
+            float: None,
+            guessed: false,
+            stack: None,
+            frame: None,
+        },
+    );
+    let mut reads = 0u32;
+    let mut sizes = vec![0; registers.len()];
+    let mut returned = Vec::<Option<u64>>::new();
+    let mut worklist = vec![function.address];
+    while let Some(start) = worklist.pop() {
+        let Some(block) = function.cfg.blocks.get(&start) else {
+            continue;
+        };
+        let mut state = entry[&start];
+        for (inst, stmt) in block.instructions.iter().zip(block.statements.iter()) {
+            match inst.flow() {
+                Flow::Call | Flow::IndirectCall => {
+                    let known = called(inst, stmt);
+                    state.guessed = known.is_none();
+                    let called = known.unwrap_or(Callee::unknown(state.fresh, word));
+                    reads |= called.reads & !state.written;
+                    // the argument registers are not preserved across calls
+                    state.written = all;
+                    state.fresh = 0;
+                    state.value = called.value;
+                    continue;
+                }
+                Flow::Branch | Flow::IndirectBranch
+                    if function.cfg.tail_calls.contains(&inst.ip()) =>
+                {
+                    let called = called(inst, stmt)?;
+                    reads |= called.reads & !state.written;
+                    returned.push(called.value);
+                    continue;
+                }
+                Flow::Return => {
+                    returned.push(state.value.filter(|_| !state.guessed));
+                    continue;
+                }
+                _ => {}
+            }
+            let mut read = Vec::new();
+            read_registers(stmt, &mut read);
+            for name in read {
+                let Some((whole, size)) = abi.whole(&name) else {
+                    continue;
+                };
+                if let Some(i) = registers.iter().position(|other| *other == whole) {
+                    if state.written & (1 << i) == 0 {
+                        reads |= 1 << i;
+                        sizes[i] = sizes[i].max(size);
+                    }
+                }
+                if whole == abi.result {
+                    state.guessed = false;
+                }
+            }
+            let mut written = inst.written_registers();
+            if let ExprKind::Assign(lhs, _) = &stmt.kind {
+                if let ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) = &lhs.kind {
+                    written.push(name.clone());
+                }
+            }
+            for name in written {
+                let Some((whole, size)) = abi.whole(&name) else {
+                    continue;
+                };
+                if let Some(i) = registers.iter().position(|other| *other == whole) {
+                    state.written |= 1 << i;
+                    state.fresh |= 1 << i;
+                }
+                if whole == abi.result {
+                    // the scratch uses of the checks left out of the output are no result
+                    state.value = (!is_noise(stmt)).then_some(size as u64);
+                    state.guessed = false;
+                }
+            }
+        }
+        for edge in function.cfg.edges.iter().filter(|edge| edge.from == start) {
+            let merged = match entry.get(&edge.to) {
+                Some(other) => other.merge(state),
+                None => state,
+            };
+            if entry.insert(edge.to, merged) != Some(merged) {
+                worklist.push(edge.to);
+            }
+        }
+    }
+
+    let size = |size: u64| Type::Typedef(format!("undefined{size}"));
+    let count = u32::BITS - reads.leading_zeros();
+    let value = match returned.into_iter().collect::<Option<Vec<_>>>() {
+        Some(sizes) => sizes.into_iter().max(),
+        None => None,
+    };
+    Some(Prototype {
+        return_type: match value {
+            Some(0) => size(word),
+            Some(value) => size(value),
+            None => Type::Void,
+        },
+        parameters: (0..count as usize)
+            .map(|i| Variable {
+                name: String::new(),
+                ty: match sizes[i] {
+                    // passed on to a callee, or left unread before a later parameter
+                    0 => size(word),
+                    read => size(read as u64),
+                },
+            })
+            .collect(),
+        variadic: false,
+        noreturn: false,
+    })
+}
+
+/// Collects the names of the registers the lifted statement `expr` reads, leaving out the
+/// register it assigns.
+fn read_registers(expr: &Expr, out: &mut Vec<String>) {
+    match &expr.kind {
+        ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) => out.push(name.clone()),
+        ExprKind::Assign(lhs, rhs) => {
+            if !matches!(lhs.kind, ExprKind::Lit(Lit::Symbol(Symbol::Reg(_)))) {
+                read_registers(lhs, out);
+            }
+            read_registers(rhs, out);
+        }
+        ExprKind::Call(_, args)
+        | ExprKind::MethodCall(_, args)
+        | ExprKind::Intrinsic(_, args)
+        | ExprKind::Tup(args) => args.iter().for_each(|arg| read_registers(arg, out)),
+        ExprKind::Binary(_, lhs, rhs) => {
+            read_registers(lhs, out);
+            read_registers(rhs, out);
+        }
+        ExprKind::Unary(_, inner) | ExprKind::Cast(inner, _) => read_registers(inner, out),
+        ExprKind::If(condition, _) => read_registers(condition, out),
+        ExprKind::Ret(Some(inner)) | ExprKind::Switch(inner, _) => read_registers(inner, out),
+        _ => {}
+    }
+}
+
+/// Returns `float` for a floating-point value of 4 bytes, and `double` for any other.
+fn float_type(size: u64) -> Type {
+    let (name, size) = match size {
+        4 => ("float", 4),
+        _ => ("double", 8),
+    };
+    Type::Base {
+        name: name.to_string(),
+        kind: BaseKind::Float,
+        size,
+    }
+}
+
+/// Returns the size of the floating-point value `inst` reads from or, if `write`, puts in
+/// a vector register: 4 for the single-precision scalar instructions and 8 for the
+/// double-precision ones. The moves and bitwise operations between registers copy any
+/// value, taken for a double; other writes, like the loads of a whole vector, are not
+/// taken for a floating-point value at all.
+fn float_size(inst: &iced_x86::Instruction, write: bool) -> Option<u64> {
+    let name = format!("{:?}", inst.mnemonic()).to_lowercase();
+    // the VEX forms size their operands alike
+    let name = name.strip_prefix('v').unwrap_or(&name);
+    let scalar = |suffix: &str| match suffix {
+        suffix if suffix.ends_with("ss") => Some(4),
+        suffix if suffix.ends_with("sd") => Some(8),
+        _ => None,
+    };
+    if let Some((from, to)) = name.strip_prefix("cvt").and_then(|name| name.split_once('2')) {
+        return scalar(if write { to } else { from });
+    }
+    let registers = (0..inst.op_count()).all(|op| inst.op_kind(op) == OpKind::Register);
+    match name {
+        "movaps" | "movapd" | "movq" | "xorps" | "xorpd" | "pxor" | "andps" | "andpd"
+        | "andnps" | "andnpd" | "orps" | "orpd" | "unpcklpd"
+            if registers =>
+        {
+            scalar(name).or(Some(8))
+        }
+        _ => scalar(name),
+    }
+}
+
+/// Returns `true` if `inst` only writes `reg`, although said to read it too: the scalar
+/// instructions keeping the rest of the vector they write, and the idioms clearing it.
+fn only_written(inst: &iced_x86::Instruction, reg: Register) -> bool {
+    if inst.op0_kind() != OpKind::Register || inst.op0_register() != reg {
+        return false;
+    }
+    let clearing = inst.op1_kind() == OpKind::Register && inst.op1_register() == reg;
+    match inst.mnemonic() {
+        Mnemonic::Xorps | Mnemonic::Xorpd | Mnemonic::Pxor | Mnemonic::Subps | Mnemonic::Subpd => {
+            clearing
+        }
+        Mnemonic::Movss
+        | Mnemonic::Movsd
+        | Mnemonic::Cvtsi2ss
+        | Mnemonic::Cvtsi2sd
+        | Mnemonic::Cvtss2sd
+        | Mnemonic::Cvtsd2ss
+        | Mnemonic::Sqrtss
+        | Mnemonic::Sqrtsd
+        | Mnemonic::Roundss
+        | Mnemonic::Roundsd
+        | Mnemonic::Rcpss
+        | Mnemonic::Rsqrtss => !clearing,
+        _ => false,
+    }
+}
+
+/// Returns the offset from the stack pointer on entry of the stack slot `inst` reads or
+/// takes the address of through the stack or the frame pointer, when known.
+fn stack_offset(state: &State, inst: &iced_x86::Instruction) -> Option<i64> {
+    if !(0..inst.op_count()).any(|op| inst.op_kind(op) == OpKind::Memory)
+        || inst.memory_index() != Register::None
+    {
+        return None;
+    }
+    let base = match inst.memory_base().full_register() {
+        Register::RSP => state.stack?,
+        Register::RBP => state.frame?,
+        _ => return None,
+    };
+    Some(base + displacement(inst))
+}
+
+/// Returns the displacement of the memory operand of `inst`, sign-extended.
+fn displacement(inst: &iced_x86::Instruction) -> i64 {
+    let displacement = inst.memory_displacement64();
+    match inst.memory_base().size() {
+        2 => displacement as u16 as i16 as i64,
+        4 => displacement as u32 as i32 as i64,
+        _ => displacement as i64,
+    }
+}
+
+/// Follows the stack and frame pointers through `inst`, which is no call: the pushes and
+/// pops, the frame set up and left, and the space reserved for the locals. Any other write
+/// makes them unknown.
+fn track_stack(state: &mut State, inst: &iced_x86::Instruction, info: &InstructionInfo, word: i64) {
+    let is = |op: u32, reg: Register| {
+        inst.op_kind(op) == OpKind::Register && inst.op_register(op).full_register() == reg
+    };
+    match inst.mnemonic() {
+        Mnemonic::Leave => {
+            state.stack = state.frame.map(|frame| frame + word);
+            state.frame = None;
+            return;
+        }
+        Mnemonic::Enter => {
+            state.frame = state.stack.map(|stack| stack - word);
+            state.stack = state
+                .frame
+                .map(|frame| frame - i64::from(inst.immediate16()));
+            return;
+        }
+        Mnemonic::Mov if is(0, Register::RBP) && is(1, Register::RSP) => {
+            state.frame = state.stack;
+            return;
+        }
+        Mnemonic::Add | Mnemonic::Sub
+            if is(0, Register::RSP) && inst.op1_kind() != OpKind::Register =>
+        {
+            let immediate = match inst.op0_register().size() {
+                2 => inst.immediate(1) as i16 as i64,
+                4 => inst.immediate(1) as i32 as i64,
+                _ => inst.immediate(1) as i64,
+            };
+            let sign = if inst.mnemonic() == Mnemonic::Add { 1 } else { -1 };
+            state.stack = state.stack.map(|stack| stack + sign * immediate);
+            return;
+        }
+        Mnemonic::Lea if is(0, Register::RSP) => {
+            state.stack = stack_offset(state, inst);
+            return;
+        }
+        _ => {}
+    }
+    let increment = inst.stack_pointer_increment();
+    if increment != 0 {
+        state.stack = state.stack.map(|stack| stack + i64::from(increment));
+        // `pop ebp` gives the frame pointer of the caller back
+        if inst.mnemonic() == Mnemonic::Pop && is(0, Register::RBP) {
+            state.frame = None;
+        }
+        return;
+    }
+    for used in info.used_registers() {
+        if !matches!(
+            used.access(),
+            OpAccess::Write | OpAccess::CondWrite | OpAccess::ReadWrite | OpAccess::ReadCondWrite
+        ) {
+            continue;
+        }
+        match used.register().full_register() {
+            Register::RSP => state.stack = None,
+            Register::RBP => state.frame = None,
+            _ => {}
+        }
+    }
+}
+
+/// Infers the calling convention of a 32-bit x86 `function` when the `default` one pushes
+/// the arguments: one reading ECX or EDX before writing them takes its first arguments
+/// there, like fastcall, or like thiscall when only ECX is read and that is the default.
+/// One popping its arguments with `ret N` and reading neither is stdcall.
+pub fn convention(function: &Function, default: CallingConvention) -> CallingConvention {
+    if !default.pushes_arguments() {
+        return default;
+    }
+    let registers = [Register::ECX, Register::EDX];
+    let mut factory = InstructionInfoFactory::new();
+    let mut entry = BTreeMap::from([(function.address, 0u32)]);
+    let mut reads = 0u32;
+    let mut pops = false;
+    let mut worklist = vec![function.address];
+    while let Some(start) = worklist.pop() {
+        let Some(block) = function.cfg.blocks.get(&start) else {
+            continue;
+        };
+        let mut written = entry[&start];
+        for inst in block.instructions.iter() {
+            let Some(inst) = inst.as_x86() else {
+                return default;
+            };
+            if inst.code_size() != CodeSize::Code32 {
+                return default;
+            }
+            match inst.flow_control() {
+                FlowControl::Call | FlowControl::IndirectCall => {
+                    written = u32::MAX;
+                    continue;
+                }
+                FlowControl::Return => {
+                    pops |= inst.op_count() > 0 && inst.immediate16() > 0;
+                    continue;
+                }
+                _ => {}
+            }
+            // `push ecx` also reserves a slot for a local
+            let saved = inst.mnemonic() == Mnemonic::Push;
+            for used in factory.info(inst).used_registers() {
+                let Some(i) = registers
+                    .iter()
+                    .position(|reg| reg.full_register() == used.register().full_register())
+                else {
+                    continue;
+                };
+                let read = matches!(
+                    used.access(),
+                    OpAccess::Read | OpAccess::CondRead | OpAccess::ReadWrite | OpAccess::ReadCondWrite
+                );
+                if read && !saved && written & (1 << i) == 0 {
+                    reads |= 1 << i;
+                }
+                if matches!(used.access(), OpAccess::Write | OpAccess::ReadWrite) {
+                    written |= 1 << i;
+                }
+            }
+        }
+        for edge in function.cfg.edges.iter().filter(|edge| edge.from == start) {
+            let merged = entry.get(&edge.to).map_or(written, |other| other & written);
+            if entry.insert(edge.to, merged) != Some(merged) {
+                worklist.push(edge.to);
+            }
+        }
+    }
+    match (reads, pops) {
+        (0, true) => CallingConvention::Stdcall,
+        (0, false) => default,
+        (1, _) if default == CallingConvention::Thiscall => CallingConvention::Thiscall,
+        _ => CallingConvention::Fastcall,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::abi::CallingConvention;
+    use crate::memory::memory::Memory;
+    use crate::rtti::Classes;
+    use crate::unwind::UnwindTable;
+
+    const FUNCTIONS: [&str; 7] = ["mul", "half", "mixed", "scale", "zero", "store", "add"];
+
+    /// Lifts the functions of `assets/test_float`, infers the prototypes of its leaves and
+    /// applies them to `main`, returning the calls it makes to them, their declarations and
+    /// their returns.
+    fn lift() -> (Vec<String>, Vec<String>, Vec<String>) {
+        let data = std::fs::read("assets/test_float").unwrap();
+        let file = object::File::parse(&*data).unwrap();
+        let memory = Memory::load(&file, &data, None).unwrap();
+        let symbols = SymbolTable::from_file(&file, &memory);
+        let unwind = UnwindTable::from_file(&file, &memory);
+        let conventions = Conventions::new(CallingConvention::SysV);
+        let none = BTreeSet::new();
+        let lift = |name| {
+            let address = symbols.lookup(name).unwrap();
+            let classes = Classes::default();
+            Function::lift(&memory, &unwind, address, &conventions, &none, &none, &classes)
+        };
+        let mut prototypes = PrototypeDb::new(8, 8);
+        let mut declarations = Vec::new();
+        let mut returns = Vec::new();
+        for name in FUNCTIONS {
+            let mut function = lift(name);
+            let architecture = memory.architecture();
+            let prototype =
+                infer(&function, &[], &prototypes, &symbols, &conventions, architecture).unwrap();
+            declarations.push(prototype.declare(name));
+            prototypes.insert_inferred(function.address, prototype.clone());
+            function.prototype = Some(prototype);
+            prototypes.apply_returns(&mut function, &memory, &conventions);
+            let statements = function.statements().map(|statement| statement.named(&symbols));
+            returns.extend(
+                statements
+                    .map(|statement| statement.to_string())
+                    .filter(|statement| statement.starts_with("return")),
+            );
+        }
+        let mut main = lift("main");
+        prototypes.apply(&mut main, &symbols, &memory, &conventions);
+        let calls = main
+            .statements()
+            .map(|statement| statement.named(&symbols).to_string())
+            .filter(|statement| FUNCTIONS.iter().any(|name| statement.contains(name)))
+            .collect();
+        (calls, declarations, returns)
+    }
+
+    #[test]
+    fn float_prototypes() {
+        assert_eq!(
+            lift().1,
+            [
+                "double mul(double, double)",
+                "float half(float)",
+                "undefined4 mixed(undefined4, double)",
+                "double scale(undefined4, undefined8, double)",
+                "double zero(void)",
+                "void store(undefined8, double)",
+                "undefined8 add(undefined8, undefined8)",
+            ]
+        );
+    }
+
+    #[test]
+    fn float_arguments() {
+        let calls = lift().0;
+        assert!(calls.contains(&"mul(@XMM0, @XMM1)".to_string()), "{calls:?}");
+        assert!(calls.contains(&"half(@XMM0)".to_string()), "{calls:?}");
+        assert!(calls.contains(&"scale(@RDI, @RSI, @XMM0)".to_string()), "{calls:?}");
+    }
+    /// Infers the prototype of the x86-64 `code` loaded at 0x1000, calling `strlen` at
+    /// 0x2000 and a function of unknown prototype at 0x3000.
+    fn infer_raw(code: &[u8]) -> String {
+        let memory = Memory::raw(code, 0x1000, Architecture::X86 { bitness: 64 }).unwrap();
+        let mut symbols = SymbolTable::default();
+        symbols.insert(0x2000, "strlen");
+        let conventions = Conventions::new(CallingConvention::SysV);
+        let none = BTreeSet::new();
+        let classes = Classes::default();
+        let unwind = UnwindTable::default();
+        let function = Function::lift(&memory, &unwind, 0x1000, &conventions, &none, &none, &classes);
+        let prototypes = PrototypeDb::new(8, 8);
+        let architecture = memory.architecture();
+        let prototype = infer(&function, &[], &prototypes, &symbols, &conventions, architecture);
+        prototype.unwrap().declare("f")
+    }
+
+    #[test]
+    fn self_referencing_load() {
+        // mov rdi, [rdi]; call strlen; ret
+        let code = [0x48, 0x8B, 0x3F, 0xE8, 0xF8, 0x0F, 0x00, 0x00, 0xC3];
+        assert_eq!(infer_raw(&code), "undefined8 f(undefined8)");
+    }
+
+    #[test]
+    fn pass_through_arguments() {
+        // mov esi, 8; call 0x3000; ret: the first argument is passed on as received
+        let code = [0xBE, 0x08, 0x00, 0x00, 0x00, 0xE8, 0xF6, 0x1F, 0x00, 0x00, 0xC3];
+        assert_eq!(infer_raw(&code), "void f(undefined8)");
+    }
+
+    #[test]
+    fn returns_from_callees() {
+        // mov edi, 0x10; call 0x3000; mov qword [rax], 0; ret
+        let code = [
+            0xBF, 0x10, 0x00, 0x00, 0x00, 0xE8, 0xF6, 0x1F, 0x00, 0x00, 0x48, 0xC7, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0xC3,
+        ];
+        assert_eq!(infer_raw(&code), "undefined8 f(void)");
+    }
+
+    #[test]
+    fn return_values() {
+        assert_eq!(
+            lift().2,
+            [
+                "return @XMM0",
+                "return @XMM0",
+                "return @EAX",
+                "return @XMM0",
+                "return @XMM0",
+                "return",
+                "return @RAX",
+            ]
+        );
+    }
+}
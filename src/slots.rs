@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use iced_x86::{InstructionInfo, OpAccess, Register};
+
+use crate::ast::get_register_name;
+
+/// A stack slot, by the name of the stack or frame pointer it is addressed through and its
+/// offset from it, or by [`ENTRY`] and its offset from the stack pointer on entry when the
+/// call frame information tells how far the stack pointer has moved since.
+pub type Slot = (String, i64);
+
+/// The base of the slots addressed through the stack pointer at a known depth, which are
+/// kept when it is pushed or adjusted.
+pub const ENTRY: &str = "entry";
+
+/// What the data-flow analyses know of the stack slots of a function: the value each holds,
+/// with the size it was stored with. The slots left out may hold anything.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Slots<V> {
+    slots: BTreeMap<Slot, (usize, V)>,
+}
+
+impl<V> Default for Slots<V> {
+    fn default() -> Self {
+        Self {
+            slots: BTreeMap::new(),
+        }
+    }
+}
+
+impl<V: Clone + PartialEq> Slots<V> {
+    pub fn get(&self, slot: &Slot) -> Option<&(usize, V)> {
+        self.slots.get(slot)
+    }
+
+    pub fn insert(&mut self, slot: Slot, size: usize, value: V) {
+        self.slots.insert(slot, (size, value));
+    }
+
+    pub fn remove(&mut self, slot: &Slot) {
+        self.slots.remove(slot);
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Slot, &(usize, V))> {
+        self.slots.iter()
+    }
+
+    /// Keeps the slots `other` holds the same in.
+    pub fn agreeing(&self, other: &Self) -> Self {
+        Self {
+            slots: self
+                .slots
+                .iter()
+                .filter(|(slot, value)| other.slots.get(*slot) == Some(value))
+                .map(|(slot, value)| (slot.clone(), value.clone()))
+                .collect(),
+        }
+    }
+
+    /// Forgets the slots addressed through `base`, or those overlapping the `size` bytes at
+    /// `offset` from it.
+    pub fn clobber(&mut self, base: &str, range: Option<(i64, usize)>) {
+        self.slots.retain(|(other, at), (stored, _)| {
+            other != base
+                || range.is_some_and(|(offset, size)| {
+                    *at + *stored as i64 <= offset || offset + size as i64 <= *at
+                })
+        });
+    }
+
+    /// Forgets the slots an x86 instruction may overwrite, from its `info`: those addressed
+    /// through a register it writes, and those its stores through the stack or frame pointer
+    /// overlap, with the stack pointer `depth` bytes below its value on entry if known. Its
+    /// other stores may write any slot when `escapes`, the address of a local being given
+    /// away.
+    pub fn forget(&mut self, info: &InstructionInfo, depth: Option<i64>, escapes: bool) {
+        for used in info.used_registers() {
+            if !matches!(used.access(), OpAccess::Read | OpAccess::CondRead) {
+                self.clobber(&get_register_name(used.register().full_register()), None);
+            }
+        }
+        for used in info.used_memory() {
+            if !matches!(
+                used.access(),
+                OpAccess::Write
+                    | OpAccess::CondWrite
+                    | OpAccess::ReadWrite
+                    | OpAccess::ReadCondWrite
+            ) {
+                continue;
+            }
+            let base = used.base().full_register();
+            if matches!(base, Register::RSP | Register::RBP) && used.index() == Register::None {
+                let size = used.memory_size().size();
+                let (base, offset) = rebase(base, used.displacement() as i64, depth);
+                self.clobber(&base, Some((offset, size)));
+            } else if escapes {
+                self.slots.clear();
+            }
+        }
+    }
+}
+
+/// Returns the stack slot the memory operand of the x86 `inst` addresses, relative to the
+/// stack or frame pointer.
+pub fn frame_slot(inst: &iced_x86::Instruction, depth: Option<i64>) -> Option<Slot> {
+    let base = inst.memory_base().full_register();
+    (matches!(base, Register::RSP | Register::RBP) && inst.memory_index() == Register::None)
+        .then(|| rebase(base, inst.memory_displacement64() as i64, depth))
+}
+
+/// Returns the slot at `offset` from the x86 register `base`, taken from the stack pointer
+/// on entry when `base` is the stack pointer `depth` bytes below it.
+pub fn rebase(base: Register, offset: i64, depth: Option<i64>) -> Slot {
+    match depth {
+        Some(depth) if base == Register::RSP => (ENTRY.to_string(), offset - depth),
+        _ => (get_register_name(base), offset),
+    }
+}
+
+/// Returns the `bits` low bits of `value`.
+pub fn mask(value: u64, bits: u32) -> u64 {
+    match bits {
+        64.. => value,
+        _ => value & ((1 << bits) - 1),
+    }
+}
+
+/// Returns the `bits` low bits of `value`, as a signed number.
+pub fn sign_extend(value: u64, bits: u32) -> i64 {
+    match bits {
+        0 => 0,
+        64.. => value as i64,
+        _ => ((value << (64 - bits)) as i64) >> (64 - bits),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clobbered_slots() {
+        let mut slots = Slots::default();
+        slots.insert(("RBP".to_string(), -8), 8, 1);
+        slots.insert(("RBP".to_string(), -16), 4, 2);
+        slots.insert((ENTRY.to_string(), -8), 8, 3);
+        // a store of 4 bytes at rbp - 6 overlaps only the slot at rbp - 8
+        slots.clobber("RBP", Some((-6, 4)));
+        assert_eq!(slots.get(&("RBP".to_string(), -8)), None);
+        assert_eq!(slots.get(&("RBP".to_string(), -16)), Some(&(4, 2)));
+        slots.clobber("RBP", None);
+        assert_eq!(slots.iter().count(), 1);
+        assert_eq!(rebase(Register::RSP, 0x10, Some(0x18)), (ENTRY.to_string(), -8));
+        assert_eq!(rebase(Register::RSP, 0x10, None), ("RSP".to_string(), 0x10));
+    }
+
+    #[test]
+    fn extension() {
+        assert_eq!(mask(0x1234, 8), 0x34);
+        assert_eq!(sign_extend(0xff, 8), -1);
+        assert_eq!(sign_extend(0x7f, 8), 0x7f);
+        assert_eq!(sign_extend(0xffff_fff0, 32), -0x10);
+        assert_eq!(sign_extend(u64::MAX, 64), -1);
+    }
+}
@@ -0,0 +1,405 @@
+use iced_x86::{EncodingKind, Instruction, Mnemonic, OpKind, Register};
+
+use crate::ast::*;
+use crate::parser::{get_binary, get_flag, get_not, get_operand};
+
+/// The packed instructions lifted as calls to their Intel intrinsics, by mnemonic without
+/// the `v` of the VEX and EVEX forms, with the intrinsic name past the `_mm_` prefix and
+/// the number of vector operands it reads.
+const INTRINSICS: &[(&str, &str, usize)] = &[
+    ("addps", "add_ps", 2),
+    ("addpd", "add_pd", 2),
+    ("subps", "sub_ps", 2),
+    ("subpd", "sub_pd", 2),
+    ("mulps", "mul_ps", 2),
+    ("mulpd", "mul_pd", 2),
+    ("divps", "div_ps", 2),
+    ("divpd", "div_pd", 2),
+    ("minps", "min_ps", 2),
+    ("minpd", "min_pd", 2),
+    ("maxps", "max_ps", 2),
+    ("maxpd", "max_pd", 2),
+    ("sqrtps", "sqrt_ps", 1),
+    ("sqrtpd", "sqrt_pd", 1),
+    ("haddps", "hadd_ps", 2),
+    ("haddpd", "hadd_pd", 2),
+    ("andps", "and_ps", 2),
+    ("andpd", "and_pd", 2),
+    ("andnps", "andnot_ps", 2),
+    ("andnpd", "andnot_pd", 2),
+    ("orps", "or_ps", 2),
+    ("orpd", "or_pd", 2),
+    ("xorps", "xor_ps", 2),
+    ("xorpd", "xor_pd", 2),
+    ("shufps", "shuffle_ps", 2),
+    ("shufpd", "shuffle_pd", 2),
+    ("unpcklps", "unpacklo_ps", 2),
+    ("unpcklpd", "unpacklo_pd", 2),
+    ("unpckhps", "unpackhi_ps", 2),
+    ("unpckhpd", "unpackhi_pd", 2),
+    ("movhlps", "movehl_ps", 2),
+    ("movlhps", "movelh_ps", 2),
+    ("movmskps", "movemask_ps", 1),
+    ("movmskpd", "movemask_pd", 1),
+    ("cvtdq2ps", "cvtepi32_ps", 1),
+    ("cvtdq2pd", "cvtepi32_pd", 1),
+    ("cvtps2dq", "cvtps_epi32", 1),
+    ("cvttps2dq", "cvttps_epi32", 1),
+    ("cvtpd2dq", "cvtpd_epi32", 1),
+    ("cvttpd2dq", "cvttpd_epi32", 1),
+    ("cvtps2pd", "cvtps_pd", 1),
+    ("cvtpd2ps", "cvtpd_ps", 1),
+    ("paddb", "add_epi8", 2),
+    ("paddw", "add_epi16", 2),
+    ("paddd", "add_epi32", 2),
+    ("paddq", "add_epi64", 2),
+    ("psubb", "sub_epi8", 2),
+    ("psubw", "sub_epi16", 2),
+    ("psubd", "sub_epi32", 2),
+    ("psubq", "sub_epi64", 2),
+    ("pmullw", "mullo_epi16", 2),
+    ("pmulld", "mullo_epi32", 2),
+    ("pmuludq", "mul_epu32", 2),
+    ("pmaddwd", "madd_epi16", 2),
+    ("pminub", "min_epu8", 2),
+    ("pmaxub", "max_epu8", 2),
+    ("pminsw", "min_epi16", 2),
+    ("pmaxsw", "max_epi16", 2),
+    ("pminsd", "min_epi32", 2),
+    ("pmaxsd", "max_epi32", 2),
+    ("pminud", "min_epu32", 2),
+    ("pmaxud", "max_epu32", 2),
+    ("pand", "and_si128", 2),
+    ("pandn", "andnot_si128", 2),
+    ("por", "or_si128", 2),
+    ("pxor", "xor_si128", 2),
+    ("pcmpeqb", "cmpeq_epi8", 2),
+    ("pcmpeqw", "cmpeq_epi16", 2),
+    ("pcmpeqd", "cmpeq_epi32", 2),
+    ("pcmpeqq", "cmpeq_epi64", 2),
+    ("pcmpgtb", "cmpgt_epi8", 2),
+    ("pcmpgtw", "cmpgt_epi16", 2),
+    ("pcmpgtd", "cmpgt_epi32", 2),
+    ("pcmpgtq", "cmpgt_epi64", 2),
+    ("punpcklbw", "unpacklo_epi8", 2),
+    ("punpcklwd", "unpacklo_epi16", 2),
+    ("punpckldq", "unpacklo_epi32", 2),
+    ("punpcklqdq", "unpacklo_epi64", 2),
+    ("punpckhbw", "unpackhi_epi8", 2),
+    ("punpckhwd", "unpackhi_epi16", 2),
+    ("punpckhdq", "unpackhi_epi32", 2),
+    ("punpckhqdq", "unpackhi_epi64", 2),
+    ("packsswb", "packs_epi16", 2),
+    ("packssdw", "packs_epi32", 2),
+    ("packuswb", "packus_epi16", 2),
+    ("pshufb", "shuffle_epi8", 2),
+    ("pshufd", "shuffle_epi32", 1),
+    ("pshuflw", "shufflelo_epi16", 1),
+    ("pshufhw", "shufflehi_epi16", 1),
+    ("pslldq", "slli_si128", 1),
+    ("psrldq", "srli_si128", 1),
+    ("pmovmskb", "movemask_epi8", 1),
+];
+
+/// Lifts the SSE and AVX instructions: scalar floating-point arithmetic, conversions and
+/// comparisons into typed expressions, and the packed instructions of `INTRINSICS` into
+/// calls to their intrinsics. Returns `None` for the others.
+pub fn to_expression(symbols: &mut Vec<Symbol>, inst: Instruction) -> Option<Expr> {
+    if inst.mnemonic() == Mnemonic::Vzeroupper {
+        return Some(get_intrinsic("_mm256_zeroupper", Vec::new()));
+    }
+    // masked AVX-512 forms merge into or zero the lanes left out, which no intrinsic call
+    // written this way would show
+    if inst.op_mask() != Register::None
+        || !((0..inst.op_count()).any(|i| is_vector(&inst, i)) || is_scalar_conversion(&inst))
+    {
+        return None;
+    }
+    let single = matches!(
+        inst.mnemonic(),
+        Mnemonic::Addss
+            | Mnemonic::Vaddss
+            | Mnemonic::Subss
+            | Mnemonic::Vsubss
+            | Mnemonic::Mulss
+            | Mnemonic::Vmulss
+            | Mnemonic::Divss
+            | Mnemonic::Vdivss
+            | Mnemonic::Minss
+            | Mnemonic::Vminss
+            | Mnemonic::Maxss
+            | Mnemonic::Vmaxss
+            | Mnemonic::Sqrtss
+            | Mnemonic::Vsqrtss
+            | Mnemonic::Cvtss2si
+            | Mnemonic::Vcvtss2si
+            | Mnemonic::Cvtsi2ss
+            | Mnemonic::Vcvtsi2ss
+            | Mnemonic::Cvtsd2ss
+            | Mnemonic::Vcvtsd2ss
+            | Mnemonic::Ucomiss
+            | Mnemonic::Vucomiss
+            | Mnemonic::Comiss
+            | Mnemonic::Vcomiss
+    );
+    let (first, second) = sources(&inst);
+    let value = match inst.mnemonic() {
+        Mnemonic::Addss | Mnemonic::Addsd | Mnemonic::Vaddss | Mnemonic::Vaddsd => get_binary(
+            BinOpKind::Add,
+            get_operand(symbols, inst, first),
+            get_operand(symbols, inst, second),
+        ),
+        Mnemonic::Subss | Mnemonic::Subsd | Mnemonic::Vsubss | Mnemonic::Vsubsd => get_binary(
+            BinOpKind::Sub,
+            get_operand(symbols, inst, first),
+            get_operand(symbols, inst, second),
+        ),
+        Mnemonic::Mulss | Mnemonic::Mulsd | Mnemonic::Vmulss | Mnemonic::Vmulsd => get_binary(
+            BinOpKind::Mul,
+            get_operand(symbols, inst, first),
+            get_operand(symbols, inst, second),
+        ),
+        Mnemonic::Divss | Mnemonic::Divsd | Mnemonic::Vdivss | Mnemonic::Vdivsd => get_binary(
+            BinOpKind::Div,
+            get_operand(symbols, inst, first),
+            get_operand(symbols, inst, second),
+        ),
+        Mnemonic::Minss | Mnemonic::Minsd | Mnemonic::Vminss | Mnemonic::Vminsd => {
+            let args = vec![
+                get_operand(symbols, inst, first),
+                get_operand(symbols, inst, second),
+            ];
+            get_intrinsic(if single { "fminf" } else { "fmin" }, args)
+        }
+        Mnemonic::Maxss | Mnemonic::Maxsd | Mnemonic::Vmaxss | Mnemonic::Vmaxsd => {
+            let args = vec![
+                get_operand(symbols, inst, first),
+                get_operand(symbols, inst, second),
+            ];
+            get_intrinsic(if single { "fmaxf" } else { "fmax" }, args)
+        }
+        Mnemonic::Sqrtss | Mnemonic::Sqrtsd | Mnemonic::Vsqrtss | Mnemonic::Vsqrtsd => {
+            let args = vec![get_operand(symbols, inst, second)];
+            get_intrinsic(if single { "sqrtf" } else { "sqrt" }, args)
+        }
+        // the moves of whole registers, and of the scalar in the low lane
+        Mnemonic::Movss
+        | Mnemonic::Movsd
+        | Mnemonic::Vmovss
+        | Mnemonic::Vmovsd
+        | Mnemonic::Movaps
+        | Mnemonic::Movapd
+        | Mnemonic::Movups
+        | Mnemonic::Movupd
+        | Mnemonic::Movdqa
+        | Mnemonic::Movdqu
+        | Mnemonic::Lddqu
+        | Mnemonic::Movd
+        | Mnemonic::Movq
+        | Mnemonic::Vmovaps
+        | Mnemonic::Vmovapd
+        | Mnemonic::Vmovups
+        | Mnemonic::Vmovupd
+        | Mnemonic::Vmovdqa
+        | Mnemonic::Vmovdqu
+        | Mnemonic::Vmovdqa32
+        | Mnemonic::Vmovdqa64
+        | Mnemonic::Vmovdqu8
+        | Mnemonic::Vmovdqu16
+        | Mnemonic::Vmovdqu32
+        | Mnemonic::Vmovdqu64
+        | Mnemonic::Vlddqu
+        | Mnemonic::Vmovd
+        | Mnemonic::Vmovq => get_operand(symbols, inst, second),
+        Mnemonic::Cvtsi2ss | Mnemonic::Vcvtsi2ss | Mnemonic::Cvtsd2ss | Mnemonic::Vcvtsd2ss => {
+            get_cast(get_operand(symbols, inst, second), Ty::F32)
+        }
+        Mnemonic::Cvtsi2sd | Mnemonic::Vcvtsi2sd | Mnemonic::Cvtss2sd | Mnemonic::Vcvtss2sd => {
+            get_cast(get_operand(symbols, inst, second), Ty::F64)
+        }
+        // the C conversion truncates, like these
+        Mnemonic::Cvttss2si | Mnemonic::Cvttsd2si | Mnemonic::Vcvttss2si | Mnemonic::Vcvttsd2si => {
+            get_cast(get_operand(symbols, inst, second), integer(&inst))
+        }
+        // while these round in the current rounding mode
+        Mnemonic::Cvtss2si | Mnemonic::Cvtsd2si | Mnemonic::Vcvtss2si | Mnemonic::Vcvtsd2si => {
+            let args = vec![get_operand(symbols, inst, second)];
+            get_cast(
+                get_intrinsic(if single { "lrintf" } else { "lrint" }, args),
+                integer(&inst),
+            )
+        }
+        Mnemonic::Ucomiss
+        | Mnemonic::Ucomisd
+        | Mnemonic::Comiss
+        | Mnemonic::Comisd
+        | Mnemonic::Vucomiss
+        | Mnemonic::Vucomisd
+        | Mnemonic::Vcomiss
+        | Mnemonic::Vcomisd => return Some(get_comparison(symbols, inst)),
+        // the usual way of zeroing a register
+        Mnemonic::Xorps
+        | Mnemonic::Xorpd
+        | Mnemonic::Pxor
+        | Mnemonic::Vxorps
+        | Mnemonic::Vxorpd
+        | Mnemonic::Vpxor
+        | Mnemonic::Vpxord
+        | Mnemonic::Vpxorq
+            if inst.op_kind(second) == OpKind::Register
+                && inst.op_register(first) == inst.op_register(second) =>
+        {
+            Expr {
+                id: 0,
+                kind: ExprKind::Lit(Lit::U32(0)),
+            }
+        }
+        _ => get_packed(symbols, inst)?,
+    };
+    Some(Expr {
+        id: 0,
+        kind: ExprKind::Assign(Box::new(get_operand(symbols, inst, 0)), Box::new(value)),
+    })
+}
+
+/// Returns `true` if operand `idx` of `inst` is an SSE, AVX or AVX-512 register.
+fn is_vector(inst: &Instruction, idx: u32) -> bool {
+    let reg = inst.op_register(idx);
+    inst.op_kind(idx) == OpKind::Register && (reg.is_xmm() || reg.is_ymm() || reg.is_zmm())
+}
+
+/// Returns `true` if `inst` converts a floating-point value into an integer, which reads
+/// no vector register at all when it converts one in memory, like `cvttss2si edx, [rdi]`.
+fn is_scalar_conversion(inst: &Instruction) -> bool {
+    matches!(
+        inst.mnemonic(),
+        Mnemonic::Cvttss2si
+            | Mnemonic::Cvttsd2si
+            | Mnemonic::Vcvttss2si
+            | Mnemonic::Vcvttsd2si
+            | Mnemonic::Cvtss2si
+            | Mnemonic::Cvtsd2si
+            | Mnemonic::Vcvtss2si
+            | Mnemonic::Vcvtsd2si
+    )
+}
+
+/// Returns the operands read by a two-source instruction. The legacy encodings overwrite
+/// their first source, the VEX and EVEX ones take it as a separate operand. A one-source
+/// instruction reads the second.
+fn sources(inst: &Instruction) -> (u32, u32) {
+    let operands = (0..inst.op_count())
+        .filter(|&i| !is_immediate(inst.op_kind(i)))
+        .count() as u32;
+    if inst.encoding() != EncodingKind::Legacy && operands >= 3 {
+        (1, 2)
+    } else if operands >= 2 {
+        (0, 1)
+    } else {
+        (0, 0)
+    }
+}
+
+fn is_immediate(kind: OpKind) -> bool {
+    matches!(
+        kind,
+        OpKind::Immediate8
+            | OpKind::Immediate8_2nd
+            | OpKind::Immediate16
+            | OpKind::Immediate32
+            | OpKind::Immediate64
+            | OpKind::Immediate8to16
+            | OpKind::Immediate8to32
+            | OpKind::Immediate8to64
+            | OpKind::Immediate32to64
+    )
+}
+
+/// Returns the signed integer type of the general-purpose destination of a conversion.
+fn integer(inst: &Instruction) -> Ty {
+    match inst.op0_register().size() {
+        8 => Ty::I64,
+        _ => Ty::I32,
+    }
+}
+
+pub fn get_cast(expr: Expr, ty: Ty) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Cast(Box::new(expr), ty),
+    }
+}
+
+pub fn get_intrinsic(name: &str, args: Vec<Expr>) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Intrinsic(name.to_string(), args),
+    }
+}
+
+/// Lifts `ucomisd` and friends into the flags they set: ZF, PF and CF all set when either
+/// operand is NaN, else ZF for equal and CF for less than, so that the conditions of the
+/// unsigned integer comparisons hold.
+fn get_comparison(symbols: &mut Vec<Symbol>, inst: Instruction) -> Expr {
+    let compare = |symbols: &mut Vec<Symbol>, name| {
+        get_intrinsic(
+            name,
+            vec![get_operand(symbols, inst, 0), get_operand(symbols, inst, 1)],
+        )
+    };
+    let zf = get_not(compare(symbols, "islessgreater"));
+    let pf = compare(symbols, "isunordered");
+    let cf = get_not(compare(symbols, "isgreaterequal"));
+    let flags = vec![
+        get_flag(symbols, "ZF"),
+        get_flag(symbols, "PF"),
+        get_flag(symbols, "CF"),
+    ];
+    Expr {
+        id: 0,
+        kind: ExprKind::Assign(
+            Box::new(Expr {
+                id: 0,
+                kind: ExprKind::Tup(flags),
+            }),
+            Box::new(Expr {
+                id: 0,
+                kind: ExprKind::Tup(vec![zf, pf, cf]),
+            }),
+        ),
+    }
+}
+
+/// Lifts a packed instruction of `INTRINSICS` into a call to the intrinsic for the width
+/// of its vectors, e.g. `_mm256_add_ps` for `vaddps ymm0, ymm1, ymm2`, with its immediate
+/// as the last argument.
+fn get_packed(symbols: &mut Vec<Symbol>, inst: Instruction) -> Option<Expr> {
+    let mnemonic = format!("{:?}", inst.mnemonic()).to_lowercase();
+    let mnemonic = match inst.encoding() {
+        EncodingKind::Legacy => mnemonic.as_str(),
+        _ => mnemonic.strip_prefix('v')?,
+    };
+    let (_, name, arity) = INTRINSICS.iter().find(|(entry, ..)| *entry == mnemonic)?;
+
+    let bits = (0..inst.op_count())
+        .filter(|&i| is_vector(&inst, i))
+        .map(|i| inst.op_register(i).size() * 8)
+        .max()?;
+    let name = match bits {
+        128 => format!("_mm_{name}"),
+        bits => format!("_mm{bits}_{}", name.replace("si128", &format!("si{bits}"))),
+    };
+
+    let (first, second) = sources(&inst);
+    let mut args = match arity {
+        1 => vec![get_operand(symbols, inst, second)],
+        _ => vec![
+            get_operand(symbols, inst, first),
+            get_operand(symbols, inst, second),
+        ],
+    };
+    if let Some(imm) = (0..inst.op_count()).find(|&i| is_immediate(inst.op_kind(i))) {
+        args.push(get_operand(symbols, inst, imm));
+    }
+    Some(get_intrinsic(&name, args))
+}
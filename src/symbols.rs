@@ -0,0 +1,342 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use iced_x86::{Code, Decoder, Instruction, OpKind, Register, SymbolResolver, SymbolResult};
+use object::macho;
+use object::read::macho::{MachOFile64, Nlist};
+use object::read::pe::{ImageNtHeaders, Import, PeFile};
+use object::{
+    Endianness, LittleEndian as LE, Object, ObjectSection, ObjectSymbol, ObjectSymbolTable,
+    ReadRef, RelocationTarget, SymbolIndex, SymbolKind, U32,
+};
+
+use crate::aarch64::{self, IndexMode, Opcode, Operand};
+use crate::riscv::{self, T3};
+use crate::arch::Architecture;
+use crate::memory::memory::Memory;
+use crate::rtti;
+
+/// Names known for addresses of the binary, from its symbol tables and imports.
+#[derive(Clone, Default)]
+pub struct SymbolTable {
+    names: BTreeMap<u64, String>,
+    functions: BTreeSet<u64>,
+}
+
+impl SymbolTable {
+    pub fn from_file(file: &object::File, memory: &Memory) -> Self {
+        let mut table = Self::default();
+
+        for sym in file.symbols().chain(file.dynamic_symbols()) {
+            if sym.address() == 0 || !matches!(sym.kind(), SymbolKind::Text | SymbolKind::Data) {
+                continue;
+            }
+            if let Ok(name) = sym.name() {
+                if !name.is_empty() {
+                    let addr = memory.rebase(sym.address());
+                    table.insert(addr, name);
+                    if sym.kind() == SymbolKind::Text {
+                        table.functions.insert(addr);
+                    }
+                }
+            }
+        }
+
+        for export in file.exports().unwrap_or_default() {
+            let addr = memory.rebase(export.address());
+            table.insert(addr, &String::from_utf8_lossy(export.name()));
+            if memory.is_executable(addr) {
+                table.functions.insert(addr);
+            }
+        }
+
+        let imported = match file {
+            object::File::Pe32(pe) => table.add_pe_imports(pe, memory),
+            object::File::Pe64(pe) => table.add_pe_imports(pe, memory),
+            object::File::MachO64(macho) => table.add_macho_stubs(macho, memory),
+            _ => Ok(()),
+        };
+        if let Err(err) = imported {
+            eprintln!("warning: cannot read the import table: {}", err);
+        }
+
+        // GOT slots filled in by the dynamic linker
+        if let (Some(relocations), Some(dynsym)) =
+            (file.dynamic_relocations(), file.dynamic_symbol_table())
+        {
+            for (offset, relocation) in relocations {
+                if let RelocationTarget::Symbol(idx) = relocation.target() {
+                    if let Ok(name) = dynsym.symbol_by_index(idx).and_then(|sym| sym.name()) {
+                        if !name.is_empty() {
+                            table.insert(memory.rebase(offset), name);
+                        }
+                    }
+                }
+            }
+        }
+
+        table.add_plt_stubs(memory);
+        table
+    }
+
+    /// Names the import address table slots `DLL!function`, as they are called through.
+    fn add_pe_imports<Pe: ImageNtHeaders>(
+        &mut self,
+        file: &PeFile<Pe>,
+        memory: &Memory,
+    ) -> object::Result<()> {
+        let Some(table) = file.import_table()? else {
+            return Ok(());
+        };
+        let image_base = memory.rebase(file.relative_address_base());
+
+        let mut descriptors = table.descriptors()?;
+        while let Some(descriptor) = descriptors.next()? {
+            let dll = String::from_utf8_lossy(table.name(descriptor.name.get(LE))?).into_owned();
+            let first_thunk = descriptor.first_thunk.get(LE);
+            let lookup = match descriptor.original_first_thunk.get(LE) {
+                0 => first_thunk,
+                original => original,
+            };
+
+            let mut thunks = table.thunks(lookup)?;
+            let mut slot = image_base + u64::from(first_thunk);
+            while let Some(thunk) = thunks.next::<Pe>()? {
+                let name = match table.import::<Pe>(thunk)? {
+                    Import::Name(_, name) => String::from_utf8_lossy(name).into_owned(),
+                    Import::Ordinal(ordinal) => format!("#{}", ordinal),
+                };
+                self.insert(slot, &format!("{}!{}", dll, name));
+                slot += std::mem::size_of::<Pe::ImageThunkData>() as u64;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Names the Mach-O symbol pointers after the symbol dyld binds them to, and the
+    /// `__stubs` entries `name@stub`, following the indirect symbol table.
+    fn add_macho_stubs(
+        &mut self,
+        file: &MachOFile64<Endianness>,
+        memory: &Memory,
+    ) -> object::Result<()> {
+        let endian = file.endian();
+        let mut commands = file.macho_load_commands()?;
+        let mut dysymtab = None;
+        while let Some(command) = commands.next()? {
+            dysymtab = dysymtab.or(command.dysymtab()?);
+        }
+        let Some(dysymtab) = dysymtab else {
+            return Ok(());
+        };
+        let Ok(indirect) = file.data().read_slice_at::<U32<Endianness>>(
+            dysymtab.indirectsymoff.get(endian).into(),
+            dysymtab.nindirectsyms.get(endian) as usize,
+        ) else {
+            return Ok(());
+        };
+        let symtab = file.macho_symbol_table();
+
+        for section in file.sections() {
+            let header = section.macho_section();
+            let first = header.reserved1.get(endian) as usize;
+            let (entry_size, suffix) = match header.flags.get(endian) & macho::SECTION_TYPE {
+                macho::S_SYMBOL_STUBS => (u64::from(header.reserved2.get(endian)), "@stub"),
+                macho::S_LAZY_SYMBOL_POINTERS | macho::S_NON_LAZY_SYMBOL_POINTERS => (8, ""),
+                _ => continue,
+            };
+            if entry_size == 0 {
+                continue;
+            }
+
+            for i in 0..section.size() / entry_size {
+                let Some(index) = indirect.get(first + i as usize) else {
+                    break;
+                };
+                let index = index.get(endian);
+                if index & (macho::INDIRECT_SYMBOL_LOCAL | macho::INDIRECT_SYMBOL_ABS) != 0 {
+                    continue;
+                }
+                let symbol = symtab.symbol(SymbolIndex(index as usize))?;
+                let name = String::from_utf8_lossy(symbol.name(endian, symtab.strings())?);
+                let addr = memory.rebase(section.address() + i * entry_size);
+                self.insert(addr, &format!("{}{}", name, suffix));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Names the PLT entries after the GOT slot they jump through.
+    fn add_plt_stubs(&mut self, memory: &Memory) {
+        for (section, entry_size) in [(".plt", 16), (".plt.sec", 16), (".plt.got", 8)] {
+            let Some(range) = memory.section_range(section) else {
+                continue;
+            };
+            let Some(code) = memory.get(range.start) else {
+                continue;
+            };
+
+            match memory.architecture() {
+                Architecture::X86 { bitness } => {
+                    self.add_x86_plt_stubs(code, range.start, bitness, entry_size)
+                }
+                Architecture::AArch64 => self.add_aarch64_plt_stubs(code, range.start),
+                Architecture::RiscV64 => self.add_riscv_plt_stubs(code, range.start),
+            }
+        }
+    }
+
+    fn add_x86_plt_stubs(&mut self, code: &[u8], ip: u64, bitness: u32, entry_size: u64) {
+        let mut decoder = Decoder::with_ip(bitness, code, ip, 0);
+        while decoder.can_decode() {
+            let inst = decoder.decode();
+            // 64-bit PLTs jump through rip-relative GOT slots, non-PIC 32-bit ones
+            // through absolute addresses
+            let slot = match inst.code() {
+                Code::Jmp_rm64 if inst.is_ip_rel_memory_operand() => inst.ip_rel_memory_address(),
+                Code::Jmp_rm32
+                    if inst.op0_kind() == OpKind::Memory
+                        && inst.memory_base() == Register::None
+                        && inst.memory_index() == Register::None =>
+                {
+                    inst.memory_displacement64()
+                }
+                _ => continue,
+            };
+            let stub = inst.ip() & !(entry_size - 1);
+            if let Some(name) = self.get(slot) {
+                let name = format!("{}@plt", name);
+                self.names.entry(stub).or_insert(name);
+            }
+        }
+    }
+
+    /// AArch64 PLT entries load their GOT slot with `adrp x16` and `ldr x17, [x16, #off]`,
+    /// then `br x17`, each entry starting right after the previous branch.
+    fn add_aarch64_plt_stubs(&mut self, code: &[u8], ip: u64) {
+        let mut stub = ip;
+        let mut page = None;
+        let mut slot = None;
+        for (i, word) in code.chunks_exact(4).enumerate() {
+            let address = ip + 4 * i as u64;
+            let inst = aarch64::decode(u32::from_le_bytes(word.try_into().unwrap()), address);
+            match (inst.opcode, inst.operands) {
+                (Opcode::Nop, _) if stub == address => stub += 4,
+                (Opcode::Adrp, [Operand::Reg(reg), Operand::Label(target), ..]) if reg.num == 16 => {
+                    page = Some(target)
+                }
+                (Opcode::Ldr, [Operand::Reg(_), Operand::Mem(mem), ..])
+                    if mem.base.num == 16 && mem.mode == IndexMode::Offset =>
+                {
+                    slot = page.map(|page| page.wrapping_add(mem.offset as u64));
+                }
+                (Opcode::Br, _) => {
+                    if let Some(name) = slot.and_then(|slot| self.get(slot)) {
+                        let name = format!("{}@plt", name);
+                        self.names.entry(stub).or_insert(name);
+                    }
+                    (stub, page, slot) = (address + 4, None, None);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// RISC-V PLT entries load their GOT slot with `auipc t3` and `ld t3, off(t3)`, then
+    /// `jalr t1, t3`, each entry starting right after the previous jump.
+    fn add_riscv_plt_stubs(&mut self, code: &[u8], ip: u64) {
+        let mut stub = ip;
+        let mut page = None;
+        let mut slot = None;
+        for (i, word) in code.chunks_exact(4).enumerate() {
+            let address = ip + 4 * i as u64;
+            let inst = riscv::decode(u32::from_le_bytes(word.try_into().unwrap()), address);
+            match (inst.opcode, inst.operands) {
+                (riscv::Opcode::Nop, _) if stub == address => stub += 4,
+                (riscv::Opcode::Auipc, [riscv::Operand::Reg(T3), riscv::Operand::Imm(imm), _]) => {
+                    page = Some(address.wrapping_add(riscv::upper_immediate(imm)))
+                }
+                (riscv::Opcode::Ld, [_, riscv::Operand::Mem(T3, offset), _]) => {
+                    slot = page.map(|page| page.wrapping_add(offset as u64));
+                }
+                (riscv::Opcode::Jalr | riscv::Opcode::Jr, _) => {
+                    if let Some(name) = slot.and_then(|slot| self.get(slot)) {
+                        let name = format!("{}@plt", name);
+                        self.names.entry(stub).or_insert(name);
+                    }
+                    (stub, page, slot) = (address + 4, None, None);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn insert(&mut self, addr: u64, name: &str) {
+        self.names.entry(addr).or_insert_with(|| name.to_string());
+    }
+
+    /// Names the function at `addr`, unless a symbol already does.
+    pub fn insert_function(&mut self, addr: u64, name: &str) {
+        self.insert(addr, name);
+        self.functions.insert(addr);
+    }
+
+    /// Returns the start of the functions named by the symbol and export tables.
+    pub fn functions(&self) -> impl Iterator<Item = u64> + '_ {
+        self.functions.iter().copied()
+    }
+
+    /// Returns every named address, in order.
+    pub fn names(&self) -> impl Iterator<Item = (u64, &str)> + '_ {
+        self.names.iter().map(|(addr, name)| (*addr, name.as_str()))
+    }
+
+    /// Returns the address named `name`.
+    pub fn lookup(&self, name: &str) -> Option<u64> {
+        self.names
+            .iter()
+            .find(|(_, other)| *other == name)
+            .map(|(addr, _)| *addr)
+    }
+
+    /// Returns the address of the function named `name`: an imported one is called through
+    /// its PLT or `__stubs` entry rather than its GOT slot, which `lookup` gives.
+    pub fn lookup_function(&self, name: &str) -> Option<u64> {
+        ["@plt", "@stub"]
+            .iter()
+            .find_map(|suffix| self.lookup(&format!("{name}{suffix}")))
+            .or_else(|| self.lookup(name))
+    }
+
+    pub fn get(&self, addr: u64) -> Option<&str> {
+        self.names.get(&addr).map(String::as_str)
+    }
+
+    /// Returns the name of the function at `addr`, falling back to Ghidra's `FUN_` naming.
+    pub fn function_name(&self, addr: u64) -> String {
+        self.get(addr)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("FUN_{:X}", addr))
+    }
+
+    /// Returns the name the function at `addr` is shown with in pseudocode: demangled, like
+    /// `zoo::Dog::speak` for `_ZNK3zoo3Dog5speakEv`.
+    pub fn display_name(&self, addr: u64) -> String {
+        rtti::display_name(&self.function_name(addr))
+    }
+}
+
+impl SymbolResolver for SymbolTable {
+    fn symbol(
+        &mut self,
+        _instruction: &Instruction,
+        _operand: u32,
+        _instruction_operand: Option<u32>,
+        address: u64,
+        _address_size: u32,
+    ) -> Option<SymbolResult<'_>> {
+        self.get(address)
+            .map(|name| SymbolResult::with_str(address, name))
+    }
+}
@@ -0,0 +1,632 @@
+use std::collections::{BTreeMap, HashMap};
+
+use iced_x86::{InstructionInfoFactory, Mnemonic, OpAccess, Register};
+use object::{BinaryFormat, Object, ObjectSection, ObjectSymbol, ObjectSymbolTable};
+use object::{RelocationFlags, RelocationTarget, SectionKind, SymbolKind};
+
+use crate::arch::Architecture;
+use crate::ast::{get_register_by_name, BinOpKind, Expr, ExprKind, Lit, Symbol, Ty, UnOp};
+use crate::function::Function;
+use crate::memory::memory::Memory;
+
+/// The fields of the Windows TEB, at their x86-64 and i386 offsets.
+const TEB_FIELDS: &[(&str, u64, u64)] = &[
+    ("NtTib.ExceptionList", 0x0, 0x0),
+    ("NtTib.StackBase", 0x8, 0x4),
+    ("NtTib.StackLimit", 0x10, 0x8),
+    ("NtTib.SubSystemTib", 0x18, 0xC),
+    ("NtTib.FiberData", 0x20, 0x10),
+    ("NtTib.ArbitraryUserPointer", 0x28, 0x14),
+    ("EnvironmentPointer", 0x38, 0x1C),
+    ("ClientId.UniqueProcess", 0x40, 0x20),
+    ("ClientId.UniqueThread", 0x48, 0x24),
+    ("ActiveRpcHandle", 0x50, 0x28),
+    ("ThreadLocalStoragePointer", 0x58, 0x2C),
+    ("ProcessEnvironmentBlock", 0x60, 0x30),
+    ("LastErrorValue", 0x68, 0x34),
+    ("CountOfOwnedCriticalSections", 0x6C, 0x38),
+    ("CurrentLocale", 0x108, 0xC4),
+    ("LastStatusValue", 0x1250, 0xBF4),
+    ("DeallocationStack", 0x1478, 0xE0C),
+    ("TlsExpansionSlots", 0x1780, 0xF94),
+];
+
+/// The `NtTib.Self` field of the TEB, pointing to the TEB itself.
+const TEB_SELF: (u64, u64) = (0x30, 0x18);
+
+/// The `TlsSlots` array of the TEB and its length.
+const TEB_TLS_SLOTS: (u64, u64, u64) = (0x1480, 0xE10, 64);
+
+/// The fields of the Windows PEB, at their x86-64 and i386 offsets.
+const PEB_FIELDS: &[(&str, u64, u64)] = &[
+    ("InheritedAddressSpace", 0x0, 0x0),
+    ("ReadImageFileExecOptions", 0x1, 0x1),
+    ("BeingDebugged", 0x2, 0x2),
+    ("BitField", 0x3, 0x3),
+    ("Mutant", 0x8, 0x4),
+    ("ImageBaseAddress", 0x10, 0x8),
+    ("Ldr", 0x18, 0xC),
+    ("ProcessParameters", 0x20, 0x10),
+    ("SubSystemData", 0x28, 0x14),
+    ("ProcessHeap", 0x30, 0x18),
+    ("FastPebLock", 0x38, 0x1C),
+    ("KernelCallbackTable", 0x58, 0x2C),
+    ("ApiSetMap", 0x68, 0x38),
+    ("TlsExpansionCounter", 0x70, 0x3C),
+    ("TlsBitmap", 0x78, 0x40),
+    ("NumberOfProcessors", 0xB8, 0x64),
+    ("NtGlobalFlag", 0xBC, 0x68),
+    ("HeapSegmentReserve", 0xC8, 0x78),
+    ("NumberOfHeaps", 0xE8, 0x88),
+    ("ProcessHeaps", 0xF0, 0x90),
+    ("OSMajorVersion", 0x118, 0xA4),
+    ("OSMinorVersion", 0x11C, 0xA8),
+    ("OSBuildNumber", 0x120, 0xAC),
+    ("OSPlatformId", 0x124, 0xB0),
+    ("ImageSubsystem", 0x128, 0xB4),
+    ("SessionId", 0x2C0, 0x1D4),
+];
+
+/// The fields of the glibc thread control block the thread pointer points to, at their
+/// x86-64 and i386 offsets, with the names they are known by.
+const TCB_FIELDS: &[(&str, u64, u64)] = &[
+    ("__stack_chk_guard", 0x28, 0x14),
+    ("__pointer_chk_guard", 0x30, 0x18),
+];
+
+/// The `tcb` and `self` fields of the thread control block, pointing to the block itself.
+const TCB_SELF: &[(u64, u64)] = &[(0x0, 0x0), (0x10, 0x8)];
+
+/// A thread-local variable, `offset` bytes from the thread pointer.
+struct Variable {
+    offset: i64,
+    size: u64,
+    name: String,
+}
+
+/// What a register points to, while it is known.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pointer {
+    Teb,
+    Peb,
+}
+
+/// What the FS or GS segment points to: the thread control block of an ELF program, with
+/// its thread-local variables below it, or the TEB of the current thread on Windows.
+#[derive(Default)]
+pub struct ThreadLocals {
+    /// The symbol `get_address` gives the base of the segment holding the thread pointer.
+    base: Option<&'static str>,
+    windows: bool,
+    bits: u32,
+    variables: Vec<Variable>,
+    /// The GOT slots the dynamic linker fills with the offset of a variable from the
+    /// thread pointer, read by the initial-exec model.
+    slots: BTreeMap<u64, String>,
+}
+
+impl ThreadLocals {
+    /// Reads the layout of `.tdata` and `.tbss` and the TLS symbols of an ELF file, or
+    /// notes that the segment points to the TEB for a PE file.
+    pub fn from_file(file: &object::File, memory: &Memory) -> Self {
+        let Architecture::X86 { bitness } = memory.architecture() else {
+            return Self::default();
+        };
+        let windows = file.format() == BinaryFormat::Pe;
+        let base = match (file.format(), bitness) {
+            (BinaryFormat::Pe, 64) | (BinaryFormat::Elf, 32) => "GS_BASE",
+            (BinaryFormat::Pe, 32) | (BinaryFormat::Elf, 64) => "FS_BASE",
+            _ => return Self::default(),
+        };
+        let mut tls = Self {
+            base: Some(base),
+            windows,
+            bits: bitness,
+            ..Self::default()
+        };
+        if windows {
+            return tls;
+        }
+
+        // the variables end at the thread pointer, the block rounded up to its alignment
+        let sections = file
+            .sections()
+            .filter(|section| {
+                matches!(
+                    section.kind(),
+                    SectionKind::Tls | SectionKind::UninitializedTls
+                )
+            })
+            .map(|section| (section.address(), section.size(), section.align()))
+            .collect::<Vec<_>>();
+        let Some(start) = sections.iter().map(|(addr, _, _)| *addr).min() else {
+            return tls;
+        };
+        let end = sections
+            .iter()
+            .map(|(addr, size, _)| addr + size)
+            .max()
+            .unwrap_or(start);
+        let align = sections
+            .iter()
+            .map(|(_, _, align)| *align)
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let size = (end - start).div_ceil(align) * align;
+
+        let mut variables = BTreeMap::new();
+        for sym in file.symbols().chain(file.dynamic_symbols()) {
+            if sym.kind() != SymbolKind::Tls || sym.is_undefined() {
+                continue;
+            }
+            if let Ok(name) = sym.name() {
+                if !name.is_empty() {
+                    let offset = sym.address() as i64 - size as i64;
+                    variables
+                        .entry(offset)
+                        .or_insert((sym.size(), name.to_string()));
+                }
+            }
+        }
+        tls.variables = variables
+            .into_iter()
+            .map(|(offset, (size, name))| Variable { offset, size, name })
+            .collect();
+
+        let tpoff = match bitness {
+            64 => object::elf::R_X86_64_TPOFF64,
+            _ => object::elf::R_386_TLS_TPOFF,
+        };
+        if let (Some(relocations), Some(dynsym)) =
+            (file.dynamic_relocations(), file.dynamic_symbol_table())
+        {
+            for (offset, relocation) in relocations {
+                let RelocationFlags::Elf { r_type } = relocation.flags() else {
+                    continue;
+                };
+                let RelocationTarget::Symbol(idx) = relocation.target() else {
+                    continue;
+                };
+                if r_type != tpoff {
+                    continue;
+                }
+                if let Ok(name) = dynsym.symbol_by_index(idx).and_then(|sym| sym.name()) {
+                    tls.slots.insert(memory.rebase(offset), name.to_string());
+                }
+            }
+        }
+        tls
+    }
+
+    /// Names the thread-local variables, TEB and PEB fields and thread control block
+    /// fields `function` accesses through the segment base.
+    pub fn apply(&self, function: &mut Function) {
+        let Some(base) = self.base else {
+            return;
+        };
+        let mut factory = InstructionInfoFactory::new();
+        for block in function.cfg.blocks.values_mut() {
+            // the registers holding a TEB or PEB pointer or a variable offset from a GOT slot
+            let mut pointers = HashMap::new();
+            let mut offsets = HashMap::new();
+            for (inst, stmt) in block.instructions.iter().zip(block.statements.iter_mut()) {
+                self.rename(stmt, base, &pointers, &offsets);
+                let Some(inst) = inst.as_x86() else {
+                    continue;
+                };
+                if inst.mnemonic() == Mnemonic::Call {
+                    pointers.clear();
+                    offsets.clear();
+                    continue;
+                }
+                for used in factory.info(inst).used_registers() {
+                    if used.access() != OpAccess::Read {
+                        pointers.remove(&used.register().full_register());
+                        offsets.remove(&used.register().full_register());
+                    }
+                }
+                let ExprKind::Assign(lhs, rhs) = &stmt.kind else {
+                    continue;
+                };
+                let ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) = &lhs.kind else {
+                    continue;
+                };
+                let Some(reg) = get_register_by_name(name).map(|reg| reg.full_register()) else {
+                    continue;
+                };
+                match &rhs.kind {
+                    ExprKind::Intrinsic(name, _) if name == "NtCurrentTeb" => {
+                        pointers.insert(reg, Pointer::Teb);
+                    }
+                    ExprKind::Lit(Lit::Symbol(Symbol::Local(name)))
+                        if *name == teb_field("ProcessEnvironmentBlock") =>
+                    {
+                        pointers.insert(reg, Pointer::Peb);
+                    }
+                    ExprKind::Unary(UnOp::Deref, ptr) => {
+                        if let Some(name) =
+                            constant_address(ptr).and_then(|addr| self.slots.get(&addr))
+                        {
+                            offsets.insert(reg, name.clone());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn rename(
+        &self,
+        expr: &mut Expr,
+        base: &str,
+        pointers: &HashMap<Register, Pointer>,
+        offsets: &HashMap<Register, String>,
+    ) {
+        if let ExprKind::Unary(UnOp::Deref, ptr) = &mut expr.kind {
+            if let ExprKind::Cast(addr, Ty::Ptr(ty)) = &mut ptr.kind {
+                if let Some(kind) = self.resolve(addr, ty, base, pointers, offsets) {
+                    expr.kind = kind;
+                    return;
+                }
+            }
+        }
+
+        match &mut expr.kind {
+            ExprKind::Call(_, args)
+            | ExprKind::MethodCall(_, args)
+            | ExprKind::Intrinsic(_, args)
+            | ExprKind::Tup(args) => {
+                for arg in args {
+                    self.rename(arg, base, pointers, offsets);
+                }
+            }
+            ExprKind::Binary(_, lhs, rhs) | ExprKind::Assign(lhs, rhs) => {
+                self.rename(lhs, base, pointers, offsets);
+                self.rename(rhs, base, pointers, offsets);
+            }
+            ExprKind::Unary(_, inner) | ExprKind::Cast(inner, _) => {
+                self.rename(inner, base, pointers, offsets)
+            }
+            ExprKind::If(cond, body) => {
+                self.rename(cond, base, pointers, offsets);
+                for expr in body {
+                    self.rename(expr, base, pointers, offsets);
+                }
+            }
+            ExprKind::Loop(body) => {
+                for expr in body {
+                    self.rename(expr, base, pointers, offsets);
+                }
+            }
+            ExprKind::Ret(Some(value)) => self.rename(value, base, pointers, offsets),
+            _ => {}
+        }
+    }
+
+    /// Returns what replaces the load of a `ty` from `addr`, if it is a known field or
+    /// variable.
+    fn resolve(
+        &self,
+        addr: &mut Expr,
+        ty: &Ty,
+        base: &str,
+        pointers: &HashMap<Register, Pointer>,
+        offsets: &HashMap<Register, String>,
+    ) -> Option<ExprKind> {
+        let (reg, offset) = split(addr)?;
+        if reg == base {
+            return match offset {
+                Offset::Const(offset) if self.windows => self.teb(offset),
+                Offset::Const(offset) => self.tcb(offset, ty),
+                Offset::Reg(name) => {
+                    let reg = get_register_by_name(&name)?.full_register();
+                    Some(local(offsets.get(&reg)?.clone()))
+                }
+                Offset::Indexed(offset) => {
+                    let variable = self.variable(offset)?;
+                    let ExprKind::Binary(_, _, offset_expr) = &mut addr.kind else {
+                        return None;
+                    };
+                    let ExprKind::Binary(_, index, _) = &mut offset_expr.kind else {
+                        return None;
+                    };
+                    let index = std::mem::replace(&mut **index, lit(Lit::StillUnknown));
+                    Some(member(variable, Some(index), offset - variable.offset, ty))
+                }
+            };
+        }
+        let Offset::Const(offset) = offset else {
+            return None;
+        };
+        let fields = match pointers.get(&get_register_by_name(&reg)?.full_register())? {
+            Pointer::Teb => return self.teb(offset),
+            Pointer::Peb => PEB_FIELDS,
+        };
+        let (field, _, _) = fields
+            .iter()
+            .find(|field| self.offset((field.1, field.2)) == offset)?;
+        Some(local(format!(
+            "{}->{field}",
+            teb_field("ProcessEnvironmentBlock")
+        )))
+    }
+
+    /// Returns the TEB field at `offset`.
+    fn teb(&self, offset: u64) -> Option<ExprKind> {
+        if offset == self.offset(TEB_SELF) {
+            return Some(ExprKind::Intrinsic("NtCurrentTeb".to_string(), Vec::new()));
+        }
+        let (slots, slots32, count) = TEB_TLS_SLOTS;
+        let slots = self.offset((slots, slots32));
+        let slot = u64::from(self.bits / 8);
+        if (slots..slots + count * slot).contains(&offset) && (offset - slots).is_multiple_of(slot)
+        {
+            let field = format!("TlsSlots[{}]", (offset - slots) / slot);
+            return Some(local(teb_field(&field)));
+        }
+        let (field, _, _) = TEB_FIELDS
+            .iter()
+            .find(|field| self.offset((field.1, field.2)) == offset)?;
+        Some(local(teb_field(field)))
+    }
+
+    /// Returns the thread control block field at `offset`, or the thread-local variable
+    /// there when it is negative.
+    fn tcb(&self, offset: u64, ty: &Ty) -> Option<ExprKind> {
+        if TCB_SELF.iter().any(|field| self.offset(*field) == offset) {
+            return Some(ExprKind::Intrinsic(
+                "__builtin_thread_pointer".to_string(),
+                Vec::new(),
+            ));
+        }
+        if let Some((field, _, _)) = TCB_FIELDS
+            .iter()
+            .find(|field| self.offset((field.1, field.2)) == offset)
+        {
+            return Some(local(field.to_string()));
+        }
+        let offset = match self.bits {
+            32 => offset as u32 as i32 as i64,
+            _ => offset as i64,
+        };
+        let variable = self.variable(offset)?;
+        Some(member(variable, None, offset - variable.offset, ty))
+    }
+
+    /// Returns the variable `offset` from the thread pointer falls into.
+    fn variable(&self, offset: i64) -> Option<&Variable> {
+        self.variables.iter().find(|variable| {
+            variable.offset <= offset && offset < variable.offset + variable.size.max(1) as i64
+        })
+    }
+
+    /// Picks the x86-64 or i386 offset of a field.
+    fn offset(&self, (offset64, offset32): (u64, u64)) -> u64 {
+        match self.bits {
+            64 => offset64,
+            _ => offset32,
+        }
+    }
+}
+
+/// The offset from a base register of an address.
+enum Offset {
+    Const(u64),
+    Reg(String),
+    /// An indexed address, with the constant part of its offset.
+    Indexed(i64),
+}
+
+/// Splits `addr` into the register it is relative to and the offset from it.
+fn split(addr: &Expr) -> Option<(String, Offset)> {
+    let reg = |expr: &Expr| match &expr.kind {
+        ExprKind::Lit(Lit::Symbol(Symbol::Reg(name))) => Some(name.clone()),
+        _ => None,
+    };
+    if let Some(reg) = reg(addr) {
+        return Some((reg, Offset::Const(0)));
+    }
+    let ExprKind::Binary(op, lhs, rhs) = &addr.kind else {
+        return None;
+    };
+    let base = reg(lhs)?;
+    let offset = match (op, &rhs.kind) {
+        (BinOpKind::Add, ExprKind::Lit(Lit::U64(value))) => Offset::Const(*value),
+        (BinOpKind::Add, ExprKind::Lit(Lit::U32(value))) => Offset::Const(u64::from(*value)),
+        (BinOpKind::Add, ExprKind::Lit(Lit::I64(value))) => Offset::Const(*value as u64),
+        (BinOpKind::Sub, ExprKind::Lit(Lit::I64(value))) => {
+            Offset::Const(value.wrapping_neg() as u64)
+        }
+        (BinOpKind::Add, ExprKind::Lit(Lit::Symbol(Symbol::Reg(name)))) => {
+            Offset::Reg(name.clone())
+        }
+        (BinOpKind::Add, ExprKind::Binary(inner, _, disp)) => match (inner, &disp.kind) {
+            (BinOpKind::Add, ExprKind::Lit(Lit::I64(value))) => Offset::Indexed(*value),
+            (BinOpKind::Sub, ExprKind::Lit(Lit::I64(value))) => Offset::Indexed(-value),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some((base, offset))
+}
+
+/// Reads a `ty` `within` bytes into `variable`, plus `index` when the address is computed:
+/// by name when the access covers the whole variable, else through its address.
+fn member(variable: &Variable, index: Option<Expr>, within: i64, ty: &Ty) -> ExprKind {
+    if index.is_none() && within == 0 && size(ty).is_none_or(|size| size >= variable.size) {
+        return local(variable.name.clone());
+    }
+    let mut addr = lit(Lit::Symbol(Symbol::Local(format!("&{}", variable.name))));
+    if let Some(index) = index {
+        addr = binary(BinOpKind::Add, addr, index);
+    }
+    if within != 0 {
+        addr = binary(BinOpKind::Add, addr, lit(Lit::I64(within)));
+    }
+    ExprKind::Unary(
+        UnOp::Deref,
+        Box::new(Expr {
+            id: 0,
+            kind: ExprKind::Cast(Box::new(addr), Ty::Ptr(Box::new(ty.clone()))),
+        }),
+    )
+}
+
+/// Returns the address `ptr` points to, when it is a constant.
+fn constant_address(ptr: &Expr) -> Option<u64> {
+    match &ptr.kind {
+        ExprKind::Cast(addr, _) => constant_address(addr),
+        ExprKind::Lit(Lit::U64(addr)) => Some(*addr),
+        ExprKind::Lit(Lit::U32(addr)) => Some(u64::from(*addr)),
+        _ => None,
+    }
+}
+
+/// Spells the access to a field of the TEB of the current thread.
+fn teb_field(field: &str) -> String {
+    format!("NtCurrentTeb()->{field}")
+}
+
+/// Returns the size in bytes of a scalar type.
+fn size(ty: &Ty) -> Option<u64> {
+    match ty {
+        Ty::Bool | Ty::U8 | Ty::I8 => Some(1),
+        Ty::U16 | Ty::I16 => Some(2),
+        Ty::U32 | Ty::I32 | Ty::F32 => Some(4),
+        Ty::U64 | Ty::I64 | Ty::F64 | Ty::Ptr(_) => Some(8),
+        Ty::Void | Ty::Vector(..) => None,
+    }
+}
+
+fn local(name: String) -> ExprKind {
+    ExprKind::Lit(Lit::Symbol(Symbol::Local(name)))
+}
+
+fn lit(lit: Lit) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Lit(lit),
+    }
+}
+
+fn binary(op: BinOpKind, lhs: Expr, rhs: Expr) -> Expr {
+    Expr {
+        id: 0,
+        kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::abi::{CallingConvention, Conventions};
+    use crate::rtti::Classes;
+    use crate::unwind::UnwindTable;
+
+    /// Lifts the x86 `code` and names what it reads through the segment base of `tls`.
+    fn statements(code: &[u8], tls: &ThreadLocals) -> Vec<String> {
+        let architecture = Architecture::X86 { bitness: tls.bits };
+        let memory = Memory::raw(code, 0x1000, architecture).unwrap();
+        let conventions = Conventions::new(CallingConvention::SysV);
+        let none = BTreeSet::new();
+        let unwind = UnwindTable::default();
+        let classes = Classes::default();
+        let mut function =
+            Function::lift(&memory, &unwind, 0x1000, &conventions, &none, &none, &classes);
+        tls.apply(&mut function);
+        let block = function.cfg.blocks.values().next().unwrap();
+        block.statements.iter().map(|stmt| stmt.to_string()).collect()
+    }
+
+    #[test]
+    fn windows_fields() {
+        let tls = ThreadLocals {
+            base: Some("GS_BASE"),
+            windows: true,
+            bits: 64,
+            ..ThreadLocals::default()
+        };
+        let code = [
+            0x65, 0x48, 0x8b, 0x04, 0x25, 0x30, 0x00, 0x00, 0x00, // mov rax, gs:[0x30]
+            0x48, 0x8b, 0x48, 0x60, // mov rcx, [rax + 0x60]
+            0x8a, 0x51, 0x02, // mov dl, [rcx + 2]
+            0x65, 0x8b, 0x04, 0x25, 0x68, 0x00, 0x00, 0x00, // mov eax, gs:[0x68]
+            0x65, 0x48, 0x8b, 0x04, 0x25, 0x88, 0x14, 0x00, 0x00, // mov rax, gs:[0x1488]
+            0xc3, // ret
+        ];
+        let statements = statements(&code, &tls);
+        let expected = [
+            "NtCurrentTeb()",
+            "NtCurrentTeb()->ProcessEnvironmentBlock",
+            "NtCurrentTeb()->ProcessEnvironmentBlock->BeingDebugged",
+            "NtCurrentTeb()->LastErrorValue",
+            "NtCurrentTeb()->TlsSlots[1]",
+        ];
+        for field in expected {
+            assert!(
+                statements.iter().any(|stmt| stmt.ends_with(&format!("= {field}"))),
+                "{field} not in {statements:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn i386_teb() {
+        let tls = ThreadLocals {
+            base: Some("FS_BASE"),
+            windows: true,
+            bits: 32,
+            ..ThreadLocals::default()
+        };
+        let code = [
+            0x64, 0xa1, 0x30, 0x00, 0x00, 0x00, // mov eax, fs:[0x30]
+            0xc3, // ret
+        ];
+        let statements = statements(&code, &tls);
+        assert!(
+            statements
+                .iter()
+                .any(|stmt| stmt.ends_with("= NtCurrentTeb()->ProcessEnvironmentBlock")),
+            "{statements:?}"
+        );
+    }
+
+    #[test]
+    fn elf_thread_locals() {
+        let tls = ThreadLocals {
+            base: Some("FS_BASE"),
+            windows: false,
+            bits: 64,
+            variables: vec![Variable {
+                offset: -8,
+                size: 8,
+                name: "counter".to_string(),
+            }],
+            ..ThreadLocals::default()
+        };
+        let code = [
+            0x64, 0x48, 0x8b, 0x04, 0x25, 0x28, 0x00, 0x00, 0x00, // mov rax, fs:[0x28]
+            0x64, 0x48, 0x8b, 0x0c, 0x25, 0x00, 0x00, 0x00, 0x00, // mov rcx, fs:[0]
+            0x64, 0x48, 0x8b, 0x14, 0x25, 0xf8, 0xff, 0xff, 0xff, // mov rdx, fs:[-8]
+            0x64, 0x8b, 0x34, 0x25, 0xfc, 0xff, 0xff, 0xff, // mov esi, fs:[-4]
+            0xc3, // ret
+        ];
+        let statements = statements(&code, &tls);
+        let expected = [
+            "= __stack_chk_guard",
+            "= __builtin_thread_pointer()",
+            "= counter",
+            "= *((uint32_t *)(&counter + 0x4))",
+        ];
+        for value in expected {
+            assert!(
+                statements.iter().any(|stmt| stmt.ends_with(value)),
+                "{value} not in {statements:?}"
+            );
+        }
+    }
+}
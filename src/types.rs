@@ -0,0 +1,457 @@
+use std::fmt::{Result, Write};
+
+const INDENT: &str = "    ";
+
+/// How the bits of a base type are interpreted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BaseKind {
+    Signed,
+    Unsigned,
+    Float,
+    Bool,
+}
+
+/// A C type, as described by debug info.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Void,
+    Base {
+        name: String,
+        kind: BaseKind,
+        size: u64,
+    },
+    Struct(String),
+    Union(String),
+    Enum(String),
+    Typedef(String),
+    Pointer(Box<Type>),
+    Const(Box<Type>),
+    Array(Box<Type>, Option<u64>),
+    Function(Box<Prototype>),
+}
+
+impl Type {
+    /// Spells the C declaration of `name` with this type, e.g. `char *argv[]`. An empty name
+    /// gives the type itself.
+    pub fn declare(&self, name: &str) -> String {
+        let specifier = match self {
+            Type::Void => "void".to_string(),
+            Type::Base { name, .. } => name.clone(),
+            Type::Struct(tag) => format!("struct {tag}"),
+            Type::Union(tag) => format!("union {tag}"),
+            Type::Enum(tag) => format!("enum {tag}"),
+            Type::Typedef(name) => name.clone(),
+            Type::Pointer(inner) => {
+                return match **inner {
+                    Type::Array(..) | Type::Function(_) => inner.declare(&format!("(*{name})")),
+                    _ => inner.declare(&format!("*{name}")),
+                }
+            }
+            // `char *const p` rather than `const char *p`
+            Type::Const(inner) if matches!(**inner, Type::Pointer(_)) => {
+                return inner.declare(&format!("const {name}"))
+            }
+            Type::Const(inner) => return format!("const {}", inner.declare(name)),
+            Type::Array(inner, len) => {
+                let len = len.map(|len| len.to_string()).unwrap_or_default();
+                return inner.declare(&format!("{name}[{len}]"));
+            }
+            Type::Function(prototype) => return prototype.declare(name),
+        };
+        format!("{} {}", specifier.trim_end(), name)
+            .trim_end()
+            .to_string()
+    }
+
+    /// Spells this type in Rust, with the C base types as their fixed-size equivalents.
+    pub fn rust(&self) -> String {
+        match self {
+            Type::Void => "()".to_string(),
+            Type::Base { name, kind, size } => {
+                let prefix = match kind {
+                    BaseKind::Bool => return "bool".to_string(),
+                    BaseKind::Signed => "i",
+                    BaseKind::Unsigned => "u",
+                    BaseKind::Float => "f",
+                };
+                match (kind, size) {
+                    (BaseKind::Float, 4 | 8) | (_, 1 | 2 | 4 | 8 | 16) => {
+                        format!("{prefix}{}", size * 8)
+                    }
+                    _ => name.replace(' ', "_"),
+                }
+            }
+            // the integers only known by size, like `undefined4`, are unsigned ones
+            Type::Typedef(tag) => match undefined_size(tag) {
+                Some(size) => format!("u{}", size * 8),
+                None => rust_identifier(tag),
+            },
+            Type::Struct(tag) | Type::Union(tag) | Type::Enum(tag) => rust_identifier(tag),
+            Type::Pointer(inner) => match &**inner {
+                Type::Function(prototype) => prototype.rust_type(),
+                Type::Const(inner) => format!("*const {}", inner.rust_pointee()),
+                inner => format!("*mut {}", inner.rust_pointee()),
+            },
+            Type::Const(inner) => inner.rust(),
+            Type::Array(inner, len) => format!("[{}; {}]", inner.rust(), len.unwrap_or(0)),
+            Type::Function(prototype) => prototype.rust_type(),
+        }
+    }
+
+    fn rust_pointee(&self) -> String {
+        match self {
+            Type::Void => "c_void".to_string(),
+            _ => self.rust(),
+        }
+    }
+}
+
+/// A named parameter, local variable or member.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    pub ty: Type,
+}
+
+/// The return and parameter types of a function.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prototype {
+    pub return_type: Type,
+    pub parameters: Vec<Variable>,
+    pub variadic: bool,
+    /// The function never returns, like `exit`.
+    pub noreturn: bool,
+}
+
+impl Prototype {
+    /// Spells the C declaration of the function `name`, e.g. `int main(int argc, char **argv)`.
+    pub fn declare(&self, name: &str) -> String {
+        let mut parameters = self
+            .parameters
+            .iter()
+            .map(|parameter| parameter.ty.declare(&parameter.name))
+            .collect::<Vec<_>>();
+        if self.variadic {
+            parameters.push("...".to_string());
+        }
+        if parameters.is_empty() {
+            parameters.push("void".to_string());
+        }
+        let declaration = self
+            .return_type
+            .declare(&format!("{name}({})", parameters.join(", ")));
+        if self.noreturn {
+            format!("_Noreturn {declaration}")
+        } else {
+            declaration
+        }
+    }
+
+    /// Spells the signature of a Rust function `name` with this prototype.
+    pub fn rust_signature(&self, name: &str) -> String {
+        let mut parameters = self
+            .parameters
+            .iter()
+            .enumerate()
+            .map(|(i, parameter)| match parameter.name.as_str() {
+                "" => format!("arg_{}: {}", i, parameter.ty.rust()),
+                name => format!("{}: {}", rust_identifier(name), parameter.ty.rust()),
+            })
+            .collect::<Vec<_>>();
+        if self.variadic {
+            parameters.push("args: ...".to_string());
+        }
+        let name = rust_identifier(name);
+        format!("fn {name}({}){}", parameters.join(", "), self.rust_return())
+    }
+
+    /// Spells the Rust function pointer type with this prototype.
+    fn rust_type(&self) -> String {
+        let mut parameters = self
+            .parameters
+            .iter()
+            .map(|parameter| parameter.ty.rust())
+            .collect::<Vec<_>>();
+        if self.variadic {
+            parameters.push("...".to_string());
+        }
+        format!(
+            "extern \"C\" fn({}){}",
+            parameters.join(", "),
+            self.rust_return()
+        )
+    }
+
+    fn rust_return(&self) -> String {
+        match self.return_type {
+            _ if self.noreturn => " -> !".to_string(),
+            Type::Void => String::new(),
+            _ => format!(" -> {}", self.return_type.rust()),
+        }
+    }
+}
+
+/// A type defined by the program: a struct, union, enum, typedef or C++ class.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeDef {
+    /// A struct or union; an anonymous one takes the name of the typedef naming it.
+    Record {
+        union: bool,
+        tag: Option<String>,
+        typedef: Option<String>,
+        members: Vec<Variable>,
+    },
+    Enum {
+        tag: Option<String>,
+        typedef: Option<String>,
+        enumerators: Vec<(String, i64)>,
+    },
+    Typedef(Variable),
+    /// A C++ class with virtual functions, known from its run-time type information.
+    Class {
+        name: String,
+        bases: Vec<String>,
+        /// The functions in each of its vtables, by address point.
+        vtables: Vec<(u64, Vec<VirtualFunction>)>,
+    },
+}
+
+/// A slot of a C++ vtable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VirtualFunction {
+    /// The name calls through the slot are shown with, like `speak`.
+    pub method: String,
+    /// The demangled name of the function in the slot, or `None` for a pure virtual one.
+    pub declaration: Option<String>,
+    pub address: u64,
+}
+
+impl TypeDef {
+    /// Writes the C definition, e.g. `struct point { int x; int y; };`.
+    pub fn write_c(&self, out: &mut dyn Write) -> Result {
+        let (keyword, tag, typedef) = match self {
+            TypeDef::Record {
+                union,
+                tag,
+                typedef,
+                ..
+            } => (if *union { "union" } else { "struct" }, tag, typedef),
+            TypeDef::Enum { tag, typedef, .. } => ("enum", tag, typedef),
+            TypeDef::Typedef(variable) => {
+                return out.write_fmt(format_args!(
+                    "typedef {};\n",
+                    variable.ty.declare(&variable.name)
+                ))
+            }
+            TypeDef::Class {
+                name,
+                bases,
+                vtables,
+            } => {
+                out.write_fmt(format_args!("class {name}"))?;
+                for (i, base) in bases.iter().enumerate() {
+                    let separator = if i == 0 { " :" } else { "," };
+                    out.write_fmt(format_args!("{separator} public {base}"))?;
+                }
+                out.write_str(" {\n")?;
+                for (address, functions) in vtables {
+                    // a base the compiler left the vtables of out has those of its derived
+                    // classes' at address 0
+                    if *address != 0 {
+                        out.write_fmt(format_args!("{INDENT}// vtable at 0x{address:X}\n"))?;
+                    }
+                    for function in functions {
+                        match (&function.declaration, function.address) {
+                            (Some(declaration), 0) => {
+                                out.write_fmt(format_args!("{INDENT}virtual {declaration};\n"))?
+                            }
+                            (Some(declaration), address) => out.write_fmt(format_args!(
+                                "{INDENT}virtual {declaration}; // 0x{address:X}\n"
+                            ))?,
+                            (None, _) => out.write_fmt(format_args!(
+                                "{INDENT}virtual {}() = 0;\n",
+                                function.method
+                            ))?,
+                        }
+                    }
+                }
+                return out.write_str("};\n");
+            }
+        };
+        if typedef.is_some() {
+            out.write_str("typedef ")?;
+        }
+        out.write_str(keyword)?;
+        if let Some(tag) = tag {
+            out.write_fmt(format_args!(" {tag}"))?;
+        }
+        out.write_str(" {\n")?;
+        match self {
+            TypeDef::Record { members, .. } => {
+                for member in members {
+                    out.write_fmt(format_args!(
+                        "{INDENT}{};\n",
+                        member.ty.declare(&member.name)
+                    ))?;
+                }
+            }
+            TypeDef::Enum { enumerators, .. } => {
+                for (name, value) in enumerators {
+                    out.write_fmt(format_args!("{INDENT}{name} = {value},\n"))?;
+                }
+            }
+            TypeDef::Typedef(_) | TypeDef::Class { .. } => {}
+        }
+        match typedef {
+            Some(name) => out.write_fmt(format_args!("}} {name};\n")),
+            None => out.write_str("};\n"),
+        }
+    }
+
+    /// Writes the Rust definition: a `#[repr(C)]` struct, union or enum, or a type alias.
+    pub fn write_rust(&self, out: &mut dyn Write) -> Result {
+        let (keyword, tag, typedef) = match self {
+            TypeDef::Record {
+                union,
+                tag,
+                typedef,
+                ..
+            } => (if *union { "union" } else { "struct" }, tag, typedef),
+            TypeDef::Enum { tag, typedef, .. } => ("enum", tag, typedef),
+            TypeDef::Typedef(variable) => {
+                return out.write_fmt(format_args!(
+                    "type {} = {};\n",
+                    rust_identifier(&variable.name),
+                    variable.ty.rust()
+                ))
+            }
+            // the virtual functions of a class are those of a trait object
+            TypeDef::Class {
+                name,
+                bases,
+                vtables,
+            } => {
+                out.write_fmt(format_args!("trait {}", rust_identifier(name)))?;
+                if !bases.is_empty() {
+                    let bases = bases.iter().map(|base| rust_identifier(base));
+                    out.write_fmt(format_args!(": {}", bases.collect::<Vec<_>>().join(" + ")))?;
+                }
+                out.write_str(" {\n")?;
+                for (address, functions) in vtables {
+                    if *address != 0 {
+                        out.write_fmt(format_args!("{INDENT}// vtable at 0x{address:X}\n"))?;
+                    }
+                    for function in functions {
+                        let method = rust_identifier(&function.method);
+                        out.write_fmt(format_args!("{INDENT}fn {method}(&self);"))?;
+                        match function.address {
+                            0 => out.write_str("\n")?,
+                            address => out.write_fmt(format_args!(" // 0x{address:X}\n"))?,
+                        }
+                    }
+                }
+                return out.write_str("}\n");
+            }
+        };
+        let name = typedef
+            .as_ref()
+            .or(tag.as_ref())
+            .map_or("_".to_string(), |name| rust_identifier(name));
+        out.write_fmt(format_args!("#[repr(C)]\n{keyword} {name} {{\n"))?;
+        match self {
+            TypeDef::Record { members, .. } => {
+                for member in members {
+                    out.write_fmt(format_args!(
+                        "{INDENT}{}: {},\n",
+                        rust_identifier(&member.name),
+                        member.ty.rust()
+                    ))?;
+                }
+            }
+            TypeDef::Enum { enumerators, .. } => {
+                for (name, value) in enumerators {
+                    let name = rust_identifier(name);
+                    out.write_fmt(format_args!("{INDENT}{name} = {value},\n"))?;
+                }
+            }
+            TypeDef::Typedef(_) | TypeDef::Class { .. } => {}
+        }
+        out.write_str("}\n")
+    }
+}
+
+/// Returns the size in bytes of the integer type `undefinedN` the inferred prototypes name
+/// by size, or `None` for other names.
+fn undefined_size(name: &str) -> Option<u32> {
+    let size = name.strip_prefix("undefined")?.parse::<u32>().ok()?;
+    matches!(size, 1 | 2 | 4 | 8).then_some(size)
+}
+
+/// Spells `name` as a Rust identifier: the characters C and the symbol tables allow but Rust
+/// does not, like the `@` of `puts@plt` or the `::` of a C++ name, become underscores, and
+/// keywords are raw identifiers, like `r#type`.
+pub fn rust_identifier(name: &str) -> String {
+    const KEYWORDS: [&str; 48] = [
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+        "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in",
+        "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+        "return", "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe",
+        "unsized", "use", "virtual", "where", "while", "yield",
+    ];
+    let mut identifier = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect::<String>();
+    if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+    match identifier.as_str() {
+        // the path keywords cannot be raw
+        "self" | "Self" | "super" | "crate" | "_" => identifier + "_",
+        keyword if KEYWORDS.contains(&keyword) => format!("r#{keyword}"),
+        _ => identifier,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rust_identifiers() {
+        assert_eq!(rust_identifier("puts@plt"), "puts_plt");
+        assert_eq!(rust_identifier("Shape::area"), "Shape__area");
+        assert_eq!(rust_identifier("operator new"), "operator_new");
+        assert_eq!(rust_identifier("type"), "r#type");
+        assert_eq!(rust_identifier("self"), "self_");
+        assert_eq!(rust_identifier("3d"), "_3d");
+        assert_eq!(rust_identifier("FUN_1161"), "FUN_1161");
+    }
+
+    #[test]
+    fn rust_signatures() {
+        let prototype = Prototype {
+            return_type: Type::Void,
+            parameters: vec![Variable {
+                name: "ref".to_string(),
+                ty: Type::Typedef("size_t".to_string()),
+            }],
+            variadic: false,
+            noreturn: false,
+        };
+        assert_eq!(
+            prototype.rust_signature("memset@plt"),
+            "fn memset_plt(r#ref: size_t)"
+        );
+        let prototype = Prototype {
+            return_type: Type::Typedef("undefined4".to_string()),
+            parameters: vec![Variable {
+                name: String::new(),
+                ty: Type::Typedef("undefined8".to_string()),
+            }],
+            variadic: false,
+            noreturn: false,
+        };
+        assert_eq!(prototype.rust_signature("f"), "fn f(arg_0: u64) -> u32");
+    }
+}
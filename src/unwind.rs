@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+
+use gimli::{
+    BaseAddresses, CfaRule, CieOrFde, DebugFrame, EhFrame, EhFrameHdr, EndianSlice,
+    FrameDescriptionEntry, Register, RunTimeEndian, UnwindContext, UnwindSection,
+};
+use object::{Object, ObjectSection};
+
+use crate::arch::Architecture;
+use crate::memory::memory::Memory;
+
+type Reader<'data> = EndianSlice<'data, RunTimeEndian>;
+
+/// The unwind rows of a function, described by one FDE.
+struct Frame {
+    end: u64,
+    /// Where each row starts, with the register and offset the CFA is computed from when it
+    /// is not given by an expression.
+    rows: Vec<(u64, Option<(Register, i64)>)>,
+}
+
+/// Function boundaries and stack heights read from the call frame information, which
+/// compilers emit for unwinding even in stripped binaries.
+#[derive(Default)]
+pub struct UnwindTable {
+    frames: BTreeMap<u64, Frame>,
+    stack_pointer: Option<Register>,
+}
+
+impl UnwindTable {
+    /// Reads the FDEs of `.eh_frame`, those indexed by `.eh_frame_hdr` and those of
+    /// `.debug_frame`.
+    pub fn from_file(file: &object::File, memory: &Memory) -> Self {
+        let mut table = Self::default();
+        let Some(architecture) = Architecture::for_file(file) else {
+            return table;
+        };
+        table.stack_pointer = Some(stack_pointer(architecture));
+        if let Err(err) = table.add_sections(file, memory) {
+            eprintln!("warning: cannot read the unwind tables: {}", err);
+        }
+        // the linker describes the PLT with a single FDE, its stubs are named elsewhere
+        for name in [".plt", ".plt.sec", ".plt.got"] {
+            if let Some(range) = memory.section_range(name) {
+                table.frames.retain(|start, _| !range.contains(start));
+            }
+        }
+        table
+    }
+
+    fn add_sections(
+        &mut self,
+        file: &object::File,
+        memory: &Memory,
+    ) -> gimli::Result<()> {
+        let endian = if file.is_little_endian() {
+            RunTimeEndian::Little
+        } else {
+            RunTimeEndian::Big
+        };
+        let address_size = if file.is_64() { 8 } else { 4 };
+        let section = |name| {
+            file.section_by_name(name)
+                .and_then(|section| Some((section.address(), section.data().ok()?)))
+        };
+
+        let mut bases = BaseAddresses::default();
+        if let Some((address, _)) = section(".text") {
+            bases = bases.set_text(address);
+        }
+        if let Some((address, _)) = section(".got") {
+            bases = bases.set_got(address);
+        }
+
+        if let Some((address, data)) = section(".eh_frame") {
+            bases = bases.set_eh_frame(address);
+            let mut eh_frame = EhFrame::new(data, endian);
+            eh_frame.set_address_size(address_size);
+
+            // the unwinder looks FDEs up through this index, so it is complete even where
+            // walking .eh_frame stops early at a terminator
+            if let Some((address, data)) = section(".eh_frame_hdr") {
+                bases = bases.set_eh_frame_hdr(address);
+                let hdr = EhFrameHdr::new(data, endian).parse(&bases, address_size)?;
+                if let Some(index) = hdr.table() {
+                    let mut entries = index.iter(&bases);
+                    while let Some((_, pointer)) = entries.next()? {
+                        let offset = index.pointer_to_offset(pointer)?;
+                        let fde =
+                            eh_frame.fde_from_offset(&bases, offset, EhFrame::cie_from_offset)?;
+                        self.add_frame(&eh_frame, &bases, &fde, memory)?;
+                    }
+                }
+            }
+            self.add_entries(&eh_frame, &bases, memory)?;
+        }
+
+        if let Some((_, data)) = section(".debug_frame") {
+            let mut debug_frame = DebugFrame::new(data, endian);
+            debug_frame.set_address_size(address_size);
+            self.add_entries(&debug_frame, &bases, memory)?;
+        }
+        Ok(())
+    }
+
+    fn add_entries<'data, S: UnwindSection<Reader<'data>>>(
+        &mut self,
+        section: &S,
+        bases: &BaseAddresses,
+        memory: &Memory,
+    ) -> gimli::Result<()> {
+        let mut entries = section.entries(bases);
+        while let Some(entry) = entries.next()? {
+            if let CieOrFde::Fde(partial) = entry {
+                let fde = partial.parse(S::cie_from_offset)?;
+                self.add_frame(section, bases, &fde, memory)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add_frame<'data, S: UnwindSection<Reader<'data>>>(
+        &mut self,
+        section: &S,
+        bases: &BaseAddresses,
+        fde: &FrameDescriptionEntry<Reader<'data>>,
+        memory: &Memory,
+    ) -> gimli::Result<()> {
+        let start = memory.rebase(fde.initial_address());
+        // FDEs of discarded functions are left in place with a null or empty range
+        if fde.len() == 0 || fde.initial_address() == 0 || self.frames.contains_key(&start) {
+            return Ok(());
+        }
+
+        let mut rows = Vec::new();
+        let mut context = UnwindContext::new();
+        let mut table = fde.rows(section, bases, &mut context)?;
+        while let Some(row) = table.next_row()? {
+            let cfa = match row.cfa() {
+                CfaRule::RegisterAndOffset { register, offset } => Some((*register, *offset)),
+                CfaRule::Expression(_) => None,
+            };
+            rows.push((memory.rebase(row.start_address()), cfa));
+        }
+        let end = memory.rebase(fde.end_address());
+        self.frames.insert(start, Frame { end, rows });
+        Ok(())
+    }
+
+    /// Returns the start of every function described by an FDE.
+    pub fn functions(&self) -> impl Iterator<Item = u64> + '_ {
+        self.frames.keys().copied()
+    }
+
+    /// Returns the end of the function starting at `start`, if an FDE describes it.
+    pub fn function_end(&self, start: u64) -> Option<u64> {
+        self.frames.get(&start).map(|frame| frame.end)
+    }
+
+    /// Returns how many bytes the stack pointer is below its value on entry to the function
+    /// containing `addr`, before the instruction at `addr` runs. Unknown once the CFA is
+    /// computed from the frame pointer.
+    pub fn stack_depth(&self, addr: u64) -> Option<i64> {
+        let (register, offset) = self.cfa(addr)?;
+        let (_, frame) = self.frames.range(..=addr).next_back()?;
+        let (entry_register, entry_offset) = frame.rows.first()?.1?;
+        let sp = self.stack_pointer?;
+        if register != sp || entry_register != sp {
+            return None;
+        }
+        Some(offset - entry_offset)
+    }
+
+    /// Returns the register the CFA is computed from before the instruction at `addr` runs,
+    /// and the offset added to it.
+    pub fn cfa(&self, addr: u64) -> Option<(Register, i64)> {
+        let (_, frame) = self.frames.range(..=addr).next_back()?;
+        if addr >= frame.end {
+            return None;
+        }
+        let (_, cfa) = *frame.rows.iter().rev().find(|(start, _)| *start <= addr)?;
+        cfa
+    }
+}
+
+/// Returns the DWARF number of the stack pointer of `architecture`.
+fn stack_pointer(architecture: Architecture) -> Register {
+    match architecture {
+        Architecture::X86 { bitness: 64 } => gimli::X86_64::RSP,
+        Architecture::X86 { .. } => gimli::X86::ESP,
+        Architecture::AArch64 => gimli::AArch64::SP,
+        Architecture::RiscV64 => gimli::RiscV::SP,
+    }
+}
@@ -0,0 +1,691 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use iced_x86::{
+    ConditionCode, FlowControl, InstructionInfoFactory, Mnemonic, OpAccess, OpKind, Register,
+};
+
+use crate::arch::{Flow, Instruction};
+use crate::ast::get_register_name;
+use crate::cfg::{Cfg, EdgeKind};
+use crate::memory::memory::Memory;
+use crate::slots::{self, frame_slot, sign_extend, Slot, Slots};
+
+/// Sets of more values than this are taken for any value.
+const MAX_VALUES: usize = 1024;
+
+/// Blocks merged into more often than this keep only what did not change, so that loops
+/// counting up are not followed to the end.
+const MAX_VISITS: usize = 8;
+
+/// The registers kept across calls by every calling convention.
+const PRESERVED: [Register; 7] = [
+    Register::RBX,
+    Register::RBP,
+    Register::RSP,
+    Register::R12,
+    Register::R13,
+    Register::R14,
+    Register::R15,
+];
+
+type Values = BTreeSet<u64>;
+
+/// A jump through a table of code addresses, like those compilers build for `switch`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JumpTable {
+    /// The first instruction computing the target from the index, from which on the
+    /// instructions up to the jump do nothing else.
+    pub start: u64,
+    /// The register or stack slot indexing the table.
+    pub index: Place,
+    /// The targets, by value of the index.
+    pub cases: Vec<(u64, u64)>,
+}
+
+impl JumpTable {
+    /// Returns the distinct targets, in address order.
+    pub fn targets(&self) -> Vec<u64> {
+        let targets = self
+            .cases
+            .iter()
+            .map(|(_, target)| *target)
+            .collect::<Values>();
+        targets.into_iter().collect()
+    }
+}
+
+/// What is known on entry to an instruction, on every path reaching it: the values each
+/// register and stack slot may hold, when they are few. Registers and slots left out may
+/// hold anything.
+#[derive(Clone, Default, PartialEq, Eq)]
+struct State {
+    registers: BTreeMap<Register, Values>,
+    slots: Slots<Values>,
+    /// What was last compared with a constant, and the constant, which a following branch
+    /// bounds.
+    compared: Option<(Place, u64)>,
+}
+
+/// A register, or the stack slot of the given size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Place {
+    Register(Register),
+    Slot(Slot, usize),
+}
+
+impl State {
+    fn join(&self, other: &State) -> State {
+        let mut registers = BTreeMap::new();
+        for (reg, values) in self.registers.iter() {
+            if let Some(others) = other.registers.get(reg) {
+                let union = values | others;
+                if union.len() <= MAX_VALUES {
+                    registers.insert(*reg, union);
+                }
+            }
+        }
+        State {
+            registers,
+            slots: self.slots.agreeing(&other.slots),
+            compared: self.compared.clone().filter(|_| self.compared == other.compared),
+        }
+    }
+
+    /// Keeps what `next` agrees with `self` on.
+    fn widen(&self, next: &State) -> State {
+        State {
+            registers: next
+                .registers
+                .iter()
+                .filter(|(reg, values)| self.registers.get(reg) == Some(values))
+                .map(|(reg, values)| (*reg, values.clone()))
+                .collect(),
+            slots: next.slots.agreeing(&self.slots),
+            compared: next.compared.clone().filter(|_| self.compared == next.compared),
+        }
+    }
+
+    fn read(&self, reg: Register) -> Option<Values> {
+        if matches!(
+            reg,
+            Register::AH | Register::BH | Register::CH | Register::DH
+        ) {
+            return None;
+        }
+        let values = self.registers.get(&reg.full_register())?;
+        Some(
+            values
+                .iter()
+                .map(|value| mask(*value, reg.size()))
+                .collect(),
+        )
+    }
+
+    fn write(&mut self, reg: Register, values: Values) {
+        self.registers.insert(reg.full_register(), values);
+    }
+
+    /// Bounds what was compared on the edge of `kind` leaving a block ending with `branch`.
+    fn refine(&mut self, branch: &iced_x86::Instruction, kind: EdgeKind) {
+        let Some((place, bound)) = self.compared.clone() else {
+            return;
+        };
+        let taken = match kind {
+            EdgeKind::True => true,
+            EdgeKind::False => false,
+            _ => return,
+        };
+        // the values kept on each side, all below the bound when the other side is above
+        let keep: Box<dyn Fn(u64) -> bool> = match (branch.condition_code(), taken) {
+            (ConditionCode::a, false) | (ConditionCode::be, true) => Box::new(|v| v <= bound),
+            (ConditionCode::ae, false) | (ConditionCode::b, true) => Box::new(|v| v < bound),
+            (ConditionCode::a, true) | (ConditionCode::be, false) => Box::new(|v| v > bound),
+            (ConditionCode::ae, true) | (ConditionCode::b, false) => Box::new(|v| v >= bound),
+            (ConditionCode::e, true) | (ConditionCode::ne, false) => Box::new(|v| v == bound),
+            (ConditionCode::e, false) | (ConditionCode::ne, true) => Box::new(|v| v != bound),
+            _ => return,
+        };
+        let known = match place {
+            Place::Register(reg) => self.read(reg),
+            Place::Slot(ref slot, size) => self
+                .slots
+                .get(slot)
+                .filter(|(stored, _)| *stored == size)
+                .map(|(_, values)| values.clone()),
+        };
+        let values = match known {
+            Some(values) => values.into_iter().filter(|v| keep(*v)).collect(),
+            None if bound < MAX_VALUES as u64 && !keep(bound + 1) => {
+                (0..=bound).filter(|v| keep(*v)).collect()
+            }
+            None => return,
+        };
+        match place {
+            Place::Register(reg) => self.write(reg, values),
+            Place::Slot(slot, size) => self.slots.insert(slot, size, values),
+        }
+    }
+}
+
+/// Resolves the jump tables and the calls through a register or a pointer of the x86 code
+/// in `cfg`, entered at `entry`, by the values the registers and stack slots may hold at
+/// each of them.
+pub fn resolve(
+    cfg: &Cfg,
+    entry: u64,
+    end: Option<u64>,
+    memory: &Memory,
+) -> (BTreeMap<u64, JumpTable>, BTreeMap<u64, Vec<u64>>) {
+    let Some(states) = analyze(cfg, entry, memory) else {
+        return Default::default();
+    };
+    let tables = jump_tables(cfg, &states, entry, end, memory);
+    let calls = call_targets(cfg, &states, &tables, memory);
+    (tables, calls)
+}
+
+/// Returns the jump tables of `cfg`, by address of their jump. Only the jumps loading their
+/// target from a table indexed by a bounded register or stack slot are resolved, and when
+/// the function is known to end at `end` not those leaving it: they are tail calls.
+fn jump_tables(
+    cfg: &Cfg,
+    states: &BTreeMap<u64, State>,
+    entry: u64,
+    end: Option<u64>,
+    memory: &Memory,
+) -> BTreeMap<u64, JumpTable> {
+    let mut tables = BTreeMap::new();
+    let mut factory = InstructionInfoFactory::new();
+    for block in cfg.blocks.values() {
+        let Some(Instruction::X86(jump)) = block.instructions.last() else {
+            continue;
+        };
+        if jump.flow_control() != FlowControl::IndirectBranch
+            || Instruction::X86(*jump).slot().is_some()
+        {
+            continue;
+        }
+        let insts = block
+            .instructions
+            .iter()
+            .filter_map(Instruction::as_x86)
+            .collect::<Vec<_>>();
+        // the last read of a table
+        let Some(load) = insts.iter().rposition(|inst| {
+            (0..inst.op_count()).any(|op| inst.op_kind(op) == OpKind::Memory)
+                && (inst.memory_index() != Register::None || inst.memory_base() != Register::None)
+                && inst.mnemonic() != Mnemonic::Lea
+        }) else {
+            continue;
+        };
+        let Some(mut state) = states.get(&block.start).cloned() else {
+            continue;
+        };
+        // the index is the first register or stack slot whose values alone decide the
+        // target, from where on nothing but the target is computed
+        let mut found = None;
+        for start in 0..=load {
+            let computes =
+                insts[start..insts.len() - 1].iter().all(|inst| {
+                    inst.flow_control() == FlowControl::Next
+                        && factory.info(inst).used_memory().iter().all(|used| {
+                            matches!(used.access(), OpAccess::Read | OpAccess::CondRead)
+                        })
+                });
+            if computes {
+                let registers = state
+                    .registers
+                    .iter()
+                    .map(|(reg, values)| (Place::Register(*reg), values));
+                let slots = state
+                    .slots
+                    .iter()
+                    .map(|(slot, (size, values))| (Place::Slot(slot.clone(), *size), values));
+                found = registers
+                    .chain(slots)
+                    .filter(|(_, values)| values.len() > 1)
+                    .find_map(|(index, values)| {
+                        let cases = cases(&state, &index, values, &insts[start..], memory)?;
+                        Some((start, index, cases))
+                    });
+                if found.is_some() {
+                    break;
+                }
+            }
+            step(&mut state, insts[start], memory, &mut factory);
+        }
+        let Some((start, index, cases)) = found else {
+            continue;
+        };
+        // a jump reached by a tail call may lead anywhere in the function it went to
+        let inside = |addr: u64| end.is_none_or(|end| (entry..end).contains(&addr));
+        let leaves = inside(jump.ip()) && cases.iter().any(|(_, target)| !inside(*target));
+        if !leaves {
+            tables.insert(
+                jump.ip(),
+                JumpTable {
+                    start: insts[start].ip(),
+                    index,
+                    cases,
+                },
+            );
+        }
+    }
+    tables
+}
+
+/// Returns the target `insts`, ending with an indirect jump, go to for each of the `values`
+/// of `index` in `state`, if each gives just one.
+fn cases(
+    state: &State,
+    index: &Place,
+    values: &Values,
+    insts: &[&iced_x86::Instruction],
+    memory: &Memory,
+) -> Option<Vec<(u64, u64)>> {
+    let mut factory = InstructionInfoFactory::new();
+    let (jump, computation) = insts.split_last()?;
+    let mut cases = Vec::new();
+    for &value in values {
+        let mut state = state.clone();
+        match index {
+            Place::Register(reg) => state.write(*reg, Values::from([value])),
+            Place::Slot(slot, size) => {
+                state.slots.insert(slot.clone(), *size, Values::from([value]));
+            }
+        }
+        for inst in computation.iter() {
+            step(&mut state, inst, memory, &mut factory);
+        }
+        let targets = target(&state, jump, memory)?;
+        match targets.first() {
+            Some(&target) if targets.len() == 1 && memory.is_executable(target) => {
+                cases.push((value, target))
+            }
+            _ => return None,
+        }
+    }
+    Some(cases)
+}
+
+/// Returns the code addresses the calls of `cfg` through a register or a pointer may go to,
+/// like the entries of a table of callbacks. The jumps which are not through one of the
+/// `tables` are taken for tail calls and resolved too.
+fn call_targets(
+    cfg: &Cfg,
+    states: &BTreeMap<u64, State>,
+    tables: &BTreeMap<u64, JumpTable>,
+    memory: &Memory,
+) -> BTreeMap<u64, Vec<u64>> {
+    let mut calls = BTreeMap::new();
+    let mut factory = InstructionInfoFactory::new();
+    for block in cfg.blocks.values() {
+        let Some(mut state) = states.get(&block.start).cloned() else {
+            continue;
+        };
+        for inst in block.instructions.iter().filter_map(Instruction::as_x86) {
+            let indirect = match inst.flow_control() {
+                FlowControl::IndirectCall => true,
+                FlowControl::IndirectBranch => !tables.contains_key(&inst.ip()),
+                _ => false,
+            };
+            if indirect && Instruction::X86(*inst).slot().is_none() {
+                let targets = target(&state, inst, memory).unwrap_or_default();
+                if !targets.is_empty() && targets.iter().all(|addr| memory.is_executable(*addr)) {
+                    calls.insert(inst.ip(), targets.into_iter().collect());
+                }
+            }
+            step(&mut state, inst, memory, &mut factory);
+        }
+    }
+    calls
+}
+
+/// Returns the state on entry to each block of `cfg` reached from its entry.
+fn analyze(cfg: &Cfg, entry: u64, memory: &Memory) -> Option<BTreeMap<u64, State>> {
+    // most functions have nothing to resolve
+    let mut insts = cfg
+        .blocks
+        .values()
+        .flat_map(|block| block.instructions.iter());
+    if !insts.any(|inst| {
+        matches!(inst.flow(), Flow::IndirectBranch | Flow::IndirectCall) && inst.slot().is_none()
+    }) {
+        return None;
+    }
+    let mut successors = BTreeMap::<u64, Vec<_>>::new();
+    for edge in cfg.edges.iter() {
+        successors.entry(edge.from).or_default().push(edge);
+    }
+    let mut factory = InstructionInfoFactory::new();
+    let mut states = BTreeMap::from([(entry, State::default())]);
+    let mut visits = BTreeMap::<u64, usize>::new();
+    let mut worklist = vec![entry];
+    while let Some(start) = worklist.pop() {
+        let Some(block) = cfg.blocks.get(&start) else {
+            continue;
+        };
+        let mut state = states[&start].clone();
+        let mut last = None;
+        for inst in block.instructions.iter() {
+            let Instruction::X86(inst) = inst else {
+                return None;
+            };
+            step(&mut state, inst, memory, &mut factory);
+            last = Some(inst);
+        }
+        for edge in successors.get(&start).into_iter().flatten() {
+            let mut out = state.clone();
+            if let Some(last) = last {
+                out.refine(last, edge.kind);
+            }
+            let merged = match states.get(&edge.to) {
+                Some(old) => {
+                    let visits = visits.entry(edge.to).or_default();
+                    *visits += 1;
+                    match *visits > MAX_VISITS {
+                        true => old.widen(&old.join(&out)),
+                        false => old.join(&out),
+                    }
+                }
+                None => out,
+            };
+            if states.get(&edge.to) != Some(&merged) {
+                states.insert(edge.to, merged);
+                worklist.push(edge.to);
+            }
+        }
+    }
+    Some(states)
+}
+
+fn step(
+    state: &mut State,
+    inst: &iced_x86::Instruction,
+    memory: &Memory,
+    factory: &mut InstructionInfoFactory,
+) {
+    if matches!(
+        inst.flow_control(),
+        FlowControl::Call | FlowControl::IndirectCall
+    ) {
+        state.registers.retain(|reg, _| PRESERVED.contains(reg));
+        state.compared = None;
+        return;
+    }
+
+    let values = evaluate(state, inst, memory);
+    let info = factory.info(inst);
+    if inst.rflags_modified() != 0 {
+        let immediate = matches!(
+            inst.op_kind(1),
+            OpKind::Immediate8
+                | OpKind::Immediate8to32
+                | OpKind::Immediate8to64
+                | OpKind::Immediate32
+                | OpKind::Immediate32to64
+        );
+        state.compared = match (inst.mnemonic(), inst.op0_kind()) {
+            (Mnemonic::Cmp, OpKind::Register) if immediate => Some((
+                Place::Register(inst.op0_register()),
+                mask(inst.immediate(1), inst.op0_register().size()),
+            )),
+            (Mnemonic::Cmp, OpKind::Memory) if immediate => {
+                let size = inst.memory_size().size();
+                frame_slot(inst, None)
+                    .map(|slot| (Place::Slot(slot, size), mask(inst.immediate(1), size)))
+            }
+            _ => None,
+        };
+    }
+    for used in info.used_registers() {
+        if !matches!(used.access(), OpAccess::Read | OpAccess::CondRead) {
+            let reg = used.register().full_register();
+            state.registers.remove(&reg);
+            let compared = match &state.compared {
+                Some((Place::Register(compared), _)) => compared.full_register() == reg,
+                Some((Place::Slot((base, _), _), _)) => *base == get_register_name(reg),
+                None => false,
+            };
+            if compared {
+                state.compared = None;
+            }
+        }
+    }
+    let stores = info.used_memory().iter().any(|used| {
+        matches!(
+            used.access(),
+            OpAccess::Write | OpAccess::CondWrite | OpAccess::ReadWrite | OpAccess::ReadCondWrite
+        )
+    });
+    if stores && matches!(state.compared, Some((Place::Slot(..), _))) {
+        state.compared = None;
+    }
+    state.slots.forget(info, None, false);
+
+    let Some(values) = values else {
+        return;
+    };
+    match inst.op0_kind() {
+        _ if inst.mnemonic() == Mnemonic::Cdqe => state.write(Register::RAX, values),
+        // writes of 32 bits clear the upper half, narrower ones keep it
+        OpKind::Register if inst.op0_register().is_gpr() && inst.op0_register().size() >= 4 => {
+            state.write(inst.op0_register(), values);
+        }
+        OpKind::Memory => {
+            if let Some(slot) = frame_slot(inst, None) {
+                state.slots.insert(slot, inst.memory_size().size(), values);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the values `inst` writes to its first operand, when they are few.
+fn evaluate(state: &State, inst: &iced_x86::Instruction, memory: &Memory) -> Option<Values> {
+    if inst.mnemonic() == Mnemonic::Cdqe {
+        let values = state.read(Register::EAX)?;
+        return Some(
+            values
+                .into_iter()
+                .map(|value| sign_extend(value, 32) as u64)
+                .collect(),
+        );
+    }
+    let size = match inst.op0_kind() {
+        OpKind::Register => inst.op0_register().size(),
+        OpKind::Memory => inst.memory_size().size(),
+        _ => return None,
+    };
+    let values = match inst.mnemonic() {
+        Mnemonic::Mov | Mnemonic::Movzx => operand(state, inst, 1, memory)?,
+        Mnemonic::Movsx | Mnemonic::Movsxd => {
+            let from = match inst.op1_kind() {
+                OpKind::Register => inst.op1_register().size(),
+                _ => inst.memory_size().size(),
+            };
+            let values = operand(state, inst, 1, memory)?;
+            values
+                .into_iter()
+                .map(|value| sign_extend(value, from as u32 * 8) as u64)
+                .collect()
+        }
+        Mnemonic::Lea => address(state, inst)?,
+        Mnemonic::Xor
+            if inst.op0_kind() == OpKind::Register
+                && inst.op1_kind() == OpKind::Register
+                && inst.op0_register() == inst.op1_register() =>
+        {
+            Values::from([0])
+        }
+        Mnemonic::Add
+        | Mnemonic::Sub
+        | Mnemonic::And
+        | Mnemonic::Or
+        | Mnemonic::Shl
+        | Mnemonic::Shr => {
+            let lhs = operand(state, inst, 0, memory)?;
+            let rhs = operand(state, inst, 1, memory)?;
+            let op = |a: u64, b: u64| match inst.mnemonic() {
+                Mnemonic::Add => a.wrapping_add(b),
+                Mnemonic::Sub => a.wrapping_sub(b),
+                Mnemonic::And => a & b,
+                Mnemonic::Or => a | b,
+                Mnemonic::Shl => a.wrapping_shl(b as u32),
+                _ => mask(a, size).wrapping_shr(b as u32),
+            };
+            combine(&lhs, &rhs, op)?
+        }
+        _ => return None,
+    };
+    Some(values.into_iter().map(|value| mask(value, size)).collect())
+}
+
+/// Returns the values of operand `op` of `inst`: a register, an immediate, or memory at a
+/// few known addresses.
+fn operand(
+    state: &State,
+    inst: &iced_x86::Instruction,
+    op: u32,
+    memory: &Memory,
+) -> Option<Values> {
+    match inst.op_kind(op) {
+        OpKind::Register => state.read(inst.op_register(op)),
+        OpKind::Memory => {
+            let size = inst.memory_size().size();
+            let slot = frame_slot(inst, None);
+            if let Some((stored, values)) = slot.as_ref().and_then(|slot| state.slots.get(slot)) {
+                return (size <= *stored)
+                    .then(|| values.iter().map(|value| mask(*value, size)).collect());
+            }
+            if slot.is_some()
+                || matches!(inst.memory_segment(), Register::FS | Register::GS)
+            {
+                return None;
+            }
+            address(state, inst)?
+                .into_iter()
+                .map(|addr| read(memory, addr, size))
+                .collect()
+        }
+        OpKind::Immediate32to64 => Some(Values::from([inst.immediate32to64() as u64])),
+        OpKind::Immediate8
+        | OpKind::Immediate16
+        | OpKind::Immediate32
+        | OpKind::Immediate64
+        | OpKind::Immediate8to16
+        | OpKind::Immediate8to32
+        | OpKind::Immediate8to64 => Some(Values::from([inst.immediate(op)])),
+        _ => None,
+    }
+}
+
+/// Returns the addresses the memory operand of `inst` may point to.
+fn address(state: &State, inst: &iced_x86::Instruction) -> Option<Values> {
+    if inst.is_ip_rel_memory_operand() {
+        return Some(Values::from([inst.ip_rel_memory_address()]));
+    }
+    let mut addrs = Values::from([inst.memory_displacement64()]);
+    if inst.memory_base() != Register::None {
+        addrs = combine(&addrs, &state.read(inst.memory_base())?, u64::wrapping_add)?;
+    }
+    if inst.memory_index() != Register::None {
+        let scale = u64::from(inst.memory_index_scale());
+        let index = state.read(inst.memory_index())?;
+        addrs = combine(&addrs, &index, |addr, index| {
+            addr.wrapping_add(index.wrapping_mul(scale))
+        })?;
+    }
+    Some(match inst.code_size() {
+        iced_x86::CodeSize::Code64 => addrs,
+        _ => addrs.into_iter().map(|addr| mask(addr, 4)).collect(),
+    })
+}
+
+/// Returns the addresses `inst`, an indirect call or jump, may go to.
+fn target(state: &State, inst: &iced_x86::Instruction, memory: &Memory) -> Option<Values> {
+    match inst.op0_kind() {
+        OpKind::Register => state.read(inst.op0_register()),
+        OpKind::Memory => operand(state, inst, 0, memory),
+        _ => None,
+    }
+}
+
+fn combine(a: &Values, b: &Values, op: impl Fn(u64, u64) -> u64) -> Option<Values> {
+    if a.len().saturating_mul(b.len()) > MAX_VALUES {
+        return None;
+    }
+    Some(
+        a.iter()
+            .flat_map(|a| b.iter().map(|b| op(*a, *b)))
+            .collect(),
+    )
+}
+
+/// Reads the `size`-byte little-endian value at `addr`, as the image holds it.
+fn read(memory: &Memory, addr: u64, size: usize) -> Option<u64> {
+    if size > 8 {
+        return None;
+    }
+    let bytes = memory.get(addr)?.get(..size)?;
+    let mut value = [0; 8];
+    value[..size].copy_from_slice(bytes);
+    Some(u64::from_le_bytes(value))
+}
+
+/// Returns the `size` low bytes of `value`, all of it when the size is unknown.
+fn mask(value: u64, size: usize) -> u64 {
+    match size {
+        1..=7 => slots::mask(value, size as u32 * 8),
+        _ => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arch::Architecture;
+
+    /// A `switch` through a table of offsets, as built by `gcc -fPIC`, at 0x1000, then at
+    /// 0x103C a function calling through a register and through a pointer in memory.
+    const CODE: [u8; 96] = [
+        0x83, 0xff, 0x03, 0x77, 0x24, 0x89, 0xff, 0x48, 0x8d, 0x15, 0x1e, 0x00, 0x00, 0x00, 0x48,
+        0x63, 0x04, 0xba, 0x48, 0x01, 0xd0, 0xff, 0xe0, 0xb8, 0x0a, 0x00, 0x00, 0x00, 0xc3, 0xb8,
+        0x0b, 0x00, 0x00, 0x00, 0xc3, 0xb8, 0x0c, 0x00, 0x00, 0x00, 0xc3, 0x31, 0xc0, 0xc3, 0xeb,
+        0xff, 0xff, 0xff, 0xf1, 0xff, 0xff, 0xff, 0xf7, 0xff, 0xff, 0xff, 0xeb, 0xff, 0xff, 0xff,
+        0x53, 0x48, 0x8d, 0x05, 0x0d, 0x00, 0x00, 0x00, 0xff, 0xd0, 0x48, 0x8b, 0x0d, 0x0b, 0x00,
+        0x00, 0x00, 0xff, 0xd1, 0x5b, 0xc3, 0xc3, 0x66, 0x0f, 0x1f, 0x44, 0x00, 0x00, 0x51, 0x10,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    fn build(entry: u64) -> Cfg {
+        let memory = Memory::raw(&CODE, 0x1000, Architecture::X86 { bitness: 64 }).unwrap();
+        Cfg::build(&memory, entry, None, &BTreeSet::new(), &BTreeSet::new())
+    }
+
+    #[test]
+    fn jump_table() {
+        let cfg = build(0x1000);
+        let table = &cfg.tables[&0x1015];
+        assert_eq!(table.start, 0x1005);
+        assert_eq!(table.index, Place::Register(Register::RDI));
+        let cases = [(0, 0x1017), (1, 0x101D), (2, 0x1023), (3, 0x1017)];
+        assert_eq!(table.cases, cases);
+        assert_eq!(table.targets(), [0x1017, 0x101D, 0x1023]);
+        for target in table.targets() {
+            assert!(cfg.blocks.contains_key(&target));
+        }
+    }
+
+    #[test]
+    fn unbounded_index() {
+        // past the bounds check, the index may take any value
+        assert!(build(0x1005).tables.is_empty());
+    }
+
+    #[test]
+    fn indirect_calls() {
+        let cfg = build(0x103C);
+        assert_eq!(cfg.calls, BTreeMap::from([(0x1044, vec![0x1051]), (0x104D, vec![0x1051])]));
+    }
+}
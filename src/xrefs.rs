@@ -0,0 +1,473 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use iced_x86::{FlowControl, InstructionInfoFactory, Mnemonic, OpAccess, OpKind, Register};
+use serde::{Deserialize, Serialize};
+
+use crate::aarch64::Opcode;
+use crate::arch::{Flow, Instruction};
+use crate::ast::{Expr, ExprKind, Lit, UnOp};
+use crate::function::Function;
+use crate::memory::memory::Memory;
+use crate::symbols::SymbolTable;
+
+/// Immediates below this are taken for numbers rather than addresses: position-independent
+/// images are mapped from 0, where sizes and counts would land in their sections.
+pub const MIN_IMMEDIATE_ADDRESS: u64 = 0x10000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum XrefKind {
+    Call,
+    Jump,
+    Read,
+    Write,
+    /// The address is loaded or pushed, like a function pointer or a string.
+    Address,
+}
+
+impl Display for XrefKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            XrefKind::Call => "call",
+            XrefKind::Jump => "jump",
+            XrefKind::Read => "read",
+            XrefKind::Write => "write",
+            XrefKind::Address => "addr",
+        })
+    }
+}
+
+/// A reference from the instruction at `from`, in the function at `function`, to `to`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Xref {
+    pub from: u64,
+    pub function: u64,
+    pub to: u64,
+    pub kind: XrefKind,
+}
+
+/// The code and data references made by the lifted functions, indexed by target and by
+/// referencing instruction, with the names of the functions making them. It is built from
+/// the functions lifted by a run, and may be saved for the later runs on the same binary.
+#[derive(Default)]
+pub struct XrefDb {
+    to: BTreeMap<u64, Vec<Xref>>,
+    from: BTreeMap<u64, Vec<Xref>>,
+    functions: BTreeMap<u64, String>,
+}
+
+/// An index as saved to disk: the references, the lookups being rebuilt on loading.
+#[derive(Serialize, Deserialize)]
+struct SavedIndex {
+    functions: BTreeMap<u64, String>,
+    xrefs: Vec<Xref>,
+}
+
+impl XrefDb {
+    /// Returns the path in `dir` of the index of the binary `data`, keyed by a hash of it and
+    /// of the `settings` it was analysed with, like its base address, and of the version of
+    /// deruster building the index.
+    pub fn path(dir: &Path, data: &[u8], settings: &str) -> PathBuf {
+        // FNV-1a, stable across runs and builds unlike the hasher of `std`
+        let mut hash = 0xCBF2_9CE4_8422_2325_u64;
+        let version = env!("CARGO_PKG_VERSION").as_bytes();
+        for byte in data.iter().chain(settings.as_bytes()).chain(version) {
+            hash = (hash ^ u64::from(*byte)).wrapping_mul(0x100_0000_01B3);
+        }
+        dir.join(format!("{hash:016x}.json"))
+    }
+
+    /// Loads the index saved to `path`, or `None` when there is none or it cannot be read.
+    pub fn load(path: &Path) -> Option<Self> {
+        let saved: SavedIndex = serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()?;
+        let mut xrefs = XrefDb {
+            functions: saved.functions,
+            ..Default::default()
+        };
+        for xref in saved.xrefs {
+            xrefs.insert(xref);
+        }
+        Some(xrefs)
+    }
+
+    /// Saves the index to `path`, creating its directory.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let saved = SavedIndex {
+            functions: self.functions.clone(),
+            xrefs: self.from.values().flatten().copied().collect(),
+        };
+        std::fs::write(path, serde_json::to_string(&saved)?)
+    }
+
+    /// Records the references made by the instructions of `function`.
+    pub fn add_function(&mut self, function: &Function, memory: &Memory) {
+        self.functions.insert(function.address, function.name.clone());
+        let mut factory = InstructionInfoFactory::new();
+        for block in function.cfg.blocks.values() {
+            for (inst, stmt) in block.instructions.iter().zip(block.statements.iter()) {
+                let mut refs = Vec::new();
+                match inst {
+                    Instruction::X86(inst) => x86_references(inst, memory, &mut factory, &mut refs),
+                    _ => lifted_references(inst, stmt, &mut refs),
+                }
+                // calls through a register or a pointer the constants were followed into
+                if let (Flow::IndirectCall, None, ExprKind::Call(target, _)) =
+                    (inst.flow(), inst.slot(), &stmt.kind)
+                {
+                    refs.push((*target, XrefKind::Call));
+                }
+                // and the targets the value-set analysis found for calls and jump tables
+                for target in function.cfg.calls.get(&inst.ip()).into_iter().flatten() {
+                    refs.push((*target, XrefKind::Call));
+                }
+                if let Some(table) = function.cfg.tables.get(&inst.ip()) {
+                    refs.extend(table.targets().into_iter().map(|to| (to, XrefKind::Jump)));
+                }
+                for (to, kind) in refs {
+                    self.insert(Xref {
+                        from: inst.ip(),
+                        function: function.address,
+                        to,
+                        kind,
+                    });
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, xref: Xref) {
+        let refs = self.from.entry(xref.from).or_default();
+        // instructions shared by overlapping functions are only recorded once
+        if refs
+            .iter()
+            .any(|other| other.to == xref.to && other.kind == xref.kind)
+        {
+            return;
+        }
+        refs.push(xref);
+        self.to.entry(xref.to).or_default().push(xref);
+    }
+
+    /// Returns the references to `addr`.
+    pub fn to(&self, addr: u64) -> &[Xref] {
+        self.to.get(&addr).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Returns the references to any address in `range`, like the members of a struct.
+    pub fn to_range(&self, range: Range<u64>) -> impl Iterator<Item = &Xref> {
+        self.to.range(range).flat_map(|(_, refs)| refs.iter())
+    }
+
+    /// Returns the references made by the instruction at `addr`.
+    pub fn from(&self, addr: u64) -> &[Xref] {
+        self.from.get(&addr).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Returns the functions calling the function at `addr`, in address order.
+    pub fn callers(&self, addr: u64) -> Vec<u64> {
+        let mut callers = self
+            .to(addr)
+            .iter()
+            .filter(|xref| xref.kind == XrefKind::Call)
+            .map(|xref| xref.function)
+            .collect::<Vec<_>>();
+        callers.sort_unstable();
+        callers.dedup();
+        callers
+    }
+
+    /// Returns the name of the lifted function at `addr`, or `None` if none was.
+    pub fn function_name(&self, addr: u64) -> Option<&str> {
+        self.functions.get(&addr).map(String::as_str)
+    }
+
+    /// Returns every reference, in order of target.
+    pub fn iter(&self) -> impl Iterator<Item = &Xref> {
+        self.to.values().flatten()
+    }
+}
+
+/// Collects the branch targets, the absolute and RIP-relative memory operands and the
+/// immediates which may be addresses of `inst`.
+fn x86_references(
+    inst: &iced_x86::Instruction,
+    memory: &Memory,
+    factory: &mut InstructionInfoFactory,
+    refs: &mut Vec<(u64, XrefKind)>,
+) {
+    let call = matches!(
+        inst.flow_control(),
+        FlowControl::Call | FlowControl::IndirectCall
+    );
+    if matches!(
+        inst.op0_kind(),
+        OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64
+    ) {
+        let kind = if call { XrefKind::Call } else { XrefKind::Jump };
+        refs.push((inst.near_branch_target(), kind));
+        return;
+    }
+
+    let operand = (0..inst.op_count()).any(|op| inst.op_kind(op) == OpKind::Memory);
+    let absolute = inst.memory_base() == Register::None && inst.memory_index() == Register::None;
+    // fs and gs point to thread-local storage, not to the image
+    let segment = matches!(inst.memory_segment(), Register::FS | Register::GS);
+    if operand && (inst.is_ip_rel_memory_operand() || absolute) && !segment {
+        let addr = if inst.is_ip_rel_memory_operand() {
+            inst.ip_rel_memory_address()
+        } else {
+            inst.memory_displacement64()
+        };
+        if inst.mnemonic() == Mnemonic::Lea {
+            refs.push((addr, XrefKind::Address));
+        } else if matches!(
+            inst.flow_control(),
+            FlowControl::IndirectCall | FlowControl::IndirectBranch
+        ) {
+            // calls through the GOT or the IAT are calls to the function named after the slot
+            let kind = if call { XrefKind::Call } else { XrefKind::Jump };
+            refs.push((addr, kind));
+        } else {
+            let info = factory.info(inst);
+            let mut read = false;
+            let mut write = false;
+            // leave out the stack accesses of push and pop
+            for used in info
+                .used_memory()
+                .iter()
+                .filter(|used| used.segment() != Register::SS)
+            {
+                match used.access() {
+                    OpAccess::Read | OpAccess::CondRead => read = true,
+                    OpAccess::Write | OpAccess::CondWrite => write = true,
+                    OpAccess::ReadWrite | OpAccess::ReadCondWrite => {
+                        read = true;
+                        write = true;
+                    }
+                    _ => {}
+                }
+            }
+            if read {
+                refs.push((addr, XrefKind::Read));
+            }
+            if write {
+                refs.push((addr, XrefKind::Write));
+            }
+        }
+    }
+
+    if matches!(inst.mnemonic(), Mnemonic::Mov | Mnemonic::Push) {
+        for op in 0..inst.op_count() {
+            let value = match inst.op_kind(op) {
+                OpKind::Immediate32 => u64::from(inst.immediate32()),
+                OpKind::Immediate32to64 => inst.immediate32to64() as u64,
+                OpKind::Immediate64 => inst.immediate64(),
+                _ => continue,
+            };
+            if value >= MIN_IMMEDIATE_ADDRESS && memory.get(value).is_some() {
+                refs.push((value, XrefKind::Address));
+            }
+        }
+    }
+}
+
+/// Collects the branch targets of `inst` and the constant addresses the lifter folded
+/// into `stmt`, like the `adrp` pairs of AArch64 and the `auipc` pairs of RISC-V.
+fn lifted_references(inst: &Instruction, stmt: &Expr, refs: &mut Vec<(u64, XrefKind)>) {
+    match (inst.flow(), inst.branch_target()) {
+        (Flow::Call, Some(target)) => return refs.push((target, XrefKind::Call)),
+        (Flow::Branch | Flow::ConditionalBranch, Some(target)) => {
+            return refs.push((target, XrefKind::Jump))
+        }
+        _ => {}
+    }
+    if let Instruction::AArch64(inst) = inst {
+        match (inst.opcode, inst.label()) {
+            // an adrp only gives the page, the full address comes with the next instruction
+            (Opcode::Adrp, _) | (_, None) => {}
+            (Opcode::Adr, Some(addr)) => return refs.push((addr, XrefKind::Address)),
+            (_, Some(addr)) => return refs.push((addr, XrefKind::Read)),
+        }
+    }
+    match &stmt.kind {
+        ExprKind::Assign(lhs, rhs) => {
+            match &lhs.kind {
+                ExprKind::Unary(UnOp::Deref, ptr) => {
+                    if let Some(addr) = constant(ptr) {
+                        refs.push((addr, XrefKind::Write));
+                    }
+                    loads(ptr, refs);
+                }
+                _ => loads(lhs, refs),
+            }
+            match &rhs.kind {
+                ExprKind::Lit(Lit::U64(addr)) => refs.push((*addr, XrefKind::Address)),
+                _ => loads(rhs, refs),
+            }
+        }
+        _ => loads(stmt, refs),
+    }
+}
+
+/// Collects the constant addresses dereferenced within `expr`.
+fn loads(expr: &Expr, refs: &mut Vec<(u64, XrefKind)>) {
+    match &expr.kind {
+        ExprKind::Unary(UnOp::Deref, ptr) => match constant(ptr) {
+            Some(addr) => refs.push((addr, XrefKind::Read)),
+            None => loads(ptr, refs),
+        },
+        ExprKind::Assign(lhs, rhs) | ExprKind::Binary(_, lhs, rhs) => {
+            loads(lhs, refs);
+            loads(rhs, refs);
+        }
+        ExprKind::Call(_, args)
+        | ExprKind::MethodCall(_, args)
+        | ExprKind::Intrinsic(_, args)
+        | ExprKind::Tup(args) => {
+            args.iter().for_each(|arg| loads(arg, refs))
+        }
+        ExprKind::Unary(_, inner) | ExprKind::Cast(inner, _) => loads(inner, refs),
+        ExprKind::If(cond, _) => loads(cond, refs),
+        ExprKind::Ret(Some(value)) => loads(value, refs),
+        _ => {}
+    }
+}
+
+fn constant(expr: &Expr) -> Option<u64> {
+    match &expr.kind {
+        ExprKind::Lit(Lit::U64(addr)) => Some(*addr),
+        ExprKind::Cast(inner, _) => constant(inner),
+        _ => None,
+    }
+}
+
+/// Writes the references to `addr`, and to the rest of the data object starting there up to
+/// the next named address, with the function and the offset within it they come from.
+pub fn write_references(
+    out: &mut dyn Write,
+    xrefs: &XrefDb,
+    addr: u64,
+    symbols: &SymbolTable,
+    memory: &Memory,
+) -> std::fmt::Result {
+    let name = match symbols.get(addr) {
+        Some(name) => name.to_string(),
+        None if memory.is_executable(addr) => format!("FUN_{:X}", addr),
+        None => format!("DAT_{:X}", addr),
+    };
+    // a named object covers everything up to the next name in its section
+    let mut end = addr + 1;
+    if symbols.get(addr).is_some() && !memory.is_executable(addr) {
+        let next = symbols
+            .names()
+            .map(|(other, _)| other)
+            .find(|other| *other > addr);
+        let section = memory
+            .sections()
+            .map(|(range, _)| range)
+            .find(|range| range.contains(&addr));
+        if let Some(section) = section {
+            end = next.unwrap_or(section.end).min(section.end);
+        }
+    }
+
+    out.write_fmt(format_args!("{} (0x{:X})\n", name, addr))?;
+    let callers = xrefs
+        .callers(addr)
+        .into_iter()
+        .map(|caller| symbols.function_name(caller))
+        .collect::<Vec<_>>();
+    if !callers.is_empty() {
+        out.write_fmt(format_args!("called by {}\n", callers.join(", ")))?;
+    }
+    let mut refs = xrefs.to_range(addr..end).collect::<Vec<_>>();
+    refs.sort_by_key(|xref| (xref.from, xref.kind));
+    if refs.is_empty() {
+        return out.write_str("no references\n");
+    }
+    for xref in refs {
+        let function = xrefs.function_name(xref.function).unwrap_or_default();
+        let mut line = format!(
+            "{:>8X}  {:<6}{}+0x{:X}",
+            xref.from,
+            xref.kind,
+            function,
+            xref.from - xref.function
+        );
+        if xref.to != addr {
+            write!(&mut line, "  ; {}+0x{:X}", name, xref.to - addr)?;
+        }
+        out.write_fmt(format_args!("{}\n", line))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::abi::{CallingConvention, Conventions};
+    use crate::rtti::Classes;
+    use crate::unwind::UnwindTable;
+
+    /// Indexes the references of the functions at `addresses` of the sample `assets/test`.
+    fn index(addresses: &[u64]) -> (XrefDb, SymbolTable) {
+        let data = std::fs::read("assets/test").unwrap();
+        let file = object::File::parse(&*data).unwrap();
+        let memory = Memory::load(&file, &data, None).unwrap();
+        let symbols = SymbolTable::from_file(&file, &memory);
+        let unwind = UnwindTable::from_file(&file, &memory);
+        let conventions = Conventions::new(CallingConvention::SysV);
+        let none = BTreeSet::new();
+        let classes = Classes::default();
+        let mut xrefs = XrefDb::default();
+        for address in addresses {
+            let function =
+                Function::lift(&memory, &unwind, *address, &conventions, &none, &none, &classes);
+            xrefs.add_function(&function, &memory);
+        }
+        (xrefs, symbols)
+    }
+
+    #[test]
+    fn imported_callers() {
+        let (xrefs, symbols) = index(&[0x1030, 0x1146]);
+
+        // the GOT slot is only read by the PLT entry, which the callers call
+        assert_eq!(symbols.lookup("puts"), Some(0x4000));
+        assert_eq!(symbols.lookup_function("puts"), Some(0x1030));
+        assert_eq!(xrefs.callers(0x1030), [0x1146]);
+        let call = Xref {
+            from: 0x1159,
+            function: 0x1146,
+            to: 0x1030,
+            kind: XrefKind::Call,
+        };
+        assert_eq!(xrefs.to(0x1030), [call]);
+    }
+
+    #[test]
+    fn saved_index() {
+        let (xrefs, _) = index(&[0x1030, 0x1146, 0x1161]);
+        let dir = std::env::temp_dir().join(format!("deruster-xrefs-{}", std::process::id()));
+        let path = XrefDb::path(&dir, b"binary", "");
+        assert_ne!(path, XrefDb::path(&dir, b"binary", "Some(400000)"));
+        assert_ne!(path, XrefDb::path(&dir, b"other binary", ""));
+        assert!(XrefDb::load(&path).is_none());
+
+        xrefs.save(&path).unwrap();
+        let saved = XrefDb::load(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(saved.iter().collect::<Vec<_>>(), xrefs.iter().collect::<Vec<_>>());
+        assert_eq!(saved.callers(0x1146), [0x1161]);
+        assert_eq!(saved.function_name(0x1161), xrefs.function_name(0x1161));
+        assert_eq!(saved.from(0x1159), xrefs.from(0x1159));
+    }
+}
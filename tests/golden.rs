@@ -0,0 +1,140 @@
+//! Runs deruster on the sample binaries in `assets` and compares its output with the files
+//! in `tests/golden`. After an intended change of the output, rerun with `DERUSTER_BLESS=1`
+//! to rewrite them, and review the difference.
+
+use std::path::Path;
+use std::process::Command;
+
+/// Checks the output of `deruster args` against `tests/golden/name`.
+fn golden(name: &str, args: &[&str]) {
+    compare(name, &run(args));
+}
+
+/// Runs deruster from the root of the crate and returns what it printed.
+fn run(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_deruster"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Checks `actual` against `tests/golden/name`, or writes it there when blessing.
+fn compare(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+    if std::env::var_os("DERUSTER_BLESS").is_some() {
+        std::fs::write(&path, actual).unwrap();
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        actual == expected,
+        "the output differs from {}, rerun with DERUSTER_BLESS=1 to update it",
+        path.display()
+    );
+}
+
+#[test]
+fn elf() {
+    golden("test.txt", &["assets/test"]);
+}
+
+#[test]
+fn rust_backend() {
+    golden("test.rs.txt", &["-b", "rust", "assets/test"]);
+}
+
+#[test]
+fn json() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("test.json");
+    run(&["-j", path.to_str().unwrap(), "assets/test"]);
+    compare("test.json", &std::fs::read_to_string(path).unwrap());
+}
+
+#[test]
+fn dot() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("dot");
+    run(&["-d", dir.to_str().unwrap(), "--dot-labels", "ast", "assets/test"]);
+    for file in ["callgraph.dot", "FUN_1146.dot", "FUN_1161.dot"] {
+        compare(&format!("test.{file}"), &std::fs::read_to_string(dir.join(file)).unwrap());
+    }
+}
+
+#[test]
+fn listing() {
+    golden("test.lst.txt", &["-l", "assets/test"]);
+    golden("test.att.lst.txt", &["-l", "-s", "att", "assets/test"]);
+}
+
+#[test]
+fn sections() {
+    golden("test.sections.txt", &["sections", "assets/test"]);
+    golden("test.rodata.txt", &["hexdump", "--section", ".rodata", "assets/test"]);
+    golden("test.hexdump.txt", &["hexdump", "-r", "1130+20", "assets/test"]);
+}
+
+#[test]
+fn pe() {
+    golden("test.exe.txt", &["assets/test.exe"]);
+    golden("test.exe.rebased.txt", &["--base", "10000000", "assets/test.exe"]);
+}
+
+#[test]
+fn mach_o() {
+    golden("test.macho.txt", &["assets/test.macho"]);
+    // the x86-64 slice of the universal binary is the same image
+    golden("test.macho.txt", &["assets/test.fat"]);
+    golden("test.fat.sections.txt", &["sections", "assets/test.fat"]);
+}
+
+#[test]
+fn raw() {
+    golden("test_boot.txt", &["--raw", "--bits", "16", "--region", "7e00+200", "assets/test_boot.bin"]);
+}
+
+#[test]
+fn x86_32() {
+    golden("test32.txt", &["assets/test32"]);
+    golden("test32.lst.txt", &["-l", "assets/test32"]);
+}
+
+#[test]
+fn aarch64() {
+    golden("test_arm64.txt", &["assets/test_arm64"]);
+    golden("test_arm64.lst.txt", &["-l", "assets/test_arm64"]);
+}
+
+#[test]
+fn riscv64() {
+    golden("test_riscv64.txt", &["assets/test_riscv64"]);
+    golden("test_riscv64.lst.txt", &["-l", "assets/test_riscv64"]);
+}
+
+#[test]
+fn dwarf() {
+    golden("test_dwarf.txt", &["assets/test_dwarf"]);
+    golden("test_dwarf.lst.txt", &["-l", "assets/test_dwarf"]);
+    golden("test_dwarf.rs.txt", &["-b", "rust", "assets/test_dwarf"]);
+}
+
+#[test]
+fn floating_point() {
+    golden("test_float.txt", &["assets/test_float"]);
+}
+
+#[test]
+fn cpp() {
+    golden("test_cpp.txt", &["assets/test_cpp"]);
+    golden("test_cpp.exe.txt", &["assets/test_cpp.exe"]);
+}
+
+#[test]
+fn hardened() {
+    golden("test_hardened.txt", &["assets/test_hardened"]);
+}
+
+#[test]
+fn switch() {
+    golden("test_switch.txt", &["assets/test_switch"]);
+}